@@ -0,0 +1,52 @@
+//! Errors raised by this library
+
+use std::result;
+use thiserror::Error;
+
+/// Result to wrap the KDC proxy errors.
+pub type Result<T> = result::Result<T, ProxyError>;
+
+/// Type of error in the KDC proxy.
+#[derive(Clone, PartialEq, Debug, Error)]
+pub enum ProxyError {
+    /// The request is not a valid `KDC-PROXY-MESSAGE`.
+    #[error("Invalid KDC proxy message: {}", _0)]
+    InvalidProxyMessage(String),
+
+    /// The inner message is not an AS-REQ, TGS-REQ or kpasswd request.
+    #[error("Unsupported Kerberos message")]
+    UnsupportedMessage,
+
+    /// The request does not specify the realm of the KDC.
+    #[error("No target domain specified")]
+    NoTargetDomain,
+
+    /// The target realm is not served by the proxy.
+    #[error("Unknown realm {}", _0)]
+    UnknownRealm(String),
+
+    /// None of the KDCs of the realm answered.
+    #[error("No KDC of realm {} is available: {}", _0, _1)]
+    NoKdcAvailable(String, String),
+
+    /// Error in the configuration of the proxy.
+    #[error("Configuration error: {}", _0)]
+    ConfigError(#[from] himmelblau_kerbeiros::Error),
+
+    /// Error starting the HTTP server.
+    #[error("Server error: {}", _0)]
+    ServerError(String),
+}
+
+impl ProxyError {
+    /// HTTP status code used to report the error to the client.
+    pub fn http_status(&self) -> u16 {
+        match self {
+            Self::InvalidProxyMessage(_)
+            | Self::UnsupportedMessage
+            | Self::NoTargetDomain => 400,
+            Self::UnknownRealm(_) | Self::NoKdcAvailable(_, _) => 503,
+            Self::ConfigError(_) | Self::ServerError(_) => 500,
+        }
+    }
+}
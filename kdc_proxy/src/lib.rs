@@ -0,0 +1,38 @@
+//! # KDC proxy
+//! Server side of [MS-KKDCP](https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-kkdcp/5bcebb8d-b747-4ee5-9453-428aec1c5c38),
+//! which allows clients to reach the KDC by sending the Kerberos messages
+//! over HTTP(S).
+//!
+//! The proxy accepts `KDC-PROXY-MESSAGE`s POSTed by the clients, checks
+//! that they contain a well-formed AS-REQ, TGS-REQ or kpasswd request,
+//! forwards them to a KDC of the target realm and returns the reply
+//! wrapped in a `KDC-PROXY-MESSAGE`.
+//!
+//! The server speaks plain HTTP, so TLS should be terminated by a reverse
+//! proxy placed in front of it.
+//!
+//! # Example
+//! Serve the realms defined in krb5.conf:
+//! ```no_run
+//! use himmelblau_kdc_proxy::{KdcProxy, KdcProxyServer};
+//! use himmelblau_kerbeiros::Krb5Config;
+//!
+//! let config = Krb5Config::from_env().unwrap();
+//! let proxy = KdcProxy::from_config(&config).unwrap();
+//!
+//! let server = KdcProxyServer::bind(proxy, "0.0.0.0:8080").unwrap();
+//! server.run(4);
+//! ```
+//!
+
+mod error;
+pub use error::{ProxyError, Result};
+
+mod message;
+pub use message::{build_proxy_response, MessageKind, ProxyRequest};
+
+mod proxy;
+pub use proxy::KdcProxy;
+
+mod server;
+pub use server::{KdcProxyServer, DEFAULT_PROXY_PATH, MAX_REQUEST_SIZE};
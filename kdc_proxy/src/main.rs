@@ -0,0 +1,89 @@
+//! Binary that serves the realms of a krb5.conf file through MS-KKDCP.
+
+use himmelblau_kdc_proxy::{KdcProxy, KdcProxyServer, DEFAULT_PROXY_PATH};
+use himmelblau_kerbeiros::Krb5Config;
+use std::env;
+use std::process;
+
+const DEFAULT_LISTEN_ADDRESS: &str = "127.0.0.1:8080";
+const DEFAULT_THREADS: usize = 4;
+
+const USAGE: &str = "Usage: kdc-proxy [OPTIONS]
+
+Options:
+    -l, --listen <ADDRESS>  Address to listen on [default: 127.0.0.1:8080]
+    -c, --config <PATH>     krb5.conf file with the realms to serve
+                            [default: $KRB5_CONFIG or /etc/krb5.conf]
+    -p, --path <PATH>       HTTP path of the proxy [default: /KdcProxy]
+    -t, --threads <N>       Number of worker threads [default: 4]
+    -h, --help              Print this help";
+
+struct Args {
+    listen: String,
+    config: Option<String>,
+    path: String,
+    threads: usize,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        listen: DEFAULT_LISTEN_ADDRESS.to_string(),
+        config: None,
+        path: DEFAULT_PROXY_PATH.to_string(),
+        threads: DEFAULT_THREADS,
+    };
+
+    let mut iter = env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        if arg == "-h" || arg == "--help" {
+            println!("{}", USAGE);
+            process::exit(0);
+        }
+
+        let value = iter
+            .next()
+            .ok_or_else(|| format!("Missing value for {}", arg))?;
+        match arg.as_str() {
+            "-l" | "--listen" => args.listen = value,
+            "-c" | "--config" => args.config = Some(value),
+            "-p" | "--path" => args.path = value,
+            "-t" | "--threads" => {
+                args.threads = value.parse().map_err(|_| {
+                    format!("Invalid number of threads {}", value)
+                })?
+            }
+            _ => return Err(format!("Unknown option {}", arg)),
+        }
+    }
+
+    return Ok(args);
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let config = match &args.config {
+        Some(path) => Krb5Config::load(path),
+        None => Krb5Config::from_env(),
+    }
+    .map_err(|error| error.to_string())?;
+
+    let proxy = KdcProxy::from_config(&config).map_err(|e| e.to_string())?;
+    if proxy.realms().is_empty() {
+        return Err("No realms defined in the configuration".to_string());
+    }
+
+    let mut server = KdcProxyServer::bind(proxy, args.listen.as_str())
+        .map_err(|error| error.to_string())?;
+    server.set_path(args.path);
+
+    eprintln!("Listening on http://{}", args.listen);
+    server.run(args.threads);
+    return Ok(());
+}
+
+fn main() {
+    let result = parse_args().and_then(run);
+    if let Err(error) = result {
+        eprintln!("kdc-proxy: {}", error);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,291 @@
+use crate::error::*;
+use himmelblau_kerberos_asn1::{
+    ApReq, AsReq, Asn1Object, KdcProxyMessage, KrbPriv, TgsReq,
+};
+
+/// Versions of the kpasswd protocol: RFC 3244 and the original one.
+const KPASSWD_VERSIONS: [u16; 2] = [0xff80, 0x0001];
+
+/// Size of the header of the kpasswd requests: message length,
+/// version and AP-REQ length.
+const KPASSWD_HEADER_SIZE: usize = 6;
+
+/// Kerberos messages accepted by the proxy.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MessageKind {
+    AsReq,
+    TgsReq,
+    /// Change/set password request (RFC 3244).
+    Kpasswd,
+}
+
+/// Kerberos request extracted from a `KDC-PROXY-MESSAGE`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProxyRequest {
+    pub kind: MessageKind,
+    /// Realm that should process the request.
+    pub realm: String,
+    /// Kerberos message, without the length prefix.
+    pub message: Vec<u8>,
+}
+
+impl ProxyRequest {
+    /// Parses a `KDC-PROXY-MESSAGE` and validates the inner message.
+    ///
+    /// The realm is taken from the target domain of the proxy message or,
+    /// if it is not present, from the body of the AS-REQ/TGS-REQ.
+    ///
+    /// # Errors
+    /// Returns [`ProxyError::InvalidProxyMessage`] if the proxy message is
+    /// malformed, [`ProxyError::UnsupportedMessage`] if the inner message
+    /// is not a well-formed request and [`ProxyError::NoTargetDomain`] if
+    /// the realm cannot be determined.
+    pub fn parse(raw: &[u8]) -> Result<Self> {
+        let (_, proxy_message) =
+            KdcProxyMessage::parse(raw).map_err(|error| {
+                ProxyError::InvalidProxyMessage(error.to_string())
+            })?;
+
+        let message = Self::remove_length_prefix(proxy_message.kerb_message)?;
+        let (kind, body_realm) = Self::classify_message(&message)?;
+
+        let realm = proxy_message
+            .target_domain
+            .or(body_realm)
+            .ok_or(ProxyError::NoTargetDomain)?;
+
+        return Ok(Self {
+            kind,
+            realm,
+            message,
+        });
+    }
+
+    fn remove_length_prefix(kerb_message: Vec<u8>) -> Result<Vec<u8>> {
+        if kerb_message.len() < 4 {
+            return Err(ProxyError::InvalidProxyMessage(
+                "Message too short".to_string(),
+            ));
+        }
+
+        let length = u32::from_be_bytes([
+            kerb_message[0],
+            kerb_message[1],
+            kerb_message[2],
+            kerb_message[3],
+        ]);
+
+        if length as usize != kerb_message.len() - 4 {
+            return Err(ProxyError::InvalidProxyMessage(
+                "Invalid message length".to_string(),
+            ));
+        }
+
+        return Ok(kerb_message[4..].to_vec());
+    }
+
+    fn classify_message(
+        message: &[u8],
+    ) -> Result<(MessageKind, Option<String>)> {
+        if let Ok((rest, as_req)) = AsReq::parse(message) {
+            if rest.is_empty() {
+                return Ok((MessageKind::AsReq, Some(as_req.req_body.realm)));
+            }
+        }
+
+        if let Ok((rest, tgs_req)) = TgsReq::parse(message) {
+            if rest.is_empty() {
+                return Ok((MessageKind::TgsReq, Some(tgs_req.req_body.realm)));
+            }
+        }
+
+        if is_kpasswd_request(message) {
+            return Ok((MessageKind::Kpasswd, None));
+        }
+
+        return Err(ProxyError::UnsupportedMessage);
+    }
+}
+
+/// Checks the format of a kpasswd request:
+/// ```text
+/// message length (2 bytes) | version (2 bytes) | AP-REQ length (2 bytes)
+/// AP-REQ | KRB-PRIV
+/// ```
+fn is_kpasswd_request(message: &[u8]) -> bool {
+    if message.len() < KPASSWD_HEADER_SIZE {
+        return false;
+    }
+
+    let length = u16::from_be_bytes([message[0], message[1]]) as usize;
+    let version = u16::from_be_bytes([message[2], message[3]]);
+    let ap_req_length = u16::from_be_bytes([message[4], message[5]]) as usize;
+
+    if length != message.len() || !KPASSWD_VERSIONS.contains(&version) {
+        return false;
+    }
+
+    let ap_req_end = KPASSWD_HEADER_SIZE + ap_req_length;
+    if ap_req_end > message.len() {
+        return false;
+    }
+
+    let ap_req_ok = matches!(
+        ApReq::parse(&message[KPASSWD_HEADER_SIZE..ap_req_end]),
+        Ok((rest, _)) if rest.is_empty()
+    );
+    let krb_priv_ok = matches!(
+        KrbPriv::parse(&message[ap_req_end..]),
+        Ok((rest, _)) if rest.is_empty()
+    );
+
+    return ap_req_ok && krb_priv_ok;
+}
+
+/// Wraps the reply of a KDC into a `KDC-PROXY-MESSAGE`.
+pub fn build_proxy_response(raw_reply: &[u8]) -> Vec<u8> {
+    let mut kerb_message = (raw_reply.len() as u32).to_be_bytes().to_vec();
+    kerb_message.extend_from_slice(raw_reply);
+
+    let proxy_message = KdcProxyMessage {
+        kerb_message,
+        target_domain: None,
+        dclocator_hint: None,
+    };
+    return proxy_message.build();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use himmelblau_kerberos_asn1::KdcReqBody;
+
+    fn proxy_message(message: &[u8], target_domain: Option<&str>) -> Vec<u8> {
+        let mut kerb_message = (message.len() as u32).to_be_bytes().to_vec();
+        kerb_message.extend_from_slice(message);
+        return KdcProxyMessage {
+            kerb_message,
+            target_domain: target_domain.map(String::from),
+            dclocator_hint: None,
+        }
+        .build();
+    }
+
+    fn as_req(realm: &str) -> Vec<u8> {
+        return AsReq {
+            req_body: KdcReqBody {
+                realm: realm.to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .build();
+    }
+
+    fn kpasswd_request() -> Vec<u8> {
+        let ap_req = ApReq::default().build();
+        let krb_priv = KrbPriv::default().build();
+        let length = KPASSWD_HEADER_SIZE + ap_req.len() + krb_priv.len();
+
+        let mut message = (length as u16).to_be_bytes().to_vec();
+        message.extend_from_slice(&0xff80_u16.to_be_bytes());
+        message.extend_from_slice(&(ap_req.len() as u16).to_be_bytes());
+        message.extend_from_slice(&ap_req);
+        message.extend_from_slice(&krb_priv);
+        return message;
+    }
+
+    #[test]
+    fn parse_as_req() {
+        let raw_as_req = as_req("KINGDOM.HEARTS");
+        assert_eq!(
+            ProxyRequest {
+                kind: MessageKind::AsReq,
+                realm: "KINGDOM.HEARTS".to_string(),
+                message: raw_as_req.clone(),
+            },
+            ProxyRequest::parse(&proxy_message(&raw_as_req, None)).unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_tgs_req_with_target_domain() {
+        let raw_tgs_req = TgsReq::default().build();
+        let request = ProxyRequest::parse(&proxy_message(
+            &raw_tgs_req,
+            Some("DISNEY.CASTLE"),
+        ))
+        .unwrap();
+
+        assert_eq!(MessageKind::TgsReq, request.kind);
+        assert_eq!("DISNEY.CASTLE", request.realm);
+    }
+
+    #[test]
+    fn parse_kpasswd_request() {
+        let raw_kpasswd = kpasswd_request();
+        let request = ProxyRequest::parse(&proxy_message(
+            &raw_kpasswd,
+            Some("KINGDOM.HEARTS"),
+        ))
+        .unwrap();
+
+        assert_eq!(MessageKind::Kpasswd, request.kind);
+        assert_eq!(raw_kpasswd, request.message);
+    }
+
+    #[test]
+    fn error_parsing_kpasswd_without_target_domain() {
+        assert_eq!(
+            ProxyError::NoTargetDomain,
+            ProxyRequest::parse(&proxy_message(&kpasswd_request(), None))
+                .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn error_parsing_unsupported_message() {
+        assert_eq!(
+            ProxyError::UnsupportedMessage,
+            ProxyRequest::parse(&proxy_message(&[0x30, 0x00], Some("A")))
+                .unwrap_err()
+        );
+
+        let mut truncated_kpasswd = kpasswd_request();
+        truncated_kpasswd.pop();
+        assert_eq!(
+            ProxyError::UnsupportedMessage,
+            ProxyRequest::parse(&proxy_message(&truncated_kpasswd, Some("A")))
+                .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn error_parsing_invalid_length_prefix() {
+        let raw = KdcProxyMessage {
+            kerb_message: vec![0, 0, 0, 5, 0x30, 0x00],
+            target_domain: None,
+            dclocator_hint: None,
+        }
+        .build();
+
+        match ProxyRequest::parse(&raw) {
+            Err(ProxyError::InvalidProxyMessage(_)) => {}
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn build_response() {
+        assert_eq!(
+            KdcProxyMessage {
+                kerb_message: vec![0, 0, 0, 2, 0x7e, 0x00],
+                target_domain: None,
+                dclocator_hint: None,
+            },
+            KdcProxyMessage::parse(&build_proxy_response(&[0x7e, 0x00]))
+                .unwrap()
+                .1
+        );
+    }
+}
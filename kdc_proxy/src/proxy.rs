@@ -0,0 +1,211 @@
+use crate::error::*;
+use crate::message::*;
+use himmelblau_kerbeiros::{
+    new_transporter, KdcEntry, Krb5Config, RealmConfig, TransportProtocol,
+};
+use std::collections::HashMap;
+
+/// Forwards the requests of the clients to the KDCs of the served realms.
+#[derive(Debug, Clone, Default)]
+pub struct KdcProxy {
+    realms: HashMap<String, RealmConfig>,
+}
+
+impl KdcProxy {
+    /// Creates a proxy that does not serve any realm.
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// Creates a proxy that serves the realms defined in the `[realms]`
+    /// section of the configuration.
+    ///
+    /// # Errors
+    /// Returns [`ProxyError::ConfigError`] if a realm definition is invalid.
+    pub fn from_config(config: &Krb5Config) -> Result<Self> {
+        let mut proxy = Self::new();
+        for realm in config.realms() {
+            proxy.add_realm(realm.to_string(), config.realm(realm)?);
+        }
+        return Ok(proxy);
+    }
+
+    /// Serves a realm, replacing the previous configuration of it.
+    pub fn add_realm(&mut self, realm: String, realm_config: RealmConfig) {
+        self.realms.insert(realm, realm_config);
+    }
+
+    pub fn realms(&self) -> Vec<&str> {
+        return self.realms.keys().map(|realm| realm.as_str()).collect();
+    }
+
+    /// Processes a `KDC-PROXY-MESSAGE` sent by a client and returns the
+    /// reply of the KDC wrapped in a `KDC-PROXY-MESSAGE`.
+    ///
+    /// The servers of the realm are tried in order until one of them
+    /// replies. kpasswd requests are sent to the kpasswd servers of the
+    /// realm (or the admin servers if none are defined).
+    ///
+    /// # Errors
+    /// Returns the errors of [`ProxyRequest::parse`],
+    /// [`ProxyError::UnknownRealm`] if the realm is not served and
+    /// [`ProxyError::NoKdcAvailable`] if no server replies.
+    pub fn handle(&self, raw_request: &[u8]) -> Result<Vec<u8>> {
+        let request = ProxyRequest::parse(raw_request)?;
+
+        let realm_config = self
+            .realms
+            .get(&request.realm)
+            .ok_or_else(|| ProxyError::UnknownRealm(request.realm.clone()))?;
+
+        let servers = match request.kind {
+            MessageKind::Kpasswd => {
+                realm_config.kpasswd_servers_or_admin_servers()
+            }
+            MessageKind::AsReq | MessageKind::TgsReq => {
+                realm_config.kdcs.clone()
+            }
+        };
+
+        let raw_reply = Self::forward(&request, &servers)?;
+        return Ok(build_proxy_response(&raw_reply));
+    }
+
+    fn forward(
+        request: &ProxyRequest,
+        servers: &[KdcEntry],
+    ) -> Result<Vec<u8>> {
+        let mut last_error = "No servers defined".to_string();
+
+        for server in servers {
+            let address = match server.resolve() {
+                Ok(address) => address,
+                Err(error) => {
+                    last_error = error.to_string();
+                    continue;
+                }
+            };
+
+            let transport_protocol = server
+                .transport_protocol
+                .clone()
+                .unwrap_or(TransportProtocol::TCP);
+            let transporter = new_transporter(address, transport_protocol);
+
+            match transporter.request_and_response(&request.message) {
+                Ok(raw_reply) => return Ok(raw_reply),
+                Err(error) => last_error = error.to_string(),
+            }
+        }
+
+        return Err(ProxyError::NoKdcAvailable(
+            request.realm.clone(),
+            last_error,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use himmelblau_kerberos_asn1::{
+        AsReq, Asn1Object, KdcProxyMessage, KdcReqBody,
+    };
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Answers one request over TCP and returns the received message
+    fn fake_kdc(
+        listener: TcpListener,
+        reply: Vec<u8>,
+    ) -> thread::JoinHandle<Vec<u8>> {
+        return thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut length = [0; 4];
+            stream.read_exact(&mut length).unwrap();
+            let mut message = vec![0; u32::from_be_bytes(length) as usize];
+            stream.read_exact(&mut message).unwrap();
+
+            stream
+                .write_all(&(reply.len() as u32).to_be_bytes())
+                .unwrap();
+            stream.write_all(&reply).unwrap();
+            return message;
+        });
+    }
+
+    fn proxy_as_req(realm: &str) -> (Vec<u8>, Vec<u8>) {
+        let raw_as_req = AsReq {
+            req_body: KdcReqBody {
+                realm: realm.to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .build();
+
+        let mut kerb_message = (raw_as_req.len() as u32).to_be_bytes().to_vec();
+        kerb_message.extend_from_slice(&raw_as_req);
+
+        let raw_proxy_message = KdcProxyMessage {
+            kerb_message,
+            target_domain: None,
+            dclocator_hint: None,
+        }
+        .build();
+        return (raw_as_req, raw_proxy_message);
+    }
+
+    #[test]
+    fn forward_request_to_kdc() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let kdc_address = listener.local_addr().unwrap();
+        let kdc = fake_kdc(listener, vec![0x7e, 0x00]);
+
+        let mut proxy = KdcProxy::new();
+        proxy.add_realm(
+            "KINGDOM.HEARTS".to_string(),
+            RealmConfig {
+                kdcs: vec![
+                    KdcEntry::new("127.0.0.1".to_string(), 1),
+                    KdcEntry::new(
+                        kdc_address.ip().to_string(),
+                        kdc_address.port(),
+                    ),
+                ],
+                ..Default::default()
+            },
+        );
+
+        let (raw_as_req, raw_proxy_message) = proxy_as_req("KINGDOM.HEARTS");
+        let raw_response = proxy.handle(&raw_proxy_message).unwrap();
+
+        assert_eq!(build_proxy_response(&[0x7e, 0x00]), raw_response);
+        assert_eq!(raw_as_req, kdc.join().unwrap());
+    }
+
+    #[test]
+    fn error_forwarding_to_unknown_realm() {
+        let (_, raw_proxy_message) = proxy_as_req("KINGDOM.HEARTS");
+
+        assert_eq!(
+            ProxyError::UnknownRealm("KINGDOM.HEARTS".to_string()),
+            KdcProxy::new().handle(&raw_proxy_message).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn error_forwarding_without_available_kdcs() {
+        let mut proxy = KdcProxy::new();
+        proxy.add_realm("KINGDOM.HEARTS".to_string(), RealmConfig::default());
+        let (_, raw_proxy_message) = proxy_as_req("KINGDOM.HEARTS");
+
+        match proxy.handle(&raw_proxy_message) {
+            Err(ProxyError::NoKdcAvailable(realm, _)) => {
+                assert_eq!("KINGDOM.HEARTS", realm);
+            }
+            _ => unreachable!(),
+        }
+    }
+}
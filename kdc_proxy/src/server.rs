@@ -0,0 +1,150 @@
+use crate::error::*;
+use crate::proxy::KdcProxy;
+use std::io::Read;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::thread;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// Path where the proxy listens by default, as Windows clients expect.
+pub const DEFAULT_PROXY_PATH: &str = "/KdcProxy";
+
+/// Maximum size of the accepted requests.
+pub const MAX_REQUEST_SIZE: usize = 128 * 1024;
+
+const KERBEROS_CONTENT_TYPE: &str = "application/kerberos";
+
+/// HTTP server that accepts the MS-KKDCP requests and passes them to a
+/// [`KdcProxy`].
+pub struct KdcProxyServer {
+    proxy: KdcProxy,
+    server: Server,
+    path: String,
+}
+
+impl KdcProxyServer {
+    /// Listens in the given address.
+    ///
+    /// # Errors
+    /// Returns [`ProxyError::ServerError`] if it is not possible to listen
+    /// in the address.
+    pub fn bind<A: ToSocketAddrs>(proxy: KdcProxy, address: A) -> Result<Self> {
+        let server = Server::http(address)
+            .map_err(|error| ProxyError::ServerError(error.to_string()))?;
+
+        return Ok(Self {
+            proxy,
+            server,
+            path: DEFAULT_PROXY_PATH.to_string(),
+        });
+    }
+
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        return self.server.server_addr().to_ip();
+    }
+
+    /// Changes the path where the requests are accepted.
+    pub fn set_path(&mut self, path: String) {
+        self.path = path;
+    }
+
+    /// Serves requests with the given number of worker threads. It only
+    /// returns if the server fails.
+    pub fn run(&self, threads: usize) {
+        thread::scope(|scope| {
+            for _ in 0..threads.max(1) {
+                scope.spawn(|| {
+                    while let Ok(request) = self.server.recv() {
+                        self.handle_http_request(request);
+                    }
+                });
+            }
+        });
+    }
+
+    fn handle_http_request(&self, mut request: Request) {
+        let response = self.process_http_request(&mut request);
+        // nothing can be done if the client is gone
+        let _ = request.respond(response);
+    }
+
+    fn process_http_request(
+        &self,
+        request: &mut Request,
+    ) -> Response<std::io::Cursor<Vec<u8>>> {
+        let path = request.url().split('?').next().unwrap_or_default();
+        if path != self.path {
+            return Response::from_data(Vec::new()).with_status_code(404);
+        }
+
+        if request.method() != &Method::Post {
+            return Response::from_data(Vec::new()).with_status_code(405);
+        }
+
+        let mut body = Vec::new();
+        let read_result = request
+            .as_reader()
+            .take(MAX_REQUEST_SIZE as u64 + 1)
+            .read_to_end(&mut body);
+        if read_result.is_err() {
+            return Response::from_data(Vec::new()).with_status_code(400);
+        }
+        if body.len() > MAX_REQUEST_SIZE {
+            return Response::from_data(Vec::new()).with_status_code(413);
+        }
+
+        match self.proxy.handle(&body) {
+            Ok(raw_response) => {
+                let content_type =
+                    Header::from_bytes("Content-Type", KERBEROS_CONTENT_TYPE)
+                        .expect("Invalid header");
+                return Response::from_data(raw_response)
+                    .with_header(content_type);
+            }
+            Err(error) => {
+                return Response::from_data(Vec::new())
+                    .with_status_code(error.http_status());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Write;
+    use std::net::TcpStream;
+
+    fn post(address: SocketAddr, path: &str, body: &[u8]) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\nHost: localhost\r\n\
+             Content-Type: application/kerberos\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n",
+            path,
+            body.len()
+        )
+        .unwrap();
+        stream.write_all(body).unwrap();
+
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).unwrap();
+        return String::from_utf8_lossy(&response).to_string();
+    }
+
+    #[test]
+    fn reply_http_errors() {
+        let server =
+            KdcProxyServer::bind(KdcProxy::new(), "127.0.0.1:0").unwrap();
+        let address = server.local_addr().unwrap();
+        thread::spawn(move || server.run(1));
+
+        assert!(post(address, "/other", &[]).starts_with("HTTP/1.1 404"));
+        assert!(post(address, DEFAULT_PROXY_PATH, &[0x30, 0x00])
+            .starts_with("HTTP/1.1 400"));
+        assert!(
+            post(address, DEFAULT_PROXY_PATH, &[0; MAX_REQUEST_SIZE + 1])
+                .starts_with("HTTP/1.1 413")
+        );
+    }
+}
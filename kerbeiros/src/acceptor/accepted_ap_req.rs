@@ -0,0 +1,184 @@
+use super::authorization_data::{walk_ad_elements, AdElement};
+use super::cammac::auth_indicators;
+use crate::Result;
+use chrono::Utc;
+use himmelblau_kerberos_asn1::{
+    ApRep, Asn1Object, Authenticator, AuthorizationData, EncApRepPart,
+    EncTicketPart, EncryptedData, EncryptionKey, KerberosTime, PrincipalName,
+    Realm, TicketFlags,
+};
+use himmelblau_kerberos_constants::ap_options::MUTUAL_REQUIRED;
+use himmelblau_kerberos_constants::key_usages::KEY_USAGE_AP_REP_ENC_PART;
+use himmelblau_kerberos_crypto::new_kerberos_cipher;
+use himmelblau_kerberos_pac::Pac;
+
+/// AP-REQ verified by an [`ApReqAcceptor`](super::ApReqAcceptor), with
+/// the identity of the authenticated client and the keys of the session.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AcceptedApReq {
+    ap_options: u32,
+    srealm: Realm,
+    sname: PrincipalName,
+    enc_ticket_part: EncTicketPart,
+    authenticator: Authenticator,
+    ticket_ad_elements: Vec<AdElement>,
+    authenticator_ad_elements: Vec<AdElement>,
+}
+
+impl AcceptedApReq {
+    pub(crate) fn new(
+        ap_options: u32,
+        srealm: Realm,
+        sname: PrincipalName,
+        enc_ticket_part: EncTicketPart,
+        authenticator: Authenticator,
+        ticket_ad_elements: Vec<AdElement>,
+        authenticator_ad_elements: Vec<AdElement>,
+    ) -> Self {
+        return Self {
+            ap_options,
+            srealm,
+            sname,
+            enc_ticket_part,
+            authenticator,
+            ticket_ad_elements,
+            authenticator_ad_elements,
+        };
+    }
+
+    /// Realm of the authenticated client.
+    pub fn crealm(&self) -> &Realm {
+        return &self.enc_ticket_part.crealm;
+    }
+
+    /// Name of the authenticated client.
+    pub fn cname(&self) -> &PrincipalName {
+        return &self.enc_ticket_part.cname;
+    }
+
+    /// Realm of the service of the ticket.
+    pub fn srealm(&self) -> &Realm {
+        return &self.srealm;
+    }
+
+    /// Name of the service of the ticket.
+    pub fn sname(&self) -> &PrincipalName {
+        return &self.sname;
+    }
+
+    /// Session key of the ticket.
+    pub fn session_key(&self) -> &EncryptionKey {
+        return &self.enc_ticket_part.key;
+    }
+
+    /// Subkey chosen by the client in the authenticator.
+    pub fn subkey(&self) -> Option<&EncryptionKey> {
+        return self.authenticator.subkey.as_ref();
+    }
+
+    /// Initial sequence number chosen by the client in the authenticator.
+    pub fn seq_number(&self) -> Option<u32> {
+        return self.authenticator.seq_number;
+    }
+
+    pub fn flags(&self) -> &TicketFlags {
+        return &self.enc_ticket_part.flags;
+    }
+
+    pub fn authtime(&self) -> &KerberosTime {
+        return &self.enc_ticket_part.authtime;
+    }
+
+    pub fn endtime(&self) -> &KerberosTime {
+        return &self.enc_ticket_part.endtime;
+    }
+
+    /// Authorization data included by the KDC in the ticket, such as the
+    /// PAC of Active Directory.
+    pub fn authorization_data(&self) -> Option<&AuthorizationData> {
+        return self.enc_ticket_part.authorization_data.as_ref();
+    }
+
+    /// Decoded authorization data of the ticket.
+    pub fn ticket_ad_elements(&self) -> &[AdElement] {
+        return &self.ticket_ad_elements;
+    }
+
+    /// Decoded authorization data of the authenticator.
+    pub fn authenticator_ad_elements(&self) -> &[AdElement] {
+        return &self.authenticator_ad_elements;
+    }
+
+    /// PAC of the ticket, whose server signature was verified.
+    pub fn pac(&self) -> Option<&Pac> {
+        return walk_ad_elements(&self.ticket_ad_elements)
+            .into_iter()
+            .find_map(|element| match element {
+                AdElement::Win2kPac(pac) => Some(pac),
+                _ => None,
+            });
+    }
+
+    /// Authentication indicators of the ticket, such as "otp" or
+    /// "pkinit", which show how the client was authenticated.
+    pub fn auth_indicators(&self) -> Vec<&str> {
+        return auth_indicators(&self.ticket_ad_elements);
+    }
+
+    /// Options of the AP-REQ.
+    pub fn ap_options(&self) -> u32 {
+        return self.ap_options;
+    }
+
+    /// Whether the client requires the service to authenticate with an
+    /// AP-REP.
+    pub fn is_mutual_required(&self) -> bool {
+        return self.ap_options & MUTUAL_REQUIRED != 0;
+    }
+
+    /// Decrypted authenticator of the AP-REQ.
+    pub fn authenticator(&self) -> &Authenticator {
+        return &self.authenticator;
+    }
+
+    /// Decrypted part of the ticket of the AP-REQ.
+    pub fn enc_ticket_part(&self) -> &EncTicketPart {
+        return &self.enc_ticket_part;
+    }
+
+    /// Builds the AP-REP that authenticates the service to the client, as
+    /// described in RFC4120, section 3.2.4. It may include a new `subkey`
+    /// and the initial `seq_number` of the service.
+    pub fn ap_rep(
+        &self,
+        subkey: Option<EncryptionKey>,
+        seq_number: Option<u32>,
+    ) -> Result<ApRep> {
+        let enc_ap_rep_part = EncApRepPart {
+            ctime: self.authenticator.ctime.clone(),
+            cusec: self.authenticator.cusec,
+            subkey,
+            seq_number,
+        };
+
+        let session_key = self.session_key();
+        let cipher = new_kerberos_cipher(session_key.keytype)?;
+        return Ok(ApRep {
+            enc_part: EncryptedData::new(
+                session_key.keytype,
+                None,
+                cipher.encrypt(
+                    &session_key.keyvalue,
+                    KEY_USAGE_AP_REP_ENC_PART,
+                    &enc_ap_rep_part.build(),
+                )?,
+            ),
+            ..Default::default()
+        });
+    }
+
+    /// Whether the ticket is still valid.
+    pub fn is_expired(&self) -> bool {
+        return ***self.endtime() < Utc::now();
+    }
+}
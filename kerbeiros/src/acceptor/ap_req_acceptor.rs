@@ -0,0 +1,904 @@
+use super::accepted_ap_req::AcceptedApReq;
+use super::authorization_data::{
+    walk_ad_elements, AdElement, AuthorizationDataDecoder,
+};
+use super::cammac::auth_indicators;
+use super::replay_cache::{MemoryReplayCache, ReplayCache, ReplayEntry};
+use crate::client::default_keytab_path;
+use crate::config::{Krb5Config, DEFAULT_CLOCKSKEW_SECONDS};
+use crate::credentials::{load_keytab, KeytabKeys};
+use crate::messages::same_principal_name;
+use crate::{Error, Result};
+use chrono::{DateTime, Duration, Utc};
+use himmelblau_kerberos_asn1::{
+    ApReq, Asn1Object, Authenticator, AuthorizationData, EncTicketPart,
+    EncryptionKey, PrincipalName, Realm,
+};
+use himmelblau_kerberos_constants::ap_options::USE_SESSION_KEY;
+use himmelblau_kerberos_constants::key_usages::KEY_USAGE_AP_REQ_AUTHEN;
+use himmelblau_kerberos_constants::ticket_flags::INVALID;
+use himmelblau_kerberos_crypto::{
+    key_size, new_kerberos_cipher, supported_etypes, Key,
+};
+use himmelblau_kerberos_keytab::Keytab;
+use std::path::Path;
+
+/// Verifies the AP-REQ messages sent by the clients to a service, as
+/// described in RFC4120, section 3.2.3.
+///
+/// The ticket is decrypted with the key of the keytab that matches its
+/// service name, version and encryption type. Then, the authenticator is
+/// decrypted with the session key of the ticket, and the client, the
+/// times and the clock skew are checked. Finally, the authenticator is
+/// stored in a replay cache, which rejects the AP-REQ messages that reuse
+/// it. By default, the cache is kept in memory, so a
+/// [`FileReplayCache`](super::FileReplayCache) should be used when the
+/// service runs in several processes.
+///
+/// The authorization data of the ticket and the authenticator is decoded
+/// with an [`AuthorizationDataDecoder`], so the AP-REQ is rejected if it
+/// includes a critical element that is not understood. If the ticket
+/// includes a PAC or *AD-CAMMAC* elements, their server signature and
+/// `svc-verifier` are verified with the key of the service. Additionally,
+/// the acceptor can require the tickets to include one of a set of
+/// authentication indicators, such as "otp" or "pkinit".
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+/// use himmelblau_kerberos_asn1::Asn1Object;
+///
+/// let acceptor = ApReqAcceptor::from_keytab_file("/etc/krb5.keytab").unwrap();
+///
+/// # let raw_ap_req = vec![];
+/// let accepted = acceptor.accept(&raw_ap_req).unwrap();
+/// println!("Authenticated {}@{}", accepted.cname(), accepted.crealm());
+///
+/// if accepted.is_mutual_required() {
+///     let raw_ap_rep = accepted.ap_rep(None, None).unwrap().build();
+/// }
+/// ```
+pub struct ApReqAcceptor {
+    keytab: Keytab,
+    service: Option<PrincipalName>,
+    service_realm: Option<Realm>,
+    clock_skew: Duration,
+    replay_cache: Option<Box<dyn ReplayCache>>,
+    ad_decoder: AuthorizationDataDecoder,
+    required_auth_indicators: Vec<String>,
+}
+
+impl ApReqAcceptor {
+    /// Creates an acceptor for any service of the keytab.
+    pub fn new(keytab: Keytab) -> Self {
+        return Self {
+            keytab,
+            service: None,
+            service_realm: None,
+            clock_skew: Duration::seconds(DEFAULT_CLOCKSKEW_SECONDS),
+            replay_cache: Some(Box::new(MemoryReplayCache::new())),
+            ad_decoder: AuthorizationDataDecoder::new(),
+            required_auth_indicators: Vec::new(),
+        };
+    }
+
+    /// Creates an acceptor with the keytab stored in `path`.
+    pub fn from_keytab_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        return Ok(Self::new(load_keytab(path.as_ref())?));
+    }
+
+    /// Creates an acceptor with the default keytab and the clock skew of
+    /// a `krb5.conf` file.
+    pub fn from_config(config: &Krb5Config) -> Result<Self> {
+        let mut acceptor =
+            Self::from_keytab_file(default_keytab_path(config)?)?;
+        acceptor.set_clock_skew(config.clockskew());
+        return Ok(acceptor);
+    }
+
+    pub fn service(&self) -> Option<&PrincipalName> {
+        return self.service.as_ref();
+    }
+
+    /// Restricts the tickets accepted to the ones issued for `service`,
+    /// or allows any service of the keytab if `None`. The name type is
+    /// ignored, as MIT and Windows do.
+    pub fn set_service(&mut self, service: Option<PrincipalName>) {
+        self.service = service;
+    }
+
+    pub fn service_realm(&self) -> Option<&Realm> {
+        return self.service_realm.as_ref();
+    }
+
+    /// Restricts the tickets accepted to the ones issued in `realm`, or
+    /// allows any realm of the keytab if `None`.
+    pub fn set_service_realm(&mut self, realm: Option<Realm>) {
+        self.service_realm = realm;
+    }
+
+    pub fn clock_skew(&self) -> Duration {
+        return self.clock_skew;
+    }
+
+    /// Maximum difference allowed between the clocks of the client and
+    /// the service, 5 minutes by default.
+    pub fn set_clock_skew(&mut self, clock_skew: Duration) {
+        self.clock_skew = clock_skew;
+    }
+
+    /// Sets the cache used to detect replayed authenticators, or disables
+    /// the detection if `None`.
+    pub fn set_replay_cache(
+        &mut self,
+        replay_cache: Option<Box<dyn ReplayCache>>,
+    ) {
+        self.replay_cache = replay_cache;
+    }
+
+    /// Adds a type of authorization data element that is handled by the
+    /// application, so it is accepted when critical.
+    pub fn add_understood_ad_type(&mut self, ad_type: i32) {
+        self.ad_decoder.add_understood_type(ad_type);
+    }
+
+    pub fn required_auth_indicators(&self) -> &[String] {
+        return &self.required_auth_indicators;
+    }
+
+    /// Only accepts the tickets that include at least one of these
+    /// authentication indicators, or any ticket if empty.
+    pub fn set_required_auth_indicators(&mut self, indicators: Vec<String>) {
+        self.required_auth_indicators = indicators;
+    }
+
+    /// Parses and verifies an AP-REQ.
+    pub fn accept(&self, raw_ap_req: &[u8]) -> Result<AcceptedApReq> {
+        let (_, ap_req) = ApReq::parse(raw_ap_req)?;
+        return self.accept_ap_req(&ap_req);
+    }
+
+    /// Verifies an AP-REQ.
+    ///
+    /// # Errors
+    /// Returns [`Error::KeytabError`] if the keytab has no key for the
+    /// service of the ticket, [`Error::InvalidTicket`] if the ticket
+    /// cannot be decrypted or is not valid at this time,
+    /// [`Error::InvalidApReq`] if the authenticator is not valid,
+    /// [`Error::InvalidAuthorizationData`] if the authorization data
+    /// cannot be accepted, and [`Error::ReplayError`] if the
+    /// authenticator was already received.
+    pub fn accept_ap_req(&self, ap_req: &ApReq) -> Result<AcceptedApReq> {
+        let ticket = &ap_req.ticket;
+        let ap_options = ap_req.ap_options.flags;
+
+        if let Some(service) = &self.service {
+            if !same_principal_name(service, &ticket.sname) {
+                return Err(Error::InvalidApReq(format!(
+                    "ticket for {} was not issued for {}",
+                    ticket.sname, service
+                )));
+            }
+        }
+
+        if let Some(realm) = &self.service_realm {
+            if *realm != ticket.realm {
+                return Err(Error::InvalidApReq(format!(
+                    "ticket of {} was not issued in {}",
+                    ticket.realm, realm
+                )));
+            }
+        }
+
+        if ap_options & USE_SESSION_KEY != 0 {
+            return Err(Error::InvalidApReq(
+                "user-to-user authentication is not supported".to_string(),
+            ));
+        }
+
+        let keys = KeytabKeys::new(
+            &self.keytab,
+            &ticket.realm,
+            &ticket.sname.to_string(),
+            &supported_etypes().into_iter().collect(),
+        );
+        if keys.is_empty() {
+            return Err(Error::KeytabError(format!(
+                "no supported key for {}@{}",
+                ticket.sname, ticket.realm
+            )));
+        }
+
+        let (enc_ticket_part, service_key) =
+            keys.decrypt_ticket_with_key(ticket)?;
+        let authenticator = decrypt_authenticator(ap_req, &enc_ticket_part)?;
+
+        let now = Utc::now();
+        self.validate_authenticator(&authenticator, &enc_ticket_part, now)?;
+        self.validate_ticket(&enc_ticket_part, now)?;
+
+        let ticket_ad_elements = self.decode_authorization_data(
+            enc_ticket_part.authorization_data.as_ref(),
+            &enc_ticket_part.key,
+        )?;
+        verify_ticket_ad_elements(&ticket_ad_elements, service_key)?;
+        self.validate_auth_indicators(&ticket_ad_elements)?;
+        let authenticator_ad_elements = match &authenticator.authorization_data
+        {
+            Some(authorization_data) => {
+                self.ad_decoder.decode_authenticator(authorization_data)?
+            }
+            None => Vec::new(),
+        };
+
+        if let Some(replay_cache) = &self.replay_cache {
+            let entry = ReplayEntry::from_authenticator(
+                &authenticator,
+                &ap_req.authenticator,
+                format!("{}@{}", ticket.sname, ticket.realm),
+            );
+            replay_cache.store(&entry, self.clock_skew)?;
+        }
+
+        return Ok(AcceptedApReq::new(
+            ap_options,
+            ticket.realm.clone(),
+            ticket.sname.clone(),
+            enc_ticket_part,
+            authenticator,
+            ticket_ad_elements,
+            authenticator_ad_elements,
+        ));
+    }
+
+    fn validate_auth_indicators(&self, elements: &[AdElement]) -> Result<()> {
+        if self.required_auth_indicators.is_empty() {
+            return Ok(());
+        }
+
+        let indicators = auth_indicators(elements);
+        if !self
+            .required_auth_indicators
+            .iter()
+            .any(|required| indicators.contains(&required.as_str()))
+        {
+            return Err(Error::InvalidTicket(format!(
+                "none of the required authentication indicators {:?}",
+                self.required_auth_indicators
+            )));
+        }
+        return Ok(());
+    }
+
+    fn decode_authorization_data(
+        &self,
+        authorization_data: Option<&AuthorizationData>,
+        session_key: &EncryptionKey,
+    ) -> Result<Vec<AdElement>> {
+        return match authorization_data {
+            Some(authorization_data) => {
+                self.ad_decoder.decode(authorization_data, session_key)
+            }
+            None => Ok(Vec::new()),
+        };
+    }
+
+    fn validate_authenticator(
+        &self,
+        authenticator: &Authenticator,
+        enc_ticket_part: &EncTicketPart,
+        now: DateTime<Utc>,
+    ) -> Result<()> {
+        if authenticator.crealm != enc_ticket_part.crealm
+            || authenticator.cname != enc_ticket_part.cname
+        {
+            return Err(Error::InvalidApReq(format!(
+                "client {}@{} does not match the ticket",
+                authenticator.cname, authenticator.crealm
+            )));
+        }
+
+        let ctime = **authenticator.ctime;
+        if ctime > now + self.clock_skew || ctime < now - self.clock_skew {
+            return Err(Error::InvalidApReq(
+                "clock skew too great".to_string(),
+            ));
+        }
+
+        // the subkey is chosen by the client, so it must be checked
+        // before being used by the ciphers
+        if let Some(subkey) = &authenticator.subkey {
+            if !has_valid_size(subkey) {
+                return Err(Error::InvalidApReq(format!(
+                    "subkey of etype {} with invalid size {}",
+                    subkey.keytype,
+                    subkey.keyvalue.len()
+                )));
+            }
+        }
+        return Ok(());
+    }
+
+    fn validate_ticket(
+        &self,
+        enc_ticket_part: &EncTicketPart,
+        now: DateTime<Utc>,
+    ) -> Result<()> {
+        if enc_ticket_part.flags.flags & INVALID != 0 {
+            return Err(Error::InvalidTicket(
+                "ticket is marked as invalid".to_string(),
+            ));
+        }
+
+        let starttime = enc_ticket_part
+            .starttime
+            .as_ref()
+            .unwrap_or(&enc_ticket_part.authtime);
+        if ***starttime > now + self.clock_skew {
+            return Err(Error::InvalidTicket(
+                "ticket not yet valid".to_string(),
+            ));
+        }
+
+        if **enc_ticket_part.endtime < now - self.clock_skew {
+            return Err(Error::InvalidTicket("ticket expired".to_string()));
+        }
+        return Ok(());
+    }
+}
+
+/// Verifies the server signature of the PACs of the ticket and the
+/// `svc-verifier` of its *AD-CAMMAC* elements.
+fn verify_ticket_ad_elements(
+    elements: &[AdElement],
+    service_key: &Key,
+) -> Result<()> {
+    for element in walk_ad_elements(elements) {
+        match element {
+            AdElement::Win2kPac(pac) => {
+                pac.verify_server_signature(service_key).map_err(|error| {
+                    Error::InvalidTicket(format!("invalid PAC: {}", error))
+                })?;
+            }
+            AdElement::Cammac(cammac)
+                if cammac.cammac().svc_verifier.is_some() =>
+            {
+                cammac.verify_svc_verifier(service_key)?;
+            }
+            _ => {}
+        }
+    }
+    return Ok(());
+}
+
+/// Whether the key has the size of the keys of its encryption type. The
+/// keys of unsupported encryption types are never used, so they are
+/// considered valid.
+pub(crate) fn has_valid_size(key: &EncryptionKey) -> bool {
+    return match key_size(key.keytype) {
+        Some(size) => key.keyvalue.len() == size,
+        None => true,
+    };
+}
+
+fn decrypt_authenticator(
+    ap_req: &ApReq,
+    enc_ticket_part: &EncTicketPart,
+) -> Result<Authenticator> {
+    let session_key = &enc_ticket_part.key;
+    if ap_req.authenticator.etype != session_key.keytype {
+        return Err(Error::InvalidApReq(format!(
+            "authenticator encrypted with etype {} instead of the etype {} \
+             of the session key",
+            ap_req.authenticator.etype, session_key.keytype
+        )));
+    }
+    let cipher = new_kerberos_cipher(session_key.keytype)?;
+    let plaintext = cipher
+        .decrypt(
+            &session_key.keyvalue,
+            KEY_USAGE_AP_REQ_AUTHEN,
+            &ap_req.authenticator.cipher,
+        )
+        .map_err(|_| {
+            Error::InvalidApReq(
+                "authenticator cannot be decrypted with the session key"
+                    .to_string(),
+            )
+        })?;
+    return Ok(Authenticator::parse(&plaintext)?.1);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::acceptor::cammac::cammac_checksum;
+    use crate::requesters::test_kdc::keytab_entry;
+    use himmelblau_kerberos_asn1::{
+        AdCammac, AdKdcIssued, ApRep, AuthorizationDataEntry, Checksum,
+        EncApRepPart, EncryptedData, Ticket, Utf8String, VerifierMac,
+    };
+    use himmelblau_kerberos_constants::ad_types::{
+        AD_AUTHENTICATION_INDICATOR, AD_CAMMAC, AD_IF_RELEVANT, AD_KDCISSUED,
+        AD_WIN2K_PACK,
+    };
+    use himmelblau_kerberos_constants::ap_options::MUTUAL_REQUIRED;
+    use himmelblau_kerberos_constants::etypes::{
+        AES128_CTS_HMAC_SHA1_96, AES256_CTS_HMAC_SHA1_96,
+    };
+    use himmelblau_kerberos_constants::key_usages::{
+        KEY_USAGE_AD_KDCISSUED_CKSUM, KEY_USAGE_AP_REP_ENC_PART,
+        KEY_USAGE_AS_REP_TICKET,
+    };
+    use himmelblau_kerberos_constants::principal_names::{
+        NT_PRINCIPAL, NT_SRV_HST,
+    };
+    use himmelblau_kerberos_constants::ticket_flags::FORWARDABLE;
+    use himmelblau_kerberos_crypto::{checksum, mandatory_checksum_type};
+    use himmelblau_kerberos_pac::{
+        Pac, PacClientInfo, PacInfoBuffer, PAC_CLIENT_INFO,
+    };
+
+    const SERVICE_KEY: Key = Key::AES256Key([4; 32]);
+
+    fn service_name(host: &str) -> PrincipalName {
+        let mut service = PrincipalName::new(NT_SRV_HST, "http".to_string());
+        service.push(host.to_string());
+        return service;
+    }
+
+    fn mickey() -> PrincipalName {
+        return PrincipalName::new(NT_PRINCIPAL, "mickey".to_string());
+    }
+
+    fn session_key() -> EncryptionKey {
+        return EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![7; 32]);
+    }
+
+    fn create_acceptor() -> ApReqAcceptor {
+        return ApReqAcceptor::new(Keytab::new(
+            5,
+            2,
+            vec![keytab_entry(
+                "http/castle.kingdom.hearts@KINGDOM.HEARTS",
+                2,
+                &SERVICE_KEY,
+            )],
+        ));
+    }
+
+    fn create_enc_ticket_part() -> EncTicketPart {
+        let now = Utc::now();
+        return EncTicketPart {
+            flags: FORWARDABLE.into(),
+            key: session_key(),
+            crealm: "KINGDOM.HEARTS".to_string(),
+            cname: mickey(),
+            authtime: now.into(),
+            starttime: Some(now.into()),
+            endtime: (now + Duration::hours(10)).into(),
+            ..Default::default()
+        };
+    }
+
+    fn create_enc_ticket_part_with_pac(service_key: &Key) -> EncTicketPart {
+        let mut pac = Pac::new(vec![PacInfoBuffer::new(
+            PAC_CLIENT_INFO,
+            PacClientInfo::new(1, "mickey".to_string()).build(),
+        )]);
+        pac.sign(service_key, &Key::RC4Key([9; 16]), None).unwrap();
+        let if_relevant = vec![AuthorizationDataEntry {
+            ad_type: AD_WIN2K_PACK,
+            ad_data: pac.build(),
+        }];
+
+        let mut enc_ticket_part = create_enc_ticket_part();
+        enc_ticket_part.authorization_data =
+            Some(vec![AuthorizationDataEntry {
+                ad_type: AD_IF_RELEVANT,
+                ad_data: if_relevant.build(),
+            }]);
+        return enc_ticket_part;
+    }
+
+    fn create_enc_ticket_part_with_indicator(indicator: &str) -> EncTicketPart {
+        let elements = vec![AuthorizationDataEntry {
+            ad_type: AD_AUTHENTICATION_INDICATOR,
+            ad_data: vec![Utf8String::from(indicator)].build(),
+        }];
+        let cammac = AdCammac {
+            svc_verifier: Some(VerifierMac {
+                mac: cammac_checksum(&elements, &SERVICE_KEY).unwrap(),
+                ..Default::default()
+            }),
+            elements,
+            ..Default::default()
+        };
+        let if_relevant = vec![AuthorizationDataEntry {
+            ad_type: AD_CAMMAC,
+            ad_data: cammac.build(),
+        }];
+
+        let mut enc_ticket_part = create_enc_ticket_part();
+        enc_ticket_part.authorization_data =
+            Some(vec![AuthorizationDataEntry {
+                ad_type: AD_IF_RELEVANT,
+                ad_data: if_relevant.build(),
+            }]);
+        return enc_ticket_part;
+    }
+
+    fn create_authenticator() -> Authenticator {
+        return Authenticator {
+            crealm: "KINGDOM.HEARTS".to_string(),
+            cname: mickey(),
+            ctime: Utc::now().into(),
+            subkey: Some(EncryptionKey::new(
+                AES256_CTS_HMAC_SHA1_96,
+                vec![8; 32],
+            )),
+            seq_number: Some(1234),
+            ..Default::default()
+        };
+    }
+
+    fn create_ap_req(
+        host: &str,
+        enc_ticket_part: &EncTicketPart,
+        authenticator: &Authenticator,
+    ) -> ApReq {
+        let cipher = new_kerberos_cipher(AES256_CTS_HMAC_SHA1_96).unwrap();
+        let ticket = Ticket::new(
+            "KINGDOM.HEARTS".to_string(),
+            service_name(host),
+            EncryptedData::new(
+                AES256_CTS_HMAC_SHA1_96,
+                Some(2),
+                cipher
+                    .encrypt(
+                        SERVICE_KEY.as_bytes(),
+                        KEY_USAGE_AS_REP_TICKET,
+                        &enc_ticket_part.build(),
+                    )
+                    .unwrap(),
+            ),
+        );
+
+        return ApReq {
+            ap_options: MUTUAL_REQUIRED.into(),
+            ticket,
+            authenticator: EncryptedData::new(
+                enc_ticket_part.key.keytype,
+                None,
+                new_kerberos_cipher(enc_ticket_part.key.keytype)
+                    .unwrap()
+                    .encrypt(
+                        &enc_ticket_part.key.keyvalue,
+                        KEY_USAGE_AP_REQ_AUTHEN,
+                        &authenticator.build(),
+                    )
+                    .unwrap(),
+            ),
+            ..Default::default()
+        };
+    }
+
+    fn accept(
+        enc_ticket_part: &EncTicketPart,
+        authenticator: &Authenticator,
+    ) -> Result<AcceptedApReq> {
+        let ap_req = create_ap_req(
+            "castle.kingdom.hearts",
+            enc_ticket_part,
+            authenticator,
+        );
+        return create_acceptor().accept(&ap_req.build());
+    }
+
+    #[test]
+    fn accept_ap_req() {
+        let authenticator = create_authenticator();
+        let accepted =
+            accept(&create_enc_ticket_part(), &authenticator).unwrap();
+
+        assert_eq!("KINGDOM.HEARTS", accepted.crealm());
+        assert_eq!(&mickey(), accepted.cname());
+        assert_eq!(&service_name("castle.kingdom.hearts"), accepted.sname());
+        assert_eq!(&session_key(), accepted.session_key());
+        assert_eq!(authenticator.subkey.as_ref(), accepted.subkey());
+        assert_eq!(Some(1234), accepted.seq_number());
+        assert_eq!(FORWARDABLE, accepted.flags().flags);
+        assert!(accepted.is_mutual_required());
+    }
+
+    #[test]
+    fn build_ap_rep() {
+        let accepted =
+            accept(&create_enc_ticket_part(), &create_authenticator()).unwrap();
+
+        let raw_ap_rep = accepted.ap_rep(None, Some(5678)).unwrap().build();
+
+        let (_, ap_rep) = ApRep::parse(&raw_ap_rep).unwrap();
+        let cipher = new_kerberos_cipher(ap_rep.enc_part.etype).unwrap();
+        let plaintext = cipher
+            .decrypt(
+                &session_key().keyvalue,
+                KEY_USAGE_AP_REP_ENC_PART,
+                &ap_rep.enc_part.cipher,
+            )
+            .unwrap();
+        let (_, enc_ap_rep_part) = EncApRepPart::parse(&plaintext).unwrap();
+        assert_eq!(accepted.authenticator().ctime, enc_ap_rep_part.ctime);
+        assert_eq!(None, enc_ap_rep_part.subkey);
+        assert_eq!(Some(5678), enc_ap_rep_part.seq_number);
+    }
+
+    #[test]
+    fn error_with_ticket_of_other_service() {
+        let ap_req = create_ap_req(
+            "castle.kingdom.hearts",
+            &create_enc_ticket_part(),
+            &create_authenticator(),
+        );
+        let mut acceptor = create_acceptor();
+        acceptor.set_service(Some(service_name("gummi.kingdom.hearts")));
+
+        assert!(matches!(
+            acceptor.accept_ap_req(&ap_req),
+            Err(Error::InvalidApReq(_))
+        ));
+    }
+
+    #[test]
+    fn accept_ticket_of_service_with_other_name_type() {
+        let ap_req = create_ap_req(
+            "castle.kingdom.hearts",
+            &create_enc_ticket_part(),
+            &create_authenticator(),
+        );
+        let mut service = service_name("castle.kingdom.hearts");
+        service.name_type = NT_PRINCIPAL;
+        let mut acceptor = create_acceptor();
+        acceptor.set_service(Some(service));
+        acceptor.set_service_realm(Some("KINGDOM.HEARTS".to_string()));
+
+        acceptor.accept_ap_req(&ap_req).unwrap();
+    }
+
+    #[test]
+    fn error_with_ticket_of_other_realm() {
+        let mut ap_req = create_ap_req(
+            "castle.kingdom.hearts",
+            &create_enc_ticket_part(),
+            &create_authenticator(),
+        );
+        ap_req.ticket.realm = "EVIL.REALM".to_string();
+        let mut acceptor = create_acceptor();
+        acceptor.set_service(Some(service_name("castle.kingdom.hearts")));
+        acceptor.set_service_realm(Some("KINGDOM.HEARTS".to_string()));
+
+        assert_eq!(
+            Err(Error::InvalidApReq(
+                "ticket of EVIL.REALM was not issued in KINGDOM.HEARTS"
+                    .to_string()
+            )),
+            acceptor.accept_ap_req(&ap_req).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn error_without_key_of_service() {
+        let ap_req = create_ap_req(
+            "gummi.kingdom.hearts",
+            &create_enc_ticket_part(),
+            &create_authenticator(),
+        );
+
+        assert!(matches!(
+            create_acceptor().accept_ap_req(&ap_req),
+            Err(Error::KeytabError(_))
+        ));
+    }
+
+    #[test]
+    fn error_with_client_other_than_ticket() {
+        let mut authenticator = create_authenticator();
+        authenticator.cname =
+            PrincipalName::new(NT_PRINCIPAL, "pete".to_string());
+
+        assert!(matches!(
+            accept(&create_enc_ticket_part(), &authenticator),
+            Err(Error::InvalidApReq(_))
+        ));
+    }
+
+    #[test]
+    fn error_with_authenticator_etype_other_than_session_key() {
+        let mut enc_ticket_part = create_enc_ticket_part();
+        enc_ticket_part.key =
+            EncryptionKey::new(AES128_CTS_HMAC_SHA1_96, vec![7; 16]);
+        let mut ap_req = create_ap_req(
+            "castle.kingdom.hearts",
+            &enc_ticket_part,
+            &create_authenticator(),
+        );
+        ap_req.authenticator.etype = AES256_CTS_HMAC_SHA1_96;
+
+        assert!(matches!(
+            create_acceptor().accept_ap_req(&ap_req),
+            Err(Error::InvalidApReq(_))
+        ));
+    }
+
+    #[test]
+    fn error_with_clock_skew() {
+        let mut authenticator = create_authenticator();
+        authenticator.ctime = (Utc::now() - Duration::minutes(10)).into();
+
+        assert_eq!(
+            Err(Error::InvalidApReq("clock skew too great".to_string())),
+            accept(&create_enc_ticket_part(), &authenticator)
+        );
+    }
+
+    #[test]
+    fn error_with_subkey_of_invalid_size() {
+        let mut authenticator = create_authenticator();
+        authenticator.subkey =
+            Some(EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![8; 3]));
+
+        assert!(matches!(
+            accept(&create_enc_ticket_part(), &authenticator),
+            Err(Error::InvalidApReq(_))
+        ));
+    }
+
+    #[test]
+    fn error_with_replayed_authenticator() {
+        let ap_req = create_ap_req(
+            "castle.kingdom.hearts",
+            &create_enc_ticket_part(),
+            &create_authenticator(),
+        );
+        let acceptor = create_acceptor();
+
+        acceptor.accept_ap_req(&ap_req).unwrap();
+        assert!(matches!(
+            acceptor.accept_ap_req(&ap_req),
+            Err(Error::ReplayError(_))
+        ));
+    }
+
+    #[test]
+    fn accept_replayed_authenticator_without_replay_cache() {
+        let ap_req = create_ap_req(
+            "castle.kingdom.hearts",
+            &create_enc_ticket_part(),
+            &create_authenticator(),
+        );
+        let mut acceptor = create_acceptor();
+        acceptor.set_replay_cache(None);
+
+        acceptor.accept_ap_req(&ap_req).unwrap();
+        acceptor.accept_ap_req(&ap_req).unwrap();
+    }
+
+    #[test]
+    fn accept_ticket_with_pac() {
+        let accepted = accept(
+            &create_enc_ticket_part_with_pac(&SERVICE_KEY),
+            &create_authenticator(),
+        )
+        .unwrap();
+
+        let pac = accepted.pac().unwrap();
+        assert_eq!("mickey", pac.client_info().unwrap().unwrap().name);
+        assert_eq!(1, accepted.ticket_ad_elements()[0].children().len());
+    }
+
+    #[test]
+    fn error_with_pac_signed_with_other_key() {
+        assert!(matches!(
+            accept(
+                &create_enc_ticket_part_with_pac(&Key::AES256Key([5; 32])),
+                &create_authenticator()
+            ),
+            Err(Error::InvalidTicket(_))
+        ));
+    }
+
+    #[test]
+    fn accept_ticket_with_required_auth_indicator() {
+        let ap_req = create_ap_req(
+            "castle.kingdom.hearts",
+            &create_enc_ticket_part_with_indicator("otp"),
+            &create_authenticator(),
+        );
+        let mut acceptor = create_acceptor();
+        acceptor.set_required_auth_indicators(vec![
+            "pkinit".to_string(),
+            "otp".to_string(),
+        ]);
+
+        let accepted = acceptor.accept_ap_req(&ap_req).unwrap();
+        assert_eq!(vec!["otp"], accepted.auth_indicators());
+    }
+
+    #[test]
+    fn error_without_required_auth_indicator() {
+        let ap_req = create_ap_req(
+            "castle.kingdom.hearts",
+            &create_enc_ticket_part_with_indicator("hardened"),
+            &create_authenticator(),
+        );
+        let mut acceptor = create_acceptor();
+        acceptor.set_required_auth_indicators(vec!["otp".to_string()]);
+
+        assert!(matches!(
+            acceptor.accept_ap_req(&ap_req),
+            Err(Error::InvalidTicket(_))
+        ));
+    }
+
+    #[test]
+    fn error_with_critical_authorization_data_not_understood() {
+        let mut enc_ticket_part = create_enc_ticket_part();
+        enc_ticket_part.authorization_data =
+            Some(vec![AuthorizationDataEntry {
+                ad_type: 999,
+                ad_data: vec![1, 2, 3],
+            }]);
+
+        assert!(matches!(
+            accept(&enc_ticket_part, &create_authenticator()),
+            Err(Error::InvalidAuthorizationData(_))
+        ));
+    }
+
+    #[test]
+    fn error_with_kdc_issued_in_authenticator() {
+        let elements = vec![AuthorizationDataEntry {
+            ad_type: AD_AUTHENTICATION_INDICATOR,
+            ad_data: vec![Utf8String::from("otp")].build(),
+        }];
+        let key = session_key();
+        let kdc_issued = AdKdcIssued {
+            ad_checksum: Checksum {
+                cksumtype: mandatory_checksum_type(key.keytype).unwrap(),
+                checksum: checksum(
+                    mandatory_checksum_type(key.keytype).unwrap(),
+                    &key.keyvalue,
+                    KEY_USAGE_AD_KDCISSUED_CKSUM,
+                    &elements.build(),
+                )
+                .unwrap(),
+            },
+            elements,
+            ..Default::default()
+        };
+        let mut authenticator = create_authenticator();
+        authenticator.authorization_data = Some(vec![AuthorizationDataEntry {
+            ad_type: AD_KDCISSUED,
+            ad_data: kdc_issued.build(),
+        }]);
+
+        assert_eq!(
+            Err(Error::InvalidAuthorizationData(
+                "AD-KDCIssued outside of the ticket".to_string()
+            )),
+            accept(&create_enc_ticket_part(), &authenticator).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn error_with_expired_ticket() {
+        let mut enc_ticket_part = create_enc_ticket_part();
+        enc_ticket_part.endtime = (Utc::now() - Duration::hours(1)).into();
+
+        assert_eq!(
+            Err(Error::InvalidTicket("ticket expired".to_string())),
+            accept(&enc_ticket_part, &create_authenticator())
+        );
+    }
+}
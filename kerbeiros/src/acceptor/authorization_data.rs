@@ -0,0 +1,558 @@
+use super::cammac::CammacElements;
+use crate::{Error, Result};
+use himmelblau_kerberos_asn1::{
+    AdAndOr, AdAuthenticationIndicator, AdCammac, AdKdcIssued, Asn1Object,
+    AuthorizationData, AuthorizationDataEntry, Checksum, EncryptionKey,
+    KerbAdRestrictionEntry, PrincipalName, Realm,
+};
+use himmelblau_kerberos_constants::ad_types::{
+    AD_AND_OR, AD_AUTHENTICATION_INDICATOR, AD_AUTH_DATA_AP_OPTIONS, AD_CAMMAC,
+    AD_IF_RELEVANT, AD_KDCISSUED, AD_MANDATORY_FOR_KDC, AD_WIN2K_PACK,
+    KERB_AUTH_DATA_TOKEN_RESTRICTIONS, KERB_LOCAL,
+};
+use himmelblau_kerberos_constants::key_usages::KEY_USAGE_AD_KDCISSUED_CKSUM;
+use himmelblau_kerberos_crypto::{checksum, mandatory_checksum_type};
+use himmelblau_kerberos_pac::Pac;
+use std::convert::TryInto;
+
+/// Maximum number of container elements, such as *AD-IF-RELEVANT*, that
+/// can be nested inside each other.
+const MAX_NESTING_DEPTH: usize = 8;
+
+/// Element of the authorization data of a ticket or an authenticator,
+/// decoded according to its `ad-type`, as described in RFC4120, section
+/// 5.2.6, and MS-KILE, section 2.2.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AdElement {
+    /// *AD-IF-RELEVANT* elements, which can be ignored if not understood.
+    IfRelevant(Vec<AdElement>),
+
+    /// *AD-KDCIssued* elements, whose checksum was verified.
+    KdcIssued(KdcIssuedElements),
+
+    /// *AD-AND-OR* elements, with the number of them that must be
+    /// satisfied.
+    AndOr(i32, Vec<AdElement>),
+
+    /// *AD-MANDATORY-FOR-KDC* elements.
+    MandatoryForKdc(Vec<AdElement>),
+
+    /// *AD-CAMMAC* elements of RFC7751, whose verifiers can be checked
+    /// with the keys of the service and the KDC.
+    Cammac(CammacElements),
+
+    /// Authentication indicators of RFC8129, such as "otp" or "pkinit".
+    AuthenticationIndicators(Vec<String>),
+
+    /// *KERB-AD-RESTRICTION-ENTRY* elements of MS-KILE.
+    TokenRestrictions(Vec<KerbAdRestrictionEntry>),
+
+    /// *KERB-LOCAL* data of MS-KILE, only meaningful for the client.
+    KerbLocal(Vec<u8>),
+
+    /// Options of *AD-AUTH-DATA-AP-OPTIONS* of MS-KILE, such as
+    /// `KERB_AP_OPTIONS_CBT`.
+    ApOptions(u32),
+
+    /// Privilege Attribute Certificate of Active Directory.
+    Win2kPac(Pac),
+
+    /// Element of a type that is not decoded.
+    Unknown(AuthorizationDataEntry),
+}
+
+impl AdElement {
+    /// `ad-type` of the element.
+    pub fn ad_type(&self) -> i32 {
+        return match self {
+            AdElement::IfRelevant(_) => AD_IF_RELEVANT,
+            AdElement::KdcIssued(_) => AD_KDCISSUED,
+            AdElement::AndOr(_, _) => AD_AND_OR,
+            AdElement::MandatoryForKdc(_) => AD_MANDATORY_FOR_KDC,
+            AdElement::Cammac(_) => AD_CAMMAC,
+            AdElement::AuthenticationIndicators(_) => {
+                AD_AUTHENTICATION_INDICATOR
+            }
+            AdElement::TokenRestrictions(_) => {
+                KERB_AUTH_DATA_TOKEN_RESTRICTIONS
+            }
+            AdElement::KerbLocal(_) => KERB_LOCAL,
+            AdElement::ApOptions(_) => AD_AUTH_DATA_AP_OPTIONS,
+            AdElement::Win2kPac(_) => AD_WIN2K_PACK,
+            AdElement::Unknown(entry) => entry.ad_type,
+        };
+    }
+
+    /// Elements contained by this one, if it is a container.
+    pub fn children(&self) -> &[AdElement] {
+        return match self {
+            AdElement::IfRelevant(elements)
+            | AdElement::AndOr(_, elements)
+            | AdElement::MandatoryForKdc(elements) => elements,
+            AdElement::KdcIssued(kdc_issued) => &kdc_issued.elements,
+            AdElement::Cammac(cammac) => cammac.elements(),
+            _ => &[],
+        };
+    }
+}
+
+/// Content of an *AD-KDCIssued* element.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KdcIssuedElements {
+    pub ad_checksum: Checksum,
+    pub i_realm: Option<Realm>,
+    pub i_sname: Option<PrincipalName>,
+    pub elements: Vec<AdElement>,
+}
+
+/// Returns the elements and all the elements contained by them, in
+/// depth-first order.
+pub fn walk_ad_elements(elements: &[AdElement]) -> Vec<&AdElement> {
+    let mut walked = Vec::new();
+    for element in elements {
+        walked.push(element);
+        walked.append(&mut walk_ad_elements(element.children()));
+    }
+    return walked;
+}
+
+/// Decodes the authorization data of tickets and authenticators.
+///
+/// The checksum of the *AD-KDCIssued* elements is verified with the
+/// session key of the ticket, and they are only accepted in the ticket. As required by RFC4120, the elements that
+/// are not inside an *AD-IF-RELEVANT* element are critical, so an error
+/// is returned if their type is not understood. The types of the elements
+/// that the application handles by itself can be added to the understood
+/// ones, and are returned as [`AdElement::Unknown`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AuthorizationDataDecoder {
+    understood_types: Vec<i32>,
+}
+
+impl AuthorizationDataDecoder {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// Adds a type of element that must not be rejected when critical.
+    pub fn add_understood_type(&mut self, ad_type: i32) {
+        self.understood_types.push(ad_type);
+    }
+
+    /// Decodes the authorization data of a ticket.
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidAuthorizationData`] if an element cannot
+    /// be decoded, the checksum of an *AD-KDCIssued* element does not
+    /// match or a critical element is not understood.
+    pub fn decode(
+        &self,
+        authorization_data: &AuthorizationData,
+        session_key: &EncryptionKey,
+    ) -> Result<Vec<AdElement>> {
+        return self.decode_elements(
+            authorization_data,
+            Some(session_key),
+            true,
+            0,
+        );
+    }
+
+    /// Decodes the authorization data of an authenticator. Since the
+    /// client knows the session key, it could forge the checksum of the
+    /// *AD-KDCIssued* elements, so they are rejected.
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidAuthorizationData`] if an element cannot
+    /// be decoded, an *AD-KDCIssued* element is included or a critical
+    /// element is not understood.
+    pub fn decode_authenticator(
+        &self,
+        authorization_data: &AuthorizationData,
+    ) -> Result<Vec<AdElement>> {
+        return self.decode_elements(authorization_data, None, true, 0);
+    }
+
+    fn decode_elements(
+        &self,
+        authorization_data: &AuthorizationData,
+        session_key: Option<&EncryptionKey>,
+        critical: bool,
+        depth: usize,
+    ) -> Result<Vec<AdElement>> {
+        if depth > MAX_NESTING_DEPTH {
+            return Err(Error::InvalidAuthorizationData(format!(
+                "more than {} levels of nested elements",
+                MAX_NESTING_DEPTH
+            )));
+        }
+
+        let mut elements = Vec::with_capacity(authorization_data.len());
+        for entry in authorization_data.iter() {
+            let element =
+                self.decode_entry(entry, session_key, critical, depth)?;
+            if critical && !self.is_understood(&element) {
+                return Err(Error::InvalidAuthorizationData(format!(
+                    "critical element of type {} not understood",
+                    entry.ad_type
+                )));
+            }
+            elements.push(element);
+        }
+        return Ok(elements);
+    }
+
+    fn decode_entry(
+        &self,
+        entry: &AuthorizationDataEntry,
+        session_key: Option<&EncryptionKey>,
+        critical: bool,
+        depth: usize,
+    ) -> Result<AdElement> {
+        let ad_data = &entry.ad_data;
+        let depth = depth + 1;
+        let element = match entry.ad_type {
+            AD_IF_RELEVANT => AdElement::IfRelevant(self.decode_elements(
+                &parse_ad::<AuthorizationData>(entry)?,
+                session_key,
+                false,
+                depth,
+            )?),
+            AD_KDCISSUED => AdElement::KdcIssued(self.decode_kdc_issued(
+                &parse_ad(entry)?,
+                session_key,
+                critical,
+                depth,
+            )?),
+            AD_AND_OR => self.decode_and_or(
+                &parse_ad(entry)?,
+                session_key,
+                critical,
+                depth,
+            )?,
+            AD_MANDATORY_FOR_KDC => {
+                AdElement::MandatoryForKdc(self.decode_elements(
+                    &parse_ad(entry)?,
+                    session_key,
+                    critical,
+                    depth,
+                )?)
+            }
+            AD_CAMMAC => {
+                let cammac: AdCammac = parse_ad(entry)?;
+                let elements = self.decode_elements(
+                    &cammac.elements,
+                    session_key,
+                    critical,
+                    depth,
+                )?;
+                AdElement::Cammac(CammacElements::new(cammac, elements))
+            }
+            AD_AUTHENTICATION_INDICATOR => {
+                let indicators: AdAuthenticationIndicator = parse_ad(entry)?;
+                AdElement::AuthenticationIndicators(
+                    indicators
+                        .into_iter()
+                        .map(|indicator| indicator.string)
+                        .collect(),
+                )
+            }
+            KERB_AUTH_DATA_TOKEN_RESTRICTIONS => {
+                AdElement::TokenRestrictions(parse_ad(entry)?)
+            }
+            KERB_LOCAL => AdElement::KerbLocal(ad_data.clone()),
+            AD_AUTH_DATA_AP_OPTIONS => {
+                let options: [u8; 4] =
+                    ad_data.as_slice().try_into().map_err(|_| {
+                        Error::InvalidAuthorizationData(format!(
+                            "{} bytes of AP options",
+                            ad_data.len()
+                        ))
+                    })?;
+                AdElement::ApOptions(u32::from_le_bytes(options))
+            }
+            AD_WIN2K_PACK => {
+                AdElement::Win2kPac(Pac::parse(ad_data).map_err(|error| {
+                    Error::InvalidAuthorizationData(error.to_string())
+                })?)
+            }
+            _ => AdElement::Unknown(entry.clone()),
+        };
+        return Ok(element);
+    }
+
+    fn decode_kdc_issued(
+        &self,
+        kdc_issued: &AdKdcIssued,
+        session_key: Option<&EncryptionKey>,
+        critical: bool,
+        depth: usize,
+    ) -> Result<KdcIssuedElements> {
+        let session_key = session_key.ok_or_else(|| {
+            Error::InvalidAuthorizationData(
+                "AD-KDCIssued outside of the ticket".to_string(),
+            )
+        })?;
+        let ad_checksum = &kdc_issued.ad_checksum;
+        let cksumtype = mandatory_checksum_type(session_key.keytype)?;
+        if ad_checksum.cksumtype != cksumtype {
+            return Err(Error::InvalidAuthorizationData(format!(
+                "checksum type {} of AD-KDCIssued instead of {}",
+                ad_checksum.cksumtype, cksumtype
+            )));
+        }
+        let expected = checksum(
+            ad_checksum.cksumtype,
+            &session_key.keyvalue,
+            KEY_USAGE_AD_KDCISSUED_CKSUM,
+            &kdc_issued.elements.build(),
+        )?;
+        if expected != ad_checksum.checksum {
+            return Err(Error::InvalidAuthorizationData(
+                "invalid checksum of AD-KDCIssued".to_string(),
+            ));
+        }
+
+        return Ok(KdcIssuedElements {
+            ad_checksum: ad_checksum.clone(),
+            i_realm: kdc_issued.i_realm.clone(),
+            i_sname: kdc_issued.i_sname.clone(),
+            elements: self.decode_elements(
+                &kdc_issued.elements,
+                Some(session_key),
+                critical,
+                depth,
+            )?,
+        });
+    }
+
+    /// Decodes an *AD-AND-OR* element, which is critical only if less
+    /// than `condition-count` elements are understood.
+    fn decode_and_or(
+        &self,
+        and_or: &AdAndOr,
+        session_key: Option<&EncryptionKey>,
+        critical: bool,
+        depth: usize,
+    ) -> Result<AdElement> {
+        let elements =
+            self.decode_elements(&and_or.elements, session_key, false, depth)?;
+        let understood = elements
+            .iter()
+            .filter(|element| self.is_understood(element))
+            .count();
+        if critical && (understood as i64) < and_or.condition_count as i64 {
+            return Err(Error::InvalidAuthorizationData(format!(
+                "only {} of {} elements of AD-AND-OR are understood",
+                understood, and_or.condition_count
+            )));
+        }
+        return Ok(AdElement::AndOr(and_or.condition_count, elements));
+    }
+
+    fn is_understood(&self, element: &AdElement) -> bool {
+        return match element {
+            AdElement::Unknown(entry) => {
+                self.understood_types.contains(&entry.ad_type)
+            }
+            _ => true,
+        };
+    }
+}
+
+fn parse_ad<T: Asn1Object>(entry: &AuthorizationDataEntry) -> Result<T> {
+    let (_, value) = T::parse(&entry.ad_data).map_err(|_| {
+        Error::InvalidAuthorizationData(format!(
+            "invalid element of type {}",
+            entry.ad_type
+        ))
+    })?;
+    return Ok(value);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use himmelblau_kerberos_constants::checksum_types::HMAC_SHA1_96_AES256;
+    use himmelblau_kerberos_constants::etypes::{
+        AES128_CTS_HMAC_SHA1_96, AES256_CTS_HMAC_SHA1_96,
+    };
+    use himmelblau_kerberos_pac::PAC_CLIENT_INFO;
+    use himmelblau_kerberos_pac::{PacClientInfo, PacInfoBuffer};
+
+    fn session_key() -> EncryptionKey {
+        return EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![7; 32]);
+    }
+
+    fn entry(ad_type: i32, ad_data: Vec<u8>) -> AuthorizationDataEntry {
+        return AuthorizationDataEntry { ad_type, ad_data };
+    }
+
+    fn create_pac() -> Pac {
+        return Pac::new(vec![PacInfoBuffer::new(
+            PAC_CLIENT_INFO,
+            PacClientInfo::new(1, "mickey".to_string()).build(),
+        )]);
+    }
+
+    fn kdc_issued(elements: AuthorizationData) -> AuthorizationDataEntry {
+        let ad_checksum = Checksum {
+            cksumtype: HMAC_SHA1_96_AES256,
+            checksum: checksum(
+                HMAC_SHA1_96_AES256,
+                &session_key().keyvalue,
+                KEY_USAGE_AD_KDCISSUED_CKSUM,
+                &elements.build(),
+            )
+            .unwrap(),
+        };
+        let kdc_issued = AdKdcIssued {
+            ad_checksum,
+            elements,
+            ..Default::default()
+        };
+        return entry(AD_KDCISSUED, kdc_issued.build());
+    }
+
+    #[test]
+    fn decode_nested_authorization_data() {
+        let if_relevant = vec![
+            entry(AD_WIN2K_PACK, create_pac().build()),
+            entry(AD_AUTH_DATA_AP_OPTIONS, vec![0, 0x40, 0, 0]),
+            entry(999, vec![1, 2, 3]),
+        ];
+        let authorization_data = vec![
+            entry(AD_IF_RELEVANT, if_relevant.build()),
+            kdc_issued(vec![entry(KERB_LOCAL, vec![4, 5])]),
+        ];
+
+        let elements = AuthorizationDataDecoder::new()
+            .decode(&authorization_data, &session_key())
+            .unwrap();
+
+        let walked = walk_ad_elements(&elements);
+        assert_eq!(
+            vec![
+                AD_IF_RELEVANT,
+                AD_WIN2K_PACK,
+                AD_AUTH_DATA_AP_OPTIONS,
+                999,
+                AD_KDCISSUED,
+                KERB_LOCAL
+            ],
+            walked
+                .iter()
+                .map(|element| element.ad_type())
+                .collect::<Vec<i32>>()
+        );
+        assert_eq!(&AdElement::Win2kPac(create_pac()), walked[1]);
+        assert_eq!(&AdElement::ApOptions(0x4000), walked[2]);
+        assert_eq!(&AdElement::KerbLocal(vec![4, 5]), walked[5]);
+    }
+
+    #[test]
+    fn decode_understood_critical_element() {
+        let authorization_data = vec![entry(999, vec![1, 2, 3])];
+        let mut decoder = AuthorizationDataDecoder::new();
+        decoder.add_understood_type(999);
+
+        assert_eq!(
+            vec![AdElement::Unknown(entry(999, vec![1, 2, 3]))],
+            decoder.decode(&authorization_data, &session_key()).unwrap()
+        );
+    }
+
+    #[test]
+    fn decode_and_or_with_enough_understood_elements() {
+        let and_or = AdAndOr {
+            condition_count: 1,
+            elements: vec![entry(999, vec![]), entry(KERB_LOCAL, vec![])],
+        };
+        let authorization_data = vec![entry(AD_AND_OR, and_or.build())];
+
+        let elements = AuthorizationDataDecoder::new()
+            .decode(&authorization_data, &session_key())
+            .unwrap();
+
+        assert_eq!(2, elements[0].children().len());
+    }
+
+    #[test]
+    fn error_with_critical_element_not_understood() {
+        let authorization_data =
+            vec![kdc_issued(vec![entry(999, vec![1, 2, 3])])];
+
+        assert_eq!(
+            Err(Error::InvalidAuthorizationData(
+                "critical element of type 999 not understood".to_string()
+            )),
+            AuthorizationDataDecoder::new()
+                .decode(&authorization_data, &session_key())
+        );
+    }
+
+    #[test]
+    fn error_with_invalid_kdc_issued_checksum() {
+        let authorization_data =
+            vec![kdc_issued(vec![entry(KERB_LOCAL, vec![4, 5])])];
+        let other_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![8; 32]);
+
+        assert_eq!(
+            Err(Error::InvalidAuthorizationData(
+                "invalid checksum of AD-KDCIssued".to_string()
+            )),
+            AuthorizationDataDecoder::new()
+                .decode(&authorization_data, &other_key)
+        );
+    }
+
+    #[test]
+    fn error_with_kdc_issued_checksum_of_other_type() {
+        let authorization_data =
+            vec![kdc_issued(vec![entry(KERB_LOCAL, vec![4, 5])])];
+        let aes128_key =
+            EncryptionKey::new(AES128_CTS_HMAC_SHA1_96, vec![8; 16]);
+
+        assert_eq!(
+            Err(Error::InvalidAuthorizationData(
+                "checksum type 16 of AD-KDCIssued instead of 15".to_string()
+            )),
+            AuthorizationDataDecoder::new()
+                .decode(&authorization_data, &aes128_key)
+        );
+    }
+
+    #[test]
+    fn error_with_kdc_issued_in_authenticator() {
+        let authorization_data = vec![entry(
+            AD_IF_RELEVANT,
+            vec![kdc_issued(vec![entry(KERB_LOCAL, vec![4, 5])])].build(),
+        )];
+
+        assert_eq!(
+            Err(Error::InvalidAuthorizationData(
+                "AD-KDCIssued outside of the ticket".to_string()
+            )),
+            AuthorizationDataDecoder::new()
+                .decode_authenticator(&authorization_data)
+        );
+    }
+
+    #[test]
+    fn error_with_too_nested_elements() {
+        let mut authorization_data = vec![entry(KERB_LOCAL, vec![4, 5])];
+        for _ in 0..=MAX_NESTING_DEPTH {
+            authorization_data =
+                vec![entry(AD_IF_RELEVANT, authorization_data.build())];
+        }
+
+        assert_eq!(
+            Err(Error::InvalidAuthorizationData(
+                "more than 8 levels of nested elements".to_string()
+            )),
+            AuthorizationDataDecoder::new()
+                .decode(&authorization_data, &session_key())
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use super::authorization_data::{walk_ad_elements, AdElement};
+use crate::{Error, Result};
+use himmelblau_kerberos_asn1::{
+    AdCammac, Asn1Object, AuthorizationData, Checksum, VerifierMac,
+};
+use himmelblau_kerberos_constants::key_usages::KEY_USAGE_CAMMAC_CHECKSUM;
+use himmelblau_kerberos_crypto::{checksum, mandatory_checksum_type, Key};
+
+/// Content of an *AD-CAMMAC* element, as described in RFC7751.
+///
+/// The elements of the container are trusted by the service that
+/// decrypts the ticket. However, when the ticket is presented to the KDC
+/// or another service, the verifiers prove that the elements were issued
+/// by the KDC.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CammacElements {
+    cammac: AdCammac,
+    elements: Vec<AdElement>,
+}
+
+impl CammacElements {
+    pub(crate) fn new(cammac: AdCammac, elements: Vec<AdElement>) -> Self {
+        return Self { cammac, elements };
+    }
+
+    /// The raw *AD-CAMMAC*, with its verifiers.
+    pub fn cammac(&self) -> &AdCammac {
+        return &self.cammac;
+    }
+
+    /// Decoded elements of the container.
+    pub fn elements(&self) -> &[AdElement] {
+        return &self.elements;
+    }
+
+    /// Verifies the `svc-verifier` with the key of the service that
+    /// decrypted the ticket.
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidAuthorizationData`] if the verifier is
+    /// missing or does not match.
+    pub fn verify_svc_verifier(&self, service_key: &Key) -> Result<()> {
+        return verify_mac(
+            "svc-verifier",
+            self.cammac.svc_verifier.as_ref(),
+            &self.cammac.elements,
+            service_key,
+        );
+    }
+
+    /// Verifies the `kdc-verifier` with the key of the `krbtgt` account.
+    pub fn verify_kdc_verifier(&self, krbtgt_key: &Key) -> Result<()> {
+        return verify_mac(
+            "kdc-verifier",
+            self.cammac.kdc_verifier.as_ref(),
+            &self.cammac.elements,
+            krbtgt_key,
+        );
+    }
+}
+
+fn verify_mac(
+    name: &str,
+    verifier: Option<&VerifierMac>,
+    elements: &AuthorizationData,
+    key: &Key,
+) -> Result<()> {
+    let verifier = verifier.ok_or_else(|| {
+        Error::InvalidAuthorizationData(format!("AD-CAMMAC without {}", name))
+    })?;
+    if verifier.mac != cammac_checksum(elements, key)? {
+        return Err(Error::InvalidAuthorizationData(format!(
+            "invalid {} of AD-CAMMAC",
+            name
+        )));
+    }
+    return Ok(());
+}
+
+/// Computes the checksum of the elements of an *AD-CAMMAC* with the
+/// mandatory checksum type of the key.
+pub(crate) fn cammac_checksum(
+    elements: &AuthorizationData,
+    key: &Key,
+) -> Result<Checksum> {
+    if let Key::Secret(_) = key {
+        return Err(Error::InvalidAuthorizationData(
+            "AD-CAMMAC cannot be verified with a password".to_string(),
+        ));
+    }
+    let cksumtype = mandatory_checksum_type(key.etypes()[0])?;
+    return Ok(Checksum {
+        cksumtype,
+        checksum: checksum(
+            cksumtype,
+            key.as_bytes(),
+            KEY_USAGE_CAMMAC_CHECKSUM,
+            &elements.build(),
+        )?,
+    });
+}
+
+/// Authentication indicators included in the *AD-CAMMAC* elements, which
+/// show how the client was authenticated, as described in RFC8129.
+/// Indicators outside of an *AD-CAMMAC* are ignored, since they may not
+/// be issued by the KDC.
+pub fn auth_indicators(elements: &[AdElement]) -> Vec<&str> {
+    let mut indicators = Vec::new();
+    for element in walk_ad_elements(elements) {
+        if let AdElement::Cammac(cammac) = element {
+            for child in cammac.elements() {
+                if let AdElement::AuthenticationIndicators(values) = child {
+                    indicators.extend(values.iter().map(String::as_str));
+                }
+            }
+        }
+    }
+    return indicators;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::acceptor::AuthorizationDataDecoder;
+    use himmelblau_kerberos_asn1::{
+        AuthorizationDataEntry, EncryptionKey, Utf8String,
+    };
+    use himmelblau_kerberos_constants::ad_types::{
+        AD_AUTHENTICATION_INDICATOR, AD_CAMMAC, AD_IF_RELEVANT,
+    };
+    use himmelblau_kerberos_constants::etypes::AES256_CTS_HMAC_SHA1_96;
+
+    const SERVICE_KEY: Key = Key::AES256Key([4; 32]);
+    const KRBTGT_KEY: Key = Key::RC4Key([9; 16]);
+
+    fn indicators_entry(indicators: &[&str]) -> AuthorizationDataEntry {
+        let indicators: Vec<Utf8String> =
+            indicators.iter().map(|&i| Utf8String::from(i)).collect();
+        return AuthorizationDataEntry {
+            ad_type: AD_AUTHENTICATION_INDICATOR,
+            ad_data: indicators.build(),
+        };
+    }
+
+    fn cammac_entry(elements: AuthorizationData) -> AuthorizationDataEntry {
+        let cammac = AdCammac {
+            svc_verifier: Some(VerifierMac {
+                mac: cammac_checksum(&elements, &SERVICE_KEY).unwrap(),
+                ..Default::default()
+            }),
+            kdc_verifier: Some(VerifierMac {
+                mac: cammac_checksum(&elements, &KRBTGT_KEY).unwrap(),
+                ..Default::default()
+            }),
+            elements,
+            ..Default::default()
+        };
+        let if_relevant = vec![AuthorizationDataEntry {
+            ad_type: AD_CAMMAC,
+            ad_data: cammac.build(),
+        }];
+        return AuthorizationDataEntry {
+            ad_type: AD_IF_RELEVANT,
+            ad_data: if_relevant.build(),
+        };
+    }
+
+    fn decode(authorization_data: AuthorizationData) -> Vec<AdElement> {
+        let session_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![7; 32]);
+        return AuthorizationDataDecoder::new()
+            .decode(&authorization_data, &session_key)
+            .unwrap();
+    }
+
+    fn cammac_of(elements: &[AdElement]) -> &CammacElements {
+        return match &elements[0].children()[0] {
+            AdElement::Cammac(cammac) => cammac,
+            element => panic!("Unexpected element {:?}", element),
+        };
+    }
+
+    #[test]
+    fn get_auth_indicators_of_cammac() {
+        let elements = decode(vec![
+            cammac_entry(vec![indicators_entry(&["otp", "pkinit"])]),
+            indicators_entry(&["hardened"]),
+        ]);
+
+        assert_eq!(vec!["otp", "pkinit"], auth_indicators(&elements));
+    }
+
+    #[test]
+    fn verify_cammac_verifiers() {
+        let elements =
+            decode(vec![cammac_entry(vec![indicators_entry(&["otp"])])]);
+        let cammac = cammac_of(&elements);
+
+        cammac.verify_svc_verifier(&SERVICE_KEY).unwrap();
+        cammac.verify_kdc_verifier(&KRBTGT_KEY).unwrap();
+    }
+
+    #[test]
+    fn error_verifying_cammac_with_other_key() {
+        let elements =
+            decode(vec![cammac_entry(vec![indicators_entry(&["otp"])])]);
+
+        assert_eq!(
+            Err(Error::InvalidAuthorizationData(
+                "invalid svc-verifier of AD-CAMMAC".to_string()
+            )),
+            cammac_of(&elements).verify_svc_verifier(&Key::AES256Key([5; 32]))
+        );
+    }
+}
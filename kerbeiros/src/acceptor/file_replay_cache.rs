@@ -0,0 +1,439 @@
+use super::replay_cache::{ReplayCache, ReplayEntry};
+use crate::client::current_euid;
+use crate::{Error, Result};
+use chrono::{Duration, Utc};
+use rand::RngCore;
+use std::convert::TryInto;
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Environment variable with the name of the replay cache, as in MIT.
+pub const KRB5RCACHENAME_ENV: &str = "KRB5RCACHENAME";
+
+/// Environment variable with the directory of the default replay cache.
+pub const KRB5RCACHEDIR_ENV: &str = "KRB5RCACHEDIR";
+
+/// Directory of the default replay cache, if `KRB5RCACHEDIR` is not set.
+pub const DEFAULT_RCACHE_DIR: &str = "/var/tmp";
+
+const SEED_LEN: usize = 16;
+const TAG_LEN: usize = 12;
+const RECORD_LEN: u64 = TAG_LEN as u64 + 4;
+const FIRST_TABLE_RECORDS: u64 = 1023;
+const MAX_SIZE: u64 = i32::MAX as u64;
+
+/// Replay cache stored in a file with the `file2` format of MIT, so it
+/// can be shared with other processes of the host, including the MIT
+/// services.
+///
+/// The file starts with a random seed of 16 bytes, followed by hash
+/// tables of records with the first 12 bytes of the tag of an entry
+/// and its timestamp, as a 32 bits big-endian integer. Each table
+/// doubles the size of the previous one, and is only used when the
+/// slots of the entry in the previous tables are taken. The file is
+/// locked while an entry is stored.
+///
+/// Since only the tag is stored, entries are identified by the checksum
+/// of the authenticator, regardless of the client and the server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileReplayCache {
+    path: PathBuf,
+}
+
+impl FileReplayCache {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        return Self { path: path.into() };
+    }
+
+    /// Creates the replay cache used by default by MIT, named by the
+    /// `KRB5RCACHENAME` environment variable or stored in
+    /// `$KRB5RCACHEDIR/krb5_<euid>.rcache2`.
+    ///
+    /// # Errors
+    /// Returns [`Error::ConfigError`] if `KRB5RCACHENAME` has a type other
+    /// than `dfl` or `file2`.
+    pub fn from_env() -> Result<Self> {
+        if let Ok(name) = env::var(KRB5RCACHENAME_ENV) {
+            match name.split_once(':') {
+                Some(("file2", path)) => return Ok(Self::new(path)),
+                Some(("dfl", _)) => {}
+                _ => {
+                    return Err(Error::ConfigError(format!(
+                        "Unsupported replay cache: {}",
+                        name
+                    )))
+                }
+            }
+        }
+
+        let dir = env::var(KRB5RCACHEDIR_ENV)
+            .unwrap_or_else(|_| DEFAULT_RCACHE_DIR.to_string());
+        return Ok(Self::new(
+            Path::new(&dir).join(format!("krb5_{}.rcache2", current_euid()?)),
+        ));
+    }
+
+    pub fn path(&self) -> &Path {
+        return &self.path;
+    }
+
+    fn open(&self) -> Result<File> {
+        let mut options = OpenOptions::new();
+        options.read(true).write(true).create(true).truncate(false);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+
+        return options
+            .open(&self.path)
+            .map_err(|error| Error::IOError(error.into()));
+    }
+}
+
+impl ReplayCache for FileReplayCache {
+    fn store(&self, entry: &ReplayEntry, clock_skew: Duration) -> Result<()> {
+        let mut tag = [0; TAG_LEN];
+        let len = entry.tag().len().min(TAG_LEN);
+        tag[..len].copy_from_slice(&entry.tag()[..len]);
+
+        let file = self.open()?;
+        lock_file(&file, true).map_err(|error| Error::IOError(error.into()))?;
+        let stored = store_tag(
+            &file,
+            &tag,
+            Utc::now().timestamp() as u32,
+            clock_skew.num_seconds() as u32,
+        )
+        .map_err(|error| Error::IOError(error.into()));
+        let _ = lock_file(&file, false);
+
+        if !stored? {
+            return Err(Error::ReplayError(format!(
+                "authenticator of {} for {}",
+                entry.client(),
+                entry.server()
+            )));
+        }
+        return Ok(());
+    }
+}
+
+/// Stores the tag in the hash tables of the file, as `store` of
+/// `rc_file2.c` does. Returns `false` if the tag was already stored.
+fn store_tag(
+    mut file: &File,
+    tag: &[u8; TAG_LEN],
+    now: u32,
+    skew: u32,
+) -> std::io::Result<bool> {
+    let mut seed = [0; SEED_LEN];
+    if read_at(file, 0, &mut seed)? < SEED_LEN {
+        rand::thread_rng().fill_bytes(&mut seed);
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(&seed)?;
+    }
+
+    let mut table_offset = 0;
+    let mut nrecords = 0;
+    let mut avail_offset = None;
+    loop {
+        next_table(&mut table_offset, &mut nrecords)?;
+
+        let index = siphash24(tag, &seed) % nrecords;
+        let record_offset = table_offset + index * RECORD_LEN;
+
+        let mut buffer = [0; 2 * RECORD_LEN as usize];
+        let nread =
+            read_at(file, record_offset, &mut buffer)? / RECORD_LEN as usize;
+        let records: Vec<Record> = buffer
+            .chunks(RECORD_LEN as usize)
+            .take(nread)
+            .map(Record::parse)
+            .collect();
+
+        if records.iter().any(|record| &record.tag == tag) {
+            return Ok(false);
+        }
+
+        // First record available for writing: empty, beyond the end of
+        // the file, or expired.
+        if avail_offset.is_none() {
+            for i in 0..2 {
+                if records
+                    .get(i)
+                    .map(|record| record.is_expired(now, skew))
+                    .unwrap_or(true)
+                {
+                    avail_offset = Some(record_offset + i as u64 * RECORD_LEN);
+                    break;
+                }
+            }
+        }
+
+        if records.len() < 2 || records.iter().any(|r| r.timestamp == 0) {
+            let mut record = [0; RECORD_LEN as usize];
+            record[..TAG_LEN].copy_from_slice(tag);
+            record[TAG_LEN..].copy_from_slice(&now.to_be_bytes());
+
+            file.seek(SeekFrom::Start(avail_offset.unwrap_or(record_offset)))?;
+            file.write_all(&record)?;
+            return Ok(true);
+        }
+
+        // Use a different hash seed for the next table.
+        seed[0] = seed[0].wrapping_add(1);
+    }
+}
+
+/// Record of a hash table of the file.
+struct Record {
+    tag: [u8; TAG_LEN],
+    timestamp: u32,
+}
+
+impl Record {
+    fn parse(raw: &[u8]) -> Self {
+        let mut tag = [0; TAG_LEN];
+        tag.copy_from_slice(&raw[..TAG_LEN]);
+        let mut timestamp = [0; 4];
+        timestamp.copy_from_slice(&raw[TAG_LEN..]);
+        return Self {
+            tag,
+            timestamp: u32::from_be_bytes(timestamp),
+        };
+    }
+
+    fn is_expired(&self, now: u32, skew: u32) -> bool {
+        return (now.wrapping_sub(self.timestamp.wrapping_add(skew)) as i32)
+            > 0;
+    }
+}
+
+/// Locks or unlocks the whole file with a POSIX record lock, as
+/// `krb5_lock_file` of MIT does, so the lock is shared with the MIT
+/// processes. The lock waits until it is acquired.
+#[cfg(unix)]
+fn lock_file(file: &File, lock: bool) -> std::io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    // zeroed start and length cover the whole file
+    let mut flock: libc::flock = unsafe { std::mem::zeroed() };
+    flock.l_whence = libc::SEEK_SET as _;
+    let command = if lock {
+        flock.l_type = libc::F_WRLCK as _;
+        libc::F_SETLKW
+    } else {
+        flock.l_type = libc::F_UNLCK as _;
+        libc::F_SETLK
+    };
+
+    loop {
+        if unsafe { libc::fcntl(file.as_raw_fd(), command, &flock) } == 0 {
+            return Ok(());
+        }
+        let error = std::io::Error::last_os_error();
+        if error.kind() != std::io::ErrorKind::Interrupted {
+            return Err(error);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn lock_file(_file: &File, _lock: bool) -> std::io::Result<()> {
+    return Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "File locks are not available in this platform",
+    ));
+}
+
+/// Moves to the next hash table: the first one has 1023 records after the
+/// seed, the second one 2048, and the next ones double the previous size.
+fn next_table(offset: &mut u64, nrecords: &mut u64) -> std::io::Result<()> {
+    if *offset == 0 {
+        *offset = SEED_LEN as u64;
+        *nrecords = FIRST_TABLE_RECORDS;
+    } else if *offset == SEED_LEN as u64 {
+        *offset += *nrecords * RECORD_LEN;
+        *nrecords = (FIRST_TABLE_RECORDS + 1) * 2;
+    } else {
+        *offset += *nrecords * RECORD_LEN;
+        *nrecords *= 2;
+    }
+
+    if *nrecords > MAX_SIZE / RECORD_LEN
+        || *offset > MAX_SIZE - *nrecords * RECORD_LEN
+    {
+        return Err(std::io::Error::other("replay cache is full"));
+    }
+    return Ok(());
+}
+
+/// Reads into `buffer` from `offset`, until the buffer is full or the end
+/// of the file is reached.
+fn read_at(
+    mut file: &File,
+    offset: u64,
+    buffer: &mut [u8],
+) -> std::io::Result<usize> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut total = 0;
+    while total < buffer.len() {
+        match file.read(&mut buffer[total..])? {
+            0 => break,
+            n => total += n,
+        }
+    }
+    return Ok(total);
+}
+
+/// SipHash-2-4 of `data` with a 128 bits `key`, as `k5_siphash24` of MIT.
+fn siphash24(data: &[u8], key: &[u8; SEED_LEN]) -> u64 {
+    let k0 = u64::from_le_bytes(key[..8].try_into().unwrap());
+    let k1 = u64::from_le_bytes(key[8..].try_into().unwrap());
+    let mut v = [
+        k0 ^ 0x736f6d6570736575,
+        k1 ^ 0x646f72616e646f6d,
+        k0 ^ 0x6c7967656e657261,
+        k1 ^ 0x7465646279746573,
+    ];
+
+    fn round(v: &mut [u64; 4]) {
+        v[0] = v[0].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(13) ^ v[0];
+        v[0] = v[0].rotate_left(32);
+        v[2] = v[2].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(16) ^ v[2];
+        v[0] = v[0].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(21) ^ v[0];
+        v[2] = v[2].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(17) ^ v[2];
+        v[2] = v[2].rotate_left(32);
+    }
+
+    let mut compress = |m: u64| {
+        v[3] ^= m;
+        round(&mut v);
+        round(&mut v);
+        v[0] ^= m;
+    };
+
+    let chunks = data.chunks_exact(8);
+    let remainder = chunks.remainder();
+    for chunk in chunks {
+        compress(u64::from_le_bytes(chunk.try_into().unwrap()));
+    }
+    let mut last = [0; 8];
+    last[..remainder.len()].copy_from_slice(remainder);
+    last[7] = data.len() as u8;
+    compress(u64::from_le_bytes(last));
+
+    v[2] ^= 0xff;
+    for _ in 0..4 {
+        round(&mut v);
+    }
+    return v[0] ^ v[1] ^ v[2] ^ v[3];
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+
+    fn create_entry(tag: u8) -> ReplayEntry {
+        return ReplayEntry::new(
+            "mickey@KINGDOM.HEARTS".to_string(),
+            "http/castle.kingdom.hearts@KINGDOM.HEARTS".to_string(),
+            Utc::now(),
+            1234,
+            vec![tag; 12],
+        );
+    }
+
+    fn temp_cache(name: &str) -> FileReplayCache {
+        let path = env::temp_dir().join(format!(
+            "kerbeiros_rcache_{}_{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        return FileReplayCache::new(path);
+    }
+
+    #[test]
+    fn siphash24_test_vector() {
+        let key: [u8; 16] = core::array::from_fn(|i| i as u8);
+        let data: Vec<u8> = (0..15).collect();
+        assert_eq!(0xa129ca6149be45e5, siphash24(&data, &key));
+    }
+
+    #[test]
+    fn detect_replay_in_file() {
+        let cache = temp_cache("replay");
+        let clock_skew = Duration::minutes(5);
+
+        cache.store(&create_entry(1), clock_skew).unwrap();
+        cache.store(&create_entry(2), clock_skew).unwrap();
+
+        let other_process = FileReplayCache::new(cache.path());
+        assert!(matches!(
+            other_process.store(&create_entry(1), clock_skew),
+            Err(Error::ReplayError(_))
+        ));
+        let _ = fs::remove_file(cache.path());
+    }
+
+    #[test]
+    fn write_records_in_file2_format() {
+        let cache = temp_cache("format");
+        cache.store(&create_entry(3), Duration::minutes(5)).unwrap();
+
+        let data = fs::read(cache.path()).unwrap();
+        let seed: [u8; 16] = data[..16].try_into().unwrap();
+        let index = siphash24(&[3; 12], &seed) % FIRST_TABLE_RECORDS;
+        let offset = 16 + index as usize * 16;
+
+        assert_eq!(offset + 16, data.len());
+        assert_eq!([3; 12], data[offset..offset + 12]);
+        assert_ne!([0; 4], data[offset + 12..offset + 16]);
+        let _ = fs::remove_file(cache.path());
+    }
+
+    #[test]
+    fn detect_replay_of_record_with_zero_timestamp() {
+        let cache = temp_cache("zero_timestamp");
+        let seed = [7; 16];
+        let index = siphash24(&[4; 12], &seed) % FIRST_TABLE_RECORDS;
+        let offset = 16 + index as usize * 16;
+        let mut data = vec![0; offset + 16];
+        data[..16].copy_from_slice(&seed);
+        data[offset..offset + 12].copy_from_slice(&[4; 12]);
+        fs::write(cache.path(), data).unwrap();
+
+        assert!(matches!(
+            cache.store(&create_entry(4), Duration::minutes(5)),
+            Err(Error::ReplayError(_))
+        ));
+        let _ = fs::remove_file(cache.path());
+    }
+
+    #[test]
+    fn accept_last_table_that_fits_in_max_size() {
+        let mut nrecords = 1024;
+        let mut offset = MAX_SIZE - 3 * nrecords * RECORD_LEN;
+        next_table(&mut offset, &mut nrecords).unwrap();
+
+        assert_eq!(2048, nrecords);
+        assert_eq!(MAX_SIZE, offset + nrecords * RECORD_LEN);
+    }
+
+    #[test]
+    fn error_with_table_beyond_max_size() {
+        let mut nrecords = 1024;
+        let mut offset = MAX_SIZE - 3 * nrecords * RECORD_LEN + 1;
+        assert!(next_table(&mut offset, &mut nrecords).is_err());
+    }
+}
@@ -0,0 +1,20 @@
+//! Service side of the Kerberos authentication, that verifies the AP-REQ
+//! messages sent by the clients with the keys of a keytab.
+
+mod ap_req_acceptor;
+pub use ap_req_acceptor::*;
+
+mod accepted_ap_req;
+pub use accepted_ap_req::*;
+
+mod replay_cache;
+pub use replay_cache::*;
+
+mod file_replay_cache;
+pub use file_replay_cache::*;
+
+mod authorization_data;
+pub use authorization_data::*;
+
+mod cammac;
+pub use cammac::*;
@@ -0,0 +1,207 @@
+use crate::{Error, Result};
+use chrono::{DateTime, Duration, Utc};
+use himmelblau_kerberos_asn1::{Authenticator, EncryptedData};
+use himmelblau_kerberos_constants::etypes::RC4_HMAC;
+use himmelblau_kerberos_crypto::AES_MAC_SIZE;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Size of the HMAC-MD5 checksum of the RC4 encryption.
+const RC4_MAC_SIZE: usize = 16;
+
+/// Authenticator seen by a service, stored in a [`ReplayCache`] to reject
+/// the AP-REQ messages that reuse it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ReplayEntry {
+    client: String,
+    server: String,
+    ctime: DateTime<Utc>,
+    cusec: i32,
+    tag: Vec<u8>,
+}
+
+impl ReplayEntry {
+    pub fn new(
+        client: String,
+        server: String,
+        ctime: DateTime<Utc>,
+        cusec: i32,
+        tag: Vec<u8>,
+    ) -> Self {
+        return Self {
+            client,
+            server,
+            ctime,
+            cusec,
+            tag,
+        };
+    }
+
+    /// Creates the entry of a decrypted authenticator, received for
+    /// `server` in the `encrypted` form.
+    pub(crate) fn from_authenticator(
+        authenticator: &Authenticator,
+        encrypted: &EncryptedData,
+        server: String,
+    ) -> Self {
+        return Self::new(
+            format!("{}@{}", authenticator.cname, authenticator.crealm),
+            server,
+            **authenticator.ctime,
+            authenticator.cusec,
+            authenticator_tag(encrypted),
+        );
+    }
+
+    /// Client of the authenticator, as `name@REALM`.
+    pub fn client(&self) -> &str {
+        return &self.client;
+    }
+
+    /// Service that received the authenticator, as `name@REALM`.
+    pub fn server(&self) -> &str {
+        return &self.server;
+    }
+
+    pub fn ctime(&self) -> &DateTime<Utc> {
+        return &self.ctime;
+    }
+
+    pub fn cusec(&self) -> i32 {
+        return self.cusec;
+    }
+
+    /// Checksum of the encrypted authenticator, that identifies it as
+    /// the tags of the replay caches of MIT do.
+    pub fn tag(&self) -> &[u8] {
+        return &self.tag;
+    }
+}
+
+/// Checksum included at the end of the ciphertext of the authenticator,
+/// which is the tag used by MIT.
+fn authenticator_tag(encrypted: &EncryptedData) -> Vec<u8> {
+    let mac_size = match encrypted.etype {
+        RC4_HMAC => RC4_MAC_SIZE,
+        _ => AES_MAC_SIZE,
+    };
+    let cipher = &encrypted.cipher;
+    return cipher[cipher.len().saturating_sub(mac_size)..].to_vec();
+}
+
+/// Store of the authenticators accepted by a service, in order to detect
+/// replayed AP-REQ messages, as described in RFC4120, section 3.2.3.
+///
+/// Entries only need to be kept for the `clock_skew` window, since older
+/// authenticators are rejected by their time.
+pub trait ReplayCache: Send + Sync {
+    /// Stores an entry.
+    ///
+    /// # Errors
+    /// Returns [`Error::ReplayError`] if the entry was already stored and
+    /// has not expired.
+    fn store(&self, entry: &ReplayEntry, clock_skew: Duration) -> Result<()>;
+}
+
+/// Replay cache kept in memory, only valid for a single process.
+#[derive(Debug, Default)]
+pub struct MemoryReplayCache {
+    entries: Mutex<HashMap<ReplayEntry, DateTime<Utc>>>,
+}
+
+impl MemoryReplayCache {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// Number of entries stored, including the expired ones that were not
+    /// removed yet.
+    pub fn len(&self) -> usize {
+        return self.lock().len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.len() == 0;
+    }
+
+    fn lock(
+        &self,
+    ) -> std::sync::MutexGuard<'_, HashMap<ReplayEntry, DateTime<Utc>>> {
+        return self
+            .entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+    }
+}
+
+impl ReplayCache for MemoryReplayCache {
+    fn store(&self, entry: &ReplayEntry, clock_skew: Duration) -> Result<()> {
+        let now = Utc::now();
+        let mut entries = self.lock();
+        entries.retain(|_, stored| *stored + clock_skew >= now);
+
+        if entries.contains_key(entry) {
+            return Err(Error::ReplayError(format!(
+                "authenticator of {} for {}",
+                entry.client, entry.server
+            )));
+        }
+
+        entries.insert(entry.clone(), now);
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use himmelblau_kerberos_constants::etypes::AES256_CTS_HMAC_SHA1_96;
+
+    fn create_entry(tag: u8) -> ReplayEntry {
+        return ReplayEntry::new(
+            "mickey@KINGDOM.HEARTS".to_string(),
+            "http/castle.kingdom.hearts@KINGDOM.HEARTS".to_string(),
+            Utc::now(),
+            1234,
+            vec![tag; 12],
+        );
+    }
+
+    #[test]
+    fn detect_replay_in_memory() {
+        let cache = MemoryReplayCache::new();
+        let clock_skew = Duration::minutes(5);
+
+        let entry = create_entry(1);
+        cache.store(&entry, clock_skew).unwrap();
+        cache.store(&create_entry(2), clock_skew).unwrap();
+
+        assert!(matches!(
+            cache.store(&entry, clock_skew),
+            Err(Error::ReplayError(_))
+        ));
+        assert_eq!(2, cache.len());
+    }
+
+    #[test]
+    fn remove_expired_entries_from_memory() {
+        let cache = MemoryReplayCache::new();
+        let clock_skew = Duration::seconds(-1);
+
+        let entry = create_entry(1);
+        cache.store(&entry, clock_skew).unwrap();
+        cache.store(&entry, clock_skew).unwrap();
+
+        assert_eq!(1, cache.len());
+    }
+
+    #[test]
+    fn tag_is_checksum_of_authenticator() {
+        let mut cipher = vec![0; 40];
+        cipher.extend_from_slice(&[9; 12]);
+        let encrypted =
+            EncryptedData::new(AES256_CTS_HMAC_SHA1_96, None, cipher);
+
+        assert_eq!(vec![9; 12], authenticator_tag(&encrypted));
+    }
+}
@@ -0,0 +1,389 @@
+use super::ccache_name::default_ccache_path;
+use super::credential_cache::CredentialCache;
+use super::kerberos_client::*;
+use super::keytab_name::default_keytab_path;
+use crate::config::Krb5Config;
+use crate::credentials::Credential;
+use crate::messages::{is_tgs, tgt_realm};
+use crate::requesters::{AsyncTgsRequester, AsyncTgtRequester};
+use crate::transporter::{AsyncTransporter, Socks5Proxy};
+use crate::{Error, Result};
+use ascii::AsciiString;
+use himmelblau_kerberos_asn1::{PrincipalName, Realm};
+use himmelblau_kerberos_crypto::Key;
+use himmelblau_kerberos_keytab::Keytab;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Async version of [`KerberosClient`], which sends the requests through
+/// [`AsyncTransporter`]s. Available with the `async` feature.
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+/// use himmelblau_kerberos_asn1::PrincipalName;
+/// use himmelblau_kerberos_constants::principal_names::NT_SRV_HST;
+/// use ascii::AsciiString;
+/// use himmelblau_kerberos_crypto::Key;
+///
+/// async fn request() {
+///     let client = AsyncKerberosClient::from_env().unwrap();
+///
+///     let username = AsciiString::from_ascii("Bob").unwrap();
+///     client
+///         .request_tgt(&username, &Key::Secret("S3cr3t".to_string()))
+///         .await
+///         .unwrap();
+///
+///     let mut service = PrincipalName::new(NT_SRV_HST, "HTTP".to_string());
+///     service.push("www.contoso.com".to_string());
+///     let credential = client.get_credential(&service, None).await.unwrap();
+/// }
+/// ```
+pub struct AsyncKerberosClient {
+    config: Krb5Config,
+    realm: Option<AsciiString>,
+    ccache_path: PathBuf,
+    keytab_path: PathBuf,
+    transporters: HashMap<String, Arc<dyn AsyncTransporter>>,
+    socks5_proxy: Option<Socks5Proxy>,
+}
+
+impl AsyncKerberosClient {
+    /// Creates a client that uses the default realm, the default
+    /// credential cache and the default keytab of the configuration.
+    ///
+    /// # Errors
+    /// Returns error if the default credential cache or keytab is not
+    /// supported.
+    pub fn new(config: Krb5Config) -> Result<Self> {
+        let ccache_path = default_ccache_path(&config)?;
+        let keytab_path = default_keytab_path(&config)?;
+        return Ok(Self {
+            realm: config.default_realm(),
+            config,
+            ccache_path,
+            keytab_path,
+            transporters: HashMap::new(),
+            socks5_proxy: None,
+        });
+    }
+
+    /// Creates a client with the configuration of `KRB5_CONFIG` or
+    /// `/etc/krb5.conf`.
+    pub fn from_env() -> Result<Self> {
+        return Self::new(Krb5Config::from_env()?);
+    }
+
+    /// Realm of the user to request the TGT.
+    pub fn realm(&self) -> Option<&AsciiString> {
+        return self.realm.as_ref();
+    }
+
+    pub fn set_realm(&mut self, realm: AsciiString) {
+        self.realm = Some(realm);
+    }
+
+    pub fn ccache_path(&self) -> &Path {
+        return &self.ccache_path;
+    }
+
+    /// Uses a credential cache other than the default one.
+    pub fn set_ccache_path<P: Into<PathBuf>>(&mut self, path: P) {
+        self.ccache_path = path.into();
+    }
+
+    pub fn keytab_path(&self) -> &Path {
+        return &self.keytab_path;
+    }
+
+    /// Uses a keytab other than the default one.
+    pub fn set_keytab_path<P: Into<PathBuf>>(&mut self, path: P) {
+        self.keytab_path = path.into();
+    }
+
+    /// Sends the messages for the KDC of `realm` through a custom
+    /// transporter, instead of the KDC of the configuration.
+    pub fn set_transporter(
+        &mut self,
+        realm: &str,
+        transporter: Box<dyn AsyncTransporter>,
+    ) {
+        self.transporters
+            .insert(realm.to_string(), Arc::from(transporter));
+    }
+
+    /// Reaches the KDCs of the configuration through a SOCKS5 proxy, or
+    /// directly if `None`. The custom transporters are not affected.
+    pub fn set_socks5_proxy(&mut self, proxy: Option<Socks5Proxy>) {
+        self.socks5_proxy = proxy;
+    }
+
+    /// Loads the credential cache of the client.
+    pub fn credential_cache(&self) -> Result<CredentialCache> {
+        return CredentialCache::load(&self.ccache_path);
+    }
+
+    /// Requests a TGT for the user and stores it in a new credential
+    /// cache. See [`KerberosClient::request_tgt`].
+    pub async fn request_tgt(
+        &self,
+        username: &AsciiString,
+        user_key: &Key,
+    ) -> Result<Credential> {
+        let tgt = self
+            .tgt_requester()?
+            .request(username, Some(user_key))
+            .await?;
+        store_tgt(&self.ccache_path, &tgt)?;
+        return Ok(tgt);
+    }
+
+    /// Requests a TGT with the keys of `username` stored in `keytab` and
+    /// stores it in a new credential cache. See
+    /// [`KerberosClient::request_tgt_with_keytab`].
+    pub async fn request_tgt_with_keytab(
+        &self,
+        username: &AsciiString,
+        keytab: &Keytab,
+    ) -> Result<Credential> {
+        let tgt = self
+            .tgt_requester()?
+            .request_with_keytab(username, keytab)
+            .await?;
+        store_tgt(&self.ccache_path, &tgt)?;
+        return Ok(tgt);
+    }
+
+    fn tgt_requester(&self) -> Result<AsyncTgtRequester> {
+        let realm = self.realm.as_ref().ok_or_else(|| {
+            Error::ConfigError("No default realm specified".to_string())
+        })?;
+
+        match self.transporters.get(realm.as_str()) {
+            Some(transporter) => {
+                return Ok(AsyncTgtRequester::with_transporter(
+                    realm.clone(),
+                    Box::new(transporter.clone()),
+                ));
+            }
+            None => {
+                let mut tgt_requester =
+                    AsyncTgtRequester::from_config(&self.config, Some(realm))?;
+                tgt_requester.set_socks5_proxy(self.socks5_proxy.clone());
+                return Ok(tgt_requester);
+            }
+        }
+    }
+
+    /// Retrieves a ticket for `service` from the credential cache or from
+    /// the KDCs. See [`KerberosClient::get_credential`].
+    pub async fn get_credential(
+        &self,
+        service: &PrincipalName,
+        realm: Option<&str>,
+    ) -> Result<Credential> {
+        let mut ccache = self.credential_cache()?;
+        let (client_realm, _) = ccache.client()?;
+        let service_realm = match realm {
+            Some(realm) => realm.to_string(),
+            None => service_realm(&self.config, service, &client_realm),
+        };
+
+        if let Some(credential) = ccache.find_valid(&service_realm, service) {
+            return Ok(credential);
+        }
+
+        let mut tgt = self
+            .get_tgt(&mut ccache, &client_realm, &service_realm)
+            .await?;
+        let mut visited_realms = vec![tgt_realm(&tgt)?];
+
+        for _ in 0..MAX_REFERRAL_HOPS {
+            let credential = self
+                .tgs_requester(&tgt_realm(&tgt)?)?
+                .request(&tgt, service)
+                .await?;
+
+            if is_tgs(service) || !is_tgs(credential.sname()) {
+                store_credential(&mut ccache, &credential, &service_realm);
+                ccache.save()?;
+                return Ok(credential);
+            }
+
+            let referral_realm = tgt_realm(&credential)?;
+            if visited_realms.contains(&referral_realm) {
+                return Err(Error::InvalidKdcReply(format!(
+                    "referral loop to realm {}",
+                    referral_realm
+                )));
+            }
+            visited_realms.push(referral_realm);
+            ccache.store(credential.clone());
+            tgt = credential;
+        }
+
+        return Err(too_many_hops(service));
+    }
+
+    /// Retrieves a TGT to request tickets to the KDC of `service_realm`,
+    /// following the realms of the authentication path.
+    async fn get_tgt(
+        &self,
+        ccache: &mut CredentialCache,
+        client_realm: &Realm,
+        service_realm: &Realm,
+    ) -> Result<Credential> {
+        if let Some(tgt) =
+            ccache.find_valid(client_realm, &krbtgt_name(service_realm))
+        {
+            return Ok(tgt);
+        }
+
+        let mut tgt = ccache
+            .find_valid(client_realm, &krbtgt_name(client_realm))
+            .ok_or_else(|| {
+                Error::NotAvailableData(format!(
+                    "TGT of realm {} in credential cache",
+                    client_realm
+                ))
+            })?;
+
+        let path = realm_path(&self.config, client_realm, service_realm);
+        let mut current_realm = client_realm.clone();
+
+        for _ in 0..MAX_REFERRAL_HOPS {
+            if &current_realm == service_realm {
+                ccache.save()?;
+                return Ok(tgt);
+            }
+
+            let next_realm = next_realm_in_path(&path, &current_realm);
+            let next_tgt_name = krbtgt_name(&next_realm);
+
+            tgt = match ccache.find_valid(&current_realm, &next_tgt_name) {
+                Some(cached_tgt) => cached_tgt,
+                None => {
+                    let new_tgt = self
+                        .tgs_requester(&current_realm)?
+                        .request(&tgt, &next_tgt_name)
+                        .await?;
+                    ccache.store(new_tgt.clone());
+                    new_tgt
+                }
+            };
+            current_realm = tgt_realm(&tgt)?;
+        }
+
+        return Err(too_many_hops(&krbtgt_name(service_realm)));
+    }
+
+    fn tgs_requester(&self, realm: &str) -> Result<AsyncTgsRequester> {
+        if let Some(transporter) = self.transporters.get(realm) {
+            return Ok(AsyncTgsRequester::with_transporter(Box::new(
+                transporter.clone(),
+            )));
+        }
+        let realm = AsciiString::from_ascii(realm)?;
+        let mut tgs_requester =
+            AsyncTgsRequester::from_config(&self.config, &realm)?;
+        tgs_requester.set_socks5_proxy(self.socks5_proxy.clone());
+        return Ok(tgs_requester);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::requesters::test_kdc::{
+        keytab_entry, test_tgt, TestAsKdc, TestKdc,
+    };
+    use himmelblau_kerberos_constants::principal_names::{
+        NT_PRINCIPAL, NT_SRV_HST,
+    };
+    use std::env;
+    use std::fs;
+    use std::sync::atomic::Ordering;
+
+    fn ccache_path(name: &str) -> PathBuf {
+        return env::temp_dir().join(format!(
+            "kerbeiros_async_{}_{}.ccache",
+            name,
+            std::process::id()
+        ));
+    }
+
+    fn create_client(ccache_path: &Path) -> AsyncKerberosClient {
+        let config = Krb5Config::parse(
+            "[libdefaults]\n  default_realm = KINGDOM.HEARTS\n",
+        )
+        .unwrap();
+        let mut client = AsyncKerberosClient::new(config).unwrap();
+        client.set_ccache_path(ccache_path);
+        return client;
+    }
+
+    #[tokio::test]
+    async fn get_credential_and_store_it_in_ccache() {
+        let path = ccache_path("store");
+        let mut ccache = CredentialCache::new(
+            &path,
+            &"KINGDOM.HEARTS".to_string(),
+            &PrincipalName::new(NT_PRINCIPAL, "mickey".to_string()),
+        );
+        ccache.store(test_tgt());
+        ccache.save().unwrap();
+
+        let mut service = PrincipalName::new(NT_SRV_HST, "http".to_string());
+        service.push("castle.kingdom.hearts".to_string());
+        let kdc = TestKdc::new("KINGDOM.HEARTS", vec![service.clone()], None);
+        let requests = kdc.requests();
+        let mut client = create_client(&path);
+        client.set_transporter("KINGDOM.HEARTS", Box::new(kdc));
+
+        let credential = client
+            .get_credential(&service, Some("KINGDOM.HEARTS"))
+            .await
+            .unwrap();
+        let cached = client
+            .get_credential(&service, Some("KINGDOM.HEARTS"))
+            .await
+            .unwrap();
+
+        assert_eq!(&service, credential.sname());
+        assert_eq!(credential.key(), cached.key());
+        assert_eq!(1, requests.load(Ordering::SeqCst));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn request_tgt_with_keytab_and_store_it() {
+        let path = ccache_path("keytab");
+        let key = Key::AES256Key([1; 32]);
+        let kdc =
+            TestAsKdc::new("KINGDOM.HEARTS", vec![(2, key.clone())], true);
+        let keytab = Keytab::new(
+            5,
+            2,
+            vec![keytab_entry("mickey@KINGDOM.HEARTS", 2, &key)],
+        );
+        let mut client = create_client(&path);
+        client.set_transporter("KINGDOM.HEARTS", Box::new(kdc));
+
+        let tgt = client
+            .request_tgt_with_keytab(
+                &AsciiString::from_ascii("mickey").unwrap(),
+                &keytab,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!("krbtgt/KINGDOM.HEARTS", tgt.sname().to_string());
+        let credentials = client.credential_cache().unwrap().credentials();
+        assert_eq!(1, credentials.len());
+        assert_eq!(tgt.key(), credentials[0].key());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,121 @@
+use crate::config::Krb5Config;
+use crate::{Error, Result};
+use std::env;
+use std::path::PathBuf;
+
+/// Environment variable with the name of the default credential cache.
+pub const KRB5CCNAME_ENV: &str = "KRB5CCNAME";
+
+/// Credential cache used when neither `KRB5CCNAME` nor
+/// `default_ccache_name` are defined, as in MIT.
+pub const DEFAULT_CCACHE_NAME: &str = "FILE:/tmp/krb5cc_%{uid}";
+
+/// Retrieves the path of the default credential cache, from the
+/// `KRB5CCNAME` environment variable, the `default_ccache_name` relation
+/// of the configuration, or `/tmp/krb5cc_<uid>`.
+///
+/// # Errors
+/// Returns [`Error::ConfigError`] if the cache is not a file, since other
+/// cache types, such as `KEYRING` or `KCM`, are not supported.
+pub fn default_ccache_path(config: &Krb5Config) -> Result<PathBuf> {
+    let name = match env::var(KRB5CCNAME_ENV) {
+        Ok(name) if !name.is_empty() => name,
+        _ => config
+            .default_ccache_name()
+            .unwrap_or(DEFAULT_CCACHE_NAME)
+            .to_string(),
+    };
+    return ccache_path_from_name(&name);
+}
+
+/// Retrieves the path of a credential cache from its name, in the
+/// `[TYPE:]residual` format. The `%{uid}`, `%{euid}` and `%{TEMP}` tokens
+/// of the name are expanded.
+pub fn ccache_path_from_name(name: &str) -> Result<PathBuf> {
+    let path = match name.split_once(':') {
+        Some(("FILE", path)) => path,
+        Some((cache_type, _)) if !cache_type.contains('/') => {
+            return Err(Error::ConfigError(format!(
+                "Unsupported credential cache type: {}",
+                cache_type
+            )));
+        }
+        _ => name,
+    };
+
+    return expand_path_tokens(path);
+}
+
+/// Expands the `%{uid}`, `%{euid}` and `%{TEMP}` tokens of a path.
+pub(crate) fn expand_path_tokens(path: &str) -> Result<PathBuf> {
+    let mut path = path.replace("%{TEMP}", "/tmp");
+    if path.contains("%{uid}") {
+        path = path.replace("%{uid}", &current_uid()?.to_string());
+    }
+    if path.contains("%{euid}") {
+        path = path.replace("%{euid}", &current_euid()?.to_string());
+    }
+    return Ok(PathBuf::from(path));
+}
+
+/// Real user id of the process.
+#[cfg(unix)]
+pub(crate) fn current_uid() -> Result<u32> {
+    return Ok(unsafe { libc::getuid() });
+}
+
+/// Effective user id of the process.
+#[cfg(unix)]
+pub(crate) fn current_euid() -> Result<u32> {
+    return Ok(unsafe { libc::geteuid() });
+}
+
+#[cfg(not(unix))]
+pub(crate) fn current_uid() -> Result<u32> {
+    return Err(Error::ConfigError(
+        "User id is not available in this platform".to_string(),
+    ));
+}
+
+#[cfg(not(unix))]
+pub(crate) fn current_euid() -> Result<u32> {
+    return current_uid();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ccache_path_from_file_names() {
+        assert_eq!(
+            PathBuf::from("/tmp/krb5cc_mickey"),
+            ccache_path_from_name("FILE:/tmp/krb5cc_mickey").unwrap()
+        );
+        assert_eq!(
+            PathBuf::from("/tmp/krb5cc_mickey"),
+            ccache_path_from_name("/tmp/krb5cc_mickey").unwrap()
+        );
+        assert_eq!(
+            PathBuf::from("/tmp/krb5cc_mickey"),
+            ccache_path_from_name("FILE:%{TEMP}/krb5cc_mickey").unwrap()
+        );
+    }
+
+    #[test]
+    fn expand_uid_in_ccache_name() {
+        let path = ccache_path_from_name(DEFAULT_CCACHE_NAME).unwrap();
+        let uid = current_uid().unwrap();
+        assert_eq!(PathBuf::from(format!("/tmp/krb5cc_{}", uid)), path);
+    }
+
+    #[test]
+    fn error_with_unsupported_ccache_type() {
+        assert_eq!(
+            Err(Error::ConfigError(
+                "Unsupported credential cache type: KEYRING".to_string()
+            )),
+            ccache_path_from_name("KEYRING:persistent:1000")
+        );
+    }
+}
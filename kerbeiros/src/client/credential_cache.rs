@@ -0,0 +1,188 @@
+use crate::credentials::Credential;
+use crate::mappers::PrincipalMapper;
+use crate::messages::same_principal_name;
+use crate::{Error, Result};
+use chrono::Utc;
+use himmelblau_kerberos_asn1::{PrincipalName, Realm};
+use himmelblau_kerberos_ccache::{
+    CCache, Credential as CredentialEntry, Header,
+};
+use himmelblau_kerberos_constants::ticket_flags;
+use std::convert::TryFrom;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Credential cache stored in a file, in the ccache format used by MIT.
+///
+/// The entries that cannot be decoded as credentials, such as the
+/// configuration entries written by MIT, are kept untouched when the
+/// cache is saved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CredentialCache {
+    path: PathBuf,
+    ccache: CCache,
+}
+
+impl CredentialCache {
+    /// Creates an empty cache for the given client. It is not written
+    /// until [`save`](Self::save) is called.
+    pub fn new<P: Into<PathBuf>>(
+        path: P,
+        realm: &Realm,
+        client: &PrincipalName,
+    ) -> Self {
+        let primary_principal =
+            PrincipalMapper::realm_and_principal_name_to_principal(
+                realm, client,
+            );
+        return Self {
+            path: path.into(),
+            ccache: CCache::new(Header::default(), primary_principal, vec![]),
+        };
+    }
+
+    /// Loads the cache from a file.
+    ///
+    /// # Errors
+    /// Returns [`Error::IOError`] if the file cannot be read, and
+    /// [`Error::BinaryParseError`] if it is not a valid ccache.
+    pub fn load<P: Into<PathBuf>>(path: P) -> Result<Self> {
+        let path = path.into();
+        let data =
+            fs::read(&path).map_err(|error| Error::IOError(error.into()))?;
+        let (_, ccache) = CCache::parse(&data)?;
+        return Ok(Self { path, ccache });
+    }
+
+    pub fn path(&self) -> &Path {
+        return &self.path;
+    }
+
+    /// Realm and name of the client that owns the credentials.
+    pub fn client(&self) -> Result<(Realm, PrincipalName)> {
+        return PrincipalMapper::principal_to_realm_and_principal_name(
+            self.ccache.primary_principal.clone(),
+        );
+    }
+
+    /// Credentials of the cache, excluding the entries that cannot be
+    /// decoded.
+    pub fn credentials(&self) -> Vec<Credential> {
+        return self
+            .ccache
+            .credentials
+            .iter()
+            .filter_map(|entry| Credential::try_from(entry.clone()).ok())
+            .collect();
+    }
+
+    /// Retrieves a credential of the client for the service which is
+    /// currently valid. As in MIT, the name types of the principals are
+    /// not compared.
+    pub fn find_valid(
+        &self,
+        srealm: &str,
+        sname: &PrincipalName,
+    ) -> Option<Credential> {
+        let (crealm, cname) = self.client().ok()?;
+        let now = Utc::now();
+
+        return self.credentials().into_iter().find(|credential| {
+            let starttime = credential
+                .starttime()
+                .unwrap_or_else(|| credential.authtime());
+            return credential.crealm() == &crealm
+                && same_principal_name(credential.cname(), &cname)
+                && credential.srealm() == srealm
+                && same_principal_name(credential.sname(), sname)
+                && credential.flags().flags & ticket_flags::INVALID == 0
+                && ***starttime <= now
+                && ***credential.endtime() > now;
+        });
+    }
+
+    /// Adds a credential to the cache, replacing the previous ones for
+    /// the same client and service.
+    pub fn store(&mut self, credential: Credential) {
+        let entry = CredentialEntry::from(credential);
+        self.ccache.credentials.retain(|cached| {
+            cached.client != entry.client || cached.server != entry.server
+        });
+        self.ccache.credentials.push(entry);
+    }
+
+    /// Writes the cache to its file, which is only readable by the user.
+    ///
+    /// The data is written to a new temporary file of the same directory,
+    /// which then replaces the cache, so the processes that read it never
+    /// see a partially written cache.
+    pub fn save(&self) -> Result<()> {
+        let data = self.ccache.clone().build();
+        let temp_path = self.temp_path();
+
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+
+        let mut file = options
+            .open(&temp_path)
+            .map_err(|error| Error::IOError(error.into()))?;
+        let result = file
+            .write_all(&data)
+            .and_then(|_| file.sync_all())
+            .and_then(|_| fs::rename(&temp_path, &self.path));
+        if let Err(error) = result {
+            let _ = fs::remove_file(&temp_path);
+            return Err(Error::IOError(error.into()));
+        }
+        return Ok(());
+    }
+
+    fn temp_path(&self) -> PathBuf {
+        let mut file_name = self
+            .path
+            .file_name()
+            .map(|file_name| file_name.to_os_string())
+            .unwrap_or_default();
+        file_name.push(format!(".tmp{:08x}", rand::random::<u32>()));
+        return self.path.with_file_name(file_name);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use himmelblau_kerberos_constants::principal_names::NT_PRINCIPAL;
+    use std::env;
+
+    #[test]
+    fn save_replaces_cache_atomically() {
+        let dir = env::temp_dir()
+            .join(format!("kerbeiros_ccache_save_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("krb5cc");
+        fs::write(&path, b"stale").unwrap();
+
+        let ccache = CredentialCache::new(
+            &path,
+            &"KINGDOM.HEARTS".to_string(),
+            &PrincipalName::new(NT_PRINCIPAL, "mickey".to_string()),
+        );
+        ccache.save().unwrap();
+
+        assert_eq!(ccache, CredentialCache::load(&path).unwrap());
+        assert_eq!(1, fs::read_dir(&dir).unwrap().count());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(0o600, mode & 0o777);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
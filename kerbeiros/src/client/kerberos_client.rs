@@ -0,0 +1,649 @@
+use super::ccache_name::default_ccache_path;
+use super::credential_cache::CredentialCache;
+use super::keytab_name::default_keytab_path;
+use super::verify_init_creds::verify_initial_credentials;
+use crate::config::Krb5Config;
+use crate::credentials::{load_keytab, Credential};
+use crate::messages::{is_tgs, tgt_realm};
+use crate::requesters::{TgsRequester, TgtRequester};
+use crate::transporter::{Socks5Proxy, Transporter};
+use crate::{Error, Result};
+use ascii::AsciiString;
+use himmelblau_kerberos_asn1::{PrincipalName, Realm};
+use himmelblau_kerberos_constants::principal_names::{NT_SRV_HST, NT_SRV_INST};
+use himmelblau_kerberos_crypto::Key;
+use himmelblau_kerberos_keytab::Keytab;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Maximum number of realms crossed to retrieve a ticket, as in MIT.
+pub const MAX_REFERRAL_HOPS: usize = 10;
+
+/// Client that retrieves service tickets as `krb5_get_credentials` does.
+///
+/// The tickets are taken from the default credential cache if they are
+/// still valid. Otherwise, they are requested to the KDC with the TGT of
+/// the cache, and stored into it. Services of other realms are reached by
+/// following the referrals of the KDCs, or the `[capaths]` of the
+/// configuration.
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+/// use himmelblau_kerberos_asn1::PrincipalName;
+/// use himmelblau_kerberos_constants::principal_names::NT_SRV_HST;
+/// use ascii::AsciiString;
+/// use himmelblau_kerberos_crypto::Key;
+///
+/// let client = KerberosClient::from_env().unwrap();
+///
+/// // Like kinit, store a new TGT in the credential cache
+/// let username = AsciiString::from_ascii("Bob").unwrap();
+/// client
+///     .request_tgt(&username, &Key::Secret("S3cr3t".to_string()))
+///     .unwrap();
+///
+/// let mut service = PrincipalName::new(NT_SRV_HST, "HTTP".to_string());
+/// service.push("www.contoso.com".to_string());
+/// let credential = client.get_credential(&service, None).unwrap();
+/// ```
+pub struct KerberosClient {
+    config: Krb5Config,
+    realm: Option<AsciiString>,
+    ccache_path: PathBuf,
+    keytab_path: PathBuf,
+    transporters: HashMap<String, Arc<dyn Transporter>>,
+    socks5_proxy: Option<Socks5Proxy>,
+}
+
+impl KerberosClient {
+    /// Creates a client that uses the default realm, the default
+    /// credential cache and the default keytab of the configuration.
+    ///
+    /// # Errors
+    /// Returns error if the default credential cache or keytab is not
+    /// supported.
+    pub fn new(config: Krb5Config) -> Result<Self> {
+        let ccache_path = default_ccache_path(&config)?;
+        let keytab_path = default_keytab_path(&config)?;
+        return Ok(Self {
+            realm: config.default_realm(),
+            config,
+            ccache_path,
+            keytab_path,
+            transporters: HashMap::new(),
+            socks5_proxy: None,
+        });
+    }
+
+    /// Creates a client with the configuration of `KRB5_CONFIG` or
+    /// `/etc/krb5.conf`.
+    pub fn from_env() -> Result<Self> {
+        return Self::new(Krb5Config::from_env()?);
+    }
+
+    /// Realm of the user to request the TGT.
+    pub fn realm(&self) -> Option<&AsciiString> {
+        return self.realm.as_ref();
+    }
+
+    pub fn set_realm(&mut self, realm: AsciiString) {
+        self.realm = Some(realm);
+    }
+
+    pub fn ccache_path(&self) -> &Path {
+        return &self.ccache_path;
+    }
+
+    /// Uses a credential cache other than the default one.
+    pub fn set_ccache_path<P: Into<PathBuf>>(&mut self, path: P) {
+        self.ccache_path = path.into();
+    }
+
+    pub fn keytab_path(&self) -> &Path {
+        return &self.keytab_path;
+    }
+
+    /// Uses a keytab other than the default one to verify the TGTs.
+    pub fn set_keytab_path<P: Into<PathBuf>>(&mut self, path: P) {
+        self.keytab_path = path.into();
+    }
+
+    /// Sends the messages for the KDC of `realm` through a custom
+    /// transporter, instead of the KDC of the configuration.
+    pub fn set_transporter(
+        &mut self,
+        realm: &str,
+        transporter: Box<dyn Transporter>,
+    ) {
+        self.transporters
+            .insert(realm.to_string(), Arc::from(transporter));
+    }
+
+    /// Reaches the KDCs of the configuration through a SOCKS5 proxy, or
+    /// directly if `None`. The custom transporters are not affected.
+    pub fn set_socks5_proxy(&mut self, proxy: Option<Socks5Proxy>) {
+        self.socks5_proxy = proxy;
+    }
+
+    /// Loads the credential cache of the client.
+    pub fn credential_cache(&self) -> Result<CredentialCache> {
+        return CredentialCache::load(&self.ccache_path);
+    }
+
+    /// Requests a TGT for the user, as `kinit` does, and stores it in a
+    /// new credential cache, replacing the previous one.
+    pub fn request_tgt(
+        &self,
+        username: &AsciiString,
+        user_key: &Key,
+    ) -> Result<Credential> {
+        let tgt = self.tgt_requester()?.request(username, Some(user_key))?;
+        self.store_tgt(&tgt)?;
+        return Ok(tgt);
+    }
+
+    /// Requests a TGT with the keys of `username` stored in `keytab` and
+    /// saves it into a new credential cache, as `kinit -k` does. See
+    /// [`TgtRequester::request_with_keytab`].
+    pub fn request_tgt_with_keytab(
+        &self,
+        username: &AsciiString,
+        keytab: &Keytab,
+    ) -> Result<Credential> {
+        let tgt = self
+            .tgt_requester()?
+            .request_with_keytab(username, keytab)?;
+        self.store_tgt(&tgt)?;
+        return Ok(tgt);
+    }
+
+    /// Verifies that `tgt` was issued by the real KDC of its realm, by
+    /// decrypting a ticket for `server` with the keytab of the client. See
+    /// [`verify_initial_credentials`].
+    pub fn verify_initial_credentials(
+        &self,
+        tgt: &Credential,
+        server: Option<&PrincipalName>,
+    ) -> Result<Credential> {
+        let keytab = load_keytab(&self.keytab_path)?;
+        let tgs_requester = self.tgs_requester(tgt.crealm())?;
+        return verify_initial_credentials(
+            tgt,
+            &tgs_requester,
+            &keytab,
+            server,
+        );
+    }
+
+    fn tgt_requester(&self) -> Result<TgtRequester> {
+        let realm = self.realm.as_ref().ok_or_else(|| {
+            Error::ConfigError("No default realm specified".to_string())
+        })?;
+
+        match self.transporters.get(realm.as_str()) {
+            Some(transporter) => {
+                return Ok(TgtRequester::with_transporter(
+                    realm.clone(),
+                    Box::new(transporter.clone()),
+                ));
+            }
+            None => {
+                let mut tgt_requester =
+                    TgtRequester::from_config(&self.config, Some(realm))?;
+                tgt_requester.set_socks5_proxy(self.socks5_proxy.clone());
+                return Ok(tgt_requester);
+            }
+        }
+    }
+
+    fn store_tgt(&self, tgt: &Credential) -> Result<()> {
+        return store_tgt(&self.ccache_path, tgt);
+    }
+
+    /// Retrieves a ticket for `service` from the credential cache or, if
+    /// there is no valid one, from the KDC by using the TGT of the cache.
+    ///
+    /// If `realm` is not specified, the realm of host based services is
+    /// taken from the `[domain_realm]` section of the configuration, and
+    /// the realm of the client is used for the rest.
+    ///
+    /// # Errors
+    /// Returns error if there is no credential cache, it does not include
+    /// a TGT, or the KDCs do not issue the ticket.
+    pub fn get_credential(
+        &self,
+        service: &PrincipalName,
+        realm: Option<&str>,
+    ) -> Result<Credential> {
+        let mut ccache = self.credential_cache()?;
+        let (client_realm, _) = ccache.client()?;
+        let service_realm = match realm {
+            Some(realm) => realm.to_string(),
+            None => service_realm(&self.config, service, &client_realm),
+        };
+
+        if let Some(credential) = ccache.find_valid(&service_realm, service) {
+            return Ok(credential);
+        }
+
+        let mut tgt =
+            self.get_tgt(&mut ccache, &client_realm, &service_realm)?;
+        let mut visited_realms = vec![tgt_realm(&tgt)?];
+
+        for _ in 0..MAX_REFERRAL_HOPS {
+            let credential = self
+                .tgs_requester(&tgt_realm(&tgt)?)?
+                .request(&tgt, service)?;
+
+            if is_tgs(service) || !is_tgs(credential.sname()) {
+                store_credential(&mut ccache, &credential, &service_realm);
+                ccache.save()?;
+                return Ok(credential);
+            }
+
+            let referral_realm = tgt_realm(&credential)?;
+            if visited_realms.contains(&referral_realm) {
+                return Err(Error::InvalidKdcReply(format!(
+                    "referral loop to realm {}",
+                    referral_realm
+                )));
+            }
+            visited_realms.push(referral_realm);
+            ccache.store(credential.clone());
+            tgt = credential;
+        }
+
+        return Err(too_many_hops(service));
+    }
+
+    /// Retrieves a TGT to request tickets to the KDC of `service_realm`.
+    /// If it is not in the cache, the cross-realm TGTs are requested
+    /// through the realms of the authentication path.
+    fn get_tgt(
+        &self,
+        ccache: &mut CredentialCache,
+        client_realm: &Realm,
+        service_realm: &Realm,
+    ) -> Result<Credential> {
+        if let Some(tgt) =
+            ccache.find_valid(client_realm, &krbtgt_name(service_realm))
+        {
+            return Ok(tgt);
+        }
+
+        let mut tgt = ccache
+            .find_valid(client_realm, &krbtgt_name(client_realm))
+            .ok_or_else(|| {
+                Error::NotAvailableData(format!(
+                    "TGT of realm {} in credential cache",
+                    client_realm
+                ))
+            })?;
+
+        let path = realm_path(&self.config, client_realm, service_realm);
+        let mut current_realm = client_realm.clone();
+
+        for _ in 0..MAX_REFERRAL_HOPS {
+            if &current_realm == service_realm {
+                ccache.save()?;
+                return Ok(tgt);
+            }
+
+            let next_realm = next_realm_in_path(&path, &current_realm);
+            let next_tgt_name = krbtgt_name(&next_realm);
+
+            tgt = match ccache.find_valid(&current_realm, &next_tgt_name) {
+                Some(cached_tgt) => cached_tgt,
+                None => {
+                    let new_tgt = self
+                        .tgs_requester(&current_realm)?
+                        .request(&tgt, &next_tgt_name)?;
+                    ccache.store(new_tgt.clone());
+                    new_tgt
+                }
+            };
+            current_realm = tgt_realm(&tgt)?;
+        }
+
+        return Err(too_many_hops(&krbtgt_name(service_realm)));
+    }
+
+    fn tgs_requester(&self, realm: &str) -> Result<TgsRequester> {
+        if let Some(transporter) = self.transporters.get(realm) {
+            return Ok(TgsRequester::with_transporter(Box::new(
+                transporter.clone(),
+            )));
+        }
+        let realm = AsciiString::from_ascii(realm)?;
+        let mut tgs_requester =
+            TgsRequester::from_config(&self.config, &realm)?;
+        tgs_requester.set_socks5_proxy(self.socks5_proxy.clone());
+        return Ok(tgs_requester);
+    }
+}
+
+/// Stores a service ticket in the cache. The tickets obtained through
+/// referrals are also stored under the realm requested by the client, so
+/// they are found for later requests, as MIT does.
+pub(super) fn store_credential(
+    ccache: &mut CredentialCache,
+    credential: &Credential,
+    service_realm: &Realm,
+) {
+    ccache.store(credential.clone());
+    if credential.srealm() != service_realm {
+        ccache.store(credential.with_srealm(service_realm.clone()));
+    }
+}
+
+/// Replaces the credential cache with a new one that only contains `tgt`.
+pub(super) fn store_tgt(ccache_path: &Path, tgt: &Credential) -> Result<()> {
+    let mut ccache =
+        CredentialCache::new(ccache_path, tgt.crealm(), tgt.cname());
+    ccache.store(tgt.clone());
+    return ccache.save();
+}
+
+/// Guesses the realm of a service when it is not specified.
+pub(super) fn service_realm(
+    config: &Krb5Config,
+    service: &PrincipalName,
+    client_realm: &Realm,
+) -> Realm {
+    let is_host_based =
+        service.name_type == NT_SRV_HST || service.name_type == NT_SRV_INST;
+    if is_host_based && service.name_string.len() == 2 && !is_tgs(service) {
+        if let Some(realm) = config.realm_of_host(&service.name_string[1]) {
+            return realm;
+        }
+    }
+    return client_realm.clone();
+}
+
+/// Realms crossed from `client_realm` to `service_realm`, as configured
+/// in `[capaths]`.
+pub(super) fn realm_path(
+    config: &Krb5Config,
+    client_realm: &Realm,
+    service_realm: &Realm,
+) -> Vec<String> {
+    let mut path = vec![client_realm.clone()];
+    path.append(&mut config.capath(client_realm, service_realm));
+    path.push(service_realm.clone());
+    return path;
+}
+
+/// Returns the realm that follows `realm` in the path, or the last one
+/// if it is not in the path.
+pub(super) fn next_realm_in_path(path: &[String], realm: &str) -> String {
+    return match path.iter().position(|hop| hop == realm) {
+        Some(index) if index + 1 < path.len() => path[index + 1].clone(),
+        _ => path[path.len() - 1].clone(),
+    };
+}
+
+pub(super) fn krbtgt_name(realm: &str) -> PrincipalName {
+    return PrincipalName {
+        name_type: NT_SRV_INST,
+        name_string: vec!["krbtgt".to_string(), realm.to_string()],
+    };
+}
+
+pub(super) fn too_many_hops(service: &PrincipalName) -> Error {
+    return Error::InvalidKdcReply(format!(
+        "too many referrals requesting {}",
+        service
+    ));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::requesters::test_kdc::{test_tgt, TestAsKdc, TestKdc};
+    use himmelblau_kerberos_constants::principal_names::NT_PRINCIPAL;
+    use himmelblau_kerberos_keytab::{
+        CountedOctetString, KeyBlock, KeytabEntry,
+    };
+    use std::env;
+    use std::fs;
+    use std::sync::atomic::Ordering;
+
+    struct TestCCache {
+        path: PathBuf,
+    }
+
+    impl TestCCache {
+        fn new(name: &str, credentials: Vec<Credential>) -> Self {
+            let path = env::temp_dir().join(format!(
+                "kerbeiros_{}_{}.ccache",
+                name,
+                std::process::id()
+            ));
+            let mut ccache = CredentialCache::new(
+                &path,
+                &"KINGDOM.HEARTS".to_string(),
+                &PrincipalName::new(NT_PRINCIPAL, "mickey".to_string()),
+            );
+            for credential in credentials.into_iter() {
+                ccache.store(credential);
+            }
+            ccache.save().unwrap();
+            return Self { path };
+        }
+    }
+
+    impl Drop for TestCCache {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+
+    fn service_name(host: &str) -> PrincipalName {
+        let mut service = PrincipalName::new(NT_SRV_HST, "http".to_string());
+        service.push(host.to_string());
+        return service;
+    }
+
+    fn create_client(ccache: &TestCCache, config: &str) -> KerberosClient {
+        let mut client =
+            KerberosClient::new(Krb5Config::parse(config).unwrap()).unwrap();
+        client.set_ccache_path(&ccache.path);
+        return client;
+    }
+
+    #[test]
+    fn get_credential_and_store_it_in_ccache() {
+        let ccache = TestCCache::new("store", vec![test_tgt()]);
+        let service = service_name("castle.kingdom.hearts");
+        let kdc = TestKdc::new("KINGDOM.HEARTS", vec![service.clone()], None);
+        let requests = kdc.requests();
+
+        let mut client = create_client(&ccache, "");
+        client.set_transporter("KINGDOM.HEARTS", Box::new(kdc));
+
+        let credential = client.get_credential(&service, None).unwrap();
+        assert_eq!(&service, credential.sname());
+
+        let cached_credential = client.get_credential(&service, None).unwrap();
+        assert_eq!(credential.key(), cached_credential.key());
+        assert_eq!(credential.ticket(), cached_credential.ticket());
+        assert_eq!(1, requests.load(Ordering::SeqCst));
+
+        let cached = client.credential_cache().unwrap().credentials();
+        assert_eq!(2, cached.len());
+    }
+
+    #[test]
+    fn request_tgt_with_keytab_and_store_it_in_ccache() {
+        let ccache = TestCCache::new("keytab", vec![]);
+        let key = Key::AES256Key([7; 32]);
+        let keytab = Keytab::new(
+            5,
+            2,
+            vec![KeytabEntry::new(
+                CountedOctetString::new(b"KINGDOM.HEARTS".to_vec()),
+                vec![CountedOctetString::new(b"mickey".to_vec())],
+                1,
+                0,
+                1,
+                KeyBlock::new(18, key.as_bytes().to_vec()),
+                Some(1),
+            )],
+        );
+
+        let mut client = create_client(
+            &ccache,
+            "[libdefaults]\n default_realm = KINGDOM.HEARTS\n",
+        );
+        client.set_transporter(
+            "KINGDOM.HEARTS",
+            Box::new(TestAsKdc::new("KINGDOM.HEARTS", vec![(1, key)], true)),
+        );
+
+        let tgt = client
+            .request_tgt_with_keytab(
+                &AsciiString::from_ascii("mickey").unwrap(),
+                &keytab,
+            )
+            .unwrap();
+
+        let cached = client
+            .credential_cache()
+            .unwrap()
+            .find_valid("KINGDOM.HEARTS", &krbtgt_name("KINGDOM.HEARTS"))
+            .unwrap();
+        assert_eq!(tgt.ticket(), cached.ticket());
+    }
+
+    #[test]
+    fn get_credential_of_other_realm_through_referrals() {
+        let ccache = TestCCache::new("referrals", vec![test_tgt()]);
+        let service = service_name("castle.disney.world");
+        let local_kdc =
+            TestKdc::new("KINGDOM.HEARTS", vec![], Some("DISNEY.WORLD"));
+        let remote_kdc =
+            TestKdc::new("DISNEY.WORLD", vec![service.clone()], None);
+
+        let mut client = create_client(&ccache, "");
+        client.set_transporter("KINGDOM.HEARTS", Box::new(local_kdc));
+        client.set_transporter("DISNEY.WORLD", Box::new(remote_kdc));
+
+        let credential = client.get_credential(&service, None).unwrap();
+        assert_eq!(&service, credential.sname());
+        assert_eq!("DISNEY.WORLD", credential.srealm());
+        assert_eq!("KINGDOM.HEARTS", credential.crealm());
+
+        let ccache = client.credential_cache().unwrap();
+        assert!(ccache
+            .find_valid("KINGDOM.HEARTS", &krbtgt_name("DISNEY.WORLD"))
+            .is_some());
+    }
+
+    #[test]
+    fn get_credential_obtained_through_referrals_from_ccache() {
+        let ccache = TestCCache::new("cached_referral", vec![test_tgt()]);
+        let service = service_name("castle.disney.world");
+        let local_kdc =
+            TestKdc::new("KINGDOM.HEARTS", vec![], Some("DISNEY.WORLD"));
+        let remote_kdc =
+            TestKdc::new("DISNEY.WORLD", vec![service.clone()], None);
+        let requests = remote_kdc.requests();
+
+        let mut client = create_client(&ccache, "");
+        client.set_transporter("KINGDOM.HEARTS", Box::new(local_kdc));
+        client.set_transporter("DISNEY.WORLD", Box::new(remote_kdc));
+
+        let credential = client.get_credential(&service, None).unwrap();
+        let cached_credential = client.get_credential(&service, None).unwrap();
+
+        assert_eq!(credential.ticket(), cached_credential.ticket());
+        assert_eq!(1, requests.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn find_credential_with_other_name_type() {
+        let ccache = TestCCache::new("name_type", vec![test_tgt()]);
+        let client = create_client(&ccache, "");
+
+        let mut tgt_name = krbtgt_name("KINGDOM.HEARTS");
+        tgt_name.name_type = NT_PRINCIPAL;
+
+        assert!(client
+            .credential_cache()
+            .unwrap()
+            .find_valid("KINGDOM.HEARTS", &tgt_name)
+            .is_some());
+    }
+
+    #[test]
+    fn get_credential_of_other_realm_through_capath() {
+        let ccache = TestCCache::new("capath", vec![test_tgt()]);
+        let service = service_name("castle.disney.world");
+        let local_kdc =
+            TestKdc::new("KINGDOM.HEARTS", vec![krbtgt_name("TOONTOWN")], None);
+        let middle_kdc =
+            TestKdc::new("TOONTOWN", vec![krbtgt_name("DISNEY.WORLD")], None);
+        let remote_kdc =
+            TestKdc::new("DISNEY.WORLD", vec![service.clone()], None);
+
+        let mut client = create_client(
+            &ccache,
+            "
+[domain_realm]
+    .disney.world = DISNEY.WORLD
+
+[capaths]
+    KINGDOM.HEARTS = {
+        DISNEY.WORLD = TOONTOWN
+    }
+",
+        );
+        client.set_transporter("KINGDOM.HEARTS", Box::new(local_kdc));
+        client.set_transporter("TOONTOWN", Box::new(middle_kdc));
+        client.set_transporter("DISNEY.WORLD", Box::new(remote_kdc));
+
+        let credential = client.get_credential(&service, None).unwrap();
+        assert_eq!("DISNEY.WORLD", credential.srealm());
+
+        let ccache = client.credential_cache().unwrap();
+        assert!(ccache
+            .find_valid("TOONTOWN", &krbtgt_name("DISNEY.WORLD"))
+            .is_some());
+    }
+
+    #[test]
+    fn error_getting_credential_without_tgt() {
+        let ccache = TestCCache::new("no_tgt", vec![]);
+        let client = create_client(&ccache, "");
+
+        assert_eq!(
+            Err(Error::NotAvailableData(
+                "TGT of realm KINGDOM.HEARTS in credential cache".to_string()
+            )),
+            client.get_credential(&service_name("castle"), None)
+        );
+    }
+
+    #[test]
+    fn error_with_referral_loop() {
+        let ccache = TestCCache::new("loop", vec![test_tgt()]);
+        let local_kdc =
+            TestKdc::new("KINGDOM.HEARTS", vec![], Some("DISNEY.WORLD"));
+        let remote_kdc =
+            TestKdc::new("DISNEY.WORLD", vec![], Some("KINGDOM.HEARTS"));
+
+        let mut client = create_client(&ccache, "");
+        client.set_transporter("KINGDOM.HEARTS", Box::new(local_kdc));
+        client.set_transporter("DISNEY.WORLD", Box::new(remote_kdc));
+
+        assert_eq!(
+            Err(Error::InvalidKdcReply(
+                "referral loop to realm KINGDOM.HEARTS".to_string()
+            )),
+            client.get_credential(&service_name("castle"), None)
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use super::ccache_name::expand_path_tokens;
+use crate::config::Krb5Config;
+use crate::{Error, Result};
+use std::env;
+use std::path::PathBuf;
+
+/// Environment variable with the name of the default keytab.
+pub const KRB5_KTNAME_ENV: &str = "KRB5_KTNAME";
+
+/// Keytab used when neither `KRB5_KTNAME` nor `default_keytab_name` are
+/// defined, as in MIT.
+pub const DEFAULT_KEYTAB_NAME: &str = "FILE:/etc/krb5.keytab";
+
+/// Retrieves the path of the default keytab, from the `KRB5_KTNAME`
+/// environment variable, the `default_keytab_name` relation of the
+/// configuration, or `/etc/krb5.keytab`.
+///
+/// # Errors
+/// Returns [`Error::ConfigError`] if the keytab is not a file.
+pub fn default_keytab_path(config: &Krb5Config) -> Result<PathBuf> {
+    let name = match env::var(KRB5_KTNAME_ENV) {
+        Ok(name) if !name.is_empty() => name,
+        _ => config
+            .default_keytab_name()
+            .unwrap_or(DEFAULT_KEYTAB_NAME)
+            .to_string(),
+    };
+    return keytab_path_from_name(&name);
+}
+
+/// Retrieves the path of a keytab from its name, in the
+/// `[TYPE:]residual` format. The `%{uid}`, `%{euid}` and `%{TEMP}` tokens
+/// of the name are expanded.
+pub fn keytab_path_from_name(name: &str) -> Result<PathBuf> {
+    let path = match name.split_once(':') {
+        Some(("FILE", path)) | Some(("WRFILE", path)) => path,
+        Some((keytab_type, _)) if !keytab_type.contains('/') => {
+            return Err(Error::ConfigError(format!(
+                "Unsupported keytab type: {}",
+                keytab_type
+            )));
+        }
+        _ => name,
+    };
+    return expand_path_tokens(path);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn keytab_path_from_file_names() {
+        assert_eq!(
+            PathBuf::from("/etc/krb5.keytab"),
+            keytab_path_from_name(DEFAULT_KEYTAB_NAME).unwrap()
+        );
+        assert_eq!(
+            PathBuf::from("/etc/http.keytab"),
+            keytab_path_from_name("WRFILE:/etc/http.keytab").unwrap()
+        );
+        assert_eq!(
+            PathBuf::from("/etc/http.keytab"),
+            keytab_path_from_name("/etc/http.keytab").unwrap()
+        );
+    }
+
+    #[test]
+    fn error_with_unsupported_keytab_type() {
+        assert_eq!(
+            Err(Error::ConfigError(
+                "Unsupported keytab type: MEMORY".to_string()
+            )),
+            keytab_path_from_name("MEMORY:keytab")
+        );
+    }
+}
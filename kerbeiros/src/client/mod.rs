@@ -0,0 +1,22 @@
+//! High level client, that keeps the tickets in a credential cache and
+//! retrieves them from the KDC when required.
+
+mod ccache_name;
+pub use ccache_name::*;
+
+mod keytab_name;
+pub use keytab_name::*;
+
+mod kerberos_client;
+pub use kerberos_client::*;
+
+#[cfg(feature = "async")]
+mod async_kerberos_client;
+#[cfg(feature = "async")]
+pub use async_kerberos_client::*;
+
+mod credential_cache;
+pub use credential_cache::*;
+
+mod verify_init_creds;
+pub use verify_init_creds::*;
@@ -0,0 +1,200 @@
+use crate::credentials::{Credential, KeytabKeys};
+use crate::requesters::TgsRequester;
+use crate::{Error, Result};
+use himmelblau_kerberos_asn1::PrincipalName;
+use himmelblau_kerberos_constants::principal_names::NT_SRV_HST;
+use himmelblau_kerberos_crypto::supported_etypes;
+use himmelblau_kerberos_keytab::Keytab;
+
+/// Verifies that `tgt` was issued by the real KDC of its realm, as
+/// `krb5_verify_init_creds` of MIT does.
+///
+/// A ticket for `server` is requested with the TGT and decrypted with the
+/// key of the service stored in `keytab`. Since only the real KDC knows
+/// that key, this prevents a rogue KDC from validating any password when
+/// the TGT is used to authenticate a user, as in a login. If `server` is
+/// `None`, the first `host` service of the realm found in the keytab is
+/// used.
+///
+/// Returns the ticket obtained for the service.
+///
+/// # Errors
+/// Returns [`Error::KeytabError`] if the keytab has no key for the
+/// service, and [`Error::InvalidTicket`] if the ticket cannot be
+/// decrypted with the keytab or was not issued for the client of the TGT.
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+/// use himmelblau_kerberos_crypto::Key;
+/// use himmelblau_kerberos_keytab::Keytab;
+/// use ascii::AsciiString;
+/// use std::fs;
+///
+/// let client = KerberosClient::from_env().unwrap();
+/// let username = AsciiString::from_ascii("Bob").unwrap();
+/// let tgt = client
+///     .request_tgt(&username, &Key::Secret("S3cr3t".to_string()))
+///     .unwrap();
+///
+/// let data = fs::read("/etc/krb5.keytab").unwrap();
+/// let keytab = Keytab::parse(&data).unwrap().1;
+/// let tgs_requester = TgsRequester::from_config(
+///     &Krb5Config::from_env().unwrap(),
+///     client.realm().unwrap(),
+/// )
+/// .unwrap();
+///
+/// verify_initial_credentials(&tgt, &tgs_requester, &keytab, None).unwrap();
+/// ```
+pub fn verify_initial_credentials(
+    tgt: &Credential,
+    tgs_requester: &TgsRequester,
+    keytab: &Keytab,
+    server: Option<&PrincipalName>,
+) -> Result<Credential> {
+    let realm = tgt.crealm();
+    let server = match server {
+        Some(server) => server.clone(),
+        None => host_principal(keytab, realm).ok_or_else(|| {
+            Error::KeytabError(format!("no host key for realm {}", realm))
+        })?,
+    };
+
+    let keys = KeytabKeys::new(
+        keytab,
+        realm,
+        &server.to_string(),
+        &supported_etypes().into_iter().collect(),
+    );
+    if keys.is_empty() {
+        return Err(Error::KeytabError(format!(
+            "no supported key for {}@{}",
+            server, realm
+        )));
+    }
+
+    let credential = tgs_requester.request(tgt, &server)?;
+    let enc_ticket_part = keys.decrypt_ticket(credential.ticket())?;
+
+    if &enc_ticket_part.key != credential.key() {
+        return Err(Error::InvalidTicket(format!(
+            "session key of the ticket for {} does not match the reply",
+            server
+        )));
+    }
+
+    if &enc_ticket_part.crealm != tgt.crealm()
+        || &enc_ticket_part.cname != tgt.cname()
+    {
+        return Err(Error::InvalidTicket(format!(
+            "ticket for {} was issued to {}@{}",
+            server, enc_ticket_part.cname, enc_ticket_part.crealm
+        )));
+    }
+
+    return Ok(credential);
+}
+
+/// First `host/<hostname>` principal of `realm` stored in the keytab.
+fn host_principal(keytab: &Keytab, realm: &str) -> Option<PrincipalName> {
+    for entry in keytab.entries.iter() {
+        let entry_realm = String::from_utf8_lossy(&entry.realm.data);
+        if !entry_realm.eq_ignore_ascii_case(realm)
+            || entry.components.len() != 2
+            || entry.components[0].data != b"host"
+        {
+            continue;
+        }
+
+        let mut principal = PrincipalName::new(NT_SRV_HST, "host".to_string());
+        principal.push(
+            String::from_utf8_lossy(&entry.components[1].data).to_string(),
+        );
+        return Some(principal);
+    }
+    return None;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::requesters::test_kdc::{keytab_entry, test_tgt, TestKdc};
+    use himmelblau_kerberos_crypto::Key;
+
+    fn host_name() -> PrincipalName {
+        let mut host = PrincipalName::new(NT_SRV_HST, "host".to_string());
+        host.push("castle.kingdom.hearts".to_string());
+        return host;
+    }
+
+    fn host_keytab(key: &Key) -> Keytab {
+        return Keytab::new(
+            5,
+            2,
+            vec![
+                keytab_entry("mickey@KINGDOM.HEARTS", 1, &Key::RC4Key([1; 16])),
+                keytab_entry(
+                    "host/castle.kingdom.hearts@KINGDOM.HEARTS",
+                    4,
+                    key,
+                ),
+            ],
+        );
+    }
+
+    fn verify(kdc_key: Key, keytab_key: &Key) -> Result<Credential> {
+        let mut kdc = TestKdc::new("KINGDOM.HEARTS", vec![host_name()], None);
+        kdc.set_service_key(4, kdc_key);
+        let tgs_requester = TgsRequester::with_transporter(Box::new(kdc));
+
+        return verify_initial_credentials(
+            &test_tgt(),
+            &tgs_requester,
+            &host_keytab(keytab_key),
+            None,
+        );
+    }
+
+    #[test]
+    fn verify_tgt_with_host_key() {
+        let key = Key::AES256Key([4; 32]);
+        let credential = verify(key.clone(), &key).unwrap();
+        assert_eq!(&host_name(), credential.sname());
+    }
+
+    #[test]
+    fn error_verifying_tgt_of_rogue_kdc() {
+        let result = verify(Key::AES256Key([5; 32]), &Key::AES256Key([4; 32]));
+        assert!(matches!(result, Err(Error::InvalidTicket(_))));
+    }
+
+    #[test]
+    fn error_verifying_without_host_key() {
+        let tgs_requester = TgsRequester::with_transporter(Box::new(
+            TestKdc::new("KINGDOM.HEARTS", vec![host_name()], None),
+        ));
+        let keytab = Keytab::new(
+            5,
+            2,
+            vec![keytab_entry(
+                "mickey@KINGDOM.HEARTS",
+                1,
+                &Key::RC4Key([1; 16]),
+            )],
+        );
+
+        assert_eq!(
+            Err(Error::KeytabError(
+                "no host key for realm KINGDOM.HEARTS".to_string()
+            )),
+            verify_initial_credentials(
+                &test_tgt(),
+                &tgs_requester,
+                &keytab,
+                None
+            )
+        );
+    }
+}
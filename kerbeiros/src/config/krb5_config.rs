@@ -0,0 +1,589 @@
+use super::profile::{Profile, ProfileValue};
+use super::realm_config::{
+    KdcEntry, RealmConfig, DEFAULT_KADMIN_PORT, DEFAULT_KPASSWD_PORT,
+};
+use super::values::{parse_bool, parse_duration, parse_enctypes};
+use crate::transporter::DEFAULT_KERBEROS_PORT;
+use crate::Result;
+use ascii::AsciiString;
+use chrono::Duration;
+use std::env;
+use std::path::Path;
+
+/// Environment variable with the list of configuration files,
+/// separated by `:`.
+pub const KRB5_CONFIG_ENV: &str = "KRB5_CONFIG";
+
+/// Configuration file used when `KRB5_CONFIG` is not defined.
+pub const DEFAULT_KRB5_CONFIG_PATH: &str = "/etc/krb5.conf";
+
+/// Default maximum clock skew allowed, 5 minutes.
+pub const DEFAULT_CLOCKSKEW_SECONDS: i64 = 300;
+
+/// Default size limit to send messages over UDP.
+pub const DEFAULT_UDP_PREFERENCE_LIMIT: usize = 1465;
+
+/// Typed view of a `krb5.conf` configuration, in the MIT/Heimdal format.
+///
+/// # Examples
+///
+/// ```
+/// use himmelblau_kerbeiros::Krb5Config;
+/// use himmelblau_kerberos_constants::etypes::*;
+///
+/// let config = Krb5Config::parse("
+/// [libdefaults]
+///     default_realm = KINGDOM.HEARTS
+///     default_tkt_enctypes = aes256-cts-hmac-sha1-96 rc4-hmac
+///
+/// [realms]
+///     KINGDOM.HEARTS = {
+///         kdc = kdc.kingdom.hearts
+///     }
+///
+/// [domain_realm]
+///     .kingdom.hearts = KINGDOM.HEARTS
+/// ").unwrap();
+///
+/// assert_eq!("KINGDOM.HEARTS", config.default_realm().unwrap());
+/// assert_eq!(
+///     Some(vec![AES256_CTS_HMAC_SHA1_96, RC4_HMAC]),
+///     config.default_tkt_enctypes()
+/// );
+/// assert_eq!(
+///     "kdc.kingdom.hearts",
+///     config.realm("KINGDOM.HEARTS").unwrap().kdcs[0].host
+/// );
+/// assert_eq!(
+///     Some("KINGDOM.HEARTS".to_string()),
+///     config.realm_of_host("castle.kingdom.hearts")
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Krb5Config {
+    profile: Profile,
+}
+
+impl Krb5Config {
+    pub fn new(profile: Profile) -> Self {
+        return Self { profile };
+    }
+
+    /// Loads the configuration from the files of the `KRB5_CONFIG`
+    /// environment variable or, if it is not defined, from
+    /// `/etc/krb5.conf`. Files that do not exist are skipped.
+    ///
+    /// # Errors
+    /// Returns error if an existing file cannot be read or parsed.
+    pub fn from_env() -> Result<Self> {
+        let paths = match env::var(KRB5_CONFIG_ENV) {
+            Ok(paths) => paths
+                .split(':')
+                .filter(|path| !path.is_empty())
+                .map(|path| path.to_string())
+                .collect(),
+            Err(_) => vec![DEFAULT_KRB5_CONFIG_PATH.to_string()],
+        };
+
+        return Ok(Self::new(Profile::load_files(&paths)?));
+    }
+
+    /// Loads the configuration from a file.
+    ///
+    /// # Errors
+    /// Returns error if the file cannot be read or parsed.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        return Ok(Self::new(Profile::load(path)?));
+    }
+
+    /// Parses the configuration from the content of a file.
+    ///
+    /// # Errors
+    /// Returns error if the content cannot be parsed.
+    pub fn parse(content: &str) -> Result<Self> {
+        return Ok(Self::new(Profile::parse(content)?));
+    }
+
+    /// Underlying profile, to access relations without a typed getter.
+    pub fn profile(&self) -> &Profile {
+        return &self.profile;
+    }
+
+    fn libdefault(&self, name: &str) -> Option<&str> {
+        return self.profile.value(&["libdefaults", name]);
+    }
+
+    fn libdefault_bool(&self, name: &str) -> Option<bool> {
+        return self.libdefault(name).and_then(parse_bool);
+    }
+
+    fn libdefault_duration(&self, name: &str) -> Option<Duration> {
+        return self.libdefault(name).and_then(parse_duration);
+    }
+
+    /// Realm used when no one is specified.
+    pub fn default_realm(&self) -> Option<AsciiString> {
+        return self
+            .libdefault("default_realm")
+            .and_then(|realm| AsciiString::from_ascii(realm).ok());
+    }
+
+    /// Name of the default credential cache, used when `KRB5CCNAME` is
+    /// not defined.
+    pub fn default_ccache_name(&self) -> Option<&str> {
+        return self.libdefault("default_ccache_name");
+    }
+
+    /// Name of the default keytab, used when `KRB5_KTNAME` is not
+    /// defined.
+    pub fn default_keytab_name(&self) -> Option<&str> {
+        return self.libdefault("default_keytab_name");
+    }
+
+    /// Encryption types to request in the AS-REQ and TGS-REQ messages.
+    /// As MIT, if not defined, `permitted_enctypes` is used.
+    pub fn default_tkt_enctypes(&self) -> Option<Vec<i32>> {
+        return self
+            .libdefault("default_tkt_enctypes")
+            .or_else(|| self.libdefault("permitted_enctypes"))
+            .map(parse_enctypes);
+    }
+
+    /// Encryption types allowed for session keys.
+    pub fn permitted_enctypes(&self) -> Option<Vec<i32>> {
+        return self.libdefault("permitted_enctypes").map(parse_enctypes);
+    }
+
+    /// Whether tickets should be requested as forwardable.
+    pub fn forwardable(&self) -> Option<bool> {
+        return self.libdefault_bool("forwardable");
+    }
+
+    /// Whether tickets should be requested as proxiable.
+    pub fn proxiable(&self) -> Option<bool> {
+        return self.libdefault_bool("proxiable");
+    }
+
+    /// Whether the canonicalize option should be requested.
+    pub fn canonicalize(&self) -> Option<bool> {
+        return self.libdefault_bool("canonicalize");
+    }
+
+    /// Whether the KDCs can be located by using DNS.
+    pub fn dns_lookup_kdc(&self) -> bool {
+        return self.libdefault_bool("dns_lookup_kdc").unwrap_or(true);
+    }
+
+    /// Whether the realm of a host can be located by using DNS.
+    pub fn dns_lookup_realm(&self) -> bool {
+        return self.libdefault_bool("dns_lookup_realm").unwrap_or(false);
+    }
+
+    /// Requested lifetime of the tickets.
+    pub fn ticket_lifetime(&self) -> Option<Duration> {
+        return self.libdefault_duration("ticket_lifetime");
+    }
+
+    /// Requested renewable lifetime of the tickets.
+    pub fn renew_lifetime(&self) -> Option<Duration> {
+        return self.libdefault_duration("renew_lifetime");
+    }
+
+    /// Maximum clock skew allowed between hosts.
+    pub fn clockskew(&self) -> Duration {
+        return self
+            .libdefault_duration("clockskew")
+            .unwrap_or_else(|| Duration::seconds(DEFAULT_CLOCKSKEW_SECONDS));
+    }
+
+    /// Size limit of the messages sent over UDP. Bigger messages are sent
+    /// over TCP.
+    pub fn udp_preference_limit(&self) -> usize {
+        return self
+            .libdefault("udp_preference_limit")
+            .and_then(|limit| limit.trim().parse().ok())
+            .unwrap_or(DEFAULT_UDP_PREFERENCE_LIMIT);
+    }
+
+    /// Names of the realms defined in the `[realms]` section.
+    pub fn realms(&self) -> Vec<&str> {
+        return self.profile.names(&["realms"]);
+    }
+
+    /// Configuration of a realm defined in the `[realms]` section.
+    ///
+    /// # Errors
+    /// Returns [`Error::ConfigError`](crate::Error::ConfigError) if some
+    /// address of the realm is invalid.
+    pub fn realm(&self, realm: &str) -> Result<RealmConfig> {
+        let path = ["realms", realm];
+        return Ok(RealmConfig {
+            kdcs: self.realm_entries(&path, "kdc", DEFAULT_KERBEROS_PORT)?,
+            master_kdcs: self.realm_entries(
+                &path,
+                "master_kdc",
+                DEFAULT_KERBEROS_PORT,
+            )?,
+            admin_servers: self.realm_entries(
+                &path,
+                "admin_server",
+                DEFAULT_KADMIN_PORT,
+            )?,
+            kpasswd_servers: self.realm_entries(
+                &path,
+                "kpasswd_server",
+                DEFAULT_KPASSWD_PORT,
+            )?,
+            default_domain: self
+                .profile
+                .value(&["realms", realm, "default_domain"])
+                .map(|domain| domain.to_string()),
+        });
+    }
+
+    fn realm_entries(
+        &self,
+        realm_path: &[&str],
+        name: &str,
+        default_port: u16,
+    ) -> Result<Vec<KdcEntry>> {
+        let mut path = realm_path.to_vec();
+        path.push(name);
+
+        let mut entries = Vec::new();
+        for value in self.profile.values(&path) {
+            // Heimdal allows several addresses in the same relation
+            for entry in value.split_whitespace() {
+                entries.push(KdcEntry::parse(entry, default_port)?);
+            }
+        }
+        return Ok(entries);
+    }
+
+    /// Retrieves the realm of a host from the `[domain_realm]` section.
+    /// As MIT, the host name is checked first, and then each of its
+    /// parent domains, with and without leading dot.
+    pub fn realm_of_host(&self, host: &str) -> Option<String> {
+        let host = host.trim_end_matches('.').to_lowercase();
+        let mappings: Vec<(String, &str)> = self
+            .profile
+            .sections(&["domain_realm"])
+            .into_iter()
+            .flat_map(|section| section.relations.iter())
+            .filter_map(|relation| match &relation.value {
+                ProfileValue::String(realm) => {
+                    Some((relation.name.to_lowercase(), realm.as_str()))
+                }
+                _ => None,
+            })
+            .collect();
+
+        let find = |domain: &str| -> Option<String> {
+            return mappings
+                .iter()
+                .find(|(name, _)| name == domain)
+                .map(|(_, realm)| realm.to_string());
+        };
+
+        let mut domain = host.as_str();
+        loop {
+            if let Some(realm) = find(domain) {
+                return Some(realm);
+            }
+
+            if let Some(parent) = domain.strip_prefix('.') {
+                domain = parent;
+                continue;
+            }
+
+            match domain.find('.') {
+                Some(dot) => domain = &domain[dot..],
+                None => return None,
+            }
+        }
+    }
+
+    /// Retrieves the intermediate realms in the authentication path from
+    /// `client_realm` to `server_realm`, excluding both of them.
+    ///
+    /// The `[capaths]` section is used if it defines the path; otherwise
+    /// the hierarchical path based on the realm names is calculated.
+    pub fn capath(
+        &self,
+        client_realm: &str,
+        server_realm: &str,
+    ) -> Vec<String> {
+        if client_realm == server_realm {
+            return Vec::new();
+        }
+
+        let path = ["capaths", client_realm, server_realm];
+        if !self.profile.sections(&path[..2]).is_empty() {
+            let values = self.profile.values(&path);
+            if !values.is_empty() {
+                return values
+                    .iter()
+                    .flat_map(|value| value.split_whitespace())
+                    .filter(|realm| *realm != ".")
+                    .map(|realm| realm.to_string())
+                    .collect();
+            }
+        }
+
+        return hierarchical_capath(client_realm, server_realm);
+    }
+}
+
+/// Calculates the intermediate realms of the path between two realms
+/// by going up in the client realm hierarchy to the common ancestor,
+/// and then down to the server realm. If the realms do not have a common
+/// ancestor, the direct path is returned.
+fn hierarchical_capath(client_realm: &str, server_realm: &str) -> Vec<String> {
+    let client_components: Vec<&str> = client_realm.split('.').collect();
+    let server_components: Vec<&str> = server_realm.split('.').collect();
+
+    let common = client_components
+        .iter()
+        .rev()
+        .zip(server_components.iter().rev())
+        .take_while(|(c, s)| c == s)
+        .count();
+
+    if common == 0 {
+        return Vec::new();
+    }
+
+    let mut path = Vec::new();
+    for i in 1..=(client_components.len() - common) {
+        path.push(client_components[i..].join("."));
+    }
+
+    let server_steps = server_components.len() - common;
+    for i in (1..server_steps).rev() {
+        path.push(server_components[i..].join("."));
+    }
+
+    path.retain(|realm| realm != client_realm && realm != server_realm);
+    return path;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::transporter::TransportProtocol;
+    use crate::Error;
+    use himmelblau_kerberos_constants::etypes::*;
+
+    const KRB5_CONF: &str = "
+[libdefaults]
+    default_realm = KINGDOM.HEARTS
+    default_tkt_enctypes = aes256-cts-hmac-sha1-96 rc4-hmac
+    permitted_enctypes = aes256-cts-hmac-sha1-96 aes128-cts-hmac-sha1-96
+    forwardable = true
+    proxiable = no
+    ticket_lifetime = 10h
+    renew_lifetime = 7d
+    clockskew = 600
+    udp_preference_limit = 1
+    dns_lookup_kdc = false
+
+[realms]
+    KINGDOM.HEARTS = {
+        kdc = kdc1.kingdom.hearts
+        kdc = tcp/kdc2.kingdom.hearts:8888
+        admin_server = admin.kingdom.hearts
+        default_domain = kingdom.hearts
+    }
+    DISNEY.CASTLE = {
+        kdc = 10.0.0.1
+        kpasswd_server = kpasswd.disney.castle
+    }
+
+[domain_realm]
+    .kingdom.hearts = KINGDOM.HEARTS
+    kingdom.hearts = KINGDOM.HEARTS
+    castle.disney.com = DISNEY.CASTLE
+    .Disney.com = DISNEY.COM
+
+[capaths]
+    KINGDOM.HEARTS = {
+        DISNEY.CASTLE = HOLLOW.BASTION
+        DISNEY.CASTLE = TRAVERSE.TOWN
+        OLYMPUS.COLISEUM = .
+    }
+";
+
+    fn config() -> Krb5Config {
+        return Krb5Config::parse(KRB5_CONF).unwrap();
+    }
+
+    #[test]
+    fn libdefaults() {
+        let config = config();
+
+        assert_eq!("KINGDOM.HEARTS", config.default_realm().unwrap());
+        assert_eq!(
+            Some(vec![AES256_CTS_HMAC_SHA1_96, RC4_HMAC]),
+            config.default_tkt_enctypes()
+        );
+        assert_eq!(
+            Some(vec![AES256_CTS_HMAC_SHA1_96, AES128_CTS_HMAC_SHA1_96]),
+            config.permitted_enctypes()
+        );
+        assert_eq!(Some(true), config.forwardable());
+        assert_eq!(Some(false), config.proxiable());
+        assert_eq!(None, config.canonicalize());
+        assert_eq!(Some(Duration::hours(10)), config.ticket_lifetime());
+        assert_eq!(Some(Duration::days(7)), config.renew_lifetime());
+        assert_eq!(Duration::seconds(600), config.clockskew());
+        assert_eq!(1, config.udp_preference_limit());
+        assert!(!config.dns_lookup_kdc());
+    }
+
+    #[test]
+    fn default_libdefaults() {
+        let config = Krb5Config::parse("").unwrap();
+
+        assert_eq!(None, config.default_realm());
+        assert_eq!(None, config.default_tkt_enctypes());
+        assert_eq!(
+            Duration::seconds(DEFAULT_CLOCKSKEW_SECONDS),
+            config.clockskew()
+        );
+        assert_eq!(DEFAULT_UDP_PREFERENCE_LIMIT, config.udp_preference_limit());
+        assert!(config.dns_lookup_kdc());
+        assert!(!config.dns_lookup_realm());
+    }
+
+    #[test]
+    fn default_tkt_enctypes_fallback_to_permitted_enctypes() {
+        let config = Krb5Config::parse(
+            "[libdefaults]\npermitted_enctypes = aes128-cts\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            Some(vec![AES128_CTS_HMAC_SHA1_96]),
+            config.default_tkt_enctypes()
+        );
+    }
+
+    #[test]
+    fn realms() {
+        let config = config();
+
+        assert_eq!(vec!["KINGDOM.HEARTS", "DISNEY.CASTLE"], config.realms());
+
+        let realm = config.realm("KINGDOM.HEARTS").unwrap();
+        assert_eq!(
+            vec![
+                KdcEntry::new("kdc1.kingdom.hearts".to_string(), 88),
+                KdcEntry {
+                    host: "kdc2.kingdom.hearts".to_string(),
+                    port: 8888,
+                    transport_protocol: Some(TransportProtocol::TCP),
+                }
+            ],
+            realm.kdcs
+        );
+        assert_eq!(
+            vec![KdcEntry::new("admin.kingdom.hearts".to_string(), 749)],
+            realm.admin_servers
+        );
+        assert_eq!(
+            vec![KdcEntry::new("admin.kingdom.hearts".to_string(), 464)],
+            realm.kpasswd_servers_or_admin_servers()
+        );
+        assert_eq!(Some("kingdom.hearts".to_string()), realm.default_domain);
+
+        let realm = config.realm("DISNEY.CASTLE").unwrap();
+        assert_eq!(
+            vec![KdcEntry::new("kpasswd.disney.castle".to_string(), 464)],
+            realm.kpasswd_servers
+        );
+
+        assert_eq!(RealmConfig::default(), config.realm("UNKNOWN").unwrap());
+    }
+
+    #[test]
+    fn error_realm_with_invalid_kdc() {
+        let config =
+            Krb5Config::parse("[realms]\nA = {\nkdc = host:port\n}\n").unwrap();
+
+        assert_eq!(
+            Error::ConfigError("Invalid port port".to_string()),
+            config.realm("A").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn domain_realm_mapping() {
+        let config = config();
+
+        assert_eq!(
+            Some("KINGDOM.HEARTS".to_string()),
+            config.realm_of_host("castle.kingdom.hearts")
+        );
+        assert_eq!(
+            Some("KINGDOM.HEARTS".to_string()),
+            config.realm_of_host("kingdom.hearts")
+        );
+        assert_eq!(
+            Some("KINGDOM.HEARTS".to_string()),
+            config.realm_of_host("a.b.Kingdom.Hearts.")
+        );
+        assert_eq!(
+            Some("DISNEY.CASTLE".to_string()),
+            config.realm_of_host("castle.disney.com")
+        );
+        assert_eq!(
+            Some("DISNEY.COM".to_string()),
+            config.realm_of_host("other.disney.com")
+        );
+        assert_eq!(None, config.realm_of_host("unknown.host"));
+    }
+
+    #[test]
+    fn capaths() {
+        let config = config();
+
+        assert_eq!(
+            vec!["HOLLOW.BASTION".to_string(), "TRAVERSE.TOWN".to_string()],
+            config.capath("KINGDOM.HEARTS", "DISNEY.CASTLE")
+        );
+        assert_eq!(
+            Vec::<String>::new(),
+            config.capath("KINGDOM.HEARTS", "OLYMPUS.COLISEUM")
+        );
+        assert_eq!(
+            Vec::<String>::new(),
+            config.capath("KINGDOM.HEARTS", "KINGDOM.HEARTS")
+        );
+    }
+
+    #[test]
+    fn hierarchical_capaths() {
+        let config = Krb5Config::default();
+
+        assert_eq!(
+            vec!["EXAMPLE.COM".to_string()],
+            config.capath("ENG.EXAMPLE.COM", "SALES.EXAMPLE.COM")
+        );
+        assert_eq!(
+            Vec::<String>::new(),
+            config.capath("ENG.EXAMPLE.COM", "EXAMPLE.COM")
+        );
+        assert_eq!(
+            vec![
+                "ENG.EXAMPLE.COM".to_string(),
+                "EXAMPLE.COM".to_string(),
+                "SALES.EXAMPLE.COM".to_string()
+            ],
+            config.capath("A.ENG.EXAMPLE.COM", "X.SALES.EXAMPLE.COM")
+        );
+        assert_eq!(
+            Vec::<String>::new(),
+            config.capath("EXAMPLE.COM", "EXAMPLE.ORG")
+        );
+    }
+}
@@ -0,0 +1,17 @@
+//! Configuration of the library from `krb5.conf` files
+//!
+//! The files are parsed with the profile format used by MIT and Heimdal,
+//! and the configuration can be used to build the requesters, instead of
+//! specifying realm, KDC address and encryption types in code.
+//!
+
+mod profile;
+pub use profile::*;
+
+mod krb5_config;
+pub use krb5_config::*;
+
+mod realm_config;
+pub use realm_config::*;
+
+mod values;
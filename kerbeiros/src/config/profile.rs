@@ -0,0 +1,729 @@
+use crate::{Error, Result};
+use std::fs;
+use std::path::Path;
+
+/// Maximum nesting of include/includedir directives, to avoid loops.
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// Value of a relation in a profile, which can be a string or a subsection.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProfileValue {
+    String(String),
+    Section(ProfileSection),
+}
+
+/// Relation of a profile section, in the form `name = value`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileRelation {
+    pub name: String,
+    pub value: ProfileValue,
+}
+
+/// Section of a profile, composed by an ordered list of relations.
+/// The same name can appear in several relations.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ProfileSection {
+    pub relations: Vec<ProfileRelation>,
+    pub is_final: bool,
+}
+
+impl ProfileSection {
+    /// Retrieves the string values of the relations with the given name.
+    pub fn values(&self, name: &str) -> Vec<&str> {
+        let mut values = Vec::new();
+        for relation in self.relations.iter() {
+            if relation.name == name {
+                if let ProfileValue::String(value) = &relation.value {
+                    values.push(value.as_str());
+                }
+            }
+        }
+        return values;
+    }
+
+    /// Retrieves the first string value of the relations with the given name.
+    pub fn value(&self, name: &str) -> Option<&str> {
+        return self.values(name).into_iter().next();
+    }
+
+    /// Retrieves the subsections with the given name.
+    pub fn sections(&self, name: &str) -> Vec<&ProfileSection> {
+        let mut sections = Vec::new();
+        for relation in self.relations.iter() {
+            if relation.name == name {
+                if let ProfileValue::Section(section) = &relation.value {
+                    sections.push(section);
+                }
+            }
+        }
+        return sections;
+    }
+
+    /// Retrieves the names of the relations, without duplicates and
+    /// keeping the order of appearance.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::new();
+        for relation in self.relations.iter() {
+            if !names.contains(&relation.name.as_str()) {
+                names.push(&relation.name);
+            }
+        }
+        return names;
+    }
+
+    fn push_section(&mut self, name: String, section: ProfileSection) {
+        self.relations.push(ProfileRelation {
+            name,
+            value: ProfileValue::Section(section),
+        });
+    }
+
+    fn push_string(&mut self, name: String, value: String) {
+        self.relations.push(ProfileRelation {
+            name,
+            value: ProfileValue::String(value),
+        });
+    }
+
+    /// Top level sections with the same name are merged, as MIT does.
+    fn merge_top_level_section(
+        &mut self,
+        name: String,
+        section: ProfileSection,
+    ) {
+        for relation in self.relations.iter_mut() {
+            if relation.name == name {
+                if let ProfileValue::Section(existing) = &mut relation.value {
+                    existing.relations.extend(section.relations);
+                    existing.is_final |= section.is_final;
+                    return;
+                }
+            }
+        }
+        self.push_section(name, section);
+    }
+}
+
+/// Configuration tree in the profile format used by MIT and Heimdal
+/// `krb5.conf` files.
+///
+/// A profile can be composed by several files. Lookups are done in the
+/// order the files were loaded, so values of the first files take
+/// precedence, unless a section is marked as final with `*`.
+///
+/// # Examples
+///
+/// ```
+/// use himmelblau_kerbeiros::Profile;
+///
+/// let profile = Profile::parse("
+/// [libdefaults]
+///     default_realm = KINGDOM.HEARTS
+/// [realms]
+///     KINGDOM.HEARTS = {
+///         kdc = kdc1.kingdom.hearts
+///         kdc = kdc2.kingdom.hearts:88
+///     }
+/// ").unwrap();
+///
+/// assert_eq!(
+///     Some("KINGDOM.HEARTS"),
+///     profile.value(&["libdefaults", "default_realm"])
+/// );
+/// assert_eq!(
+///     vec!["kdc1.kingdom.hearts", "kdc2.kingdom.hearts:88"],
+///     profile.values(&["realms", "KINGDOM.HEARTS", "kdc"])
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Profile {
+    roots: Vec<ProfileSection>,
+}
+
+impl Profile {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// Parses the content of a profile file. The include and includedir
+    /// directives are resolved from the filesystem.
+    ///
+    /// # Errors
+    /// Returns [`Error::ProfileParseError`] if the content has syntax errors.
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut profile = Self::new();
+        profile.roots.push(ProfileParser::parse(content, 0)?);
+        return Ok(profile);
+    }
+
+    /// Loads a profile from a file.
+    ///
+    /// # Errors
+    /// Returns error if the file cannot be read or has syntax errors.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut profile = Self::new();
+        profile.add_file(path)?;
+        return Ok(profile);
+    }
+
+    /// Loads a profile from several files. Files that do not exist are
+    /// skipped, as MIT does with the `KRB5_CONFIG` list.
+    ///
+    /// # Errors
+    /// Returns error if an existing file cannot be read or has syntax errors.
+    pub fn load_files<P: AsRef<Path>>(paths: &[P]) -> Result<Self> {
+        let mut profile = Self::new();
+        for path in paths.iter() {
+            if path.as_ref().exists() {
+                profile.add_file(path)?;
+            }
+        }
+        return Ok(profile);
+    }
+
+    /// Adds a file to the profile, with less precedence than the
+    /// already loaded files.
+    ///
+    /// # Errors
+    /// Returns error if the file cannot be read or has syntax errors.
+    pub fn add_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.roots
+            .push(ProfileParser::parse_file(path.as_ref(), 0)?);
+        return Ok(());
+    }
+
+    /// Retrieves all the string values under the given path.
+    pub fn values(&self, path: &[&str]) -> Vec<&str> {
+        let (last, sections_path) = match path.split_last() {
+            Some(split) => split,
+            None => return Vec::new(),
+        };
+
+        let mut values = Vec::new();
+        for root in self.roots.iter() {
+            let (sections, is_final) = Self::find_sections(root, sections_path);
+            for section in sections {
+                values.extend(section.values(last));
+            }
+            if is_final {
+                break;
+            }
+        }
+        return values;
+    }
+
+    /// Retrieves the first string value under the given path.
+    pub fn value(&self, path: &[&str]) -> Option<&str> {
+        return self.values(path).into_iter().next();
+    }
+
+    /// Retrieves all the sections under the given path.
+    pub fn sections(&self, path: &[&str]) -> Vec<&ProfileSection> {
+        let mut sections = Vec::new();
+        for root in self.roots.iter() {
+            let (found, is_final) = Self::find_sections(root, path);
+            sections.extend(found);
+            if is_final {
+                break;
+            }
+        }
+        return sections;
+    }
+
+    /// Retrieves the names of the relations included in the sections of
+    /// the given path, without duplicates.
+    pub fn names(&self, path: &[&str]) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::new();
+        for section in self.sections(path) {
+            for name in section.names() {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        return names;
+    }
+
+    fn find_sections<'a>(
+        root: &'a ProfileSection,
+        path: &[&str],
+    ) -> (Vec<&'a ProfileSection>, bool) {
+        let mut sections = vec![root];
+        let mut is_final = false;
+
+        for name in path.iter() {
+            let mut next_sections = Vec::new();
+            for section in sections {
+                next_sections.extend(section.sections(name));
+            }
+            is_final = next_sections.iter().any(|s| s.is_final);
+            sections = next_sections;
+        }
+
+        return (sections, is_final);
+    }
+}
+
+/// Parser of the profile syntax, following the MIT implementation.
+struct ProfileParser {
+    root: ProfileSection,
+    current_section: Option<(String, ProfileSection)>,
+    groups: Vec<(String, ProfileSection)>,
+    include_depth: usize,
+}
+
+impl ProfileParser {
+    fn parse_file(path: &Path, include_depth: usize) -> Result<ProfileSection> {
+        let content = fs::read_to_string(path).map_err(|error| {
+            Error::ConfigError(format!(
+                "Unable to read {}: {}",
+                path.display(),
+                error
+            ))
+        })?;
+        return Self::parse(&content, include_depth);
+    }
+
+    fn parse(content: &str, include_depth: usize) -> Result<ProfileSection> {
+        let mut parser = Self {
+            root: ProfileSection::default(),
+            current_section: None,
+            groups: Vec::new(),
+            include_depth,
+        };
+
+        for (i, line) in content.lines().enumerate() {
+            parser.parse_line(line).map_err(|error| match error {
+                Error::ProfileParseError(0, message) => {
+                    Error::ProfileParseError(i + 1, message)
+                }
+                error => error,
+            })?;
+        }
+
+        if !parser.groups.is_empty() {
+            return Err(Error::ProfileParseError(
+                content.lines().count(),
+                "Missing closing brace".to_string(),
+            ));
+        }
+
+        parser.close_current_section();
+        return Ok(parser.root);
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<()> {
+        if let Some(path) = Self::directive_argument(line, "include") {
+            return self.include_file(path);
+        }
+
+        if let Some(path) = Self::directive_argument(line, "includedir") {
+            return self.include_dir(path);
+        }
+
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            return Ok(());
+        }
+
+        if line.starts_with('[') {
+            return self.parse_section_header(line);
+        }
+
+        if self.current_section.is_none() {
+            // MIT ignores everything before the first section
+            return Ok(());
+        }
+
+        if line.starts_with('}') {
+            return self.close_group(line);
+        }
+
+        return self.parse_relation(line);
+    }
+
+    fn directive_argument<'a>(
+        line: &'a str,
+        directive: &str,
+    ) -> Option<&'a str> {
+        let rest = line.strip_prefix(directive)?;
+        if !rest.starts_with(|c: char| c.is_ascii_whitespace()) {
+            return None;
+        }
+        return Some(rest.trim());
+    }
+
+    fn parse_section_header(&mut self, line: &str) -> Result<()> {
+        if !self.groups.is_empty() {
+            return Err(Self::syntax_error("Section header inside a group"));
+        }
+
+        let end = line
+            .find(']')
+            .ok_or_else(|| Self::syntax_error("Missing ] in section header"))?;
+        let name = line[1..end].to_string();
+        let is_final = line[end + 1..].trim_start().starts_with('*');
+
+        self.close_current_section();
+        self.current_section = Some((
+            name,
+            ProfileSection {
+                relations: Vec::new(),
+                is_final,
+            },
+        ));
+        return Ok(());
+    }
+
+    fn close_current_section(&mut self) {
+        if let Some((name, section)) = self.current_section.take() {
+            self.root.merge_top_level_section(name, section);
+        }
+    }
+
+    fn close_group(&mut self, line: &str) -> Result<()> {
+        let (name, mut section) = self
+            .groups
+            .pop()
+            .ok_or_else(|| Self::syntax_error("Extra closing brace"))?;
+        section.is_final = line[1..].trim_start().starts_with('*');
+        self.innermost_section().push_section(name, section);
+        return Ok(());
+    }
+
+    fn parse_relation(&mut self, line: &str) -> Result<()> {
+        let name_end = line
+            .find(|c: char| c == '=' || c.is_ascii_whitespace())
+            .unwrap_or(line.len());
+        let name = &line[..name_end];
+        let rest = line[name_end..].trim_start();
+
+        let value = rest
+            .strip_prefix('=')
+            .ok_or_else(|| Self::syntax_error("Missing = in relation"))?
+            .trim();
+
+        if name.is_empty() {
+            return Err(Self::syntax_error("Empty relation name"));
+        }
+
+        // A tag may be marked as final with an ending *
+        let name = name.trim_end_matches('*').to_string();
+
+        if value == "{" {
+            self.groups.push((name, ProfileSection::default()));
+            return Ok(());
+        }
+
+        let value = if let Some(quoted) = value.strip_prefix('"') {
+            Self::parse_quoted_string(quoted)
+        } else {
+            value.to_string()
+        };
+
+        self.innermost_section().push_string(name, value);
+        return Ok(());
+    }
+
+    fn parse_quoted_string(quoted: &str) -> String {
+        let mut value = String::new();
+        let mut chars = quoted.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => break,
+                '\\' => match chars.next() {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some('b') => value.push('\u{8}'),
+                    Some(other) => value.push(other),
+                    None => break,
+                },
+                c => value.push(c),
+            }
+        }
+
+        return value;
+    }
+
+    fn innermost_section(&mut self) -> &mut ProfileSection {
+        if let Some((_, section)) = self.groups.last_mut() {
+            return section;
+        }
+
+        // parse_line ensures that a section is open before parsing relations
+        return &mut self
+            .current_section
+            .get_or_insert_with(|| (String::new(), ProfileSection::default()))
+            .1;
+    }
+
+    fn include_file(&mut self, path: &str) -> Result<()> {
+        if self.include_depth >= MAX_INCLUDE_DEPTH {
+            return Err(Error::ConfigError(format!(
+                "Too many nested includes in {}",
+                path
+            )));
+        }
+
+        let included =
+            Self::parse_file(Path::new(path), self.include_depth + 1)?;
+        for relation in included.relations {
+            if let ProfileValue::Section(section) = relation.value {
+                self.root.merge_top_level_section(relation.name, section);
+            }
+        }
+        return Ok(());
+    }
+
+    fn include_dir(&mut self, path: &str) -> Result<()> {
+        let entries = fs::read_dir(path).map_err(|error| {
+            Error::ConfigError(format!("Unable to read {}: {}", path, error))
+        })?;
+
+        let mut files = Vec::new();
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if Self::is_valid_included_file_name(&file_name) {
+                files.push(entry.path());
+            }
+        }
+        files.sort();
+
+        for file in files {
+            self.include_file(&file.to_string_lossy())?;
+        }
+        return Ok(());
+    }
+
+    /// As MIT, only the files with names composed by alphanumeric
+    /// characters, dashes or underscores, optionally ending in `.conf`,
+    /// are included.
+    fn is_valid_included_file_name(file_name: &str) -> bool {
+        let name = file_name.strip_suffix(".conf").unwrap_or(file_name);
+        return !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    }
+
+    /// Line number is filled by the caller.
+    fn syntax_error(message: &str) -> Error {
+        return Error::ProfileParseError(0, message.to_string());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn parse_sections_and_relations() {
+        let profile = Profile::parse(
+            "
+# comment
+[libdefaults]
+    default_realm = KINGDOM.HEARTS
+    ; other comment
+    dns_lookup_kdc = false
+
+[realms]
+    KINGDOM.HEARTS = {
+        kdc = kdc1.kingdom.hearts
+        kdc = kdc2.kingdom.hearts:88
+        admin_server = admin.kingdom.hearts
+    }
+",
+        )
+        .unwrap();
+
+        assert_eq!(
+            Some("KINGDOM.HEARTS"),
+            profile.value(&["libdefaults", "default_realm"])
+        );
+        assert_eq!(
+            Some("false"),
+            profile.value(&["libdefaults", "dns_lookup_kdc"])
+        );
+        assert_eq!(
+            vec!["kdc1.kingdom.hearts", "kdc2.kingdom.hearts:88"],
+            profile.values(&["realms", "KINGDOM.HEARTS", "kdc"])
+        );
+        assert_eq!(vec!["KINGDOM.HEARTS"], profile.names(&["realms"]));
+    }
+
+    #[test]
+    fn parse_nested_subsections() {
+        let profile = Profile::parse(
+            "
+[capaths]
+    KINGDOM.HEARTS = {
+        DISNEY.CASTLE = {
+            nested = value
+        }
+        OLYMPUS.COLISEUM = HOLLOW.BASTION
+    }
+",
+        )
+        .unwrap();
+
+        assert_eq!(
+            Some("value"),
+            profile.value(&[
+                "capaths",
+                "KINGDOM.HEARTS",
+                "DISNEY.CASTLE",
+                "nested"
+            ])
+        );
+        assert_eq!(
+            vec!["HOLLOW.BASTION"],
+            profile.values(&["capaths", "KINGDOM.HEARTS", "OLYMPUS.COLISEUM"])
+        );
+    }
+
+    #[test]
+    fn parse_quoted_values() {
+        let profile = Profile::parse(
+            "[libdefaults]\n  quoted = \"with \\\"quotes\\\"\\tand tab\"",
+        )
+        .unwrap();
+
+        assert_eq!(
+            Some("with \"quotes\"\tand tab"),
+            profile.value(&["libdefaults", "quoted"])
+        );
+    }
+
+    #[test]
+    fn ignore_lines_before_first_section() {
+        let profile =
+            Profile::parse("garbage\n[libdefaults]\n  a = b\n").unwrap();
+        assert_eq!(Some("b"), profile.value(&["libdefaults", "a"]));
+    }
+
+    #[test]
+    fn merge_repeated_sections() {
+        let profile = Profile::parse(
+            "[libdefaults]\na = 1\n[realms]\n[libdefaults]\nb = 2\n",
+        )
+        .unwrap();
+        assert_eq!(Some("1"), profile.value(&["libdefaults", "a"]));
+        assert_eq!(Some("2"), profile.value(&["libdefaults", "b"]));
+        assert_eq!(1, profile.sections(&["libdefaults"]).len());
+    }
+
+    #[test]
+    fn error_missing_equal_sign() {
+        assert_eq!(
+            Error::ProfileParseError(3, "Missing = in relation".to_string()),
+            Profile::parse("[libdefaults]\na = 1\nb 2\n").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn error_missing_closing_brace() {
+        assert_eq!(
+            Error::ProfileParseError(2, "Missing closing brace".to_string()),
+            Profile::parse("[realms]\nA = {\n").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn error_extra_closing_brace() {
+        assert_eq!(
+            Error::ProfileParseError(2, "Extra closing brace".to_string()),
+            Profile::parse("[realms]\n}\n").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn first_files_take_precedence() {
+        let dir = env::temp_dir().join("kerbeiros_profile_precedence");
+        fs::create_dir_all(&dir).unwrap();
+        let first = dir.join("first.conf");
+        let second = dir.join("second.conf");
+        fs::write(&first, "[libdefaults]\na = first\n").unwrap();
+        fs::write(&second, "[libdefaults]\na = second\nb = second\n").unwrap();
+
+        let profile = Profile::load_files(&[
+            first.clone(),
+            dir.join("missing.conf"),
+            second.clone(),
+        ])
+        .unwrap();
+
+        assert_eq!(Some("first"), profile.value(&["libdefaults", "a"]));
+        assert_eq!(Some("second"), profile.value(&["libdefaults", "b"]));
+        assert_eq!(
+            vec!["first", "second"],
+            profile.values(&["libdefaults", "a"])
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn final_sections_stop_lookup() {
+        let dir = env::temp_dir().join("kerbeiros_profile_final");
+        fs::create_dir_all(&dir).unwrap();
+        let first = dir.join("first.conf");
+        let second = dir.join("second.conf");
+        fs::write(&first, "[libdefaults]*\na = first\n").unwrap();
+        fs::write(&second, "[libdefaults]\na = second\n").unwrap();
+
+        let profile = Profile::load_files(&[first, second]).unwrap();
+        assert_eq!(vec!["first"], profile.values(&["libdefaults", "a"]));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn include_files_and_directories() {
+        let dir = env::temp_dir().join("kerbeiros_profile_include");
+        let conf_dir = dir.join("krb5.conf.d");
+        fs::create_dir_all(&conf_dir).unwrap();
+
+        let included = dir.join("included.conf");
+        fs::write(&included, "[libdefaults]\nincluded = yes\n").unwrap();
+        fs::write(
+            conf_dir.join("realms.conf"),
+            "[realms]\nA = {\nkdc = a\n}\n",
+        )
+        .unwrap();
+        fs::write(conf_dir.join("ignored.conf~"), "[realms]\nB = {\n}\n")
+            .unwrap();
+
+        let profile = Profile::parse(&format!(
+            "include {}\nincludedir {}\n[libdefaults]\nmain = yes\n",
+            included.display(),
+            conf_dir.display()
+        ))
+        .unwrap();
+
+        assert_eq!(Some("yes"), profile.value(&["libdefaults", "included"]));
+        assert_eq!(Some("yes"), profile.value(&["libdefaults", "main"]));
+        assert_eq!(Some("a"), profile.value(&["realms", "A", "kdc"]));
+        assert_eq!(vec!["A"], profile.names(&["realms"]));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn error_include_loop() {
+        let dir = env::temp_dir().join("kerbeiros_profile_loop");
+        fs::create_dir_all(&dir).unwrap();
+        let looped = dir.join("loop.conf");
+        fs::write(&looped, format!("include {}\n", looped.display())).unwrap();
+
+        match Profile::load(&looped).unwrap_err() {
+            Error::ConfigError(message) => {
+                assert!(message.starts_with("Too many nested includes"))
+            }
+            error => panic!("Unexpected error {:?}", error),
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,256 @@
+use crate::transporter::TransportProtocol;
+use crate::{Error, Result};
+use std::net::{IpAddr, SocketAddr};
+
+/// Address of a KDC, as specified in the `kdc`, `master_kdc`,
+/// `admin_server` or `kpasswd_server` relations of a realm.
+///
+/// The accepted formats are `host`, `host:port`, `[ipv6]:port`, and
+/// any of them prefixed by `tcp/` or `udp/` to force the transport
+/// protocol. With the `kkdcp` feature, the URL of a KDC proxy
+/// (`https://host[:port]/path`) is also accepted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KdcEntry {
+    pub host: String,
+    pub port: u16,
+    pub transport_protocol: Option<TransportProtocol>,
+}
+
+impl KdcEntry {
+    pub fn new(host: String, port: u16) -> Self {
+        return Self {
+            host,
+            port,
+            transport_protocol: None,
+        };
+    }
+
+    /// Parses an entry, using `default_port` if no port is specified.
+    ///
+    /// # Errors
+    /// Returns [`Error::ConfigError`] if the port is invalid.
+    pub fn parse(entry: &str, default_port: u16) -> Result<Self> {
+        let entry = entry.trim();
+        if let Some(url_rest) = entry.strip_prefix("https://") {
+            return Self::parse_proxy_url(entry, url_rest);
+        }
+
+        let (transport_protocol, address) =
+            if let Some(address) = entry.strip_prefix("tcp/") {
+                (Some(TransportProtocol::TCP), address)
+            } else if let Some(address) = entry.strip_prefix("udp/") {
+                (Some(TransportProtocol::UDP), address)
+            } else {
+                (None, entry)
+            };
+
+        let (host, port) = Self::split_host_and_port(address)?;
+
+        return Ok(Self {
+            host: host.to_string(),
+            port: port.unwrap_or(default_port),
+            transport_protocol,
+        });
+    }
+
+    #[cfg(feature = "kkdcp")]
+    fn parse_proxy_url(url: &str, url_rest: &str) -> Result<Self> {
+        let authority = url_rest.split('/').next().unwrap_or_default();
+        let (host, port) = Self::split_host_and_port(authority)?;
+
+        return Ok(Self {
+            host: host.to_string(),
+            port: port.unwrap_or(DEFAULT_HTTPS_PORT),
+            transport_protocol: Some(TransportProtocol::KKDCP(url.to_string())),
+        });
+    }
+
+    #[cfg(not(feature = "kkdcp"))]
+    fn parse_proxy_url(url: &str, _url_rest: &str) -> Result<Self> {
+        return Err(Error::ConfigError(format!(
+            "KDC proxy {} requires the kkdcp feature",
+            url
+        )));
+    }
+
+    fn split_host_and_port(address: &str) -> Result<(&str, Option<u16>)> {
+        if let Some(rest) = address.strip_prefix('[') {
+            let end = rest.find(']').ok_or_else(|| {
+                Error::ConfigError(format!("Invalid address {}", address))
+            })?;
+            let port = match rest[end + 1..].strip_prefix(':') {
+                Some(port) => Some(Self::parse_port(port)?),
+                None => None,
+            };
+            return Ok((&rest[..end], port));
+        }
+
+        // more than one colon is an IPv6 address without port
+        if address.matches(':').count() == 1 {
+            if let Some((host, port)) = address.split_once(':') {
+                return Ok((host, Some(Self::parse_port(port)?)));
+            }
+        }
+
+        return Ok((address, None));
+    }
+
+    fn parse_port(port: &str) -> Result<u16> {
+        return port
+            .parse()
+            .map_err(|_| Error::ConfigError(format!("Invalid port {}", port)));
+    }
+
+    /// Resolves the host name into a socket address.
+    ///
+    /// # Errors
+    /// Returns [`Error::NameResolutionError`] if the name cannot be resolved.
+    pub fn resolve(&self) -> Result<SocketAddr> {
+        if let Ok(ip) = self.host.parse::<IpAddr>() {
+            return Ok(SocketAddr::new(ip, self.port));
+        }
+
+        let ips = dns_lookup::lookup_host(&self.host)
+            .map_err(|_| Error::NameResolutionError(self.host.clone()))?;
+
+        let ip = ips
+            .first()
+            .ok_or_else(|| Error::NameResolutionError(self.host.clone()))?;
+
+        return Ok(SocketAddr::new(*ip, self.port));
+    }
+}
+
+/// Port used by KDC proxies.
+#[cfg(feature = "kkdcp")]
+pub const DEFAULT_HTTPS_PORT: u16 = 443;
+
+/// Port used by kadmin servers.
+pub const DEFAULT_KADMIN_PORT: u16 = 749;
+
+/// Port used by kpasswd servers.
+pub const DEFAULT_KPASSWD_PORT: u16 = 464;
+
+/// Configuration of a realm, defined in the `[realms]` section.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RealmConfig {
+    pub kdcs: Vec<KdcEntry>,
+    pub master_kdcs: Vec<KdcEntry>,
+    pub admin_servers: Vec<KdcEntry>,
+    pub kpasswd_servers: Vec<KdcEntry>,
+    pub default_domain: Option<String>,
+}
+
+impl RealmConfig {
+    /// Servers that should receive the password change requests. As MIT,
+    /// if no kpasswd server is specified, the admin servers are used.
+    pub fn kpasswd_servers_or_admin_servers(&self) -> Vec<KdcEntry> {
+        if !self.kpasswd_servers.is_empty() {
+            return self.kpasswd_servers.clone();
+        }
+
+        return self
+            .admin_servers
+            .iter()
+            .map(|server| KdcEntry {
+                host: server.host.clone(),
+                port: DEFAULT_KPASSWD_PORT,
+                transport_protocol: server.transport_protocol.clone(),
+            })
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::transporter::DEFAULT_KERBEROS_PORT;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn parse_kdc_entries() {
+        assert_eq!(
+            KdcEntry::new("kdc.kingdom.hearts".to_string(), 88),
+            KdcEntry::parse("kdc.kingdom.hearts", 88).unwrap()
+        );
+        assert_eq!(
+            KdcEntry::new("kdc.kingdom.hearts".to_string(), 8888),
+            KdcEntry::parse("kdc.kingdom.hearts:8888", 88).unwrap()
+        );
+        assert_eq!(
+            KdcEntry::new("::1".to_string(), 88),
+            KdcEntry::parse("::1", 88).unwrap()
+        );
+        assert_eq!(
+            KdcEntry::new("::1".to_string(), 750),
+            KdcEntry::parse("[::1]:750", 88).unwrap()
+        );
+        assert_eq!(
+            KdcEntry {
+                host: "10.0.0.1".to_string(),
+                port: 88,
+                transport_protocol: Some(TransportProtocol::UDP),
+            },
+            KdcEntry::parse("udp/10.0.0.1", 88).unwrap()
+        );
+        assert_eq!(
+            Some(TransportProtocol::TCP),
+            KdcEntry::parse("tcp/kdc:88", 88)
+                .unwrap()
+                .transport_protocol
+        );
+    }
+
+    #[cfg(feature = "kkdcp")]
+    #[test]
+    fn parse_kdc_proxy_entries() {
+        assert_eq!(
+            KdcEntry {
+                host: "proxy.kingdom.hearts".to_string(),
+                port: DEFAULT_HTTPS_PORT,
+                transport_protocol: Some(TransportProtocol::KKDCP(
+                    "https://proxy.kingdom.hearts/KdcProxy".to_string()
+                )),
+            },
+            KdcEntry::parse("https://proxy.kingdom.hearts/KdcProxy", 88)
+                .unwrap()
+        );
+        assert_eq!(
+            8443,
+            KdcEntry::parse("https://proxy.kingdom.hearts:8443", 88)
+                .unwrap()
+                .port
+        );
+    }
+
+    #[test]
+    fn error_parsing_invalid_port() {
+        assert!(KdcEntry::parse("kdc:port", 88).is_err());
+        assert!(KdcEntry::parse("kdc:100000", 88).is_err());
+        assert!(KdcEntry::parse("[::1", 88).is_err());
+    }
+
+    #[test]
+    fn resolve_ip_entries() {
+        assert_eq!(
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 88),
+            KdcEntry::parse("10.0.0.1", DEFAULT_KERBEROS_PORT)
+                .unwrap()
+                .resolve()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn kpasswd_servers_fallback_to_admin_servers() {
+        let realm_config = RealmConfig {
+            admin_servers: vec![KdcEntry::new("admin".to_string(), 749)],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            vec![KdcEntry::new("admin".to_string(), DEFAULT_KPASSWD_PORT)],
+            realm_config.kpasswd_servers_or_admin_servers()
+        );
+    }
+}
@@ -0,0 +1,211 @@
+//! Parsers of the value formats used in krb5.conf relations.
+
+use chrono::Duration;
+use himmelblau_kerberos_constants::etypes::{
+    AES128_CTS_HMAC_SHA1_96, AES256_CTS_HMAC_SHA1_96, DES_CBC_CRC, DES_CBC_MD5,
+    RC4_HMAC, RC4_HMAC_EXP,
+};
+
+/// Parses a boolean in the formats accepted by MIT.
+pub(crate) fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "y" | "yes" | "true" | "t" | "1" | "on" => Some(true),
+        "n" | "no" | "false" | "nil" | "0" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+/// Parses a time duration. Accepted formats are a number of seconds
+/// (`36000`), `h:m[:s]` (`10:00`), units (`1d 10h30m`) or a combination
+/// of days and `h:m[:s]` (`1d 10:00:00`).
+pub(crate) fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+
+    if value.chars().all(|c| c.is_ascii_digit()) {
+        return Duration::try_seconds(value.parse().ok()?);
+    }
+
+    let mut seconds: i64 = 0;
+    let mut rest = value;
+
+    if rest.contains(':') {
+        if let Some(day_end) = rest.find('d') {
+            let days: i64 = rest[..day_end].trim().parse().ok()?;
+            seconds = days.checked_mul(86400)?;
+            rest = rest[day_end + 1..].trim();
+        }
+
+        let parts: Vec<&str> = rest.split(':').collect();
+        if parts.len() < 2 || parts.len() > 3 {
+            return None;
+        }
+        let multipliers = [3600, 60, 1];
+        for (part, multiplier) in parts.iter().zip(multipliers.iter()) {
+            let number: i64 = part.trim().parse().ok()?;
+            seconds = seconds.checked_add(number.checked_mul(*multiplier)?)?;
+        }
+        return Duration::try_seconds(seconds);
+    }
+
+    let mut number = String::new();
+    for c in rest.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        if c.is_ascii_whitespace() {
+            continue;
+        }
+
+        let multiplier = match c {
+            'd' => 86400,
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+        let n: i64 = number.parse().ok()?;
+        seconds = seconds.checked_add(n.checked_mul(multiplier)?)?;
+        number.clear();
+    }
+
+    if !number.is_empty() {
+        return None;
+    }
+
+    return Duration::try_seconds(seconds);
+}
+
+const DEFAULT_ENCTYPES: [i32; 3] =
+    [AES256_CTS_HMAC_SHA1_96, AES128_CTS_HMAC_SHA1_96, RC4_HMAC];
+
+/// Parses a list of encryption types, separated by spaces or commas.
+/// Names, family names (`aes`, `rc4`, `des`), `DEFAULT` and numbers are
+/// accepted. A `-` prefix removes the types from the list.
+/// Unknown names are ignored.
+pub(crate) fn parse_enctypes(value: &str) -> Vec<i32> {
+    let mut etypes: Vec<i32> = Vec::new();
+
+    for name in value
+        .split(|c: char| c == ',' || c.is_ascii_whitespace())
+        .filter(|name| !name.is_empty())
+    {
+        let (remove, name) = match name.strip_prefix('-') {
+            Some(name) => (true, name),
+            None => (false, name.strip_prefix('+').unwrap_or(name)),
+        };
+
+        for etype in enctype_name_to_etypes(name) {
+            if remove {
+                etypes.retain(|e| *e != etype);
+            } else if !etypes.contains(&etype) {
+                etypes.push(etype);
+            }
+        }
+    }
+
+    return etypes;
+}
+
+fn enctype_name_to_etypes(name: &str) -> Vec<i32> {
+    if let Ok(etype) = name.parse::<i32>() {
+        return vec![etype];
+    }
+
+    match name.to_lowercase().as_str() {
+        "default" => DEFAULT_ENCTYPES.to_vec(),
+        "aes" => vec![AES256_CTS_HMAC_SHA1_96, AES128_CTS_HMAC_SHA1_96],
+        "rc4" => vec![RC4_HMAC],
+        "des" => vec![DES_CBC_CRC, DES_CBC_MD5],
+        "aes256-cts-hmac-sha1-96" | "aes256-cts" | "aes256-sha1" => {
+            vec![AES256_CTS_HMAC_SHA1_96]
+        }
+        "aes128-cts-hmac-sha1-96" | "aes128-cts" | "aes128-sha1" => {
+            vec![AES128_CTS_HMAC_SHA1_96]
+        }
+        "arcfour-hmac" | "rc4-hmac" | "arcfour-hmac-md5" => vec![RC4_HMAC],
+        "arcfour-hmac-exp" | "rc4-hmac-exp" | "arcfour-hmac-md5-exp" => {
+            vec![RC4_HMAC_EXP]
+        }
+        "des-cbc-crc" => vec![DES_CBC_CRC],
+        "des-cbc-md5" => vec![DES_CBC_MD5],
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_booleans() {
+        for value in ["y", "yes", "true", "t", "1", "on", "TRUE", " Yes "] {
+            assert_eq!(Some(true), parse_bool(value));
+        }
+
+        for value in ["n", "no", "false", "nil", "0", "off", "False"] {
+            assert_eq!(Some(false), parse_bool(value));
+        }
+
+        assert_eq!(None, parse_bool("maybe"));
+    }
+
+    #[test]
+    fn parse_durations() {
+        assert_eq!(Some(Duration::seconds(36000)), parse_duration("36000"));
+        assert_eq!(Some(Duration::hours(10)), parse_duration("10h"));
+        assert_eq!(Some(Duration::days(7)), parse_duration("7d"));
+        assert_eq!(
+            Some(Duration::hours(10) + Duration::minutes(30)),
+            parse_duration("10h30m")
+        );
+        assert_eq!(
+            Some(
+                Duration::days(1) + Duration::hours(10) + Duration::seconds(5)
+            ),
+            parse_duration("1d 10h 5s")
+        );
+        assert_eq!(
+            Some(Duration::hours(10) + Duration::minutes(5)),
+            parse_duration("10:05")
+        );
+        assert_eq!(
+            Some(Duration::days(1) + Duration::hours(2) + Duration::seconds(3)),
+            parse_duration("1d 02:00:03")
+        );
+        assert_eq!(None, parse_duration(""));
+        assert_eq!(None, parse_duration("10x"));
+        assert_eq!(None, parse_duration("10h5"));
+        assert_eq!(None, parse_duration("99999999999999999999"));
+        assert_eq!(None, parse_duration("9999999999999999d"));
+    }
+
+    #[test]
+    fn parse_enctype_names() {
+        assert_eq!(
+            vec![AES256_CTS_HMAC_SHA1_96, AES128_CTS_HMAC_SHA1_96, RC4_HMAC],
+            parse_enctypes("aes256-cts-hmac-sha1-96 aes128-cts rc4-hmac")
+        );
+        assert_eq!(
+            vec![AES256_CTS_HMAC_SHA1_96, RC4_HMAC],
+            parse_enctypes("aes256-cts,arcfour-hmac-md5, unknown-type")
+        );
+        assert_eq!(vec![RC4_HMAC, 18], parse_enctypes("23 18"));
+    }
+
+    #[test]
+    fn parse_enctype_families_and_removals() {
+        assert_eq!(
+            vec![AES256_CTS_HMAC_SHA1_96, AES128_CTS_HMAC_SHA1_96],
+            parse_enctypes("DEFAULT -rc4")
+        );
+        assert_eq!(
+            vec![AES128_CTS_HMAC_SHA1_96, RC4_HMAC],
+            parse_enctypes("aes rc4 -aes256-cts")
+        );
+    }
+}
@@ -95,6 +95,14 @@ impl Credential {
         return self.client_part.encrypted_pa_data.as_ref();
     }
 
+    /// Copy of the credential whose service is stated in `srealm`. The
+    /// ticket is not modified.
+    pub(crate) fn with_srealm(&self, srealm: Realm) -> Self {
+        let mut credential = self.clone();
+        credential.client_part.srealm = srealm;
+        return credential;
+    }
+
     /// Saves the credential into a file by using the ccache format, used by Linux.
     pub fn save_into_ccache_file(self, path: &str) -> Result<()> {
         return CredentialWarehouse::from(self).save_into_ccache_file(path);
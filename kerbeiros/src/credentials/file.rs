@@ -34,9 +34,11 @@ impl<'a> CredentialFileConverter<'a> {
     }
 
     fn save_data_to_file(&self, data: &[u8]) -> Result<()> {
-        let mut fp = File::create(self.path).map_err(|_| Error::IOError)?;
+        let mut fp = File::create(self.path)
+            .map_err(|error| Error::IOError(error.into()))?;
 
-        fp.write_all(data).map_err(|_| Error::IOError)?;
+        fp.write_all(data)
+            .map_err(|error| Error::IOError(error.into()))?;
 
         return Ok(());
     }
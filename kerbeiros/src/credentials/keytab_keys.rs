@@ -0,0 +1,293 @@
+use crate::{Error, Result};
+use himmelblau_kerberos_asn1::{Asn1Object, EncTicketPart, Ticket};
+use himmelblau_kerberos_constants::etypes::{
+    AES128_CTS_HMAC_SHA1_96, AES256_CTS_HMAC_SHA1_96, RC4_HMAC,
+};
+use himmelblau_kerberos_constants::key_usages::KEY_USAGE_AS_REP_TICKET;
+use himmelblau_kerberos_crypto::{new_kerberos_cipher, Key};
+use himmelblau_kerberos_keytab::{KeyBlock, Keytab, KeytabEntry};
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::convert::TryInto;
+use std::fs;
+use std::path::Path;
+
+/// Key of a keytab entry, along with its version number.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct KeytabKey {
+    pub kvno: u32,
+    pub key: Key,
+}
+
+/// Keys of a principal stored in a keytab, sorted from the newest to the
+/// oldest version.
+pub(crate) struct KeytabKeys {
+    keys: Vec<KeytabKey>,
+}
+
+impl KeytabKeys {
+    /// Selects the keys of `username@realm` whose encryption type is in
+    /// `etypes`. Entries with unsupported encryption types are ignored.
+    pub fn new(
+        keytab: &Keytab,
+        realm: &str,
+        username: &str,
+        etypes: &HashSet<i32>,
+    ) -> Self {
+        let mut keys: Vec<KeytabKey> = keytab
+            .entries
+            .iter()
+            .filter(|entry| is_entry_of(entry, realm, username))
+            .filter_map(|entry| {
+                let key = key_from_key_block(&entry.key)?;
+                if !etypes.contains(&key.etypes()[0]) {
+                    return None;
+                }
+                return Some(KeytabKey {
+                    kvno: entry.vno.unwrap_or(entry.vno8 as u32),
+                    key,
+                });
+            })
+            .collect();
+
+        keys.sort_by_key(|k| Reverse(k.kvno));
+        return Self { keys };
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.keys.is_empty();
+    }
+
+    pub fn etypes(&self) -> HashSet<i32> {
+        return self.keys.iter().map(|k| k.key.etypes()[0]).collect();
+    }
+
+    /// Encryption types of the keys, without duplicates, in the order of
+    /// the keys.
+    pub fn ordered_etypes(&self) -> Vec<i32> {
+        let mut etypes = Vec::new();
+        for keytab_key in self.keys.iter() {
+            let etype = keytab_key.key.etypes()[0];
+            if !etypes.contains(&etype) {
+                etypes.push(etype);
+            }
+        }
+        return etypes;
+    }
+
+    /// Keys to try for pre-authentication, following the order of the
+    /// encryption types advertised by the KDC.
+    pub fn for_etypes(&self, etypes: &[i32]) -> Vec<&KeytabKey> {
+        let mut keys = Vec::new();
+        for etype in etypes.iter() {
+            keys.extend(
+                self.keys.iter().filter(|k| k.key.etypes()[0] == *etype),
+            );
+        }
+        return keys;
+    }
+
+    /// Keys that may decrypt a reply encrypted with `etype`. If `kvno`
+    /// is specified, the keys of that version come first.
+    pub fn for_reply(&self, etype: i32, kvno: Option<u32>) -> Vec<&KeytabKey> {
+        let mut keys: Vec<&KeytabKey> = self
+            .keys
+            .iter()
+            .filter(|k| k.key.etypes()[0] == etype)
+            .collect();
+        if let Some(kvno) = kvno {
+            keys.sort_by_key(|k| k.kvno != kvno);
+        }
+        return keys;
+    }
+
+    /// Decrypts the encrypted part of a ticket issued for the principal
+    /// of the keys.
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidTicket`] if no key can decrypt the ticket.
+    pub fn decrypt_ticket(&self, ticket: &Ticket) -> Result<EncTicketPart> {
+        return Ok(self.decrypt_ticket_with_key(ticket)?.0);
+    }
+
+    /// Decrypts the encrypted part of a ticket, returning also the key
+    /// that decrypted it.
+    pub fn decrypt_ticket_with_key(
+        &self,
+        ticket: &Ticket,
+    ) -> Result<(EncTicketPart, &Key)> {
+        let etype = ticket.enc_part.etype;
+        for keytab_key in self.for_reply(etype, ticket.enc_part.kvno) {
+            let cipher = new_kerberos_cipher(etype)?;
+            let plaintext = match cipher.decrypt(
+                keytab_key.key.as_bytes(),
+                KEY_USAGE_AS_REP_TICKET,
+                &ticket.enc_part.cipher,
+            ) {
+                Ok(plaintext) => plaintext,
+                Err(_) => continue,
+            };
+            return Ok((EncTicketPart::parse(&plaintext)?.1, &keytab_key.key));
+        }
+
+        return Err(Error::InvalidTicket(format!(
+            "no key of the keytab decrypts the ticket for {}@{}",
+            ticket.sname, ticket.realm
+        )));
+    }
+}
+
+/// Reads and parses a keytab file.
+pub(crate) fn load_keytab(path: &Path) -> Result<Keytab> {
+    let data = fs::read(path).map_err(|error| Error::IOError(error.into()))?;
+    let (_, keytab) = Keytab::parse(&data).map_err(|_| {
+        Error::KeytabError(format!("invalid keytab {}", path.display()))
+    })?;
+    return Ok(keytab);
+}
+
+fn is_entry_of(entry: &KeytabEntry, realm: &str, username: &str) -> bool {
+    let entry_realm = String::from_utf8_lossy(&entry.realm.data);
+    let entry_name = entry
+        .components
+        .iter()
+        .map(|component| String::from_utf8_lossy(&component.data))
+        .collect::<Vec<_>>()
+        .join("/");
+
+    return entry_realm.eq_ignore_ascii_case(realm)
+        && entry_name.eq_ignore_ascii_case(username);
+}
+
+fn key_from_key_block(key_block: &KeyBlock) -> Option<Key> {
+    let keyvalue = key_block.keyvalue.as_slice();
+    match key_block.keytype as i32 {
+        RC4_HMAC => return Some(Key::RC4Key(keyvalue.try_into().ok()?)),
+        AES128_CTS_HMAC_SHA1_96 => {
+            return Some(Key::AES128Key(keyvalue.try_into().ok()?))
+        }
+        AES256_CTS_HMAC_SHA1_96 => {
+            return Some(Key::AES256Key(keyvalue.try_into().ok()?))
+        }
+        _ => return None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::requesters::test_kdc::keytab_entry;
+    use chrono::{Duration, Utc};
+    use himmelblau_kerberos_asn1::{
+        EncryptedData, EncryptionKey, PrincipalName,
+    };
+    use himmelblau_kerberos_constants::principal_names::NT_SRV_HST;
+
+    fn aes256_key(byte: u8) -> Key {
+        return Key::AES256Key([byte; 32]);
+    }
+
+    fn rc4_key(byte: u8) -> Key {
+        return Key::RC4Key([byte; 16]);
+    }
+
+    #[test]
+    fn select_keys_of_principal() {
+        let keytab = Keytab::new(
+            5,
+            2,
+            vec![
+                keytab_entry("mickey@KINGDOM.HEARTS", 1, &rc4_key(1)),
+                keytab_entry("minnie@KINGDOM.HEARTS", 3, &aes256_key(2)),
+                keytab_entry("mickey@DISNEY.WORLD", 3, &aes256_key(3)),
+                keytab_entry("mickey@KINGDOM.HEARTS", 2, &aes256_key(4)),
+                KeytabEntry {
+                    key: KeyBlock::new(3, vec![0; 8]),
+                    ..keytab_entry("mickey@KINGDOM.HEARTS", 3, &rc4_key(5))
+                },
+            ],
+        );
+        let etypes = [AES256_CTS_HMAC_SHA1_96, RC4_HMAC]
+            .iter()
+            .cloned()
+            .collect();
+
+        let keys =
+            KeytabKeys::new(&keytab, "kingdom.hearts", "Mickey", &etypes);
+
+        assert_eq!(
+            vec![
+                &KeytabKey {
+                    kvno: 2,
+                    key: aes256_key(4)
+                },
+                &KeytabKey {
+                    kvno: 1,
+                    key: rc4_key(1)
+                },
+            ],
+            keys.for_etypes(&[AES256_CTS_HMAC_SHA1_96, RC4_HMAC])
+        );
+        assert_eq!(
+            vec![&KeytabKey {
+                kvno: 1,
+                key: rc4_key(1)
+            }],
+            keys.for_etypes(&[RC4_HMAC, AES128_CTS_HMAC_SHA1_96])
+        );
+    }
+
+    fn encrypt_ticket(kvno: u32, key: &Key) -> Ticket {
+        let enc_ticket_part = EncTicketPart {
+            key: EncryptionKey::new(RC4_HMAC, vec![1; 16]),
+            crealm: "KINGDOM.HEARTS".to_string(),
+            endtime: (Utc::now() + Duration::hours(1)).into(),
+            ..Default::default()
+        };
+        let etype = key.etypes()[0];
+        let cipher = new_kerberos_cipher(etype).unwrap();
+        return Ticket::new(
+            "KINGDOM.HEARTS".to_string(),
+            PrincipalName::new(NT_SRV_HST, "host".to_string()),
+            EncryptedData::new(
+                etype,
+                Some(kvno),
+                cipher
+                    .encrypt(
+                        key.as_bytes(),
+                        KEY_USAGE_AS_REP_TICKET,
+                        &enc_ticket_part.build(),
+                    )
+                    .unwrap(),
+            ),
+        );
+    }
+
+    #[test]
+    fn decrypt_ticket_with_key_of_its_kvno() {
+        let keytab = Keytab::new(
+            5,
+            2,
+            vec![
+                keytab_entry("host/castle@KINGDOM.HEARTS", 3, &aes256_key(3)),
+                keytab_entry("host/castle@KINGDOM.HEARTS", 2, &aes256_key(2)),
+            ],
+        );
+        let keys = KeytabKeys::new(
+            &keytab,
+            "KINGDOM.HEARTS",
+            "host/castle",
+            &[AES256_CTS_HMAC_SHA1_96].iter().cloned().collect(),
+        );
+
+        let enc_ticket_part = keys
+            .decrypt_ticket(&encrypt_ticket(2, &aes256_key(2)))
+            .unwrap();
+        assert_eq!("KINGDOM.HEARTS", enc_ticket_part.crealm);
+
+        assert!(matches!(
+            keys.decrypt_ticket(&encrypt_ticket(2, &aes256_key(4))),
+            Err(Error::InvalidTicket(_))
+        ));
+    }
+}
@@ -1,9 +1,15 @@
 use super::super::credential::*;
+use crate::messages::{
+    AsRepValidator, AsReqChecksumVerifier, EncodedAsReq, TgsRepValidator,
+};
 use crate::Result;
 use himmelblau_kerberos_asn1::{
-    AsRep, Asn1Object, EncAsRepPart, EtypeInfo2, KrbCredInfo, Ticket,
+    AsRep, Asn1Object, EncAsRepPart, EncTgsRepPart, EtypeInfo2, KrbCredInfo,
+    TgsRep, TgsReq, Ticket,
+};
+use himmelblau_kerberos_constants::key_usages::{
+    KEY_USAGE_AS_REP_ENC_PART, KEY_USAGE_TGS_REP_ENC_PART_SESSION_KEY,
 };
-use himmelblau_kerberos_constants::key_usages::KEY_USAGE_AS_REP_ENC_PART;
 use himmelblau_kerberos_constants::pa_data_types::PA_ETYPE_INFO2;
 use himmelblau_kerberos_crypto::new_kerberos_cipher;
 use himmelblau_kerberos_crypto::Key;
@@ -43,22 +49,41 @@ impl CredentialKrbInfoMapper {
         return (krb_cred_info, credential.ticket().clone());
     }
 
+    /// Decrypts the AS-REP received in response to `as_req` and, if it
+    /// is valid, creates the credential that it contains.
     pub fn kdc_rep_to_credential(
         key: &Key,
+        as_req: &EncodedAsReq,
         kdc_rep: AsRep,
     ) -> Result<Credential> {
-        let plaintext = match key {
+        let (reply_key, plaintext) = match key {
             Key::Secret(password) => {
                 Self::decrypt_enc_kdc_rep_part_with_password(
                     password, &kdc_rep,
                 )?
             }
-            cipher_key => Self::decrypt_enc_kdc_rep_part_with_cipher_key(
-                cipher_key, &kdc_rep,
-            )?,
+            cipher_key => (
+                cipher_key.as_bytes().to_vec(),
+                Self::decrypt_enc_kdc_rep_part_with_cipher_key(
+                    cipher_key, &kdc_rep,
+                )?,
+            ),
         };
 
         let (_, enc_kdc_rep_part) = EncAsRepPart::parse(&plaintext)?;
+        let protected = AsReqChecksumVerifier::verify(
+            as_req.raw(),
+            &enc_kdc_rep_part,
+            kdc_rep.enc_part.etype,
+            &reply_key,
+        )?;
+        AsRepValidator::validate(
+            as_req.as_req(),
+            &kdc_rep,
+            &enc_kdc_rep_part,
+            protected,
+            as_req.clock_skew(),
+        )?;
 
         return Ok(Credential::new(
             kdc_rep.crealm,
@@ -68,36 +93,73 @@ impl CredentialKrbInfoMapper {
         ));
     }
 
+    /// Decrypts the TGS-REP received in response to `tgs_req`, which was
+    /// sent with `tgt`, and, if it is valid, creates the credential that
+    /// it contains.
+    pub fn tgs_rep_to_credential(
+        tgt: &Credential,
+        tgs_req: &TgsReq,
+        tgs_rep: TgsRep,
+    ) -> Result<Credential> {
+        let session_key = tgt.key();
+        let cipher = new_kerberos_cipher(session_key.keytype)?;
+        let plaintext = cipher.decrypt(
+            &session_key.keyvalue,
+            KEY_USAGE_TGS_REP_ENC_PART_SESSION_KEY,
+            &tgs_rep.enc_part.cipher,
+        )?;
+
+        // Some KDCs use the tag of EncASRepPart, as allowed by RFC4120
+        let enc_tgs_rep_part = match EncTgsRepPart::parse(&plaintext) {
+            Ok((_, enc_tgs_rep_part)) => enc_tgs_rep_part,
+            Err(_) => EncAsRepPart::parse(&plaintext)?.1.into(),
+        };
+        TgsRepValidator::validate(tgs_req, tgt, &tgs_rep, &enc_tgs_rep_part)?;
+
+        return Ok(Credential::new(
+            tgs_rep.crealm,
+            tgs_rep.cname,
+            tgs_rep.ticket,
+            enc_tgs_rep_part.into(),
+        ));
+    }
+
+    /// Returns the key derived from the password along with the
+    /// decrypted data.
     fn decrypt_enc_kdc_rep_part_with_password(
         password: &str,
         kdc_rep: &AsRep,
-    ) -> Result<Vec<u8>> {
+    ) -> Result<(Vec<u8>, Vec<u8>)> {
         let cipher = new_kerberos_cipher(kdc_rep.enc_part.etype)?;
-        return Ok(cipher.generate_key_from_string_and_decrypt(
+        let reply_key = cipher.generate_key_from_string(
             password,
-            &Self::as_rep_encryption_salt(kdc_rep),
+            &Self::as_rep_encryption_salt(kdc_rep)?,
+        );
+        let plaintext = cipher.decrypt(
+            &reply_key,
             KEY_USAGE_AS_REP_ENC_PART,
             &kdc_rep.enc_part.cipher,
-        )?);
+        )?;
+        return Ok((reply_key, plaintext));
     }
 
-    fn as_rep_encryption_salt(as_rep: &AsRep) -> Vec<u8> {
+    fn as_rep_encryption_salt(as_rep: &AsRep) -> Result<Vec<u8>> {
         if let Some(padata) = &as_rep.padata {
             for entry_data in padata.iter() {
                 if entry_data.padata_type == PA_ETYPE_INFO2 {
-                    let etype_info =
-                        EtypeInfo2::parse(&entry_data.padata_value).unwrap().1;
+                    let (_, etype_info) =
+                        EtypeInfo2::parse(&entry_data.padata_value)?;
 
                     for info2_entry in etype_info.iter() {
                         if let Some(salt) = &info2_entry.salt {
-                            return salt.as_bytes().to_vec();
+                            return Ok(salt.as_bytes().to_vec());
                         }
                     }
                 }
             }
         }
 
-        return Vec::new();
+        return Ok(Vec::new());
     }
 
     fn decrypt_enc_kdc_rep_part_with_cipher_key(
@@ -128,7 +190,7 @@ impl CredentialKrbInfoMapper {
         key: &Key,
         kdc_rep: &AsRep,
     ) -> Result<Vec<u8>> {
-        let cipher = new_kerberos_cipher(key.etypes()[0]).unwrap();
+        let cipher = new_kerberos_cipher(key.etypes()[0])?;
         return Ok(cipher.decrypt(
             key.as_bytes(),
             KEY_USAGE_AS_REP_ENC_PART,
@@ -142,12 +204,16 @@ impl CredentialKrbInfoMapper {
 mod test {
     use super::*;
     use chrono::prelude::*;
+    use chrono::Duration;
     use himmelblau_kerberos_asn1::*;
     use himmelblau_kerberos_constants::address_types::NETBIOS;
+    use himmelblau_kerberos_constants::checksum_types::HMAC_SHA1_96_AES256;
     use himmelblau_kerberos_constants::etypes::*;
+    use himmelblau_kerberos_constants::key_usages::KEY_USAGE_AS_REQ;
     use himmelblau_kerberos_constants::pa_data_types::*;
     use himmelblau_kerberos_constants::principal_names::*;
-    use himmelblau_kerberos_constants::ticket_flags;
+    use himmelblau_kerberos_constants::{kdc_options, ticket_flags};
+    use himmelblau_kerberos_crypto::checksum;
 
     #[test]
     fn convert_to_krb_info() {
@@ -323,6 +389,7 @@ mod test {
             credential,
             CredentialKrbInfoMapper::kdc_rep_to_credential(
                 &Key::Secret("Minnie1234".to_string()),
+                &encode_as_req(create_as_req_to_check_decryption()),
                 as_rep
             )
             .unwrap()
@@ -337,12 +404,8 @@ mod test {
         assert_eq!(
             credential,
             CredentialKrbInfoMapper::kdc_rep_to_credential(
-                &Key::AES256Key([
-                    0xd3, 0x30, 0x1f, 0x0f, 0x25, 0x39, 0xcc, 0x40, 0x26, 0xa5,
-                    0x69, 0xf8, 0xb7, 0xc3, 0x67, 0x15, 0xc8, 0xda, 0xef, 0x10,
-                    0x9f, 0xa3, 0xd8, 0xb2, 0xe1, 0x46, 0x16, 0xaa, 0xca, 0xb5,
-                    0x49, 0xfd
-                ]),
+                &Key::AES256Key(AES256_KEY_TO_CHECK_DECRYPTION),
+                &encode_as_req(create_as_req_to_check_decryption()),
                 as_rep
             )
             .unwrap()
@@ -364,12 +427,118 @@ mod test {
                     0x61, 0x7f, 0x72, 0xfd, 0xbc, 0x85, 0x1c, 0x45, 0x9a, 0x1c,
                     0x39, 0xbf, 0x83, 0x23, 0x56, 0x09
                 ]),
+                &encode_as_req(create_as_req_to_check_decryption()),
                 as_rep
             )
             .unwrap()
         );
     }
 
+    #[should_panic(expected = "Asn1Error")]
+    #[test]
+    fn decode_and_decrypt_enc_part_with_invalid_etype_info2() {
+        let mut as_rep = create_as_rep_aes256_to_decrypt();
+        as_rep.padata =
+            Some(vec![PaData::new(PA_ETYPE_INFO2, vec![0x30, 0x84])]);
+
+        CredentialKrbInfoMapper::kdc_rep_to_credential(
+            &Key::Secret("Minnie1234".to_string()),
+            &encode_as_req(create_as_req_to_check_decryption()),
+            as_rep,
+        )
+        .unwrap();
+    }
+
+    #[should_panic(expected = "InvalidKdcReply")]
+    #[test]
+    fn decode_and_decrypt_enc_part_of_other_request() {
+        let mut as_req = create_as_req_to_check_decryption();
+        as_req.req_body.nonce = 1;
+
+        CredentialKrbInfoMapper::kdc_rep_to_credential(
+            &Key::Secret("Minnie1234".to_string()),
+            &encode_as_req(as_req),
+            create_as_rep_aes256_to_decrypt(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn decode_and_decrypt_enc_part_with_req_checksum() {
+        let as_req = encode_as_req(create_as_req_to_check_decryption());
+        let as_rep = create_as_rep_with_req_checksum(as_req.raw());
+
+        let credential = CredentialKrbInfoMapper::kdc_rep_to_credential(
+            &Key::AES256Key(AES256_KEY_TO_CHECK_DECRYPTION),
+            &as_req,
+            as_rep,
+        )
+        .unwrap();
+
+        assert!(credential.encrypted_pa_data().is_some());
+    }
+
+    #[should_panic(expected = "checksum of the AS-REQ does not match")]
+    #[test]
+    fn decode_and_decrypt_enc_part_with_req_checksum_of_other_request() {
+        let mut as_req = create_as_req_to_check_decryption();
+        let as_rep = create_as_rep_with_req_checksum(
+            encode_as_req(as_req.clone()).raw(),
+        );
+        as_req.req_body.etypes.push(RC4_HMAC);
+
+        CredentialKrbInfoMapper::kdc_rep_to_credential(
+            &Key::AES256Key(AES256_KEY_TO_CHECK_DECRYPTION),
+            &encode_as_req(as_req),
+            as_rep,
+        )
+        .unwrap();
+    }
+
+    const AES256_KEY_TO_CHECK_DECRYPTION: [u8; 32] = [
+        0xd3, 0x30, 0x1f, 0x0f, 0x25, 0x39, 0xcc, 0x40, 0x26, 0xa5, 0x69, 0xf8,
+        0xb7, 0xc3, 0x67, 0x15, 0xc8, 0xda, 0xef, 0x10, 0x9f, 0xa3, 0xd8, 0xb2,
+        0xe1, 0x46, 0x16, 0xaa, 0xca, 0xb5, 0x49, 0xfd,
+    ];
+
+    /// Re-encrypts the AS-REP used to check the decryption, after adding
+    /// the checksum of `raw_as_req` to its encrypted part.
+    fn create_as_rep_with_req_checksum(raw_as_req: &[u8]) -> AsRep {
+        let mut as_rep = create_as_rep_aes256_to_decrypt();
+        let cipher = new_kerberos_cipher(AES256_CTS_HMAC_SHA1_96).unwrap();
+        let plaintext = cipher
+            .decrypt(
+                &AES256_KEY_TO_CHECK_DECRYPTION,
+                KEY_USAGE_AS_REP_ENC_PART,
+                &as_rep.enc_part.cipher,
+            )
+            .unwrap();
+        let (_, mut enc_as_rep_part) = EncAsRepPart::parse(&plaintext).unwrap();
+
+        let req_checksum = Checksum {
+            cksumtype: HMAC_SHA1_96_AES256,
+            checksum: checksum(
+                HMAC_SHA1_96_AES256,
+                &AES256_KEY_TO_CHECK_DECRYPTION,
+                KEY_USAGE_AS_REQ,
+                raw_as_req,
+            )
+            .unwrap(),
+        };
+        enc_as_rep_part.flags.flags |= ticket_flags::ENC_PA_REP;
+        enc_as_rep_part.encrypted_pa_data =
+            Some(vec![PaData::new(PA_REQ_ENC_PA_REP, req_checksum.build())]);
+
+        as_rep.enc_part.cipher = cipher
+            .encrypt(
+                &AES256_KEY_TO_CHECK_DECRYPTION,
+                KEY_USAGE_AS_REP_ENC_PART,
+                &enc_as_rep_part.build(),
+            )
+            .unwrap();
+        return as_rep;
+    }
+
     fn create_as_rep_aes256_to_decrypt() -> AsRep {
         let encrypted_data = EncryptedData::new(
             AES256_CTS_HMAC_SHA1_96,
@@ -412,8 +581,8 @@ mod test {
         );
 
         let ticket = Ticket::new(
-            Realm::from("fake"),
-            PrincipalName::new(NT_SRV_INST, KerberosString::from("fake")),
+            Realm::from("KINGDOM.HEARTS"),
+            krbtgt_principal_name(),
             EncryptedData::new(AES256_CTS_HMAC_SHA1_96, None, vec![0x9]),
         );
 
@@ -431,8 +600,8 @@ mod test {
 
         let as_rep = AsRep::new(
             Some(padata),
-            Realm::from("fake"),
-            PrincipalName::new(NT_PRINCIPAL, KerberosString::from("fake")),
+            Realm::from("KINGDOM.HEARTS"),
+            PrincipalName::new(NT_PRINCIPAL, KerberosString::from("mickey")),
             ticket,
             encrypted_data,
         );
@@ -441,8 +610,8 @@ mod test {
 
     fn create_credential_to_check_decryption() -> Credential {
         let ticket = Ticket::new(
-            Realm::from("fake"),
-            PrincipalName::new(NT_SRV_INST, KerberosString::from("fake")),
+            Realm::from("KINGDOM.HEARTS"),
+            krbtgt_principal_name(),
             EncryptedData::new(AES256_CTS_HMAC_SHA1_96, None, vec![0x9]),
         );
 
@@ -473,10 +642,6 @@ mod test {
 
         let kerb_time = Utc.with_ymd_and_hms(2019, 4, 18, 06, 00, 31).unwrap();
 
-        let mut sname =
-            PrincipalName::new(NT_SRV_INST, KerberosString::from("krbtgt"));
-        sname.push(KerberosString::from("KINGDOM.HEARTS"));
-
         let mut encrypted_pa_datas = MethodData::default();
         encrypted_pa_datas.push(PaData::new(
             PA_SUPPORTED_ENCTYPES,
@@ -505,7 +670,7 @@ mod test {
                     .into(),
             ),
             srealm: Realm::from("KINGDOM.HEARTS"),
-            sname,
+            sname: krbtgt_principal_name(),
             caddr: None,
             encrypted_pa_data: Some(encrypted_pa_datas),
         };
@@ -516,10 +681,41 @@ mod test {
         )]);
 
         return Credential::new(
-            Realm::from("fake"),
-            PrincipalName::new(NT_PRINCIPAL, KerberosString::from("fake")),
+            Realm::from("KINGDOM.HEARTS"),
+            PrincipalName::new(NT_PRINCIPAL, KerberosString::from("mickey")),
             ticket,
             enc_as_rep_part,
         );
     }
+
+    fn krbtgt_principal_name() -> PrincipalName {
+        let mut sname =
+            PrincipalName::new(NT_SRV_INST, KerberosString::from("krbtgt"));
+        sname.push(KerberosString::from("KINGDOM.HEARTS"));
+        return sname;
+    }
+
+    /// The replies were recorded long ago, so their times are not
+    /// checked against the clock.
+    fn encode_as_req(as_req: AsReq) -> EncodedAsReq {
+        return EncodedAsReq::new(as_req).with_clock_skew(Duration::MAX);
+    }
+
+    fn create_as_req_to_check_decryption() -> AsReq {
+        let mut as_req = AsReq::default();
+        as_req.req_body.kdc_options =
+            (kdc_options::FORWARDABLE | kdc_options::RENEWABLE).into();
+        as_req.req_body.cname = Some(PrincipalName::new(
+            NT_PRINCIPAL,
+            KerberosString::from("mickey"),
+        ));
+        as_req.req_body.realm = Realm::from("KINGDOM.HEARTS");
+        as_req.req_body.sname = Some(krbtgt_principal_name());
+        as_req.req_body.till =
+            Utc.with_ymd_and_hms(2037, 9, 13, 2, 48, 5).unwrap().into();
+        as_req.req_body.rtime = Some(as_req.req_body.till.clone());
+        as_req.req_body.nonce = 104645460;
+        as_req.req_body.etypes = vec![AES256_CTS_HMAC_SHA1_96];
+        return as_req;
+    }
 }
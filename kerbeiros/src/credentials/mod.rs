@@ -11,5 +11,8 @@ pub use credential::*;
 mod credential_warehouse;
 pub use credential_warehouse::*;
 
+mod keytab_keys;
+pub(crate) use keytab_keys::{load_keytab, KeytabKeys};
+
 mod mappers;
 pub(crate) use mappers::CredentialKrbInfoMapper;
@@ -1,9 +1,13 @@
 //! Errors raised by this library
 
-use crate::messages::{AsRep, KrbError};
+use crate::messages::{AsRep, KdcError};
 use ascii::FromAsciiError;
+use himmelblau_kerberos_ccache::{Needed, ParseError};
+use std::fmt;
+use std::io;
 use std::result;
 use std::string::FromUtf8Error;
+use std::sync::Arc;
 use thiserror::Error;
 
 /// Result to wrap kerbeiros error.
@@ -18,7 +22,7 @@ pub enum Error {
 
     /// Error produced in the application of cryptographic algorithms.
     #[error("Cryptography error: {}", _0)]
-    CryptographyError(himmelblau_kerberos_crypto::Error),
+    CryptographyError(#[source] himmelblau_kerberos_crypto::Error),
 
     /// Invalid ascii string.
     #[error("Invalid ascii string")]
@@ -33,8 +37,8 @@ pub enum Error {
     InvalidMicroseconds(u32),
 
     /// Error in i/o operation.
-    #[error("Error in i/o operation")]
-    IOError,
+    #[error("Error in i/o operation: {}", _0)]
+    IOError(#[source] IoError),
 
     /// Invalid key
     #[error(
@@ -47,16 +51,16 @@ pub enum Error {
     InvalidKeyLength(usize),
 
     /// Received KRB-ERROR response.
-    #[error("Received {:?}", _0)]
-    KrbErrorResponse(KrbError),
+    #[error("Received KRB-ERROR: {}", _0)]
+    KrbErrorResponse(KdcError),
 
     /// Error resolving name.
     #[error("Error resolving name: {}", _0)]
     NameResolutionError(String),
 
     /// Error sending/receiving data over the network.
-    #[error("Network error")]
-    NetworkError,
+    #[error("Network error: {}", _0)]
+    NetworkError(#[source] IoError),
 
     /// No key was provided in order to decrypt the KDC response.
     #[error("No key was provided")]
@@ -72,7 +76,7 @@ pub enum Error {
 
     /// Error parsing AS-REP message.
     #[error("Error parsing AsRep: {}", _1)]
-    ParseAsRepError(AsRep, Box<Error>),
+    ParseAsRepError(AsRep, #[source] Box<Error>),
 
     /// The type of the principal name was not specified.
     #[error("Undefined type of principal name: {}", _0)]
@@ -87,8 +91,96 @@ pub enum Error {
     NoAddress,
 
     /// Error parsing binary data
-    #[error("Error parsing binary data")]
-    BinaryParseError,
+    #[error("Error parsing binary data: {}", _0)]
+    BinaryParseError(String),
+
+    /// Syntax error in a profile (krb5.conf) file, with the line number.
+    #[error("Error parsing profile at line {}: {}", _0, _1)]
+    ProfileParseError(usize, String),
+
+    /// The reply of the KDC does not match the request.
+    #[error("Invalid KDC reply: {}", _0)]
+    InvalidKdcReply(String),
+
+    /// The credential used to request a service ticket is not a TGT.
+    #[error("Invalid ticket granting ticket: {}", _0)]
+    InvalidTgt(String),
+
+    /// The ticket cannot be decrypted or does not match the expected one.
+    #[error("Invalid ticket: {}", _0)]
+    InvalidTicket(String),
+
+    /// The AP-REQ received by a service is not valid.
+    #[error("Invalid AP-REQ: {}", _0)]
+    InvalidApReq(String),
+
+    /// The authorization data cannot be decoded, has an invalid checksum
+    /// or includes a critical element that is not understood.
+    #[error("Invalid authorization data: {}", _0)]
+    InvalidAuthorizationData(String),
+
+    /// A GSS-API token is malformed or does not match the security
+    /// context.
+    #[error("Invalid GSS-API token: {}", _0)]
+    InvalidGssToken(String),
+
+    /// The authenticator of an AP-REQ was already received by the service.
+    #[error("Request is a replay: {}", _0)]
+    ReplayError(String),
+
+    /// The keytab cannot be read or has no usable key.
+    #[error("Keytab error: {}", _0)]
+    KeytabError(String),
+
+    /// The HTTP header does not include a valid Negotiate token.
+    #[error("Invalid Negotiate header: {}", _0)]
+    InvalidNegotiateHeader(String),
+
+    /// Invalid or unavailable configuration.
+    #[error("Configuration error: {}", _0)]
+    ConfigError(String),
+}
+
+/// Wrapper of [`io::Error`] that can be cloned and compared, in order to
+/// be included in [`Error`]. Two errors are equal if they have the same
+/// kind and message.
+#[derive(Debug, Clone)]
+pub struct IoError(Arc<io::Error>);
+
+impl IoError {
+    pub fn kind(&self) -> io::ErrorKind {
+        return self.0.kind();
+    }
+
+    /// The wrapped i/o error.
+    pub fn inner(&self) -> &io::Error {
+        return &self.0;
+    }
+}
+
+impl PartialEq for IoError {
+    fn eq(&self, other: &Self) -> bool {
+        return self.kind() == other.kind()
+            && self.0.to_string() == other.0.to_string();
+    }
+}
+
+impl fmt::Display for IoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return self.0.fmt(f);
+    }
+}
+
+impl std::error::Error for IoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        return self.0.source();
+    }
+}
+
+impl From<io::Error> for IoError {
+    fn from(error: io::Error) -> Self {
+        return Self(Arc::new(error));
+    }
 }
 
 impl From<himmelblau_kerberos_crypto::Error> for Error {
@@ -121,9 +213,23 @@ impl From<himmelblau_kerberos_asn1::Error> for Error {
     }
 }
 
-impl<E> From<himmelblau_kerberos_ccache::Error<E>> for Error {
-    fn from(_error: himmelblau_kerberos_ccache::Error<E>) -> Self {
-        return Self::BinaryParseError;
+impl<I> From<himmelblau_kerberos_ccache::Error<ParseError<I>>> for Error {
+    fn from(error: himmelblau_kerberos_ccache::Error<ParseError<I>>) -> Self {
+        let description = match error {
+            himmelblau_kerberos_ccache::Error::Incomplete(Needed::Size(
+                size,
+            )) => {
+                format!("incomplete data, {} more bytes needed", size)
+            }
+            himmelblau_kerberos_ccache::Error::Incomplete(Needed::Unknown) => {
+                "incomplete data".to_string()
+            }
+            himmelblau_kerberos_ccache::Error::Error(error)
+            | himmelblau_kerberos_ccache::Error::Failure(error) => {
+                format!("invalid data, {}", error.code.description())
+            }
+        };
+        return Self::BinaryParseError(description);
     }
 }
 
@@ -135,7 +241,7 @@ mod tests {
     fn test_kerberos_error() {
         match produce_invalid_network_error() {
             Err(kerberos_error) => match kerberos_error {
-                Error::NetworkError => {}
+                Error::NetworkError(_) => {}
                 _ => {
                     unreachable!();
                 }
@@ -146,8 +252,68 @@ mod tests {
         }
     }
 
+    #[test]
+    fn network_error_keeps_io_error() {
+        let error = Error::NetworkError(
+            io::Error::new(io::ErrorKind::TimedOut, "timed out").into(),
+        );
+
+        match &error {
+            Error::NetworkError(io_error) => {
+                assert_eq!(io::ErrorKind::TimedOut, io_error.kind());
+            }
+            _ => unreachable!(),
+        }
+        assert_eq!("Network error: timed out", error.to_string());
+        assert!(std::error::Error::source(&error).is_some());
+    }
+
+    #[test]
+    fn krb_error_response_display() {
+        let error = Error::KrbErrorResponse(KdcError::new(
+            himmelblau_kerberos_asn1::KrbError {
+                error_code: 24,
+                ..Default::default()
+            },
+        ));
+
+        assert_eq!(
+            "Received KRB-ERROR: KDC_ERR_PREAUTH_FAILED (24): \
+             Pre-authentication information was invalid",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn ccache_parse_error_keeps_description() {
+        let error = Error::from(
+            himmelblau_kerberos_ccache::CCache::parse(&[0x05, 0x04])
+                .unwrap_err(),
+        );
+
+        assert_eq!(
+            Error::BinaryParseError("invalid data, End of file".to_string()),
+            error
+        );
+    }
+
+    #[test]
+    fn parse_as_rep_error_keeps_source() {
+        let error = Error::ParseAsRepError(
+            AsRep::default(),
+            Box::new(Error::NoKeyProvided),
+        );
+
+        assert_eq!(
+            "No key was provided",
+            std::error::Error::source(&error).unwrap().to_string()
+        );
+    }
+
     fn produce_invalid_network_error() -> Result<()> {
-        Err(Error::NetworkError)?;
+        Err(Error::NetworkError(
+            io::Error::from(io::ErrorKind::ConnectionRefused).into(),
+        ))?;
         unreachable!();
     }
 }
@@ -0,0 +1,426 @@
+use super::context::{random_key, random_seq_number, GssContext};
+use super::gss_checksum::{
+    ChannelBindings, GssChecksum, GSS_CHECKSUM_TYPE, GSS_C_DELEG_FLAG,
+    GSS_C_MUTUAL_FLAG,
+};
+use super::initial_context_token::{
+    InitialContextToken, TOK_ID_AP_REP, TOK_ID_AP_REQ,
+};
+use crate::acceptor::{AcceptedApReq, ApReqAcceptor};
+use crate::{Error, Result};
+use himmelblau_kerberos_asn1::{ApReq, Asn1Object, EncryptedData, KrbCred};
+use himmelblau_kerberos_constants::etypes::{NO_ENCRYPTION, RC4_HMAC};
+use himmelblau_kerberos_constants::key_usages::KEY_USAGE_KRB_CRED_ENC_PART;
+use himmelblau_kerberos_crypto::new_kerberos_cipher;
+
+/// Acceptor of security contexts of the Kerberos V5 mechanism, as
+/// described in RFC4121, which verifies the initial tokens sent by the
+/// clients with an [`ApReqAcceptor`].
+///
+/// When the initiator requests mutual authentication, the acceptor
+/// replies with an AP-REP token that includes a new subkey, except for
+/// RC4 sessions, as Windows does, and its initial sequence number.
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+///
+/// let acceptor = ApReqAcceptor::from_keytab_file("/etc/krb5.keytab").unwrap();
+/// let gss_acceptor = GssAcceptor::new(acceptor);
+///
+/// # let token = vec![];
+/// let (context, reply_token) = gss_acceptor.accept(&token).unwrap();
+/// println!("Authenticated {}@{}", context.cname(), context.crealm());
+/// if let Some(reply_token) = reply_token {
+///     // Send the reply token to the client
+/// }
+/// ```
+pub struct GssAcceptor {
+    acceptor: ApReqAcceptor,
+    channel_bindings: Option<ChannelBindings>,
+}
+
+impl GssAcceptor {
+    pub fn new(acceptor: ApReqAcceptor) -> Self {
+        return Self {
+            acceptor,
+            channel_bindings: None,
+        };
+    }
+
+    pub fn acceptor(&self) -> &ApReqAcceptor {
+        return &self.acceptor;
+    }
+
+    pub fn acceptor_mut(&mut self) -> &mut ApReqAcceptor {
+        return &mut self.acceptor;
+    }
+
+    /// Sets the channel bindings of the secure channel where the contexts
+    /// are established. Initiators that do not send channel bindings are
+    /// still accepted.
+    pub fn set_channel_bindings(
+        &mut self,
+        channel_bindings: Option<ChannelBindings>,
+    ) {
+        self.channel_bindings = channel_bindings;
+    }
+
+    /// Accepts the initial token of a context, and returns the context
+    /// and the reply token for the initiator, if the authentication is
+    /// mutual.
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidGssToken`] if the token or its
+    /// authenticator checksum are not valid, or the channel bindings do
+    /// not match, and the errors of [`ApReqAcceptor::accept_ap_req`].
+    pub fn accept(
+        &self,
+        token: &[u8],
+    ) -> Result<(GssContext, Option<Vec<u8>>)> {
+        let token = InitialContextToken::parse(token)?;
+        if token.tok_id != TOK_ID_AP_REQ {
+            return Err(Error::InvalidGssToken(
+                "initial token is not an AP-REQ".to_string(),
+            ));
+        }
+        let (_, ap_req) = ApReq::parse(&token.message)?;
+        let accepted = self.acceptor.accept_ap_req(&ap_req)?;
+
+        let gss_checksum = match &accepted.authenticator().cksum {
+            Some(cksum) if cksum.cksumtype == GSS_CHECKSUM_TYPE => {
+                GssChecksum::parse(&cksum.checksum)?
+            }
+            _ => {
+                return Err(Error::InvalidGssToken(
+                    "authenticator without GSS checksum".to_string(),
+                ))
+            }
+        };
+        self.verify_channel_bindings(&gss_checksum)?;
+
+        let mut flags = gss_checksum.flags;
+        if accepted.is_mutual_required() {
+            flags |= GSS_C_MUTUAL_FLAG;
+        }
+
+        let delegated_credential = match &gss_checksum.delegation {
+            Some(raw_krb_cred) if flags & GSS_C_DELEG_FLAG != 0 => {
+                Some(decrypt_krb_cred(raw_krb_cred, &accepted)?)
+            }
+            _ => {
+                flags &= !GSS_C_DELEG_FLAG;
+                None
+            }
+        };
+
+        let initiator_seq_number = accepted.seq_number().unwrap_or_default();
+        let mut acceptor_subkey = None;
+        let mut seq_number = initiator_seq_number;
+        let mut reply_token = None;
+        if flags & GSS_C_MUTUAL_FLAG != 0 {
+            let session_key = accepted.session_key();
+            if session_key.keytype != RC4_HMAC {
+                acceptor_subkey = Some(random_key(session_key));
+            }
+            seq_number = random_seq_number();
+            let ap_rep =
+                accepted.ap_rep(acceptor_subkey.clone(), Some(seq_number))?;
+            reply_token = Some(
+                InitialContextToken::new(TOK_ID_AP_REP, ap_rep.build()).build(),
+            );
+        }
+
+        let context = GssContext {
+            initiator: false,
+            flags,
+            crealm: accepted.crealm().clone(),
+            cname: accepted.cname().clone(),
+            srealm: accepted.srealm().clone(),
+            sname: accepted.sname().clone(),
+            session_key: accepted.session_key().clone(),
+            initiator_subkey: accepted.subkey().cloned(),
+            acceptor_subkey,
+            send_seq_number: seq_number as u64,
+            recv_seq_number: initiator_seq_number as u64,
+            delegated_credential,
+            accepted_ap_req: Some(accepted),
+        };
+        return Ok((context, reply_token));
+    }
+
+    fn verify_channel_bindings(
+        &self,
+        gss_checksum: &GssChecksum,
+    ) -> Result<()> {
+        if gss_checksum.channel_bindings == [0; 16] {
+            return Ok(());
+        }
+
+        let expected = self
+            .channel_bindings
+            .as_ref()
+            .map(ChannelBindings::hash)
+            .unwrap_or_default();
+        if gss_checksum.channel_bindings != expected {
+            return Err(Error::InvalidGssToken(
+                "channel bindings do not match".to_string(),
+            ));
+        }
+        return Ok(());
+    }
+}
+
+/// Decrypts the delegated KRB-CRED, which can be encrypted with the
+/// session key or the subkey of the initiator, or not encrypted at all.
+/// The returned KRB-CRED has the `enc-part` decrypted.
+fn decrypt_krb_cred(
+    raw_krb_cred: &[u8],
+    accepted: &AcceptedApReq,
+) -> Result<KrbCred> {
+    let (_, mut krb_cred) = KrbCred::parse(raw_krb_cred)?;
+    if krb_cred.enc_part.etype == NO_ENCRYPTION {
+        return Ok(krb_cred);
+    }
+
+    let cipher = new_kerberos_cipher(krb_cred.enc_part.etype)?;
+    let keys = std::iter::once(accepted.session_key())
+        .chain(accepted.subkey())
+        .filter(|key| key.keytype == krb_cred.enc_part.etype);
+    for key in keys {
+        if let Ok(plaintext) = cipher.decrypt(
+            &key.keyvalue,
+            KEY_USAGE_KRB_CRED_ENC_PART,
+            &krb_cred.enc_part.cipher,
+        ) {
+            krb_cred.enc_part =
+                EncryptedData::new(NO_ENCRYPTION, None, plaintext);
+            return Ok(krb_cred);
+        }
+    }
+
+    return Err(Error::InvalidGssToken(
+        "delegated credentials cannot be decrypted".to_string(),
+    ));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gss::test_context::{
+        create_acceptor, create_credential, create_credential_with_pac, mickey,
+    };
+    use crate::gss::{GssInitiator, GSS_C_REPLAY_FLAG};
+    use himmelblau_kerberos_asn1::{ApRep, EncKrbCredPart, EncryptionKey};
+    use himmelblau_kerberos_constants::etypes::{
+        AES128_CTS_HMAC_SHA1_96, AES256_CTS_HMAC_SHA1_96,
+    };
+    use himmelblau_kerberos_pac::{
+        Pac, PacClientInfo, PacInfoBuffer, PAC_CLIENT_INFO,
+    };
+
+    #[test]
+    fn establish_context_with_mutual_authentication() {
+        let mut initiator =
+            GssInitiator::new(create_credential(), GSS_C_MUTUAL_FLAG);
+        let token = initiator.initial_token().unwrap();
+
+        let (acceptor_context, reply_token) =
+            create_acceptor().accept(&token).unwrap();
+        let initiator_context =
+            initiator.finish(reply_token.as_deref()).unwrap();
+
+        assert!(initiator_context.is_initiator());
+        assert!(!acceptor_context.is_initiator());
+        assert_eq!(&mickey(), acceptor_context.cname());
+        assert_eq!(GSS_C_MUTUAL_FLAG, acceptor_context.flags());
+        assert!(acceptor_context.acceptor_subkey().is_some());
+        assert_eq!(
+            initiator_context.context_key(),
+            acceptor_context.context_key()
+        );
+        assert_eq!(
+            initiator_context.initiator_subkey(),
+            acceptor_context.initiator_subkey()
+        );
+        assert_eq!(
+            initiator_context.send_seq_number(),
+            acceptor_context.recv_seq_number()
+        );
+        assert_eq!(
+            initiator_context.recv_seq_number(),
+            acceptor_context.send_seq_number()
+        );
+    }
+
+    #[test]
+    fn error_with_ap_rep_of_other_etype() {
+        let mut initiator =
+            GssInitiator::new(create_credential(), GSS_C_MUTUAL_FLAG);
+        let token = initiator.initial_token().unwrap();
+
+        let (_, reply_token) = create_acceptor().accept(&token).unwrap();
+        let mut reply =
+            InitialContextToken::parse(&reply_token.unwrap()).unwrap();
+        let (_, mut ap_rep) = ApRep::parse(&reply.message).unwrap();
+        ap_rep.enc_part.etype = AES128_CTS_HMAC_SHA1_96;
+        reply.message = ap_rep.build();
+
+        assert!(matches!(
+            initiator.finish(Some(&reply.build())),
+            Err(Error::InvalidGssToken(_))
+        ));
+    }
+
+    #[test]
+    fn error_with_ap_rep_subkey_of_invalid_size() {
+        let mut initiator =
+            GssInitiator::new(create_credential(), GSS_C_MUTUAL_FLAG);
+        let token = initiator.initial_token().unwrap();
+
+        let (context, _) = create_acceptor().accept(&token).unwrap();
+        let subkey = EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 3]);
+        let ap_rep = context
+            .accepted_ap_req()
+            .unwrap()
+            .ap_rep(Some(subkey), Some(1))
+            .unwrap();
+        let reply = InitialContextToken::new(TOK_ID_AP_REP, ap_rep.build());
+
+        assert!(matches!(
+            initiator.finish(Some(&reply.build())),
+            Err(Error::InvalidGssToken(_))
+        ));
+    }
+
+    #[test]
+    fn accept_context_with_pac() {
+        let pac = Pac::new(vec![PacInfoBuffer::new(
+            PAC_CLIENT_INFO,
+            PacClientInfo::new(1, "mickey".to_string()).build(),
+        )]);
+        let mut initiator =
+            GssInitiator::new(create_credential_with_pac(pac), 0);
+        let token = initiator.initial_token().unwrap();
+
+        let (context, _) = create_acceptor().accept(&token).unwrap();
+
+        assert!(context.accepted_ap_req().unwrap().pac().is_some());
+    }
+
+    #[test]
+    fn establish_context_without_mutual_authentication() {
+        let mut initiator = GssInitiator::new(create_credential(), 0);
+        let token = initiator.initial_token().unwrap();
+
+        let (acceptor_context, reply_token) =
+            create_acceptor().accept(&token).unwrap();
+        let initiator_context = initiator.finish(None).unwrap();
+
+        assert_eq!(None, reply_token);
+        assert_eq!(None, acceptor_context.acceptor_subkey());
+        assert_eq!(
+            initiator_context.initiator_subkey().unwrap(),
+            acceptor_context.context_key()
+        );
+        assert_eq!(
+            acceptor_context.send_seq_number(),
+            acceptor_context.recv_seq_number()
+        );
+    }
+
+    #[test]
+    fn delegate_credentials() {
+        let krb_cred = KrbCred::new(
+            vec![create_credential().ticket().clone()],
+            EncryptedData::new(
+                NO_ENCRYPTION,
+                None,
+                EncKrbCredPart::default().build(),
+            ),
+        );
+        let mut initiator =
+            GssInitiator::new(create_credential(), GSS_C_MUTUAL_FLAG);
+        initiator.set_delegated_credential(Some(krb_cred.clone()));
+        let token = initiator.initial_token().unwrap();
+
+        let (context, _) = create_acceptor().accept(&token).unwrap();
+
+        assert_eq!(GSS_C_DELEG_FLAG | GSS_C_MUTUAL_FLAG, context.flags());
+        assert_eq!(Some(&krb_cred), context.delegated_credential());
+    }
+
+    #[test]
+    fn accept_context_with_channel_bindings() {
+        let bindings = ChannelBindings::new(b"tls-server-end-point:".to_vec());
+        let mut initiator =
+            GssInitiator::new(create_credential(), GSS_C_MUTUAL_FLAG);
+        initiator.set_channel_bindings(Some(bindings.clone()));
+        let token = initiator.initial_token().unwrap();
+
+        let mut acceptor = create_acceptor();
+        acceptor.set_channel_bindings(Some(bindings));
+
+        acceptor.accept(&token).unwrap();
+    }
+
+    #[test]
+    fn error_with_other_channel_bindings() {
+        let mut initiator =
+            GssInitiator::new(create_credential(), GSS_C_MUTUAL_FLAG);
+        initiator.set_channel_bindings(Some(ChannelBindings::new(vec![1])));
+        let token = initiator.initial_token().unwrap();
+
+        let mut acceptor = create_acceptor();
+        acceptor.set_channel_bindings(Some(ChannelBindings::new(vec![2])));
+
+        assert_eq!(
+            Err(Error::InvalidGssToken(
+                "channel bindings do not match".to_string()
+            )),
+            acceptor.accept(&token).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn protect_messages_of_context() {
+        let mut initiator = GssInitiator::new(
+            create_credential(),
+            GSS_C_MUTUAL_FLAG | GSS_C_REPLAY_FLAG,
+        );
+        let token = initiator.initial_token().unwrap();
+        let (acceptor_context, reply_token) =
+            create_acceptor().accept(&token).unwrap();
+        let initiator_context =
+            initiator.finish(reply_token.as_deref()).unwrap();
+
+        let mut initiator_protector = initiator_context.message_protector();
+        let mut acceptor_protector = acceptor_context.message_protector();
+
+        let token = initiator_protector.wrap(b"request", true).unwrap();
+        assert_eq!(
+            (b"request".to_vec(), true),
+            acceptor_protector.unwrap(&token).unwrap()
+        );
+        let token = acceptor_protector.get_mic(b"response").unwrap();
+        initiator_protector.verify_mic(b"response", &token).unwrap();
+    }
+
+    #[test]
+    fn error_with_reply_token_of_other_context() {
+        let mut initiator =
+            GssInitiator::new(create_credential(), GSS_C_MUTUAL_FLAG);
+        initiator.initial_token().unwrap();
+        let mut other_initiator =
+            GssInitiator::new(create_credential(), GSS_C_MUTUAL_FLAG);
+        let other_token = other_initiator.initial_token().unwrap();
+
+        let (_, reply_token) = create_acceptor().accept(&other_token).unwrap();
+
+        assert!(matches!(
+            initiator.finish(reply_token.as_deref()),
+            Err(Error::InvalidGssToken(_))
+        ));
+    }
+}
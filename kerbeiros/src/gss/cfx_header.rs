@@ -0,0 +1,162 @@
+use crate::{Error, Result};
+use std::convert::TryInto;
+
+/// `TOK_ID` of the MIC tokens of RFC4121.
+pub const TOK_ID_MIC: [u8; 2] = [0x04, 0x04];
+/// `TOK_ID` of the Wrap tokens of RFC4121.
+pub const TOK_ID_WRAP: [u8; 2] = [0x05, 0x04];
+
+/// The token was sent by the acceptor of the context.
+pub const CFX_FLAG_SENT_BY_ACCEPTOR: u8 = 0x01;
+/// The data of the Wrap token is encrypted.
+pub const CFX_FLAG_SEALED: u8 = 0x02;
+/// The token is protected with the subkey of the acceptor.
+pub const CFX_FLAG_ACCEPTOR_SUBKEY: u8 = 0x04;
+
+pub(crate) const CFX_HEADER_LENGTH: usize = 16;
+const FILLER: u8 = 0xff;
+
+/// Header of the per-message tokens of the Kerberos V5 mechanism,
+/// described in RFC4121, section 4.2.6.
+///
+/// # Definition
+/// ```text
+/// Octet no   Name        Description
+/// --------------------------------------------------------------
+/// 0..1     TOK_ID     Identification field. 04 04 (MIC) or 05 04 (Wrap).
+/// 2        Flags      Attributes field.
+/// 3..7     Filler     Contains five (MIC) or one (Wrap) octets of ff,
+///                     followed by EC and RRC in Wrap tokens.
+/// 4..5     EC         Extra count (Wrap only).
+/// 6..7     RRC        Right rotation count (Wrap only).
+/// 8..15    SND_SEQ    Sequence number field in big-endian order.
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CfxHeader {
+    pub tok_id: [u8; 2],
+    pub flags: u8,
+    pub ec: u16,
+    pub rrc: u16,
+    pub snd_seq: u64,
+}
+
+impl CfxHeader {
+    pub fn new(tok_id: [u8; 2], flags: u8, snd_seq: u64) -> Self {
+        return Self {
+            tok_id,
+            flags,
+            snd_seq,
+            ..Default::default()
+        };
+    }
+
+    pub fn is_wrap(&self) -> bool {
+        return self.tok_id == TOK_ID_WRAP;
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        let mut bytes = self.tok_id.to_vec();
+        bytes.push(self.flags);
+        if self.is_wrap() {
+            bytes.push(FILLER);
+            bytes.extend_from_slice(&self.ec.to_be_bytes());
+            bytes.extend_from_slice(&self.rrc.to_be_bytes());
+        } else {
+            bytes.extend_from_slice(&[FILLER; 5]);
+        }
+        bytes.extend_from_slice(&self.snd_seq.to_be_bytes());
+        return bytes;
+    }
+
+    /// Parses the header at the beginning of a token.
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidGssToken`] if the token is too short, or
+    /// its `TOK_ID` or filler are not valid.
+    pub fn parse(raw: &[u8]) -> Result<Self> {
+        if raw.len() < CFX_HEADER_LENGTH {
+            return Err(Error::InvalidGssToken("token too short".to_string()));
+        }
+
+        let mut header = Self {
+            tok_id: [raw[0], raw[1]],
+            flags: raw[2],
+            snd_seq: u64::from_be_bytes(raw[8..16].try_into().unwrap()),
+            ..Default::default()
+        };
+
+        let valid_filler = match header.tok_id {
+            TOK_ID_WRAP => {
+                header.ec = u16::from_be_bytes([raw[4], raw[5]]);
+                header.rrc = u16::from_be_bytes([raw[6], raw[7]]);
+                raw[3] == FILLER
+            }
+            TOK_ID_MIC => raw[3..8].iter().all(|byte| *byte == FILLER),
+            _ => {
+                return Err(Error::InvalidGssToken(format!(
+                    "unknown TOK_ID {:02x}{:02x}",
+                    raw[0], raw[1]
+                )))
+            }
+        };
+        if !valid_filler {
+            return Err(Error::InvalidGssToken("invalid filler".to_string()));
+        }
+
+        return Ok(header);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn build_and_parse_mic_header() {
+        let header = CfxHeader::new(
+            TOK_ID_MIC,
+            CFX_FLAG_SENT_BY_ACCEPTOR | CFX_FLAG_ACCEPTOR_SUBKEY,
+            0x01020304,
+        );
+        let raw = header.build();
+
+        assert_eq!(
+            vec![
+                0x04, 0x04, 0x05, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00,
+                0x00, 0x00, 0x01, 0x02, 0x03, 0x04
+            ],
+            raw
+        );
+        assert_eq!(header, CfxHeader::parse(&raw).unwrap());
+    }
+
+    #[test]
+    fn build_and_parse_wrap_header() {
+        let mut header = CfxHeader::new(TOK_ID_WRAP, CFX_FLAG_SEALED, 7);
+        header.ec = 16;
+        header.rrc = 28;
+        let raw = header.build();
+
+        assert_eq!(
+            vec![
+                0x05, 0x04, 0x02, 0xff, 0x00, 0x10, 0x00, 0x1c, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x07
+            ],
+            raw
+        );
+        assert_eq!(header, CfxHeader::parse(&raw).unwrap());
+    }
+
+    #[test]
+    fn error_parsing_header_with_invalid_filler() {
+        let raw = vec![
+            0x04, 0x04, 0x00, 0xff, 0xff, 0x00, 0xff, 0xff, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x01,
+        ];
+
+        assert_eq!(
+            Err(Error::InvalidGssToken("invalid filler".to_string())),
+            CfxHeader::parse(&raw)
+        );
+    }
+}
@@ -0,0 +1,132 @@
+use super::gss_checksum::{GSS_C_REPLAY_FLAG, GSS_C_SEQUENCE_FLAG};
+use super::message_protector::MessageProtector;
+use crate::acceptor::AcceptedApReq;
+use himmelblau_kerberos_asn1::{EncryptionKey, KrbCred, PrincipalName, Realm};
+use rand::{Rng, RngCore};
+
+/// Security context of the Kerberos V5 mechanism, established by a
+/// [`GssInitiator`](super::GssInitiator) or a
+/// [`GssAcceptor`](super::GssAcceptor).
+///
+/// The key used to protect the messages is the subkey of the acceptor, if
+/// it was sent in the AP-REP, or else the subkey of the initiator, or
+/// else the session key of the ticket, as described in RFC4121, section
+/// 2.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GssContext {
+    pub(crate) initiator: bool,
+    pub(crate) flags: u32,
+    pub(crate) crealm: Realm,
+    pub(crate) cname: PrincipalName,
+    pub(crate) srealm: Realm,
+    pub(crate) sname: PrincipalName,
+    pub(crate) session_key: EncryptionKey,
+    pub(crate) initiator_subkey: Option<EncryptionKey>,
+    pub(crate) acceptor_subkey: Option<EncryptionKey>,
+    pub(crate) send_seq_number: u64,
+    pub(crate) recv_seq_number: u64,
+    pub(crate) delegated_credential: Option<KrbCred>,
+    pub(crate) accepted_ap_req: Option<AcceptedApReq>,
+}
+
+impl GssContext {
+    /// Whether this side initiated the context.
+    pub fn is_initiator(&self) -> bool {
+        return self.initiator;
+    }
+
+    /// Flags of the context, such as `GSS_C_MUTUAL_FLAG`.
+    pub fn flags(&self) -> u32 {
+        return self.flags;
+    }
+
+    pub fn crealm(&self) -> &Realm {
+        return &self.crealm;
+    }
+
+    pub fn cname(&self) -> &PrincipalName {
+        return &self.cname;
+    }
+
+    pub fn srealm(&self) -> &Realm {
+        return &self.srealm;
+    }
+
+    pub fn sname(&self) -> &PrincipalName {
+        return &self.sname;
+    }
+
+    /// Session key of the ticket.
+    pub fn session_key(&self) -> &EncryptionKey {
+        return &self.session_key;
+    }
+
+    pub fn initiator_subkey(&self) -> Option<&EncryptionKey> {
+        return self.initiator_subkey.as_ref();
+    }
+
+    pub fn acceptor_subkey(&self) -> Option<&EncryptionKey> {
+        return self.acceptor_subkey.as_ref();
+    }
+
+    /// Key used to protect the messages of the context.
+    pub fn context_key(&self) -> &EncryptionKey {
+        return self
+            .acceptor_subkey
+            .as_ref()
+            .or(self.initiator_subkey.as_ref())
+            .unwrap_or(&self.session_key);
+    }
+
+    /// Sequence number of the next message sent.
+    pub fn send_seq_number(&self) -> u64 {
+        return self.send_seq_number;
+    }
+
+    /// Sequence number expected in the next message received.
+    pub fn recv_seq_number(&self) -> u64 {
+        return self.recv_seq_number;
+    }
+
+    /// Credentials delegated by the initiator, with the `enc-part`
+    /// decrypted, as the KRB-CRED files.
+    pub fn delegated_credential(&self) -> Option<&KrbCred> {
+        return self.delegated_credential.as_ref();
+    }
+
+    /// AP-REQ verified by the acceptor, with the authorization data of
+    /// the ticket.
+    pub fn accepted_ap_req(&self) -> Option<&AcceptedApReq> {
+        return self.accepted_ap_req.as_ref();
+    }
+
+    /// Creates the protector of the messages of the context, with its key
+    /// and sequence numbers. Replays are only detected if
+    /// `GSS_C_REPLAY_FLAG` or `GSS_C_SEQUENCE_FLAG` were requested.
+    pub fn message_protector(&self) -> MessageProtector {
+        let mut protector =
+            MessageProtector::new(self.context_key().clone(), self.initiator);
+        protector.set_acceptor_subkey(self.acceptor_subkey.is_some());
+        protector.set_send_seq_number(self.send_seq_number);
+        protector.set_recv_seq_number(self.recv_seq_number);
+        protector.set_detect_replays(
+            self.flags & (GSS_C_REPLAY_FLAG | GSS_C_SEQUENCE_FLAG) != 0,
+        );
+        return protector;
+    }
+}
+
+/// New random key with the same encryption type and size as `key`, used
+/// as subkey of the context.
+pub(crate) fn random_key(key: &EncryptionKey) -> EncryptionKey {
+    let mut keyvalue = vec![0; key.keyvalue.len()];
+    rand::thread_rng().fill_bytes(&mut keyvalue);
+    return EncryptionKey::new(key.keytype, keyvalue);
+}
+
+/// Random initial sequence number. As MIT Kerberos does, it is limited to
+/// 30 bits, in order to avoid problems with implementations that treat
+/// it as a signed number.
+pub(crate) fn random_seq_number() -> u32 {
+    return rand::thread_rng().gen::<u32>() & 0x3fffffff;
+}
@@ -0,0 +1,224 @@
+use crate::{Error, Result};
+use md5::{Digest, Md5};
+use std::convert::TryInto;
+
+/// Checksum type of the authenticator of the AP-REQ sent by the
+/// initiator of a security context.
+pub const GSS_CHECKSUM_TYPE: i32 = 0x8003;
+
+/// The initiator delegates its credentials to the acceptor.
+pub const GSS_C_DELEG_FLAG: u32 = 0x01;
+/// The acceptor must authenticate with an AP-REP.
+pub const GSS_C_MUTUAL_FLAG: u32 = 0x02;
+/// Replayed messages are detected.
+pub const GSS_C_REPLAY_FLAG: u32 = 0x04;
+/// Messages out of sequence are detected.
+pub const GSS_C_SEQUENCE_FLAG: u32 = 0x08;
+/// Messages can be encrypted.
+pub const GSS_C_CONF_FLAG: u32 = 0x10;
+/// Messages can be signed.
+pub const GSS_C_INTEG_FLAG: u32 = 0x20;
+/// The security context uses the DCE style of MS-KILE.
+pub const GSS_C_DCE_STYLE: u32 = 0x1000;
+/// The acceptor can only identify the client, not impersonate it.
+pub const GSS_C_IDENTIFY_FLAG: u32 = 0x2000;
+/// The acceptor can send extended errors.
+pub const GSS_C_EXTENDED_ERROR_FLAG: u32 = 0x4000;
+
+const BINDINGS_LENGTH: u32 = 16;
+const DELEGATION_OPTION: u16 = 1;
+const MIN_CHECKSUM_LENGTH: usize = 24;
+
+/// Content of the authenticator checksum of the Kerberos V5 mechanism,
+/// described in RFC4121, section 4.1.1.
+///
+/// # Definition
+/// ```text
+/// Octet   Name      Description
+/// ---------------------------------------------------------------
+/// 0..3    Lgth      Number of octets in Bnd field;  Represented
+///                   in little-endian order;  Contains value 16.
+/// 4..19   Bnd       Channel binding information
+/// 20..23  Flags     Four-octet context-establishment flags in
+///                   little-endian order
+/// 24..25  DlgOpt    The delegation option identifier (=1) in
+///                   little-endian order [optional]
+/// 26..27  Dlgth     The length of the Deleg field in little-endian
+///                   order [optional]
+/// 28..(n-1) Deleg   A KRB_CRED message (n = Dlgth + 28) [optional]
+/// n..last  Exts     Extensions [optional]
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GssChecksum {
+    /// MD5 hash of the channel bindings, or zeros if there are none.
+    pub channel_bindings: [u8; 16],
+    pub flags: u32,
+    /// Encoded KRB-CRED with the delegated credentials.
+    pub delegation: Option<Vec<u8>>,
+    pub extensions: Vec<u8>,
+}
+
+impl GssChecksum {
+    pub fn new(flags: u32) -> Self {
+        return Self {
+            flags,
+            ..Default::default()
+        };
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        let mut bytes = BINDINGS_LENGTH.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&self.channel_bindings);
+        bytes.extend_from_slice(&self.flags.to_le_bytes());
+        if let Some(delegation) = &self.delegation {
+            bytes.extend_from_slice(&DELEGATION_OPTION.to_le_bytes());
+            bytes.extend_from_slice(&(delegation.len() as u16).to_le_bytes());
+            bytes.extend_from_slice(delegation);
+        }
+        bytes.extend_from_slice(&self.extensions);
+        return bytes;
+    }
+
+    /// Parses the checksum. The delegated credentials are only read if
+    /// the `GSS_C_DELEG_FLAG` flag is set.
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidGssToken`] if the checksum is too short.
+    pub fn parse(raw: &[u8]) -> Result<Self> {
+        if raw.len() < MIN_CHECKSUM_LENGTH
+            || read_u32(raw, 0)? != BINDINGS_LENGTH
+        {
+            return Err(Error::InvalidGssToken(
+                "invalid authenticator checksum".to_string(),
+            ));
+        }
+
+        let mut checksum = Self {
+            channel_bindings: raw[4..20].try_into().unwrap(),
+            flags: read_u32(raw, 20)?,
+            ..Default::default()
+        };
+
+        let mut rest = &raw[MIN_CHECKSUM_LENGTH..];
+        if checksum.flags & GSS_C_DELEG_FLAG != 0 && rest.len() >= 4 {
+            let option = u16::from_le_bytes([rest[0], rest[1]]);
+            let length = u16::from_le_bytes([rest[2], rest[3]]) as usize;
+            if option != DELEGATION_OPTION || rest.len() < 4 + length {
+                return Err(Error::InvalidGssToken(
+                    "invalid delegated credentials".to_string(),
+                ));
+            }
+            checksum.delegation = Some(rest[4..4 + length].to_vec());
+            rest = &rest[4 + length..];
+        }
+        checksum.extensions = rest.to_vec();
+        return Ok(checksum);
+    }
+}
+
+fn read_u32(raw: &[u8], offset: usize) -> Result<u32> {
+    let bytes = raw
+        .get(offset..offset + 4)
+        .ok_or_else(|| Error::InvalidGssToken("truncated data".to_string()))?;
+    return Ok(u32::from_le_bytes(bytes.try_into().unwrap()));
+}
+
+/// Channel bindings of a security context, which tie it to the secure
+/// channel, such as TLS, where it is established.
+///
+/// # Definition
+/// ```c
+/// typedef struct gss_channel_bindings_struct {
+///     OM_uint32 initiator_addrtype;
+///     gss_buffer_desc initiator_address;
+///     OM_uint32 acceptor_addrtype;
+///     gss_buffer_desc acceptor_address;
+///     gss_buffer_desc application_data;
+/// } *gss_channel_bindings_t;
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ChannelBindings {
+    pub initiator_addrtype: u32,
+    pub initiator_address: Vec<u8>,
+    pub acceptor_addrtype: u32,
+    pub acceptor_address: Vec<u8>,
+    pub application_data: Vec<u8>,
+}
+
+impl ChannelBindings {
+    /// Creates the bindings that only include application data, such as
+    /// the `tls-server-end-point` bindings of RFC5929.
+    pub fn new(application_data: Vec<u8>) -> Self {
+        return Self {
+            application_data,
+            ..Default::default()
+        };
+    }
+
+    /// MD5 hash of the bindings, included in the authenticator checksum
+    /// as described in RFC4121, section 4.1.1.2.
+    pub fn hash(&self) -> [u8; 16] {
+        let mut md5 = Md5::new();
+        md5.update(self.initiator_addrtype.to_le_bytes());
+        md5.update((self.initiator_address.len() as u32).to_le_bytes());
+        md5.update(&self.initiator_address);
+        md5.update(self.acceptor_addrtype.to_le_bytes());
+        md5.update((self.acceptor_address.len() as u32).to_le_bytes());
+        md5.update(&self.acceptor_address);
+        md5.update((self.application_data.len() as u32).to_le_bytes());
+        md5.update(&self.application_data);
+        return md5.finalize().into();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn build_and_parse_checksum() {
+        let checksum = GssChecksum::new(GSS_C_MUTUAL_FLAG | GSS_C_INTEG_FLAG);
+        let raw = checksum.build();
+
+        assert_eq!(
+            vec![
+                0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x22, 0x00, 0x00, 0x00
+            ],
+            raw
+        );
+        assert_eq!(checksum, GssChecksum::parse(&raw).unwrap());
+    }
+
+    #[test]
+    fn build_and_parse_checksum_with_delegation() {
+        let mut checksum = GssChecksum::new(GSS_C_DELEG_FLAG);
+        checksum.channel_bindings = [7; 16];
+        checksum.delegation = Some(vec![0x76, 0x00]);
+        checksum.extensions = vec![1, 2, 3];
+        let raw = checksum.build();
+
+        assert_eq!([0x01, 0x00, 0x02, 0x00, 0x76, 0x00], raw[24..30]);
+        assert_eq!(checksum, GssChecksum::parse(&raw).unwrap());
+    }
+
+    #[test]
+    fn hash_channel_bindings() {
+        let bindings = ChannelBindings::new(b"tls-server-end-point:".to_vec());
+
+        let mut data = vec![0; 16];
+        data.extend_from_slice(&21u32.to_le_bytes());
+        data.extend_from_slice(b"tls-server-end-point:");
+        let expected: [u8; 16] = Md5::digest(&data).into();
+        assert_eq!(expected, bindings.hash());
+    }
+
+    #[test]
+    fn error_parsing_truncated_checksum() {
+        assert!(matches!(
+            GssChecksum::parse(&[0x10, 0, 0, 0, 1, 2]),
+            Err(Error::InvalidGssToken(_))
+        ));
+    }
+}
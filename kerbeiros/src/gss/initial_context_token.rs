@@ -0,0 +1,192 @@
+use crate::{Error, Result};
+
+/// Object identifier of the Kerberos V5 mechanism, 1.2.840.113554.1.2.2.
+pub const KRB5_MECH_OID: &[u8] =
+    &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x12, 0x01, 0x02, 0x02];
+
+/// Object identifier of the Kerberos V5 mechanism used by old versions of
+/// Windows, 1.2.840.48018.1.2.2.
+pub const MS_KRB5_MECH_OID: &[u8] =
+    &[0x2a, 0x86, 0x48, 0x82, 0xf7, 0x12, 0x01, 0x02, 0x02];
+
+/// `TOK_ID` of the tokens that contain an AP-REQ.
+pub const TOK_ID_AP_REQ: [u8; 2] = [0x01, 0x00];
+/// `TOK_ID` of the tokens that contain an AP-REP.
+pub const TOK_ID_AP_REP: [u8; 2] = [0x02, 0x00];
+/// `TOK_ID` of the tokens that contain a KRB-ERROR.
+pub const TOK_ID_KRB_ERROR: [u8; 2] = [0x03, 0x00];
+
+const APPLICATION_0_TAG: u8 = 0x60;
+const OID_TAG: u8 = 0x06;
+
+/// Token exchanged to establish a Kerberos V5 security context, framed as
+/// described in RFC2743, section 3.1, and RFC4121, section 4.1.
+///
+/// # Definition
+/// ```asn1
+/// InitialContextToken ::= [APPLICATION 0] IMPLICIT SEQUENCE {
+///         thisMech        MechType,
+///         innerToken      ANY DEFINED BY thisMech
+/// }
+/// ```
+/// For Kerberos V5, the `innerToken` is the `TOK_ID` followed by the
+/// AP-REQ, AP-REP or KRB-ERROR message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InitialContextToken {
+    /// Encoded object identifier of the mechanism, without tag and
+    /// length.
+    pub mech: Vec<u8>,
+    pub tok_id: [u8; 2],
+    pub message: Vec<u8>,
+}
+
+impl InitialContextToken {
+    /// Creates a token of the Kerberos V5 mechanism.
+    pub fn new(tok_id: [u8; 2], message: Vec<u8>) -> Self {
+        return Self {
+            mech: KRB5_MECH_OID.to_vec(),
+            tok_id,
+            message,
+        };
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        let mut inner = self.tok_id.to_vec();
+        inner.extend_from_slice(&self.message);
+        return build_gss_token(&self.mech, &inner);
+    }
+
+    /// Parses a token of the Kerberos V5 mechanism, with any of its
+    /// object identifiers.
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidGssToken`] if the framing is not valid or
+    /// the mechanism is not Kerberos V5.
+    pub fn parse(raw: &[u8]) -> Result<Self> {
+        let (mech, rest) = parse_gss_token(raw)?;
+        if mech != KRB5_MECH_OID && mech != MS_KRB5_MECH_OID {
+            return Err(Error::InvalidGssToken(
+                "mechanism is not Kerberos V5".to_string(),
+            ));
+        }
+
+        if rest.len() < 2 {
+            return Err(Error::InvalidGssToken("missing TOK_ID".to_string()));
+        }
+
+        return Ok(Self {
+            mech: mech.to_vec(),
+            tok_id: [rest[0], rest[1]],
+            message: rest[2..].to_vec(),
+        });
+    }
+}
+
+/// Frames the inner token of a mechanism as described in RFC2743,
+/// section 3.1.
+pub(crate) fn build_gss_token(mech: &[u8], inner: &[u8]) -> Vec<u8> {
+    let mut value = vec![OID_TAG];
+    value.append(&mut build_der_length(mech.len()));
+    value.extend_from_slice(mech);
+    value.extend_from_slice(inner);
+
+    let mut token = vec![APPLICATION_0_TAG];
+    token.append(&mut build_der_length(value.len()));
+    token.append(&mut value);
+    return token;
+}
+
+/// Object identifier of the mechanism and inner token of a token framed
+/// as described in RFC2743, section 3.1.
+pub(crate) fn parse_gss_token(raw: &[u8]) -> Result<(&[u8], &[u8])> {
+    let (value, _) = parse_tlv(raw, APPLICATION_0_TAG)?;
+    return parse_tlv(value, OID_TAG);
+}
+
+/// Value of the DER element of `raw` with the given tag, and the data
+/// after it. Only lengths of up to 4 bytes are supported.
+fn parse_tlv(raw: &[u8], tag: u8) -> Result<(&[u8], &[u8])> {
+    if raw.first() != Some(&tag) {
+        return Err(Error::InvalidGssToken(format!(
+            "expected tag 0x{:02x}",
+            tag
+        )));
+    }
+
+    let invalid_length =
+        || Error::InvalidGssToken("invalid DER length".to_string());
+    let first = *raw.get(1).ok_or_else(invalid_length)?;
+    let (length, start) = if first < 0x80 {
+        (first as usize, 2)
+    } else {
+        let size = (first & 0x7f) as usize;
+        if size == 0 || size > 4 || raw.len() < 2 + size {
+            return Err(invalid_length());
+        }
+        let length = raw[2..2 + size]
+            .iter()
+            .fold(0usize, |length, byte| (length << 8) | *byte as usize);
+        (length, 2 + size)
+    };
+
+    let end = start.checked_add(length).ok_or_else(invalid_length)?;
+    let value = raw.get(start..end).ok_or_else(invalid_length)?;
+    return Ok((value, &raw[end..]));
+}
+
+fn build_der_length(length: usize) -> Vec<u8> {
+    if length < 0x80 {
+        return vec![length as u8];
+    }
+    let bytes: Vec<u8> = (length as u32)
+        .to_be_bytes()
+        .iter()
+        .copied()
+        .skip_while(|byte| *byte == 0)
+        .collect();
+    let mut encoded = vec![0x80 | bytes.len() as u8];
+    encoded.extend_from_slice(&bytes);
+    return encoded;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn build_and_parse_token() {
+        let token = InitialContextToken::new(TOK_ID_AP_REP, vec![0x6f, 0x00]);
+        let raw = token.build();
+
+        assert_eq!(
+            vec![
+                0x60, 0x0f, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x12,
+                0x01, 0x02, 0x02, 0x02, 0x00, 0x6f, 0x00
+            ],
+            raw
+        );
+        assert_eq!(token, InitialContextToken::parse(&raw).unwrap());
+    }
+
+    #[test]
+    fn build_and_parse_token_with_long_message() {
+        let token = InitialContextToken::new(TOK_ID_AP_REQ, vec![1; 300]);
+        let raw = token.build();
+
+        assert_eq!([0x60, 0x82, 0x01, 0x39], raw[..4]);
+        assert_eq!(token, InitialContextToken::parse(&raw).unwrap());
+    }
+
+    #[test]
+    fn error_parsing_token_of_other_mechanism() {
+        let raw = vec![
+            0x60, 0x0c, 0x06, 0x06, 0x2b, 0x06, 0x01, 0x05, 0x05, 0x02, 0x01,
+            0x00, 0x6e, 0x00,
+        ];
+
+        assert!(matches!(
+            InitialContextToken::parse(&raw),
+            Err(Error::InvalidGssToken(_))
+        ));
+    }
+}
@@ -0,0 +1,276 @@
+use super::context::{random_key, random_seq_number, GssContext};
+use super::gss_checksum::{
+    ChannelBindings, GssChecksum, GSS_CHECKSUM_TYPE, GSS_C_DELEG_FLAG,
+    GSS_C_MUTUAL_FLAG,
+};
+use super::initial_context_token::{
+    InitialContextToken, TOK_ID_AP_REP, TOK_ID_AP_REQ, TOK_ID_KRB_ERROR,
+};
+use crate::acceptor::has_valid_size;
+use crate::credentials::Credential;
+use crate::{Error, Result};
+use himmelblau_kerberos_asn1::{
+    ApRep, ApReq, Asn1Object, Authenticator, Checksum, EncApRepPart,
+    EncryptedData, EncryptionKey, KrbCred, KrbError,
+};
+use himmelblau_kerberos_constants::ap_options::MUTUAL_REQUIRED;
+use himmelblau_kerberos_constants::etypes::NO_ENCRYPTION;
+use himmelblau_kerberos_constants::key_usages::{
+    KEY_USAGE_AP_REP_ENC_PART, KEY_USAGE_AP_REQ_AUTHEN,
+    KEY_USAGE_KRB_CRED_ENC_PART,
+};
+use himmelblau_kerberos_crypto::new_kerberos_cipher;
+
+/// Initiator of a security context of the Kerberos V5 mechanism, as
+/// described in RFC4121, which authenticates the client to a service with
+/// a ticket of it.
+///
+/// The initial token contains an AP-REQ whose authenticator includes a
+/// new subkey, the initial sequence number of the initiator and the
+/// requested flags. If `GSS_C_MUTUAL_FLAG` is requested, the reply token
+/// of the acceptor must be passed to [`GssInitiator::finish`], in order to
+/// verify the AP-REP and to retrieve the subkey of the acceptor.
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+/// use himmelblau_kerberos_asn1::PrincipalName;
+/// use himmelblau_kerberos_constants::principal_names::NT_SRV_HST;
+///
+/// let client = KerberosClient::from_env().unwrap();
+/// let mut service = PrincipalName::new(NT_SRV_HST, "HTTP".to_string());
+/// service.push("www.contoso.com".to_string());
+/// let credential = client.get_credential(&service, None).unwrap();
+///
+/// let mut initiator = GssInitiator::new(credential, GSS_C_MUTUAL_FLAG);
+/// let token = initiator.initial_token().unwrap();
+/// # let reply_token = vec![];
+/// // Send the token to the service and receive its reply
+/// let context = initiator.finish(Some(&reply_token)).unwrap();
+/// ```
+pub struct GssInitiator {
+    credential: Credential,
+    flags: u32,
+    channel_bindings: Option<ChannelBindings>,
+    delegated_credential: Option<KrbCred>,
+    authenticator: Option<Authenticator>,
+}
+
+impl GssInitiator {
+    /// Creates an initiator that authenticates with a ticket for the
+    /// service, requesting the given `GSS_C_*` flags.
+    pub fn new(credential: Credential, flags: u32) -> Self {
+        return Self {
+            credential,
+            flags: flags & !GSS_C_DELEG_FLAG,
+            channel_bindings: None,
+            delegated_credential: None,
+            authenticator: None,
+        };
+    }
+
+    pub fn flags(&self) -> u32 {
+        return self.flags;
+    }
+
+    /// Whether the acceptor must reply with an AP-REP.
+    pub fn is_mutual(&self) -> bool {
+        return self.flags & GSS_C_MUTUAL_FLAG != 0;
+    }
+
+    /// Sets the channel bindings of the secure channel used to establish
+    /// the context.
+    pub fn set_channel_bindings(
+        &mut self,
+        channel_bindings: Option<ChannelBindings>,
+    ) {
+        self.channel_bindings = channel_bindings;
+    }
+
+    /// Delegates credentials to the acceptor, usually a forwarded TGT,
+    /// which sets the `GSS_C_DELEG_FLAG` flag. The `enc-part` of the
+    /// KRB-CRED is encrypted with the session key if it is not encrypted.
+    pub fn set_delegated_credential(&mut self, krb_cred: Option<KrbCred>) {
+        match krb_cred {
+            Some(_) => self.flags |= GSS_C_DELEG_FLAG,
+            None => self.flags &= !GSS_C_DELEG_FLAG,
+        }
+        self.delegated_credential = krb_cred;
+    }
+
+    /// Creates the initial token of the context, with the AP-REQ.
+    pub fn initial_token(&mut self) -> Result<Vec<u8>> {
+        let session_key = self.credential.key();
+        let cipher = new_kerberos_cipher(session_key.keytype)?;
+
+        let mut gss_checksum = GssChecksum::new(self.flags);
+        if let Some(channel_bindings) = &self.channel_bindings {
+            gss_checksum.channel_bindings = channel_bindings.hash();
+        }
+        if let Some(krb_cred) = &self.delegated_credential {
+            gss_checksum.delegation =
+                Some(encrypt_krb_cred(krb_cred, session_key)?.build());
+        }
+
+        let authenticator = Authenticator {
+            crealm: self.credential.crealm().clone(),
+            cname: self.credential.cname().clone(),
+            cksum: Some(Checksum {
+                cksumtype: GSS_CHECKSUM_TYPE,
+                checksum: gss_checksum.build(),
+            }),
+            subkey: Some(random_key(session_key)),
+            seq_number: Some(random_seq_number()),
+            ..Default::default()
+        };
+
+        let raw_authenticator = authenticator.build();
+
+        let ap_options = if self.is_mutual() { MUTUAL_REQUIRED } else { 0 };
+        let ap_req = ApReq {
+            ap_options: ap_options.into(),
+            ticket: self.credential.ticket().clone(),
+            authenticator: EncryptedData::new(
+                session_key.keytype,
+                None,
+                cipher.encrypt(
+                    &session_key.keyvalue,
+                    KEY_USAGE_AP_REQ_AUTHEN,
+                    &raw_authenticator,
+                )?,
+            ),
+            ..Default::default()
+        };
+
+        // Keep the encoded authenticator, since the ctime of the AP-REP is
+        // compared with it, and the encoding drops the fractions of second.
+        let (_, authenticator) = Authenticator::parse(&raw_authenticator)?;
+        self.authenticator = Some(authenticator);
+        return Ok(
+            InitialContextToken::new(TOK_ID_AP_REQ, ap_req.build()).build()
+        );
+    }
+
+    /// Establishes the context. The reply token of the acceptor is
+    /// required if the authentication is mutual, and ignored otherwise.
+    ///
+    /// # Errors
+    /// Returns [`Error::KrbErrorResponse`] if the acceptor replied with a
+    /// KRB-ERROR, and [`Error::InvalidGssToken`] if the reply is not a
+    /// valid AP-REP for the initial token.
+    pub fn finish(self, reply_token: Option<&[u8]>) -> Result<GssContext> {
+        let authenticator = self.authenticator.as_ref().ok_or_else(|| {
+            Error::InvalidGssToken("initial token was not created".to_string())
+        })?;
+        let seq_number = authenticator.seq_number.unwrap_or_default();
+
+        let mut acceptor_subkey = None;
+        let mut recv_seq_number = seq_number;
+        if self.is_mutual() {
+            let reply_token = reply_token.ok_or_else(|| {
+                Error::InvalidGssToken("missing AP-REP token".to_string())
+            })?;
+            let enc_ap_rep_part = self.verify_ap_rep(reply_token)?;
+            acceptor_subkey = enc_ap_rep_part.subkey;
+            recv_seq_number = enc_ap_rep_part.seq_number.unwrap_or(seq_number);
+        }
+
+        let credential = self.credential;
+        return Ok(GssContext {
+            initiator: true,
+            flags: self.flags,
+            crealm: credential.crealm().clone(),
+            cname: credential.cname().clone(),
+            srealm: credential.srealm().clone(),
+            sname: credential.sname().clone(),
+            session_key: credential.key().clone(),
+            initiator_subkey: authenticator.subkey.clone(),
+            acceptor_subkey,
+            send_seq_number: seq_number as u64,
+            recv_seq_number: recv_seq_number as u64,
+            delegated_credential: None,
+            accepted_ap_req: None,
+        });
+    }
+
+    fn verify_ap_rep(&self, reply_token: &[u8]) -> Result<EncApRepPart> {
+        let token = InitialContextToken::parse(reply_token)?;
+        if token.tok_id == TOK_ID_KRB_ERROR {
+            let (_, krb_error) = KrbError::parse(&token.message)?;
+            return Err(Error::KrbErrorResponse(krb_error.into()));
+        }
+        if token.tok_id != TOK_ID_AP_REP {
+            return Err(Error::InvalidGssToken(
+                "reply token is not an AP-REP".to_string(),
+            ));
+        }
+
+        let (_, ap_rep) = ApRep::parse(&token.message)?;
+        let session_key = self.credential.key();
+        if ap_rep.enc_part.etype != session_key.keytype {
+            return Err(Error::InvalidGssToken(format!(
+                "AP-REP encrypted with etype {} instead of the etype {} of \
+                 the session key",
+                ap_rep.enc_part.etype, session_key.keytype
+            )));
+        }
+        let cipher = new_kerberos_cipher(session_key.keytype)?;
+        let plaintext = cipher
+            .decrypt(
+                &session_key.keyvalue,
+                KEY_USAGE_AP_REP_ENC_PART,
+                &ap_rep.enc_part.cipher,
+            )
+            .map_err(|_| {
+                Error::InvalidGssToken(
+                    "AP-REP cannot be decrypted with the session key"
+                        .to_string(),
+                )
+            })?;
+        let (_, enc_ap_rep_part) = EncApRepPart::parse(&plaintext)?;
+        if let Some(subkey) = &enc_ap_rep_part.subkey {
+            if !has_valid_size(subkey) {
+                return Err(Error::InvalidGssToken(format!(
+                    "AP-REP subkey of etype {} with invalid size {}",
+                    subkey.keytype,
+                    subkey.keyvalue.len()
+                )));
+            }
+        }
+
+        let authenticator = self.authenticator.as_ref().ok_or_else(|| {
+            Error::InvalidGssToken("initial token was not created".to_string())
+        })?;
+        if enc_ap_rep_part.ctime != authenticator.ctime
+            || enc_ap_rep_part.cusec != authenticator.cusec
+        {
+            return Err(Error::InvalidGssToken(
+                "AP-REP does not match the authenticator".to_string(),
+            ));
+        }
+        return Ok(enc_ap_rep_part);
+    }
+}
+
+/// Encrypts the `enc-part` of a KRB-CRED with the session key, unless it
+/// is already encrypted.
+fn encrypt_krb_cred(
+    krb_cred: &KrbCred,
+    session_key: &EncryptionKey,
+) -> Result<KrbCred> {
+    let mut krb_cred = krb_cred.clone();
+    if krb_cred.enc_part.etype == NO_ENCRYPTION {
+        let cipher = new_kerberos_cipher(session_key.keytype)?;
+        krb_cred.enc_part = EncryptedData::new(
+            session_key.keytype,
+            None,
+            cipher.encrypt(
+                &session_key.keyvalue,
+                KEY_USAGE_KRB_CRED_ENC_PART,
+                &krb_cred.enc_part.cipher,
+            )?,
+        );
+    }
+    return Ok(krb_cred);
+}
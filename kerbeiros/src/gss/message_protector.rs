@@ -0,0 +1,593 @@
+use super::cfx_header::{
+    CfxHeader, CFX_FLAG_ACCEPTOR_SUBKEY, CFX_FLAG_SEALED,
+    CFX_FLAG_SENT_BY_ACCEPTOR, CFX_HEADER_LENGTH, TOK_ID_MIC, TOK_ID_WRAP,
+};
+use super::rc4_tokens;
+use crate::{Error, Result};
+use himmelblau_kerberos_asn1::EncryptionKey;
+use himmelblau_kerberos_constants::etypes::RC4_HMAC;
+use himmelblau_kerberos_constants::key_usages::{
+    KEY_USAGE_ACCEPTOR_SEAL, KEY_USAGE_ACCEPTOR_SIGN, KEY_USAGE_INITIATOR_SEAL,
+    KEY_USAGE_INITIATOR_SIGN,
+};
+use himmelblau_kerberos_crypto::{
+    checksum, mandatory_checksum_type, new_kerberos_cipher,
+};
+
+/// Number of sequence numbers received that are tracked to detect
+/// replays, as in MIT.
+const REPLAY_WINDOW: u64 = 64;
+
+/// Protects the messages exchanged through a security context of the
+/// Kerberos V5 mechanism with the MIC and Wrap tokens described in
+/// RFC4121, section 4.2. With RC4_HMAC keys, the legacy tokens of
+/// RFC1964 are used instead, as described in RFC4757, since Windows does
+/// not use the tokens of RFC4121 for this encryption type.
+///
+/// It only requires the key of the context, so it can be used by
+/// protocols such as LDAP, NFS or SASL that establish the context by
+/// other means. The sequence numbers of the tokens sent are incremented
+/// in every token. As in MIT, the tokens received can arrive out of
+/// order, but the ones with a sequence number that was already seen, or
+/// that is behind the window of the last 64 sequence numbers, are
+/// rejected, unless the replay detection is disabled.
+///
+/// # Examples
+///
+/// ```
+/// use himmelblau_kerbeiros::*;
+/// use himmelblau_kerberos_asn1::EncryptionKey;
+/// use himmelblau_kerberos_constants::etypes::AES256_CTS_HMAC_SHA1_96;
+///
+/// let key = EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![7; 32]);
+/// let mut initiator = MessageProtector::new(key.clone(), true);
+/// let mut acceptor = MessageProtector::new(key, false);
+///
+/// let token = initiator.wrap(b"hello", true).unwrap();
+/// let (message, sealed) = acceptor.unwrap(&token).unwrap();
+/// assert_eq!(b"hello".to_vec(), message);
+/// assert!(sealed);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageProtector {
+    key: EncryptionKey,
+    initiator: bool,
+    acceptor_subkey: bool,
+    send_seq_number: u64,
+    recv_seq_number: u64,
+    recv_window: u64,
+    detect_replays: bool,
+    rrc: u16,
+}
+
+impl MessageProtector {
+    /// Creates a protector for the side of the context that is the
+    /// initiator, or the acceptor if `initiator` is false.
+    pub fn new(key: EncryptionKey, initiator: bool) -> Self {
+        return Self {
+            key,
+            initiator,
+            acceptor_subkey: false,
+            send_seq_number: 0,
+            recv_seq_number: 0,
+            recv_window: 0,
+            detect_replays: true,
+            rrc: 0,
+        };
+    }
+
+    pub fn key(&self) -> &EncryptionKey {
+        return &self.key;
+    }
+
+    pub fn is_initiator(&self) -> bool {
+        return self.initiator;
+    }
+
+    /// Indicates that the key is the subkey sent by the acceptor in the
+    /// AP-REP, which is signaled in the flags of the tokens.
+    pub fn set_acceptor_subkey(&mut self, acceptor_subkey: bool) {
+        self.acceptor_subkey = acceptor_subkey;
+    }
+
+    /// Sequence number of the next token sent.
+    pub fn send_seq_number(&self) -> u64 {
+        return self.send_seq_number;
+    }
+
+    pub fn set_send_seq_number(&mut self, seq_number: u64) {
+        self.send_seq_number = seq_number;
+    }
+
+    /// Sequence number expected in the next token received.
+    pub fn recv_seq_number(&self) -> u64 {
+        return self.recv_seq_number;
+    }
+
+    pub fn set_recv_seq_number(&mut self, seq_number: u64) {
+        self.recv_seq_number = seq_number;
+        self.recv_window = 0;
+    }
+
+    /// Whether the tokens received with repeated or too old sequence
+    /// numbers are rejected, true by default.
+    pub fn set_detect_replays(&mut self, detect_replays: bool) {
+        self.detect_replays = detect_replays;
+    }
+
+    /// Sets the right rotation count of the Wrap tokens sent, 0 by
+    /// default. DCE RPC uses a rotation of 28 bytes.
+    pub fn set_rrc(&mut self, rrc: u16) {
+        self.rrc = rrc;
+    }
+
+    /// Creates a MIC token for the message.
+    pub fn get_mic(&mut self, message: &[u8]) -> Result<Vec<u8>> {
+        if self.is_rc4() {
+            let token = rc4_tokens::get_mic(
+                &self.key,
+                self.initiator,
+                self.send_seq_number as u32,
+                message,
+            );
+            self.send_seq_number = self.send_seq_number.wrapping_add(1);
+            return Ok(token);
+        }
+
+        let header =
+            CfxHeader::new(TOK_ID_MIC, self.send_flags(), self.send_seq_number);
+        let mut token = header.build();
+
+        let mut data = message.to_vec();
+        data.extend_from_slice(&token);
+        token.append(&mut self.checksum(self.send_usage(false), &data)?);
+
+        self.send_seq_number = self.send_seq_number.wrapping_add(1);
+        return Ok(token);
+    }
+
+    /// Verifies the MIC token of a message.
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidGssToken`] if the token is not valid for
+    /// the message, and [`Error::ReplayError`] if the token was already
+    /// received.
+    pub fn verify_mic(&mut self, message: &[u8], token: &[u8]) -> Result<()> {
+        if self.is_rc4() {
+            let seq_number = rc4_tokens::verify_mic(
+                &self.key,
+                self.initiator,
+                message,
+                token,
+            )?;
+            return self.receive_seq_number(seq_number as u64);
+        }
+
+        let header = self.parse_header(token, TOK_ID_MIC)?;
+
+        let mut data = message.to_vec();
+        data.extend_from_slice(&token[..CFX_HEADER_LENGTH]);
+        let expected = self.checksum(self.recv_usage(false), &data)?;
+        if token[CFX_HEADER_LENGTH..] != expected[..] {
+            return Err(Error::InvalidGssToken("invalid MIC".to_string()));
+        }
+
+        return self.receive_seq_number(header.snd_seq);
+    }
+
+    /// Creates a Wrap token with the message, which is encrypted if
+    /// `confidential`, or only signed otherwise.
+    pub fn wrap(
+        &mut self,
+        message: &[u8],
+        confidential: bool,
+    ) -> Result<Vec<u8>> {
+        if self.is_rc4() {
+            let token = rc4_tokens::wrap(
+                &self.key,
+                self.initiator,
+                self.send_seq_number as u32,
+                message,
+                confidential,
+            );
+            self.send_seq_number = self.send_seq_number.wrapping_add(1);
+            return Ok(token);
+        }
+
+        let mut flags = self.send_flags();
+        if confidential {
+            flags |= CFX_FLAG_SEALED;
+        }
+        let mut header =
+            CfxHeader::new(TOK_ID_WRAP, flags, self.send_seq_number);
+
+        let mut data = message.to_vec();
+        if confidential {
+            data.extend_from_slice(&header.build());
+            let cipher = new_kerberos_cipher(self.key.keytype)?;
+            data = cipher.encrypt(
+                &self.key.keyvalue,
+                self.send_usage(true),
+                &data,
+            )?;
+        } else {
+            data.extend_from_slice(&header.build());
+            let mut checksum = self.checksum(self.send_usage(false), &data)?;
+            data.truncate(message.len());
+            header.ec = checksum.len() as u16;
+            data.append(&mut checksum);
+        }
+
+        header.rrc = self.rrc;
+        if !data.is_empty() {
+            let rotation = self.rrc as usize % data.len();
+            data.rotate_right(rotation);
+        }
+
+        let mut token = header.build();
+        token.append(&mut data);
+        self.send_seq_number = self.send_seq_number.wrapping_add(1);
+        return Ok(token);
+    }
+
+    /// Verifies a Wrap token and returns the message, and whether it was
+    /// encrypted.
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidGssToken`] if the token cannot be
+    /// decrypted or its checksum is not valid, and
+    /// [`Error::ReplayError`] if the token was already received.
+    pub fn unwrap(&mut self, token: &[u8]) -> Result<(Vec<u8>, bool)> {
+        if self.is_rc4() {
+            let (message, sealed, seq_number) =
+                rc4_tokens::unwrap(&self.key, self.initiator, token)?;
+            self.receive_seq_number(seq_number as u64)?;
+            return Ok((message, sealed));
+        }
+
+        let header = self.parse_header(token, TOK_ID_WRAP)?;
+        let mut data = token[CFX_HEADER_LENGTH..].to_vec();
+        if !data.is_empty() {
+            let rotation = header.rrc as usize % data.len();
+            data.rotate_left(rotation);
+        }
+
+        let mut unrotated_header = header.clone();
+        unrotated_header.rrc = 0;
+        let ec = header.ec as usize;
+
+        let sealed = header.flags & CFX_FLAG_SEALED != 0;
+        let message = if sealed {
+            let cipher = new_kerberos_cipher(self.key.keytype)?;
+            let mut plaintext = cipher
+                .decrypt(&self.key.keyvalue, self.recv_usage(true), &data)
+                .map_err(|_| {
+                    Error::InvalidGssToken(
+                        "Wrap token cannot be decrypted".to_string(),
+                    )
+                })?;
+            if plaintext.len() < ec + CFX_HEADER_LENGTH
+                || plaintext[plaintext.len() - CFX_HEADER_LENGTH..]
+                    != unrotated_header.build()[..]
+            {
+                return Err(Error::InvalidGssToken(
+                    "header of Wrap token was modified".to_string(),
+                ));
+            }
+            plaintext.truncate(plaintext.len() - CFX_HEADER_LENGTH - ec);
+            plaintext
+        } else {
+            if data.len() < ec {
+                return Err(Error::InvalidGssToken(
+                    "token too short".to_string(),
+                ));
+            }
+            let checksum = data.split_off(data.len() - ec);
+            unrotated_header.ec = 0;
+            let message_length = data.len();
+            data.append(&mut unrotated_header.build());
+            if checksum != self.checksum(self.recv_usage(false), &data)? {
+                return Err(Error::InvalidGssToken(
+                    "invalid checksum of Wrap token".to_string(),
+                ));
+            }
+            data.truncate(message_length);
+            data
+        };
+
+        self.receive_seq_number(header.snd_seq)?;
+        return Ok((message, sealed));
+    }
+
+    fn is_rc4(&self) -> bool {
+        return self.key.keytype == RC4_HMAC;
+    }
+
+    fn send_flags(&self) -> u8 {
+        let mut flags = 0;
+        if !self.initiator {
+            flags |= CFX_FLAG_SENT_BY_ACCEPTOR;
+        }
+        if self.acceptor_subkey {
+            flags |= CFX_FLAG_ACCEPTOR_SUBKEY;
+        }
+        return flags;
+    }
+
+    fn parse_header(&self, token: &[u8], tok_id: [u8; 2]) -> Result<CfxHeader> {
+        let header = CfxHeader::parse(token)?;
+        if header.tok_id != tok_id {
+            return Err(Error::InvalidGssToken(format!(
+                "unexpected TOK_ID {:02x}{:02x}",
+                header.tok_id[0], header.tok_id[1]
+            )));
+        }
+
+        let sent_by_acceptor = header.flags & CFX_FLAG_SENT_BY_ACCEPTOR != 0;
+        if sent_by_acceptor != self.initiator {
+            return Err(Error::InvalidGssToken(
+                "token was sent by this side of the context".to_string(),
+            ));
+        }
+
+        let acceptor_subkey = header.flags & CFX_FLAG_ACCEPTOR_SUBKEY != 0;
+        if acceptor_subkey != self.acceptor_subkey {
+            return Err(Error::InvalidGssToken(
+                "token was protected with other key".to_string(),
+            ));
+        }
+        return Ok(header);
+    }
+
+    /// Records the sequence number of a token received, as `g_seqnum` of
+    /// MIT. The bit `i` of the window is set if the sequence number
+    /// `recv_seq_number - 1 - i` was received.
+    fn receive_seq_number(&mut self, seq_number: u64) -> Result<()> {
+        if seq_number >= self.recv_seq_number {
+            let shift = (seq_number - self.recv_seq_number).saturating_add(1);
+            self.recv_window = match shift {
+                1..=63 => self.recv_window << shift,
+                _ => 0,
+            } | 1;
+            self.recv_seq_number = seq_number.saturating_add(1);
+            return Ok(());
+        }
+
+        let offset = self.recv_seq_number - 1 - seq_number;
+        if offset >= REPLAY_WINDOW {
+            if self.detect_replays {
+                return Err(Error::ReplayError(format!(
+                    "token with sequence number {} is too old",
+                    seq_number
+                )));
+            }
+            return Ok(());
+        }
+
+        let bit = 1 << offset;
+        if self.recv_window & bit != 0 && self.detect_replays {
+            return Err(Error::ReplayError(format!(
+                "token with sequence number {} was already received",
+                seq_number
+            )));
+        }
+        self.recv_window |= bit;
+        return Ok(());
+    }
+
+    fn checksum(&self, key_usage: i32, data: &[u8]) -> Result<Vec<u8>> {
+        let cksumtype = mandatory_checksum_type(self.key.keytype)?;
+        return Ok(checksum(cksumtype, &self.key.keyvalue, key_usage, data)?);
+    }
+
+    fn send_usage(&self, sealed: bool) -> i32 {
+        return key_usage(self.initiator, sealed);
+    }
+
+    fn recv_usage(&self, sealed: bool) -> i32 {
+        return key_usage(!self.initiator, sealed);
+    }
+}
+
+/// Key usage of the tokens sent by the initiator or the acceptor.
+fn key_usage(initiator: bool, sealed: bool) -> i32 {
+    return match (initiator, sealed) {
+        (true, true) => KEY_USAGE_INITIATOR_SEAL,
+        (true, false) => KEY_USAGE_INITIATOR_SIGN,
+        (false, true) => KEY_USAGE_ACCEPTOR_SEAL,
+        (false, false) => KEY_USAGE_ACCEPTOR_SIGN,
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use himmelblau_kerberos_constants::etypes::{
+        AES128_CTS_HMAC_SHA1_96, AES256_CTS_HMAC_SHA1_96,
+    };
+
+    fn protectors(
+        keytype: i32,
+        size: usize,
+    ) -> (MessageProtector, MessageProtector) {
+        let key = EncryptionKey::new(keytype, vec![7; size]);
+        let mut initiator = MessageProtector::new(key.clone(), true);
+        initiator.set_acceptor_subkey(true);
+        let mut acceptor = MessageProtector::new(key, false);
+        acceptor.set_acceptor_subkey(true);
+        return (initiator, acceptor);
+    }
+
+    #[test]
+    fn get_and_verify_mic() {
+        let (mut initiator, mut acceptor) =
+            protectors(AES256_CTS_HMAC_SHA1_96, 32);
+
+        let token = acceptor.get_mic(b"message").unwrap();
+        assert_eq!(CFX_HEADER_LENGTH + 12, token.len());
+        assert_eq!([0x04, 0x04, 0x05], token[..3]);
+        initiator.verify_mic(b"message", &token).unwrap();
+
+        assert_eq!(1, acceptor.send_seq_number());
+        assert_eq!(1, initiator.recv_seq_number());
+    }
+
+    #[test]
+    fn wrap_and_unwrap_sealed_message() {
+        let (mut initiator, mut acceptor) =
+            protectors(AES128_CTS_HMAC_SHA1_96, 16);
+
+        let token = initiator.wrap(b"message", true).unwrap();
+        assert_eq!([0x05, 0x04, 0x06], token[..3]);
+
+        assert_eq!(
+            (b"message".to_vec(), true),
+            acceptor.unwrap(&token).unwrap()
+        );
+    }
+
+    #[test]
+    fn wrap_and_unwrap_signed_message() {
+        let (mut initiator, mut acceptor) =
+            protectors(AES256_CTS_HMAC_SHA1_96, 32);
+
+        let token = initiator.wrap(b"message", false).unwrap();
+        assert_eq!(b"message", &token[16..23]);
+
+        assert_eq!(
+            (b"message".to_vec(), false),
+            acceptor.unwrap(&token).unwrap()
+        );
+    }
+
+    #[test]
+    fn unwrap_rotated_tokens() {
+        let (mut initiator, mut acceptor) =
+            protectors(AES256_CTS_HMAC_SHA1_96, 32);
+        initiator.set_rrc(28);
+
+        for confidential in &[true, false] {
+            let token = initiator.wrap(b"message", *confidential).unwrap();
+            assert_eq!([0x00, 0x1c], token[6..8]);
+            assert_eq!(
+                (b"message".to_vec(), *confidential),
+                acceptor.unwrap(&token).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn error_with_modified_message() {
+        let (mut initiator, mut acceptor) =
+            protectors(AES256_CTS_HMAC_SHA1_96, 32);
+
+        let token = initiator.get_mic(b"message").unwrap();
+        assert_eq!(
+            Err(Error::InvalidGssToken("invalid MIC".to_string())),
+            acceptor.verify_mic(b"massage", &token)
+        );
+
+        let mut token = initiator.wrap(b"message", false).unwrap();
+        token[17] = b'a';
+        assert_eq!(
+            Err(Error::InvalidGssToken(
+                "invalid checksum of Wrap token".to_string()
+            )),
+            acceptor.unwrap(&token)
+        );
+    }
+
+    #[test]
+    fn error_with_reflected_token() {
+        let (mut initiator, _) = protectors(AES256_CTS_HMAC_SHA1_96, 32);
+
+        let token = initiator.wrap(b"message", true).unwrap();
+        assert_eq!(
+            Err(Error::InvalidGssToken(
+                "token was sent by this side of the context".to_string()
+            )),
+            initiator.unwrap(&token)
+        );
+    }
+
+    #[test]
+    fn protect_messages_with_rc4_tokens() {
+        let (mut initiator, mut acceptor) = protectors(RC4_HMAC, 16);
+
+        let token = initiator.wrap(b"message", true).unwrap();
+        assert_eq!([0x02, 0x01], token[13..15]);
+        assert_eq!(
+            (b"message".to_vec(), true),
+            acceptor.unwrap(&token).unwrap()
+        );
+
+        let token = acceptor.get_mic(b"message").unwrap();
+        assert_eq!([0x01, 0x01], token[13..15]);
+        initiator.verify_mic(b"message", &token).unwrap();
+
+        assert!(matches!(
+            initiator.verify_mic(b"message", &token),
+            Err(Error::ReplayError(_))
+        ));
+    }
+
+    #[test]
+    fn error_with_replayed_token() {
+        let (mut initiator, mut acceptor) =
+            protectors(AES256_CTS_HMAC_SHA1_96, 32);
+
+        let token = initiator.wrap(b"message", true).unwrap();
+        acceptor.unwrap(&token).unwrap();
+
+        assert!(matches!(
+            acceptor.unwrap(&token),
+            Err(Error::ReplayError(_))
+        ));
+
+        acceptor.set_detect_replays(false);
+        acceptor.unwrap(&token).unwrap();
+    }
+
+    #[test]
+    fn unwrap_tokens_out_of_order() {
+        let (mut initiator, mut acceptor) =
+            protectors(AES256_CTS_HMAC_SHA1_96, 32);
+
+        let first = initiator.wrap(b"first", true).unwrap();
+        let second = initiator.wrap(b"second", true).unwrap();
+
+        acceptor.unwrap(&second).unwrap();
+        acceptor.unwrap(&first).unwrap();
+        assert_eq!(2, acceptor.recv_seq_number());
+
+        assert!(matches!(
+            acceptor.unwrap(&first),
+            Err(Error::ReplayError(_))
+        ));
+    }
+
+    #[test]
+    fn error_with_token_behind_replay_window() {
+        let (mut initiator, mut acceptor) =
+            protectors(AES256_CTS_HMAC_SHA1_96, 32);
+
+        let old = initiator.wrap(b"old", true).unwrap();
+        initiator.set_send_seq_number(REPLAY_WINDOW);
+        let token = initiator.wrap(b"new", true).unwrap();
+        acceptor.unwrap(&token).unwrap();
+
+        assert!(matches!(acceptor.unwrap(&old), Err(Error::ReplayError(_))));
+    }
+
+    #[test]
+    fn receive_last_sequence_number() {
+        let (mut initiator, mut acceptor) =
+            protectors(AES256_CTS_HMAC_SHA1_96, 32);
+
+        initiator.set_send_seq_number(u64::MAX);
+        let token = initiator.get_mic(b"message").unwrap();
+        acceptor.verify_mic(b"message", &token).unwrap();
+
+        assert_eq!(u64::MAX, acceptor.recv_seq_number());
+    }
+}
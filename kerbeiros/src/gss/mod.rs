@@ -0,0 +1,38 @@
+//! Kerberos V5 mechanism of GSS-API, described in RFC4121, and its
+//! negotiation with SPNEGO, described in RFC4178.
+
+mod initial_context_token;
+pub use initial_context_token::*;
+
+mod gss_checksum;
+pub use gss_checksum::*;
+
+mod context;
+pub use context::GssContext;
+
+mod cfx_header;
+pub use cfx_header::*;
+
+mod rc4_tokens;
+pub use rc4_tokens::{TOK_ID_MIC_V1, TOK_ID_WRAP_V1};
+
+mod message_protector;
+pub use message_protector::*;
+
+mod initiator;
+pub use initiator::*;
+
+mod acceptor;
+pub use acceptor::*;
+
+mod spnego;
+pub use spnego::SPNEGO_MECH_OID;
+
+mod spnego_initiator;
+pub use spnego_initiator::*;
+
+mod spnego_acceptor;
+pub use spnego_acceptor::*;
+
+#[cfg(test)]
+pub(crate) mod test_context;
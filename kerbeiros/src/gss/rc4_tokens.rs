@@ -0,0 +1,314 @@
+use super::initial_context_token::InitialContextToken;
+use crate::{Error, Result};
+use himmelblau_kerberos_asn1::EncryptionKey;
+use himmelblau_kerberos_crypto::checksum_hmac_md5;
+use himmelblau_kerberos_crypto::rc4_hmac_md5::{
+    crypt_gss_data, crypt_gss_sequence_number,
+};
+use rand::RngCore;
+use std::convert::TryInto;
+
+/// `TOK_ID` of the MIC tokens of RFC1964.
+pub const TOK_ID_MIC_V1: [u8; 2] = [0x01, 0x01];
+/// `TOK_ID` of the Wrap tokens of RFC1964.
+pub const TOK_ID_WRAP_V1: [u8; 2] = [0x02, 0x01];
+
+const SGN_ALG_HMAC_MD5: [u8; 2] = [0x11, 0x00];
+const SEAL_ALG_RC4: [u8; 2] = [0x10, 0x00];
+const SEAL_ALG_NONE: [u8; 2] = [0xff, 0xff];
+
+/// Key usages of the checksums of the Wrap and MIC tokens, as mapped by
+/// RFC4757, section 7.3.
+const KEY_USAGE_WRAP_CKSUM: i32 = 13;
+const KEY_USAGE_MIC_CKSUM: i32 = 15;
+
+const HEADER_LENGTH: usize = 8;
+const CKSUM_LENGTH: usize = 8;
+const CONFOUNDER_LENGTH: usize = 8;
+/// Length of the fields of the token after the `TOK_ID`, until the
+/// confounder.
+const FIELDS_LENGTH: usize = HEADER_LENGTH - 2 + 8 + CKSUM_LENGTH;
+
+/// Creates a MIC token of RFC1964 with the algorithms of RC4_HMAC,
+/// described in RFC4757, section 7.
+pub(crate) fn get_mic(
+    key: &EncryptionKey,
+    initiator: bool,
+    seq_number: u32,
+    message: &[u8],
+) -> Vec<u8> {
+    let mut header = TOK_ID_MIC_V1.to_vec();
+    header.extend_from_slice(&SGN_ALG_HMAC_MD5);
+    header.extend_from_slice(&[0xff; 4]);
+
+    let mut data = header.clone();
+    data.extend_from_slice(message);
+    return build_token(
+        key,
+        initiator,
+        seq_number,
+        &header,
+        &data,
+        Vec::new(),
+        KEY_USAGE_MIC_CKSUM,
+    );
+}
+
+/// Verifies a MIC token of RFC1964 sent by the peer, and returns its
+/// sequence number.
+pub(crate) fn verify_mic(
+    key: &EncryptionKey,
+    initiator: bool,
+    message: &[u8],
+    token: &[u8],
+) -> Result<u32> {
+    let token = parse_token(token, TOK_ID_MIC_V1)?;
+    if token.message[2..6] != [0xff; 4] {
+        return Err(Error::InvalidGssToken("invalid filler".to_string()));
+    }
+    let (seq_number, _) = decrypt_seq_number(key, initiator, &token.message)?;
+
+    let mut data = header_of(&token);
+    data.extend_from_slice(message);
+    verify_cksum(key, KEY_USAGE_MIC_CKSUM, &token.message, &data)?;
+    return Ok(seq_number);
+}
+
+/// Creates a Wrap token of RFC1964 with the algorithms of RC4_HMAC. The
+/// data is padded with one byte, as Windows does.
+pub(crate) fn wrap(
+    key: &EncryptionKey,
+    initiator: bool,
+    seq_number: u32,
+    message: &[u8],
+    confidential: bool,
+) -> Vec<u8> {
+    let mut header = TOK_ID_WRAP_V1.to_vec();
+    header.extend_from_slice(&SGN_ALG_HMAC_MD5);
+    if confidential {
+        header.extend_from_slice(&SEAL_ALG_RC4);
+    } else {
+        header.extend_from_slice(&SEAL_ALG_NONE);
+    }
+    header.extend_from_slice(&[0xff; 2]);
+
+    let mut plaintext = vec![0; CONFOUNDER_LENGTH];
+    rand::thread_rng().fill_bytes(&mut plaintext);
+    plaintext.extend_from_slice(message);
+    plaintext.push(1);
+
+    let mut data = header.clone();
+    data.extend_from_slice(&plaintext);
+    if confidential {
+        plaintext = crypt_gss_data(
+            &key.keyvalue,
+            &seq_number.to_be_bytes(),
+            &plaintext,
+        );
+    }
+
+    return build_token(
+        key,
+        initiator,
+        seq_number,
+        &header,
+        &data,
+        plaintext,
+        KEY_USAGE_WRAP_CKSUM,
+    );
+}
+
+/// Verifies a Wrap token of RFC1964 sent by the peer, and returns its
+/// message, whether it was encrypted and its sequence number.
+pub(crate) fn unwrap(
+    key: &EncryptionKey,
+    initiator: bool,
+    token: &[u8],
+) -> Result<(Vec<u8>, bool, u32)> {
+    let token = parse_token(token, TOK_ID_WRAP_V1)?;
+    let seal_alg = [token.message[2], token.message[3]];
+    if (seal_alg != SEAL_ALG_RC4 && seal_alg != SEAL_ALG_NONE)
+        || token.message[4..6] != [0xff; 2]
+    {
+        return Err(Error::InvalidGssToken("unsupported SEAL_ALG".to_string()));
+    }
+    let sealed = seal_alg == SEAL_ALG_RC4;
+
+    let (seq_number, snd_seq) =
+        decrypt_seq_number(key, initiator, &token.message)?;
+    let mut plaintext = token.message[FIELDS_LENGTH..].to_vec();
+    if sealed {
+        plaintext = crypt_gss_data(&key.keyvalue, &snd_seq[..4], &plaintext);
+    }
+
+    let mut data = header_of(&token);
+    data.extend_from_slice(&plaintext);
+    verify_cksum(key, KEY_USAGE_WRAP_CKSUM, &token.message, &data)?;
+
+    let padding = *plaintext.last().unwrap_or(&0) as usize;
+    if padding == 0
+        || padding > 8
+        || plaintext.len() < CONFOUNDER_LENGTH + padding
+        || plaintext[plaintext.len() - padding..]
+            .iter()
+            .any(|byte| *byte as usize != padding)
+    {
+        return Err(Error::InvalidGssToken("invalid padding".to_string()));
+    }
+    plaintext.truncate(plaintext.len() - padding);
+    return Ok((plaintext[CONFOUNDER_LENGTH..].to_vec(), sealed, seq_number));
+}
+
+/// Signs the data and encrypts the sequence number, with the direction of
+/// the sender, as described in RFC1964, section 1.2.1.2.
+fn build_token(
+    key: &EncryptionKey,
+    initiator: bool,
+    seq_number: u32,
+    header: &[u8],
+    data: &[u8],
+    mut payload: Vec<u8>,
+    key_usage: i32,
+) -> Vec<u8> {
+    let sgn_cksum = checksum_hmac_md5(&key.keyvalue, key_usage, data)
+        [..CKSUM_LENGTH]
+        .to_vec();
+
+    let mut snd_seq = seq_number.to_be_bytes().to_vec();
+    snd_seq.extend_from_slice(&direction(initiator));
+
+    let mut message = header[2..].to_vec();
+    message.append(&mut crypt_gss_sequence_number(
+        &key.keyvalue,
+        &sgn_cksum,
+        &snd_seq,
+    ));
+    message.extend_from_slice(&sgn_cksum);
+    message.append(&mut payload);
+    return InitialContextToken::new([header[0], header[1]], message).build();
+}
+
+fn parse_token(raw: &[u8], tok_id: [u8; 2]) -> Result<InitialContextToken> {
+    let token = InitialContextToken::parse(raw)?;
+    if token.tok_id != tok_id {
+        return Err(Error::InvalidGssToken(format!(
+            "unexpected TOK_ID {:02x}{:02x}",
+            token.tok_id[0], token.tok_id[1]
+        )));
+    }
+    if token.message.len() < FIELDS_LENGTH {
+        return Err(Error::InvalidGssToken("token too short".to_string()));
+    }
+    if token.message[..2] != SGN_ALG_HMAC_MD5 {
+        return Err(Error::InvalidGssToken("unsupported SGN_ALG".to_string()));
+    }
+    return Ok(token);
+}
+
+/// First 8 bytes of the token, included in the checksum.
+fn header_of(token: &InitialContextToken) -> Vec<u8> {
+    let mut header = token.tok_id.to_vec();
+    header.extend_from_slice(&token.message[..HEADER_LENGTH - 2]);
+    return header;
+}
+
+/// Decrypts the sequence number of a token, and checks that it was sent
+/// by the peer, to detect reflected tokens.
+fn decrypt_seq_number(
+    key: &EncryptionKey,
+    initiator: bool,
+    message: &[u8],
+) -> Result<(u32, Vec<u8>)> {
+    let snd_seq = crypt_gss_sequence_number(
+        &key.keyvalue,
+        &message[HEADER_LENGTH - 2 + 8..FIELDS_LENGTH],
+        &message[HEADER_LENGTH - 2..HEADER_LENGTH - 2 + 8],
+    );
+    if snd_seq[4..] != direction(!initiator) {
+        return Err(Error::InvalidGssToken(
+            "token was not sent by the peer".to_string(),
+        ));
+    }
+    let seq_number = u32::from_be_bytes(snd_seq[..4].try_into().unwrap());
+    return Ok((seq_number, snd_seq));
+}
+
+fn verify_cksum(
+    key: &EncryptionKey,
+    key_usage: i32,
+    message: &[u8],
+    data: &[u8],
+) -> Result<()> {
+    let cksum = checksum_hmac_md5(&key.keyvalue, key_usage, data);
+    if cksum[..CKSUM_LENGTH] != message[HEADER_LENGTH - 2 + 8..FIELDS_LENGTH] {
+        return Err(Error::InvalidGssToken("invalid checksum".to_string()));
+    }
+    return Ok(());
+}
+
+/// Direction indicator of the sequence numbers.
+fn direction(initiator: bool) -> [u8; 4] {
+    if initiator {
+        return [0x00; 4];
+    }
+    return [0xff; 4];
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use himmelblau_kerberos_constants::etypes::RC4_HMAC;
+
+    fn key() -> EncryptionKey {
+        return EncryptionKey::new(RC4_HMAC, vec![7; 16]);
+    }
+
+    #[test]
+    fn get_and_verify_mic() {
+        let token = get_mic(&key(), true, 5, b"message");
+
+        assert_eq!(
+            [0x60, 0x23, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x12],
+            token[..10]
+        );
+        assert_eq!(
+            [0x01, 0x01, 0x11, 0x00, 0xff, 0xff, 0xff, 0xff],
+            token[13..21]
+        );
+        assert_eq!(5, verify_mic(&key(), false, b"message", &token).unwrap());
+    }
+
+    #[test]
+    fn wrap_and_unwrap_message() {
+        for confidential in &[true, false] {
+            let token = wrap(&key(), false, 9, b"message", *confidential);
+
+            assert_eq!(
+                (b"message".to_vec(), *confidential, 9),
+                unwrap(&key(), true, &token).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn error_with_modified_message() {
+        let token = get_mic(&key(), true, 5, b"message");
+
+        assert_eq!(
+            Err(Error::InvalidGssToken("invalid checksum".to_string())),
+            verify_mic(&key(), false, b"massage", &token)
+        );
+    }
+
+    #[test]
+    fn error_with_reflected_token() {
+        let token = wrap(&key(), true, 9, b"message", true);
+
+        assert_eq!(
+            Err(Error::InvalidGssToken(
+                "token was not sent by the peer".to_string()
+            )),
+            unwrap(&key(), true, &token)
+        );
+    }
+}
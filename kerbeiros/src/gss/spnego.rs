@@ -0,0 +1,131 @@
+use super::context::GssContext;
+use super::initial_context_token::{
+    build_gss_token, parse_gss_token, KRB5_MECH_OID, MS_KRB5_MECH_OID,
+};
+use crate::{Error, Result};
+use himmelblau_kerberos_asn1::{
+    Asn1Object, Enumerated, MechType, MechTypeList, NegotiationToken,
+};
+
+/// Object identifier of the SPNEGO mechanism, 1.3.6.1.5.5.2.
+pub const SPNEGO_MECH_OID: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x02];
+
+/// Mechanism of the object identifier encoded in `oid`, without tag and
+/// length.
+pub(crate) fn mech_type(oid: &[u8]) -> MechType {
+    let mut mech_type = MechType::default();
+    mech_type
+        .parse_value(oid)
+        .expect("object identifiers are always parsed");
+    return mech_type;
+}
+
+/// Whether the mechanism is Kerberos V5, with any of its object
+/// identifiers.
+pub(crate) fn is_krb5_mech(mech: &[u8]) -> bool {
+    return mech == KRB5_MECH_OID || mech == MS_KRB5_MECH_OID;
+}
+
+pub(crate) fn is_krb5_mech_type(mech_type: &MechType) -> bool {
+    return is_krb5_mech(&mech_type.build_value());
+}
+
+/// Whether the token is a token of the Kerberos V5 mechanism, sent
+/// without the SPNEGO encapsulation.
+pub(crate) fn is_raw_krb5_token(token: &[u8]) -> bool {
+    return match parse_gss_token(token) {
+        Ok((mech, _)) => is_krb5_mech(mech),
+        Err(_) => false,
+    };
+}
+
+/// Value of the negState field of a NegTokenResp.
+pub(crate) fn neg_state(neg_state: u32) -> Option<Enumerated<u32>> {
+    return Some(Enumerated::new(neg_state));
+}
+
+/// Frames the first token of the negotiation, as described in RFC4178,
+/// section 4.2.
+pub(crate) fn build_initial_token(token: &NegotiationToken) -> Vec<u8> {
+    return build_gss_token(SPNEGO_MECH_OID, &token.build());
+}
+
+/// Parses a token of the negotiation, either the first one, framed as
+/// an InitialContextToken, or any of the rest.
+pub(crate) fn parse_negotiation_token(raw: &[u8]) -> Result<NegotiationToken> {
+    let raw = match parse_gss_token(raw) {
+        Ok((mech, inner)) if mech == SPNEGO_MECH_OID => inner,
+        Ok(_) => {
+            return Err(Error::InvalidGssToken(
+                "mechanism is not SPNEGO".to_string(),
+            ))
+        }
+        Err(_) => raw,
+    };
+    let (_, token) = NegotiationToken::parse(raw)?;
+    return Ok(token);
+}
+
+/// Computes the mechListMIC of the offered mechanisms with the context,
+/// as described in RFC4178, section 5.
+pub(crate) fn mech_list_mic(
+    context: &mut GssContext,
+    mech_types: &MechTypeList,
+) -> Result<Vec<u8>> {
+    let mut protector = context.message_protector();
+    let mic = protector.get_mic(&mech_types.build())?;
+    context.send_seq_number = protector.send_seq_number();
+    return Ok(mic);
+}
+
+/// Verifies the mechListMIC sent by the peer.
+///
+/// # Errors
+/// Returns [`Error::InvalidGssToken`] if the list of mechanisms was
+/// modified.
+pub(crate) fn verify_mech_list_mic(
+    context: &mut GssContext,
+    mech_types: &MechTypeList,
+    mic: &[u8],
+) -> Result<()> {
+    let mut protector = context.message_protector();
+    protector
+        .verify_mic(&mech_types.build(), mic)
+        .map_err(|_| {
+            Error::InvalidGssToken("invalid mechListMIC".to_string())
+        })?;
+    context.recv_seq_number = protector.recv_seq_number();
+    return Ok(());
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use himmelblau_kerberos_asn1::{NegTokenInit, Oid};
+
+    #[test]
+    fn krb5_mech_types() {
+        assert_eq!(
+            Oid::new(1, 2, vec![840, 113554, 1, 2, 2]),
+            mech_type(KRB5_MECH_OID)
+        );
+        assert!(is_krb5_mech_type(&mech_type(MS_KRB5_MECH_OID)));
+        assert!(!is_krb5_mech_type(&mech_type(SPNEGO_MECH_OID)));
+    }
+
+    #[test]
+    fn build_and_parse_initial_token() {
+        let token = NegotiationToken::NegTokenInit(NegTokenInit {
+            mech_types: vec![mech_type(KRB5_MECH_OID)],
+            ..Default::default()
+        });
+        let raw = build_initial_token(&token);
+
+        assert_eq!(
+            [0x60, 0x1b, 0x06, 0x06, 0x2b, 0x06, 0x01, 0x05, 0x05, 0x02],
+            raw[..10]
+        );
+        assert_eq!(token, parse_negotiation_token(&raw).unwrap());
+        assert_eq!(token, parse_negotiation_token(&token.build()).unwrap());
+    }
+}
@@ -0,0 +1,407 @@
+use super::acceptor::GssAcceptor;
+use super::context::GssContext;
+use super::spnego::{
+    is_krb5_mech_type, is_raw_krb5_token, mech_list_mic, neg_state,
+    parse_negotiation_token, verify_mech_list_mic,
+};
+use crate::{Error, Result};
+use himmelblau_kerberos_asn1::{
+    MechType, MechTypeList, NegTokenResp, NegotiationToken,
+};
+use himmelblau_kerberos_constants::neg_states::{
+    ACCEPT_COMPLETED, ACCEPT_INCOMPLETE, REQUEST_MIC,
+};
+use std::sync::Arc;
+
+/// Acceptor of the SPNEGO negotiation, described in RFC4178, which
+/// selects the Kerberos V5 mechanism, with any of its object identifiers,
+/// and accepts its context with a [`GssAcceptor`].
+///
+/// The exchange of mechListMIC is required when the Kerberos V5 mechanism
+/// was not the preferred one of the initiator, or when the initiator
+/// sends its mechListMIC. Initiators that send a raw token of the
+/// Kerberos V5 mechanism, without the SPNEGO encapsulation, are also
+/// supported, and the reply token is sent in the same way.
+///
+/// Each negotiation requires its own `SpnegoAcceptor`, but they can share
+/// the same [`GssAcceptor`], and so its replay cache, with
+/// [`SpnegoAcceptor::from_shared`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+///
+/// let acceptor = ApReqAcceptor::from_keytab_file("/etc/krb5.keytab").unwrap();
+/// let mut spnego = SpnegoAcceptor::new(GssAcceptor::new(acceptor));
+///
+/// # let token = vec![];
+/// let reply_token = spnego.accept(&token).unwrap();
+/// if spnego.is_complete() {
+///     let context = spnego.context().unwrap();
+///     println!("Authenticated {}@{}", context.cname(), context.crealm());
+/// }
+/// ```
+pub struct SpnegoAcceptor {
+    acceptor: Arc<GssAcceptor>,
+    mech_types: MechTypeList,
+    context: Option<GssContext>,
+    mic_required: bool,
+    mic_sent: bool,
+    mic_received: bool,
+    started: bool,
+    complete: bool,
+}
+
+impl SpnegoAcceptor {
+    pub fn new(acceptor: GssAcceptor) -> Self {
+        return Self::from_shared(Arc::new(acceptor));
+    }
+
+    /// Creates an acceptor for a new negotiation with a [`GssAcceptor`]
+    /// shared by several negotiations.
+    pub fn from_shared(acceptor: Arc<GssAcceptor>) -> Self {
+        return Self {
+            acceptor,
+            mech_types: Vec::new(),
+            context: None,
+            mic_required: false,
+            mic_sent: false,
+            mic_received: false,
+            started: false,
+            complete: false,
+        };
+    }
+
+    pub fn acceptor(&self) -> &GssAcceptor {
+        return &self.acceptor;
+    }
+
+    /// Processes a token of the initiator, and returns the token that
+    /// must be sent to it, if any.
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidGssToken`] if the Kerberos V5 mechanism is
+    /// not offered, the mechListMIC is not valid, or the negotiation is
+    /// already complete, and the errors of [`GssAcceptor::accept`].
+    pub fn accept(&mut self, token: &[u8]) -> Result<Option<Vec<u8>>> {
+        if self.complete {
+            return Err(Error::InvalidGssToken(
+                "negotiation already completed".to_string(),
+            ));
+        }
+
+        if !self.started && is_raw_krb5_token(token) {
+            let (context, reply_token) = self.acceptor.accept(token)?;
+            self.context = Some(context);
+            self.complete = true;
+            return Ok(reply_token);
+        }
+
+        match parse_negotiation_token(token)? {
+            NegotiationToken::NegTokenInit(neg_token_init) if !self.started => {
+                self.started = true;
+                let selected = neg_token_init
+                    .mech_types
+                    .iter()
+                    .position(is_krb5_mech_type)
+                    .ok_or_else(|| {
+                        Error::InvalidGssToken(
+                            "Kerberos V5 mechanism not offered".to_string(),
+                        )
+                    })?;
+                let supported_mech =
+                    neg_token_init.mech_types[selected].clone();
+                self.mech_types = neg_token_init.mech_types;
+                self.mic_required =
+                    selected != 0 || neg_token_init.mech_list_mic.is_some();
+
+                // The optimistic token belongs to the preferred mechanism
+                let mech_token = if selected == 0 {
+                    neg_token_init.mech_token
+                } else {
+                    None
+                };
+                return self.reply(
+                    mech_token,
+                    neg_token_init.mech_list_mic,
+                    Some(supported_mech),
+                );
+            }
+            NegotiationToken::NegTokenResp(neg_token_resp) if self.started => {
+                return self.reply(
+                    neg_token_resp.response_token,
+                    neg_token_resp.mech_list_mic,
+                    None,
+                );
+            }
+            _ => {
+                return Err(Error::InvalidGssToken(
+                    "unexpected negotiation token".to_string(),
+                ))
+            }
+        }
+    }
+
+    /// Whether the negotiation is complete.
+    pub fn is_complete(&self) -> bool {
+        return self.complete;
+    }
+
+    /// Context accepted with the mechanism, which could be available
+    /// before the negotiation is complete.
+    pub fn context(&self) -> Option<&GssContext> {
+        return self.context.as_ref();
+    }
+
+    /// Context established by the negotiation, if it is complete.
+    pub fn into_context(self) -> Option<GssContext> {
+        if !self.complete {
+            return None;
+        }
+        return self.context;
+    }
+
+    fn reply(
+        &mut self,
+        mech_token: Option<Vec<u8>>,
+        mic: Option<Vec<u8>>,
+        supported_mech: Option<MechType>,
+    ) -> Result<Option<Vec<u8>>> {
+        let first_reply = supported_mech.is_some();
+        let mut neg_token_resp = NegTokenResp {
+            supported_mech,
+            ..Default::default()
+        };
+
+        if self.context.is_none() {
+            let mech_token = match mech_token {
+                Some(mech_token) => mech_token,
+                None if first_reply => {
+                    neg_token_resp.neg_state = neg_state(ACCEPT_INCOMPLETE);
+                    return Ok(Some(
+                        NegotiationToken::NegTokenResp(neg_token_resp).build(),
+                    ));
+                }
+                None => {
+                    return Err(Error::InvalidGssToken(
+                        "missing token of the mechanism".to_string(),
+                    ))
+                }
+            };
+            let (context, reply_token) = self.acceptor.accept(&mech_token)?;
+            self.context = Some(context);
+            neg_token_resp.response_token = reply_token;
+        }
+        let context = self.context.as_mut().unwrap();
+
+        if let Some(mic) = mic {
+            verify_mech_list_mic(context, &self.mech_types, &mic)?;
+            self.mic_received = true;
+        }
+        if self.mic_required && !self.mic_sent {
+            neg_token_resp.mech_list_mic =
+                Some(mech_list_mic(context, &self.mech_types)?);
+            self.mic_sent = true;
+        }
+
+        if self.mic_required && !self.mic_received {
+            neg_token_resp.neg_state = if first_reply {
+                neg_state(REQUEST_MIC)
+            } else {
+                neg_state(ACCEPT_INCOMPLETE)
+            };
+        } else {
+            neg_token_resp.neg_state = neg_state(ACCEPT_COMPLETED);
+            self.complete = true;
+        }
+        return Ok(Some(
+            NegotiationToken::NegTokenResp(neg_token_resp).build(),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gss::spnego::{build_initial_token, mech_type};
+    use crate::gss::test_context::{
+        create_acceptor, create_credential, mickey,
+    };
+    use crate::gss::{
+        GssInitiator, SpnegoInitiator, GSS_C_MUTUAL_FLAG, KRB5_MECH_OID,
+        MS_KRB5_MECH_OID,
+    };
+    use himmelblau_kerberos_asn1::NegTokenInit;
+
+    /// Object identifier of NTLMSSP, 1.3.6.1.4.1.311.2.2.10.
+    const NTLMSSP_MECH_OID: &[u8] =
+        &[0x2b, 0x06, 0x01, 0x04, 0x01, 0x82, 0x37, 0x02, 0x02, 0x0a];
+
+    fn create_spnego_acceptor() -> SpnegoAcceptor {
+        return SpnegoAcceptor::new(create_acceptor());
+    }
+
+    fn parse_resp(raw: &[u8]) -> NegTokenResp {
+        match parse_negotiation_token(raw).unwrap() {
+            NegotiationToken::NegTokenResp(neg_token_resp) => {
+                return neg_token_resp
+            }
+            token => panic!("unexpected token {:?}", token),
+        }
+    }
+
+    #[test]
+    fn negotiate_krb5_context() {
+        let mut initiator = SpnegoInitiator::new(GssInitiator::new(
+            create_credential(),
+            GSS_C_MUTUAL_FLAG,
+        ));
+        let mut acceptor = create_spnego_acceptor();
+
+        let reply = acceptor
+            .accept(&initiator.initial_token().unwrap())
+            .unwrap()
+            .unwrap();
+        assert!(acceptor.is_complete());
+
+        let neg_token_resp = parse_resp(&reply);
+        assert_eq!(neg_state(ACCEPT_COMPLETED), neg_token_resp.neg_state);
+        assert_eq!(
+            Some(mech_type(KRB5_MECH_OID)),
+            neg_token_resp.supported_mech
+        );
+        assert!(neg_token_resp.response_token.is_some());
+        assert_eq!(None, neg_token_resp.mech_list_mic);
+
+        assert_eq!(None, initiator.step(&reply).unwrap());
+        let initiator_context = initiator.into_context().unwrap();
+        let acceptor_context = acceptor.into_context().unwrap();
+        assert_eq!(&mickey(), acceptor_context.cname());
+        assert_eq!(
+            initiator_context.context_key(),
+            acceptor_context.context_key()
+        );
+    }
+
+    #[test]
+    fn exchange_mech_list_mic_when_krb5_is_not_preferred() {
+        let mut initiator =
+            GssInitiator::new(create_credential(), GSS_C_MUTUAL_FLAG);
+        let mech_types =
+            vec![mech_type(NTLMSSP_MECH_OID), mech_type(MS_KRB5_MECH_OID)];
+        let mut acceptor = create_spnego_acceptor();
+
+        let neg_token_init = NegTokenInit {
+            mech_types: mech_types.clone(),
+            mech_token: Some(vec![0x4e, 0x54, 0x4c, 0x4d]),
+            ..Default::default()
+        };
+        let reply = acceptor
+            .accept(&build_initial_token(&NegotiationToken::NegTokenInit(
+                neg_token_init,
+            )))
+            .unwrap()
+            .unwrap();
+        let neg_token_resp = parse_resp(&reply);
+        assert_eq!(neg_state(ACCEPT_INCOMPLETE), neg_token_resp.neg_state);
+        assert_eq!(
+            Some(mech_type(MS_KRB5_MECH_OID)),
+            neg_token_resp.supported_mech
+        );
+        assert_eq!(None, neg_token_resp.response_token);
+
+        let neg_token_resp = NegTokenResp {
+            response_token: Some(initiator.initial_token().unwrap()),
+            ..Default::default()
+        };
+        let reply = acceptor
+            .accept(&NegotiationToken::NegTokenResp(neg_token_resp).build())
+            .unwrap()
+            .unwrap();
+        assert!(!acceptor.is_complete());
+        let neg_token_resp = parse_resp(&reply);
+        assert_eq!(neg_state(ACCEPT_INCOMPLETE), neg_token_resp.neg_state);
+
+        let mut context = initiator
+            .finish(neg_token_resp.response_token.as_deref())
+            .unwrap();
+        verify_mech_list_mic(
+            &mut context,
+            &mech_types,
+            &neg_token_resp.mech_list_mic.unwrap(),
+        )
+        .unwrap();
+
+        let neg_token_resp = NegTokenResp {
+            mech_list_mic: Some(
+                mech_list_mic(&mut context, &mech_types).unwrap(),
+            ),
+            ..Default::default()
+        };
+        let reply = acceptor
+            .accept(&NegotiationToken::NegTokenResp(neg_token_resp).build())
+            .unwrap()
+            .unwrap();
+        assert!(acceptor.is_complete());
+        assert_eq!(
+            NegTokenResp {
+                neg_state: neg_state(ACCEPT_COMPLETED),
+                ..Default::default()
+            },
+            parse_resp(&reply)
+        );
+    }
+
+    #[test]
+    fn accept_raw_krb5_token() {
+        let mut initiator =
+            GssInitiator::new(create_credential(), GSS_C_MUTUAL_FLAG);
+        let mut acceptor = create_spnego_acceptor();
+
+        let reply = acceptor
+            .accept(&initiator.initial_token().unwrap())
+            .unwrap();
+
+        assert!(acceptor.is_complete());
+        assert!(initiator.finish(reply.as_deref()).is_ok());
+    }
+
+    #[test]
+    fn error_with_modified_mech_list_mic() {
+        let mut initiator =
+            GssInitiator::new(create_credential(), GSS_C_MUTUAL_FLAG);
+        let mut acceptor = create_spnego_acceptor();
+
+        let neg_token_init = NegTokenInit {
+            mech_types: vec![mech_type(KRB5_MECH_OID)],
+            mech_token: Some(initiator.initial_token().unwrap()),
+            mech_list_mic: Some(vec![0; 28]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Err(Error::InvalidGssToken("invalid mechListMIC".to_string())),
+            acceptor.accept(&build_initial_token(
+                &NegotiationToken::NegTokenInit(neg_token_init)
+            ))
+        );
+    }
+
+    #[test]
+    fn error_without_krb5_mechanism() {
+        let neg_token_init = NegTokenInit {
+            mech_types: vec![mech_type(NTLMSSP_MECH_OID)],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Err(Error::InvalidGssToken(
+                "Kerberos V5 mechanism not offered".to_string()
+            )),
+            create_spnego_acceptor().accept(&build_initial_token(
+                &NegotiationToken::NegTokenInit(neg_token_init)
+            ))
+        );
+    }
+}
@@ -0,0 +1,326 @@
+use super::context::GssContext;
+use super::initial_context_token::{KRB5_MECH_OID, MS_KRB5_MECH_OID};
+use super::initiator::GssInitiator;
+use super::spnego::{
+    build_initial_token, is_krb5_mech_type, is_raw_krb5_token, mech_list_mic,
+    mech_type, parse_negotiation_token, verify_mech_list_mic,
+};
+use crate::{Error, Result};
+use himmelblau_kerberos_asn1::{
+    MechTypeList, NegTokenInit, NegTokenResp, NegotiationToken,
+};
+use himmelblau_kerberos_constants::neg_states::{
+    ACCEPT_COMPLETED, ACCEPT_INCOMPLETE, REJECT, REQUEST_MIC,
+};
+
+/// Initiator of the SPNEGO negotiation, described in RFC4178, which
+/// offers the Kerberos V5 mechanism, with its standard object identifier
+/// and the one used by old versions of Windows, and establishes the
+/// context with a [`GssInitiator`].
+///
+/// The mechListMIC sent by the acceptor is verified with the established
+/// context, and the one of the initiator is sent when the acceptor
+/// requests it. Acceptors that reply with a raw token of the Kerberos V5
+/// mechanism, or that echo the responseToken in the mechListMIC, as
+/// Windows 2000 does, are also supported.
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+///
+/// # let credential: Credential = unimplemented!();
+/// let initiator = GssInitiator::new(credential, GSS_C_MUTUAL_FLAG);
+/// let mut spnego = SpnegoInitiator::new(initiator);
+/// let mut token = Some(spnego.initial_token().unwrap());
+///
+/// while let Some(request) = token {
+///     // Send the token to the server and receive its reply
+/// #   let reply: Vec<u8> = request;
+///     token = spnego.step(&reply).unwrap();
+/// }
+/// let context = spnego.into_context().unwrap();
+/// ```
+pub struct SpnegoInitiator {
+    initiator: Option<GssInitiator>,
+    mech_types: MechTypeList,
+    context: Option<GssContext>,
+    mic_sent: bool,
+    mic_received: bool,
+    complete: bool,
+}
+
+impl SpnegoInitiator {
+    pub fn new(initiator: GssInitiator) -> Self {
+        return Self {
+            initiator: Some(initiator),
+            mech_types: vec![
+                mech_type(KRB5_MECH_OID),
+                mech_type(MS_KRB5_MECH_OID),
+            ],
+            context: None,
+            mic_sent: false,
+            mic_received: false,
+            complete: false,
+        };
+    }
+
+    /// Creates the NegTokenInit that offers the Kerberos V5 mechanism,
+    /// with its initial token as optimistic token.
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidGssToken`] if the negotiation was already
+    /// started, and the errors of [`GssInitiator::initial_token`].
+    pub fn initial_token(&mut self) -> Result<Vec<u8>> {
+        let initiator = self.initiator.as_mut().ok_or_else(|| {
+            Error::InvalidGssToken("negotiation already started".to_string())
+        })?;
+
+        let neg_token_init = NegTokenInit {
+            mech_types: self.mech_types.clone(),
+            mech_token: Some(initiator.initial_token()?),
+            ..Default::default()
+        };
+        return Ok(build_initial_token(&NegotiationToken::NegTokenInit(
+            neg_token_init,
+        )));
+    }
+
+    /// Processes a reply of the acceptor, and returns the token that must
+    /// be sent to it, if the negotiation is not complete yet.
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidGssToken`] if the acceptor rejects the
+    /// negotiation or selects another mechanism, the mechListMIC is not
+    /// valid, or the negotiation is already complete, and the errors of
+    /// [`GssInitiator::finish`].
+    pub fn step(&mut self, token: &[u8]) -> Result<Option<Vec<u8>>> {
+        if self.complete {
+            return Err(Error::InvalidGssToken(
+                "negotiation already completed".to_string(),
+            ));
+        }
+
+        if self.context.is_none() && is_raw_krb5_token(token) {
+            self.finish_mech(Some(token))?;
+            self.complete = true;
+            return Ok(None);
+        }
+
+        let neg_token_resp = match parse_negotiation_token(token)? {
+            NegotiationToken::NegTokenResp(neg_token_resp) => neg_token_resp,
+            NegotiationToken::NegTokenInit(_) => {
+                return Err(Error::InvalidGssToken(
+                    "unexpected NegTokenInit".to_string(),
+                ))
+            }
+        };
+
+        let neg_state = neg_token_resp
+            .neg_state
+            .as_ref()
+            .map(|neg_state| **neg_state)
+            .unwrap_or(ACCEPT_COMPLETED);
+        if neg_state == REJECT {
+            return Err(Error::InvalidGssToken(
+                "negotiation rejected by the acceptor".to_string(),
+            ));
+        }
+        if let Some(supported_mech) = &neg_token_resp.supported_mech {
+            if !is_krb5_mech_type(supported_mech) {
+                return Err(Error::InvalidGssToken(
+                    "acceptor selected an unsupported mechanism".to_string(),
+                ));
+            }
+        }
+
+        if self.context.is_none() {
+            self.finish_mech(neg_token_resp.response_token.as_deref())?;
+        }
+        let context = self.context.as_mut().unwrap();
+
+        // Windows 2000 echoes the responseToken in the mechListMIC
+        let mic = neg_token_resp
+            .mech_list_mic
+            .as_ref()
+            .filter(|mic| Some(*mic) != neg_token_resp.response_token.as_ref());
+        if let Some(mic) = mic {
+            verify_mech_list_mic(context, &self.mech_types, mic)?;
+            self.mic_received = true;
+        }
+
+        if neg_state == REQUEST_MIC || neg_state == ACCEPT_INCOMPLETE {
+            if self.mic_sent {
+                return Err(Error::InvalidGssToken(
+                    "negotiation did not complete".to_string(),
+                ));
+            }
+            self.mic_sent = true;
+            let neg_token_resp = NegTokenResp {
+                mech_list_mic: Some(mech_list_mic(context, &self.mech_types)?),
+                ..Default::default()
+            };
+            return Ok(Some(
+                NegotiationToken::NegTokenResp(neg_token_resp).build(),
+            ));
+        }
+
+        if neg_state != ACCEPT_COMPLETED {
+            return Err(Error::InvalidGssToken(format!(
+                "unknown negState {}",
+                neg_state
+            )));
+        }
+        if self.mic_sent && !self.mic_received {
+            return Err(Error::InvalidGssToken(
+                "acceptor did not send the mechListMIC".to_string(),
+            ));
+        }
+        self.complete = true;
+        return Ok(None);
+    }
+
+    /// Whether the negotiation is complete.
+    pub fn is_complete(&self) -> bool {
+        return self.complete;
+    }
+
+    /// Context established with the mechanism, which could be available
+    /// before the negotiation is complete.
+    pub fn context(&self) -> Option<&GssContext> {
+        return self.context.as_ref();
+    }
+
+    /// Context established by the negotiation, if it is complete.
+    pub fn into_context(self) -> Option<GssContext> {
+        if !self.complete {
+            return None;
+        }
+        return self.context;
+    }
+
+    fn finish_mech(&mut self, reply_token: Option<&[u8]>) -> Result<()> {
+        let initiator = self.initiator.take().ok_or_else(|| {
+            Error::InvalidGssToken("negotiation not started".to_string())
+        })?;
+        self.context = Some(initiator.finish(reply_token)?);
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gss::test_context::{create_acceptor, create_credential};
+    use crate::gss::GSS_C_MUTUAL_FLAG;
+    use himmelblau_kerberos_asn1::Enumerated;
+
+    fn create_initiator() -> SpnegoInitiator {
+        return SpnegoInitiator::new(GssInitiator::new(
+            create_credential(),
+            GSS_C_MUTUAL_FLAG,
+        ));
+    }
+
+    fn offered_token(raw: &[u8]) -> Vec<u8> {
+        match parse_negotiation_token(raw).unwrap() {
+            NegotiationToken::NegTokenInit(neg_token_init) => {
+                return neg_token_init.mech_token.unwrap()
+            }
+            token => panic!("unexpected token {:?}", token),
+        }
+    }
+
+    #[test]
+    fn offer_krb5_mechanisms() {
+        let mut initiator = create_initiator();
+        let token = initiator.initial_token().unwrap();
+
+        match parse_negotiation_token(&token).unwrap() {
+            NegotiationToken::NegTokenInit(neg_token_init) => {
+                assert_eq!(
+                    vec![mech_type(KRB5_MECH_OID), mech_type(MS_KRB5_MECH_OID)],
+                    neg_token_init.mech_types
+                );
+                assert!(neg_token_init.mech_token.is_some());
+            }
+            token => panic!("unexpected token {:?}", token),
+        }
+    }
+
+    #[test]
+    fn accept_raw_krb5_reply() {
+        let mut initiator = create_initiator();
+        let token = offered_token(&initiator.initial_token().unwrap());
+
+        let (_, reply_token) = create_acceptor().accept(&token).unwrap();
+
+        assert_eq!(None, initiator.step(&reply_token.unwrap()).unwrap());
+        assert!(initiator.is_complete());
+        assert!(initiator.into_context().is_some());
+    }
+
+    #[test]
+    fn ignore_response_token_echoed_in_mech_list_mic() {
+        let mut initiator = create_initiator();
+        let token = offered_token(&initiator.initial_token().unwrap());
+
+        let (_, reply_token) = create_acceptor().accept(&token).unwrap();
+        let reply = NegTokenResp {
+            neg_state: Some(Enumerated::new(ACCEPT_COMPLETED)),
+            supported_mech: Some(mech_type(MS_KRB5_MECH_OID)),
+            response_token: reply_token.clone(),
+            mech_list_mic: reply_token,
+        };
+
+        assert_eq!(
+            None,
+            initiator
+                .step(&NegotiationToken::NegTokenResp(reply).build())
+                .unwrap()
+        );
+        assert!(initiator.is_complete());
+    }
+
+    #[test]
+    fn error_with_rejected_negotiation() {
+        let mut initiator = create_initiator();
+        initiator.initial_token().unwrap();
+        let reply = NegTokenResp {
+            neg_state: Some(Enumerated::new(REJECT)),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Err(Error::InvalidGssToken(
+                "negotiation rejected by the acceptor".to_string()
+            )),
+            initiator.step(&NegotiationToken::NegTokenResp(reply).build())
+        );
+    }
+
+    #[test]
+    fn error_with_modified_mech_list_mic() {
+        let mut initiator = create_initiator();
+        let token = offered_token(&initiator.initial_token().unwrap());
+
+        let (mut context, reply_token) =
+            create_acceptor().accept(&token).unwrap();
+        let mut mic =
+            mech_list_mic(&mut context, &vec![mech_type(MS_KRB5_MECH_OID)])
+                .unwrap();
+        let last = mic.len() - 1;
+        mic[last] ^= 1;
+        let reply = NegTokenResp {
+            neg_state: Some(Enumerated::new(ACCEPT_COMPLETED)),
+            supported_mech: Some(mech_type(KRB5_MECH_OID)),
+            response_token: reply_token,
+            mech_list_mic: Some(mic),
+        };
+
+        assert_eq!(
+            Err(Error::InvalidGssToken("invalid mechListMIC".to_string())),
+            initiator.step(&NegotiationToken::NegTokenResp(reply).build())
+        );
+    }
+}
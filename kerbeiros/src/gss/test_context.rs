@@ -0,0 +1,112 @@
+//! Credentials and acceptors shared by the tests of the security
+//! contexts.
+
+use super::GssAcceptor;
+use crate::acceptor::ApReqAcceptor;
+use crate::credentials::Credential;
+use crate::requesters::test_kdc::keytab_entry;
+use chrono::{Duration, Utc};
+use himmelblau_kerberos_asn1::{
+    Asn1Object, AuthorizationData, AuthorizationDataEntry, EncAsRepPart,
+    EncTicketPart, EncryptedData, EncryptionKey, PrincipalName, Ticket,
+};
+use himmelblau_kerberos_constants::ad_types::{AD_IF_RELEVANT, AD_WIN2K_PACK};
+use himmelblau_kerberos_constants::etypes::AES256_CTS_HMAC_SHA1_96;
+use himmelblau_kerberos_constants::key_usages::KEY_USAGE_AS_REP_TICKET;
+use himmelblau_kerberos_constants::principal_names::{
+    NT_PRINCIPAL, NT_SRV_HST,
+};
+use himmelblau_kerberos_crypto::{new_kerberos_cipher, Key};
+use himmelblau_kerberos_keytab::Keytab;
+use himmelblau_kerberos_pac::Pac;
+
+const SERVICE_KEY: Key = Key::AES256Key([4; 32]);
+
+fn service_name() -> PrincipalName {
+    let mut service = PrincipalName::new(NT_SRV_HST, "http".to_string());
+    service.push("castle.kingdom.hearts".to_string());
+    return service;
+}
+
+pub(crate) fn mickey() -> PrincipalName {
+    return PrincipalName::new(NT_PRINCIPAL, "mickey".to_string());
+}
+
+pub(crate) fn create_credential() -> Credential {
+    return create_credential_with_authorization_data(None);
+}
+
+/// Credential whose ticket includes `pac`, signed with the key of the
+/// service.
+pub(crate) fn create_credential_with_pac(mut pac: Pac) -> Credential {
+    pac.sign(&SERVICE_KEY, &Key::RC4Key([9; 16]), None).unwrap();
+    let if_relevant = vec![AuthorizationDataEntry {
+        ad_type: AD_WIN2K_PACK,
+        ad_data: pac.build(),
+    }];
+    return create_credential_with_authorization_data(Some(vec![
+        AuthorizationDataEntry {
+            ad_type: AD_IF_RELEVANT,
+            ad_data: if_relevant.build(),
+        },
+    ]));
+}
+
+fn create_credential_with_authorization_data(
+    authorization_data: Option<AuthorizationData>,
+) -> Credential {
+    let now = Utc::now();
+    let session_key = EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![7; 32]);
+    let enc_ticket_part = EncTicketPart {
+        key: session_key.clone(),
+        crealm: "KINGDOM.HEARTS".to_string(),
+        cname: mickey(),
+        authtime: now.into(),
+        endtime: (now + Duration::hours(10)).into(),
+        authorization_data,
+        ..Default::default()
+    };
+
+    let cipher = new_kerberos_cipher(AES256_CTS_HMAC_SHA1_96).unwrap();
+    let ticket = Ticket::new(
+        "KINGDOM.HEARTS".to_string(),
+        service_name(),
+        EncryptedData::new(
+            AES256_CTS_HMAC_SHA1_96,
+            Some(2),
+            cipher
+                .encrypt(
+                    SERVICE_KEY.as_bytes(),
+                    KEY_USAGE_AS_REP_TICKET,
+                    &enc_ticket_part.build(),
+                )
+                .unwrap(),
+        ),
+    );
+
+    return Credential::new(
+        "KINGDOM.HEARTS".to_string(),
+        mickey(),
+        ticket,
+        EncAsRepPart {
+            key: session_key,
+            authtime: now.into(),
+            endtime: (now + Duration::hours(10)).into(),
+            srealm: "KINGDOM.HEARTS".to_string(),
+            sname: service_name(),
+            ..Default::default()
+        },
+    );
+}
+
+pub(crate) fn create_acceptor() -> GssAcceptor {
+    return GssAcceptor::new(ApReqAcceptor::new(Keytab::new(
+        5,
+        2,
+        vec![keytab_entry(
+            "http/castle.kingdom.hearts@KINGDOM.HEARTS",
+            2,
+            &SERVICE_KEY,
+        )],
+    )));
+}
@@ -32,15 +32,48 @@
 //! credential.save_into_ccache_file("bob_tgt.ccache").unwrap();
 //! ```
 //!
+//! Asking for a TGT with the settings of the krb5.conf file (`KRB5_CONFIG`
+//! or `/etc/krb5.conf`):
+//!
+//! ```no_run
+//! use himmelblau_kerbeiros::*;
+//! use ascii::AsciiString;
+//! use himmelblau_kerberos_crypto::Key;
+//!
+//! let config = Krb5Config::from_env().unwrap();
+//! let username = AsciiString::from_ascii("Bob").unwrap();
+//! let user_key = Key::Secret("S3cr3t".to_string());
+//!
+//! // Use the default realm of the configuration
+//! let tgt_requester = TgtRequester::from_config(&config, None).unwrap();
+//! let credential = tgt_requester.request(&username, Some(&user_key)).unwrap();
+//! ```
+//!
+//! Retrieving a service ticket with the TGT of the default credential
+//! cache (`KRB5CCNAME`), as `kvno` does:
+//!
+//! ```no_run
+//! use himmelblau_kerbeiros::*;
+//! use himmelblau_kerberos_asn1::PrincipalName;
+//! use himmelblau_kerberos_constants::principal_names::NT_SRV_HST;
+//!
+//! let client = KerberosClient::from_env().unwrap();
+//!
+//! let mut service = PrincipalName::new(NT_SRV_HST, "HTTP".to_string());
+//! service.push("www.contoso.com".to_string());
+//! let credential = client.get_credential(&service, None).unwrap();
+//! ```
+//!
 //! # Kerberos References
 //! * [RFC 4120: The Kerberos Network Authentication Service (V5)](https://tools.ietf.org/html/rfc4120)
 //! * [\[MS-KILE\]: Kerberos Protocol Extensions](https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-kile)
 //!
 
-mod transporter;
+pub mod transporter;
+pub use transporter::*;
 
 mod error;
-pub use error::{Error, Result};
+pub use error::{Error, IoError, Result};
 
 pub mod messages;
 pub use messages::*;
@@ -54,4 +87,21 @@ pub use requesters::*;
 pub mod utils;
 pub use utils::*;
 
+pub mod config;
+pub use config::*;
+
+pub mod client;
+pub use client::*;
+
+pub mod acceptor;
+pub use acceptor::*;
+
+pub mod gss;
+pub use gss::*;
+
+#[cfg(feature = "negotiate")]
+pub mod negotiate;
+#[cfg(feature = "negotiate")]
+pub use negotiate::*;
+
 mod mappers;
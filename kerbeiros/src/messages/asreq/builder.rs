@@ -4,11 +4,11 @@ use crate::error::*;
 use ascii::AsciiString;
 use chrono::{Duration, Utc};
 use himmelblau_kerberos_asn1::{
-    AsReq, Asn1Object, EncryptedData, KerbPaPacRequest, KerberosString, PaData,
-    PrincipalName,
+    AsReq, Asn1Object, EncryptedData, KerbPaPacRequest, KerberosString,
+    KerberosTime, PaData, PrincipalName,
 };
 use himmelblau_kerberos_constants::pa_data_types::{
-    PA_ENC_TIMESTAMP, PA_PAC_REQUEST,
+    PA_ENC_TIMESTAMP, PA_PAC_REQUEST, PA_REQ_ENC_PA_REP,
 };
 use himmelblau_kerberos_constants::principal_names::{
     NT_PRINCIPAL, NT_SRV_INST,
@@ -35,14 +35,13 @@ impl<'a> AsReqBuilder<'a> {
         };
     }
 
-    pub fn build_as_req(
+    pub fn create_as_req(
         username: &'a AsciiString,
         user_key: Option<&'a Key>,
         options: &'a AsReqOptions,
-    ) -> Result<Vec<u8>> {
+    ) -> Result<AsReq> {
         let builder = Self::new(username, user_key, options);
-        let as_req = builder.create_as_req_struct()?;
-        return Ok(as_req.build());
+        return builder.create_as_req_struct();
     }
 
     fn create_as_req_struct(&self) -> Result<AsReq> {
@@ -62,41 +61,41 @@ impl<'a> AsReqBuilder<'a> {
             ],
         });
 
-        as_req.req_body.rtime = Some(
-            Utc::now()
-                .checked_add_signed(Duration::weeks(20 * 52))
-                .unwrap()
-                .into(),
-        );
+        let default_lifetime = Duration::weeks(20 * 52);
 
-        as_req.req_body.till = Utc::now()
-            .checked_add_signed(Duration::weeks(20 * 52))
-            .unwrap()
-            .into();
+        as_req.req_body.rtime = Some(Self::time_after(
+            self.options.renew_lifetime().unwrap_or(default_lifetime),
+        )?);
+
+        as_req.req_body.till = Self::time_after(
+            self.options.ticket_lifetime().unwrap_or(default_lifetime),
+        )?;
+
+        as_req.req_body.nonce = self
+            .options
+            .nonce()
+            .unwrap_or_else(|| rand::thread_rng().gen::<u32>());
 
-        as_req.req_body.nonce = rand::thread_rng().gen::<u32>();
+        let mut padata = Vec::new();
 
         if self.options.should_be_pac_included() {
-            as_req.padata = Some(vec![PaData::new(
+            padata.push(PaData::new(
                 PA_PAC_REQUEST,
                 KerbPaPacRequest::new(true).build(),
-            )]);
+            ));
         }
 
+        // Advertise support for the AS-REQ checksum of RFC6806
+        padata.push(PaData::new(PA_REQ_ENC_PA_REP, vec![]));
+
         if let Some(user_key) = &self.user_key {
             let (etype, encrypted_data) =
                 self.produce_encrypted_timestamp(user_key)?;
 
-            let enc_ts_pa_data = PaData::new(
+            padata.push(PaData::new(
                 PA_ENC_TIMESTAMP,
                 EncryptedData::new(etype, None, encrypted_data).build(),
-            );
-
-            if let Some(pa_datas) = &mut as_req.padata {
-                pa_datas.push(enc_ts_pa_data);
-            } else {
-                as_req.padata = Some(vec![enc_ts_pa_data]);
-            }
+            ));
 
             as_req.req_body.etypes.push(etype);
         } else {
@@ -104,9 +103,22 @@ impl<'a> AsReqBuilder<'a> {
                 as_req.req_body.etypes.push(*etype);
             }
         }
+
+        as_req.padata = Some(padata);
         return Ok(as_req);
     }
 
+    fn time_after(lifetime: Duration) -> Result<KerberosTime> {
+        let time =
+            Utc::now().checked_add_signed(lifetime).ok_or_else(|| {
+                Error::ConfigError(format!(
+                    "Lifetime out of range: {}",
+                    lifetime
+                ))
+            })?;
+        return Ok(time.into());
+    }
+
     fn produce_encrypted_timestamp(
         &self,
         user_key: &Key,
@@ -180,6 +192,15 @@ mod test {
         );
     }
 
+    #[test]
+    fn as_req_advertises_enc_pa_rep() {
+        let as_req_struct = create_as_req_struct_with_key(None);
+        assert!(as_req_struct
+            .padata
+            .unwrap()
+            .contains(&PaData::new(PA_REQ_ENC_PA_REP, vec![])));
+    }
+
     fn create_as_req_struct_with_key(user_key: Option<&Key>) -> AsReq {
         let username = AsciiString::from_ascii("Mickey").unwrap();
         let options = AsReqOptions::new(
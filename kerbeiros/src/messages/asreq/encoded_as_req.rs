@@ -0,0 +1,41 @@
+use crate::config::DEFAULT_CLOCKSKEW_SECONDS;
+use chrono::Duration;
+use himmelblau_kerberos_asn1::{AsReq, Asn1Object};
+
+/// AS-REQ along with the exact bytes sent to the KDC, which are required
+/// to verify the checksum of the request included in the reply, and the
+/// clock skew allowed to validate the times of the reply.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct EncodedAsReq {
+    as_req: AsReq,
+    raw: Vec<u8>,
+    clock_skew: Duration,
+}
+
+impl EncodedAsReq {
+    pub fn new(as_req: AsReq) -> Self {
+        let raw = as_req.build();
+        return Self {
+            as_req,
+            raw,
+            clock_skew: Duration::seconds(DEFAULT_CLOCKSKEW_SECONDS),
+        };
+    }
+
+    pub fn with_clock_skew(mut self, clock_skew: Duration) -> Self {
+        self.clock_skew = clock_skew;
+        return self;
+    }
+
+    pub fn as_req(&self) -> &AsReq {
+        return &self.as_req;
+    }
+
+    pub fn raw(&self) -> &[u8] {
+        return &self.raw;
+    }
+
+    pub fn clock_skew(&self) -> Duration {
+        return self.clock_skew;
+    }
+}
@@ -5,3 +5,12 @@ mod options;
 pub(crate) use options::AsReqOptions;
 
 mod timestamp_cipher;
+
+mod reply_validator;
+pub(crate) use reply_validator::{is_later, realms_match, AsRepValidator};
+
+mod encoded_as_req;
+pub(crate) use encoded_as_req::EncodedAsReq;
+
+mod req_checksum;
+pub(crate) use req_checksum::AsReqChecksumVerifier;
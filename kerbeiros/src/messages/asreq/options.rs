@@ -1,5 +1,7 @@
+use crate::config::DEFAULT_CLOCKSKEW_SECONDS;
 use crate::Result;
 use ascii::AsciiString;
+use chrono::Duration;
 use himmelblau_kerberos_constants::etypes::{
     AES128_CTS_HMAC_SHA1_96, AES256_CTS_HMAC_SHA1_96, RC4_HMAC,
 };
@@ -9,12 +11,16 @@ use himmelblau_kerberos_constants::kdc_options::{
 use himmelblau_kerberos_crypto::is_supported_etype;
 use std::collections::HashSet;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub(crate) struct AsReqOptions {
     realm: AsciiString,
     etypes: HashSet<i32>,
     kdc_options: u32,
     pac: bool,
+    ticket_lifetime: Option<Duration>,
+    renew_lifetime: Option<Duration>,
+    clock_skew: Duration,
+    nonce: Option<u32>,
 }
 
 impl AsReqOptions {
@@ -31,9 +37,23 @@ impl AsReqOptions {
             .cloned()
             .collect(),
             pac: true,
+            ticket_lifetime: None,
+            renew_lifetime: None,
+            clock_skew: Duration::seconds(DEFAULT_CLOCKSKEW_SECONDS),
+            nonce: None,
         };
     }
 
+    /// Fixed nonce for the requests, a random one is generated if `None`
+    pub fn nonce(&self) -> Option<u32> {
+        return self.nonce;
+    }
+
+    #[cfg(test)]
+    pub fn set_nonce(&mut self, nonce: Option<u32>) {
+        self.nonce = nonce;
+    }
+
     pub fn realm(&self) -> &AsciiString {
         return &self.realm;
     }
@@ -90,6 +110,36 @@ impl AsReqOptions {
         return self.kdc_options;
     }
 
+    pub fn set_kdc_options(&mut self, kdc_options: u32) {
+        self.kdc_options = kdc_options;
+    }
+
+    pub fn ticket_lifetime(&self) -> Option<Duration> {
+        return self.ticket_lifetime;
+    }
+
+    pub fn set_ticket_lifetime(&mut self, ticket_lifetime: Option<Duration>) {
+        self.ticket_lifetime = ticket_lifetime;
+    }
+
+    pub fn renew_lifetime(&self) -> Option<Duration> {
+        return self.renew_lifetime;
+    }
+
+    pub fn set_renew_lifetime(&mut self, renew_lifetime: Option<Duration>) {
+        self.renew_lifetime = renew_lifetime;
+    }
+
+    /// Maximum difference allowed between the local time and the
+    /// starttime of the tickets received.
+    pub fn clock_skew(&self) -> Duration {
+        return self.clock_skew;
+    }
+
+    pub fn set_clock_skew(&mut self, clock_skew: Duration) {
+        self.clock_skew = clock_skew;
+    }
+
     pub fn should_be_pac_included(&self) -> bool {
         return self.pac;
     }
@@ -0,0 +1,522 @@
+use crate::{Error, Result};
+use chrono::{Duration, Utc};
+use himmelblau_kerberos_asn1::{
+    AsRep, AsReq, EncAsRepPart, KerberosTime, Realm,
+};
+use himmelblau_kerberos_constants::{kdc_options, ticket_flags};
+
+/// Pairs of KDC option requested by the client and the ticket flag that
+/// the KDC is allowed to set in response.
+const OPTION_FLAGS: [(u32, u32); 5] = [
+    (kdc_options::FORWARDABLE, ticket_flags::FORWARDABLE),
+    (kdc_options::PROXIABLE, ticket_flags::PROXIABLE),
+    (kdc_options::ALLOW_POSTDATE, ticket_flags::MAY_POSTDATE),
+    (kdc_options::POSTDATED, ticket_flags::POSTDATE),
+    (
+        kdc_options::RENEWABLE | kdc_options::RENEWABLE_OK,
+        ticket_flags::RENEWABLE,
+    ),
+];
+
+/// Checks that an AS-REP is a legitimate reply to the AS-REQ sent, as
+/// described in RFC4120, section 3.1.5.
+///
+/// When the name canonicalization is requested, the realms can differ in
+/// case, as allowed by RFC6806. The client name can only be changed by the
+/// KDC if the reply is `protected` by a verified PA-REQ-ENC-PA-REP, which
+/// RFC6806, section 11, requires the KDCs to include when they
+/// canonicalize the client or refer it to another realm, since the AS-REQ
+/// is sent in clear.
+///
+/// The starttime of the tickets that are not postdated must be within the
+/// `clock_skew` of the local time, as checked by MIT Kerberos.
+pub(crate) struct AsRepValidator<'a> {
+    as_req: &'a AsReq,
+    as_rep: &'a AsRep,
+    enc_as_rep_part: &'a EncAsRepPart,
+    protected: bool,
+    clock_skew: Duration,
+}
+
+impl<'a> AsRepValidator<'a> {
+    pub fn validate(
+        as_req: &'a AsReq,
+        as_rep: &'a AsRep,
+        enc_as_rep_part: &'a EncAsRepPart,
+        protected: bool,
+        clock_skew: Duration,
+    ) -> Result<()> {
+        let validator = Self {
+            as_req,
+            as_rep,
+            enc_as_rep_part,
+            protected,
+            clock_skew,
+        };
+        validator.validate_nonce()?;
+        validator.validate_etype()?;
+        validator.validate_client()?;
+        validator.validate_server()?;
+        validator.validate_flags()?;
+        validator.validate_times()?;
+        return Ok(());
+    }
+
+    fn is_option_requested(&self, option: u32) -> bool {
+        return self.as_req.req_body.kdc_options.flags & option != 0;
+    }
+
+    fn validate_nonce(&self) -> Result<()> {
+        if self.enc_as_rep_part.nonce != self.as_req.req_body.nonce {
+            return Err(invalid_reply(format!(
+                "nonce {} does not match the requested {}",
+                self.enc_as_rep_part.nonce, self.as_req.req_body.nonce
+            )));
+        }
+        return Ok(());
+    }
+
+    fn validate_etype(&self) -> Result<()> {
+        let etype = self.as_rep.enc_part.etype;
+        if !self.as_req.req_body.etypes.contains(&etype) {
+            return Err(invalid_reply(format!(
+                "etype {} was not requested",
+                etype
+            )));
+        }
+        return Ok(());
+    }
+
+    fn validate_client(&self) -> Result<()> {
+        let canonicalize = self.is_option_requested(kdc_options::CANONICALIZE);
+        if canonicalize && self.protected {
+            return Ok(());
+        }
+
+        let cname_matches = match &self.as_req.req_body.cname {
+            Some(cname) => *cname == self.as_rep.cname,
+            None => true,
+        };
+        let crealm_matches = realms_match(
+            &self.as_req.req_body.realm,
+            &self.as_rep.crealm,
+            canonicalize,
+        );
+
+        if !cname_matches || !crealm_matches {
+            return Err(invalid_reply(format!(
+                "client {}@{} was not requested",
+                self.as_rep.cname, self.as_rep.crealm
+            )));
+        }
+        return Ok(());
+    }
+
+    fn validate_server(&self) -> Result<()> {
+        let sname = &self.enc_as_rep_part.sname;
+        let srealm = &self.enc_as_rep_part.srealm;
+
+        if *sname != self.as_rep.ticket.sname
+            || *srealm != self.as_rep.ticket.realm
+        {
+            return Err(invalid_reply(format!(
+                "server {}@{} does not match the ticket",
+                sname, srealm
+            )));
+        }
+
+        let sname_matches = match &self.as_req.req_body.sname {
+            Some(requested_sname) => requested_sname == sname,
+            None => true,
+        };
+        let srealm_matches = realms_match(
+            &self.as_req.req_body.realm,
+            srealm,
+            self.is_option_requested(kdc_options::CANONICALIZE),
+        );
+
+        if !sname_matches || !srealm_matches {
+            return Err(invalid_reply(format!(
+                "server {}@{} was not requested",
+                sname, srealm
+            )));
+        }
+        return Ok(());
+    }
+
+    fn validate_flags(&self) -> Result<()> {
+        let flags = self.enc_as_rep_part.flags.flags;
+        for (option, flag) in OPTION_FLAGS.iter() {
+            if flags & flag != 0 && !self.is_option_requested(*option) {
+                return Err(invalid_reply(format!(
+                    "ticket flag {:#010x} was not requested",
+                    flag
+                )));
+            }
+        }
+        return Ok(());
+    }
+
+    fn validate_times(&self) -> Result<()> {
+        let req_body = &self.as_req.req_body;
+        let enc_part = self.enc_as_rep_part;
+
+        let starttime =
+            enc_part.starttime.as_ref().unwrap_or(&enc_part.authtime);
+
+        match &req_body.from {
+            Some(from) => {
+                if enc_part.starttime.as_ref() != Some(from) {
+                    return Err(invalid_reply(
+                        "starttime does not match the requested".to_string(),
+                    ));
+                }
+            }
+            None => {
+                let skew = (Utc::now() - ***starttime).num_seconds().abs();
+                if skew > self.clock_skew.num_seconds() {
+                    return Err(invalid_reply(
+                        "starttime is out of the clock skew".to_string(),
+                    ));
+                }
+            }
+        }
+
+        if is_later(starttime, &enc_part.endtime) {
+            return Err(invalid_reply(
+                "endtime is earlier than starttime".to_string(),
+            ));
+        }
+
+        if is_later(&enc_part.endtime, &req_body.till) {
+            return Err(invalid_reply(
+                "endtime is later than the requested".to_string(),
+            ));
+        }
+
+        if let Some(renew_till) = &enc_part.renew_till {
+            let max_renew_till =
+                if self.is_option_requested(kdc_options::RENEWABLE) {
+                    req_body.rtime.as_ref()
+                } else {
+                    Some(&req_body.till)
+                };
+
+            if let Some(max_renew_till) = max_renew_till {
+                if is_later(renew_till, max_renew_till) {
+                    return Err(invalid_reply(
+                        "renew-till is later than the requested".to_string(),
+                    ));
+                }
+            }
+        }
+
+        return Ok(());
+    }
+}
+
+/// Compares the realms, ignoring the case if the KDC is allowed to
+/// canonicalize them.
+pub(crate) fn realms_match(
+    requested: &Realm,
+    received: &Realm,
+    canonicalize: bool,
+) -> bool {
+    if canonicalize {
+        return requested.eq_ignore_ascii_case(received);
+    }
+    return requested == received;
+}
+
+pub(crate) fn is_later(time: &KerberosTime, other: &KerberosTime) -> bool {
+    return ***time > ***other;
+}
+
+fn invalid_reply(reason: String) -> Error {
+    return Error::InvalidKdcReply(reason);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::messages::asreq::{AsReqBuilder, AsReqOptions};
+    use ascii::AsciiString;
+    use himmelblau_kerberos_asn1::{
+        EncryptedData, KdcReqBody, PrincipalName, Ticket,
+    };
+    use himmelblau_kerberos_constants::etypes::*;
+    use himmelblau_kerberos_constants::principal_names::{
+        NT_PRINCIPAL, NT_SRV_INST,
+    };
+
+    fn krbtgt_name(realm: &str) -> PrincipalName {
+        let mut sname = PrincipalName::new(NT_SRV_INST, "krbtgt".to_string());
+        sname.push(realm.to_string());
+        return sname;
+    }
+
+    fn create_as_req() -> AsReq {
+        let now = Utc::now();
+        return AsReq {
+            req_body: KdcReqBody {
+                kdc_options: (kdc_options::FORWARDABLE
+                    | kdc_options::RENEWABLE)
+                    .into(),
+                cname: Some(PrincipalName::new(
+                    NT_PRINCIPAL,
+                    "mickey".to_string(),
+                )),
+                realm: "KINGDOM.HEARTS".to_string(),
+                sname: Some(krbtgt_name("KINGDOM.HEARTS")),
+                till: (now + Duration::hours(10)).into(),
+                rtime: Some((now + Duration::days(7)).into()),
+                nonce: 1234,
+                etypes: vec![AES256_CTS_HMAC_SHA1_96],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+    }
+
+    fn create_as_rep() -> (AsRep, EncAsRepPart) {
+        let now = Utc::now();
+        let as_rep = AsRep {
+            crealm: "KINGDOM.HEARTS".to_string(),
+            cname: PrincipalName::new(NT_PRINCIPAL, "mickey".to_string()),
+            ticket: Ticket {
+                realm: "KINGDOM.HEARTS".to_string(),
+                sname: krbtgt_name("KINGDOM.HEARTS"),
+                ..Default::default()
+            },
+            enc_part: EncryptedData {
+                etype: AES256_CTS_HMAC_SHA1_96,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let enc_as_rep_part = EncAsRepPart {
+            nonce: 1234,
+            flags: (ticket_flags::INITIAL
+                | ticket_flags::FORWARDABLE
+                | ticket_flags::RENEWABLE)
+                .into(),
+            authtime: now.into(),
+            endtime: (now + Duration::hours(8)).into(),
+            renew_till: Some((now + Duration::days(6)).into()),
+            srealm: "KINGDOM.HEARTS".to_string(),
+            sname: krbtgt_name("KINGDOM.HEARTS"),
+            ..Default::default()
+        };
+        return (as_rep, enc_as_rep_part);
+    }
+
+    fn clock_skew() -> Duration {
+        return Duration::minutes(5);
+    }
+
+    fn validate(
+        as_req: &AsReq,
+        as_rep: &AsRep,
+        enc_as_rep_part: &EncAsRepPart,
+    ) -> String {
+        match AsRepValidator::validate(
+            as_req,
+            as_rep,
+            enc_as_rep_part,
+            false,
+            clock_skew(),
+        ) {
+            Err(Error::InvalidKdcReply(reason)) => return reason,
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn validate_legitimate_reply() {
+        let (as_rep, enc_as_rep_part) = create_as_rep();
+        AsRepValidator::validate(
+            &create_as_req(),
+            &as_rep,
+            &enc_as_rep_part,
+            false,
+            clock_skew(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn error_with_different_nonce() {
+        let (as_rep, mut enc_as_rep_part) = create_as_rep();
+        enc_as_rep_part.nonce = 4321;
+        assert_eq!(
+            "nonce 4321 does not match the requested 1234",
+            validate(&create_as_req(), &as_rep, &enc_as_rep_part)
+        );
+    }
+
+    #[test]
+    fn error_with_etype_not_requested() {
+        let (mut as_rep, enc_as_rep_part) = create_as_rep();
+        as_rep.enc_part.etype = RC4_HMAC;
+        assert_eq!(
+            "etype 23 was not requested",
+            validate(&create_as_req(), &as_rep, &enc_as_rep_part)
+        );
+    }
+
+    #[test]
+    fn error_with_different_client() {
+        let (mut as_rep, enc_as_rep_part) = create_as_rep();
+        as_rep.cname = PrincipalName::new(NT_PRINCIPAL, "goofy".to_string());
+        assert_eq!(
+            "client goofy@KINGDOM.HEARTS was not requested",
+            validate(&create_as_req(), &as_rep, &enc_as_rep_part)
+        );
+    }
+
+    #[test]
+    fn accept_canonicalized_names() {
+        let mut as_req = create_as_req();
+        as_req.req_body.kdc_options.flags |= kdc_options::CANONICALIZE;
+        as_req.req_body.realm = "kingdom.hearts".to_string();
+        let (mut as_rep, enc_as_rep_part) = create_as_rep();
+        as_rep.cname = PrincipalName::new(NT_PRINCIPAL, "Mickey".to_string());
+
+        AsRepValidator::validate(
+            &as_req,
+            &as_rep,
+            &enc_as_rep_part,
+            true,
+            clock_skew(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn accept_canonicalized_realm_without_protection() {
+        let mut as_req = create_as_req();
+        as_req.req_body.kdc_options.flags |= kdc_options::CANONICALIZE;
+        as_req.req_body.realm = "kingdom.hearts".to_string();
+        let (as_rep, enc_as_rep_part) = create_as_rep();
+
+        AsRepValidator::validate(
+            &as_req,
+            &as_rep,
+            &enc_as_rep_part,
+            false,
+            clock_skew(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn error_with_different_client_of_default_options() {
+        let username = AsciiString::from_ascii("mickey").unwrap();
+        let options = AsReqOptions::new(
+            AsciiString::from_ascii("KINGDOM.HEARTS").unwrap(),
+        );
+        let as_req =
+            AsReqBuilder::create_as_req(&username, None, &options).unwrap();
+        let (mut as_rep, mut enc_as_rep_part) = create_as_rep();
+        as_rep.cname = PrincipalName::new(NT_PRINCIPAL, "goofy".to_string());
+        enc_as_rep_part.nonce = as_req.req_body.nonce;
+        enc_as_rep_part.endtime = as_req.req_body.till.clone();
+        enc_as_rep_part.renew_till = as_req.req_body.rtime.clone();
+
+        assert_eq!(
+            "client goofy@KINGDOM.HEARTS was not requested",
+            validate(&as_req, &as_rep, &enc_as_rep_part)
+        );
+    }
+
+    #[test]
+    fn error_with_different_server() {
+        let (mut as_rep, mut enc_as_rep_part) = create_as_rep();
+        enc_as_rep_part.sname = krbtgt_name("EVIL.REALM");
+        as_rep.ticket.sname = krbtgt_name("EVIL.REALM");
+        assert_eq!(
+            "server krbtgt/EVIL.REALM@KINGDOM.HEARTS was not requested",
+            validate(&create_as_req(), &as_rep, &enc_as_rep_part)
+        );
+    }
+
+    #[test]
+    fn error_with_server_different_from_ticket() {
+        let (mut as_rep, enc_as_rep_part) = create_as_rep();
+        as_rep.ticket.realm = "EVIL.REALM".to_string();
+        assert_eq!(
+            "server krbtgt/KINGDOM.HEARTS@KINGDOM.HEARTS does not match \
+             the ticket",
+            validate(&create_as_req(), &as_rep, &enc_as_rep_part)
+        );
+    }
+
+    #[test]
+    fn error_with_flag_not_requested() {
+        let (as_rep, mut enc_as_rep_part) = create_as_rep();
+        enc_as_rep_part.flags.flags |= ticket_flags::PROXIABLE;
+        assert_eq!(
+            "ticket flag 0x10000000 was not requested",
+            validate(&create_as_req(), &as_rep, &enc_as_rep_part)
+        );
+    }
+
+    #[test]
+    fn error_with_stale_starttime() {
+        let (as_rep, mut enc_as_rep_part) = create_as_rep();
+        enc_as_rep_part.starttime =
+            Some((Utc::now() - Duration::minutes(10)).into());
+        assert_eq!(
+            "starttime is out of the clock skew",
+            validate(&create_as_req(), &as_rep, &enc_as_rep_part)
+        );
+    }
+
+    #[test]
+    fn accept_starttime_within_clock_skew() {
+        let (as_rep, mut enc_as_rep_part) = create_as_rep();
+        enc_as_rep_part.authtime = (Utc::now() - Duration::hours(1)).into();
+        enc_as_rep_part.starttime =
+            Some((Utc::now() + Duration::minutes(2)).into());
+        AsRepValidator::validate(
+            &create_as_req(),
+            &as_rep,
+            &enc_as_rep_part,
+            false,
+            clock_skew(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn error_with_endtime_later_than_requested() {
+        let (as_rep, mut enc_as_rep_part) = create_as_rep();
+        enc_as_rep_part.endtime = (Utc::now() + Duration::days(1)).into();
+        assert_eq!(
+            "endtime is later than the requested",
+            validate(&create_as_req(), &as_rep, &enc_as_rep_part)
+        );
+    }
+
+    #[test]
+    fn error_with_endtime_before_starttime() {
+        let (as_rep, mut enc_as_rep_part) = create_as_rep();
+        enc_as_rep_part.endtime = (Utc::now() - Duration::hours(1)).into();
+        assert_eq!(
+            "endtime is earlier than starttime",
+            validate(&create_as_req(), &as_rep, &enc_as_rep_part)
+        );
+    }
+
+    #[test]
+    fn error_with_renew_till_later_than_requested() {
+        let (as_rep, mut enc_as_rep_part) = create_as_rep();
+        enc_as_rep_part.renew_till =
+            Some((Utc::now() + Duration::days(8)).into());
+        assert_eq!(
+            "renew-till is later than the requested",
+            validate(&create_as_req(), &as_rep, &enc_as_rep_part)
+        );
+    }
+}
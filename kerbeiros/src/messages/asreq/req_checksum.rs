@@ -0,0 +1,176 @@
+use crate::{Error, Result};
+use himmelblau_kerberos_asn1::{Asn1Object, Checksum, EncAsRepPart};
+use himmelblau_kerberos_constants::key_usages::KEY_USAGE_AS_REQ;
+use himmelblau_kerberos_constants::pa_data_types::PA_REQ_ENC_PA_REP;
+use himmelblau_kerberos_constants::ticket_flags;
+use himmelblau_kerberos_crypto::{checksum, mandatory_checksum_type};
+
+/// Verifies the checksum of the AS-REQ that the KDC includes in the
+/// `encrypted-pa-data` of the reply, as described in RFC6806, section 11.
+///
+/// Since the AS-REQ is sent in clear, the checksum protects the requested
+/// options and encryption types from being tampered with.
+pub(crate) struct AsReqChecksumVerifier {}
+
+impl AsReqChecksumVerifier {
+    /// Verifies the checksum with the reply key, used to encrypt the
+    /// AS-REP, and returns whether the reply was protected by it. An error
+    /// is returned if the checksum is invalid, or if the KDC sets the
+    /// `enc-pa-rep` flag without including it.
+    pub fn verify(
+        raw_as_req: &[u8],
+        enc_as_rep_part: &EncAsRepPart,
+        reply_etype: i32,
+        reply_key: &[u8],
+    ) -> Result<bool> {
+        let raw_checksum = match Self::find_raw_checksum(enc_as_rep_part) {
+            Some(raw_checksum) => raw_checksum,
+            None => {
+                if enc_as_rep_part.flags.flags & ticket_flags::ENC_PA_REP != 0 {
+                    return Err(invalid_checksum(
+                        "missing PA-REQ-ENC-PA-REP".to_string(),
+                    ));
+                }
+                return Ok(false);
+            }
+        };
+
+        let (_, req_checksum) = Checksum::parse(raw_checksum)?;
+        let cksumtype = mandatory_checksum_type(reply_etype)?;
+        if req_checksum.cksumtype != cksumtype {
+            return Err(invalid_checksum(format!(
+                "checksum type {} does not match the reply key",
+                req_checksum.cksumtype
+            )));
+        }
+
+        let expected_checksum =
+            checksum(cksumtype, reply_key, KEY_USAGE_AS_REQ, raw_as_req)?;
+        if expected_checksum != req_checksum.checksum {
+            return Err(invalid_checksum(
+                "checksum of the AS-REQ does not match".to_string(),
+            ));
+        }
+
+        return Ok(true);
+    }
+
+    fn find_raw_checksum(enc_as_rep_part: &EncAsRepPart) -> Option<&[u8]> {
+        let encrypted_pa_data = enc_as_rep_part.encrypted_pa_data.as_ref()?;
+        return encrypted_pa_data
+            .iter()
+            .find(|pa_data| pa_data.padata_type == PA_REQ_ENC_PA_REP)
+            .map(|pa_data| pa_data.padata_value.as_slice());
+    }
+}
+
+fn invalid_checksum(reason: String) -> Error {
+    return Error::InvalidKdcReply(reason);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use himmelblau_kerberos_asn1::PaData;
+    use himmelblau_kerberos_constants::checksum_types::HMAC_SHA1_96_AES256;
+    use himmelblau_kerberos_constants::etypes::AES256_CTS_HMAC_SHA1_96;
+
+    const RAW_AS_REQ: &[u8] = &[0x6a, 0x03, 0x30, 0x01, 0x00];
+    const REPLY_KEY: &[u8] = &[0x11; 32];
+
+    fn create_enc_as_rep_part(
+        flags: u32,
+        req_checksum: Option<Checksum>,
+    ) -> EncAsRepPart {
+        let mut enc_as_rep_part = EncAsRepPart {
+            flags: flags.into(),
+            ..Default::default()
+        };
+        if let Some(req_checksum) = req_checksum {
+            enc_as_rep_part.encrypted_pa_data = Some(vec![PaData::new(
+                PA_REQ_ENC_PA_REP,
+                req_checksum.build(),
+            )]);
+        }
+        return enc_as_rep_part;
+    }
+
+    fn create_checksum(raw_as_req: &[u8]) -> Checksum {
+        return Checksum {
+            cksumtype: HMAC_SHA1_96_AES256,
+            checksum: checksum(
+                HMAC_SHA1_96_AES256,
+                REPLY_KEY,
+                KEY_USAGE_AS_REQ,
+                raw_as_req,
+            )
+            .unwrap(),
+        };
+    }
+
+    fn verify(enc_as_rep_part: &EncAsRepPart) -> Result<bool> {
+        return AsReqChecksumVerifier::verify(
+            RAW_AS_REQ,
+            enc_as_rep_part,
+            AES256_CTS_HMAC_SHA1_96,
+            REPLY_KEY,
+        );
+    }
+
+    #[test]
+    fn accept_valid_checksum() {
+        let enc_as_rep_part = create_enc_as_rep_part(
+            ticket_flags::ENC_PA_REP,
+            Some(create_checksum(RAW_AS_REQ)),
+        );
+        assert!(verify(&enc_as_rep_part).unwrap());
+    }
+
+    #[test]
+    fn accept_reply_of_kdc_without_support() {
+        assert!(
+            !verify(&create_enc_as_rep_part(ticket_flags::INITIAL, None))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn error_with_checksum_of_other_request() {
+        let enc_as_rep_part = create_enc_as_rep_part(
+            ticket_flags::ENC_PA_REP,
+            Some(create_checksum(&[0x6a, 0x03, 0x30, 0x01, 0x01])),
+        );
+        assert_eq!(
+            Err(Error::InvalidKdcReply(
+                "checksum of the AS-REQ does not match".to_string()
+            )),
+            verify(&enc_as_rep_part)
+        );
+    }
+
+    #[test]
+    fn error_with_flag_but_without_checksum() {
+        assert_eq!(
+            Err(Error::InvalidKdcReply(
+                "missing PA-REQ-ENC-PA-REP".to_string()
+            )),
+            verify(&create_enc_as_rep_part(ticket_flags::ENC_PA_REP, None))
+        );
+    }
+
+    #[test]
+    fn error_with_unkeyed_checksum_type() {
+        let mut req_checksum = create_checksum(RAW_AS_REQ);
+        req_checksum.cksumtype = 7;
+        let enc_as_rep_part = create_enc_as_rep_part(
+            ticket_flags::ENC_PA_REP,
+            Some(req_checksum),
+        );
+        assert_eq!(
+            Err(Error::InvalidKdcReply(
+                "checksum type 7 does not match the reply key".to_string()
+            )),
+            verify(&enc_as_rep_part)
+        );
+    }
+}
@@ -110,7 +110,7 @@ impl<'a> AsReqTimestampCipher<'a> {
                 key,
                 KEY_USAGE_AS_REQ_TIMESTAMP,
                 &self.raw_timestamp,
-            ),
+            )?,
         ));
     }
 
@@ -128,7 +128,7 @@ impl<'a> AsReqTimestampCipher<'a> {
                 salt,
                 KEY_USAGE_AS_REQ_TIMESTAMP,
                 &self.raw_timestamp,
-            ),
+            )?,
         ));
     }
 
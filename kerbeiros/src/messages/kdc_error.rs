@@ -0,0 +1,307 @@
+use himmelblau_kerberos_asn1::{
+    Asn1Object, KerbErrorData, KrbError, MethodData, TypedData,
+};
+use himmelblau_kerberos_constants::error_codes::ErrorCode;
+use himmelblau_kerberos_constants::kerb_error_data_type::KERB_ERR_TYPE_EXTENDED;
+use std::fmt;
+
+/// *KRB-ERROR* received from the KDC, with the error code and the
+/// `e-data` field decoded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KdcError {
+    krb_error: KrbError,
+    error_code: Option<ErrorCode>,
+    error_data: Option<ErrorData>,
+}
+
+impl KdcError {
+    pub fn new(krb_error: KrbError) -> Self {
+        let error_code = ErrorCode::from_code(krb_error.error_code);
+        let error_data = krb_error.e_data.as_deref().map(ErrorData::parse);
+        return Self {
+            krb_error,
+            error_code,
+            error_data,
+        };
+    }
+
+    /// The raw *KRB-ERROR* message.
+    pub fn krb_error(&self) -> &KrbError {
+        return &self.krb_error;
+    }
+
+    pub fn into_krb_error(self) -> KrbError {
+        return self.krb_error;
+    }
+
+    /// Named error code, or `None` if the KDC sent an unknown code.
+    pub fn error_code(&self) -> Option<ErrorCode> {
+        return self.error_code;
+    }
+
+    /// Numeric value of the error code.
+    pub fn raw_error_code(&self) -> i32 {
+        return self.krb_error.error_code;
+    }
+
+    /// Additional text sent by the KDC to explain the error.
+    pub fn e_text(&self) -> Option<&str> {
+        return self.krb_error.e_text.as_deref();
+    }
+
+    /// Decoded `e-data` field.
+    pub fn error_data(&self) -> Option<&ErrorData> {
+        return self.error_data.as_ref();
+    }
+
+    /// NTSTATUS sent by Active Directory in the extended error data.
+    pub fn ntstatus(&self) -> Option<NtStatus> {
+        return self.error_data.as_ref().and_then(ErrorData::ntstatus);
+    }
+}
+
+impl From<KrbError> for KdcError {
+    fn from(krb_error: KrbError) -> Self {
+        return Self::new(krb_error);
+    }
+}
+
+impl fmt::Display for KdcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.error_code {
+            Some(code) => write!(f, "{}: {}", code, code.description())?,
+            None => write!(f, "Unknown error code {}", self.raw_error_code())?,
+        }
+
+        if let Some(ntstatus) = self.ntstatus() {
+            write!(f, ": {}", ntstatus)?;
+        }
+
+        if let Some(e_text) = self.e_text() {
+            if !e_text.is_empty() {
+                write!(f, " ({})", e_text)?;
+            }
+        }
+
+        return Ok(());
+    }
+}
+
+/// Content of the `e-data` field of *KRB-ERROR*.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorData {
+    /// *METHOD-DATA*, usually sent with `KDC_ERR_PREAUTH_REQUIRED` to
+    /// indicate the accepted pre-authentication methods.
+    MethodData(MethodData),
+
+    /// *KERB-ERROR-DATA* of MS-KILE, sent by Active Directory.
+    KerbErrorData(KerbErrorData),
+
+    /// *TYPED-DATA* of RFC4120.
+    TypedData(TypedData),
+
+    /// Data that could not be decoded.
+    Raw(Vec<u8>),
+}
+
+impl ErrorData {
+    /// Decodes the `e-data` field. The formats are tried in order and the
+    /// first one that consumes all the data is chosen.
+    pub fn parse(raw: &[u8]) -> Self {
+        if let Ok((rest, method_data)) = MethodData::parse(raw) {
+            if rest.is_empty() {
+                return Self::MethodData(method_data);
+            }
+        }
+
+        if let Ok((rest, kerb_error_data)) = KerbErrorData::parse(raw) {
+            if rest.is_empty() {
+                return Self::KerbErrorData(kerb_error_data);
+            }
+        }
+
+        if let Ok((rest, typed_data)) = TypedData::parse(raw) {
+            if rest.is_empty() {
+                return Self::TypedData(typed_data);
+            }
+        }
+
+        return Self::Raw(raw.to_vec());
+    }
+
+    /// Extracts the NTSTATUS of a *KERB-EXT-ERROR* included in a
+    /// *KERB-ERROR-DATA* of type `KERB_ERR_TYPE_EXTENDED`, as described
+    /// in MS-KILE, section 2.2.1.
+    pub fn ntstatus(&self) -> Option<NtStatus> {
+        let kerb_error_data = match self {
+            Self::KerbErrorData(kerb_error_data) => kerb_error_data,
+            _ => return None,
+        };
+
+        if kerb_error_data.data_type != KERB_ERR_TYPE_EXTENDED {
+            return None;
+        }
+
+        let data_value = kerb_error_data.data_value.as_ref()?;
+        if data_value.len() < 4 {
+            return None;
+        }
+
+        let status = u32::from_le_bytes([
+            data_value[0],
+            data_value[1],
+            data_value[2],
+            data_value[3],
+        ]);
+        return Some(NtStatus(status));
+    }
+}
+
+/// Windows status code, as defined in MS-ERREF, section 2.3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NtStatus(pub u32);
+
+impl NtStatus {
+    /// Description of the most common status codes returned by Active
+    /// Directory in authentication errors.
+    pub fn description(&self) -> Option<&'static str> {
+        let description = match self.0 {
+            0xC0000064 => "user does not exist",
+            0xC000006A => "wrong password",
+            0xC000006D => "logon failure",
+            0xC000006E => "account restriction",
+            0xC000006F => "logon outside allowed hours",
+            0xC0000070 => "logon from unauthorized workstation",
+            0xC0000071 => "password expired",
+            0xC0000072 => "account disabled",
+            0xC000015B => "logon type not granted",
+            0xC0000133 => "time difference at the domain controller",
+            0xC0000193 => "account expired",
+            0xC0000224 => "password must change",
+            0xC0000234 => "account locked",
+            0xC0000413 => "authentication firewall failed",
+            _ => return None,
+        };
+        return Some(description);
+    }
+}
+
+impl fmt::Display for NtStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.description() {
+            Some(description) => {
+                return write!(f, "{} (0x{:08X})", description, self.0);
+            }
+            None => return write!(f, "NTSTATUS 0x{:08X}", self.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use himmelblau_kerberos_asn1::{PaData, TypedDataEntry};
+    use himmelblau_kerberos_constants::error_codes::*;
+    use himmelblau_kerberos_constants::pa_data_types::*;
+
+    fn krb_error_with_e_data(error_code: i32, e_data: Vec<u8>) -> KrbError {
+        return KrbError {
+            pvno: 5,
+            msg_type: 30,
+            error_code,
+            e_data: Some(e_data),
+            ..Default::default()
+        };
+    }
+
+    #[test]
+    fn decode_method_data() {
+        let method_data: MethodData =
+            vec![PaData::new(PA_ENC_TIMESTAMP, vec![])];
+        let kdc_error = KdcError::from(krb_error_with_e_data(
+            KDC_ERR_PREAUTH_REQUIRED,
+            method_data.build(),
+        ));
+
+        assert_eq!(
+            Some(ErrorCode::KdcErrPreauthRequired),
+            kdc_error.error_code()
+        );
+        assert_eq!(
+            Some(&ErrorData::MethodData(method_data)),
+            kdc_error.error_data()
+        );
+        assert_eq!(None, kdc_error.ntstatus());
+    }
+
+    #[test]
+    fn decode_kerb_error_data_with_ntstatus() {
+        let kerb_error_data = KerbErrorData {
+            data_type: KERB_ERR_TYPE_EXTENDED,
+            data_value: Some(vec![
+                0x34, 0x02, 0x00, 0xc0, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00,
+                0x00, 0x00,
+            ]),
+        };
+        let mut krb_error = krb_error_with_e_data(
+            KDC_ERR_CLIENT_REVOKED,
+            kerb_error_data.build(),
+        );
+        krb_error.e_text = Some("Locked".to_string());
+        let kdc_error = KdcError::new(krb_error);
+
+        assert_eq!(Some(NtStatus(0xC0000234)), kdc_error.ntstatus());
+        assert_eq!(
+            "KDC_ERR_CLIENT_REVOKED (18): Clients credentials have been \
+             revoked: account locked (0xC0000234) (Locked)",
+            kdc_error.to_string()
+        );
+    }
+
+    #[test]
+    fn decode_typed_data() {
+        let typed_data: TypedData = vec![TypedDataEntry {
+            data_type: 1,
+            data_value: Some(vec![0x01]),
+        }];
+        let kdc_error = KdcError::new(krb_error_with_e_data(
+            KRB_ERR_GENERIC,
+            typed_data.build(),
+        ));
+
+        assert_eq!(
+            Some(&ErrorData::TypedData(typed_data)),
+            kdc_error.error_data()
+        );
+    }
+
+    #[test]
+    fn keep_undecodable_e_data() {
+        let kdc_error = KdcError::new(krb_error_with_e_data(
+            KRB_ERR_GENERIC,
+            vec![0x01, 0x02],
+        ));
+
+        assert_eq!(
+            Some(&ErrorData::Raw(vec![0x01, 0x02])),
+            kdc_error.error_data()
+        );
+    }
+
+    #[test]
+    fn display_unknown_error_code() {
+        let kdc_error = KdcError::new(KrbError {
+            error_code: 1000,
+            ..Default::default()
+        });
+
+        assert_eq!("Unknown error code 1000", kdc_error.to_string());
+        assert_eq!(None, kdc_error.error_code());
+        assert_eq!(None, kdc_error.error_data());
+    }
+
+    #[test]
+    fn display_unknown_ntstatus() {
+        assert_eq!("NTSTATUS 0xC0000001", NtStatus(0xC0000001).to_string());
+    }
+}
@@ -3,6 +3,14 @@
 mod asreq;
 pub(crate) use asreq::*;
 
+mod tgsreq;
+pub(crate) use tgsreq::*;
+
+mod kdc_error;
+pub use kdc_error::*;
+
 pub use himmelblau_kerberos_asn1::AsRep;
 pub use himmelblau_kerberos_asn1::AsReq;
 pub use himmelblau_kerberos_asn1::KrbError;
+pub use himmelblau_kerberos_asn1::TgsRep;
+pub use himmelblau_kerberos_asn1::TgsReq;
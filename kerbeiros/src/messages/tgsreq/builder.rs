@@ -0,0 +1,211 @@
+use super::reply_validator::is_tgs;
+use crate::credentials::Credential;
+use crate::error::*;
+use chrono::Utc;
+use himmelblau_kerberos_asn1::{
+    ApReq, Asn1Object, Authenticator, Checksum, EncryptedData, KerberosTime,
+    PaData, PrincipalName, Realm, TgsReq,
+};
+use himmelblau_kerberos_constants::key_usages::{
+    KEY_USAGE_TGS_REQ_AUTHEN, KEY_USAGE_TGS_REQ_AUTHEN_CKSUM,
+};
+use himmelblau_kerberos_constants::pa_data_types::PA_TGS_REQ;
+use himmelblau_kerberos_crypto::{
+    checksum, mandatory_checksum_type, new_kerberos_cipher,
+};
+use rand::Rng;
+
+/// Builds the TGS-REQ to request a ticket for a service with a TGT, as
+/// described in RFC4120, section 3.3.1.
+///
+/// The request is authenticated with an AP-REQ that includes the TGT and
+/// an authenticator with the checksum of the request body, both keyed
+/// with the TGT session key.
+pub(crate) struct TgsReqBuilder<'a> {
+    tgt: &'a Credential,
+    sname: &'a PrincipalName,
+    kdc_options: u32,
+    etypes: &'a [i32],
+}
+
+impl<'a> TgsReqBuilder<'a> {
+    pub fn create_tgs_req(
+        tgt: &'a Credential,
+        sname: &'a PrincipalName,
+        kdc_options: u32,
+        etypes: &'a [i32],
+    ) -> Result<TgsReq> {
+        let builder = Self {
+            tgt,
+            sname,
+            kdc_options,
+            etypes,
+        };
+        return builder.create_tgs_req_struct();
+    }
+
+    fn create_tgs_req_struct(&self) -> Result<TgsReq> {
+        let mut tgs_req = TgsReq::default();
+        tgs_req.req_body.kdc_options = self.kdc_options.into();
+        tgs_req.req_body.realm = tgt_realm(self.tgt)?;
+        tgs_req.req_body.sname = Some(self.sname.clone());
+        tgs_req.req_body.till = self.tgt.endtime().clone();
+        tgs_req.req_body.nonce = rand::thread_rng().gen::<u32>();
+        tgs_req.req_body.etypes = self.etypes.to_vec();
+
+        let ap_req = self.create_ap_req(&tgs_req.req_body.build())?;
+        tgs_req.padata = Some(vec![PaData::new(PA_TGS_REQ, ap_req.build())]);
+
+        return Ok(tgs_req);
+    }
+
+    fn create_ap_req(&self, raw_req_body: &[u8]) -> Result<ApReq> {
+        let session_key = self.tgt.key();
+        let etype = session_key.keytype;
+        let cksumtype = mandatory_checksum_type(etype)?;
+
+        let now = Utc::now();
+        let mut authenticator = Authenticator {
+            crealm: self.tgt.crealm().clone(),
+            cname: self.tgt.cname().clone(),
+            cksum: Some(Checksum {
+                cksumtype,
+                checksum: checksum(
+                    cksumtype,
+                    &session_key.keyvalue,
+                    KEY_USAGE_TGS_REQ_AUTHEN_CKSUM,
+                    raw_req_body,
+                )?,
+            }),
+            ..Default::default()
+        };
+        authenticator.ctime = KerberosTime::from(now);
+
+        let cipher = new_kerberos_cipher(etype)?;
+        let encrypted_authenticator = cipher.encrypt(
+            &session_key.keyvalue,
+            KEY_USAGE_TGS_REQ_AUTHEN,
+            &authenticator.build(),
+        )?;
+
+        return Ok(ApReq {
+            ticket: self.tgt.ticket().clone(),
+            authenticator: EncryptedData::new(
+                etype,
+                None,
+                encrypted_authenticator,
+            ),
+            ..Default::default()
+        });
+    }
+}
+
+/// Realm of the KDC that issued the TGT, which is the one that must
+/// receive the TGS-REQ. For a cross-realm TGT `krbtgt/B@A`, this is `B`.
+pub(crate) fn tgt_realm(tgt: &Credential) -> Result<Realm> {
+    let sname = tgt.sname();
+    if !is_tgs(sname) {
+        return Err(Error::InvalidTgt(format!(
+            "{} is not a ticket granting service",
+            sname
+        )));
+    }
+    return Ok(sname.name_string[1].clone());
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use himmelblau_kerberos_asn1::{EncAsRepPart, EncryptionKey, Ticket};
+    use himmelblau_kerberos_constants::etypes::AES256_CTS_HMAC_SHA1_96;
+    use himmelblau_kerberos_constants::kdc_options::CANONICALIZE;
+    use himmelblau_kerberos_constants::principal_names::{
+        NT_PRINCIPAL, NT_SRV_HST, NT_SRV_INST,
+    };
+
+    fn create_tgt(realm: &str) -> Credential {
+        return Credential::new(
+            "KINGDOM.HEARTS".to_string(),
+            PrincipalName::new(NT_PRINCIPAL, "mickey".to_string()),
+            Ticket::default(),
+            EncAsRepPart {
+                key: EncryptionKey::new(
+                    AES256_CTS_HMAC_SHA1_96,
+                    vec![0x11; 32],
+                ),
+                srealm: "KINGDOM.HEARTS".to_string(),
+                sname: PrincipalName {
+                    name_type: NT_SRV_INST,
+                    name_string: vec!["krbtgt".to_string(), realm.to_string()],
+                },
+                ..Default::default()
+            },
+        );
+    }
+
+    #[test]
+    fn create_tgs_req_authenticated_with_tgt() {
+        let tgt = create_tgt("DISNEY.WORLD");
+        let mut sname = PrincipalName::new(NT_SRV_HST, "http".to_string());
+        sname.push("castle.disney.world".to_string());
+
+        let tgs_req = TgsReqBuilder::create_tgs_req(
+            &tgt,
+            &sname,
+            CANONICALIZE,
+            &[AES256_CTS_HMAC_SHA1_96],
+        )
+        .unwrap();
+
+        assert_eq!("DISNEY.WORLD", tgs_req.req_body.realm);
+        assert_eq!(Some(sname), tgs_req.req_body.sname);
+        assert_eq!(CANONICALIZE, tgs_req.req_body.kdc_options.flags);
+
+        let padata = tgs_req.padata.unwrap();
+        assert_eq!(PA_TGS_REQ, padata[0].padata_type);
+        let (_, ap_req) = ApReq::parse(&padata[0].padata_value).unwrap();
+
+        let cipher = new_kerberos_cipher(AES256_CTS_HMAC_SHA1_96).unwrap();
+        let raw_authenticator = cipher
+            .decrypt(
+                &[0x11; 32],
+                KEY_USAGE_TGS_REQ_AUTHEN,
+                &ap_req.authenticator.cipher,
+            )
+            .unwrap();
+        let (_, authenticator) =
+            Authenticator::parse(&raw_authenticator).unwrap();
+
+        assert_eq!(
+            checksum(
+                mandatory_checksum_type(AES256_CTS_HMAC_SHA1_96).unwrap(),
+                &[0x11; 32],
+                KEY_USAGE_TGS_REQ_AUTHEN_CKSUM,
+                &tgs_req.req_body.build(),
+            )
+            .unwrap(),
+            authenticator.cksum.unwrap().checksum
+        );
+        assert_eq!(tgt.cname(), &authenticator.cname);
+    }
+
+    #[test]
+    fn error_creating_tgs_req_without_tgt() {
+        let tgt = Credential::new(
+            "KINGDOM.HEARTS".to_string(),
+            PrincipalName::new(NT_PRINCIPAL, "mickey".to_string()),
+            Ticket::default(),
+            EncAsRepPart {
+                sname: PrincipalName::new(NT_PRINCIPAL, "mickey".to_string()),
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(
+            Err(Error::InvalidTgt(
+                "mickey is not a ticket granting service".to_string()
+            )),
+            tgt_realm(&tgt)
+        );
+    }
+}
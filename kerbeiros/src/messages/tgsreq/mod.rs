@@ -0,0 +1,7 @@
+mod builder;
+pub(crate) use builder::{tgt_realm, TgsReqBuilder};
+
+mod reply_validator;
+pub(crate) use reply_validator::{
+    is_tgs, same_principal_name, TgsRepValidator,
+};
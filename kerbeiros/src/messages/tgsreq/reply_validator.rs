@@ -0,0 +1,274 @@
+use super::super::asreq::{is_later, realms_match};
+use crate::credentials::Credential;
+use crate::{Error, Result};
+use himmelblau_kerberos_asn1::{EncTgsRepPart, PrincipalName, TgsRep, TgsReq};
+use himmelblau_kerberos_constants::kdc_options;
+
+/// Checks that a TGS-REP is a legitimate reply to the TGS-REQ sent with a
+/// TGT, as described in RFC4120, section 3.3.3.
+///
+/// When the name canonicalization is requested, the KDC can reply with a
+/// referral TGT for another realm instead of the requested service, as
+/// allowed by RFC6806.
+pub(crate) struct TgsRepValidator<'a> {
+    tgs_req: &'a TgsReq,
+    tgt: &'a Credential,
+    tgs_rep: &'a TgsRep,
+    enc_tgs_rep_part: &'a EncTgsRepPart,
+}
+
+impl<'a> TgsRepValidator<'a> {
+    pub fn validate(
+        tgs_req: &'a TgsReq,
+        tgt: &'a Credential,
+        tgs_rep: &'a TgsRep,
+        enc_tgs_rep_part: &'a EncTgsRepPart,
+    ) -> Result<()> {
+        let validator = Self {
+            tgs_req,
+            tgt,
+            tgs_rep,
+            enc_tgs_rep_part,
+        };
+        validator.validate_nonce()?;
+        validator.validate_client()?;
+        validator.validate_server()?;
+        validator.validate_times()?;
+        return Ok(());
+    }
+
+    fn is_option_requested(&self, option: u32) -> bool {
+        return self.tgs_req.req_body.kdc_options.flags & option != 0;
+    }
+
+    fn validate_nonce(&self) -> Result<()> {
+        if self.enc_tgs_rep_part.nonce != self.tgs_req.req_body.nonce {
+            return Err(invalid_reply(format!(
+                "nonce {} does not match the requested {}",
+                self.enc_tgs_rep_part.nonce, self.tgs_req.req_body.nonce
+            )));
+        }
+        return Ok(());
+    }
+
+    fn validate_client(&self) -> Result<()> {
+        if self.tgs_rep.cname != *self.tgt.cname()
+            || self.tgs_rep.crealm != *self.tgt.crealm()
+        {
+            return Err(invalid_reply(format!(
+                "client {}@{} does not match the TGT",
+                self.tgs_rep.cname, self.tgs_rep.crealm
+            )));
+        }
+        return Ok(());
+    }
+
+    fn validate_server(&self) -> Result<()> {
+        let sname = &self.enc_tgs_rep_part.sname;
+        let srealm = &self.enc_tgs_rep_part.srealm;
+
+        if *sname != self.tgs_rep.ticket.sname
+            || *srealm != self.tgs_rep.ticket.realm
+        {
+            return Err(invalid_reply(format!(
+                "server {}@{} does not match the ticket",
+                sname, srealm
+            )));
+        }
+
+        let canonicalize = self.is_option_requested(kdc_options::CANONICALIZE);
+        let sname_matches = match &self.tgs_req.req_body.sname {
+            Some(requested_sname) => {
+                requested_sname == sname || (canonicalize && is_tgs(sname))
+            }
+            None => true,
+        };
+        let srealm_matches =
+            realms_match(&self.tgs_req.req_body.realm, srealm, canonicalize);
+
+        if !sname_matches || !srealm_matches {
+            return Err(invalid_reply(format!(
+                "server {}@{} was not requested",
+                sname, srealm
+            )));
+        }
+        return Ok(());
+    }
+
+    fn validate_times(&self) -> Result<()> {
+        let enc_part = self.enc_tgs_rep_part;
+
+        let starttime =
+            enc_part.starttime.as_ref().unwrap_or(&enc_part.authtime);
+        if is_later(starttime, &enc_part.endtime) {
+            return Err(invalid_reply(
+                "endtime is earlier than starttime".to_string(),
+            ));
+        }
+
+        if is_later(&enc_part.endtime, &self.tgs_req.req_body.till) {
+            return Err(invalid_reply(
+                "endtime is later than the requested".to_string(),
+            ));
+        }
+
+        return Ok(());
+    }
+}
+
+/// Checks if the principal is a ticket granting service, `krbtgt/REALM`.
+pub(crate) fn is_tgs(sname: &PrincipalName) -> bool {
+    return sname.name_string.len() == 2 && sname.name_string[0] == "krbtgt";
+}
+
+/// Whether both names have the same components. The name type is ignored,
+/// as in `krb5_principal_compare`.
+pub(crate) fn same_principal_name(
+    name: &PrincipalName,
+    other: &PrincipalName,
+) -> bool {
+    return name.name_string == other.name_string;
+}
+
+fn invalid_reply(reason: String) -> Error {
+    return Error::InvalidKdcReply(reason);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::{Duration, Utc};
+    use himmelblau_kerberos_asn1::{EncAsRepPart, Ticket};
+    use himmelblau_kerberos_constants::principal_names::{
+        NT_PRINCIPAL, NT_SRV_INST,
+    };
+
+    fn krbtgt_name(realm: &str) -> PrincipalName {
+        return PrincipalName {
+            name_type: NT_SRV_INST,
+            name_string: vec!["krbtgt".to_string(), realm.to_string()],
+        };
+    }
+
+    fn service_name() -> PrincipalName {
+        return PrincipalName {
+            name_type: NT_SRV_INST,
+            name_string: vec!["http".to_string(), "castle".to_string()],
+        };
+    }
+
+    fn create_tgt() -> Credential {
+        return Credential::new(
+            "KINGDOM.HEARTS".to_string(),
+            PrincipalName::new(NT_PRINCIPAL, "mickey".to_string()),
+            Ticket::default(),
+            EncAsRepPart {
+                srealm: "KINGDOM.HEARTS".to_string(),
+                sname: krbtgt_name("KINGDOM.HEARTS"),
+                ..Default::default()
+            },
+        );
+    }
+
+    fn create_tgs_req(kdc_options: u32) -> TgsReq {
+        let mut tgs_req = TgsReq::default();
+        tgs_req.req_body.kdc_options = kdc_options.into();
+        tgs_req.req_body.realm = "KINGDOM.HEARTS".to_string();
+        tgs_req.req_body.sname = Some(service_name());
+        tgs_req.req_body.till = (Utc::now() + Duration::days(1)).into();
+        tgs_req.req_body.nonce = 1234;
+        return tgs_req;
+    }
+
+    fn create_tgs_rep(sname: PrincipalName) -> (TgsRep, EncTgsRepPart) {
+        let now = Utc::now();
+        let tgs_rep = TgsRep {
+            crealm: "KINGDOM.HEARTS".to_string(),
+            cname: PrincipalName::new(NT_PRINCIPAL, "mickey".to_string()),
+            ticket: Ticket {
+                realm: "KINGDOM.HEARTS".to_string(),
+                sname: sname.clone(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let enc_tgs_rep_part = EncTgsRepPart {
+            nonce: 1234,
+            authtime: now.into(),
+            endtime: (now + Duration::hours(10)).into(),
+            srealm: "KINGDOM.HEARTS".to_string(),
+            sname,
+            ..Default::default()
+        };
+        return (tgs_rep, enc_tgs_rep_part);
+    }
+
+    fn validate(
+        tgs_req: &TgsReq,
+        tgs_rep: &TgsRep,
+        enc_tgs_rep_part: &EncTgsRepPart,
+    ) -> Result<()> {
+        return TgsRepValidator::validate(
+            tgs_req,
+            &create_tgt(),
+            tgs_rep,
+            enc_tgs_rep_part,
+        );
+    }
+
+    #[test]
+    fn accept_valid_reply() {
+        let (tgs_rep, enc_tgs_rep_part) = create_tgs_rep(service_name());
+        validate(&create_tgs_req(0), &tgs_rep, &enc_tgs_rep_part).unwrap();
+    }
+
+    #[test]
+    fn accept_referral_with_canonicalize() {
+        let (tgs_rep, enc_tgs_rep_part) =
+            create_tgs_rep(krbtgt_name("DISNEY.WORLD"));
+        validate(
+            &create_tgs_req(kdc_options::CANONICALIZE),
+            &tgs_rep,
+            &enc_tgs_rep_part,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn error_with_referral_without_canonicalize() {
+        let (tgs_rep, enc_tgs_rep_part) =
+            create_tgs_rep(krbtgt_name("DISNEY.WORLD"));
+        assert_eq!(
+            Err(Error::InvalidKdcReply(
+                "server krbtgt/DISNEY.WORLD@KINGDOM.HEARTS was not requested"
+                    .to_string()
+            )),
+            validate(&create_tgs_req(0), &tgs_rep, &enc_tgs_rep_part)
+        );
+    }
+
+    #[test]
+    fn error_with_different_nonce() {
+        let (tgs_rep, mut enc_tgs_rep_part) = create_tgs_rep(service_name());
+        enc_tgs_rep_part.nonce = 1;
+        assert_eq!(
+            Err(Error::InvalidKdcReply(
+                "nonce 1 does not match the requested 1234".to_string()
+            )),
+            validate(&create_tgs_req(0), &tgs_rep, &enc_tgs_rep_part)
+        );
+    }
+
+    #[test]
+    fn error_with_different_client() {
+        let (mut tgs_rep, enc_tgs_rep_part) = create_tgs_rep(service_name());
+        tgs_rep.cname = PrincipalName::new(NT_PRINCIPAL, "goofy".to_string());
+        assert_eq!(
+            Err(Error::InvalidKdcReply(
+                "client goofy@KINGDOM.HEARTS does not match the TGT"
+                    .to_string()
+            )),
+            validate(&create_tgs_req(0), &tgs_rep, &enc_tgs_rep_part)
+        );
+    }
+}
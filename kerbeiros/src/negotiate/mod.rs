@@ -0,0 +1,30 @@
+//! HTTP authentication with the Negotiate scheme, described in RFC4559,
+//! which sends the tokens of the SPNEGO negotiation in the
+//! `Authorization` and `WWW-Authenticate` headers.
+//!
+//! The helpers only deal with the values of the headers, so they can be
+//! used with any HTTP client or server. With the `negotiate-layer`
+//! feature, the [`NegotiateLayer`] authenticates the requests of the
+//! servers built on tower, such as axum or hyper. With the
+//! `negotiate-client-layer` feature, the [`NegotiateClientLayer`]
+//! authenticates the requests of the clients built on tower, such as
+//! hyper.
+
+mod negotiate_header;
+pub use negotiate_header::*;
+
+mod negotiate_client;
+pub use negotiate_client::*;
+
+mod negotiate_acceptor;
+pub use negotiate_acceptor::*;
+
+#[cfg(feature = "negotiate-layer")]
+mod negotiate_layer;
+#[cfg(feature = "negotiate-layer")]
+pub use negotiate_layer::*;
+
+#[cfg(feature = "negotiate-client-layer")]
+mod negotiate_client_layer;
+#[cfg(feature = "negotiate-client-layer")]
+pub use negotiate_client_layer::*;
@@ -0,0 +1,273 @@
+use super::negotiate_header::{
+    build_negotiate_header, parse_negotiate_header, NEGOTIATE_SCHEME,
+};
+use crate::acceptor::ApReqAcceptor;
+use crate::gss::{GssAcceptor, GssContext, SpnegoAcceptor};
+use crate::{Error, Result};
+use himmelblau_kerberos_asn1::{PrincipalName, Realm};
+use himmelblau_kerberos_pac::Sid;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Identity of a client authenticated with the Negotiate scheme, to be
+/// stored as an extension of the request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NegotiatePrincipal {
+    pub crealm: Realm,
+    pub cname: PrincipalName,
+    /// SID of the user, if the ticket includes a PAC with its logon
+    /// information.
+    pub user_sid: Option<Sid>,
+    /// SIDs of the groups of the user, from the PAC of the ticket.
+    pub group_sids: Vec<Sid>,
+}
+
+impl NegotiatePrincipal {
+    /// Extracts the identity of the client of a context, and its groups
+    /// from the PAC of the ticket, if any.
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidAuthorizationData`] if the logon
+    /// information of the PAC cannot be decoded.
+    pub fn from_context(context: &GssContext) -> Result<Self> {
+        let mut principal = Self {
+            crealm: context.crealm().clone(),
+            cname: context.cname().clone(),
+            user_sid: None,
+            group_sids: Vec::new(),
+        };
+
+        let pac = context
+            .accepted_ap_req()
+            .and_then(|accepted| accepted.pac());
+        if let Some(pac) = pac {
+            let logon_info = pac.logon_info().map_err(|error| {
+                Error::InvalidAuthorizationData(error.to_string())
+            })?;
+            if let Some(logon_info) = logon_info {
+                principal.user_sid = logon_info.user_sid();
+                principal.group_sids = logon_info.group_sids();
+            }
+        }
+        return Ok(principal);
+    }
+
+    /// Name of the client, as `user@REALM`.
+    pub fn name(&self) -> String {
+        return format!("{}@{}", self.cname, self.crealm);
+    }
+}
+
+/// Client authenticated by a [`NegotiateAcceptor`], with the value of
+/// the `WWW-Authenticate` header that must be included in the response
+/// for the mutual authentication, if any.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NegotiateAuthentication {
+    pub principal: NegotiatePrincipal,
+    pub www_authenticate: Option<String>,
+}
+
+/// Server side of the HTTP Negotiate authentication, described in
+/// RFC4559, which verifies the SPNEGO or raw Kerberos V5 tokens of the
+/// `Authorization` headers with a [`GssAcceptor`], so the tickets are
+/// decrypted with its keytab and the replays are rejected by its replay
+/// cache.
+///
+/// The acceptor can be shared by the handlers of the requests, as the
+/// middleware of a web framework would do: requests without
+/// `Authorization` header are answered with `401 Unauthorized` and the
+/// [`NegotiateAcceptor::challenge`] in the `WWW-Authenticate` header,
+/// while the rest are passed to [`NegotiateAcceptor::authenticate`].
+///
+/// Since the requests are not bound to a connection, the negotiation must
+/// complete in one round trip, as it does when the client prefers the
+/// Kerberos V5 mechanism and sends its token optimistically.
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+///
+/// let acceptor =
+///     NegotiateAcceptor::from_keytab_file("/etc/krb5.keytab").unwrap();
+///
+/// # let authorization = "";
+/// match acceptor.authenticate(authorization) {
+///     Ok(authentication) => {
+///         println!("Authenticated {}", authentication.principal.name());
+///         if let Some(www_authenticate) = authentication.www_authenticate {
+///             // Include the `WWW-Authenticate` header in the response
+///         }
+///     }
+///     Err(_) => {
+///         // Reply `401 Unauthorized` with the challenge
+///         let www_authenticate = NegotiateAcceptor::challenge();
+///     }
+/// }
+/// ```
+#[derive(Clone)]
+pub struct NegotiateAcceptor {
+    acceptor: Arc<GssAcceptor>,
+}
+
+impl NegotiateAcceptor {
+    pub fn new(acceptor: GssAcceptor) -> Self {
+        return Self {
+            acceptor: Arc::new(acceptor),
+        };
+    }
+
+    /// Creates an acceptor with the keytab stored in `path`. See
+    /// [`ApReqAcceptor::from_keytab_file`].
+    pub fn from_keytab_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        return Ok(Self::new(GssAcceptor::new(
+            ApReqAcceptor::from_keytab_file(path)?,
+        )));
+    }
+
+    pub fn acceptor(&self) -> &GssAcceptor {
+        return &self.acceptor;
+    }
+
+    /// Value of the `WWW-Authenticate` header of the `401` responses to
+    /// the requests that are not authenticated.
+    pub fn challenge() -> &'static str {
+        return NEGOTIATE_SCHEME;
+    }
+
+    /// Verifies the token of the `Authorization` header of a request.
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidNegotiateHeader`] if the header has no
+    /// Negotiate token, [`Error::InvalidGssToken`] if the negotiation
+    /// does not complete in one round trip, and the errors of
+    /// [`SpnegoAcceptor::accept`].
+    pub fn authenticate(
+        &self,
+        authorization: &str,
+    ) -> Result<NegotiateAuthentication> {
+        let token =
+            parse_negotiate_header(authorization)?.ok_or_else(|| {
+                Error::InvalidNegotiateHeader("missing token".to_string())
+            })?;
+
+        let mut spnego = SpnegoAcceptor::from_shared(self.acceptor.clone());
+        let reply_token = spnego.accept(&token)?;
+        let context = spnego.into_context().ok_or_else(|| {
+            Error::InvalidGssToken(
+                "negotiation does not complete in one round trip".to_string(),
+            )
+        })?;
+
+        return Ok(NegotiateAuthentication {
+            principal: NegotiatePrincipal::from_context(&context)?,
+            www_authenticate: reply_token
+                .map(|token| build_negotiate_header(&token)),
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gss::test_context::{
+        create_acceptor, create_credential, create_credential_with_pac, mickey,
+    };
+    use crate::negotiate::{NegotiateClient, NegotiateStatus};
+    use himmelblau_kerberos_pac::{
+        GroupMembership, KerbValidationInfo, Pac, PacInfoBuffer, PAC_LOGON_INFO,
+    };
+
+    fn domain_sid() -> Sid {
+        return Sid::new(
+            1,
+            [0, 0, 0, 0, 0, 5],
+            vec![21, 3623811015, 3361044348, 30300820],
+        );
+    }
+
+    #[test]
+    fn authenticate_client_mutually() {
+        let acceptor = NegotiateAcceptor::new(create_acceptor());
+        let mut negotiate = NegotiateClient::new(create_credential());
+
+        let authentication = acceptor
+            .authenticate(&negotiate.authorization().unwrap())
+            .unwrap();
+
+        assert_eq!(
+            NegotiatePrincipal {
+                crealm: "KINGDOM.HEARTS".to_string(),
+                cname: mickey(),
+                user_sid: None,
+                group_sids: Vec::new(),
+            },
+            authentication.principal
+        );
+        assert_eq!("mickey@KINGDOM.HEARTS", authentication.principal.name());
+        assert_eq!(
+            NegotiateStatus::Complete,
+            negotiate
+                .process_www_authenticate(
+                    &authentication.www_authenticate.unwrap()
+                )
+                .unwrap()
+        );
+        assert!(negotiate.is_complete());
+    }
+
+    #[test]
+    fn authenticate_client_with_groups_of_pac() {
+        let logon_info = KerbValidationInfo {
+            user_id: 1104,
+            primary_group_id: 513,
+            group_ids: vec![
+                GroupMembership::new(513, 7),
+                GroupMembership::new(512, 7),
+            ],
+            logon_domain_id: Some(domain_sid()),
+            ..Default::default()
+        };
+        let pac = Pac::new(vec![PacInfoBuffer::new(
+            PAC_LOGON_INFO,
+            logon_info.build(),
+        )]);
+        let acceptor = NegotiateAcceptor::new(create_acceptor());
+        let mut negotiate =
+            NegotiateClient::new(create_credential_with_pac(pac));
+
+        let principal = acceptor
+            .authenticate(&negotiate.authorization().unwrap())
+            .unwrap()
+            .principal;
+
+        assert_eq!(Some(domain_sid().with_rid(1104)), principal.user_sid);
+        assert_eq!(
+            vec![domain_sid().with_rid(513), domain_sid().with_rid(512)],
+            principal.group_sids
+        );
+    }
+
+    #[test]
+    fn error_with_replayed_authorization() {
+        let acceptor = NegotiateAcceptor::new(create_acceptor());
+        let authorization = NegotiateClient::new(create_credential())
+            .authorization()
+            .unwrap();
+
+        assert!(acceptor.authenticate(&authorization).is_ok());
+        assert!(matches!(
+            acceptor.clone().authenticate(&authorization),
+            Err(Error::ReplayError(_))
+        ));
+    }
+
+    #[test]
+    fn error_without_token() {
+        assert_eq!(
+            Err(Error::InvalidNegotiateHeader("missing token".to_string())),
+            NegotiateAcceptor::new(create_acceptor())
+                .authenticate(NegotiateAcceptor::challenge())
+        );
+    }
+}
@@ -0,0 +1,235 @@
+use super::negotiate_header::{build_negotiate_header, parse_negotiate_header};
+use crate::client::KerberosClient;
+use crate::credentials::Credential;
+use crate::gss::{
+    GssContext, GssInitiator, SpnegoInitiator, GSS_C_MUTUAL_FLAG,
+};
+use crate::{Error, Result};
+use himmelblau_kerberos_asn1::PrincipalName;
+use himmelblau_kerberos_constants::principal_names::NT_SRV_HST;
+
+/// Client of the HTTP Negotiate authentication, described in RFC4559,
+/// which builds the `Authorization` header with the SPNEGO token of a
+/// service ticket, and verifies the token of the `WWW-Authenticate`
+/// header sent by the server, to authenticate it.
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+///
+/// let mut negotiate =
+///     NegotiateClient::from_env("HTTP/www.contoso.com@CONTOSO.COM").unwrap();
+/// let authorization = negotiate.authorization().unwrap();
+/// // Send the request with the `Authorization: <authorization>` header
+///
+/// # let www_authenticate = "";
+/// // Verify the server with its `WWW-Authenticate` header
+/// match negotiate.process_www_authenticate(www_authenticate).unwrap() {
+///     NegotiateStatus::Complete => assert!(negotiate.is_complete()),
+///     NegotiateStatus::Continue(authorization) => {
+///         // Send the next request with `authorization`
+///     }
+///     NegotiateStatus::Unauthenticated => {
+///         // The server was not authenticated
+///     }
+/// }
+/// ```
+pub struct NegotiateClient {
+    spnego: SpnegoInitiator,
+}
+
+impl NegotiateClient {
+    /// Creates a client that authenticates with the service ticket
+    /// `credential`, requesting mutual authentication.
+    pub fn new(credential: Credential) -> Self {
+        return Self::from_initiator(GssInitiator::new(
+            credential,
+            GSS_C_MUTUAL_FLAG,
+        ));
+    }
+
+    /// Creates a client with a custom initiator, to request other flags
+    /// or set channel bindings.
+    pub fn from_initiator(initiator: GssInitiator) -> Self {
+        return Self {
+            spnego: SpnegoInitiator::new(initiator),
+        };
+    }
+
+    /// Creates a client for the service principal `spn`, such as
+    /// `HTTP/host@REALM`, whose ticket is retrieved by `client` with the
+    /// TGT of its credential cache.
+    ///
+    /// # Errors
+    /// Returns [`Error::PrincipalNameTypeUndefined`] if `spn` is not
+    /// valid, and the errors of [`KerberosClient::get_credential`].
+    pub fn from_client(client: &KerberosClient, spn: &str) -> Result<Self> {
+        let (service, realm) = parse_service_principal(spn)?;
+        let credential = client.get_credential(&service, realm.as_deref())?;
+        return Ok(Self::new(credential));
+    }
+
+    /// Creates a client for the service principal `spn` with the default
+    /// credential cache of the configuration. See
+    /// [`KerberosClient::from_env`].
+    pub fn from_env(spn: &str) -> Result<Self> {
+        return Self::from_client(&KerberosClient::from_env()?, spn);
+    }
+
+    /// Value of the `Authorization` header of the first request.
+    pub fn authorization(&mut self) -> Result<String> {
+        return Ok(build_negotiate_header(&self.spnego.initial_token()?));
+    }
+
+    /// Processes the `WWW-Authenticate` header of the server and returns
+    /// the status of the negotiation.
+    ///
+    /// A header without token, as the one of the first `401` response or
+    /// a final response without the token of the mutual authentication,
+    /// does not complete the negotiation, so
+    /// [`NegotiateStatus::Unauthenticated`] is returned.
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidNegotiateHeader`] if the header has no
+    /// Negotiate challenge, and the errors of [`SpnegoInitiator::step`].
+    pub fn process_www_authenticate(
+        &mut self,
+        www_authenticate: &str,
+    ) -> Result<NegotiateStatus> {
+        if let Some(token) = parse_negotiate_header(www_authenticate)? {
+            if let Some(token) = self.spnego.step(&token)? {
+                return Ok(NegotiateStatus::Continue(build_negotiate_header(
+                    &token,
+                )));
+            }
+        }
+
+        if self.is_complete() {
+            return Ok(NegotiateStatus::Complete);
+        }
+        return Ok(NegotiateStatus::Unauthenticated);
+    }
+
+    /// Whether the server was authenticated and the negotiation is
+    /// complete.
+    pub fn is_complete(&self) -> bool {
+        return self.spnego.is_complete();
+    }
+
+    /// Context established with the server.
+    pub fn context(&self) -> Option<&GssContext> {
+        return self.spnego.context();
+    }
+}
+
+/// Status of the negotiation after processing a `WWW-Authenticate` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NegotiateStatus {
+    /// The negotiation continues with the next request, which must be
+    /// sent with this `Authorization` header.
+    Continue(String),
+
+    /// The server was authenticated and the negotiation is complete.
+    Complete,
+
+    /// The header carries no token to authenticate the server, as the
+    /// challenge of a `401` response or a final response without the
+    /// token of the mutual authentication.
+    Unauthenticated,
+}
+
+/// Parses a service principal of the form `service/host[@REALM]`. The
+/// realm is `None` if it is not specified.
+///
+/// # Errors
+/// Returns [`Error::PrincipalNameTypeUndefined`] if the principal has no
+/// host.
+pub fn parse_service_principal(
+    spn: &str,
+) -> Result<(PrincipalName, Option<String>)> {
+    let (name, realm) = match spn.rsplit_once('@') {
+        Some((name, realm)) => (name, Some(realm.to_string())),
+        None => (spn, None),
+    };
+
+    let mut components = name.split('/');
+    let service = components.next().unwrap_or_default();
+    let hosts: Vec<&str> = components.collect();
+    if service.is_empty()
+        || hosts.is_empty()
+        || hosts.iter().any(|host| host.is_empty())
+        || realm.as_deref() == Some("")
+    {
+        return Err(Error::PrincipalNameTypeUndefined(spn.to_string()));
+    }
+
+    let mut principal = PrincipalName::new(NT_SRV_HST, service.to_string());
+    for host in hosts {
+        principal.push(host.to_string());
+    }
+    return Ok((principal, realm));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gss::test_context::{create_acceptor, create_credential};
+    use crate::gss::SpnegoAcceptor;
+
+    #[test]
+    fn parse_service_principals() {
+        let mut service = PrincipalName::new(NT_SRV_HST, "HTTP".to_string());
+        service.push("www.contoso.com".to_string());
+
+        assert_eq!(
+            (service.clone(), Some("CONTOSO.COM".to_string())),
+            parse_service_principal("HTTP/www.contoso.com@CONTOSO.COM")
+                .unwrap()
+        );
+        assert_eq!(
+            (service, None),
+            parse_service_principal("HTTP/www.contoso.com").unwrap()
+        );
+    }
+
+    #[test]
+    fn error_parsing_principal_without_host() {
+        for spn in &["HTTP@CONTOSO.COM", "HTTP/", "/www.contoso.com", "a/b@"] {
+            assert_eq!(
+                Err(Error::PrincipalNameTypeUndefined(spn.to_string())),
+                parse_service_principal(spn)
+            );
+        }
+    }
+
+    #[test]
+    fn authenticate_with_server() {
+        let mut negotiate = NegotiateClient::new(create_credential());
+        let mut acceptor = SpnegoAcceptor::new(create_acceptor());
+
+        let authorization = negotiate.authorization().unwrap();
+        assert!(authorization.starts_with("Negotiate YII"));
+
+        let token = parse_negotiate_header(&authorization).unwrap().unwrap();
+        let reply = acceptor.accept(&token).unwrap().unwrap();
+
+        assert_eq!(
+            NegotiateStatus::Unauthenticated,
+            negotiate.process_www_authenticate("Negotiate").unwrap()
+        );
+        assert!(!negotiate.is_complete());
+
+        assert_eq!(
+            NegotiateStatus::Complete,
+            negotiate
+                .process_www_authenticate(&build_negotiate_header(&reply))
+                .unwrap()
+        );
+        assert!(negotiate.is_complete());
+        assert_eq!(
+            acceptor.context().unwrap().context_key(),
+            negotiate.context().unwrap().context_key()
+        );
+    }
+}
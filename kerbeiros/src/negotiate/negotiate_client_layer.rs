@@ -0,0 +1,386 @@
+use super::negotiate_client::{NegotiateClient, NegotiateStatus};
+use super::negotiate_header::NEGOTIATE_SCHEME;
+use crate::Error;
+use http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use http::{HeaderValue, Request, Response, StatusCode};
+use std::future::{poll_fn, Future};
+use std::io;
+use std::panic::resume_unwind;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Error of the services wrapped by the [`NegotiateClientLayer`], either
+/// of the inner service or of the authentication.
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+type ClientFactory =
+    Arc<dyn Fn(&str) -> Result<NegotiateClient, Error> + Send + Sync>;
+
+/// Tower layer that authenticates the requests of HTTP clients, such as
+/// hyper, with the Negotiate scheme described in RFC4559.
+///
+/// The requests are sent without credentials. If the server answers with
+/// `401 Unauthorized` and the Negotiate challenge, the request is sent
+/// again with the `Authorization` header built by the [`NegotiateClient`]
+/// that the layer creates for the host of the request. The token of the
+/// mutual authentication in the `WWW-Authenticate` header of the final
+/// response must authenticate the server, or an error is returned
+/// instead of the response.
+///
+/// The clients are created, and their tickets retrieved, in a blocking
+/// task of tokio, so the service must run inside a tokio runtime. The
+/// body of the requests must be cloneable to send them again.
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+///
+/// // Retrieves the tickets of HTTP/<host> with the default ccache
+/// let layer = NegotiateClientLayer::from_env();
+/// // Wrap the service of the client, as `ServiceBuilder::layer(layer)`
+/// ```
+#[derive(Clone)]
+pub struct NegotiateClientLayer {
+    factory: ClientFactory,
+}
+
+impl NegotiateClientLayer {
+    /// Creates a layer that authenticates with the clients created by
+    /// `factory` for the host of each request.
+    pub fn new<F>(factory: F) -> Self
+    where
+        F: Fn(&str) -> Result<NegotiateClient, Error> + Send + Sync + 'static,
+    {
+        return Self {
+            factory: Arc::new(factory),
+        };
+    }
+
+    /// Creates a layer that authenticates with the `HTTP/<host>` tickets
+    /// retrieved with the default credential cache. See
+    /// [`NegotiateClient::from_env`].
+    pub fn from_env() -> Self {
+        return Self::new(|host| {
+            return NegotiateClient::from_env(&format!("HTTP/{}", host));
+        });
+    }
+}
+
+impl<S> Layer<S> for NegotiateClientLayer {
+    type Service = NegotiateClientService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        return NegotiateClientService {
+            inner,
+            factory: self.factory.clone(),
+        };
+    }
+}
+
+/// Service created by the [`NegotiateClientLayer`].
+#[derive(Clone)]
+pub struct NegotiateClientService<S> {
+    inner: S,
+    factory: ClientFactory,
+}
+
+/// Future of the response of a [`NegotiateClientService`].
+pub type NegotiateClientFuture<ResBody> =
+    Pin<Box<dyn Future<Output = Result<Response<ResBody>, BoxError>> + Send>>;
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>>
+    for NegotiateClientService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send,
+    S::Error: Into<BoxError>,
+    ReqBody: Clone + Send + 'static,
+    ResBody: Send + 'static,
+{
+    type Response = Response<ResBody>;
+    type Error = BoxError;
+    type Future = NegotiateClientFuture<ResBody>;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        return self.inner.poll_ready(cx).map_err(Into::into);
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        // the inner service that was polled ready is the one called
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let factory = self.factory.clone();
+
+        return Box::pin(async move {
+            let response =
+                inner.call(request.clone()).await.map_err(Into::into)?;
+            if !is_negotiate_challenge(&response) {
+                return Ok(response);
+            }
+
+            let host = request
+                .uri()
+                .host()
+                .ok_or_else(|| {
+                    Error::NotAvailableData(format!(
+                        "host of {}",
+                        request.uri()
+                    ))
+                })?
+                .to_string();
+            let mut negotiate = create_client(factory, host).await?;
+
+            let mut request = request;
+            request.headers_mut().insert(
+                AUTHORIZATION,
+                HeaderValue::from_str(&negotiate.authorization()?)?,
+            );
+            poll_fn(|cx| inner.poll_ready(cx))
+                .await
+                .map_err(Into::into)?;
+            let response = inner.call(request).await.map_err(Into::into)?;
+
+            verify_server(&mut negotiate, &response)?;
+            return Ok(response);
+        });
+    }
+}
+
+/// Whether the response is a `401 Unauthorized` that offers the
+/// Negotiate scheme.
+fn is_negotiate_challenge<ResBody>(response: &Response<ResBody>) -> bool {
+    return response.status() == StatusCode::UNAUTHORIZED
+        && response
+            .headers()
+            .get_all(WWW_AUTHENTICATE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .any(|value| {
+                return value
+                    .split_whitespace()
+                    .next()
+                    .map(|scheme| scheme.eq_ignore_ascii_case(NEGOTIATE_SCHEME))
+                    .unwrap_or(false);
+            });
+}
+
+/// Creates the client of the host in a blocking task, since retrieving
+/// the ticket may block.
+async fn create_client(
+    factory: ClientFactory,
+    host: String,
+) -> Result<NegotiateClient, Error> {
+    let result = tokio::task::spawn_blocking(move || factory(&host)).await;
+
+    match result {
+        Ok(result) => return result,
+        Err(error) if error.is_panic() => resume_unwind(error.into_panic()),
+        Err(_) => {
+            return Err(Error::IOError(
+                io::Error::other("authentication cancelled").into(),
+            ))
+        }
+    }
+}
+
+/// Verifies the mutual authentication of the final response. The
+/// responses rejected by the server with `401 Unauthorized` are returned
+/// as they are.
+fn verify_server<ResBody>(
+    negotiate: &mut NegotiateClient,
+    response: &Response<ResBody>,
+) -> Result<(), Error> {
+    if response.status() == StatusCode::UNAUTHORIZED {
+        return Ok(());
+    }
+
+    let www_authenticate = match response.headers().get(WWW_AUTHENTICATE) {
+        Some(www_authenticate) => www_authenticate.to_str().map_err(|_| {
+            Error::InvalidNegotiateHeader(
+                "invalid characters in WWW-Authenticate".to_string(),
+            )
+        })?,
+        None => NEGOTIATE_SCHEME,
+    };
+
+    match negotiate.process_www_authenticate(www_authenticate)? {
+        NegotiateStatus::Complete => return Ok(()),
+        _ => {
+            return Err(Error::InvalidNegotiateHeader(
+                "the server was not authenticated".to_string(),
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gss::test_context::{create_acceptor, create_credential};
+    use crate::negotiate::NegotiateAcceptor;
+    use std::convert::Infallible;
+    use std::future::{ready, Ready};
+
+    /// Server that replies `200 OK` to every request.
+    #[derive(Clone)]
+    struct Hello {}
+
+    impl Service<Request<String>> for Hello {
+        type Response = Response<String>;
+        type Error = Infallible;
+        type Future = Ready<Result<Response<String>, Infallible>>;
+
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Result<(), Infallible>> {
+            return Poll::Ready(Ok(()));
+        }
+
+        fn call(&mut self, _request: Request<String>) -> Self::Future {
+            return ready(Ok(Response::new("Hello".to_string())));
+        }
+    }
+
+    /// Server that authenticates the requests with a [`NegotiateAcceptor`].
+    #[derive(Clone)]
+    struct Server {
+        acceptor: NegotiateAcceptor,
+    }
+
+    impl Service<Request<String>> for Server {
+        type Response = Response<String>;
+        type Error = Infallible;
+        type Future = Ready<Result<Response<String>, Infallible>>;
+
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Result<(), Infallible>> {
+            return Poll::Ready(Ok(()));
+        }
+
+        fn call(&mut self, request: Request<String>) -> Self::Future {
+            let authentication = request
+                .headers()
+                .get(AUTHORIZATION)
+                .map(|value| value.to_str().unwrap())
+                .map(|value| self.acceptor.authenticate(value).unwrap());
+
+            let mut response = Response::new(String::new());
+            match authentication {
+                Some(authentication) => {
+                    *response.body_mut() = authentication.principal.name();
+                    response.headers_mut().insert(
+                        WWW_AUTHENTICATE,
+                        HeaderValue::from_str(
+                            &authentication.www_authenticate.unwrap(),
+                        )
+                        .unwrap(),
+                    );
+                }
+                None => {
+                    *response.status_mut() = StatusCode::UNAUTHORIZED;
+                    response.headers_mut().insert(
+                        WWW_AUTHENTICATE,
+                        HeaderValue::from_static(NEGOTIATE_SCHEME),
+                    );
+                }
+            }
+            return ready(Ok(response));
+        }
+    }
+
+    /// Server that challenges the requests without `Authorization`, and
+    /// accepts the rest without authenticating itself.
+    #[derive(Clone)]
+    struct Impostor {}
+
+    impl Service<Request<String>> for Impostor {
+        type Response = Response<String>;
+        type Error = Infallible;
+        type Future = Ready<Result<Response<String>, Infallible>>;
+
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Result<(), Infallible>> {
+            return Poll::Ready(Ok(()));
+        }
+
+        fn call(&mut self, request: Request<String>) -> Self::Future {
+            let mut response = Response::new(String::new());
+            if !request.headers().contains_key(AUTHORIZATION) {
+                *response.status_mut() = StatusCode::UNAUTHORIZED;
+                response.headers_mut().insert(
+                    WWW_AUTHENTICATE,
+                    HeaderValue::from_static(NEGOTIATE_SCHEME),
+                );
+            }
+            return ready(Ok(response));
+        }
+    }
+
+    fn create_layer() -> NegotiateClientLayer {
+        return NegotiateClientLayer::new(|host| {
+            assert_eq!("castle.kingdom.hearts", host);
+            return Ok(NegotiateClient::new(create_credential()));
+        });
+    }
+
+    fn create_request() -> Request<String> {
+        return Request::builder()
+            .uri("http://castle.kingdom.hearts/")
+            .body(String::new())
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn authenticate_after_challenge() {
+        let server = Server {
+            acceptor: NegotiateAcceptor::new(create_acceptor()),
+        };
+        let mut client = create_layer().layer(server);
+
+        let response = client.call(create_request()).await.unwrap();
+
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!("mickey@KINGDOM.HEARTS", response.body());
+    }
+
+    #[tokio::test]
+    async fn send_request_without_challenge() {
+        let mut client = NegotiateClientLayer::new(|_| {
+            panic!("No authentication is required");
+        })
+        .layer(Hello {});
+
+        let response = client.call(create_request()).await.unwrap();
+
+        assert_eq!(StatusCode::OK, response.status());
+    }
+
+    #[tokio::test]
+    async fn error_if_server_is_not_authenticated() {
+        let mut client = create_layer().layer(Impostor {});
+
+        let error = client.call(create_request()).await.unwrap_err();
+
+        assert_eq!(
+            Some(&Error::InvalidNegotiateHeader(
+                "the server was not authenticated".to_string()
+            )),
+            error.downcast_ref::<Error>()
+        );
+    }
+}
@@ -0,0 +1,89 @@
+use crate::{Error, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+/// Name of the HTTP authentication scheme of SPNEGO.
+pub const NEGOTIATE_SCHEME: &str = "Negotiate";
+
+/// Value of the `Authorization` or `WWW-Authenticate` headers that
+/// carries `token`, as `Negotiate <base64 token>`.
+pub fn build_negotiate_header(token: &[u8]) -> String {
+    return format!("{} {}", NEGOTIATE_SCHEME, STANDARD.encode(token));
+}
+
+/// Token of the Negotiate challenge or credentials of a header value. The
+/// value may include the challenges of other schemes, separated by
+/// commas, as the `WWW-Authenticate` headers do. A challenge without
+/// token, as the first one sent by the servers, returns `None`.
+///
+/// # Errors
+/// Returns [`Error::InvalidNegotiateHeader`] if there is no Negotiate
+/// challenge or its token is not valid base64.
+pub fn parse_negotiate_header(value: &str) -> Result<Option<Vec<u8>>> {
+    for challenge in value.split(',') {
+        let mut parts = challenge.trim().splitn(2, ' ');
+        let scheme = parts.next().unwrap_or_default();
+        if !scheme.eq_ignore_ascii_case(NEGOTIATE_SCHEME) {
+            continue;
+        }
+
+        let token = parts.next().unwrap_or_default().trim();
+        if token.is_empty() {
+            return Ok(None);
+        }
+        return STANDARD.decode(token).map(Some).map_err(|_| {
+            Error::InvalidNegotiateHeader(
+                "token is not valid base64".to_string(),
+            )
+        });
+    }
+
+    return Err(Error::InvalidNegotiateHeader(
+        "no Negotiate challenge".to_string(),
+    ));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn build_and_parse_header() {
+        let header = build_negotiate_header(&[0x60, 0x01, 0xff]);
+
+        assert_eq!("Negotiate YAH/", header);
+        assert_eq!(
+            Some(vec![0x60, 0x01, 0xff]),
+            parse_negotiate_header(&header).unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_challenges_of_several_schemes() {
+        assert_eq!(
+            None,
+            parse_negotiate_header("Basic realm=\"castle\", negotiate")
+                .unwrap()
+        );
+        assert_eq!(
+            Some(vec![0x60, 0x01, 0xff]),
+            parse_negotiate_header("NTLM, Negotiate YAH/").unwrap()
+        );
+    }
+
+    #[test]
+    fn error_parsing_header_without_negotiate() {
+        assert_eq!(
+            Err(Error::InvalidNegotiateHeader(
+                "no Negotiate challenge".to_string()
+            )),
+            parse_negotiate_header("Basic realm=\"castle\"")
+        );
+        assert_eq!(
+            Err(Error::InvalidNegotiateHeader(
+                "token is not valid base64".to_string()
+            )),
+            parse_negotiate_header("Negotiate ???")
+        );
+    }
+}
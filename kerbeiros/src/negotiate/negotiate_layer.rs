@@ -0,0 +1,265 @@
+use super::negotiate_acceptor::{
+    NegotiateAcceptor, NegotiateAuthentication, NegotiatePrincipal,
+};
+use crate::Error;
+use http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use http::{HeaderValue, Request, Response, StatusCode};
+use std::future::Future;
+use std::io;
+use std::panic::resume_unwind;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Tower layer that authenticates the requests with the Negotiate scheme,
+/// described in RFC4559, by means of a [`NegotiateAcceptor`].
+///
+/// The requests whose `Authorization` header is missing or cannot be
+/// verified are answered with `401 Unauthorized` and the Negotiate
+/// challenge, without reaching the inner service. If the token cannot be
+/// verified, the [`Error`] is included as an extension of the response.
+/// The rest are passed to the inner service with the
+/// [`NegotiatePrincipal`] of the client as an extension, and the token of
+/// the mutual authentication is included in the `WWW-Authenticate` header
+/// of its response.
+///
+/// The tokens are verified by the returned future in a blocking task of
+/// tokio, since decrypting them and checking the replay cache may block,
+/// so the service must run inside a tokio runtime.
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+///
+/// let layer = NegotiateLayer::new(
+///     NegotiateAcceptor::from_keytab_file("/etc/krb5.keytab").unwrap(),
+/// );
+/// // Wrap the service of the server, as `Router::layer(layer)` in axum
+/// ```
+#[derive(Clone)]
+pub struct NegotiateLayer {
+    acceptor: NegotiateAcceptor,
+}
+
+impl NegotiateLayer {
+    pub fn new(acceptor: NegotiateAcceptor) -> Self {
+        return Self { acceptor };
+    }
+}
+
+impl<S> Layer<S> for NegotiateLayer {
+    type Service = NegotiateService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        return NegotiateService {
+            inner,
+            acceptor: self.acceptor.clone(),
+        };
+    }
+}
+
+/// Service created by the [`NegotiateLayer`].
+#[derive(Clone)]
+pub struct NegotiateService<S> {
+    inner: S,
+    acceptor: NegotiateAcceptor,
+}
+
+/// Future of the response of a [`NegotiateService`].
+pub type NegotiateFuture<ResBody, E> =
+    Pin<Box<dyn Future<Output = Result<Response<ResBody>, E>> + Send>>;
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for NegotiateService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send,
+    S::Error: Send,
+    ReqBody: Send + 'static,
+    ResBody: Default + Send + 'static,
+{
+    type Response = Response<ResBody>;
+    type Error = S::Error;
+    type Future = NegotiateFuture<ResBody, S::Error>;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        return self.inner.poll_ready(cx);
+    }
+
+    fn call(&mut self, mut request: Request<ReqBody>) -> Self::Future {
+        // the inner service that was polled ready is the one called
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let acceptor = self.acceptor.clone();
+        let authorization = request
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|authorization| authorization.to_str().ok())
+            .map(|authorization| authorization.to_string());
+
+        return Box::pin(async move {
+            let authorization = match authorization {
+                Some(authorization) => authorization,
+                None => return Ok(unauthorized(None)),
+            };
+            let authentication =
+                match authenticate(acceptor, authorization).await {
+                    Ok(authentication) => authentication,
+                    Err(error) => return Ok(unauthorized(Some(error))),
+                };
+
+            let www_authenticate = authentication
+                .www_authenticate
+                .and_then(|value| HeaderValue::from_str(&value).ok());
+            request
+                .extensions_mut()
+                .insert::<NegotiatePrincipal>(authentication.principal);
+
+            let mut response = inner.call(request).await?;
+            if let Some(www_authenticate) = www_authenticate {
+                response
+                    .headers_mut()
+                    .insert(WWW_AUTHENTICATE, www_authenticate);
+            }
+            return Ok(response);
+        });
+    }
+}
+
+/// Verifies the token of the `Authorization` header in a blocking task.
+async fn authenticate(
+    acceptor: NegotiateAcceptor,
+    authorization: String,
+) -> Result<NegotiateAuthentication, Error> {
+    let result = tokio::task::spawn_blocking(move || {
+        return acceptor.authenticate(&authorization);
+    })
+    .await;
+
+    match result {
+        Ok(result) => return result,
+        Err(error) if error.is_panic() => resume_unwind(error.into_panic()),
+        Err(_) => {
+            return Err(Error::IOError(
+                io::Error::other("authentication cancelled").into(),
+            ))
+        }
+    }
+}
+
+/// Response `401 Unauthorized` with the Negotiate challenge, and the error
+/// of the authentication as extension, if any.
+fn unauthorized<ResBody: Default>(error: Option<Error>) -> Response<ResBody> {
+    let mut response = Response::new(ResBody::default());
+    *response.status_mut() = StatusCode::UNAUTHORIZED;
+    response.headers_mut().insert(
+        WWW_AUTHENTICATE,
+        HeaderValue::from_static(NegotiateAcceptor::challenge()),
+    );
+    if let Some(error) = error {
+        response.extensions_mut().insert(error);
+    }
+    return response;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gss::test_context::{create_acceptor, create_credential};
+    use crate::negotiate::{NegotiateClient, NegotiateStatus};
+    use std::convert::Infallible;
+    use std::future::{ready, Ready};
+
+    /// Service that replies with the name of the authenticated client.
+    #[derive(Clone)]
+    struct Hello {}
+
+    impl Service<Request<String>> for Hello {
+        type Response = Response<String>;
+        type Error = Infallible;
+        type Future = Ready<Result<Response<String>, Infallible>>;
+
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Result<(), Infallible>> {
+            return Poll::Ready(Ok(()));
+        }
+
+        fn call(&mut self, request: Request<String>) -> Self::Future {
+            let principal =
+                request.extensions().get::<NegotiatePrincipal>().unwrap();
+            return ready(Ok(Response::new(format!(
+                "Hello {}",
+                principal.name()
+            ))));
+        }
+    }
+
+    fn create_service() -> NegotiateService<Hello> {
+        return NegotiateLayer::new(NegotiateAcceptor::new(create_acceptor()))
+            .layer(Hello {});
+    }
+
+    #[tokio::test]
+    async fn authenticate_request() {
+        let mut negotiate = NegotiateClient::new(create_credential());
+        let request = Request::builder()
+            .header(AUTHORIZATION, negotiate.authorization().unwrap())
+            .body(String::new())
+            .unwrap();
+
+        let response = create_service().call(request).await.unwrap();
+
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!("Hello mickey@KINGDOM.HEARTS", response.body());
+        let www_authenticate = response
+            .headers()
+            .get(WWW_AUTHENTICATE)
+            .unwrap()
+            .to_str()
+            .unwrap();
+        assert_eq!(
+            NegotiateStatus::Complete,
+            negotiate
+                .process_www_authenticate(www_authenticate)
+                .unwrap()
+        );
+        assert!(negotiate.is_complete());
+    }
+
+    #[tokio::test]
+    async fn challenge_request_without_authorization() {
+        let request = Request::new(String::new());
+
+        let response = create_service().call(request).await.unwrap();
+
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+        assert_eq!(
+            "Negotiate",
+            response.headers().get(WWW_AUTHENTICATE).unwrap()
+        );
+        assert_eq!("", response.body());
+        assert_eq!(None, response.extensions().get::<Error>());
+    }
+
+    #[tokio::test]
+    async fn challenge_request_with_invalid_token() {
+        let request = Request::builder()
+            .header(AUTHORIZATION, "Negotiate YAH/")
+            .body(String::new())
+            .unwrap();
+
+        let response = create_service().call(request).await.unwrap();
+
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+        assert!(response.extensions().get::<Error>().is_some());
+    }
+}
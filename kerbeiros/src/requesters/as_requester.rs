@@ -1,3 +1,5 @@
+use super::requester_config::*;
+use crate::config::Krb5Config;
 use crate::error::*;
 use crate::messages::*;
 use crate::transporter::*;
@@ -5,7 +7,7 @@ use ascii::AsciiString;
 use himmelblau_kerberos_asn1::{AsRep, Asn1Object, KrbError};
 use himmelblau_kerberos_crypto::Key;
 use std::collections::HashSet;
-use std::net::IpAddr;
+use std::net::{IpAddr, SocketAddr};
 
 /// Encapsule the possible responses to an AS-REQ request
 #[derive(Debug, PartialEq)]
@@ -52,23 +54,86 @@ pub enum AsReqResponse {
 pub struct AsRequester {
     as_options: AsReqOptions,
     transporter: Box<dyn Transporter>,
-    kdc_address: IpAddr,
+    kdcs: Vec<KdcAddress>,
 }
 
 impl AsRequester {
     pub fn new(realm: AsciiString, kdc_address: IpAddr) -> Self {
+        return Self::new_with_kdcs(
+            realm,
+            vec![KdcAddress::new(
+                SocketAddr::new(kdc_address, DEFAULT_KERBEROS_PORT),
+                TransportProtocol::TCP,
+                TransportOptions::default(),
+            )],
+        );
+    }
+
+    fn new_with_kdcs(realm: AsciiString, kdcs: Vec<KdcAddress>) -> Self {
         return Self {
-            kdc_address,
-            transporter: new_transporter(kdc_address, TransportProtocol::TCP),
+            transporter: new_kdcs_transporter(&kdcs),
+            kdcs,
             as_options: AsReqOptions::new(realm),
         };
     }
 
+    /// Creates a requester that sends the messages through a custom
+    /// transporter.
+    pub fn with_transporter(
+        realm: AsciiString,
+        transporter: Box<dyn Transporter>,
+    ) -> Self {
+        return Self {
+            transporter,
+            kdcs: Vec::new(),
+            as_options: AsReqOptions::new(realm),
+        };
+    }
+
+    /// Creates a requester from the settings of a `krb5.conf` file.
+    ///
+    /// If `realm` is `None`, the `default_realm` of the configuration
+    /// is used. The KDCs are taken from the `kdc` relations of the realm,
+    /// and tried in order until one of them is reached, or resolved
+    /// through DNS if none is specified and `dns_lookup_kdc` is enabled. The KDCs without transport protocol are reached over
+    /// UDP, except for the messages bigger than `udp_preference_limit`,
+    /// which are sent over TCP. The encryption types, the KDC options and
+    /// the lifetimes of the requested tickets are also taken from the
+    /// configuration.
+    ///
+    /// # Errors
+    /// Returns [`Error::ConfigError`] if no realm is specified, the
+    /// configuration is invalid or the realm has no KDC and
+    /// `dns_lookup_kdc` is disabled, and [`Error::NameResolutionError`]
+    /// if the KDC cannot be resolved.
+    pub fn from_config(
+        config: &Krb5Config,
+        realm: Option<&AsciiString>,
+    ) -> Result<Self> {
+        let realm = realm_from_config(config, realm)?;
+        let kdcs = kdc_addresses_from_config(config, &realm)?;
+
+        let mut as_requester = Self::new_with_kdcs(realm, kdcs);
+        apply_config_to_options(config, &mut as_requester.as_options)?;
+        return Ok(as_requester);
+    }
+
     pub fn request(
         &self,
         username: &AsciiString,
         user_key: Option<&Key>,
     ) -> Result<AsReqResponse> {
+        let (_, response) = self.send_as_req(username, user_key)?;
+        return Ok(response);
+    }
+
+    /// Sends an AS-REQ and returns it along with the response, in order to
+    /// validate the response against the request.
+    pub(crate) fn send_as_req(
+        &self,
+        username: &AsciiString,
+        user_key: Option<&Key>,
+    ) -> Result<(EncodedAsReq, AsReqResponse)> {
         return AsRequest::request(
             username,
             user_key,
@@ -77,6 +142,18 @@ impl AsRequester {
         );
     }
 
+    /// Sends an AS-REQ without pre-authentication that only offers the
+    /// given encryption types, instead of the configured ones.
+    pub(crate) fn send_as_req_with_etypes(
+        &self,
+        username: &AsciiString,
+        etypes: HashSet<i32>,
+    ) -> Result<(EncodedAsReq, AsReqResponse)> {
+        let mut options = self.as_options.clone();
+        options.set_etypes(etypes)?;
+        return AsRequest::request(username, None, &options, &self.transporter);
+    }
+
     pub fn etypes(&self) -> &HashSet<i32> {
         return self.as_options.etypes();
     }
@@ -97,21 +174,62 @@ impl AsRequester {
         return self.as_options.realm();
     }
 
+    /// Changes the protocol used to reach the KDC. It has no effect if
+    /// the requester uses a custom transporter.
     pub fn set_transport_protocol(
         &mut self,
         transport_protocol: TransportProtocol,
     ) {
-        self.transporter =
-            new_transporter(self.kdc_address, transport_protocol);
+        for kdc in self.kdcs.iter_mut() {
+            kdc.transport_protocol = transport_protocol.clone();
+        }
+        self.update_transporter();
     }
 
-    #[cfg(test)]
+    /// Sends the messages through a SOCKS5 proxy, or directly to the KDC
+    /// if `None`. It has no effect if the requester uses a custom
+    /// transporter.
+    pub fn set_socks5_proxy(&mut self, proxy: Option<Socks5Proxy>) {
+        for kdc in self.kdcs.iter_mut() {
+            kdc.transport_options.socks5_proxy = proxy.clone();
+        }
+        self.update_transporter();
+    }
+
+    /// Sets the maximum size of the responses accepted from the KDC,
+    /// [`DEFAULT_MAX_RESPONSE_SIZE`] by default. It has no effect if the
+    /// requester uses a custom transporter.
+    pub fn set_max_response_size(&mut self, max_response_size: usize) {
+        for kdc in self.kdcs.iter_mut() {
+            kdc.transport_options.max_response_size = max_response_size;
+        }
+        self.update_transporter();
+    }
+
+    fn update_transporter(&mut self) {
+        if !self.kdcs.is_empty() {
+            self.transporter = new_kdcs_transporter(&self.kdcs);
+        }
+    }
+
+    /// Replaces the transporter used to send the messages.
     pub fn set_transporter(&mut self, transporter: Box<dyn Transporter>) {
+        self.kdcs.clear();
         self.transporter = transporter;
     }
+
+    #[cfg(test)]
+    pub(crate) fn set_nonce(&mut self, nonce: u32) {
+        self.as_options.set_nonce(Some(nonce));
+    }
+
+    #[cfg(test)]
+    pub(crate) fn set_clock_skew(&mut self, clock_skew: chrono::Duration) {
+        self.as_options.set_clock_skew(clock_skew);
+    }
 }
 
-struct AsRequest {}
+pub(crate) struct AsRequest {}
 
 impl AsRequest {
     #[allow(clippy::borrowed_box)]
@@ -120,14 +238,19 @@ impl AsRequest {
         user_key: Option<&Key>,
         options: &AsReqOptions,
         transporter: &Box<dyn Transporter>,
-    ) -> Result<AsReqResponse> {
-        let raw_as_req =
-            AsReqBuilder::build_as_req(username, user_key, options).unwrap();
-        let raw_response = transporter.request_and_response(&raw_as_req)?;
-        return Self::parse_as_request_response(&raw_response);
+    ) -> Result<(EncodedAsReq, AsReqResponse)> {
+        let as_req = EncodedAsReq::new(AsReqBuilder::create_as_req(
+            username, user_key, options,
+        )?)
+        .with_clock_skew(options.clock_skew());
+        let raw_response = transporter.request_and_response(as_req.raw())?;
+        let response = Self::parse_as_request_response(&raw_response)?;
+        return Ok((as_req, response));
     }
 
-    fn parse_as_request_response(raw_response: &[u8]) -> Result<AsReqResponse> {
+    pub fn parse_as_request_response(
+        raw_response: &[u8],
+    ) -> Result<AsReqResponse> {
         match KrbError::parse(raw_response) {
             Ok((_, krb_error)) => {
                 return Ok(AsReqResponse::KrbError(krb_error));
@@ -143,6 +266,9 @@ impl AsRequest {
 #[cfg(test)]
 mod test {
     use super::*;
+    use himmelblau_kerberos_constants::etypes::AES256_CTS_HMAC_SHA1_96;
+    use himmelblau_kerberos_constants::kdc_options;
+    use std::io::{Read, Write};
     use std::net::Ipv4Addr;
 
     #[test]
@@ -384,4 +510,170 @@ mod test {
             _ => {}
         }
     }
+    #[test]
+    fn create_from_config() {
+        let config = Krb5Config::parse(
+            "
+[libdefaults]
+    default_realm = KINGDOM.HEARTS
+    default_tkt_enctypes = aes256-cts-hmac-sha1-96 des-cbc-crc
+    forwardable = false
+    proxiable = true
+    ticket_lifetime = 10h
+    renew_lifetime = 0
+
+[realms]
+    KINGDOM.HEARTS = {
+        kdc = udp/10.0.0.1:8888
+    }
+",
+        )
+        .unwrap();
+
+        let as_requester = AsRequester::from_config(&config, None).unwrap();
+
+        assert_eq!(
+            &AsciiString::from_ascii("KINGDOM.HEARTS").unwrap(),
+            as_requester.realm()
+        );
+        assert_eq!(
+            vec![SocketAddr::new(
+                IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+                8888
+            )],
+            as_requester
+                .kdcs
+                .iter()
+                .map(|kdc| kdc.address)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            &[AES256_CTS_HMAC_SHA1_96]
+                .iter()
+                .cloned()
+                .collect::<HashSet<i32>>(),
+            as_requester.etypes()
+        );
+        assert_eq!(
+            kdc_options::PROXIABLE | kdc_options::CANONICALIZE,
+            as_requester.kdc_options()
+        );
+        assert_eq!(
+            Some(chrono::Duration::hours(10)),
+            as_requester.as_options.ticket_lifetime()
+        );
+    }
+
+    #[test]
+    fn error_creating_from_config_without_realm() {
+        let config = Krb5Config::parse("[libdefaults]\n").unwrap();
+
+        assert_eq!(
+            Error::ConfigError("No default realm specified".to_string()),
+            AsRequester::from_config(&config, None).err().unwrap()
+        );
+    }
+    /// Spawns a KDC that answers one request over TCP with a KRB-ERROR.
+    fn spawn_tcp_kdc() -> (SocketAddr, std::thread::JoinHandle<()>) {
+        let kdc = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let kdc_addr = kdc.local_addr().unwrap();
+        let kdc_thread = std::thread::spawn(move || {
+            let (mut stream, _) = kdc.accept().unwrap();
+            let mut length = [0; 4];
+            stream.read_exact(&mut length).unwrap();
+            let mut request = vec![0; u32::from_be_bytes(length) as usize];
+            stream.read_exact(&mut request).unwrap();
+
+            let krb_error = vec![
+                0x7e, 0x81, 0xdc, 0x30, 0x81, 0xd9, 0xa0, 0x03, 0x02, 0x01,
+                0x05, 0xa1, 0x03, 0x02, 0x01, 0x1e, 0xa4, 0x11, 0x18, 0x0f,
+                0x32, 0x30, 0x31, 0x39, 0x30, 0x34, 0x31, 0x38, 0x30, 0x36,
+                0x30, 0x30, 0x33, 0x31, 0x5a, 0xa5, 0x05, 0x02, 0x03, 0x05,
+                0x34, 0x2f, 0xa6, 0x03, 0x02, 0x01, 0x19, 0xa9, 0x10, 0x1b,
+                0x0e, 0x4b, 0x49, 0x4e, 0x47, 0x44, 0x4f, 0x4d, 0x2e, 0x48,
+                0x45, 0x41, 0x52, 0x54, 0x53, 0xaa, 0x23, 0x30, 0x21, 0xa0,
+                0x03, 0x02, 0x01, 0x02, 0xa1, 0x1a, 0x30, 0x18, 0x1b, 0x06,
+                0x6b, 0x72, 0x62, 0x74, 0x67, 0x74, 0x1b, 0x0e, 0x4b, 0x49,
+                0x4e, 0x47, 0x44, 0x4f, 0x4d, 0x2e, 0x48, 0x45, 0x41, 0x52,
+                0x54, 0x53, 0xac, 0x77, 0x04, 0x75, 0x30, 0x73, 0x30, 0x50,
+                0xa1, 0x03, 0x02, 0x01, 0x13, 0xa2, 0x49, 0x04, 0x47, 0x30,
+                0x45, 0x30, 0x1d, 0xa0, 0x03, 0x02, 0x01, 0x12, 0xa1, 0x16,
+                0x1b, 0x14, 0x4b, 0x49, 0x4e, 0x47, 0x44, 0x4f, 0x4d, 0x2e,
+                0x48, 0x45, 0x41, 0x52, 0x54, 0x53, 0x6d, 0x69, 0x63, 0x6b,
+                0x65, 0x79, 0x30, 0x05, 0xa0, 0x03, 0x02, 0x01, 0x17, 0x30,
+                0x1d, 0xa0, 0x03, 0x02, 0x01, 0x03, 0xa1, 0x16, 0x1b, 0x14,
+                0x4b, 0x49, 0x4e, 0x47, 0x44, 0x4f, 0x4d, 0x2e, 0x48, 0x45,
+                0x41, 0x52, 0x54, 0x53, 0x6d, 0x69, 0x63, 0x6b, 0x65, 0x79,
+                0x30, 0x09, 0xa1, 0x03, 0x02, 0x01, 0x02, 0xa2, 0x02, 0x04,
+                0x00, 0x30, 0x09, 0xa1, 0x03, 0x02, 0x01, 0x10, 0xa2, 0x02,
+                0x04, 0x00, 0x30, 0x09, 0xa1, 0x03, 0x02, 0x01, 0x0f, 0xa2,
+                0x02, 0x04, 0x00,
+            ];
+            stream
+                .write_all(&(krb_error.len() as u32).to_be_bytes())
+                .unwrap();
+            stream.write_all(&krb_error).unwrap();
+        });
+        return (kdc_addr, kdc_thread);
+    }
+
+    #[test]
+    fn request_through_socks5_proxy() {
+        let (kdc_addr, kdc_thread) = spawn_tcp_kdc();
+
+        let proxy_addr = test_proxy::spawn_socks5_proxy(None);
+        let mut as_requester = AsRequester::new(
+            AsciiString::from_ascii("KINGDOM.HEARTS").unwrap(),
+            kdc_addr.ip(),
+        );
+        as_requester.kdcs[0].address = kdc_addr;
+        as_requester.set_socks5_proxy(Some(Socks5Proxy::new(proxy_addr)));
+
+        match as_requester
+            .request(&AsciiString::from_ascii("Mickey").unwrap(), None)
+            .unwrap()
+        {
+            AsReqResponse::KrbError(_) => {}
+            _ => {
+                unreachable!();
+            }
+        }
+        kdc_thread.join().unwrap();
+    }
+
+    #[test]
+    fn request_to_next_kdc_if_first_is_unreachable() {
+        let unreachable_addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let (kdc_addr, kdc_thread) = spawn_tcp_kdc();
+        let config = Krb5Config::parse(&format!(
+            "
+[realms]
+    KINGDOM.HEARTS = {{
+        kdc = tcp/{}
+        kdc = tcp/{}
+    }}
+",
+            unreachable_addr, kdc_addr
+        ))
+        .unwrap();
+        let as_requester = AsRequester::from_config(
+            &config,
+            Some(&AsciiString::from_ascii("KINGDOM.HEARTS").unwrap()),
+        )
+        .unwrap();
+
+        match as_requester
+            .request(&AsciiString::from_ascii("Mickey").unwrap(), None)
+            .unwrap()
+        {
+            AsReqResponse::KrbError(_) => {}
+            _ => {
+                unreachable!();
+            }
+        }
+        kdc_thread.join().unwrap();
+    }
 }
@@ -0,0 +1,260 @@
+use super::as_requester::*;
+use super::requester_config::*;
+use crate::config::Krb5Config;
+use crate::error::*;
+use crate::messages::*;
+use crate::transporter::*;
+use ascii::AsciiString;
+use himmelblau_kerberos_crypto::Key;
+use std::collections::HashSet;
+use std::net::{IpAddr, SocketAddr};
+
+/// Async version of [`AsRequester`], which sends the AS-REQ requests
+/// through an [`AsyncTransporter`]. Available with the `async` feature.
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+/// use ascii::AsciiString;
+/// use std::net::*;
+/// use himmelblau_kerberos_crypto::Key;
+///
+/// async fn request() {
+///     let realm = AsciiString::from_ascii("CONTOSO.COM").unwrap();
+///     let kdc_address = IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1));
+///     let username = AsciiString::from_ascii("Bob").unwrap();
+///     let user_key = Key::Secret("S3cr3t".to_string());
+///
+///     let as_requester = AsyncAsRequester::new(realm, kdc_address);
+///     let response = as_requester
+///         .request(&username, Some(&user_key))
+///         .await
+///         .unwrap();
+/// }
+/// ```
+///
+pub struct AsyncAsRequester {
+    as_options: AsReqOptions,
+    transporter: Box<dyn AsyncTransporter>,
+    kdcs: Vec<KdcAddress>,
+}
+
+impl AsyncAsRequester {
+    pub fn new(realm: AsciiString, kdc_address: IpAddr) -> Self {
+        return Self::new_with_kdcs(
+            realm,
+            vec![KdcAddress::new(
+                SocketAddr::new(kdc_address, DEFAULT_KERBEROS_PORT),
+                TransportProtocol::TCP,
+                TransportOptions::default(),
+            )],
+        );
+    }
+
+    fn new_with_kdcs(realm: AsciiString, kdcs: Vec<KdcAddress>) -> Self {
+        return Self {
+            transporter: new_async_kdcs_transporter(&kdcs),
+            kdcs,
+            as_options: AsReqOptions::new(realm),
+        };
+    }
+
+    /// Creates a requester that sends the messages through a custom
+    /// transporter.
+    pub fn with_transporter(
+        realm: AsciiString,
+        transporter: Box<dyn AsyncTransporter>,
+    ) -> Self {
+        return Self {
+            transporter,
+            kdcs: Vec::new(),
+            as_options: AsReqOptions::new(realm),
+        };
+    }
+
+    /// Creates a requester from the settings of a `krb5.conf` file.
+    /// See [`AsRequester::from_config`].
+    pub fn from_config(
+        config: &Krb5Config,
+        realm: Option<&AsciiString>,
+    ) -> Result<Self> {
+        let realm = realm_from_config(config, realm)?;
+        let kdcs = kdc_addresses_from_config(config, &realm)?;
+
+        let mut as_requester = Self::new_with_kdcs(realm, kdcs);
+        apply_config_to_options(config, &mut as_requester.as_options)?;
+        return Ok(as_requester);
+    }
+
+    pub async fn request(
+        &self,
+        username: &AsciiString,
+        user_key: Option<&Key>,
+    ) -> Result<AsReqResponse> {
+        let (_, response) = self.send_as_req(username, user_key).await?;
+        return Ok(response);
+    }
+
+    /// Sends an AS-REQ and returns it along with the response, in order to
+    /// validate the response against the request.
+    pub(crate) async fn send_as_req(
+        &self,
+        username: &AsciiString,
+        user_key: Option<&Key>,
+    ) -> Result<(EncodedAsReq, AsReqResponse)> {
+        return self
+            .send_as_req_with_options(username, user_key, &self.as_options)
+            .await;
+    }
+
+    /// Sends an AS-REQ without pre-authentication that only offers the
+    /// given encryption types, instead of the configured ones.
+    pub(crate) async fn send_as_req_with_etypes(
+        &self,
+        username: &AsciiString,
+        etypes: HashSet<i32>,
+    ) -> Result<(EncodedAsReq, AsReqResponse)> {
+        let mut options = self.as_options.clone();
+        options.set_etypes(etypes)?;
+        return self
+            .send_as_req_with_options(username, None, &options)
+            .await;
+    }
+
+    async fn send_as_req_with_options(
+        &self,
+        username: &AsciiString,
+        user_key: Option<&Key>,
+        options: &AsReqOptions,
+    ) -> Result<(EncodedAsReq, AsReqResponse)> {
+        let as_req = EncodedAsReq::new(AsReqBuilder::create_as_req(
+            username, user_key, options,
+        )?)
+        .with_clock_skew(options.clock_skew());
+        let raw_response =
+            self.transporter.request_and_response(as_req.raw()).await?;
+        let response = AsRequest::parse_as_request_response(&raw_response)?;
+        return Ok((as_req, response));
+    }
+
+    pub fn etypes(&self) -> &HashSet<i32> {
+        return self.as_options.etypes();
+    }
+
+    pub fn set_etype(&mut self, etype: i32) -> Result<()> {
+        return self.as_options.set_etype(etype);
+    }
+
+    pub fn set_etypes(&mut self, etypes: HashSet<i32>) -> Result<()> {
+        return self.as_options.set_etypes(etypes);
+    }
+
+    pub fn kdc_options(&self) -> u32 {
+        return self.as_options.kdc_options();
+    }
+
+    pub fn realm(&self) -> &AsciiString {
+        return self.as_options.realm();
+    }
+
+    /// Changes the protocol used to reach the KDC. It has no effect if
+    /// the requester uses a custom transporter.
+    pub fn set_transport_protocol(
+        &mut self,
+        transport_protocol: TransportProtocol,
+    ) {
+        for kdc in self.kdcs.iter_mut() {
+            kdc.transport_protocol = transport_protocol.clone();
+        }
+        self.update_transporter();
+    }
+
+    /// Sends the messages through a SOCKS5 proxy, or directly to the KDC
+    /// if `None`. It has no effect if the requester uses a custom
+    /// transporter.
+    pub fn set_socks5_proxy(&mut self, proxy: Option<Socks5Proxy>) {
+        for kdc in self.kdcs.iter_mut() {
+            kdc.transport_options.socks5_proxy = proxy.clone();
+        }
+        self.update_transporter();
+    }
+
+    /// Sets the maximum size of the responses accepted from the KDC. It
+    /// has no effect if the requester uses a custom transporter.
+    pub fn set_max_response_size(&mut self, max_response_size: usize) {
+        for kdc in self.kdcs.iter_mut() {
+            kdc.transport_options.max_response_size = max_response_size;
+        }
+        self.update_transporter();
+    }
+
+    fn update_transporter(&mut self) {
+        if !self.kdcs.is_empty() {
+            self.transporter = new_async_kdcs_transporter(&self.kdcs);
+        }
+    }
+
+    /// Replaces the transporter used to send the messages.
+    pub fn set_transporter(&mut self, transporter: Box<dyn AsyncTransporter>) {
+        self.kdcs.clear();
+        self.transporter = transporter;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const KRB_ERROR_PREAUTH_REQUIRED: &[u8] = &[
+        0x7e, 0x81, 0xdc, 0x30, 0x81, 0xd9, 0xa0, 0x03, 0x02, 0x01, 0x05, 0xa1,
+        0x03, 0x02, 0x01, 0x1e, 0xa4, 0x11, 0x18, 0x0f, 0x32, 0x30, 0x31, 0x39,
+        0x30, 0x34, 0x31, 0x38, 0x30, 0x36, 0x30, 0x30, 0x33, 0x31, 0x5a, 0xa5,
+        0x05, 0x02, 0x03, 0x05, 0x34, 0x2f, 0xa6, 0x03, 0x02, 0x01, 0x19, 0xa9,
+        0x10, 0x1b, 0x0e, 0x4b, 0x49, 0x4e, 0x47, 0x44, 0x4f, 0x4d, 0x2e, 0x48,
+        0x45, 0x41, 0x52, 0x54, 0x53, 0xaa, 0x23, 0x30, 0x21, 0xa0, 0x03, 0x02,
+        0x01, 0x02, 0xa1, 0x1a, 0x30, 0x18, 0x1b, 0x06, 0x6b, 0x72, 0x62, 0x74,
+        0x67, 0x74, 0x1b, 0x0e, 0x4b, 0x49, 0x4e, 0x47, 0x44, 0x4f, 0x4d, 0x2e,
+        0x48, 0x45, 0x41, 0x52, 0x54, 0x53, 0xac, 0x77, 0x04, 0x75, 0x30, 0x73,
+        0x30, 0x50, 0xa1, 0x03, 0x02, 0x01, 0x13, 0xa2, 0x49, 0x04, 0x47, 0x30,
+        0x45, 0x30, 0x1d, 0xa0, 0x03, 0x02, 0x01, 0x12, 0xa1, 0x16, 0x1b, 0x14,
+        0x4b, 0x49, 0x4e, 0x47, 0x44, 0x4f, 0x4d, 0x2e, 0x48, 0x45, 0x41, 0x52,
+        0x54, 0x53, 0x6d, 0x69, 0x63, 0x6b, 0x65, 0x79, 0x30, 0x05, 0xa0, 0x03,
+        0x02, 0x01, 0x17, 0x30, 0x1d, 0xa0, 0x03, 0x02, 0x01, 0x03, 0xa1, 0x16,
+        0x1b, 0x14, 0x4b, 0x49, 0x4e, 0x47, 0x44, 0x4f, 0x4d, 0x2e, 0x48, 0x45,
+        0x41, 0x52, 0x54, 0x53, 0x6d, 0x69, 0x63, 0x6b, 0x65, 0x79, 0x30, 0x09,
+        0xa1, 0x03, 0x02, 0x01, 0x02, 0xa2, 0x02, 0x04, 0x00, 0x30, 0x09, 0xa1,
+        0x03, 0x02, 0x01, 0x10, 0xa2, 0x02, 0x04, 0x00, 0x30, 0x09, 0xa1, 0x03,
+        0x02, 0x01, 0x0f, 0xa2, 0x02, 0x04, 0x00,
+    ];
+
+    struct FakeTransporter {}
+
+    impl AsyncTransporter for FakeTransporter {
+        fn request_and_response<'a>(
+            &'a self,
+            _raw_request: &'a [u8],
+        ) -> TransporterFuture<'a> {
+            return Box::pin(async { Ok(KRB_ERROR_PREAUTH_REQUIRED.to_vec()) });
+        }
+    }
+
+    #[tokio::test]
+    async fn receive_krb_error() {
+        let as_requester = AsyncAsRequester::with_transporter(
+            AsciiString::from_ascii("KINGDOM.HEARTS").unwrap(),
+            Box::new(FakeTransporter {}),
+        );
+
+        match as_requester
+            .request(&AsciiString::from_ascii("Mickey").unwrap(), None)
+            .await
+            .unwrap()
+        {
+            AsReqResponse::KrbError(_) => {}
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use super::requester_config::*;
+use super::tgs_requester::*;
+use crate::config::Krb5Config;
+use crate::credentials::*;
+use crate::error::*;
+use crate::messages::*;
+use crate::transporter::*;
+use ascii::AsciiString;
+use himmelblau_kerberos_asn1::{Asn1Object, PrincipalName};
+use himmelblau_kerberos_constants::kdc_options::{CANONICALIZE, FORWARDABLE};
+use himmelblau_kerberos_crypto::supported_etypes;
+use std::net::{IpAddr, SocketAddr};
+
+/// Async version of [`TgsRequester`], which sends the TGS-REQ requests
+/// through an [`AsyncTransporter`]. Available with the `async` feature.
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+/// use himmelblau_kerberos_asn1::PrincipalName;
+/// use himmelblau_kerberos_constants::principal_names::NT_SRV_INST;
+/// use ascii::AsciiString;
+/// use std::net::*;
+/// use himmelblau_kerberos_crypto::Key;
+///
+/// async fn request() {
+///     let realm = AsciiString::from_ascii("CONTOSO.COM").unwrap();
+///     let kdc_address = IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1));
+///     let username = AsciiString::from_ascii("Bob").unwrap();
+///     let user_key = Key::Secret("S3cr3t".to_string());
+///
+///     let tgt_requester = AsyncTgtRequester::new(realm, kdc_address);
+///     let tgt = tgt_requester
+///         .request(&username, Some(&user_key))
+///         .await
+///         .unwrap();
+///
+///     let mut service = PrincipalName::new(NT_SRV_INST, "HTTP".to_string());
+///     service.push("www.contoso.com".to_string());
+///
+///     let tgs_requester = AsyncTgsRequester::new(kdc_address);
+///     let credential = tgs_requester.request(&tgt, &service).await.unwrap();
+/// }
+/// ```
+pub struct AsyncTgsRequester {
+    transporter: Box<dyn AsyncTransporter>,
+    kdcs: Vec<KdcAddress>,
+    kdc_options: u32,
+    etypes: Vec<i32>,
+}
+
+impl AsyncTgsRequester {
+    pub fn new(kdc_address: IpAddr) -> Self {
+        return Self::new_with_kdcs(vec![KdcAddress::new(
+            SocketAddr::new(kdc_address, DEFAULT_KERBEROS_PORT),
+            TransportProtocol::TCP,
+            TransportOptions::default(),
+        )]);
+    }
+
+    fn new_with_kdcs(kdcs: Vec<KdcAddress>) -> Self {
+        let mut tgs_requester =
+            Self::with_transporter(new_async_kdcs_transporter(&kdcs));
+        tgs_requester.kdcs = kdcs;
+        return tgs_requester;
+    }
+
+    /// Creates a requester that sends the messages through a custom
+    /// transporter.
+    pub fn with_transporter(transporter: Box<dyn AsyncTransporter>) -> Self {
+        return Self {
+            transporter,
+            kdcs: Vec::new(),
+            kdc_options: FORWARDABLE | CANONICALIZE,
+            etypes: supported_etypes(),
+        };
+    }
+
+    /// Creates a requester for the KDC of `realm` from the settings of a
+    /// `krb5.conf` file. See [`TgsRequester::from_config`].
+    pub fn from_config(
+        config: &Krb5Config,
+        realm: &AsciiString,
+    ) -> Result<Self> {
+        let kdcs = kdc_addresses_from_config(config, realm)?;
+        let mut tgs_requester = Self::new_with_kdcs(kdcs);
+        apply_config_to_tgs_options(
+            config,
+            &mut tgs_requester.kdc_options,
+            &mut tgs_requester.etypes,
+        );
+        return Ok(tgs_requester);
+    }
+
+    /// Requests a ticket for `service` with `tgt`. See
+    /// [`TgsRequester::request`].
+    pub async fn request(
+        &self,
+        tgt: &Credential,
+        service: &PrincipalName,
+    ) -> Result<Credential> {
+        let tgs_req = TgsReqBuilder::create_tgs_req(
+            tgt,
+            service,
+            self.kdc_options,
+            &self.etypes,
+        )?;
+        let raw_response = self
+            .transporter
+            .request_and_response(&tgs_req.build())
+            .await?;
+        return TgsRequester::process_tgs_response(
+            tgt,
+            &tgs_req,
+            &raw_response,
+        );
+    }
+
+    pub fn kdc_options(&self) -> u32 {
+        return self.kdc_options;
+    }
+
+    pub fn set_kdc_options(&mut self, kdc_options: u32) {
+        self.kdc_options = kdc_options;
+    }
+
+    pub fn etypes(&self) -> &[i32] {
+        return &self.etypes;
+    }
+
+    /// Sets the encryption types requested for the session key, in order
+    /// of preference. See [`TgsRequester::set_etypes`].
+    pub fn set_etypes(&mut self, etypes: Vec<i32>) -> Result<()> {
+        error_if_unsupported_etypes(&etypes)?;
+        self.etypes = etypes;
+        return Ok(());
+    }
+
+    /// Sends the messages through a SOCKS5 proxy, or directly to the KDC
+    /// if `None`. It has no effect if the requester uses a custom
+    /// transporter.
+    pub fn set_socks5_proxy(&mut self, proxy: Option<Socks5Proxy>) {
+        for kdc in self.kdcs.iter_mut() {
+            kdc.transport_options.socks5_proxy = proxy.clone();
+        }
+        self.update_transporter();
+    }
+
+    fn update_transporter(&mut self) {
+        if !self.kdcs.is_empty() {
+            self.transporter = new_async_kdcs_transporter(&self.kdcs);
+        }
+    }
+
+    /// Replaces the transporter used to send the messages.
+    pub fn set_transporter(&mut self, transporter: Box<dyn AsyncTransporter>) {
+        self.kdcs.clear();
+        self.transporter = transporter;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::test_kdc::*;
+    use super::*;
+    use himmelblau_kerberos_constants::error_codes::KDC_ERR_S_PRINCIPAL_UNKNOWN;
+    use himmelblau_kerberos_constants::principal_names::NT_SRV_INST;
+
+    fn service_name() -> PrincipalName {
+        let mut service = PrincipalName::new(NT_SRV_INST, "http".to_string());
+        service.push("castle.kingdom.hearts".to_string());
+        return service;
+    }
+
+    #[tokio::test]
+    async fn request_service_ticket() {
+        let tgt = test_tgt();
+        let kdc = TestKdc::new("KINGDOM.HEARTS", vec![service_name()], None);
+        let tgs_requester = AsyncTgsRequester::with_transporter(Box::new(kdc));
+
+        let credential =
+            tgs_requester.request(&tgt, &service_name()).await.unwrap();
+
+        assert_eq!(&service_name(), credential.sname());
+        assert_eq!(tgt.cname(), credential.cname());
+        assert_ne!(tgt.key(), credential.key());
+    }
+
+    #[tokio::test]
+    async fn request_service_ticket_receiving_krb_error() {
+        let kdc = TestKdc::new("KINGDOM.HEARTS", vec![], None);
+        let tgs_requester = AsyncTgsRequester::with_transporter(Box::new(kdc));
+
+        match tgs_requester.request(&test_tgt(), &service_name()).await {
+            Err(Error::KrbErrorResponse(kdc_error)) => {
+                assert_eq!(
+                    KDC_ERR_S_PRINCIPAL_UNKNOWN,
+                    kdc_error.raw_error_code()
+                );
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use super::as_requester::AsReqResponse;
+use super::async_as_requester::*;
+use super::keytab_request::AsyncKeytabTGTRequest;
+use super::tgt_requester::TGTRequest;
+use crate::config::Krb5Config;
+use crate::credentials::*;
+use crate::transporter::*;
+use crate::Result;
+use ascii::AsciiString;
+use himmelblau_kerberos_crypto::Key;
+use himmelblau_kerberos_keytab::Keytab;
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::path::Path;
+
+/// Async version of [`TgtRequester`](super::TgtRequester). Available with
+/// the `async` feature.
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+/// use ascii::AsciiString;
+/// use std::net::*;
+/// use himmelblau_kerberos_crypto::Key;
+///
+/// async fn request() {
+///     let realm = AsciiString::from_ascii("CONTOSO.COM").unwrap();
+///     let kdc_address = IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1));
+///     let username = AsciiString::from_ascii("Bob").unwrap();
+///     let user_key = Key::Secret("S3cr3t".to_string());
+///
+///     let tgt_requester = AsyncTgtRequester::new(realm, kdc_address);
+///     let credential = tgt_requester
+///         .request(&username, Some(&user_key))
+///         .await
+///         .unwrap();
+/// }
+/// ```
+///
+pub struct AsyncTgtRequester {
+    as_requester: AsyncAsRequester,
+}
+
+impl AsyncTgtRequester {
+    pub fn new(realm: AsciiString, kdc_address: IpAddr) -> Self {
+        return Self {
+            as_requester: AsyncAsRequester::new(realm, kdc_address),
+        };
+    }
+
+    /// Creates a requester that sends the messages through a custom
+    /// transporter.
+    pub fn with_transporter(
+        realm: AsciiString,
+        transporter: Box<dyn AsyncTransporter>,
+    ) -> Self {
+        return Self {
+            as_requester: AsyncAsRequester::with_transporter(
+                realm,
+                transporter,
+            ),
+        };
+    }
+
+    /// Creates a requester from the settings of a `krb5.conf` file.
+    /// See [`AsRequester::from_config`](super::AsRequester::from_config).
+    pub fn from_config(
+        config: &Krb5Config,
+        realm: Option<&AsciiString>,
+    ) -> Result<Self> {
+        return Ok(Self {
+            as_requester: AsyncAsRequester::from_config(config, realm)?,
+        });
+    }
+
+    pub async fn request(
+        &self,
+        username: &AsciiString,
+        user_key: Option<&Key>,
+    ) -> Result<Credential> {
+        match self.as_requester.send_as_req(username, None).await? {
+            (_, AsReqResponse::KrbError(krb_error)) => {
+                let user_key =
+                    TGTRequest::process_1st_krb_error(krb_error, user_key)?;
+                let (as_req, response) = self
+                    .as_requester
+                    .send_as_req(username, Some(user_key))
+                    .await?;
+                return TGTRequest::process_2nd_response(
+                    &as_req,
+                    response,
+                    Some(user_key),
+                );
+            }
+            (as_req, AsReqResponse::AsRep(as_rep)) => {
+                return TGTRequest::extract_credential_from_as_rep(
+                    &as_req, as_rep, user_key,
+                );
+            }
+        }
+    }
+
+    /// Requests a TGT with the keys of `username` stored in `keytab`. See
+    /// [`TgtRequester::request_with_keytab`](super::TgtRequester::request_with_keytab).
+    pub async fn request_with_keytab(
+        &self,
+        username: &AsciiString,
+        keytab: &Keytab,
+    ) -> Result<Credential> {
+        return AsyncKeytabTGTRequest::request(
+            username,
+            keytab,
+            &self.as_requester,
+        )
+        .await;
+    }
+
+    /// Same as [`request_with_keytab`](Self::request_with_keytab), but
+    /// reading the keytab from a file.
+    pub async fn request_with_keytab_file<P: AsRef<Path>>(
+        &self,
+        username: &AsciiString,
+        path: P,
+    ) -> Result<Credential> {
+        let keytab = load_keytab(path.as_ref())?;
+        return self.request_with_keytab(username, &keytab).await;
+    }
+
+    pub fn etypes(&self) -> &HashSet<i32> {
+        return self.as_requester.etypes();
+    }
+
+    pub fn set_etype(&mut self, etype: i32) -> Result<()> {
+        return self.as_requester.set_etype(etype);
+    }
+
+    pub fn set_etypes(&mut self, etypes: HashSet<i32>) -> Result<()> {
+        return self.as_requester.set_etypes(etypes);
+    }
+
+    pub fn kdc_options(&self) -> u32 {
+        return self.as_requester.kdc_options();
+    }
+
+    pub fn realm(&self) -> &AsciiString {
+        return self.as_requester.realm();
+    }
+
+    pub fn set_transport_protocol(
+        &mut self,
+        transport_protocol: TransportProtocol,
+    ) {
+        self.as_requester.set_transport_protocol(transport_protocol);
+    }
+
+    /// Sends the messages through a SOCKS5 proxy, or directly to the KDC
+    /// if `None`.
+    pub fn set_socks5_proxy(&mut self, proxy: Option<Socks5Proxy>) {
+        self.as_requester.set_socks5_proxy(proxy);
+    }
+
+    /// Sets the maximum size of the responses accepted from the KDC.
+    pub fn set_max_response_size(&mut self, max_response_size: usize) {
+        self.as_requester.set_max_response_size(max_response_size);
+    }
+
+    /// Replaces the transporter used to send the messages.
+    pub fn set_transporter(&mut self, transporter: Box<dyn AsyncTransporter>) {
+        self.as_requester.set_transporter(transporter);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Error;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    const KRB_ERROR_PREAUTH_REQUIRED: &[u8] = &[
+        0x7e, 0x81, 0xdc, 0x30, 0x81, 0xd9, 0xa0, 0x03, 0x02, 0x01, 0x05, 0xa1,
+        0x03, 0x02, 0x01, 0x1e, 0xa4, 0x11, 0x18, 0x0f, 0x32, 0x30, 0x31, 0x39,
+        0x30, 0x34, 0x31, 0x38, 0x30, 0x36, 0x30, 0x30, 0x33, 0x31, 0x5a, 0xa5,
+        0x05, 0x02, 0x03, 0x05, 0x34, 0x2f, 0xa6, 0x03, 0x02, 0x01, 0x19, 0xa9,
+        0x10, 0x1b, 0x0e, 0x4b, 0x49, 0x4e, 0x47, 0x44, 0x4f, 0x4d, 0x2e, 0x48,
+        0x45, 0x41, 0x52, 0x54, 0x53, 0xaa, 0x23, 0x30, 0x21, 0xa0, 0x03, 0x02,
+        0x01, 0x02, 0xa1, 0x1a, 0x30, 0x18, 0x1b, 0x06, 0x6b, 0x72, 0x62, 0x74,
+        0x67, 0x74, 0x1b, 0x0e, 0x4b, 0x49, 0x4e, 0x47, 0x44, 0x4f, 0x4d, 0x2e,
+        0x48, 0x45, 0x41, 0x52, 0x54, 0x53, 0xac, 0x77, 0x04, 0x75, 0x30, 0x73,
+        0x30, 0x50, 0xa1, 0x03, 0x02, 0x01, 0x13, 0xa2, 0x49, 0x04, 0x47, 0x30,
+        0x45, 0x30, 0x1d, 0xa0, 0x03, 0x02, 0x01, 0x12, 0xa1, 0x16, 0x1b, 0x14,
+        0x4b, 0x49, 0x4e, 0x47, 0x44, 0x4f, 0x4d, 0x2e, 0x48, 0x45, 0x41, 0x52,
+        0x54, 0x53, 0x6d, 0x69, 0x63, 0x6b, 0x65, 0x79, 0x30, 0x05, 0xa0, 0x03,
+        0x02, 0x01, 0x17, 0x30, 0x1d, 0xa0, 0x03, 0x02, 0x01, 0x03, 0xa1, 0x16,
+        0x1b, 0x14, 0x4b, 0x49, 0x4e, 0x47, 0x44, 0x4f, 0x4d, 0x2e, 0x48, 0x45,
+        0x41, 0x52, 0x54, 0x53, 0x6d, 0x69, 0x63, 0x6b, 0x65, 0x79, 0x30, 0x09,
+        0xa1, 0x03, 0x02, 0x01, 0x02, 0xa2, 0x02, 0x04, 0x00, 0x30, 0x09, 0xa1,
+        0x03, 0x02, 0x01, 0x10, 0xa2, 0x02, 0x04, 0x00, 0x30, 0x09, 0xa1, 0x03,
+        0x02, 0x01, 0x0f, 0xa2, 0x02, 0x04, 0x00,
+    ];
+
+    struct FakeTransporter {
+        requests: AtomicUsize,
+    }
+
+    impl AsyncTransporter for FakeTransporter {
+        fn request_and_response<'a>(
+            &'a self,
+            _raw_request: &'a [u8],
+        ) -> TransporterFuture<'a> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            return Box::pin(async { Ok(KRB_ERROR_PREAUTH_REQUIRED.to_vec()) });
+        }
+    }
+
+    #[tokio::test]
+    async fn request_tgt_receiving_krb_error() {
+        let transporter = Arc::new(FakeTransporter {
+            requests: AtomicUsize::new(0),
+        });
+        let tgt_requester = AsyncTgtRequester::with_transporter(
+            AsciiString::from_ascii("KINGDOM.HEARTS").unwrap(),
+            Box::new(transporter.clone()),
+        );
+
+        let error = tgt_requester
+            .request(
+                &AsciiString::from_ascii("mickey").unwrap(),
+                Some(&Key::Secret("Minnie1234".to_string())),
+            )
+            .await
+            .err()
+            .unwrap();
+
+        match error {
+            Error::KrbErrorResponse(_) => {}
+            _ => unreachable!(),
+        }
+        assert_eq!(2, transporter.requests.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,421 @@
+use super::as_requester::*;
+#[cfg(feature = "async")]
+use super::async_as_requester::AsyncAsRequester;
+use super::tgt_requester::TGTRequest;
+use crate::credentials::{Credential, KeytabKeys};
+use crate::messages::{EncodedAsReq, ErrorData, KdcError};
+use crate::{Error, Result};
+use ascii::AsciiString;
+use himmelblau_kerberos_asn1::{AsRep, Asn1Object, EtypeInfo2, KrbError};
+use himmelblau_kerberos_constants::error_codes::{
+    KDC_ERR_PREAUTH_FAILED, KDC_ERR_PREAUTH_REQUIRED, KRB_AP_ERR_BAD_INTEGRITY,
+};
+use himmelblau_kerberos_constants::pa_data_types::PA_ETYPE_INFO2;
+use himmelblau_kerberos_keytab::Keytab;
+use std::collections::HashSet;
+
+/// Encryption types advertised by the KDC in the ETYPE-INFO2 of a
+/// *KRB-ERROR* with `KDC_ERR_PREAUTH_REQUIRED`, by order of preference.
+pub(crate) fn etype_info2_etypes(krb_error: &KrbError) -> Vec<i32> {
+    let raw_e_data = match &krb_error.e_data {
+        Some(raw_e_data) => raw_e_data,
+        None => return Vec::new(),
+    };
+
+    let method_data = match ErrorData::parse(raw_e_data) {
+        ErrorData::MethodData(method_data) => method_data,
+        _ => return Vec::new(),
+    };
+
+    for padata in method_data.iter() {
+        if padata.padata_type != PA_ETYPE_INFO2 {
+            continue;
+        }
+        if let Ok((_, etype_info2)) = EtypeInfo2::parse(&padata.padata_value) {
+            return etype_info2.iter().map(|entry| entry.etype).collect();
+        }
+    }
+
+    return Vec::new();
+}
+
+/// Errors returned by the KDC when the pre-authentication was encrypted
+/// with a wrong key, so another key of the keytab can be tried.
+fn is_wrong_key_error(krb_error: &KrbError) -> bool {
+    return krb_error.error_code == KDC_ERR_PREAUTH_FAILED
+        || krb_error.error_code == KRB_AP_ERR_BAD_INTEGRITY;
+}
+
+/// Requests a TGT with the keys of a keytab, as `kinit -k` does.
+///
+/// A first AS-REQ is sent without pre-authentication in order to know
+/// the encryption types accepted by the KDC. Then, the keys of the
+/// keytab are tried in the order advertised by the KDC, from the newest
+/// to the oldest version, until the KDC accepts one of them.
+pub(crate) struct KeytabTGTRequest<'a> {
+    username: &'a AsciiString,
+    keys: KeytabKeys,
+    as_requester: &'a AsRequester,
+}
+
+impl<'a> KeytabTGTRequest<'a> {
+    pub fn request(
+        username: &'a AsciiString,
+        keytab: &Keytab,
+        as_requester: &'a AsRequester,
+    ) -> Result<Credential> {
+        let request = Self {
+            username,
+            keys: user_keys(
+                username,
+                keytab,
+                as_requester.realm(),
+                as_requester.etypes(),
+            )?,
+            as_requester,
+        };
+        return request.request_tgt();
+    }
+
+    fn request_tgt(&self) -> Result<Credential> {
+        match self
+            .as_requester
+            .send_as_req_with_etypes(self.username, self.keys.etypes())?
+        {
+            (_, AsReqResponse::KrbError(krb_error)) => {
+                if krb_error.error_code != KDC_ERR_PREAUTH_REQUIRED {
+                    return Err(Error::KrbErrorResponse(krb_error.into()));
+                }
+                return self.request_with_preauth(&krb_error);
+            }
+            (as_req, AsReqResponse::AsRep(as_rep)) => {
+                return self.extract_credential(&as_req, as_rep);
+            }
+        }
+    }
+
+    fn request_with_preauth(&self, krb_error: &KrbError) -> Result<Credential> {
+        let etypes = preauth_etypes(&self.keys, krb_error);
+        let mut error = no_key_for_etypes(&etypes);
+
+        for keytab_key in self.keys.for_etypes(&etypes) {
+            let (as_req, response) = self
+                .as_requester
+                .send_as_req(self.username, Some(&keytab_key.key))?;
+
+            match response {
+                AsReqResponse::KrbError(krb_error)
+                    if is_wrong_key_error(&krb_error) =>
+                {
+                    error = Error::KrbErrorResponse(KdcError::new(krb_error));
+                }
+                response => {
+                    return TGTRequest::process_2nd_response(
+                        &as_req,
+                        response,
+                        Some(&keytab_key.key),
+                    );
+                }
+            }
+        }
+
+        return Err(error);
+    }
+
+    fn extract_credential(
+        &self,
+        as_req: &EncodedAsReq,
+        as_rep: AsRep,
+    ) -> Result<Credential> {
+        return extract_credential_with_keys(&self.keys, as_req, as_rep);
+    }
+}
+
+/// Keys of `username` in `keytab` for the encryption types of the
+/// requester.
+fn user_keys(
+    username: &AsciiString,
+    keytab: &Keytab,
+    realm: &AsciiString,
+    etypes: &HashSet<i32>,
+) -> Result<KeytabKeys> {
+    let keys =
+        KeytabKeys::new(keytab, realm.as_str(), username.as_str(), etypes);
+    if keys.is_empty() {
+        return Err(Error::KeytabError(format!(
+            "no supported key for {}@{}",
+            username, realm
+        )));
+    }
+    return Ok(keys);
+}
+
+/// Encryption types of the keys to try for the pre-authentication, the
+/// advertised by the KDC or, if none, the ones of the keytab.
+fn preauth_etypes(keys: &KeytabKeys, krb_error: &KrbError) -> Vec<i32> {
+    let etypes = etype_info2_etypes(krb_error);
+    if etypes.is_empty() {
+        return keys.ordered_etypes();
+    }
+    return etypes;
+}
+
+fn no_key_for_etypes(etypes: &[i32]) -> Error {
+    return Error::KeytabError(format!(
+        "no key for the encryption types supported by the KDC: {:?}",
+        etypes
+    ));
+}
+
+/// Decrypts an AS-REP received without pre-authentication, with the
+/// keys that match the encryption type and version of the reply.
+fn extract_credential_with_keys(
+    keys: &KeytabKeys,
+    as_req: &EncodedAsReq,
+    as_rep: AsRep,
+) -> Result<Credential> {
+    let mut error =
+        Error::ParseAsRepError(as_rep.clone(), Box::new(Error::NoKeyProvided));
+
+    for keytab_key in
+        keys.for_reply(as_rep.enc_part.etype, as_rep.enc_part.kvno)
+    {
+        match TGTRequest::extract_credential_from_as_rep(
+            as_req,
+            as_rep.clone(),
+            Some(&keytab_key.key),
+        ) {
+            Ok(credential) => return Ok(credential),
+            Err(e) => error = e,
+        }
+    }
+
+    return Err(error);
+}
+
+/// Async version of [`KeytabTGTRequest`], which sends the requests
+/// through an [`AsyncAsRequester`].
+#[cfg(feature = "async")]
+pub(crate) struct AsyncKeytabTGTRequest<'a> {
+    username: &'a AsciiString,
+    keys: KeytabKeys,
+    as_requester: &'a AsyncAsRequester,
+}
+
+#[cfg(feature = "async")]
+impl<'a> AsyncKeytabTGTRequest<'a> {
+    pub async fn request(
+        username: &'a AsciiString,
+        keytab: &Keytab,
+        as_requester: &'a AsyncAsRequester,
+    ) -> Result<Credential> {
+        let request = Self {
+            username,
+            keys: user_keys(
+                username,
+                keytab,
+                as_requester.realm(),
+                as_requester.etypes(),
+            )?,
+            as_requester,
+        };
+        return request.request_tgt().await;
+    }
+
+    async fn request_tgt(&self) -> Result<Credential> {
+        match self
+            .as_requester
+            .send_as_req_with_etypes(self.username, self.keys.etypes())
+            .await?
+        {
+            (_, AsReqResponse::KrbError(krb_error)) => {
+                if krb_error.error_code != KDC_ERR_PREAUTH_REQUIRED {
+                    return Err(Error::KrbErrorResponse(krb_error.into()));
+                }
+                return self.request_with_preauth(&krb_error).await;
+            }
+            (as_req, AsReqResponse::AsRep(as_rep)) => {
+                return extract_credential_with_keys(
+                    &self.keys, &as_req, as_rep,
+                );
+            }
+        }
+    }
+
+    async fn request_with_preauth(
+        &self,
+        krb_error: &KrbError,
+    ) -> Result<Credential> {
+        let etypes = preauth_etypes(&self.keys, krb_error);
+        let mut error = no_key_for_etypes(&etypes);
+
+        for keytab_key in self.keys.for_etypes(&etypes) {
+            let (as_req, response) = self
+                .as_requester
+                .send_as_req(self.username, Some(&keytab_key.key))
+                .await?;
+
+            match response {
+                AsReqResponse::KrbError(krb_error)
+                    if is_wrong_key_error(&krb_error) =>
+                {
+                    error = Error::KrbErrorResponse(KdcError::new(krb_error));
+                }
+                response => {
+                    return TGTRequest::process_2nd_response(
+                        &as_req,
+                        response,
+                        Some(&keytab_key.key),
+                    );
+                }
+            }
+        }
+
+        return Err(error);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::requesters::test_kdc::{keytab_entry, TestAsKdc};
+    use crate::requesters::TgtRequester;
+    use himmelblau_kerberos_crypto::Key;
+    use std::sync::atomic::Ordering;
+
+    fn aes256_key(byte: u8) -> Key {
+        return Key::AES256Key([byte; 32]);
+    }
+
+    fn rc4_key(byte: u8) -> Key {
+        return Key::RC4Key([byte; 16]);
+    }
+
+    fn request_tgt(kdc: TestAsKdc, keytab: &Keytab) -> Result<Credential> {
+        let tgt_requester = TgtRequester::with_transporter(
+            AsciiString::from_ascii("KINGDOM.HEARTS").unwrap(),
+            Box::new(kdc),
+        );
+        return tgt_requester.request_with_keytab(
+            &AsciiString::from_ascii("mickey").unwrap(),
+            keytab,
+        );
+    }
+
+    #[test]
+    fn request_tgt_with_keytab() {
+        let kdc = TestAsKdc::new(
+            "KINGDOM.HEARTS",
+            vec![(2, aes256_key(1)), (2, rc4_key(2))],
+            true,
+        );
+        let requests = kdc.requests();
+        let keytab = Keytab::new(
+            5,
+            2,
+            vec![
+                keytab_entry("mickey@KINGDOM.HEARTS", 2, &rc4_key(2)),
+                keytab_entry("mickey@KINGDOM.HEARTS", 2, &aes256_key(1)),
+            ],
+        );
+
+        let tgt = request_tgt(kdc, &keytab).unwrap();
+
+        assert_eq!("krbtgt/KINGDOM.HEARTS", tgt.sname().to_string());
+        assert_eq!(2, requests.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn use_etype_advertised_by_kdc() {
+        let kdc = TestAsKdc::new("KINGDOM.HEARTS", vec![(2, rc4_key(2))], true);
+        let requests = kdc.requests();
+        let keytab = Keytab::new(
+            5,
+            2,
+            vec![
+                keytab_entry("mickey@KINGDOM.HEARTS", 2, &aes256_key(1)),
+                keytab_entry("mickey@KINGDOM.HEARTS", 2, &rc4_key(2)),
+            ],
+        );
+
+        request_tgt(kdc, &keytab).unwrap();
+
+        assert_eq!(2, requests.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn fall_back_to_older_kvno_when_preauth_fails() {
+        let kdc =
+            TestAsKdc::new("KINGDOM.HEARTS", vec![(2, aes256_key(2))], true);
+        let requests = kdc.requests();
+        let keytab = Keytab::new(
+            5,
+            2,
+            vec![
+                keytab_entry("mickey@KINGDOM.HEARTS", 2, &aes256_key(2)),
+                keytab_entry("mickey@KINGDOM.HEARTS", 3, &aes256_key(3)),
+            ],
+        );
+
+        request_tgt(kdc, &keytab).unwrap();
+
+        assert_eq!(3, requests.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn use_kvno_of_reply_without_preauth() {
+        let kdc =
+            TestAsKdc::new("KINGDOM.HEARTS", vec![(2, aes256_key(2))], false);
+        let requests = kdc.requests();
+        let keytab = Keytab::new(
+            5,
+            2,
+            vec![
+                keytab_entry("mickey@KINGDOM.HEARTS", 3, &aes256_key(3)),
+                keytab_entry("mickey@KINGDOM.HEARTS", 2, &aes256_key(2)),
+            ],
+        );
+
+        request_tgt(kdc, &keytab).unwrap();
+
+        assert_eq!(1, requests.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn error_when_no_key_is_accepted() {
+        let kdc =
+            TestAsKdc::new("KINGDOM.HEARTS", vec![(2, aes256_key(2))], true);
+        let keytab = Keytab::new(
+            5,
+            2,
+            vec![keytab_entry("mickey@KINGDOM.HEARTS", 3, &aes256_key(3))],
+        );
+
+        match request_tgt(kdc, &keytab).unwrap_err() {
+            Error::KrbErrorResponse(kdc_error) => {
+                assert_eq!(KDC_ERR_PREAUTH_FAILED, kdc_error.raw_error_code())
+            }
+            error => panic!("unexpected error {}", error),
+        }
+    }
+
+    #[test]
+    fn error_without_keys_of_principal() {
+        let kdc =
+            TestAsKdc::new("KINGDOM.HEARTS", vec![(2, aes256_key(2))], true);
+        let requests = kdc.requests();
+        let keytab = Keytab::new(
+            5,
+            2,
+            vec![keytab_entry("minnie@KINGDOM.HEARTS", 2, &aes256_key(2))],
+        );
+
+        assert_eq!(
+            Error::KeytabError(
+                "no supported key for mickey@KINGDOM.HEARTS".to_string()
+            ),
+            request_tgt(kdc, &keytab).unwrap_err()
+        );
+        assert_eq!(0, requests.load(Ordering::SeqCst));
+    }
+}
@@ -1,4 +1,9 @@
 //! This module exports the classes that are responsible for send the different requests to the KDC and receive its responses
+//!
+//! With the `async` feature, async versions of the requesters are also
+//! available, which share the message building code with the blocking ones.
+
+mod requester_config;
 
 mod as_requester;
 pub use as_requester::*;
@@ -6,4 +11,27 @@ pub use as_requester::*;
 mod tgt_requester;
 pub use tgt_requester::*;
 
+mod keytab_request;
+
+mod tgs_requester;
+pub use tgs_requester::*;
+
+#[cfg(feature = "async")]
+mod async_as_requester;
+#[cfg(feature = "async")]
+pub use async_as_requester::*;
+
+#[cfg(feature = "async")]
+mod async_tgt_requester;
+#[cfg(feature = "async")]
+pub use async_tgt_requester::*;
+
+#[cfg(feature = "async")]
+mod async_tgs_requester;
+#[cfg(feature = "async")]
+pub use async_tgs_requester::*;
+
 pub use crate::transporter::TransportProtocol;
+
+#[cfg(test)]
+pub(crate) mod test_kdc;
@@ -0,0 +1,289 @@
+//! Helpers to set up the requesters from a `krb5.conf` configuration,
+//! shared by the blocking and async requesters.
+
+use crate::config::Krb5Config;
+use crate::error::*;
+use crate::messages::AsReqOptions;
+use crate::transporter::*;
+use crate::utils::resolve_realm_kdc;
+use ascii::AsciiString;
+use himmelblau_kerberos_constants::kdc_options::{
+    CANONICALIZE, FORWARDABLE, PROXIABLE, RENEWABLE, RENEWABLE_OK,
+};
+use himmelblau_kerberos_crypto::is_supported_etype;
+use std::collections::HashSet;
+use std::net::SocketAddr;
+
+/// Returns the given realm, or the default realm of the configuration.
+pub(crate) fn realm_from_config(
+    config: &Krb5Config,
+    realm: Option<&AsciiString>,
+) -> Result<AsciiString> {
+    return match realm {
+        Some(realm) => Ok(realm.clone()),
+        None => config.default_realm().ok_or_else(|| {
+            Error::ConfigError("No default realm specified".to_string())
+        }),
+    };
+}
+
+/// KDC of a realm, along with the protocol and options used to reach it.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct KdcAddress {
+    pub address: SocketAddr,
+    pub transport_protocol: TransportProtocol,
+    pub transport_options: TransportOptions,
+}
+
+impl KdcAddress {
+    pub fn new(
+        address: SocketAddr,
+        transport_protocol: TransportProtocol,
+        transport_options: TransportOptions,
+    ) -> Self {
+        return Self {
+            address,
+            transport_protocol,
+            transport_options,
+        };
+    }
+
+    fn transporter(&self) -> Box<dyn Transporter> {
+        return new_transporter_with_options(
+            self.address,
+            self.transport_protocol.clone(),
+            &self.transport_options,
+        );
+    }
+
+    #[cfg(feature = "async")]
+    fn async_transporter(&self) -> Box<dyn AsyncTransporter> {
+        return new_async_transporter_with_options(
+            self.address,
+            self.transport_protocol.clone(),
+            &self.transport_options,
+        );
+    }
+}
+
+/// Returns every resolvable KDC of the realm, in the order of the
+/// configuration, or the one resolved through DNS if the realm has no
+/// KDCs and `dns_lookup_kdc` is enabled.
+///
+/// As in MIT, the KDCs without transport protocol are reached over UDP,
+/// unless the messages exceed the `udp_preference_limit`, so the limit is
+/// returned in the transport options.
+pub(crate) fn kdc_addresses_from_config(
+    config: &Krb5Config,
+    realm: &AsciiString,
+) -> Result<Vec<KdcAddress>> {
+    let realm_config = config.realm(realm.as_str())?;
+    let mut error = None;
+    let mut kdcs = Vec::new();
+
+    for kdc in realm_config.kdcs.iter() {
+        match kdc.resolve() {
+            Ok(address) => kdcs.push(match &kdc.transport_protocol {
+                Some(transport_protocol) => KdcAddress::new(
+                    address,
+                    transport_protocol.clone(),
+                    TransportOptions::default(),
+                ),
+                None => udp_preferred(config, address),
+            }),
+            Err(err) => error = Some(err),
+        }
+    }
+
+    if !kdcs.is_empty() {
+        return Ok(kdcs);
+    }
+
+    if !config.dns_lookup_kdc() {
+        return Err(error.unwrap_or_else(|| {
+            Error::ConfigError(format!(
+                "No KDC of realm {} specified and dns_lookup_kdc is disabled",
+                realm
+            ))
+        }));
+    }
+
+    let kdc_ip = resolve_realm_kdc(realm)?;
+    return Ok(vec![udp_preferred(
+        config,
+        SocketAddr::new(kdc_ip, DEFAULT_KERBEROS_PORT),
+    )]);
+}
+
+fn udp_preferred(config: &Krb5Config, address: SocketAddr) -> KdcAddress {
+    let options = TransportOptions {
+        udp_preference_limit: Some(config.udp_preference_limit()),
+        ..Default::default()
+    };
+    return KdcAddress::new(address, TransportProtocol::UDP, options);
+}
+
+/// Creates the transporter of the KDCs, which tries each of them in
+/// order until one is reached.
+pub(crate) fn new_kdcs_transporter(
+    kdcs: &[KdcAddress],
+) -> Box<dyn Transporter> {
+    if kdcs.len() == 1 {
+        return kdcs[0].transporter();
+    }
+    return Box::new(FailoverTransporter::new(
+        kdcs.iter().map(KdcAddress::transporter).collect(),
+    ));
+}
+
+/// Async version of [`new_kdcs_transporter`].
+#[cfg(feature = "async")]
+pub(crate) fn new_async_kdcs_transporter(
+    kdcs: &[KdcAddress],
+) -> Box<dyn AsyncTransporter> {
+    if kdcs.len() == 1 {
+        return kdcs[0].async_transporter();
+    }
+    return Box::new(AsyncFailoverTransporter::new(
+        kdcs.iter().map(KdcAddress::async_transporter).collect(),
+    ));
+}
+
+/// Sets the encryption types, KDC options and ticket lifetimes of the
+/// configuration.
+pub(crate) fn apply_config_to_options(
+    config: &Krb5Config,
+    options: &mut AsReqOptions,
+) -> Result<()> {
+    if let Some(etypes) = config.default_tkt_enctypes() {
+        let etypes: HashSet<i32> = etypes
+            .into_iter()
+            .filter(|etype| is_supported_etype(*etype))
+            .collect();
+        if !etypes.is_empty() {
+            options.set_etypes(etypes)?;
+        }
+    }
+
+    let mut kdc_options = options.kdc_options();
+    let toggles = [
+        (config.forwardable(), FORWARDABLE),
+        (config.proxiable(), PROXIABLE),
+        (config.canonicalize(), CANONICALIZE),
+    ];
+    for (enabled, flag) in toggles.iter() {
+        match enabled {
+            Some(true) => kdc_options |= flag,
+            Some(false) => kdc_options &= !flag,
+            None => {}
+        }
+    }
+
+    let renew_lifetime = config.renew_lifetime();
+    if let Some(lifetime) = renew_lifetime {
+        if lifetime.is_zero() {
+            kdc_options &= !(RENEWABLE | RENEWABLE_OK);
+        }
+    }
+    options.set_kdc_options(kdc_options);
+
+    options.set_ticket_lifetime(config.ticket_lifetime());
+    options.set_renew_lifetime(renew_lifetime);
+    options.set_clock_skew(config.clockskew());
+    return Ok(());
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn realm() -> AsciiString {
+        return AsciiString::from_ascii("KINGDOM.HEARTS").unwrap();
+    }
+
+    #[test]
+    fn prefer_udp_for_kdc_without_transport_protocol() {
+        let config = Krb5Config::parse(
+            "
+[libdefaults]
+    udp_preference_limit = 1000
+
+[realms]
+    KINGDOM.HEARTS = {
+        kdc = 10.0.0.1:8888
+    }
+",
+        )
+        .unwrap();
+
+        let kdcs = kdc_addresses_from_config(&config, &realm()).unwrap();
+
+        assert_eq!(
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 8888),
+            kdcs[0].address
+        );
+        assert_eq!(TransportProtocol::UDP, kdcs[0].transport_protocol);
+        assert_eq!(Some(1000), kdcs[0].transport_options.udp_preference_limit);
+    }
+
+    #[test]
+    fn use_transport_protocol_of_kdc() {
+        let config = Krb5Config::parse(
+            "
+[realms]
+    KINGDOM.HEARTS = {
+        kdc = tcp/10.0.0.1
+    }
+",
+        )
+        .unwrap();
+
+        let kdcs = kdc_addresses_from_config(&config, &realm()).unwrap();
+
+        assert_eq!(TransportProtocol::TCP, kdcs[0].transport_protocol);
+        assert_eq!(None, kdcs[0].transport_options.udp_preference_limit);
+    }
+
+    #[test]
+    fn return_every_kdc_of_realm() {
+        let config = Krb5Config::parse(
+            "
+[realms]
+    KINGDOM.HEARTS = {
+        kdc = tcp/10.0.0.1
+        kdc = 10.0.0.2
+    }
+",
+        )
+        .unwrap();
+
+        let kdcs = kdc_addresses_from_config(&config, &realm()).unwrap();
+
+        assert_eq!(
+            vec![
+                SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 88),
+                SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)), 88),
+            ],
+            kdcs.iter().map(|kdc| kdc.address).collect::<Vec<_>>()
+        );
+        assert_eq!(TransportProtocol::TCP, kdcs[0].transport_protocol);
+        assert_eq!(TransportProtocol::UDP, kdcs[1].transport_protocol);
+    }
+
+    #[test]
+    fn error_without_kdcs_if_dns_lookup_kdc_is_disabled() {
+        let config = Krb5Config::parse(
+            "
+[libdefaults]
+    dns_lookup_kdc = false
+",
+        )
+        .unwrap();
+
+        assert!(matches!(
+            kdc_addresses_from_config(&config, &realm()),
+            Err(Error::ConfigError(_))
+        ));
+    }
+}
@@ -0,0 +1,436 @@
+//! KDCs that issue tickets without any real service key, to test the
+//! requests of tickets.
+//!
+//! The session key of each ticket is stored in clear in its encrypted
+//! part, so the KDC can read it back when the ticket is used as TGT.
+
+use crate::credentials::Credential;
+use crate::error::*;
+use crate::messages::is_tgs;
+use crate::transporter::Transporter;
+#[cfg(feature = "async")]
+use crate::transporter::{AsyncTransporter, TransporterFuture};
+use chrono::{Duration, Utc};
+use himmelblau_kerberos_asn1::{
+    ApReq, AsRep, AsReq, Asn1Object, Authenticator, EncAsRepPart,
+    EncTgsRepPart, EncTicketPart, EncryptedData, EncryptionKey,
+    EtypeInfo2Entry, KrbError, PaData, PrincipalName, TgsRep, TgsReq, Ticket,
+};
+use himmelblau_kerberos_constants::error_codes::{
+    KDC_ERR_ETYPE_NOSUPP, KDC_ERR_PREAUTH_FAILED, KDC_ERR_PREAUTH_REQUIRED,
+    KDC_ERR_S_PRINCIPAL_UNKNOWN,
+};
+use himmelblau_kerberos_constants::etypes::AES256_CTS_HMAC_SHA1_96;
+use himmelblau_kerberos_constants::key_usages::{
+    KEY_USAGE_AS_REP_ENC_PART, KEY_USAGE_AS_REP_TICKET,
+    KEY_USAGE_AS_REQ_TIMESTAMP, KEY_USAGE_TGS_REP_ENC_PART_SESSION_KEY,
+    KEY_USAGE_TGS_REQ_AUTHEN,
+};
+use himmelblau_kerberos_constants::pa_data_types::{
+    PA_ENC_TIMESTAMP, PA_ETYPE_INFO2,
+};
+use himmelblau_kerberos_constants::principal_names::{
+    NT_PRINCIPAL, NT_SRV_INST,
+};
+use himmelblau_kerberos_constants::ticket_flags;
+use himmelblau_kerberos_crypto::{new_kerberos_cipher, Key};
+use himmelblau_kerberos_keytab::{CountedOctetString, KeyBlock, KeytabEntry};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+pub(crate) struct TestKdc {
+    realm: String,
+    services: Vec<PrincipalName>,
+    referral: Option<String>,
+    service_key: Option<(u32, Key)>,
+    requests: Arc<AtomicUsize>,
+}
+
+impl TestKdc {
+    /// KDC of `realm` that issues tickets for `services`, and referrals
+    /// to `referral` for the rest.
+    pub fn new(
+        realm: &str,
+        services: Vec<PrincipalName>,
+        referral: Option<&str>,
+    ) -> Self {
+        return Self {
+            realm: realm.to_string(),
+            services,
+            referral: referral.map(|realm| realm.to_string()),
+            service_key: None,
+            requests: Arc::new(AtomicUsize::new(0)),
+        };
+    }
+
+    /// Encrypts the tickets of the services with `key`, as a real KDC
+    /// does, instead of storing the session key in clear.
+    pub fn set_service_key(&mut self, kvno: u32, key: Key) {
+        self.service_key = Some((kvno, key));
+    }
+
+    fn encrypt_ticket(&self, credential: &Credential) -> Ticket {
+        let (kvno, key) = match &self.service_key {
+            Some(service_key) if !is_tgs(credential.sname()) => service_key,
+            _ => return credential.ticket().clone(),
+        };
+
+        let enc_ticket_part = EncTicketPart {
+            flags: credential.flags().clone(),
+            key: credential.key().clone(),
+            crealm: credential.crealm().clone(),
+            cname: credential.cname().clone(),
+            authtime: credential.authtime().clone(),
+            starttime: credential.starttime().cloned(),
+            endtime: credential.endtime().clone(),
+            ..Default::default()
+        };
+        let etype = key.etypes()[0];
+        let cipher = new_kerberos_cipher(etype).unwrap();
+        return Ticket::new(
+            credential.srealm().clone(),
+            credential.sname().clone(),
+            EncryptedData::new(
+                etype,
+                Some(*kvno),
+                cipher
+                    .encrypt(
+                        key.as_bytes(),
+                        KEY_USAGE_AS_REP_TICKET,
+                        &enc_ticket_part.build(),
+                    )
+                    .unwrap(),
+            ),
+        );
+    }
+
+    /// Counter of the requests received by the KDC.
+    pub fn requests(&self) -> Arc<AtomicUsize> {
+        return self.requests.clone();
+    }
+
+    fn reply(&self, tgs_req: TgsReq) -> Vec<u8> {
+        let padata = tgs_req.padata.unwrap();
+        let (_, ap_req) = ApReq::parse(&padata[0].padata_value).unwrap();
+        let (_, session_key) =
+            EncryptionKey::parse(&ap_req.ticket.enc_part.cipher).unwrap();
+
+        let cipher = new_kerberos_cipher(session_key.keytype).unwrap();
+        let raw_authenticator = cipher
+            .decrypt(
+                &session_key.keyvalue,
+                KEY_USAGE_TGS_REQ_AUTHEN,
+                &ap_req.authenticator.cipher,
+            )
+            .unwrap();
+        let (_, authenticator) =
+            Authenticator::parse(&raw_authenticator).unwrap();
+
+        let requested_sname = tgs_req.req_body.sname.unwrap();
+        let sname = if self.services.contains(&requested_sname) {
+            requested_sname
+        } else if let Some(referral) = &self.referral {
+            krbtgt_name(referral)
+        } else {
+            return KrbError {
+                error_code: KDC_ERR_S_PRINCIPAL_UNKNOWN,
+                realm: self.realm.clone(),
+                sname: requested_sname,
+                ..Default::default()
+            }
+            .build();
+        };
+
+        let credential = test_credential(
+            &authenticator.crealm,
+            authenticator.cname,
+            &self.realm,
+            sname,
+        );
+        let enc_tgs_rep_part = EncTgsRepPart {
+            key: credential.key().clone(),
+            nonce: tgs_req.req_body.nonce,
+            flags: credential.flags().clone(),
+            authtime: credential.authtime().clone(),
+            starttime: credential.starttime().cloned(),
+            endtime: tgs_req.req_body.till,
+            srealm: credential.srealm().clone(),
+            sname: credential.sname().clone(),
+            ..Default::default()
+        };
+
+        return TgsRep {
+            crealm: authenticator.crealm,
+            cname: credential.cname().clone(),
+            ticket: self.encrypt_ticket(&credential),
+            enc_part: EncryptedData::new(
+                session_key.keytype,
+                None,
+                cipher
+                    .encrypt(
+                        &session_key.keyvalue,
+                        KEY_USAGE_TGS_REP_ENC_PART_SESSION_KEY,
+                        &enc_tgs_rep_part.build(),
+                    )
+                    .unwrap(),
+            ),
+            ..Default::default()
+        }
+        .build();
+    }
+}
+
+impl Transporter for TestKdc {
+    fn request_and_response(&self, raw_request: &[u8]) -> Result<Vec<u8>> {
+        self.requests.fetch_add(1, Ordering::SeqCst);
+        let (_, tgs_req) = TgsReq::parse(raw_request).unwrap();
+        return Ok(self.reply(tgs_req));
+    }
+}
+
+#[cfg(feature = "async")]
+impl AsyncTransporter for TestKdc {
+    fn request_and_response<'a>(
+        &'a self,
+        raw_request: &'a [u8],
+    ) -> TransporterFuture<'a> {
+        let response = Transporter::request_and_response(self, raw_request);
+        return Box::pin(async { response });
+    }
+}
+
+/// KDC that issues TGTs to the clients that know one of its keys, to
+/// test the requests of TGTs.
+pub(crate) struct TestAsKdc {
+    realm: String,
+    keys: Vec<(u32, Key)>,
+    preauth: bool,
+    requests: Arc<AtomicUsize>,
+}
+
+impl TestAsKdc {
+    /// KDC of `realm` that knows the client `keys`, along with their
+    /// version. The pre-authentication is required if `preauth` is set.
+    pub fn new(realm: &str, keys: Vec<(u32, Key)>, preauth: bool) -> Self {
+        return Self {
+            realm: realm.to_string(),
+            keys,
+            preauth,
+            requests: Arc::new(AtomicUsize::new(0)),
+        };
+    }
+
+    /// Counter of the requests received by the KDC.
+    pub fn requests(&self) -> Arc<AtomicUsize> {
+        return self.requests.clone();
+    }
+
+    fn key(&self, etype: i32) -> Option<&(u32, Key)> {
+        return self.keys.iter().find(|(_, key)| key.etypes()[0] == etype);
+    }
+
+    fn error(&self, error_code: i32, e_data: Option<Vec<u8>>) -> Vec<u8> {
+        return KrbError {
+            error_code,
+            realm: self.realm.clone(),
+            sname: krbtgt_name(&self.realm),
+            e_data,
+            ..Default::default()
+        }
+        .build();
+    }
+
+    fn etype_info2(&self) -> Vec<u8> {
+        let etype_info2: Vec<EtypeInfo2Entry> = self
+            .keys
+            .iter()
+            .map(|(_, key)| EtypeInfo2Entry {
+                etype: key.etypes()[0],
+                ..Default::default()
+            })
+            .collect();
+        let method_data =
+            vec![PaData::new(PA_ETYPE_INFO2, etype_info2.build())];
+        return method_data.build();
+    }
+
+    fn reply(&self, as_req: AsReq) -> Vec<u8> {
+        let enc_timestamp = as_req.padata.as_ref().and_then(|padata| {
+            padata
+                .iter()
+                .find(|padata| padata.padata_type == PA_ENC_TIMESTAMP)
+                .map(|padata| {
+                    EncryptedData::parse(&padata.padata_value).unwrap().1
+                })
+        });
+
+        let (kvno, key) = match enc_timestamp {
+            Some(enc_timestamp) => {
+                let (kvno, key) = match self.key(enc_timestamp.etype) {
+                    Some(key) => key,
+                    None => return self.error(KDC_ERR_ETYPE_NOSUPP, None),
+                };
+                let cipher = new_kerberos_cipher(enc_timestamp.etype).unwrap();
+                if cipher
+                    .decrypt(
+                        key.as_bytes(),
+                        KEY_USAGE_AS_REQ_TIMESTAMP,
+                        &enc_timestamp.cipher,
+                    )
+                    .is_err()
+                {
+                    return self.error(KDC_ERR_PREAUTH_FAILED, None);
+                }
+                (kvno, key)
+            }
+            None if self.preauth => {
+                return self
+                    .error(KDC_ERR_PREAUTH_REQUIRED, Some(self.etype_info2()));
+            }
+            None => {
+                match as_req
+                    .req_body
+                    .etypes
+                    .iter()
+                    .find_map(|etype| self.key(*etype))
+                {
+                    Some((kvno, key)) => (kvno, key),
+                    None => return self.error(KDC_ERR_ETYPE_NOSUPP, None),
+                }
+            }
+        };
+
+        let cname = as_req.req_body.cname.unwrap();
+        let credential = test_credential(
+            &self.realm,
+            cname.clone(),
+            &self.realm,
+            krbtgt_name(&self.realm),
+        );
+        let enc_as_rep_part = EncAsRepPart {
+            key: credential.key().clone(),
+            nonce: as_req.req_body.nonce,
+            flags: credential.flags().clone(),
+            authtime: credential.authtime().clone(),
+            starttime: credential.starttime().cloned(),
+            endtime: credential.endtime().clone(),
+            srealm: credential.srealm().clone(),
+            sname: credential.sname().clone(),
+            ..Default::default()
+        };
+
+        let etype = key.etypes()[0];
+        let cipher = new_kerberos_cipher(etype).unwrap();
+        return AsRep {
+            crealm: self.realm.clone(),
+            cname,
+            ticket: credential.ticket().clone(),
+            enc_part: EncryptedData::new(
+                etype,
+                Some(*kvno),
+                cipher
+                    .encrypt(
+                        key.as_bytes(),
+                        KEY_USAGE_AS_REP_ENC_PART,
+                        &enc_as_rep_part.build(),
+                    )
+                    .unwrap(),
+            ),
+            ..Default::default()
+        }
+        .build();
+    }
+}
+
+impl Transporter for TestAsKdc {
+    fn request_and_response(&self, raw_request: &[u8]) -> Result<Vec<u8>> {
+        self.requests.fetch_add(1, Ordering::SeqCst);
+        let (_, as_req) = AsReq::parse(raw_request).unwrap();
+        return Ok(self.reply(as_req));
+    }
+}
+
+#[cfg(feature = "async")]
+impl AsyncTransporter for TestAsKdc {
+    fn request_and_response<'a>(
+        &'a self,
+        raw_request: &'a [u8],
+    ) -> TransporterFuture<'a> {
+        let response = Transporter::request_and_response(self, raw_request);
+        return Box::pin(async { response });
+    }
+}
+
+pub(crate) fn krbtgt_name(realm: &str) -> PrincipalName {
+    return PrincipalName {
+        name_type: NT_SRV_INST,
+        name_string: vec!["krbtgt".to_string(), realm.to_string()],
+    };
+}
+
+/// Credential for the service of `srealm`, valid for 10 hours, whose
+/// ticket carries the session key in clear.
+pub(crate) fn test_credential(
+    crealm: &str,
+    cname: PrincipalName,
+    srealm: &str,
+    sname: PrincipalName,
+) -> Credential {
+    let session_key = EncryptionKey::new(
+        AES256_CTS_HMAC_SHA1_96,
+        rand::random::<[u8; 32]>().to_vec(),
+    );
+    let now = Utc::now();
+
+    let ticket = Ticket::new(
+        srealm.to_string(),
+        sname.clone(),
+        EncryptedData::new(0, None, session_key.build()),
+    );
+
+    return Credential::new(
+        crealm.to_string(),
+        cname,
+        ticket,
+        EncAsRepPart {
+            key: session_key,
+            flags: ticket_flags::FORWARDABLE.into(),
+            authtime: now.into(),
+            starttime: Some(now.into()),
+            endtime: (now + Duration::hours(10)).into(),
+            srealm: srealm.to_string(),
+            sname,
+            ..Default::default()
+        },
+    );
+}
+
+/// TGT of mickey@KINGDOM.HEARTS.
+pub(crate) fn test_tgt() -> Credential {
+    return test_credential(
+        "KINGDOM.HEARTS",
+        PrincipalName::new(NT_PRINCIPAL, "mickey".to_string()),
+        "KINGDOM.HEARTS",
+        krbtgt_name("KINGDOM.HEARTS"),
+    );
+}
+
+/// Keytab entry of `principal`, written as `name/instance@REALM`.
+pub(crate) fn keytab_entry(
+    principal: &str,
+    kvno: u32,
+    key: &Key,
+) -> KeytabEntry {
+    let (name, realm) = principal.split_once('@').unwrap();
+    return KeytabEntry::new(
+        CountedOctetString::new(realm.as_bytes().to_vec()),
+        name.split('/')
+            .map(|c| CountedOctetString::new(c.as_bytes().to_vec()))
+            .collect(),
+        1,
+        0,
+        kvno as u8,
+        KeyBlock::new(key.etypes()[0] as u16, key.as_bytes().to_vec()),
+        Some(kvno),
+    );
+}
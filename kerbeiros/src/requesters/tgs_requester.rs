@@ -0,0 +1,325 @@
+use super::requester_config::*;
+use crate::config::Krb5Config;
+use crate::credentials::*;
+use crate::error::*;
+use crate::messages::*;
+use crate::transporter::*;
+use ascii::AsciiString;
+use himmelblau_kerberos_asn1::{Asn1Object, PrincipalName};
+use himmelblau_kerberos_constants::kdc_options::{CANONICALIZE, FORWARDABLE};
+use himmelblau_kerberos_crypto::{is_supported_etype, supported_etypes};
+use std::net::{IpAddr, SocketAddr};
+
+/// Encapsule the possible responses to a TGS-REQ request
+#[derive(Debug, PartialEq)]
+pub enum TgsReqResponse {
+    KrbError(KrbError),
+    TgsRep(TgsRep),
+}
+
+/// Requests service tickets to the KDC by using a TGT.
+///
+/// The TGT must be issued by the realm of the KDC, so a cross-realm TGT
+/// is required to request the tickets of services in other realms.
+/// When the KDC does not know the service, it can return a referral
+/// TGT for another realm instead, which is returned as the credential.
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+/// use himmelblau_kerberos_asn1::PrincipalName;
+/// use himmelblau_kerberos_constants::principal_names::NT_SRV_INST;
+/// use ascii::AsciiString;
+/// use std::net::*;
+/// use himmelblau_kerberos_crypto::Key;
+///
+/// let realm = AsciiString::from_ascii("CONTOSO.COM").unwrap();
+/// let kdc_address = IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1));
+/// let username = AsciiString::from_ascii("Bob").unwrap();
+/// let user_key = Key::Secret("S3cr3t".to_string());
+///
+/// let tgt_requester = TgtRequester::new(realm, kdc_address);
+/// let tgt = tgt_requester.request(&username, Some(&user_key)).unwrap();
+///
+/// let mut service = PrincipalName::new(NT_SRV_INST, "HTTP".to_string());
+/// service.push("www.contoso.com".to_string());
+///
+/// let tgs_requester = TgsRequester::new(kdc_address);
+/// let credential = tgs_requester.request(&tgt, &service).unwrap();
+/// ```
+pub struct TgsRequester {
+    transporter: Box<dyn Transporter>,
+    kdcs: Vec<KdcAddress>,
+    kdc_options: u32,
+    etypes: Vec<i32>,
+}
+
+impl TgsRequester {
+    pub fn new(kdc_address: IpAddr) -> Self {
+        return Self::new_with_kdcs(vec![KdcAddress::new(
+            SocketAddr::new(kdc_address, DEFAULT_KERBEROS_PORT),
+            TransportProtocol::TCP,
+            TransportOptions::default(),
+        )]);
+    }
+
+    fn new_with_kdcs(kdcs: Vec<KdcAddress>) -> Self {
+        let mut tgs_requester =
+            Self::with_transporter(new_kdcs_transporter(&kdcs));
+        tgs_requester.kdcs = kdcs;
+        return tgs_requester;
+    }
+
+    /// Creates a requester that sends the messages through a custom
+    /// transporter.
+    pub fn with_transporter(transporter: Box<dyn Transporter>) -> Self {
+        return Self {
+            transporter,
+            kdcs: Vec::new(),
+            kdc_options: FORWARDABLE | CANONICALIZE,
+            etypes: supported_etypes(),
+        };
+    }
+
+    /// Creates a requester for the KDC of `realm` from the settings of a
+    /// `krb5.conf` file. The KDC is resolved as in
+    /// [`AsRequester::from_config`].
+    pub fn from_config(
+        config: &Krb5Config,
+        realm: &AsciiString,
+    ) -> Result<Self> {
+        let kdcs = kdc_addresses_from_config(config, realm)?;
+        let mut tgs_requester = Self::new_with_kdcs(kdcs);
+        apply_config_to_tgs_options(
+            config,
+            &mut tgs_requester.kdc_options,
+            &mut tgs_requester.etypes,
+        );
+        return Ok(tgs_requester);
+    }
+
+    /// Requests a ticket for `service` with `tgt`. The realm of the service
+    /// is the one of the KDC that issued the TGT.
+    pub fn request(
+        &self,
+        tgt: &Credential,
+        service: &PrincipalName,
+    ) -> Result<Credential> {
+        let tgs_req = TgsReqBuilder::create_tgs_req(
+            tgt,
+            service,
+            self.kdc_options,
+            &self.etypes,
+        )?;
+        let raw_response =
+            self.transporter.request_and_response(&tgs_req.build())?;
+        return Self::process_tgs_response(tgt, &tgs_req, &raw_response);
+    }
+
+    /// Extracts the credential of the response to `tgs_req`, or the error
+    /// sent by the KDC.
+    pub(crate) fn process_tgs_response(
+        tgt: &Credential,
+        tgs_req: &TgsReq,
+        raw_response: &[u8],
+    ) -> Result<Credential> {
+        match Self::parse_tgs_request_response(raw_response)? {
+            TgsReqResponse::KrbError(krb_error) => {
+                return Err(Error::KrbErrorResponse(krb_error.into()));
+            }
+            TgsReqResponse::TgsRep(tgs_rep) => {
+                return CredentialKrbInfoMapper::tgs_rep_to_credential(
+                    tgt, tgs_req, tgs_rep,
+                );
+            }
+        }
+    }
+
+    pub fn parse_tgs_request_response(
+        raw_response: &[u8],
+    ) -> Result<TgsReqResponse> {
+        match KrbError::parse(raw_response) {
+            Ok((_, krb_error)) => {
+                return Ok(TgsReqResponse::KrbError(krb_error));
+            }
+            Err(_) => {
+                let tgs_rep = TgsRep::parse(raw_response)?.1;
+                return Ok(TgsReqResponse::TgsRep(tgs_rep));
+            }
+        }
+    }
+
+    pub fn kdc_options(&self) -> u32 {
+        return self.kdc_options;
+    }
+
+    pub fn set_kdc_options(&mut self, kdc_options: u32) {
+        self.kdc_options = kdc_options;
+    }
+
+    pub fn etypes(&self) -> &[i32] {
+        return &self.etypes;
+    }
+
+    /// Sets the encryption types requested for the session key, in order
+    /// of preference.
+    ///
+    /// # Errors
+    /// Returns [`Error::CryptographyError`] if any of them is not
+    /// supported.
+    pub fn set_etypes(&mut self, etypes: Vec<i32>) -> Result<()> {
+        error_if_unsupported_etypes(&etypes)?;
+        self.etypes = etypes;
+        return Ok(());
+    }
+
+    /// Sends the messages through a SOCKS5 proxy, or directly to the KDC
+    /// if `None`. It has no effect if the requester uses a custom
+    /// transporter.
+    pub fn set_socks5_proxy(&mut self, proxy: Option<Socks5Proxy>) {
+        for kdc in self.kdcs.iter_mut() {
+            kdc.transport_options.socks5_proxy = proxy.clone();
+        }
+        self.update_transporter();
+    }
+
+    fn update_transporter(&mut self) {
+        if !self.kdcs.is_empty() {
+            self.transporter = new_kdcs_transporter(&self.kdcs);
+        }
+    }
+
+    /// Replaces the transporter used to send the messages.
+    pub fn set_transporter(&mut self, transporter: Box<dyn Transporter>) {
+        self.kdcs.clear();
+        self.transporter = transporter;
+    }
+}
+
+/// Applies the `default_tkt_enctypes` and `canonicalize` settings of a
+/// `krb5.conf` file to the options of the TGS-REQs.
+pub(crate) fn apply_config_to_tgs_options(
+    config: &Krb5Config,
+    kdc_options: &mut u32,
+    etypes: &mut Vec<i32>,
+) {
+    if let Some(config_etypes) = config.default_tkt_enctypes() {
+        let config_etypes: Vec<i32> = config_etypes
+            .into_iter()
+            .filter(|etype| is_supported_etype(*etype))
+            .collect();
+        if !config_etypes.is_empty() {
+            *etypes = config_etypes;
+        }
+    }
+
+    if config.canonicalize() == Some(false) {
+        *kdc_options &= !CANONICALIZE;
+    }
+}
+
+pub(crate) fn error_if_unsupported_etypes(etypes: &[i32]) -> Result<()> {
+    for etype in etypes.iter() {
+        if !is_supported_etype(*etype) {
+            return Err(
+                himmelblau_kerberos_crypto::Error::UnsupportedAlgorithm(*etype)
+                    .into(),
+            );
+        }
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::test_kdc::*;
+    use super::*;
+    use himmelblau_kerberos_constants::error_codes::KDC_ERR_S_PRINCIPAL_UNKNOWN;
+    use himmelblau_kerberos_constants::principal_names::NT_SRV_INST;
+
+    fn service_name() -> PrincipalName {
+        let mut service = PrincipalName::new(NT_SRV_INST, "http".to_string());
+        service.push("castle.kingdom.hearts".to_string());
+        return service;
+    }
+
+    #[test]
+    fn request_service_ticket() {
+        let tgt = test_tgt();
+        let kdc = TestKdc::new("KINGDOM.HEARTS", vec![service_name()], None);
+        let tgs_requester = TgsRequester::with_transporter(Box::new(kdc));
+
+        let credential = tgs_requester.request(&tgt, &service_name()).unwrap();
+
+        assert_eq!(&service_name(), credential.sname());
+        assert_eq!("KINGDOM.HEARTS", credential.srealm());
+        assert_eq!(tgt.cname(), credential.cname());
+        assert_ne!(tgt.key(), credential.key());
+    }
+
+    #[test]
+    fn request_service_ticket_through_socks5_proxy() {
+        use std::io::{Read, Write};
+
+        let tgt = test_tgt();
+        let kdc = TestKdc::new("KINGDOM.HEARTS", vec![service_name()], None);
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let kdc_addr = listener.local_addr().unwrap();
+        let kdc_thread = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut length = [0; 4];
+            stream.read_exact(&mut length).unwrap();
+            let mut request = vec![0; u32::from_be_bytes(length) as usize];
+            stream.read_exact(&mut request).unwrap();
+
+            let response =
+                Transporter::request_and_response(&kdc, &request).unwrap();
+            stream
+                .write_all(&(response.len() as u32).to_be_bytes())
+                .unwrap();
+            stream.write_all(&response).unwrap();
+        });
+
+        let proxy_addr = test_proxy::spawn_socks5_proxy(None);
+        let mut tgs_requester =
+            TgsRequester::new_with_kdcs(vec![KdcAddress::new(
+                kdc_addr,
+                TransportProtocol::TCP,
+                TransportOptions::default(),
+            )]);
+        tgs_requester.set_socks5_proxy(Some(Socks5Proxy::new(proxy_addr)));
+
+        let credential = tgs_requester.request(&tgt, &service_name()).unwrap();
+
+        assert_eq!(&service_name(), credential.sname());
+        kdc_thread.join().unwrap();
+    }
+
+    #[test]
+    fn request_service_ticket_receiving_referral() {
+        let kdc = TestKdc::new("KINGDOM.HEARTS", vec![], Some("DISNEY.WORLD"));
+        let tgs_requester = TgsRequester::with_transporter(Box::new(kdc));
+
+        let credential =
+            tgs_requester.request(&test_tgt(), &service_name()).unwrap();
+
+        assert_eq!(&krbtgt_name("DISNEY.WORLD"), credential.sname());
+    }
+
+    #[test]
+    fn request_service_ticket_receiving_krb_error() {
+        let kdc = TestKdc::new("KINGDOM.HEARTS", vec![], None);
+        let tgs_requester = TgsRequester::with_transporter(Box::new(kdc));
+
+        match tgs_requester.request(&test_tgt(), &service_name()) {
+            Err(Error::KrbErrorResponse(kdc_error)) => {
+                assert_eq!(
+                    KDC_ERR_S_PRINCIPAL_UNKNOWN,
+                    kdc_error.raw_error_code()
+                );
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
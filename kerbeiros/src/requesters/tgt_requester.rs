@@ -1,13 +1,18 @@
 use super::as_requester::*;
+use super::keytab_request::KeytabTGTRequest;
+use crate::config::Krb5Config;
 use crate::credentials::*;
+use crate::messages::EncodedAsReq;
 use crate::transporter::*;
 use crate::{Error, Result};
 use ascii::AsciiString;
 use himmelblau_kerberos_asn1::{AsRep, KrbError};
 use himmelblau_kerberos_constants::error_codes::KDC_ERR_PREAUTH_REQUIRED;
 use himmelblau_kerberos_crypto::Key;
+use himmelblau_kerberos_keytab::Keytab;
 use std::collections::HashSet;
 use std::net::IpAddr;
+use std::path::Path;
 
 /// Gets a TGT from KDC by sending one or more AS-REQ requests
 /// # Examples
@@ -47,6 +52,28 @@ impl TgtRequester {
         };
     }
 
+    /// Creates a requester that sends the messages through a custom
+    /// transporter.
+    pub fn with_transporter(
+        realm: AsciiString,
+        transporter: Box<dyn Transporter>,
+    ) -> Self {
+        return Self {
+            as_requester: AsRequester::with_transporter(realm, transporter),
+        };
+    }
+
+    /// Creates a requester from the settings of a `krb5.conf` file.
+    /// See [`AsRequester::from_config`].
+    pub fn from_config(
+        config: &Krb5Config,
+        realm: Option<&AsciiString>,
+    ) -> Result<Self> {
+        return Ok(Self {
+            as_requester: AsRequester::from_config(config, realm)?,
+        });
+    }
+
     pub fn request(
         &self,
         username: &AsciiString,
@@ -55,6 +82,35 @@ impl TgtRequester {
         return TGTRequest::request(username, user_key, &self.as_requester);
     }
 
+    /// Requests a TGT with the keys of `username` stored in `keytab`, as
+    /// `kinit -k` does.
+    ///
+    /// The keys are chosen by the encryption types advertised by the KDC
+    /// in ETYPE-INFO2 and, if the KDC rejects the pre-authentication,
+    /// older versions of the keys are tried.
+    ///
+    /// # Errors
+    /// Returns [`Error::KeytabError`] if the keytab has no supported key
+    /// for the principal or for the encryption types of the KDC.
+    pub fn request_with_keytab(
+        &self,
+        username: &AsciiString,
+        keytab: &Keytab,
+    ) -> Result<Credential> {
+        return KeytabTGTRequest::request(username, keytab, &self.as_requester);
+    }
+
+    /// Same as [`request_with_keytab`](Self::request_with_keytab), but
+    /// reading the keytab from a file.
+    pub fn request_with_keytab_file<P: AsRef<Path>>(
+        &self,
+        username: &AsciiString,
+        path: P,
+    ) -> Result<Credential> {
+        let keytab = load_keytab(path.as_ref())?;
+        return self.request_with_keytab(username, &keytab);
+    }
+
     pub fn etypes(&self) -> &HashSet<i32> {
         return self.as_requester.etypes();
     }
@@ -82,13 +138,24 @@ impl TgtRequester {
         self.as_requester.set_transport_protocol(transport_protocol);
     }
 
-    #[cfg(test)]
-    fn set_transporter(&mut self, transporter: Box<dyn Transporter>) {
+    /// Sends the messages through a SOCKS5 proxy, or directly to the KDC
+    /// if `None`.
+    pub fn set_socks5_proxy(&mut self, proxy: Option<Socks5Proxy>) {
+        self.as_requester.set_socks5_proxy(proxy);
+    }
+
+    /// Sets the maximum size of the responses accepted from the KDC.
+    pub fn set_max_response_size(&mut self, max_response_size: usize) {
+        self.as_requester.set_max_response_size(max_response_size);
+    }
+
+    /// Replaces the transporter used to send the messages.
+    pub fn set_transporter(&mut self, transporter: Box<dyn Transporter>) {
         self.as_requester.set_transporter(transporter);
     }
 }
 
-struct TGTRequest<'a> {
+pub(crate) struct TGTRequest<'a> {
     username: &'a AsciiString,
     user_key: Option<&'a Key>,
     as_requester: &'a AsRequester,
@@ -109,56 +176,81 @@ impl<'a> TGTRequest<'a> {
     }
 
     fn request_tgt(&self) -> Result<Credential> {
-        match self.as_requester.request(self.username, None)? {
-            AsReqResponse::KrbError(krb_error) => {
-                return self.process_1st_krb_error(krb_error);
+        match self.as_requester.send_as_req(self.username, None)? {
+            (_, AsReqResponse::KrbError(krb_error)) => {
+                let user_key =
+                    Self::process_1st_krb_error(krb_error, self.user_key)?;
+                return self.request_2nd_as_req(user_key);
             }
-            AsReqResponse::AsRep(as_rep) => {
-                return self.extract_credential_from_as_rep(as_rep);
+            (as_req, AsReqResponse::AsRep(as_rep)) => {
+                return Self::extract_credential_from_as_rep(
+                    &as_req,
+                    as_rep,
+                    self.user_key,
+                );
             }
         }
     }
 
-    fn process_1st_krb_error(&self, krb_error: KrbError) -> Result<Credential> {
+    /// Returns the key to use in the second AS-REQ if the KDC requires
+    /// pre-authentication, or the received error otherwise.
+    pub fn process_1st_krb_error(
+        krb_error: KrbError,
+        user_key: Option<&Key>,
+    ) -> Result<&Key> {
         if krb_error.error_code != KDC_ERR_PREAUTH_REQUIRED {
-            return Err(Error::KrbErrorResponse(krb_error))?;
+            return Err(Error::KrbErrorResponse(krb_error.into()))?;
         }
 
-        if let Some(user_key) = self.user_key {
-            return self.request_2nd_as_req(user_key);
+        if let Some(user_key) = user_key {
+            return Ok(user_key);
         }
 
-        return Err(Error::KrbErrorResponse(krb_error))?;
+        return Err(Error::KrbErrorResponse(krb_error.into()))?;
     }
 
     fn request_2nd_as_req(&self, user_key: &Key) -> Result<Credential> {
-        match self.as_requester.request(self.username, Some(user_key))? {
+        let (as_req, response) = self
+            .as_requester
+            .send_as_req(self.username, Some(user_key))?;
+        return Self::process_2nd_response(&as_req, response, Some(user_key));
+    }
+
+    pub fn process_2nd_response(
+        as_req: &EncodedAsReq,
+        response: AsReqResponse,
+        user_key: Option<&Key>,
+    ) -> Result<Credential> {
+        match response {
             AsReqResponse::KrbError(krb_error) => {
-                return Err(Error::KrbErrorResponse(krb_error))?;
+                return Err(Error::KrbErrorResponse(krb_error.into()))?;
             }
             AsReqResponse::AsRep(as_rep) => {
-                return self.extract_credential_from_as_rep(as_rep);
+                return Self::extract_credential_from_as_rep(
+                    as_req, as_rep, user_key,
+                );
             }
         }
     }
 
-    fn extract_credential_from_as_rep(
-        &self,
+    pub fn extract_credential_from_as_rep(
+        as_req: &EncodedAsReq,
         as_rep: AsRep,
+        user_key: Option<&Key>,
     ) -> Result<Credential> {
-        let user_key;
-
-        if let Some(key) = &self.user_key {
-            user_key = key;
-        } else {
-            return Err(Error::ParseAsRepError(
-                as_rep,
-                Box::new(Error::NoKeyProvided),
-            ))?;
-        }
+        let user_key = match user_key {
+            Some(user_key) => user_key,
+            None => {
+                return Err(Error::ParseAsRepError(
+                    as_rep,
+                    Box::new(Error::NoKeyProvided),
+                ))?;
+            }
+        };
 
         match CredentialKrbInfoMapper::kdc_rep_to_credential(
             user_key,
+            as_req,
             as_rep.clone(),
         ) {
             Ok(credential) => {
@@ -404,6 +496,11 @@ mod test {
         );
 
         tgt_request.set_transporter(Box::new(FakeTransporter {}));
+        tgt_request.as_requester.set_nonce(104645460);
+        // the reply was recorded long ago
+        tgt_request
+            .as_requester
+            .set_clock_skew(chrono::Duration::MAX);
 
         tgt_request
             .request(
@@ -817,6 +914,11 @@ mod test {
         );
 
         tgt_request.set_transporter(Box::new(FakeTransporter {}));
+        tgt_request.as_requester.set_nonce(104645460);
+        // the reply was recorded long ago
+        tgt_request
+            .as_requester
+            .set_clock_skew(chrono::Duration::MAX);
 
         tgt_request
             .request(
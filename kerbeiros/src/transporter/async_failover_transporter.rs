@@ -0,0 +1,72 @@
+use crate::Error;
+
+use super::async_transporter_trait::*;
+use super::failover_transporter::no_transporter_error;
+
+/// Async version of [`FailoverTransporter`](super::FailoverTransporter).
+pub struct AsyncFailoverTransporter {
+    transporters: Vec<Box<dyn AsyncTransporter>>,
+}
+
+impl AsyncFailoverTransporter {
+    pub fn new(transporters: Vec<Box<dyn AsyncTransporter>>) -> Self {
+        return Self { transporters };
+    }
+}
+
+impl AsyncTransporter for AsyncFailoverTransporter {
+    fn request_and_response<'a>(
+        &'a self,
+        raw_request: &'a [u8],
+    ) -> TransporterFuture<'a> {
+        return Box::pin(async move {
+            let mut last_error = no_transporter_error();
+            for transporter in self.transporters.iter() {
+                match transporter.request_and_response(raw_request).await {
+                    Err(error @ Error::NetworkError(_)) => last_error = error,
+                    result => return result,
+                }
+            }
+            return Err(last_error);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Result;
+    use std::io;
+
+    struct FakeTransporter {
+        result: Result<Vec<u8>>,
+    }
+
+    impl AsyncTransporter for FakeTransporter {
+        fn request_and_response<'a>(
+            &'a self,
+            _raw_request: &'a [u8],
+        ) -> TransporterFuture<'a> {
+            return Box::pin(async move { self.result.clone() });
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failover_on_network_error() {
+        let transporter = AsyncFailoverTransporter::new(vec![
+            Box::new(FakeTransporter {
+                result: Err(Error::NetworkError(
+                    io::Error::from(io::ErrorKind::ConnectionRefused).into(),
+                )),
+            }),
+            Box::new(FakeTransporter {
+                result: Ok(vec![3]),
+            }),
+        ]);
+
+        assert_eq!(
+            vec![3],
+            transporter.request_and_response(&[1, 2]).await.unwrap()
+        );
+    }
+}
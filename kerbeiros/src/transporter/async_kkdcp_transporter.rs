@@ -0,0 +1,49 @@
+use super::async_transporter_trait::*;
+use super::kkdcp_transporter::KKDCPTransporter;
+use super::transporter_trait::Transporter;
+use crate::Error;
+use std::io;
+use std::sync::Arc;
+
+/// Send Kerberos messages to a KDC proxy asynchronously. The HTTP
+/// requests are performed by a [`KKDCPTransporter`] in the blocking
+/// thread pool of tokio.
+#[derive(Debug)]
+pub struct AsyncKKDCPTransporter {
+    transporter: Arc<KKDCPTransporter>,
+}
+
+impl AsyncKKDCPTransporter {
+    pub fn new(url: String) -> Self {
+        return Self {
+            transporter: Arc::new(KKDCPTransporter::new(url)),
+        };
+    }
+}
+
+impl From<KKDCPTransporter> for AsyncKKDCPTransporter {
+    fn from(transporter: KKDCPTransporter) -> Self {
+        return Self {
+            transporter: Arc::new(transporter),
+        };
+    }
+}
+
+impl AsyncTransporter for AsyncKKDCPTransporter {
+    fn request_and_response<'a>(
+        &'a self,
+        raw_request: &'a [u8],
+    ) -> TransporterFuture<'a> {
+        let transporter = self.transporter.clone();
+        let raw_request = raw_request.to_vec();
+        return Box::pin(async move {
+            return tokio::task::spawn_blocking(move || {
+                transporter.request_and_response(&raw_request)
+            })
+            .await
+            .map_err(|error| {
+                Error::NetworkError(io::Error::other(error).into())
+            })?;
+        });
+    }
+}
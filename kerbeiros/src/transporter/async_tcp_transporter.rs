@@ -0,0 +1,255 @@
+use super::async_transporter_trait::*;
+use super::socks5::Socks5Proxy;
+use super::tcp_transporter::TCPTransporter;
+use super::{check_response_size, DEFAULT_MAX_RESPONSE_SIZE, DEFAULT_TIMEOUT};
+use crate::Error;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+/// Send Kerberos messages over TCP asynchronously
+#[derive(Debug)]
+pub struct AsyncTCPTransporter {
+    dst_addr: SocketAddr,
+    proxy: Option<Socks5Proxy>,
+    max_response_size: usize,
+    timeout: Duration,
+}
+
+impl AsyncTCPTransporter {
+    pub fn new(dst_addr: SocketAddr) -> Self {
+        return Self {
+            dst_addr,
+            proxy: None,
+            max_response_size: DEFAULT_MAX_RESPONSE_SIZE,
+            timeout: DEFAULT_TIMEOUT,
+        };
+    }
+
+    /// Creates a transporter that connects through a SOCKS5 proxy.
+    pub fn with_socks5_proxy(dst_addr: SocketAddr, proxy: Socks5Proxy) -> Self {
+        return Self {
+            dst_addr,
+            proxy: Some(proxy),
+            max_response_size: DEFAULT_MAX_RESPONSE_SIZE,
+            timeout: DEFAULT_TIMEOUT,
+        };
+    }
+
+    /// Sets the maximum size of the responses accepted from the KDC.
+    pub fn set_max_response_size(&mut self, max_response_size: usize) {
+        self.max_response_size = max_response_size;
+    }
+
+    /// Sets the time to wait for the connection and for the response of
+    /// the KDC.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    async fn connect(&self) -> io::Result<TcpStream> {
+        match &self.proxy {
+            Some(proxy) => {
+                return proxy.connect_async(self.dst_addr, self.timeout).await
+            }
+            None => {
+                return with_timeout(
+                    self.timeout,
+                    TcpStream::connect(self.dst_addr),
+                )
+                .await
+            }
+        }
+    }
+
+    async fn request_and_response_tcp(
+        &self,
+        raw_request: &[u8],
+    ) -> io::Result<Vec<u8>> {
+        let mut tcp_stream = self.connect().await?;
+
+        let raw_sized_request =
+            TCPTransporter::set_size_header_to_request(raw_request);
+        tcp_stream.write_all(&raw_sized_request).await?;
+
+        return with_timeout(self.timeout, self.read_response(&mut tcp_stream))
+            .await;
+    }
+
+    async fn read_response(
+        &self,
+        tcp_stream: &mut TcpStream,
+    ) -> io::Result<Vec<u8>> {
+        let mut len_data_bytes = [0_u8; 4];
+        tcp_stream.read_exact(&mut len_data_bytes).await?;
+        let data_length = u32::from_be_bytes(len_data_bytes) as usize;
+        check_response_size(data_length, self.max_response_size)?;
+
+        let mut raw_response: Vec<u8> = vec![0; data_length];
+        tcp_stream.read_exact(&mut raw_response).await?;
+
+        return Ok(raw_response);
+    }
+}
+
+/// Runs an i/o operation, failing with `TimedOut` if it does not finish
+/// within `duration`.
+pub(crate) async fn with_timeout<T, F>(
+    duration: Duration,
+    operation: F,
+) -> io::Result<T>
+where
+    F: Future<Output = io::Result<T>>,
+{
+    return timeout(duration, operation)
+        .await
+        .map_err(|_| io::Error::from(io::ErrorKind::TimedOut))?;
+}
+
+impl AsyncTransporter for AsyncTCPTransporter {
+    fn request_and_response<'a>(
+        &'a self,
+        raw_request: &'a [u8],
+    ) -> TransporterFuture<'a> {
+        return Box::pin(async move {
+            let raw_response = self
+                .request_and_response_tcp(raw_request)
+                .await
+                .map_err(|error| Error::NetworkError(error.into()))?;
+            return Ok(raw_response);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::socks5::test_proxy::spawn_socks5_proxy;
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_request_and_response() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let transporter =
+            AsyncTCPTransporter::new(listener.local_addr().unwrap());
+
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0; 7];
+            stream.read_exact(&mut request).await.unwrap();
+            assert_eq!([0, 0, 0, 3, 1, 2, 3], request);
+            stream.write_all(&[0, 0, 0, 2, 4, 5]).await.unwrap();
+        });
+
+        assert_eq!(
+            vec![4, 5],
+            transporter.request_and_response(&[1, 2, 3]).await.unwrap()
+        );
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_request_through_socks5_proxy() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let kdc_addr = listener.local_addr().unwrap();
+
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0; 6];
+            stream.read_exact(&mut request).await.unwrap();
+            assert_eq!([0, 0, 0, 2, 1, 2], request);
+            stream.write_all(&[0, 0, 0, 1, 3]).await.unwrap();
+        });
+
+        let proxy_addr = spawn_socks5_proxy(Some(("mickey", "Minnie1234")));
+        let transporter = AsyncTCPTransporter::with_socks5_proxy(
+            kdc_addr,
+            Socks5Proxy::new(proxy_addr).with_credentials(
+                "mickey".to_string(),
+                "Minnie1234".to_string(),
+            ),
+        );
+
+        assert_eq!(
+            vec![3],
+            transporter.request_and_response(&[1, 2]).await.unwrap()
+        );
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_socks5_authentication_error() {
+        let proxy_addr = spawn_socks5_proxy(Some(("mickey", "Minnie1234")));
+        let transporter = AsyncTCPTransporter::with_socks5_proxy(
+            "127.0.0.1:88".parse().unwrap(),
+            Socks5Proxy::new(proxy_addr)
+                .with_credentials("mickey".to_string(), "Pluto".to_string()),
+        );
+
+        match transporter.request_and_response(&[1, 2]).await {
+            Err(Error::NetworkError(error)) => {
+                assert_eq!(io::ErrorKind::PermissionDenied, error.kind());
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_response_too_large() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut transporter =
+            AsyncTCPTransporter::new(listener.local_addr().unwrap());
+        transporter.set_max_response_size(1024);
+
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0; 7];
+            stream.read_exact(&mut request).await.unwrap();
+            stream.write_all(&[0xff, 0xff, 0xff, 0xff]).await.unwrap();
+        });
+
+        match transporter.request_and_response(&[1, 2, 3]).await {
+            Err(Error::NetworkError(error)) => {
+                assert_eq!(io::ErrorKind::InvalidData, error.kind());
+            }
+            _ => unreachable!(),
+        }
+        server.await.unwrap();
+    }
+
+    #[should_panic(expected = "NetworkError")]
+    #[tokio::test]
+    async fn test_request_networks_error() {
+        let requester = AsyncTCPTransporter::new(SocketAddr::new(
+            std::net::IpAddr::V4(std::net::Ipv4Addr::new(0, 0, 0, 0)),
+            88,
+        ));
+        requester.request_and_response(&[]).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_timeout_with_silent_kdc() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut transporter =
+            AsyncTCPTransporter::new(listener.local_addr().unwrap());
+        transporter.set_timeout(Duration::from_millis(100));
+
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            drop(stream);
+        });
+
+        match transporter.request_and_response(&[1, 2, 3]).await {
+            Err(Error::NetworkError(error)) => {
+                assert_eq!(io::ErrorKind::TimedOut, error.kind());
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+        server.abort();
+    }
+}
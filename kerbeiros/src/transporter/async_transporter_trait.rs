@@ -0,0 +1,59 @@
+use crate::error::*;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Future returned by [`AsyncTransporter::request_and_response`].
+pub type TransporterFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<Vec<u8>>> + Send + 'a>>;
+
+/// Async version of [`Transporter`](super::Transporter), used by the
+/// async requesters.
+///
+/// # Examples
+///
+/// ```
+/// use himmelblau_kerbeiros::*;
+///
+/// struct ReplayTransporter {
+///     response: Vec<u8>,
+/// }
+///
+/// impl AsyncTransporter for ReplayTransporter {
+///     fn request_and_response<'a>(
+///         &'a self,
+///         _raw_request: &'a [u8],
+///     ) -> TransporterFuture<'a> {
+///         return Box::pin(async move { Ok(self.response.clone()) });
+///     }
+/// }
+/// ```
+pub trait AsyncTransporter: Send + Sync {
+    /// Sends a message and retrieves the response.
+    ///
+    /// # Errors
+    /// Should return [`Error::NetworkError`] with the underlying i/o error
+    /// if the message cannot be delivered or the response retrieved.
+    fn request_and_response<'a>(
+        &'a self,
+        raw_request: &'a [u8],
+    ) -> TransporterFuture<'a>;
+}
+
+impl<T: AsyncTransporter + ?Sized> AsyncTransporter for Box<T> {
+    fn request_and_response<'a>(
+        &'a self,
+        raw_request: &'a [u8],
+    ) -> TransporterFuture<'a> {
+        return (**self).request_and_response(raw_request);
+    }
+}
+
+impl<T: AsyncTransporter + ?Sized> AsyncTransporter for Arc<T> {
+    fn request_and_response<'a>(
+        &'a self,
+        raw_request: &'a [u8],
+    ) -> TransporterFuture<'a> {
+        return (**self).request_and_response(raw_request);
+    }
+}
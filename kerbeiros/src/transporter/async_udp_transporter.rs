@@ -0,0 +1,254 @@
+use super::async_tcp_transporter::{with_timeout, AsyncTCPTransporter};
+use super::async_transporter_trait::*;
+use super::socks5::*;
+use super::udp_transporter::{
+    bind_address, response_buffer_size, MAX_DATAGRAM_SIZE,
+};
+use super::{
+    check_response_size, exceeds_udp_preference_limit, is_response_too_big,
+    DEFAULT_MAX_RESPONSE_SIZE, DEFAULT_TIMEOUT,
+};
+use crate::Error;
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+
+/// Send Kerberos messages over UDP asynchronously
+#[derive(Debug)]
+pub struct AsyncUDPTransporter {
+    dst_addr: SocketAddr,
+    proxy: Option<Socks5Proxy>,
+    max_response_size: usize,
+    timeout: Duration,
+    udp_preference_limit: Option<usize>,
+}
+
+impl AsyncUDPTransporter {
+    pub fn new(dst_addr: SocketAddr) -> Self {
+        return Self {
+            dst_addr,
+            proxy: None,
+            max_response_size: DEFAULT_MAX_RESPONSE_SIZE,
+            timeout: DEFAULT_TIMEOUT,
+            udp_preference_limit: None,
+        };
+    }
+
+    /// Creates a transporter that sends the datagrams through a SOCKS5
+    /// proxy, by using the UDP ASSOCIATE command.
+    pub fn with_socks5_proxy(dst_addr: SocketAddr, proxy: Socks5Proxy) -> Self {
+        return Self {
+            dst_addr,
+            proxy: Some(proxy),
+            max_response_size: DEFAULT_MAX_RESPONSE_SIZE,
+            timeout: DEFAULT_TIMEOUT,
+            udp_preference_limit: None,
+        };
+    }
+
+    /// Sets the maximum size of the responses accepted from the KDC.
+    pub fn set_max_response_size(&mut self, max_response_size: usize) {
+        self.max_response_size = max_response_size;
+    }
+
+    /// Sets the time to wait for the connection and for the response of
+    /// the KDC.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Sets the size limit of the messages sent over UDP. See
+    /// [`UDPTransporter::set_udp_preference_limit`].
+    ///
+    /// [`UDPTransporter::set_udp_preference_limit`]: super::UDPTransporter::set_udp_preference_limit
+    pub fn set_udp_preference_limit(&mut self, limit: Option<usize>) {
+        self.udp_preference_limit = limit;
+    }
+
+    fn tcp_transporter(&self) -> AsyncTCPTransporter {
+        let mut transporter = match &self.proxy {
+            Some(proxy) => AsyncTCPTransporter::with_socks5_proxy(
+                self.dst_addr,
+                proxy.clone(),
+            ),
+            None => AsyncTCPTransporter::new(self.dst_addr),
+        };
+        transporter.set_max_response_size(self.max_response_size);
+        transporter.set_timeout(self.timeout);
+        return transporter;
+    }
+
+    async fn request_and_response_socks5(
+        &self,
+        proxy: &Socks5Proxy,
+        raw_request: &[u8],
+    ) -> io::Result<Vec<u8>> {
+        // the association lasts while the control connection is open
+        let (_control_stream, relay_addr) =
+            proxy.udp_associate_async(self.timeout).await?;
+
+        let udp_socket = UdpSocket::bind(bind_address(relay_addr)).await?;
+        udp_socket.connect(relay_addr).await?;
+
+        udp_socket
+            .send(&encapsulate_udp_datagram(self.dst_addr, raw_request))
+            .await?;
+
+        let mut datagram = vec![0; MAX_DATAGRAM_SIZE];
+        let data_length =
+            with_timeout(self.timeout, udp_socket.recv(&mut datagram)).await?;
+        let raw_response =
+            decapsulate_udp_datagram(&datagram[..data_length])?.to_vec();
+        check_response_size(raw_response.len(), self.max_response_size)?;
+
+        return Ok(raw_response);
+    }
+
+    async fn request_and_response_udp(
+        &self,
+        raw_request: &[u8],
+    ) -> io::Result<Vec<u8>> {
+        if let Some(proxy) = &self.proxy {
+            return self.request_and_response_socks5(proxy, raw_request).await;
+        }
+
+        let udp_socket = UdpSocket::bind(bind_address(self.dst_addr)).await?;
+        udp_socket.connect(self.dst_addr).await?;
+
+        udp_socket.send(raw_request).await?;
+
+        let mut raw_response =
+            vec![0; response_buffer_size(self.max_response_size)];
+        let data_length =
+            with_timeout(self.timeout, udp_socket.recv(&mut raw_response))
+                .await?;
+        check_response_size(data_length, self.max_response_size)?;
+        raw_response.truncate(data_length);
+
+        return Ok(raw_response);
+    }
+}
+
+impl AsyncTransporter for AsyncUDPTransporter {
+    fn request_and_response<'a>(
+        &'a self,
+        raw_request: &'a [u8],
+    ) -> TransporterFuture<'a> {
+        return Box::pin(async move {
+            if exceeds_udp_preference_limit(
+                raw_request,
+                self.udp_preference_limit,
+            ) {
+                return self
+                    .tcp_transporter()
+                    .request_and_response(raw_request)
+                    .await;
+            }
+
+            let raw_response = self
+                .request_and_response_udp(raw_request)
+                .await
+                .map_err(|error| Error::NetworkError(error.into()))?;
+            if self.udp_preference_limit.is_some()
+                && is_response_too_big(&raw_response)
+            {
+                return self
+                    .tcp_transporter()
+                    .request_and_response(raw_request)
+                    .await;
+            }
+            return Ok(raw_response);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::socks5::test_proxy::spawn_socks5_proxy;
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_request_and_response() {
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let transporter =
+            AsyncUDPTransporter::new(server.local_addr().unwrap());
+
+        let server = tokio::spawn(async move {
+            let mut request = [0; 16];
+            let (size, client) = server.recv_from(&mut request).await.unwrap();
+            assert_eq!(&[1, 2, 3], &request[..size]);
+            server.send_to(&[4, 5], client).await.unwrap();
+        });
+
+        assert_eq!(
+            vec![4, 5],
+            transporter.request_and_response(&[1, 2, 3]).await.unwrap()
+        );
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_request_through_socks5_proxy() {
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let kdc_addr = server.local_addr().unwrap();
+
+        let server = tokio::spawn(async move {
+            let mut request = [0; 16];
+            let (size, client) = server.recv_from(&mut request).await.unwrap();
+            assert_eq!(&[1, 2], &request[..size]);
+            server.send_to(&[3], client).await.unwrap();
+        });
+
+        let proxy_addr = spawn_socks5_proxy(None);
+        let transporter = AsyncUDPTransporter::with_socks5_proxy(
+            kdc_addr,
+            Socks5Proxy::new(proxy_addr),
+        );
+
+        assert_eq!(
+            vec![3],
+            transporter.request_and_response(&[1, 2]).await.unwrap()
+        );
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_timeout_with_silent_kdc() {
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut transporter =
+            AsyncUDPTransporter::new(server.local_addr().unwrap());
+        transporter.set_timeout(Duration::from_millis(100));
+
+        match transporter.request_and_response(&[1, 2, 3]).await {
+            Err(Error::NetworkError(error)) => {
+                assert_eq!(io::ErrorKind::TimedOut, error.kind());
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_message_bigger_than_udp_preference_limit_over_tcp() {
+        let listener =
+            tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut transporter =
+            AsyncUDPTransporter::new(listener.local_addr().unwrap());
+        transporter.set_udp_preference_limit(Some(1));
+
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0; 7];
+            stream.read_exact(&mut request).await.unwrap();
+            assert_eq!([0, 0, 0, 3, 1, 2, 3], request);
+            stream.write_all(&[0, 0, 0, 2, 4, 5]).await.unwrap();
+        });
+
+        assert_eq!(
+            vec![4, 5],
+            transporter.request_and_response(&[1, 2, 3]).await.unwrap()
+        );
+        server.await.unwrap();
+    }
+}
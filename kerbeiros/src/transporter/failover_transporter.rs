@@ -0,0 +1,114 @@
+use crate::{Error, Result};
+use std::io;
+
+use super::transporter_trait::*;
+
+/// Sends the messages with the first of several transporters that
+/// delivers them, as MIT does with the KDCs of a realm.
+///
+/// The next transporter is only tried when the previous one fails with
+/// [`Error::NetworkError`], so the rest of errors, such as the responses
+/// that cannot be parsed, are returned directly.
+pub struct FailoverTransporter {
+    transporters: Vec<Box<dyn Transporter>>,
+}
+
+impl FailoverTransporter {
+    pub fn new(transporters: Vec<Box<dyn Transporter>>) -> Self {
+        return Self { transporters };
+    }
+}
+
+impl Transporter for FailoverTransporter {
+    fn request_and_response(&self, raw_request: &[u8]) -> Result<Vec<u8>> {
+        let mut last_error = no_transporter_error();
+        for transporter in self.transporters.iter() {
+            match transporter.request_and_response(raw_request) {
+                Err(error @ Error::NetworkError(_)) => last_error = error,
+                result => return result,
+            }
+        }
+        return Err(last_error);
+    }
+}
+
+pub(crate) fn no_transporter_error() -> Error {
+    return Error::NetworkError(
+        io::Error::new(io::ErrorKind::NotConnected, "No KDC to reach").into(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct FakeTransporter {
+        result: Result<Vec<u8>>,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl Transporter for FakeTransporter {
+        fn request_and_response(&self, _raw_request: &[u8]) -> Result<Vec<u8>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            return self.result.clone();
+        }
+    }
+
+    fn fake(
+        result: Result<Vec<u8>>,
+    ) -> (Box<dyn Transporter>, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let transporter = FakeTransporter {
+            result,
+            calls: calls.clone(),
+        };
+        return (Box::new(transporter), calls);
+    }
+
+    fn network_error() -> Error {
+        return Error::NetworkError(
+            io::Error::from(io::ErrorKind::ConnectionRefused).into(),
+        );
+    }
+
+    #[test]
+    fn test_failover_on_network_error() {
+        let (down, _) = fake(Err(network_error()));
+        let (up, _) = fake(Ok(vec![3]));
+        let (unused, unused_calls) = fake(Ok(vec![4]));
+        let transporter = FailoverTransporter::new(vec![down, up, unused]);
+
+        assert_eq!(vec![3], transporter.request_and_response(&[1, 2]).unwrap());
+        assert_eq!(0, unused_calls.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_no_failover_on_other_errors() {
+        let (invalid, _) =
+            fake(Err(Error::InvalidKdcReply("invalid".to_string())));
+        let (up, up_calls) = fake(Ok(vec![3]));
+        let transporter = FailoverTransporter::new(vec![invalid, up]);
+
+        assert_eq!(
+            Err(Error::InvalidKdcReply("invalid".to_string())),
+            transporter.request_and_response(&[1, 2])
+        );
+        assert_eq!(0, up_calls.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_last_network_error_if_all_fail() {
+        let (down, _) = fake(Err(no_transporter_error()));
+        let (also_down, _) = fake(Err(network_error()));
+        let transporter = FailoverTransporter::new(vec![down, also_down]);
+
+        match transporter.request_and_response(&[1, 2]) {
+            Err(Error::NetworkError(error)) => {
+                assert_eq!(io::ErrorKind::ConnectionRefused, error.kind());
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use super::tcp_transporter::TCPTransporter;
+use super::transporter_trait::*;
+use super::{check_response_size, DEFAULT_MAX_RESPONSE_SIZE};
+use crate::{Error, Result};
+use himmelblau_kerberos_asn1::{
+    AsReq, Asn1Object, KdcProxyMessage, Realm, TgsReq,
+};
+use std::io;
+use std::io::Read;
+use std::time::Duration;
+
+/// Bytes allowed in the HTTP responses of the KDC proxy in addition to
+/// the Kerberos message, to hold the `KDC-PROXY-MESSAGE` wrapping
+const PROXY_MESSAGE_OVERHEAD: usize = 4096;
+
+/// Send Kerberos messages to a KDC proxy over HTTP(S), as described in
+/// [MS-KKDCP](https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-kkdcp/5bcebb8d-b747-4ee5-9453-428aec1c5c38).
+///
+/// The messages are wrapped in a `KDC-PROXY-MESSAGE` whose target domain
+/// is the realm of the request, and POSTed to the URL of the proxy.
+#[derive(Debug)]
+pub struct KKDCPTransporter {
+    url: String,
+    agent: ureq::Agent,
+    max_response_size: usize,
+}
+
+impl KKDCPTransporter {
+    pub fn new(url: String) -> Self {
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(Duration::new(5, 0))
+            .timeout(Duration::new(30, 0))
+            .build();
+        return Self {
+            url,
+            agent,
+            max_response_size: DEFAULT_MAX_RESPONSE_SIZE,
+        };
+    }
+
+    /// Sets the maximum size of the Kerberos responses accepted from the
+    /// KDC proxy.
+    pub fn set_max_response_size(&mut self, max_response_size: usize) {
+        self.max_response_size = max_response_size;
+    }
+
+    pub fn url(&self) -> &str {
+        return &self.url;
+    }
+
+    /// Wraps a Kerberos message into a `KDC-PROXY-MESSAGE`.
+    pub(crate) fn build_proxy_message(raw_request: &[u8]) -> Vec<u8> {
+        let proxy_message = KdcProxyMessage {
+            kerb_message: TCPTransporter::set_size_header_to_request(
+                raw_request,
+            ),
+            target_domain: Self::target_domain(raw_request),
+            dclocator_hint: None,
+        };
+        return proxy_message.build();
+    }
+
+    fn target_domain(raw_request: &[u8]) -> Option<Realm> {
+        if let Ok((_, as_req)) = AsReq::parse(raw_request) {
+            return Some(as_req.req_body.realm);
+        }
+
+        if let Ok((_, tgs_req)) = TgsReq::parse(raw_request) {
+            return Some(tgs_req.req_body.realm);
+        }
+
+        return None;
+    }
+
+    /// Extracts the Kerberos message from a `KDC-PROXY-MESSAGE`.
+    pub(crate) fn parse_proxy_message(raw_response: &[u8]) -> Result<Vec<u8>> {
+        let (_, proxy_message) = KdcProxyMessage::parse(raw_response)?;
+        let kerb_message = proxy_message.kerb_message;
+
+        if kerb_message.len() < 4 {
+            return Err(invalid_response_error("message too short"));
+        }
+
+        let (len_data_bytes, message) = kerb_message.split_at(4);
+        let data_length = u32::from_be_bytes([
+            len_data_bytes[0],
+            len_data_bytes[1],
+            len_data_bytes[2],
+            len_data_bytes[3],
+        ]);
+
+        if data_length as usize != message.len() {
+            return Err(invalid_response_error("invalid message length"));
+        }
+
+        return Ok(message.to_vec());
+    }
+
+    fn post(&self, body: &[u8]) -> io::Result<Vec<u8>> {
+        let response = self
+            .agent
+            .post(&self.url)
+            .set("Content-Type", "application/kerberos")
+            .send_bytes(body)
+            .map_err(|error| io::Error::other(error.to_string()))?;
+
+        let max_body_size = self
+            .max_response_size
+            .saturating_add(PROXY_MESSAGE_OVERHEAD);
+        let mut raw_response = Vec::new();
+        response
+            .into_reader()
+            .take(max_body_size as u64 + 1)
+            .read_to_end(&mut raw_response)?;
+        check_response_size(raw_response.len(), max_body_size)?;
+
+        return Ok(raw_response);
+    }
+}
+
+fn invalid_response_error(reason: &str) -> Error {
+    return Error::NetworkError(
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid KDC proxy response: {}", reason),
+        )
+        .into(),
+    );
+}
+
+impl Transporter for KKDCPTransporter {
+    fn request_and_response(&self, raw_request: &[u8]) -> Result<Vec<u8>> {
+        let proxy_request = Self::build_proxy_message(raw_request);
+        let raw_response = self
+            .post(&proxy_request)
+            .map_err(|error| Error::NetworkError(error.into()))?;
+        let kerb_message = Self::parse_proxy_message(&raw_response)?;
+        check_response_size(kerb_message.len(), self.max_response_size)
+            .map_err(|error| Error::NetworkError(error.into()))?;
+        return Ok(kerb_message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use himmelblau_kerberos_asn1::KdcReqBody;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Serves one HTTP request and returns its body
+    fn serve_one_request(
+        listener: TcpListener,
+        response_body: Vec<u8>,
+    ) -> thread::JoinHandle<Vec<u8>> {
+        return thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+
+            let mut stream = reader.into_inner();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/kerberos\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n",
+                response_body.len()
+            )
+            .unwrap();
+            stream.write_all(&response_body).unwrap();
+            return body;
+        });
+    }
+
+    #[test]
+    fn test_request_and_response() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/KdcProxy", listener.local_addr().unwrap());
+
+        let response = KdcProxyMessage {
+            kerb_message: vec![0, 0, 0, 2, 0x7e, 0x00],
+            target_domain: None,
+            dclocator_hint: None,
+        };
+        let server = serve_one_request(listener, response.build());
+
+        let as_req = AsReq {
+            req_body: KdcReqBody {
+                realm: Realm::from("KINGDOM.HEARTS"),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let raw_as_req = as_req.build();
+
+        let transporter = KKDCPTransporter::new(url);
+        assert_eq!(
+            vec![0x7e, 0x00],
+            transporter.request_and_response(&raw_as_req).unwrap()
+        );
+
+        let request =
+            KdcProxyMessage::parse(&server.join().unwrap()).unwrap().1;
+        assert_eq!(Some(Realm::from("KINGDOM.HEARTS")), request.target_domain);
+        assert_eq!(
+            TCPTransporter::set_size_header_to_request(&raw_as_req),
+            request.kerb_message
+        );
+    }
+
+    #[test]
+    fn error_receiving_response_too_large() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/KdcProxy", listener.local_addr().unwrap());
+
+        let response = KdcProxyMessage {
+            kerb_message: vec![0, 0, 0, 3, 0x7e, 0x00, 0x00],
+            target_domain: None,
+            dclocator_hint: None,
+        };
+        let server = serve_one_request(listener, response.build());
+
+        let mut transporter = KKDCPTransporter::new(url);
+        transporter.set_max_response_size(2);
+        match transporter.request_and_response(&[]) {
+            Err(Error::NetworkError(error)) => {
+                assert_eq!(io::ErrorKind::InvalidData, error.kind())
+            }
+            _ => unreachable!(),
+        }
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn error_parsing_invalid_length() {
+        let response = KdcProxyMessage {
+            kerb_message: vec![0, 0, 0, 3, 0x7e, 0x00],
+            target_domain: None,
+            dclocator_hint: None,
+        };
+
+        match KKDCPTransporter::parse_proxy_message(&response.build()) {
+            Err(Error::NetworkError(error)) => {
+                assert_eq!(io::ErrorKind::InvalidData, error.kind())
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[should_panic(expected = "NetworkError")]
+    #[test]
+    fn test_request_networks_error() {
+        let transporter =
+            KKDCPTransporter::new("http://127.0.0.1:1/KdcProxy".to_string());
+        transporter.request_and_response(&[]).unwrap();
+    }
+}
@@ -1,40 +1,269 @@
 //! Module to provide means to transport Kerberos messages
 //!
+//! The [`Transporter`] trait can be implemented to provide a custom
+//! transport to the requesters.
 
+use himmelblau_kerberos_asn1::{Asn1Object, KrbError};
+use himmelblau_kerberos_constants::error_codes::KRB_ERR_RESPONSE_TOO_BIG;
+use std::io;
 use std::net::*;
+use std::time::Duration;
 
 mod transporter_trait;
 pub use transporter_trait::*;
 
+mod socks5;
+#[cfg(test)]
+pub(crate) use socks5::test_proxy;
+pub use socks5::Socks5Proxy;
+
 mod tcp_transporter;
-use tcp_transporter::*;
+pub use tcp_transporter::*;
 
 mod udp_transporter;
-use udp_transporter::*;
+pub use udp_transporter::*;
+
+mod failover_transporter;
+pub use failover_transporter::FailoverTransporter;
+
+#[cfg(feature = "kkdcp")]
+mod kkdcp_transporter;
+#[cfg(feature = "kkdcp")]
+pub use kkdcp_transporter::*;
+
+#[cfg(feature = "async")]
+mod async_transporter_trait;
+#[cfg(feature = "async")]
+pub use async_transporter_trait::*;
+
+#[cfg(feature = "async")]
+mod async_tcp_transporter;
+#[cfg(feature = "async")]
+pub use async_tcp_transporter::*;
+
+#[cfg(feature = "async")]
+mod async_udp_transporter;
+#[cfg(feature = "async")]
+pub use async_udp_transporter::*;
+
+#[cfg(feature = "async")]
+mod async_failover_transporter;
+#[cfg(feature = "async")]
+pub use async_failover_transporter::*;
+
+#[cfg(all(feature = "async", feature = "kkdcp"))]
+mod async_kkdcp_transporter;
+#[cfg(all(feature = "async", feature = "kkdcp"))]
+pub use async_kkdcp_transporter::*;
 
 /// Default Kerberos port 88
 pub const DEFAULT_KERBEROS_PORT: u16 = 88;
 
 /// Transport protocols available to send Kerberos messages
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum TransportProtocol {
     TCP,
     UDP,
+    /// Messages sent over HTTP(S) to the MS-KKDCP KDC proxy at the given
+    /// URL. Requires the `kkdcp` feature.
+    #[cfg(feature = "kkdcp")]
+    KKDCP(String),
 }
 
-/// Generates a transporter given and address and transport protocol
+/// Default maximum size of the responses accepted from the KDC, 1 MiB
+pub const DEFAULT_MAX_RESPONSE_SIZE: usize = 1024 * 1024;
+
+/// Default time to wait for the whole exchange with the KDC, from the
+/// connection to its response, 5 seconds
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Options used to create the transporters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransportOptions {
+    /// SOCKS5 proxy used to reach the KDC. It is not used with
+    /// [`TransportProtocol::KKDCP`].
+    pub socks5_proxy: Option<Socks5Proxy>,
+
+    /// Maximum size of the responses accepted from the KDC. Bigger
+    /// responses are rejected with a network error.
+    pub max_response_size: usize,
+
+    /// Size limit of the messages sent by the UDP transporters, as
+    /// `udp_preference_limit` of `krb5.conf`. Bigger messages, and the
+    /// ones whose response is too big for UDP, are sent over TCP. If
+    /// `None`, all the messages are sent over UDP.
+    pub udp_preference_limit: Option<usize>,
+}
+
+impl Default for TransportOptions {
+    fn default() -> Self {
+        return Self {
+            socks5_proxy: None,
+            max_response_size: DEFAULT_MAX_RESPONSE_SIZE,
+            udp_preference_limit: None,
+        };
+    }
+}
+
+/// Generates a transporter given and address and transport protocol.
+/// With [`TransportProtocol::KKDCP`] the address is ignored and the
+/// messages are sent to the URL of the proxy.
 pub fn new_transporter(
-    host_address: IpAddr,
+    dst_addr: SocketAddr,
     transport_protocol: TransportProtocol,
 ) -> Box<dyn Transporter> {
-    let dst_addr = SocketAddr::new(host_address, DEFAULT_KERBEROS_PORT);
+    return new_transporter_with_options(
+        dst_addr,
+        transport_protocol,
+        &TransportOptions::default(),
+    );
+}
 
+/// Generates a transporter that reaches the given address through a
+/// SOCKS5 proxy. The proxy is not used with [`TransportProtocol::KKDCP`].
+pub fn new_socks5_transporter(
+    dst_addr: SocketAddr,
+    transport_protocol: TransportProtocol,
+    proxy: Socks5Proxy,
+) -> Box<dyn Transporter> {
+    let options = TransportOptions {
+        socks5_proxy: Some(proxy),
+        ..Default::default()
+    };
+    return new_transporter_with_options(
+        dst_addr,
+        transport_protocol,
+        &options,
+    );
+}
+
+/// Generates a transporter given and address, transport protocol and
+/// the options of the transport.
+pub fn new_transporter_with_options(
+    dst_addr: SocketAddr,
+    transport_protocol: TransportProtocol,
+    options: &TransportOptions,
+) -> Box<dyn Transporter> {
+    match transport_protocol {
+        TransportProtocol::TCP => {
+            let mut transporter = match &options.socks5_proxy {
+                Some(proxy) => {
+                    TCPTransporter::with_socks5_proxy(dst_addr, proxy.clone())
+                }
+                None => TCPTransporter::new(dst_addr),
+            };
+            transporter.set_max_response_size(options.max_response_size);
+            return Box::new(transporter);
+        }
+        TransportProtocol::UDP => {
+            let mut transporter = match &options.socks5_proxy {
+                Some(proxy) => {
+                    UDPTransporter::with_socks5_proxy(dst_addr, proxy.clone())
+                }
+                None => UDPTransporter::new(dst_addr),
+            };
+            transporter.set_max_response_size(options.max_response_size);
+            transporter.set_udp_preference_limit(options.udp_preference_limit);
+            return Box::new(transporter);
+        }
+        #[cfg(feature = "kkdcp")]
+        TransportProtocol::KKDCP(url) => {
+            let mut transporter = KKDCPTransporter::new(url);
+            transporter.set_max_response_size(options.max_response_size);
+            return Box::new(transporter);
+        }
+    }
+}
+
+/// Generates an async transporter given and address and transport protocol
+#[cfg(feature = "async")]
+pub fn new_async_transporter(
+    dst_addr: SocketAddr,
+    transport_protocol: TransportProtocol,
+) -> Box<dyn AsyncTransporter> {
+    return new_async_transporter_with_options(
+        dst_addr,
+        transport_protocol,
+        &TransportOptions::default(),
+    );
+}
+
+/// Generates an async transporter given and address, transport protocol
+/// and the options of the transport.
+#[cfg(feature = "async")]
+pub fn new_async_transporter_with_options(
+    dst_addr: SocketAddr,
+    transport_protocol: TransportProtocol,
+    options: &TransportOptions,
+) -> Box<dyn AsyncTransporter> {
     match transport_protocol {
         TransportProtocol::TCP => {
-            return Box::new(TCPTransporter::new(dst_addr));
+            let mut transporter = match &options.socks5_proxy {
+                Some(proxy) => AsyncTCPTransporter::with_socks5_proxy(
+                    dst_addr,
+                    proxy.clone(),
+                ),
+                None => AsyncTCPTransporter::new(dst_addr),
+            };
+            transporter.set_max_response_size(options.max_response_size);
+            return Box::new(transporter);
         }
         TransportProtocol::UDP => {
-            return Box::new(UDPTransporter::new(dst_addr));
+            let mut transporter = match &options.socks5_proxy {
+                Some(proxy) => AsyncUDPTransporter::with_socks5_proxy(
+                    dst_addr,
+                    proxy.clone(),
+                ),
+                None => AsyncUDPTransporter::new(dst_addr),
+            };
+            transporter.set_max_response_size(options.max_response_size);
+            transporter.set_udp_preference_limit(options.udp_preference_limit);
+            return Box::new(transporter);
         }
+        #[cfg(feature = "kkdcp")]
+        TransportProtocol::KKDCP(url) => {
+            let mut transporter = KKDCPTransporter::new(url);
+            transporter.set_max_response_size(options.max_response_size);
+            return Box::new(AsyncKKDCPTransporter::from(transporter));
+        }
+    }
+}
+
+/// Whether the message must be sent over TCP instead of UDP, since it
+/// exceeds the `udp_preference_limit`.
+pub(crate) fn exceeds_udp_preference_limit(
+    raw_request: &[u8],
+    udp_preference_limit: Option<usize>,
+) -> bool {
+    return match udp_preference_limit {
+        Some(limit) => raw_request.len() > limit,
+        None => false,
+    };
+}
+
+/// Whether the response is a KRB-ERROR with the KRB_ERR_RESPONSE_TOO_BIG
+/// code, sent by the KDC when the response does not fit in a datagram.
+pub(crate) fn is_response_too_big(raw_response: &[u8]) -> bool {
+    return match KrbError::parse(raw_response) {
+        Ok((_, krb_error)) => krb_error.error_code == KRB_ERR_RESPONSE_TOO_BIG,
+        Err(_) => false,
+    };
+}
+
+/// Checks that the length announced or received for a response does not
+/// exceed the maximum allowed.
+pub(crate) fn check_response_size(
+    response_size: usize,
+    max_response_size: usize,
+) -> io::Result<()> {
+    if response_size > max_response_size {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Response too large: {} bytes exceeds the maximum of {}",
+                response_size, max_response_size
+            ),
+        ));
     }
+    return Ok(());
 }
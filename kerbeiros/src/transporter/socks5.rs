@@ -0,0 +1,540 @@
+//! Client side of the SOCKS5 protocol (RFC 1928), with username/password
+//! authentication (RFC 1929).
+
+use std::io;
+use std::io::{Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Instant;
+
+use super::tcp_transporter::DeadlineStream;
+
+#[cfg(feature = "async")]
+use super::async_tcp_transporter::with_timeout;
+#[cfg(feature = "async")]
+use std::time::Duration;
+#[cfg(feature = "async")]
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+#[cfg(feature = "async")]
+use tokio::net::TcpStream as AsyncTcpStream;
+
+const SOCKS_VERSION: u8 = 5;
+const AUTH_VERSION: u8 = 1;
+
+const METHOD_NO_AUTH: u8 = 0x00;
+const METHOD_USERNAME_PASSWORD: u8 = 0x02;
+const METHOD_NOT_ACCEPTABLE: u8 = 0xff;
+
+const COMMAND_CONNECT: u8 = 0x01;
+const COMMAND_UDP_ASSOCIATE: u8 = 0x03;
+
+const ADDRESS_IPV4: u8 = 0x01;
+const ADDRESS_DOMAIN: u8 = 0x03;
+const ADDRESS_IPV6: u8 = 0x04;
+
+const REPLY_SUCCEEDED: u8 = 0x00;
+
+/// SOCKS5 proxy used to reach the KDC.
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+/// use ascii::AsciiString;
+/// use std::net::*;
+///
+/// let realm = AsciiString::from_ascii("CONTOSO.COM").unwrap();
+/// let kdc_address = IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1));
+/// let proxy_address = "10.0.0.1:1080".parse().unwrap();
+///
+/// let mut tgt_requester = TgtRequester::new(realm, kdc_address);
+/// tgt_requester.set_socks5_proxy(Some(
+///     Socks5Proxy::new(proxy_address)
+///         .with_credentials("user".to_string(), "password".to_string()),
+/// ));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Socks5Proxy {
+    address: SocketAddr,
+    credentials: Option<(String, String)>,
+}
+
+impl Socks5Proxy {
+    pub fn new(address: SocketAddr) -> Self {
+        return Self {
+            address,
+            credentials: None,
+        };
+    }
+
+    /// Authenticates against the proxy with username and password.
+    pub fn with_credentials(
+        mut self,
+        username: String,
+        password: String,
+    ) -> Self {
+        self.credentials = Some((username, password));
+        return self;
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        return self.address;
+    }
+
+    /// Opens a TCP connection with the destination through the proxy. The
+    /// `deadline` also applies to the relayed stream.
+    pub(crate) fn connect(
+        &self,
+        dst_addr: SocketAddr,
+        deadline: Instant,
+    ) -> io::Result<DeadlineStream> {
+        let mut stream = self.open_control_connection(deadline)?;
+        Self::send_command(&mut stream, COMMAND_CONNECT, dst_addr)?;
+        return Ok(stream);
+    }
+
+    /// Asks the proxy to relay UDP datagrams. Returns the control
+    /// connection, which must be kept open while the datagrams are
+    /// exchanged, and the address of the relay.
+    pub(crate) fn udp_associate(
+        &self,
+        deadline: Instant,
+    ) -> io::Result<(DeadlineStream, SocketAddr)> {
+        let mut stream = self.open_control_connection(deadline)?;
+        let relay_addr =
+            Self::send_command(&mut stream, COMMAND_UDP_ASSOCIATE, any_addr())?;
+        return Ok((stream, self.relay_address(relay_addr)));
+    }
+
+    fn open_control_connection(
+        &self,
+        deadline: Instant,
+    ) -> io::Result<DeadlineStream> {
+        let mut stream = DeadlineStream::connect(&self.address, deadline)?;
+        self.negotiate_method(&mut stream)?;
+        return Ok(stream);
+    }
+
+    fn negotiate_method(&self, stream: &mut DeadlineStream) -> io::Result<()> {
+        stream.write_all(&[SOCKS_VERSION, 1, self.method()])?;
+
+        let mut reply = [0; 2];
+        stream.read_exact(&mut reply)?;
+        self.check_method_reply(reply)?;
+
+        if let Some((username, password)) = &self.credentials {
+            stream.write_all(&authentication_request(username, password)?)?;
+            let mut reply = [0; 2];
+            stream.read_exact(&mut reply)?;
+            check_authentication_reply(reply)?;
+        }
+        return Ok(());
+    }
+
+    fn send_command(
+        stream: &mut DeadlineStream,
+        command: u8,
+        dst_addr: SocketAddr,
+    ) -> io::Result<SocketAddr> {
+        stream.write_all(&command_request(command, dst_addr))?;
+
+        let mut reply = [0; 3];
+        stream.read_exact(&mut reply)?;
+        check_command_reply(reply)?;
+
+        return read_address(stream);
+    }
+
+    fn method(&self) -> u8 {
+        if self.credentials.is_some() {
+            return METHOD_USERNAME_PASSWORD;
+        }
+        return METHOD_NO_AUTH;
+    }
+
+    fn check_method_reply(&self, reply: [u8; 2]) -> io::Result<()> {
+        if reply[0] != SOCKS_VERSION {
+            return Err(socks_error("invalid version in reply"));
+        }
+        if reply[1] == METHOD_NOT_ACCEPTABLE || reply[1] != self.method() {
+            return Err(socks_error("authentication method not accepted"));
+        }
+        return Ok(());
+    }
+
+    /// The relay is reached through the IP of the proxy if the proxy
+    /// does not specify it.
+    fn relay_address(&self, mut relay_addr: SocketAddr) -> SocketAddr {
+        if relay_addr.ip().is_unspecified() {
+            relay_addr.set_ip(self.address.ip());
+        }
+        return relay_addr;
+    }
+}
+
+#[cfg(feature = "async")]
+impl Socks5Proxy {
+    /// Async version of [`connect`](Self::connect). The `timeout` limits
+    /// the whole negotiation with the proxy.
+    pub(crate) async fn connect_async(
+        &self,
+        dst_addr: SocketAddr,
+        timeout: Duration,
+    ) -> io::Result<AsyncTcpStream> {
+        return with_timeout(timeout, async {
+            let mut stream = self.open_async_control_connection().await?;
+            Self::send_command_async(&mut stream, COMMAND_CONNECT, dst_addr)
+                .await?;
+            return Ok(stream);
+        })
+        .await;
+    }
+
+    /// Async version of [`udp_associate`](Self::udp_associate).
+    pub(crate) async fn udp_associate_async(
+        &self,
+        timeout: Duration,
+    ) -> io::Result<(AsyncTcpStream, SocketAddr)> {
+        return with_timeout(timeout, async {
+            let mut stream = self.open_async_control_connection().await?;
+            let relay_addr = Self::send_command_async(
+                &mut stream,
+                COMMAND_UDP_ASSOCIATE,
+                any_addr(),
+            )
+            .await?;
+            return Ok((stream, self.relay_address(relay_addr)));
+        })
+        .await;
+    }
+
+    async fn open_async_control_connection(
+        &self,
+    ) -> io::Result<AsyncTcpStream> {
+        let mut stream = AsyncTcpStream::connect(self.address).await?;
+        stream.write_all(&[SOCKS_VERSION, 1, self.method()]).await?;
+
+        let mut reply = [0; 2];
+        stream.read_exact(&mut reply).await?;
+        self.check_method_reply(reply)?;
+
+        if let Some((username, password)) = &self.credentials {
+            stream
+                .write_all(&authentication_request(username, password)?)
+                .await?;
+            let mut reply = [0; 2];
+            stream.read_exact(&mut reply).await?;
+            check_authentication_reply(reply)?;
+        }
+        return Ok(stream);
+    }
+
+    async fn send_command_async(
+        stream: &mut AsyncTcpStream,
+        command: u8,
+        dst_addr: SocketAddr,
+    ) -> io::Result<SocketAddr> {
+        stream
+            .write_all(&command_request(command, dst_addr))
+            .await?;
+
+        let mut reply = [0; 3];
+        stream.read_exact(&mut reply).await?;
+        check_command_reply(reply)?;
+
+        // the address is read raw and then parsed
+        let mut raw_address = vec![0; 1];
+        stream.read_exact(&mut raw_address).await?;
+        let address_length = match raw_address[0] {
+            ADDRESS_IPV4 => 4,
+            ADDRESS_IPV6 => 16,
+            ADDRESS_DOMAIN => {
+                let mut length = [0; 1];
+                stream.read_exact(&mut length).await?;
+                raw_address.push(length[0]);
+                length[0] as usize
+            }
+            _ => return Err(socks_error("unknown address type")),
+        };
+
+        let mut address_and_port = vec![0; address_length + 2];
+        stream.read_exact(&mut address_and_port).await?;
+        raw_address.extend_from_slice(&address_and_port);
+
+        return read_address(&mut raw_address.as_slice());
+    }
+}
+
+fn socks_error(reason: &str) -> io::Error {
+    return io::Error::other(format!("SOCKS5 error: {}", reason));
+}
+
+fn any_addr() -> SocketAddr {
+    return SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0);
+}
+
+fn authentication_request(
+    username: &str,
+    password: &str,
+) -> io::Result<Vec<u8>> {
+    if username.len() > 255 || password.len() > 255 {
+        return Err(socks_error("username or password too long"));
+    }
+
+    let mut request = vec![AUTH_VERSION, username.len() as u8];
+    request.extend_from_slice(username.as_bytes());
+    request.push(password.len() as u8);
+    request.extend_from_slice(password.as_bytes());
+    return Ok(request);
+}
+
+fn check_authentication_reply(reply: [u8; 2]) -> io::Result<()> {
+    if reply[1] != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "SOCKS5 authentication failed",
+        ));
+    }
+    return Ok(());
+}
+
+fn command_request(command: u8, dst_addr: SocketAddr) -> Vec<u8> {
+    let mut request = vec![SOCKS_VERSION, command, 0];
+    request.extend_from_slice(&encode_address(dst_addr));
+    return request;
+}
+
+fn check_command_reply(reply: [u8; 3]) -> io::Result<()> {
+    if reply[0] != SOCKS_VERSION {
+        return Err(socks_error("invalid version in reply"));
+    }
+    if reply[1] != REPLY_SUCCEEDED {
+        return Err(socks_error(&format!(
+            "request failed with code {}",
+            reply[1]
+        )));
+    }
+    return Ok(());
+}
+
+fn encode_address(address: SocketAddr) -> Vec<u8> {
+    let mut raw = match address.ip() {
+        IpAddr::V4(ip) => {
+            let mut raw = vec![ADDRESS_IPV4];
+            raw.extend_from_slice(&ip.octets());
+            raw
+        }
+        IpAddr::V6(ip) => {
+            let mut raw = vec![ADDRESS_IPV6];
+            raw.extend_from_slice(&ip.octets());
+            raw
+        }
+    };
+    raw.extend_from_slice(&address.port().to_be_bytes());
+    return raw;
+}
+
+fn read_address<R: Read>(reader: &mut R) -> io::Result<SocketAddr> {
+    let mut address_type = [0; 1];
+    reader.read_exact(&mut address_type)?;
+
+    let ip = match address_type[0] {
+        ADDRESS_IPV4 => {
+            let mut octets = [0; 4];
+            reader.read_exact(&mut octets)?;
+            IpAddr::V4(Ipv4Addr::from(octets))
+        }
+        ADDRESS_IPV6 => {
+            let mut octets = [0; 16];
+            reader.read_exact(&mut octets)?;
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+        ADDRESS_DOMAIN => {
+            // the bound name is not needed, so the IP of the proxy is used
+            let mut length = [0; 1];
+            reader.read_exact(&mut length)?;
+            let mut name = vec![0; length[0] as usize];
+            reader.read_exact(&mut name)?;
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+        }
+        _ => return Err(socks_error("unknown address type")),
+    };
+
+    let mut port = [0; 2];
+    reader.read_exact(&mut port)?;
+    return Ok(SocketAddr::new(ip, u16::from_be_bytes(port)));
+}
+
+/// Adds the SOCKS5 UDP header to a datagram sent to `dst_addr`.
+pub(crate) fn encapsulate_udp_datagram(
+    dst_addr: SocketAddr,
+    data: &[u8],
+) -> Vec<u8> {
+    // reserved (2 bytes) and fragment number
+    let mut datagram = vec![0, 0, 0];
+    datagram.extend_from_slice(&encode_address(dst_addr));
+    datagram.extend_from_slice(data);
+    return datagram;
+}
+
+/// Removes the SOCKS5 UDP header from a datagram relayed by the proxy.
+pub(crate) fn decapsulate_udp_datagram(datagram: &[u8]) -> io::Result<&[u8]> {
+    if datagram.len() < 3 {
+        return Err(socks_error("datagram too short"));
+    }
+    if datagram[2] != 0 {
+        return Err(socks_error("fragmented datagrams are not supported"));
+    }
+
+    let mut reader = &datagram[3..];
+    read_address(&mut reader)?;
+    return Ok(reader);
+}
+
+#[cfg(test)]
+pub(crate) mod test_proxy {
+    //! Minimal SOCKS5 server used to test the transporters.
+
+    use super::*;
+    use std::net::{TcpListener, TcpStream, UdpSocket};
+    use std::thread;
+
+    /// Serves one CONNECT or UDP ASSOCIATE request. If credentials are
+    /// given, the client must authenticate with them.
+    pub fn spawn_socks5_proxy(
+        credentials: Option<(&'static str, &'static str)>,
+    ) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+
+            let mut header = [0; 2];
+            stream.read_exact(&mut header).unwrap();
+            let mut methods = vec![0; header[1] as usize];
+            stream.read_exact(&mut methods).unwrap();
+
+            match credentials {
+                Some((username, password)) => {
+                    stream
+                        .write_all(&[SOCKS_VERSION, METHOD_USERNAME_PASSWORD])
+                        .unwrap();
+                    let mut version_and_length = [0; 2];
+                    stream.read_exact(&mut version_and_length).unwrap();
+                    let mut user = vec![0; version_and_length[1] as usize];
+                    stream.read_exact(&mut user).unwrap();
+                    let mut length = [0; 1];
+                    stream.read_exact(&mut length).unwrap();
+                    let mut pass = vec![0; length[0] as usize];
+                    stream.read_exact(&mut pass).unwrap();
+
+                    let valid = user == username.as_bytes()
+                        && pass == password.as_bytes();
+                    stream
+                        .write_all(&[AUTH_VERSION, if valid { 0 } else { 1 }])
+                        .unwrap();
+                    if !valid {
+                        return;
+                    }
+                }
+                None => {
+                    stream.write_all(&[SOCKS_VERSION, METHOD_NO_AUTH]).unwrap();
+                }
+            }
+
+            let mut request = [0; 3];
+            stream.read_exact(&mut request).unwrap();
+            let dst_addr = read_address(&mut stream).unwrap();
+
+            if request[1] == COMMAND_CONNECT {
+                let mut kdc_stream = TcpStream::connect(dst_addr).unwrap();
+                let mut reply = vec![SOCKS_VERSION, REPLY_SUCCEEDED, 0];
+                reply.extend_from_slice(&encode_address(
+                    kdc_stream.local_addr().unwrap(),
+                ));
+                stream.write_all(&reply).unwrap();
+
+                let mut client_stream = stream.try_clone().unwrap();
+                let mut kdc_reader = kdc_stream.try_clone().unwrap();
+                thread::spawn(move || {
+                    let _ = io::copy(&mut kdc_reader, &mut client_stream);
+                });
+                let _ = io::copy(&mut stream, &mut kdc_stream);
+            } else {
+                let relay = UdpSocket::bind("127.0.0.1:0").unwrap();
+                let mut reply = vec![SOCKS_VERSION, REPLY_SUCCEEDED, 0];
+                // unspecified address, so the client uses the proxy IP
+                reply.extend_from_slice(&encode_address(SocketAddr::new(
+                    IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                    relay.local_addr().unwrap().port(),
+                )));
+                stream.write_all(&reply).unwrap();
+
+                let mut buffer = [0; 2048];
+                let (size, client_addr) = relay.recv_from(&mut buffer).unwrap();
+                let mut datagram = &buffer[3..size];
+                let kdc_addr = read_address(&mut datagram).unwrap();
+                relay.send_to(datagram, kdc_addr).unwrap();
+
+                let (size, kdc_addr) = relay.recv_from(&mut buffer).unwrap();
+                let reply = encapsulate_udp_datagram(kdc_addr, &buffer[..size]);
+                relay.send_to(&reply, client_addr).unwrap();
+            }
+        });
+
+        return address;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn encapsulate_and_decapsulate_datagrams() {
+        let address: SocketAddr = "10.0.0.1:88".parse().unwrap();
+        let datagram = encapsulate_udp_datagram(address, &[1, 2, 3]);
+
+        assert_eq!(
+            vec![0, 0, 0, ADDRESS_IPV4, 10, 0, 0, 1, 0, 88, 1, 2, 3],
+            datagram
+        );
+        assert_eq!(&[1, 2, 3], decapsulate_udp_datagram(&datagram).unwrap());
+    }
+
+    #[test]
+    fn error_decapsulating_fragmented_datagram() {
+        assert!(decapsulate_udp_datagram(&[
+            0,
+            0,
+            1,
+            ADDRESS_IPV4,
+            10,
+            0,
+            0,
+            1,
+            0,
+            88,
+            1
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn keep_deadline_on_relayed_stream() {
+        let kdc = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let proxy = Socks5Proxy::new(test_proxy::spawn_socks5_proxy(None));
+        let deadline = Instant::now() + Duration::from_millis(100);
+
+        let mut stream =
+            proxy.connect(kdc.local_addr().unwrap(), deadline).unwrap();
+
+        let error = stream.read_exact(&mut [0; 4]).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+        ));
+        assert!(Instant::now() >= deadline);
+    }
+}
@@ -2,42 +2,80 @@ use crate::{Error, Result};
 use std::io;
 use std::io::{Read, Write};
 use std::net::*;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+use super::socks5::Socks5Proxy;
 use super::transporter_trait::*;
+use super::{check_response_size, DEFAULT_MAX_RESPONSE_SIZE, DEFAULT_TIMEOUT};
 
 /// Send Kerberos messages over TCP
 #[derive(Debug)]
 pub struct TCPTransporter {
     dst_addr: SocketAddr,
+    proxy: Option<Socks5Proxy>,
+    max_response_size: usize,
+    timeout: Duration,
 }
 
 impl TCPTransporter {
     pub fn new(dst_addr: SocketAddr) -> Self {
-        return Self { dst_addr };
+        return Self {
+            dst_addr,
+            proxy: None,
+            max_response_size: DEFAULT_MAX_RESPONSE_SIZE,
+            timeout: DEFAULT_TIMEOUT,
+        };
+    }
+
+    /// Creates a transporter that connects through a SOCKS5 proxy.
+    pub fn with_socks5_proxy(dst_addr: SocketAddr, proxy: Socks5Proxy) -> Self {
+        return Self {
+            dst_addr,
+            proxy: Some(proxy),
+            max_response_size: DEFAULT_MAX_RESPONSE_SIZE,
+            timeout: DEFAULT_TIMEOUT,
+        };
+    }
+
+    /// Sets the maximum size of the responses accepted from the KDC.
+    pub fn set_max_response_size(&mut self, max_response_size: usize) {
+        self.max_response_size = max_response_size;
+    }
+
+    /// Sets the time to wait for the connection and for the response of
+    /// the KDC.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    fn connect(&self, deadline: Instant) -> io::Result<DeadlineStream> {
+        match &self.proxy {
+            Some(proxy) => return proxy.connect(self.dst_addr, deadline),
+            None => return DeadlineStream::connect(&self.dst_addr, deadline),
+        }
     }
 
     fn request_and_response_tcp(
         &self,
         raw_request: &[u8],
     ) -> io::Result<Vec<u8>> {
-        let mut tcp_stream =
-            TcpStream::connect_timeout(&self.dst_addr, Duration::new(5, 0))?;
+        let mut tcp_stream = self.connect(Instant::now() + self.timeout)?;
 
         let raw_sized_request = Self::set_size_header_to_request(raw_request);
         tcp_stream.write_all(&raw_sized_request)?;
 
         let mut len_data_bytes = [0_u8; 4];
         tcp_stream.read_exact(&mut len_data_bytes)?;
-        let data_length = u32::from_be_bytes(len_data_bytes);
+        let data_length = u32::from_be_bytes(len_data_bytes) as usize;
+        check_response_size(data_length, self.max_response_size)?;
 
-        let mut raw_response: Vec<u8> = vec![0; data_length as usize];
+        let mut raw_response: Vec<u8> = vec![0; data_length];
         tcp_stream.read_exact(&mut raw_response)?;
 
         return Ok(raw_response);
     }
 
-    fn set_size_header_to_request(raw_request: &[u8]) -> Vec<u8> {
+    pub(crate) fn set_size_header_to_request(raw_request: &[u8]) -> Vec<u8> {
         let request_length = raw_request.len() as u32;
         let mut raw_sized_request: Vec<u8> =
             request_length.to_be_bytes().to_vec();
@@ -47,17 +85,66 @@ impl TCPTransporter {
     }
 }
 
+/// TCP stream whose reads and writes fail once the deadline is reached,
+/// so the timeout limits the whole exchange instead of each read.
+#[derive(Debug)]
+pub(crate) struct DeadlineStream {
+    stream: TcpStream,
+    deadline: Instant,
+}
+
+impl DeadlineStream {
+    pub fn connect(addr: &SocketAddr, deadline: Instant) -> io::Result<Self> {
+        let stream =
+            TcpStream::connect_timeout(addr, remaining_time(deadline)?)?;
+        return Ok(Self { stream, deadline });
+    }
+}
+
+impl Read for DeadlineStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stream
+            .set_read_timeout(Some(remaining_time(self.deadline)?))?;
+        return self.stream.read(buf);
+    }
+}
+
+impl Write for DeadlineStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream
+            .set_write_timeout(Some(remaining_time(self.deadline)?))?;
+        return self.stream.write(buf);
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return self.stream.flush();
+    }
+}
+
+/// Time left until the deadline, or a `TimedOut` error if it was reached.
+pub(crate) fn remaining_time(deadline: Instant) -> io::Result<Duration> {
+    let now = Instant::now();
+    if now >= deadline {
+        return Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "timed out waiting for the response",
+        ));
+    }
+    return Ok(deadline - now);
+}
+
 impl Transporter for TCPTransporter {
     fn request_and_response(&self, raw_request: &[u8]) -> Result<Vec<u8>> {
         let raw_response = self
             .request_and_response_tcp(raw_request)
-            .map_err(|_| Error::NetworkError)?;
+            .map_err(|error| Error::NetworkError(error.into()))?;
         return Ok(raw_response);
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::super::socks5::test_proxy::spawn_socks5_proxy;
     use super::*;
 
     #[should_panic(expected = "NetworkError")]
@@ -69,4 +156,116 @@ mod tests {
         ));
         requester.request_and_response(&vec![]).unwrap();
     }
+
+    #[test]
+    fn test_request_through_socks5_proxy() {
+        let kdc = TcpListener::bind("127.0.0.1:0").unwrap();
+        let kdc_addr = kdc.local_addr().unwrap();
+        let kdc_thread = std::thread::spawn(move || {
+            let (mut stream, _) = kdc.accept().unwrap();
+            let mut request = [0; 6];
+            stream.read_exact(&mut request).unwrap();
+            assert_eq!([0, 0, 0, 2, 1, 2], request);
+            stream.write_all(&[0, 0, 0, 1, 3]).unwrap();
+        });
+
+        let proxy_addr = spawn_socks5_proxy(Some(("mickey", "Minnie1234")));
+        let transporter = TCPTransporter::with_socks5_proxy(
+            kdc_addr,
+            Socks5Proxy::new(proxy_addr).with_credentials(
+                "mickey".to_string(),
+                "Minnie1234".to_string(),
+            ),
+        );
+
+        assert_eq!(vec![3], transporter.request_and_response(&[1, 2]).unwrap());
+        kdc_thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_response_too_large() {
+        let kdc = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut transporter = TCPTransporter::new(kdc.local_addr().unwrap());
+        transporter.set_max_response_size(1024);
+        let kdc_thread = std::thread::spawn(move || {
+            let (mut stream, _) = kdc.accept().unwrap();
+            let mut request = [0; 6];
+            stream.read_exact(&mut request).unwrap();
+            stream.write_all(&[0xff, 0xff, 0xff, 0xff]).unwrap();
+        });
+
+        match transporter.request_and_response(&[1, 2]) {
+            Err(Error::NetworkError(error)) => {
+                assert_eq!(io::ErrorKind::InvalidData, error.kind());
+            }
+            _ => unreachable!(),
+        }
+        kdc_thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_socks5_authentication_error() {
+        let proxy_addr = spawn_socks5_proxy(Some(("mickey", "Minnie1234")));
+        let transporter = TCPTransporter::with_socks5_proxy(
+            "127.0.0.1:88".parse().unwrap(),
+            Socks5Proxy::new(proxy_addr)
+                .with_credentials("mickey".to_string(), "Pluto".to_string()),
+        );
+
+        match transporter.request_and_response(&[1, 2]) {
+            Err(Error::NetworkError(error)) => {
+                assert_eq!(io::ErrorKind::PermissionDenied, error.kind());
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_timeout_with_silent_kdc() {
+        let kdc = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut transporter = TCPTransporter::new(kdc.local_addr().unwrap());
+        transporter.set_timeout(Duration::from_millis(100));
+
+        match transporter.request_and_response(&[1, 2]) {
+            Err(Error::NetworkError(error)) => {
+                assert!(matches!(
+                    error.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ));
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_timeout_with_slow_kdc() {
+        let kdc = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut transporter = TCPTransporter::new(kdc.local_addr().unwrap());
+        transporter.set_timeout(Duration::from_millis(300));
+        let kdc_thread = std::thread::spawn(move || {
+            let (mut stream, _) = kdc.accept().unwrap();
+            let mut request = [0; 6];
+            stream.read_exact(&mut request).unwrap();
+            // every byte arrives before the timeout, but not the response
+            for byte in &[0, 0, 0, 10, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10] {
+                std::thread::sleep(Duration::from_millis(100));
+                if stream.write_all(&[*byte]).is_err() {
+                    return;
+                }
+            }
+        });
+
+        let start = Instant::now();
+        match transporter.request_and_response(&[1, 2]) {
+            Err(Error::NetworkError(error)) => {
+                assert!(matches!(
+                    error.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ));
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert!(start.elapsed() < Duration::from_millis(1000));
+        kdc_thread.join().unwrap();
+    }
 }
@@ -1,7 +1,52 @@
 use crate::error::*;
 
-/// Trait implemented by classes which deliver Kerberos messages
-pub trait Transporter {
-    /// Sends a message and retrieves the response
+/// Trait implemented by classes which deliver Kerberos messages.
+///
+/// It can be implemented in order to use a custom transport with the
+/// requesters, such as a pre-connected socket, a tunnel or a stub that
+/// replays recorded responses.
+///
+/// # Examples
+///
+/// ```
+/// use himmelblau_kerbeiros::*;
+/// use ascii::AsciiString;
+///
+/// struct ReplayTransporter {
+///     response: Vec<u8>,
+/// }
+///
+/// impl Transporter for ReplayTransporter {
+///     fn request_and_response(&self, _raw_request: &[u8]) -> Result<Vec<u8>> {
+///         return Ok(self.response.clone());
+///     }
+/// }
+///
+/// let realm = AsciiString::from_ascii("CONTOSO.COM").unwrap();
+/// let transporter = ReplayTransporter { response: vec![] };
+/// let tgt_requester =
+///     TgtRequester::with_transporter(realm, Box::new(transporter));
+/// ```
+pub trait Transporter: Send + Sync {
+    /// Sends a message and retrieves the response.
+    ///
+    /// The request and the response are the raw Kerberos messages, without
+    /// any framing of the transport protocol.
+    ///
+    /// # Errors
+    /// Should return [`Error::NetworkError`] with the underlying i/o error
+    /// if the message cannot be delivered or the response retrieved.
     fn request_and_response(&self, raw_request: &[u8]) -> Result<Vec<u8>>;
 }
+
+impl<T: Transporter + ?Sized> Transporter for Box<T> {
+    fn request_and_response(&self, raw_request: &[u8]) -> Result<Vec<u8>> {
+        return (**self).request_and_response(raw_request);
+    }
+}
+
+impl<T: Transporter + ?Sized> Transporter for std::sync::Arc<T> {
+    fn request_and_response(&self, raw_request: &[u8]) -> Result<Vec<u8>> {
+        return (**self).request_and_response(raw_request);
+    }
+}
@@ -1,63 +1,174 @@
 use crate::{Error, Result};
 use std::io;
 use std::net::*;
+use std::time::{Duration, Instant};
 
+use super::socks5::*;
+use super::tcp_transporter::{remaining_time, TCPTransporter};
 use super::transporter_trait::*;
+use super::{
+    check_response_size, exceeds_udp_preference_limit, is_response_too_big,
+    DEFAULT_MAX_RESPONSE_SIZE, DEFAULT_TIMEOUT,
+};
+
+/// Maximum size of an UDP datagram
+pub(crate) const MAX_DATAGRAM_SIZE: usize = 65535;
 
 /// Send Kerberos messages over UDP
 #[derive(Debug)]
 pub struct UDPTransporter {
     dst_addr: SocketAddr,
+    proxy: Option<Socks5Proxy>,
+    max_response_size: usize,
+    timeout: Duration,
+    udp_preference_limit: Option<usize>,
 }
 
 impl UDPTransporter {
     pub fn new(dst_addr: SocketAddr) -> Self {
-        return Self { dst_addr };
+        return Self {
+            dst_addr,
+            proxy: None,
+            max_response_size: DEFAULT_MAX_RESPONSE_SIZE,
+            timeout: DEFAULT_TIMEOUT,
+            udp_preference_limit: None,
+        };
     }
 
-    fn request_and_response_udp(
+    /// Creates a transporter that sends the datagrams through a SOCKS5
+    /// proxy, by using the UDP ASSOCIATE command.
+    pub fn with_socks5_proxy(dst_addr: SocketAddr, proxy: Socks5Proxy) -> Self {
+        return Self {
+            dst_addr,
+            proxy: Some(proxy),
+            max_response_size: DEFAULT_MAX_RESPONSE_SIZE,
+            timeout: DEFAULT_TIMEOUT,
+            udp_preference_limit: None,
+        };
+    }
+
+    /// Sets the maximum size of the responses accepted from the KDC.
+    pub fn set_max_response_size(&mut self, max_response_size: usize) {
+        self.max_response_size = max_response_size;
+    }
+
+    /// Sets the time to wait for the connection and for the response of
+    /// the KDC.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Sets the size limit of the messages sent over UDP. Bigger messages,
+    /// and the ones whose response is too big for UDP, are sent over TCP
+    /// to the same address. All the messages are sent over UDP by default.
+    pub fn set_udp_preference_limit(&mut self, limit: Option<usize>) {
+        self.udp_preference_limit = limit;
+    }
+
+    fn tcp_transporter(&self) -> TCPTransporter {
+        let mut transporter = match &self.proxy {
+            Some(proxy) => {
+                TCPTransporter::with_socks5_proxy(self.dst_addr, proxy.clone())
+            }
+            None => TCPTransporter::new(self.dst_addr),
+        };
+        transporter.set_max_response_size(self.max_response_size);
+        transporter.set_timeout(self.timeout);
+        return transporter;
+    }
+
+    fn request_and_response_socks5(
         &self,
+        proxy: &Socks5Proxy,
         raw_request: &[u8],
     ) -> io::Result<Vec<u8>> {
-        let udp_socket = UdpSocket::bind("0.0.0.0:0")?;
-        udp_socket.connect(self.dst_addr)?;
+        let deadline = Instant::now() + self.timeout;
+        // the association lasts while the control connection is open
+        let (_control_stream, relay_addr) = proxy.udp_associate(deadline)?;
 
-        udp_socket.send(raw_request)?;
+        let udp_socket = UdpSocket::bind(bind_address(relay_addr))?;
+        udp_socket.connect(relay_addr)?;
 
-        let data_length = self.calculate_response_size(&udp_socket)?;
+        udp_socket
+            .send(&encapsulate_udp_datagram(self.dst_addr, raw_request))?;
 
-        let mut raw_response = vec![0; data_length];
-        udp_socket.recv(&mut raw_response)?;
+        udp_socket.set_read_timeout(Some(remaining_time(deadline)?))?;
+        let mut datagram = vec![0; MAX_DATAGRAM_SIZE];
+        let data_length = udp_socket.recv(&mut datagram)?;
+        let raw_response =
+            decapsulate_udp_datagram(&datagram[..data_length])?.to_vec();
+        check_response_size(raw_response.len(), self.max_response_size)?;
 
         return Ok(raw_response);
     }
 
-    fn calculate_response_size(
+    fn request_and_response_udp(
         &self,
-        udp_socket: &UdpSocket,
-    ) -> io::Result<usize> {
-        let mut raw_response = vec![0; 2048];
-        let mut data_length = udp_socket.peek(&mut raw_response)?;
-        while data_length == raw_response.len() {
-            raw_response.append(&mut raw_response.clone());
-            data_length = udp_socket.peek(&mut raw_response)?;
+        raw_request: &[u8],
+    ) -> io::Result<Vec<u8>> {
+        if let Some(proxy) = &self.proxy {
+            return self.request_and_response_socks5(proxy, raw_request);
         }
-        return Ok(data_length);
+
+        let deadline = Instant::now() + self.timeout;
+        let udp_socket = UdpSocket::bind(bind_address(self.dst_addr))?;
+        udp_socket.connect(self.dst_addr)?;
+
+        udp_socket.send(raw_request)?;
+
+        udp_socket.set_read_timeout(Some(remaining_time(deadline)?))?;
+        let mut raw_response =
+            vec![0; response_buffer_size(self.max_response_size)];
+        let data_length = udp_socket.recv(&mut raw_response)?;
+        check_response_size(data_length, self.max_response_size)?;
+        raw_response.truncate(data_length);
+
+        return Ok(raw_response);
     }
 }
 
+/// Unspecified local address, of the same family as `dst_addr`, to bind
+/// the socket used to reach it.
+pub(crate) fn bind_address(dst_addr: SocketAddr) -> SocketAddr {
+    if dst_addr.is_ipv4() {
+        return (Ipv4Addr::UNSPECIFIED, 0).into();
+    }
+    return (Ipv6Addr::UNSPECIFIED, 0).into();
+}
+
+/// Size of the buffer used to receive a datagram, one byte bigger than
+/// the maximum response size (when it fits in a datagram) in order to
+/// detect the responses that exceed it.
+pub(crate) fn response_buffer_size(max_response_size: usize) -> usize {
+    return max_response_size.saturating_add(1).min(MAX_DATAGRAM_SIZE);
+}
+
 impl Transporter for UDPTransporter {
     fn request_and_response(&self, raw_request: &[u8]) -> Result<Vec<u8>> {
+        if exceeds_udp_preference_limit(raw_request, self.udp_preference_limit)
+        {
+            return self.tcp_transporter().request_and_response(raw_request);
+        }
+
         let raw_response = self
             .request_and_response_udp(raw_request)
-            .map_err(|_| Error::NetworkError)?;
+            .map_err(|error| Error::NetworkError(error.into()))?;
+        if self.udp_preference_limit.is_some()
+            && is_response_too_big(&raw_response)
+        {
+            return self.tcp_transporter().request_and_response(raw_request);
+        }
         return Ok(raw_response);
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::super::socks5::test_proxy::spawn_socks5_proxy;
     use super::*;
+    use himmelblau_kerberos_asn1::{Asn1Object, KrbError};
+    use himmelblau_kerberos_constants::error_codes::KRB_ERR_RESPONSE_TOO_BIG;
+    use std::io::{Read, Write};
 
     #[should_panic(expected = "NetworkError")]
     #[test]
@@ -68,4 +179,134 @@ mod tests {
         ));
         requester.request_and_response(&vec![]).unwrap();
     }
+
+    #[test]
+    fn test_response_too_large() {
+        let kdc = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut transporter = UDPTransporter::new(kdc.local_addr().unwrap());
+        transporter.set_max_response_size(4);
+        let kdc_thread = std::thread::spawn(move || {
+            let mut request = [0; 16];
+            let (_, client) = kdc.recv_from(&mut request).unwrap();
+            kdc.send_to(&[1, 2, 3, 4, 5], client).unwrap();
+        });
+
+        match transporter.request_and_response(&[1, 2]) {
+            Err(Error::NetworkError(error)) => {
+                assert_eq!(io::ErrorKind::InvalidData, error.kind());
+            }
+            _ => unreachable!(),
+        }
+        kdc_thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_request_through_socks5_proxy() {
+        let kdc = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let kdc_addr = kdc.local_addr().unwrap();
+        let kdc_thread = std::thread::spawn(move || {
+            let mut request = [0; 16];
+            let (size, client) = kdc.recv_from(&mut request).unwrap();
+            assert_eq!(&[1, 2], &request[..size]);
+            kdc.send_to(&[3], client).unwrap();
+        });
+
+        let proxy_addr = spawn_socks5_proxy(None);
+        let transporter = UDPTransporter::with_socks5_proxy(
+            kdc_addr,
+            Socks5Proxy::new(proxy_addr),
+        );
+
+        assert_eq!(vec![3], transporter.request_and_response(&[1, 2]).unwrap());
+        kdc_thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_timeout_with_silent_kdc() {
+        let kdc = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut transporter = UDPTransporter::new(kdc.local_addr().unwrap());
+        transporter.set_timeout(Duration::from_millis(100));
+
+        match transporter.request_and_response(&[1, 2]) {
+            Err(Error::NetworkError(error)) => {
+                assert!(matches!(
+                    error.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ));
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_request_to_ipv6_kdc() {
+        let kdc = match UdpSocket::bind("[::1]:0") {
+            Ok(kdc) => kdc,
+            // IPv6 is not available
+            Err(_) => return,
+        };
+        let transporter = UDPTransporter::new(kdc.local_addr().unwrap());
+        let kdc_thread = std::thread::spawn(move || {
+            let mut request = [0; 16];
+            let (_, client) = kdc.recv_from(&mut request).unwrap();
+            kdc.send_to(&[3], client).unwrap();
+        });
+
+        assert_eq!(vec![3], transporter.request_and_response(&[1, 2]).unwrap());
+        kdc_thread.join().unwrap();
+    }
+
+    /// Binds an UDP socket and a TCP listener to the same local port.
+    fn bind_udp_and_tcp() -> (UdpSocket, TcpListener) {
+        loop {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            if let Ok(socket) = UdpSocket::bind(listener.local_addr().unwrap())
+            {
+                return (socket, listener);
+            }
+        }
+    }
+
+    fn respond_over_tcp(listener: TcpListener, response: &[u8]) {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut size = [0; 4];
+        stream.read_exact(&mut size).unwrap();
+        let mut request = vec![0; u32::from_be_bytes(size) as usize];
+        stream.read_exact(&mut request).unwrap();
+        stream
+            .write_all(&TCPTransporter::set_size_header_to_request(response))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_send_message_bigger_than_udp_preference_limit_over_tcp() {
+        let (kdc, listener) = bind_udp_and_tcp();
+        let mut transporter = UDPTransporter::new(kdc.local_addr().unwrap());
+        transporter.set_udp_preference_limit(Some(1));
+        let kdc_thread =
+            std::thread::spawn(move || respond_over_tcp(listener, &[3]));
+
+        assert_eq!(vec![3], transporter.request_and_response(&[1, 2]).unwrap());
+        kdc_thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_retry_over_tcp_if_response_is_too_big() {
+        let (kdc, listener) = bind_udp_and_tcp();
+        let mut transporter = UDPTransporter::new(kdc.local_addr().unwrap());
+        transporter.set_udp_preference_limit(Some(1465));
+        let kdc_thread = std::thread::spawn(move || {
+            let mut request = [0; 16];
+            let (_, client) = kdc.recv_from(&mut request).unwrap();
+            let krb_error = KrbError {
+                error_code: KRB_ERR_RESPONSE_TOO_BIG,
+                ..Default::default()
+            };
+            kdc.send_to(&krb_error.build(), client).unwrap();
+            respond_over_tcp(listener, &[3]);
+        });
+
+        assert_eq!(vec![3], transporter.request_and_response(&[1, 2]).unwrap());
+        kdc_thread.join().unwrap();
+    }
 }
@@ -1,4 +1,6 @@
 use crate::{EncryptedData, Int32};
+use himmelblau_kerberos_constants::message_types::KRB_AP_REP;
+use himmelblau_kerberos_constants::protocol_version::PVNO;
 use himmelblau_red_asn1::Asn1Object;
 use himmelblau_red_asn1_derive::Sequence;
 
@@ -11,7 +13,7 @@ use himmelblau_red_asn1_derive::Sequence;
 ///        enc-part        [2] EncryptedData -- EncAPRepPart
 /// }
 /// ```
-#[derive(Sequence, Default, Debug, Clone, PartialEq)]
+#[derive(Sequence, Debug, Clone, PartialEq)]
 #[seq(application_tag = 15)]
 pub struct ApRep {
     #[seq_field(context_tag = 0)]
@@ -21,3 +23,13 @@ pub struct ApRep {
     #[seq_field(context_tag = 2)]
     pub enc_part: EncryptedData,
 }
+
+impl Default for ApRep {
+    fn default() -> Self {
+        Self {
+            pvno: PVNO,
+            msg_type: KRB_AP_REP,
+            enc_part: EncryptedData::default(),
+        }
+    }
+}
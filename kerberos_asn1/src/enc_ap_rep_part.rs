@@ -20,7 +20,7 @@ pub struct EncApRepPart {
     #[seq_field(context_tag = 1)]
     pub cusec: Microseconds,
     #[seq_field(context_tag = 2)]
-    pub subkey: EncryptionKey,
+    pub subkey: Option<EncryptionKey>,
     #[seq_field(context_tag = 3)]
-    pub seq_number: UInt32,
+    pub seq_number: Option<UInt32>,
 }
@@ -0,0 +1,63 @@
+use crate::{Int32, Realm};
+use himmelblau_red_asn1::{Asn1Object, OctetString};
+use himmelblau_red_asn1_derive::Sequence;
+
+/// (*KDC-PROXY-MESSAGE*) Message exchanged with a Kerberos KDC proxy.
+/// The `kerb_message` includes the 4 bytes length prefix used in TCP.
+/// Defined in MS-KKDCP, section 2.2.2.
+/// ```asn1
+/// KDC-PROXY-MESSAGE ::= SEQUENCE {
+///     kerb-message   [0] OCTET STRING,
+///     target-domain  [1] KERB-REALM OPTIONAL,
+///     dclocator-hint [2] INTEGER OPTIONAL
+/// }
+/// ```
+#[derive(Sequence, Default, Debug, Clone, PartialEq)]
+pub struct KdcProxyMessage {
+    #[seq_field(context_tag = 0)]
+    pub kerb_message: OctetString,
+    #[seq_field(context_tag = 1)]
+    pub target_domain: Option<Realm>,
+    #[seq_field(context_tag = 2)]
+    pub dclocator_hint: Option<Int32>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn build_kdc_proxy_message() {
+        let message = KdcProxyMessage {
+            kerb_message: vec![0x00, 0x00, 0x00, 0x02, 0x6a, 0x00],
+            target_domain: Some(Realm::from("KINGDOM.HEARTS")),
+            dclocator_hint: None,
+        };
+
+        assert_eq!(
+            vec![
+                0x30, 0x1c, 0xa0, 0x08, 0x04, 0x06, 0x00, 0x00, 0x00, 0x02,
+                0x6a, 0x00, 0xa1, 0x10, 0x1b, 0x0e, 0x4b, 0x49, 0x4e, 0x47,
+                0x44, 0x4f, 0x4d, 0x2e, 0x48, 0x45, 0x41, 0x52, 0x54, 0x53,
+            ],
+            message.build()
+        );
+    }
+
+    #[test]
+    fn parse_kdc_proxy_message() {
+        let raw = [
+            0x30, 0x0f, 0xa0, 0x08, 0x04, 0x06, 0x00, 0x00, 0x00, 0x02, 0x6b,
+            0x00, 0xa2, 0x03, 0x02, 0x01, 0x01,
+        ];
+
+        assert_eq!(
+            KdcProxyMessage {
+                kerb_message: vec![0x00, 0x00, 0x00, 0x02, 0x6b, 0x00],
+                target_domain: None,
+                dclocator_hint: Some(1),
+            },
+            KdcProxyMessage::parse(&raw).unwrap().1
+        );
+    }
+}
@@ -25,6 +25,7 @@
 //! - [RFC 4120, The Kerberos Network Authentication Service (V5)](https://tools.ietf.org/html/rfc4120)
 //! - [RFC 6806, Kerberos Principal Name Canonicalization and Cross-Realm Referrals](https://tools.ietf.org/html/rfc6806)
 //! - [MS-KILE](https://docs.microsoft.com/en-us/openspecs/windows_protocols/MS-KILE/2a32282e-dd48-4ad9-a542-609804b02cc9)
+//! - [MS-KKDCP](https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-kkdcp/5bcebb8d-b747-4ee5-9453-428aec1c5c38)
 //! - [MS-SFU](https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-sfu/3bff5864-8135-400e-bdd9-33b552051d94)
 //!
 
@@ -73,6 +74,9 @@ pub use kerb_ad_restriction_entry::KerbAdRestrictionEntry;
 mod kerb_error_data;
 pub use kerb_error_data::KerbErrorData;
 
+mod kdc_proxy_message;
+pub use kdc_proxy_message::KdcProxyMessage;
+
 mod kerb_key_list_rep;
 pub use kerb_key_list_rep::KerbKeyListRep;
 
@@ -121,6 +125,9 @@ pub use uint32::UInt32;
 mod kerberos_string;
 pub use kerberos_string::KerberosString;
 
+mod utf8_string;
+pub use utf8_string::Utf8String;
+
 mod microseconds;
 pub use microseconds::{Microseconds, MAX_MICROSECONDS, MIN_MICROSECONDS};
 
@@ -141,10 +148,16 @@ pub use host_address::{padd_netbios_string, HostAddress, HostAddresses};
 
 mod pa_data;
 pub use pa_data::{
-    AdAndOr, AdIfRelevant, AdKdcIssued, AdMandatoryForKdc, EtypeInfo,
-    EtypeInfo2, EtypeInfo2Entry, EtypeInfoEntry, KerbPaPacRequest, MethodData,
-    PaData, PaEncTimestamp, PaEncTsEnc, PaForUser, PaPacOptions, PaS4uX509User,
-    PaSupportedEnctypes, S4uUserId,
+    AdAndOr, AdAuthenticationIndicator, AdCammac, AdIfRelevant, AdKdcIssued,
+    AdMandatoryForKdc, EtypeInfo, EtypeInfo2, EtypeInfo2Entry, EtypeInfoEntry,
+    KerbPaPacRequest, MethodData, PaData, PaEncTimestamp, PaEncTsEnc,
+    PaForUser, PaPacOptions, PaS4uX509User, PaSupportedEnctypes, S4uUserId,
+    VerifierMac,
+};
+
+mod spnego;
+pub use spnego::{
+    MechType, MechTypeList, NegTokenInit, NegTokenResp, NegotiationToken,
 };
 
 mod encrypted_data;
@@ -178,6 +191,8 @@ mod krb_error;
 pub use krb_error::KrbError;
 
 pub use himmelblau_red_asn1::Asn1Object;
+pub use himmelblau_red_asn1::Enumerated;
 pub use himmelblau_red_asn1::Error;
+pub use himmelblau_red_asn1::Oid;
 pub use himmelblau_red_asn1::Result;
 pub use himmelblau_red_asn1::TagClass;
@@ -0,0 +1,9 @@
+use crate::Utf8String;
+
+/// (*AD-AUTHENTICATION-INDICATOR*) Type of *AuthorizationData*, with the
+/// indicators of how the client was authenticated, such as "otp" or
+/// "pkinit". Defined in RFC8129, section 4.
+/// ```asn1
+/// AD-AUTHENTICATION-INDICATOR ::= SEQUENCE OF UTF8String
+/// ```
+pub type AdAuthenticationIndicator = Vec<Utf8String>;
@@ -0,0 +1,61 @@
+use crate::{AuthorizationData, VerifierMac};
+use himmelblau_red_asn1::Asn1Object;
+use himmelblau_red_asn1_derive::Sequence;
+
+/// (*AD-CAMMAC*) Type of *AuthorizationData*.
+/// Defined in RFC7751, section 2.
+///
+/// The only alternative of *Verifier* is *Verifier-MAC*, which is
+/// encoded as it, so `other_verifiers` is a sequence of *Verifier-MAC*.
+/// ```asn1
+/// AD-CAMMAC                   ::= SEQUENCE {
+///       elements              [0] AuthorizationData,
+///       kdc-verifier          [1] Verifier-MAC OPTIONAL,
+///       svc-verifier          [2] Verifier-MAC OPTIONAL,
+///       other-verifiers       [3] SEQUENCE (SIZE (1..MAX))
+///                                 OF Verifier OPTIONAL
+/// }
+///
+/// Verifier             ::= CHOICE {
+///       mac            Verifier-MAC,
+///       ...
+/// }
+/// ```
+#[derive(Sequence, Default, Debug, Clone, PartialEq)]
+pub struct AdCammac {
+    #[seq_field(context_tag = 0)]
+    pub elements: AuthorizationData,
+    #[seq_field(context_tag = 1)]
+    pub kdc_verifier: Option<VerifierMac>,
+    #[seq_field(context_tag = 2)]
+    pub svc_verifier: Option<VerifierMac>,
+    #[seq_field(context_tag = 3)]
+    pub other_verifiers: Option<Vec<VerifierMac>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AuthorizationDataEntry, Checksum};
+    use himmelblau_kerberos_constants::checksum_types::HMAC_SHA1_96_AES256;
+
+    #[test]
+    fn test_build_and_parse_ad_cammac() {
+        let ad_cammac = AdCammac {
+            elements: vec![AuthorizationDataEntry {
+                ad_type: 97,
+                ad_data: vec![0x30, 0x05, 0x0c, 0x03, 0x6f, 0x74, 0x70],
+            }],
+            svc_verifier: Some(VerifierMac {
+                mac: Checksum {
+                    cksumtype: HMAC_SHA1_96_AES256,
+                    checksum: vec![1; 12],
+                },
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(ad_cammac, AdCammac::parse(&ad_cammac.build()).unwrap().1);
+    }
+}
@@ -1,6 +1,12 @@
 mod ad_and_or;
 pub use ad_and_or::AdAndOr;
 
+mod ad_authentication_indicator;
+pub use ad_authentication_indicator::AdAuthenticationIndicator;
+
+mod ad_cammac;
+pub use ad_cammac::AdCammac;
+
 mod ad_if_relevant;
 pub use ad_if_relevant::AdIfRelevant;
 
@@ -52,3 +58,6 @@ pub use pa_supported_enctypes::PaSupportedEnctypes;
 
 mod s4userid;
 pub use s4userid::S4uUserId;
+
+mod verifier_mac;
+pub use verifier_mac::VerifierMac;
@@ -0,0 +1,25 @@
+use crate::{Checksum, Int32, PrincipalName, UInt32};
+use himmelblau_red_asn1::Asn1Object;
+use himmelblau_red_asn1_derive::Sequence;
+
+/// (*Verifier-MAC*) Checksum of the elements of an *AD-CAMMAC*.
+/// Defined in RFC7751, section 2.
+/// ```asn1
+/// Verifier-MAC         ::= SEQUENCE {
+///        identifier     [0] PrincipalName OPTIONAL,
+///        kvno           [1] UInt32 OPTIONAL,
+///        enctype        [2] Int32 OPTIONAL,
+///        mac            [3] Checksum
+/// }
+/// ```
+#[derive(Sequence, Default, Debug, Clone, PartialEq)]
+pub struct VerifierMac {
+    #[seq_field(context_tag = 0)]
+    pub identifier: Option<PrincipalName>,
+    #[seq_field(context_tag = 1)]
+    pub kvno: Option<UInt32>,
+    #[seq_field(context_tag = 2)]
+    pub enctype: Option<Int32>,
+    #[seq_field(context_tag = 3)]
+    pub mac: Checksum,
+}
@@ -0,0 +1,8 @@
+mod neg_token_init;
+pub use neg_token_init::{MechType, MechTypeList, NegTokenInit};
+
+mod neg_token_resp;
+pub use neg_token_resp::NegTokenResp;
+
+mod negotiation_token;
+pub use negotiation_token::NegotiationToken;
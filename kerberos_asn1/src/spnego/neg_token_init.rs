@@ -0,0 +1,76 @@
+use himmelblau_red_asn1::{Asn1Object, BitString, OctetString, Oid};
+use himmelblau_red_asn1_derive::Sequence;
+
+/// (*MechType*) Object identifier of a GSS-API mechanism.
+/// Defined in RFC4178, section 4.1.
+pub type MechType = Oid;
+
+/// (*MechTypeList*) Mechanisms offered by the initiator, in decreasing
+/// preference order. Defined in RFC4178, section 4.1.
+pub type MechTypeList = Vec<MechType>;
+
+/// (*NegTokenInit*) First token of the SPNEGO negotiation, sent by the
+/// initiator. Defined in RFC4178, section 4.2.1.
+/// ```asn1
+/// NegTokenInit ::= SEQUENCE {
+///     mechTypes       [0] MechTypeList,
+///     reqFlags        [1] ContextFlags  OPTIONAL,
+///       -- inherited from RFC 2478 for backward compatibility,
+///       -- RECOMMENDED to be left out
+///     mechToken       [2] OCTET STRING  OPTIONAL,
+///     mechListMIC     [3] OCTET STRING  OPTIONAL,
+///     ...
+/// }
+/// ```
+#[derive(Sequence, Default, Debug, Clone, PartialEq)]
+pub struct NegTokenInit {
+    #[seq_field(context_tag = 0)]
+    pub mech_types: MechTypeList,
+    #[seq_field(context_tag = 1)]
+    pub req_flags: Option<BitString>,
+    #[seq_field(context_tag = 2)]
+    pub mech_token: Option<OctetString>,
+    #[seq_field(context_tag = 3)]
+    pub mech_list_mic: Option<OctetString>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn krb5_oid() -> Oid {
+        return Oid::new(1, 2, vec![840, 113554, 1, 2, 2]);
+    }
+
+    #[test]
+    fn test_build_neg_token_init() {
+        let neg_token_init = NegTokenInit {
+            mech_types: vec![krb5_oid()],
+            mech_token: Some(vec![0x60, 0x00]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            vec![
+                0x30, 0x15, 0xa0, 0x0d, 0x30, 0x0b, 0x06, 0x09, 0x2a, 0x86,
+                0x48, 0x86, 0xf7, 0x12, 0x01, 0x02, 0x02, 0xa2, 0x04, 0x04,
+                0x02, 0x60, 0x00
+            ],
+            neg_token_init.build()
+        );
+    }
+
+    #[test]
+    fn test_parse_neg_token_init() {
+        let raw = vec![
+            0x30, 0x15, 0xa0, 0x0d, 0x30, 0x0b, 0x06, 0x09, 0x2a, 0x86, 0x48,
+            0x86, 0xf7, 0x12, 0x01, 0x02, 0x02, 0xa2, 0x04, 0x04, 0x02, 0x60,
+            0x00,
+        ];
+
+        let (_, neg_token_init) = NegTokenInit::parse(&raw).unwrap();
+        assert_eq!(vec![krb5_oid()], neg_token_init.mech_types);
+        assert_eq!(Some(vec![0x60, 0x00]), neg_token_init.mech_token);
+        assert_eq!(None, neg_token_init.mech_list_mic);
+    }
+}
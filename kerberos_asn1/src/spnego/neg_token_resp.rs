@@ -0,0 +1,61 @@
+use super::MechType;
+use himmelblau_red_asn1::{Asn1Object, Enumerated, OctetString};
+use himmelblau_red_asn1_derive::Sequence;
+
+/// (*NegTokenResp*) Tokens of the SPNEGO negotiation after the first one.
+/// Defined in RFC4178, section 4.2.2.
+/// ```asn1
+/// NegTokenResp ::= SEQUENCE {
+///     negState       [0] ENUMERATED {
+///         accept-completed    (0),
+///         accept-incomplete   (1),
+///         reject              (2),
+///         request-mic         (3)
+///     }                                 OPTIONAL,
+///       -- REQUIRED in the first reply from the target
+///     supportedMech   [1] MechType      OPTIONAL,
+///       -- present only in the first reply from the target
+///     responseToken   [2] OCTET STRING  OPTIONAL,
+///     mechListMIC     [3] OCTET STRING  OPTIONAL,
+///     ...
+/// }
+/// ```
+#[derive(Sequence, Default, Debug, Clone, PartialEq)]
+pub struct NegTokenResp {
+    #[seq_field(context_tag = 0)]
+    pub neg_state: Option<Enumerated<u32>>,
+    #[seq_field(context_tag = 1)]
+    pub supported_mech: Option<MechType>,
+    #[seq_field(context_tag = 2)]
+    pub response_token: Option<OctetString>,
+    #[seq_field(context_tag = 3)]
+    pub mech_list_mic: Option<OctetString>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use himmelblau_kerberos_constants::neg_states::ACCEPT_COMPLETED;
+    use himmelblau_red_asn1::Oid;
+
+    #[test]
+    fn test_build_and_parse_neg_token_resp() {
+        let neg_token_resp = NegTokenResp {
+            neg_state: Some(Enumerated::new(ACCEPT_COMPLETED)),
+            supported_mech: Some(Oid::new(1, 2, vec![840, 48018, 1, 2, 2])),
+            response_token: Some(vec![0x60, 0x00]),
+            mech_list_mic: None,
+        };
+        let raw = neg_token_resp.build();
+
+        assert_eq!(
+            vec![
+                0x30, 0x18, 0xa0, 0x03, 0x0a, 0x01, 0x00, 0xa1, 0x0b, 0x06,
+                0x09, 0x2a, 0x86, 0x48, 0x82, 0xf7, 0x12, 0x01, 0x02, 0x02,
+                0xa2, 0x04, 0x04, 0x02, 0x60, 0x00
+            ],
+            raw
+        );
+        assert_eq!(neg_token_resp, NegTokenResp::parse(&raw).unwrap().1);
+    }
+}
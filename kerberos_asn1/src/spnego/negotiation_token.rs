@@ -0,0 +1,94 @@
+use super::{NegTokenInit, NegTokenResp};
+use himmelblau_red_asn1::{
+    build_length, parse_length, Asn1Object, Error, Result, Tag, TagClass,
+    TagType,
+};
+
+/// (*NegotiationToken*) Token of the SPNEGO negotiation.
+/// Defined in RFC4178, section 4.2.
+///
+/// The first token of the initiator is framed as an *InitialContextToken*
+/// of RFC2743, while the rest of tokens are sent as they are.
+/// ```asn1
+/// NegotiationToken ::= CHOICE {
+///     negTokenInit    [0] NegTokenInit,
+///     negTokenResp    [1] NegTokenResp
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum NegotiationToken {
+    NegTokenInit(NegTokenInit),
+    NegTokenResp(NegTokenResp),
+}
+
+impl NegotiationToken {
+    pub fn build(&self) -> Vec<u8> {
+        let (number, mut value) = match self {
+            Self::NegTokenInit(neg_token_init) => (0, neg_token_init.build()),
+            Self::NegTokenResp(neg_token_resp) => (1, neg_token_resp.build()),
+        };
+
+        let mut encoded =
+            Tag::new(number, TagType::Constructed, TagClass::Context).build();
+        encoded.append(&mut build_length(value.len()));
+        encoded.append(&mut value);
+        return encoded;
+    }
+
+    pub fn parse(raw: &[u8]) -> Result<(&[u8], Self)> {
+        let (raw, tag) = Tag::parse(raw)?;
+        if tag.class != TagClass::Context || tag.r#type != TagType::Constructed
+        {
+            return Err(Error::UnmatchedTag(TagClass::Context));
+        }
+
+        let (raw, length) = parse_length(raw)?;
+        if length > raw.len() {
+            return Err(Error::NoDataForLength);
+        }
+        let (value, raw) = raw.split_at(length);
+
+        let token = match tag.number {
+            0 => Self::NegTokenInit(NegTokenInit::parse(value)?.1),
+            1 => Self::NegTokenResp(NegTokenResp::parse(value)?.1),
+            _ => return Err(Error::UnmatchedTag(TagClass::Context)),
+        };
+        return Ok((raw, token));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use himmelblau_red_asn1::Enumerated;
+
+    #[test]
+    fn test_build_and_parse_neg_token_resp() {
+        let token = NegotiationToken::NegTokenResp(NegTokenResp {
+            neg_state: Some(Enumerated::new(1)),
+            ..Default::default()
+        });
+        let raw = token.build();
+
+        assert_eq!(
+            vec![0xa1, 0x07, 0x30, 0x05, 0xa0, 0x03, 0x0a, 0x01, 0x01],
+            raw
+        );
+        assert_eq!(token, NegotiationToken::parse(&raw).unwrap().1);
+    }
+
+    #[test]
+    fn test_build_and_parse_neg_token_init() {
+        let token = NegotiationToken::NegTokenInit(NegTokenInit::default());
+        let raw = token.build();
+
+        assert_eq!(vec![0xa0, 0x06, 0x30, 0x04, 0xa0, 0x02, 0x30, 0x00], raw);
+        assert_eq!(token, NegotiationToken::parse(&raw).unwrap().1);
+    }
+
+    #[should_panic(expected = "UnmatchedTag")]
+    #[test]
+    fn test_parse_unknown_choice() {
+        NegotiationToken::parse(&[0xa2, 0x02, 0x30, 0x00]).unwrap();
+    }
+}
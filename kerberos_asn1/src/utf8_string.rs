@@ -0,0 +1,84 @@
+use himmelblau_red_asn1::{Asn1Object, Tag};
+use std::ops::{Deref, DerefMut};
+
+const UTF8STRING_TAG_NUMBER: u8 = 0x0c;
+
+/// (*UTF8String*) String encoded in UTF-8, used by the authorization
+/// data elements of RFC8129.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Utf8String {
+    pub string: String,
+}
+
+impl Deref for Utf8String {
+    type Target = String;
+    fn deref(&self) -> &Self::Target {
+        &self.string
+    }
+}
+
+impl DerefMut for Utf8String {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.string
+    }
+}
+
+impl Asn1Object for Utf8String {
+    fn tag() -> Tag {
+        return Tag::new_primitive_universal(UTF8STRING_TAG_NUMBER);
+    }
+
+    fn build_value(&self) -> Vec<u8> {
+        return self.string.as_bytes().to_vec();
+    }
+
+    fn parse_value(&mut self, raw: &[u8]) -> himmelblau_red_asn1::Result<()> {
+        self.string = String::from_utf8(raw.to_vec())?;
+        return Ok(());
+    }
+}
+
+impl From<&str> for Utf8String {
+    fn from(string: &str) -> Self {
+        return Self {
+            string: string.to_string(),
+        };
+    }
+}
+
+impl From<String> for Utf8String {
+    fn from(string: String) -> Self {
+        return Self { string };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_utf8_string() {
+        assert_eq!(
+            vec![0x0c, 0x03, 0x6f, 0x74, 0x70],
+            Utf8String::from("otp").build()
+        );
+    }
+
+    #[test]
+    fn test_decode_utf8_string() {
+        assert_eq!(
+            Utf8String::from("pkinit"),
+            Utf8String::parse(&[
+                0x0c, 0x06, 0x70, 0x6b, 0x69, 0x6e, 0x69, 0x74
+            ])
+            .unwrap()
+            .1
+        );
+    }
+
+    #[should_panic(expected = "UnmatchedTag")]
+    #[test]
+    fn test_decode_general_string_as_utf8_string() {
+        Utf8String::parse(&[0x1b, 0x03, 0x6f, 0x74, 0x70]).unwrap();
+    }
+}
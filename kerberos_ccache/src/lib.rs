@@ -50,8 +50,10 @@ pub use credential::*;
 mod ccache;
 pub use ccache::*;
 
+pub use nom::error::{Error as ParseError, ErrorKind};
 pub use nom::Err as Error;
 pub use nom::IResult as Result;
+pub use nom::Needed;
 
 pub mod mappers;
 
@@ -12,5 +12,16 @@ pub const AD_MANDATORY_FOR_KDC: i32 = 8;
 pub const OSF_DCE: i32 = 64;
 pub const SESAME: i32 = 65;
 pub const AD_OSF_DCE_PKI_CERTID: i32 = 66;
+/// AD-CAMMAC, RFC7751.
+pub const AD_CAMMAC: i32 = 96;
+/// AD-AUTHENTICATION-INDICATOR, RFC8129.
+pub const AD_AUTHENTICATION_INDICATOR: i32 = 97;
 pub const AD_WIN2K_PACK: i32 = 128;
 pub const AD_ETYPE_NEGOTIATION: i32 = 129;
+
+/// KERB-AD-RESTRICTION-ENTRY elements, [MS-KILE] Section 2.2.6.
+pub const KERB_AUTH_DATA_TOKEN_RESTRICTIONS: i32 = 141;
+/// KERB-LOCAL, [MS-KILE] Section 2.2.4.
+pub const KERB_LOCAL: i32 = 142;
+/// AD-AUTH-DATA-AP-OPTIONS, [MS-KILE] Section 2.2.10.
+pub const AD_AUTH_DATA_AP_OPTIONS: i32 = 143;
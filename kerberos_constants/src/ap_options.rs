@@ -1,3 +1,8 @@
-pub const RESERVED: u32 = 0;
-pub const USE_SESSION_KEY: u32 = 1;
-pub const MUTUAL_REQUIRED: u32 = 2;
+//! Options used by the message `ApReq`.
+//!
+//! # References
+//! * RFC 4120, Section 5.5.1.
+
+pub const RESERVED: u32 = 0x80000000;
+pub const USE_SESSION_KEY: u32 = 0x40000000;
+pub const MUTUAL_REQUIRED: u32 = 0x20000000;
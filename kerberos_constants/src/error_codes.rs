@@ -2,6 +2,8 @@
 //!
 //! Defined in RFC4120, section 7.5.9.
 
+use std::fmt;
+
 /// No error
 pub const KDC_ERR_NONE: i32 = 0;
 
@@ -254,3 +256,530 @@ pub fn error_code_to_string(error_code: i32) -> String {
         _ => "".to_string(),
     }
 }
+
+/// Named error codes of *KRB-ERROR*, which can be obtained from the
+/// numeric value of the `error-code` field with [`ErrorCode::from_code`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(i32)]
+pub enum ErrorCode {
+    /// No error
+    KdcErrNone = KDC_ERR_NONE,
+    /// Client's entry in database has expired
+    KdcErrNameExp = KDC_ERR_NAME_EXP,
+    /// Server's entry in database has expired
+    KdcErrServiceExp = KDC_ERR_SERVICE_EXP,
+    /// Requested protocol version number not supported
+    KdcErrBadPvno = KDC_ERR_BAD_PVNO,
+    /// Client's key encrypted in old master key
+    KdcErrCOldMastKvno = KDC_ERR_C_OLD_MAST_KVNO,
+    /// Server's key encrypted in old master key
+    KdcErrSOldMastKvno = KDC_ERR_S_OLD_MAST_KVNO,
+    /// Client not found in Kerberos database
+    KdcErrCPrincipalUnknown = KDC_ERR_C_PRINCIPAL_UNKNOWN,
+    /// Server not found in Kerberos database
+    KdcErrSPrincipalUnknown = KDC_ERR_S_PRINCIPAL_UNKNOWN,
+    /// Multiple principal entries in database
+    KdcErrPrincipalNotUnique = KDC_ERR_PRINCIPAL_NOT_UNIQUE,
+    /// The client or server has a null key
+    KdcErrNullKey = KDC_ERR_NULL_KEY,
+    /// Ticket not eligible for postdating
+    KdcErrCannotPostdate = KDC_ERR_CANNOT_POSTDATE,
+    /// Requested starttime is later than end time
+    KdcErrNeverValid = KDC_ERR_NEVER_VALID,
+    /// KDC policy rejects request
+    KdcErrPolicy = KDC_ERR_POLICY,
+    /// KDC cannot accommodate requested option
+    KdcErrBadoption = KDC_ERR_BADOPTION,
+    /// KDC has no support for encryption type
+    KdcErrEtypeNosupp = KDC_ERR_ETYPE_NOSUPP,
+    /// KDC has no support for checksum type
+    KdcErrSumtypeNosupp = KDC_ERR_SUMTYPE_NOSUPP,
+    /// KDC has no support for padata type
+    KdcErrPadataTypeNosupp = KDC_ERR_PADATA_TYPE_NOSUPP,
+    /// KDC has no support for transited type
+    KdcErrTrtypeNosupp = KDC_ERR_TRTYPE_NOSUPP,
+    /// Clients credentials have been revoked
+    KdcErrClientRevoked = KDC_ERR_CLIENT_REVOKED,
+    /// Credentials for server have been revoked
+    KdcErrServiceRevoked = KDC_ERR_SERVICE_REVOKED,
+    /// TGT has been revoked
+    KdcErrTgtRevoked = KDC_ERR_TGT_REVOKED,
+    /// Client not yet valid; try again later
+    KdcErrClientNotyet = KDC_ERR_CLIENT_NOTYET,
+    /// Server not yet valid; try again later
+    KdcErrServiceNotyet = KDC_ERR_SERVICE_NOTYET,
+    /// Password has expired; change password to reset
+    KdcErrKeyExpired = KDC_ERR_KEY_EXPIRED,
+    /// Pre-authentication information was invalid
+    KdcErrPreauthFailed = KDC_ERR_PREAUTH_FAILED,
+    /// Additional pre- authentication required
+    KdcErrPreauthRequired = KDC_ERR_PREAUTH_REQUIRED,
+    /// Requested server and ticket don't match
+    KdcErrServerNomatch = KDC_ERR_SERVER_NOMATCH,
+    /// Server principal valid for user2user only
+    KdcErrMustUseUser2user = KDC_ERR_MUST_USE_USER2USER,
+    /// KDC Policy rejects transited path
+    KdcErrPathNotAccepted = KDC_ERR_PATH_NOT_ACCEPTED,
+    /// A service is not available
+    KdcErrSvcUnavailable = KDC_ERR_SVC_UNAVAILABLE,
+    /// Integrity check on decrypted field failed
+    KrbApErrBadIntegrity = KRB_AP_ERR_BAD_INTEGRITY,
+    /// Ticket expired
+    KrbApErrTktExpired = KRB_AP_ERR_TKT_EXPIRED,
+    /// Ticket not yet valid
+    KrbApErrTktNyv = KRB_AP_ERR_TKT_NYV,
+    /// Request is a replay
+    KrbApErrRepeat = KRB_AP_ERR_REPEAT,
+    /// The ticket isn't for us
+    KrbApErrNotUs = KRB_AP_ERR_NOT_US,
+    /// Ticket and authenticator don't match
+    KrbApErrBadmatch = KRB_AP_ERR_BADMATCH,
+    /// Clock skew too great
+    KrbApErrSkew = KRB_AP_ERR_SKEW,
+    /// Incorrect net address
+    KrbApErrBadaddr = KRB_AP_ERR_BADADDR,
+    /// Protocol version mismatch
+    KrbApErrBadversion = KRB_AP_ERR_BADVERSION,
+    /// Invalid msg type
+    KrbApErrMsgType = KRB_AP_ERR_MSG_TYPE,
+    /// Message stream modified
+    KrbApErrModified = KRB_AP_ERR_MODIFIED,
+    /// Message out of order
+    KrbApErrBadorder = KRB_AP_ERR_BADORDER,
+    /// Specified version of key is not available
+    KrbApErrBadkeyver = KRB_AP_ERR_BADKEYVER,
+    /// Service key not available
+    KrbApErrNokey = KRB_AP_ERR_NOKEY,
+    /// Mutual authentication failed
+    KrbApErrMutFail = KRB_AP_ERR_MUT_FAIL,
+    /// Incorrect message direction
+    KrbApErrBaddirection = KRB_AP_ERR_BADDIRECTION,
+    /// Alternative authentication method required
+    KrbApErrMethod = KRB_AP_ERR_METHOD,
+    /// Incorrect sequence number in message
+    KrbApErrBadseq = KRB_AP_ERR_BADSEQ,
+    /// Inappropriate type of checksum in message
+    KrbApErrInappCksum = KRB_AP_ERR_INAPP_CKSUM,
+    /// Policy rejects transited path
+    KrbApPathNotAccepted = KRB_AP_PATH_NOT_ACCEPTED,
+    /// Response too big for UDP; retry with TCP
+    KrbErrResponseTooBig = KRB_ERR_RESPONSE_TOO_BIG,
+    /// Generic error (description in e-text)
+    KrbErrGeneric = KRB_ERR_GENERIC,
+    /// Field is too long for this implementation
+    KrbErrFieldToolong = KRB_ERR_FIELD_TOOLONG,
+    /// Client not trusted
+    KdcErrorClientNotTrusted = KDC_ERROR_CLIENT_NOT_TRUSTED,
+    /// KDC not trusted
+    KdcErrorKdcNotTrusted = KDC_ERROR_KDC_NOT_TRUSTED,
+    /// Invalid signature
+    KdcErrorInvalidSig = KDC_ERROR_INVALID_SIG,
+    /// Key too weak
+    KdcErrKeyTooWeak = KDC_ERR_KEY_TOO_WEAK,
+    /// Certificate mismatch
+    KdcErrCertificateMismatch = KDC_ERR_CERTIFICATE_MISMATCH,
+    /// No TGT available to validate USER-TO-USER
+    KrbApErrNoTgt = KRB_AP_ERR_NO_TGT,
+    /// Wrong realm
+    KdcErrWrongRealm = KDC_ERR_WRONG_REALM,
+    /// Ticket must be for USER-TO-USER
+    KrbApErrUserToUserRequired = KRB_AP_ERR_USER_TO_USER_REQUIRED,
+    /// Can't verify certificate
+    KdcErrCantVerifyCertificate = KDC_ERR_CANT_VERIFY_CERTIFICATE,
+    /// Invalid certificate
+    KdcErrInvalidCertificate = KDC_ERR_INVALID_CERTIFICATE,
+    /// Revoked certificate
+    KdcErrRevokedCertificate = KDC_ERR_REVOKED_CERTIFICATE,
+    /// Revocation status unknown
+    KdcErrRevocationStatusUnknown = KDC_ERR_REVOCATION_STATUS_UNKNOWN,
+    /// Revocation status unavailable
+    KdcErrRevocationStatusUnavailable = KDC_ERR_REVOCATION_STATUS_UNAVAILABLE,
+    /// Client name mismatch
+    KdcErrClientNameMismatch = KDC_ERR_CLIENT_NAME_MISMATCH,
+    /// KDC name mismatch
+    KdcErrKdcNameMismatch = KDC_ERR_KDC_NAME_MISMATCH,
+}
+
+impl ErrorCode {
+    /// Returns the named error code of a numeric value, or `None` if the
+    /// value is unknown.
+    pub fn from_code(error_code: i32) -> Option<Self> {
+        let code = match error_code {
+            KDC_ERR_NONE => Self::KdcErrNone,
+            KDC_ERR_NAME_EXP => Self::KdcErrNameExp,
+            KDC_ERR_SERVICE_EXP => Self::KdcErrServiceExp,
+            KDC_ERR_BAD_PVNO => Self::KdcErrBadPvno,
+            KDC_ERR_C_OLD_MAST_KVNO => Self::KdcErrCOldMastKvno,
+            KDC_ERR_S_OLD_MAST_KVNO => Self::KdcErrSOldMastKvno,
+            KDC_ERR_C_PRINCIPAL_UNKNOWN => Self::KdcErrCPrincipalUnknown,
+            KDC_ERR_S_PRINCIPAL_UNKNOWN => Self::KdcErrSPrincipalUnknown,
+            KDC_ERR_PRINCIPAL_NOT_UNIQUE => Self::KdcErrPrincipalNotUnique,
+            KDC_ERR_NULL_KEY => Self::KdcErrNullKey,
+            KDC_ERR_CANNOT_POSTDATE => Self::KdcErrCannotPostdate,
+            KDC_ERR_NEVER_VALID => Self::KdcErrNeverValid,
+            KDC_ERR_POLICY => Self::KdcErrPolicy,
+            KDC_ERR_BADOPTION => Self::KdcErrBadoption,
+            KDC_ERR_ETYPE_NOSUPP => Self::KdcErrEtypeNosupp,
+            KDC_ERR_SUMTYPE_NOSUPP => Self::KdcErrSumtypeNosupp,
+            KDC_ERR_PADATA_TYPE_NOSUPP => Self::KdcErrPadataTypeNosupp,
+            KDC_ERR_TRTYPE_NOSUPP => Self::KdcErrTrtypeNosupp,
+            KDC_ERR_CLIENT_REVOKED => Self::KdcErrClientRevoked,
+            KDC_ERR_SERVICE_REVOKED => Self::KdcErrServiceRevoked,
+            KDC_ERR_TGT_REVOKED => Self::KdcErrTgtRevoked,
+            KDC_ERR_CLIENT_NOTYET => Self::KdcErrClientNotyet,
+            KDC_ERR_SERVICE_NOTYET => Self::KdcErrServiceNotyet,
+            KDC_ERR_KEY_EXPIRED => Self::KdcErrKeyExpired,
+            KDC_ERR_PREAUTH_FAILED => Self::KdcErrPreauthFailed,
+            KDC_ERR_PREAUTH_REQUIRED => Self::KdcErrPreauthRequired,
+            KDC_ERR_SERVER_NOMATCH => Self::KdcErrServerNomatch,
+            KDC_ERR_MUST_USE_USER2USER => Self::KdcErrMustUseUser2user,
+            KDC_ERR_PATH_NOT_ACCEPTED => Self::KdcErrPathNotAccepted,
+            KDC_ERR_SVC_UNAVAILABLE => Self::KdcErrSvcUnavailable,
+            KRB_AP_ERR_BAD_INTEGRITY => Self::KrbApErrBadIntegrity,
+            KRB_AP_ERR_TKT_EXPIRED => Self::KrbApErrTktExpired,
+            KRB_AP_ERR_TKT_NYV => Self::KrbApErrTktNyv,
+            KRB_AP_ERR_REPEAT => Self::KrbApErrRepeat,
+            KRB_AP_ERR_NOT_US => Self::KrbApErrNotUs,
+            KRB_AP_ERR_BADMATCH => Self::KrbApErrBadmatch,
+            KRB_AP_ERR_SKEW => Self::KrbApErrSkew,
+            KRB_AP_ERR_BADADDR => Self::KrbApErrBadaddr,
+            KRB_AP_ERR_BADVERSION => Self::KrbApErrBadversion,
+            KRB_AP_ERR_MSG_TYPE => Self::KrbApErrMsgType,
+            KRB_AP_ERR_MODIFIED => Self::KrbApErrModified,
+            KRB_AP_ERR_BADORDER => Self::KrbApErrBadorder,
+            KRB_AP_ERR_BADKEYVER => Self::KrbApErrBadkeyver,
+            KRB_AP_ERR_NOKEY => Self::KrbApErrNokey,
+            KRB_AP_ERR_MUT_FAIL => Self::KrbApErrMutFail,
+            KRB_AP_ERR_BADDIRECTION => Self::KrbApErrBaddirection,
+            KRB_AP_ERR_METHOD => Self::KrbApErrMethod,
+            KRB_AP_ERR_BADSEQ => Self::KrbApErrBadseq,
+            KRB_AP_ERR_INAPP_CKSUM => Self::KrbApErrInappCksum,
+            KRB_AP_PATH_NOT_ACCEPTED => Self::KrbApPathNotAccepted,
+            KRB_ERR_RESPONSE_TOO_BIG => Self::KrbErrResponseTooBig,
+            KRB_ERR_GENERIC => Self::KrbErrGeneric,
+            KRB_ERR_FIELD_TOOLONG => Self::KrbErrFieldToolong,
+            KDC_ERROR_CLIENT_NOT_TRUSTED => Self::KdcErrorClientNotTrusted,
+            KDC_ERROR_KDC_NOT_TRUSTED => Self::KdcErrorKdcNotTrusted,
+            KDC_ERROR_INVALID_SIG => Self::KdcErrorInvalidSig,
+            KDC_ERR_KEY_TOO_WEAK => Self::KdcErrKeyTooWeak,
+            KDC_ERR_CERTIFICATE_MISMATCH => Self::KdcErrCertificateMismatch,
+            KRB_AP_ERR_NO_TGT => Self::KrbApErrNoTgt,
+            KDC_ERR_WRONG_REALM => Self::KdcErrWrongRealm,
+            KRB_AP_ERR_USER_TO_USER_REQUIRED => {
+                Self::KrbApErrUserToUserRequired
+            }
+            KDC_ERR_CANT_VERIFY_CERTIFICATE => {
+                Self::KdcErrCantVerifyCertificate
+            }
+            KDC_ERR_INVALID_CERTIFICATE => Self::KdcErrInvalidCertificate,
+            KDC_ERR_REVOKED_CERTIFICATE => Self::KdcErrRevokedCertificate,
+            KDC_ERR_REVOCATION_STATUS_UNKNOWN => {
+                Self::KdcErrRevocationStatusUnknown
+            }
+            KDC_ERR_REVOCATION_STATUS_UNAVAILABLE => {
+                Self::KdcErrRevocationStatusUnavailable
+            }
+            KDC_ERR_CLIENT_NAME_MISMATCH => Self::KdcErrClientNameMismatch,
+            KDC_ERR_KDC_NAME_MISMATCH => Self::KdcErrKdcNameMismatch,
+            _ => return None,
+        };
+        return Some(code);
+    }
+
+    /// Numeric value of the error code.
+    pub fn code(self) -> i32 {
+        return self as i32;
+    }
+
+    /// Name of the error code, as defined in RFC4120.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::KdcErrNone => return "KDC_ERR_NONE",
+            Self::KdcErrNameExp => return "KDC_ERR_NAME_EXP",
+            Self::KdcErrServiceExp => return "KDC_ERR_SERVICE_EXP",
+            Self::KdcErrBadPvno => return "KDC_ERR_BAD_PVNO",
+            Self::KdcErrCOldMastKvno => return "KDC_ERR_C_OLD_MAST_KVNO",
+            Self::KdcErrSOldMastKvno => return "KDC_ERR_S_OLD_MAST_KVNO",
+            Self::KdcErrCPrincipalUnknown => {
+                return "KDC_ERR_C_PRINCIPAL_UNKNOWN"
+            }
+            Self::KdcErrSPrincipalUnknown => {
+                return "KDC_ERR_S_PRINCIPAL_UNKNOWN"
+            }
+            Self::KdcErrPrincipalNotUnique => {
+                return "KDC_ERR_PRINCIPAL_NOT_UNIQUE"
+            }
+            Self::KdcErrNullKey => return "KDC_ERR_NULL_KEY",
+            Self::KdcErrCannotPostdate => return "KDC_ERR_CANNOT_POSTDATE",
+            Self::KdcErrNeverValid => return "KDC_ERR_NEVER_VALID",
+            Self::KdcErrPolicy => return "KDC_ERR_POLICY",
+            Self::KdcErrBadoption => return "KDC_ERR_BADOPTION",
+            Self::KdcErrEtypeNosupp => return "KDC_ERR_ETYPE_NOSUPP",
+            Self::KdcErrSumtypeNosupp => return "KDC_ERR_SUMTYPE_NOSUPP",
+            Self::KdcErrPadataTypeNosupp => {
+                return "KDC_ERR_PADATA_TYPE_NOSUPP"
+            }
+            Self::KdcErrTrtypeNosupp => return "KDC_ERR_TRTYPE_NOSUPP",
+            Self::KdcErrClientRevoked => return "KDC_ERR_CLIENT_REVOKED",
+            Self::KdcErrServiceRevoked => return "KDC_ERR_SERVICE_REVOKED",
+            Self::KdcErrTgtRevoked => return "KDC_ERR_TGT_REVOKED",
+            Self::KdcErrClientNotyet => return "KDC_ERR_CLIENT_NOTYET",
+            Self::KdcErrServiceNotyet => return "KDC_ERR_SERVICE_NOTYET",
+            Self::KdcErrKeyExpired => return "KDC_ERR_KEY_EXPIRED",
+            Self::KdcErrPreauthFailed => return "KDC_ERR_PREAUTH_FAILED",
+            Self::KdcErrPreauthRequired => return "KDC_ERR_PREAUTH_REQUIRED",
+            Self::KdcErrServerNomatch => return "KDC_ERR_SERVER_NOMATCH",
+            Self::KdcErrMustUseUser2user => {
+                return "KDC_ERR_MUST_USE_USER2USER"
+            }
+            Self::KdcErrPathNotAccepted => return "KDC_ERR_PATH_NOT_ACCEPTED",
+            Self::KdcErrSvcUnavailable => return "KDC_ERR_SVC_UNAVAILABLE",
+            Self::KrbApErrBadIntegrity => return "KRB_AP_ERR_BAD_INTEGRITY",
+            Self::KrbApErrTktExpired => return "KRB_AP_ERR_TKT_EXPIRED",
+            Self::KrbApErrTktNyv => return "KRB_AP_ERR_TKT_NYV",
+            Self::KrbApErrRepeat => return "KRB_AP_ERR_REPEAT",
+            Self::KrbApErrNotUs => return "KRB_AP_ERR_NOT_US",
+            Self::KrbApErrBadmatch => return "KRB_AP_ERR_BADMATCH",
+            Self::KrbApErrSkew => return "KRB_AP_ERR_SKEW",
+            Self::KrbApErrBadaddr => return "KRB_AP_ERR_BADADDR",
+            Self::KrbApErrBadversion => return "KRB_AP_ERR_BADVERSION",
+            Self::KrbApErrMsgType => return "KRB_AP_ERR_MSG_TYPE",
+            Self::KrbApErrModified => return "KRB_AP_ERR_MODIFIED",
+            Self::KrbApErrBadorder => return "KRB_AP_ERR_BADORDER",
+            Self::KrbApErrBadkeyver => return "KRB_AP_ERR_BADKEYVER",
+            Self::KrbApErrNokey => return "KRB_AP_ERR_NOKEY",
+            Self::KrbApErrMutFail => return "KRB_AP_ERR_MUT_FAIL",
+            Self::KrbApErrBaddirection => return "KRB_AP_ERR_BADDIRECTION",
+            Self::KrbApErrMethod => return "KRB_AP_ERR_METHOD",
+            Self::KrbApErrBadseq => return "KRB_AP_ERR_BADSEQ",
+            Self::KrbApErrInappCksum => return "KRB_AP_ERR_INAPP_CKSUM",
+            Self::KrbApPathNotAccepted => return "KRB_AP_PATH_NOT_ACCEPTED",
+            Self::KrbErrResponseTooBig => return "KRB_ERR_RESPONSE_TOO_BIG",
+            Self::KrbErrGeneric => return "KRB_ERR_GENERIC",
+            Self::KrbErrFieldToolong => return "KRB_ERR_FIELD_TOOLONG",
+            Self::KdcErrorClientNotTrusted => {
+                return "KDC_ERROR_CLIENT_NOT_TRUSTED"
+            }
+            Self::KdcErrorKdcNotTrusted => return "KDC_ERROR_KDC_NOT_TRUSTED",
+            Self::KdcErrorInvalidSig => return "KDC_ERROR_INVALID_SIG",
+            Self::KdcErrKeyTooWeak => return "KDC_ERR_KEY_TOO_WEAK",
+            Self::KdcErrCertificateMismatch => {
+                return "KDC_ERR_CERTIFICATE_MISMATCH"
+            }
+            Self::KrbApErrNoTgt => return "KRB_AP_ERR_NO_TGT",
+            Self::KdcErrWrongRealm => return "KDC_ERR_WRONG_REALM",
+            Self::KrbApErrUserToUserRequired => {
+                return "KRB_AP_ERR_USER_TO_USER_REQUIRED"
+            }
+            Self::KdcErrCantVerifyCertificate => {
+                return "KDC_ERR_CANT_VERIFY_CERTIFICATE"
+            }
+            Self::KdcErrInvalidCertificate => {
+                return "KDC_ERR_INVALID_CERTIFICATE"
+            }
+            Self::KdcErrRevokedCertificate => {
+                return "KDC_ERR_REVOKED_CERTIFICATE"
+            }
+            Self::KdcErrRevocationStatusUnknown => {
+                return "KDC_ERR_REVOCATION_STATUS_UNKNOWN"
+            }
+            Self::KdcErrRevocationStatusUnavailable => {
+                return "KDC_ERR_REVOCATION_STATUS_UNAVAILABLE"
+            }
+            Self::KdcErrClientNameMismatch => {
+                return "KDC_ERR_CLIENT_NAME_MISMATCH"
+            }
+            Self::KdcErrKdcNameMismatch => return "KDC_ERR_KDC_NAME_MISMATCH",
+        }
+    }
+
+    /// Description of the error code.
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::KdcErrNone => return "No error",
+            Self::KdcErrNameExp => {
+                return "Client's entry in database has expired"
+            }
+            Self::KdcErrServiceExp => {
+                return "Server's entry in database has expired"
+            }
+            Self::KdcErrBadPvno => {
+                return "Requested protocol version number not supported"
+            }
+            Self::KdcErrCOldMastKvno => {
+                return "Client's key encrypted in old master key"
+            }
+            Self::KdcErrSOldMastKvno => {
+                return "Server's key encrypted in old master key"
+            }
+            Self::KdcErrCPrincipalUnknown => {
+                return "Client not found in Kerberos database"
+            }
+            Self::KdcErrSPrincipalUnknown => {
+                return "Server not found in Kerberos database"
+            }
+            Self::KdcErrPrincipalNotUnique => {
+                return "Multiple principal entries in database"
+            }
+            Self::KdcErrNullKey => {
+                return "The client or server has a null key"
+            }
+            Self::KdcErrCannotPostdate => {
+                return "Ticket not eligible for postdating"
+            }
+            Self::KdcErrNeverValid => {
+                return "Requested starttime is later than end time"
+            }
+            Self::KdcErrPolicy => return "KDC policy rejects request",
+            Self::KdcErrBadoption => {
+                return "KDC cannot accommodate requested option"
+            }
+            Self::KdcErrEtypeNosupp => {
+                return "KDC has no support for encryption type"
+            }
+            Self::KdcErrSumtypeNosupp => {
+                return "KDC has no support for checksum type"
+            }
+            Self::KdcErrPadataTypeNosupp => {
+                return "KDC has no support for padata type"
+            }
+            Self::KdcErrTrtypeNosupp => {
+                return "KDC has no support for transited type"
+            }
+            Self::KdcErrClientRevoked => {
+                return "Clients credentials have been revoked"
+            }
+            Self::KdcErrServiceRevoked => {
+                return "Credentials for server have been revoked"
+            }
+            Self::KdcErrTgtRevoked => return "TGT has been revoked",
+            Self::KdcErrClientNotyet => {
+                return "Client not yet valid; try again later"
+            }
+            Self::KdcErrServiceNotyet => {
+                return "Server not yet valid; try again later"
+            }
+            Self::KdcErrKeyExpired => {
+                return "Password has expired; change password to reset"
+            }
+            Self::KdcErrPreauthFailed => {
+                return "Pre-authentication information was invalid"
+            }
+            Self::KdcErrPreauthRequired => {
+                return "Additional pre-authentication required"
+            }
+            Self::KdcErrServerNomatch => {
+                return "Requested server and ticket don't match"
+            }
+            Self::KdcErrMustUseUser2user => {
+                return "Server principal valid for user2user only"
+            }
+            Self::KdcErrPathNotAccepted => {
+                return "KDC Policy rejects transited path"
+            }
+            Self::KdcErrSvcUnavailable => return "A service is not available",
+            Self::KrbApErrBadIntegrity => {
+                return "Integrity check on decrypted field failed"
+            }
+            Self::KrbApErrTktExpired => return "Ticket expired",
+            Self::KrbApErrTktNyv => return "Ticket not yet valid",
+            Self::KrbApErrRepeat => return "Request is a replay",
+            Self::KrbApErrNotUs => return "The ticket isn't for us",
+            Self::KrbApErrBadmatch => {
+                return "Ticket and authenticator don't match"
+            }
+            Self::KrbApErrSkew => return "Clock skew too great",
+            Self::KrbApErrBadaddr => return "Incorrect net address",
+            Self::KrbApErrBadversion => return "Protocol version mismatch",
+            Self::KrbApErrMsgType => return "Invalid msg type",
+            Self::KrbApErrModified => return "Message stream modified",
+            Self::KrbApErrBadorder => return "Message out of order",
+            Self::KrbApErrBadkeyver => {
+                return "Specified version of key is not available"
+            }
+            Self::KrbApErrNokey => return "Service key not available",
+            Self::KrbApErrMutFail => return "Mutual authentication failed",
+            Self::KrbApErrBaddirection => return "Incorrect message direction",
+            Self::KrbApErrMethod => {
+                return "Alternative authentication method required"
+            }
+            Self::KrbApErrBadseq => {
+                return "Incorrect sequence number in message"
+            }
+            Self::KrbApErrInappCksum => {
+                return "Inappropriate type of checksum in message"
+            }
+            Self::KrbApPathNotAccepted => {
+                return "Policy rejects transited path"
+            }
+            Self::KrbErrResponseTooBig => {
+                return "Response too big for UDP; retry with TCP"
+            }
+            Self::KrbErrGeneric => {
+                return "Generic error (description in e-text)"
+            }
+            Self::KrbErrFieldToolong => {
+                return "Field is too long for this implementation"
+            }
+            Self::KdcErrorClientNotTrusted => return "Client not trusted",
+            Self::KdcErrorKdcNotTrusted => return "KDC not trusted",
+            Self::KdcErrorInvalidSig => return "Invalid signature",
+            Self::KdcErrKeyTooWeak => return "Key too weak",
+            Self::KdcErrCertificateMismatch => return "Certificate mismatch",
+            Self::KrbApErrNoTgt => {
+                return "No TGT available to validate USER-TO-USER"
+            }
+            Self::KdcErrWrongRealm => return "Wrong realm",
+            Self::KrbApErrUserToUserRequired => {
+                return "Ticket must be for USER-TO-USER"
+            }
+            Self::KdcErrCantVerifyCertificate => {
+                return "Can't verify certificate"
+            }
+            Self::KdcErrInvalidCertificate => return "Invalid certificate",
+            Self::KdcErrRevokedCertificate => return "Revoked certificate",
+            Self::KdcErrRevocationStatusUnknown => {
+                return "Revocation status unknown"
+            }
+            Self::KdcErrRevocationStatusUnavailable => {
+                return "Revocation status unavailable"
+            }
+            Self::KdcErrClientNameMismatch => return "Client name mismatch",
+            Self::KdcErrKdcNameMismatch => return "KDC name mismatch",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{} ({})", self.name(), self.code());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn error_code_from_code() {
+        assert_eq!(
+            Some(ErrorCode::KdcErrPreauthRequired),
+            ErrorCode::from_code(KDC_ERR_PREAUTH_REQUIRED)
+        );
+        assert_eq!(
+            Some(ErrorCode::KdcErrKdcNameMismatch),
+            ErrorCode::from_code(76)
+        );
+        assert_eq!(None, ErrorCode::from_code(30));
+    }
+
+    #[test]
+    fn error_code_name_and_description() {
+        let code = ErrorCode::KdcErrClientRevoked;
+        assert_eq!(KDC_ERR_CLIENT_REVOKED, code.code());
+        assert_eq!("KDC_ERR_CLIENT_REVOKED", code.name());
+        assert_eq!("Clients credentials have been revoked", code.description());
+        assert_eq!("KDC_ERR_CLIENT_REVOKED (18)", code.to_string());
+        assert_eq!(error_code_to_string(18), code.name());
+    }
+}
@@ -65,3 +65,29 @@ pub const KEY_USAGE_KRB_SAFE_CKSUM: i32 = 15;
 
 pub const KEY_USAGE_KERB_NON_KERB_SALT: i32 = 16;
 pub const KEY_USAGE_KERB_NON_KERB_CKSUM_SALT: i32 = 17;
+
+/// AD-KDCIssued checksum, keyed with the session key of the ticket
+pub const KEY_USAGE_AD_KDCISSUED_CKSUM: i32 = 19;
+
+/// GSS-API Wrap tokens with confidentiality sent by the acceptor
+/// (RFC 4121, Section 2)
+pub const KEY_USAGE_ACCEPTOR_SEAL: i32 = 22;
+
+/// GSS-API MIC tokens and Wrap tokens without confidentiality sent by the
+/// acceptor (RFC 4121, Section 2)
+pub const KEY_USAGE_ACCEPTOR_SIGN: i32 = 23;
+
+/// GSS-API Wrap tokens with confidentiality sent by the initiator
+/// (RFC 4121, Section 2)
+pub const KEY_USAGE_INITIATOR_SEAL: i32 = 24;
+
+/// GSS-API MIC tokens and Wrap tokens without confidentiality sent by the
+/// initiator (RFC 4121, Section 2)
+pub const KEY_USAGE_INITIATOR_SIGN: i32 = 25;
+
+/// AS-REQ checksum of PA-REQ-ENC-PA-REP, keyed with the reply key
+/// (RFC 6806, Section 11)
+pub const KEY_USAGE_AS_REQ: i32 = 56;
+
+/// Verifier-MAC checksums of AD-CAMMAC (RFC 7751, Section 2)
+pub const KEY_USAGE_CAMMAC_CHECKSUM: i32 = 64;
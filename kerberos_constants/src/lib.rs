@@ -11,6 +11,7 @@ pub mod kdc_options;
 pub mod kerb_error_data_type;
 pub mod key_usages;
 pub mod message_types;
+pub mod neg_states;
 pub mod pa_data_types;
 pub mod pa_pac_options;
 pub mod principal_names;
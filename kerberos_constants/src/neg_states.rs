@@ -0,0 +1,9 @@
+//! States of the SPNEGO negotiation, sent in `NegTokenResp`.
+//!
+//! # References
+//! * RFC 4178, Section 4.2.2.
+
+pub const ACCEPT_COMPLETED: u32 = 0;
+pub const ACCEPT_INCOMPLETE: u32 = 1;
+pub const REJECT: u32 = 2;
+pub const REQUEST_MIC: u32 = 3;
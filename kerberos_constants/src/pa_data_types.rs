@@ -45,6 +45,7 @@ pub const PA_FX_COOKIE: i32 = 133;
 pub const PA_FX_FAST: i32 = 136;
 pub const PA_FX_ERROR: i32 = 137;
 pub const PA_ENCRYPTED_CHALLENGE: i32 = 138;
+pub const PA_REQ_ENC_PA_REP: i32 = 149;
 pub const KERB_KEY_LIST_REQ: i32 = 161;
 pub const KERB_KEY_LIST_REP: i32 = 162;
 pub const PA_SUPPORTED_ENCTYPES: i32 = 165;
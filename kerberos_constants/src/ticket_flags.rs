@@ -21,3 +21,7 @@ pub const OK_AS_DELEGATE: u32 = 0x00040000;
 
 pub const REQUEST_ANONYMOUS: u32 = 0x00020000;
 pub const NAME_CANONICALIZE: u32 = 0x00010000;
+
+/// The KDC protected the AS-REQ with a checksum in the encrypted part of
+/// the reply, as described in RFC 6806, Section 11.
+pub const ENC_PA_REP: u32 = 0x00010000;
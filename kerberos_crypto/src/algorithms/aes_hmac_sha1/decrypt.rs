@@ -11,7 +11,8 @@ pub fn encrypt(
     plaintext: &[u8],
     preamble: &[u8],
     aes_sizes: &AesSizes,
-) -> Vec<u8> {
+) -> Result<Vec<u8>> {
+    check_key_size(key, aes_sizes)?;
     let (ki, ke) = generate_ki_ke(key, key_usage, aes_sizes);
 
     let mut basic_plaintext = preamble.to_vec();
@@ -22,7 +23,7 @@ pub fn encrypt(
     let mut ciphertext = basic_encrypt(&ke, &basic_plaintext, aes_sizes);
     ciphertext.append(&mut hmac[..aes_sizes.mac_size()].to_vec());
 
-    return ciphertext;
+    return Ok(ciphertext);
 }
 
 fn basic_encrypt(
@@ -75,6 +76,7 @@ pub fn decrypt(
     ciphertext: &[u8],
     aes_sizes: &AesSizes,
 ) -> Result<Vec<u8>> {
+    check_key_size(key, aes_sizes)?;
     let (ki, ke) = generate_ki_ke(key, key_usage, aes_sizes);
 
     if ciphertext.len() < aes_sizes.block_size() + aes_sizes.mac_size() {
@@ -128,6 +130,15 @@ fn basic_decrypt(
     return Ok(plaintext);
 }
 
+/// Checks that the key has the size required by the AES algorithm, since
+/// the AES primitives cannot be used with keys of other sizes
+pub(crate) fn check_key_size(key: &[u8], aes_sizes: &AesSizes) -> Result<()> {
+    if key.len() != aes_sizes.key_size() {
+        return Err(Error::InvalidKeyLength(aes_sizes.key_size()));
+    }
+    return Ok(());
+}
+
 fn generate_ki_ke(
     key: &[u8],
     key_usage: i32,
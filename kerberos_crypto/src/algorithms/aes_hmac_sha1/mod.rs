@@ -6,6 +6,7 @@ mod keys;
 pub use keys::{generate_key, generate_key_from_string};
 
 mod decrypt;
+pub(crate) use decrypt::check_key_size;
 pub use decrypt::{decrypt, encrypt};
 
 mod preamble;
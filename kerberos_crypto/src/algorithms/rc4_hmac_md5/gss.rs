@@ -0,0 +1,67 @@
+use crate::cryptography::{hmac_md5, rc4_encrypt};
+
+/// Encrypts or decrypts the `SND_SEQ` field of the GSS-API tokens of
+/// RC4_HMAC, keyed with the `SGN_CKSUM` field of the token, as described
+/// in RFC 4757, section 7.3.
+pub fn crypt_gss_sequence_number(
+    key: &[u8],
+    sgn_cksum: &[u8],
+    snd_seq: &[u8],
+) -> Vec<u8> {
+    let kseq = hmac_md5(key, &0i32.to_le_bytes());
+    let kseq = hmac_md5(&kseq, sgn_cksum);
+    return rc4_encrypt(&kseq, snd_seq);
+}
+
+/// Encrypts or decrypts the confounder and the data of the GSS-API Wrap
+/// tokens of RC4_HMAC, keyed with the big-endian sequence number of the
+/// token, as described in RFC 4757, section 7.3.
+pub fn crypt_gss_data(key: &[u8], seq_number: &[u8], data: &[u8]) -> Vec<u8> {
+    let klocal: Vec<u8> = key.iter().map(|byte| byte ^ 0xf0).collect();
+    let kcrypt = hmac_md5(&klocal, &0i32.to_le_bytes());
+    let kcrypt = hmac_md5(&kcrypt, seq_number);
+    return rc4_encrypt(&kcrypt, data);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn crypt_sequence_number() {
+        let snd_seq = [0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x00];
+        let encrypted = crypt_gss_sequence_number(
+            &[7; 16],
+            &[1, 2, 3, 4, 5, 6, 7, 8],
+            &snd_seq,
+        );
+
+        assert_eq!(
+            vec![0xd2, 0x6d, 0xe4, 0xae, 0xf0, 0xa7, 0x2e, 0xc4],
+            encrypted
+        );
+        assert_eq!(
+            snd_seq.to_vec(),
+            crypt_gss_sequence_number(
+                &[7; 16],
+                &[1, 2, 3, 4, 5, 6, 7, 8],
+                &encrypted
+            )
+        );
+    }
+
+    #[test]
+    fn crypt_data() {
+        assert_eq!(
+            vec![
+                0x78, 0xb3, 0x34, 0x17, 0x60, 0x08, 0x79, 0x94, 0x93, 0x23,
+                0x20, 0x99, 0x4d, 0xdf, 0x25, 0xc5
+            ],
+            crypt_gss_data(
+                &[7; 16],
+                &[0x00, 0x00, 0x00, 0x05],
+                b"confoundmessage\x01"
+            )
+        );
+    }
+}
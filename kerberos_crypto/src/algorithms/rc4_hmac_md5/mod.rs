@@ -10,3 +10,6 @@ pub use key::{generate_key, generate_key_from_string};
 
 mod preamble;
 pub use preamble::generate_preamble;
+
+mod gss;
+pub use gss::{crypt_gss_data, crypt_gss_sequence_number};
@@ -1,4 +1,61 @@
+use crate::aes_hmac_sha1::check_key_size;
 use crate::cryptography::{dk, hmac_md5, hmac_sha1, md5, AesSizes};
+use crate::{Error, Result};
+use himmelblau_kerberos_constants::checksum_types::{
+    HMAC_MD5, HMAC_SHA1_96_AES128, HMAC_SHA1_96_AES256,
+};
+use himmelblau_kerberos_constants::etypes::{
+    AES128_CTS_HMAC_SHA1_96, AES256_CTS_HMAC_SHA1_96, RC4_HMAC,
+};
+
+/// Computes a keyed checksum of the given type. The key must belong to
+/// the encryption type associated with the checksum type.
+///
+/// # Error
+/// Returns [`Error::InvalidKeyLength`] if the size of the key does not
+/// match the checksum type.
+pub fn checksum(
+    cksumtype: i32,
+    key: &[u8],
+    key_usage: i32,
+    plaintext: &[u8],
+) -> Result<Vec<u8>> {
+    match cksumtype {
+        HMAC_SHA1_96_AES256 => {
+            check_key_size(key, &AesSizes::Aes256)?;
+            return Ok(checksum_sha_aes(
+                key,
+                key_usage,
+                plaintext,
+                &AesSizes::Aes256,
+            ));
+        }
+        HMAC_SHA1_96_AES128 => {
+            check_key_size(key, &AesSizes::Aes128)?;
+            return Ok(checksum_sha_aes(
+                key,
+                key_usage,
+                plaintext,
+                &AesSizes::Aes128,
+            ));
+        }
+        HMAC_MD5 => {
+            return Ok(checksum_hmac_md5(key, key_usage, plaintext));
+        }
+        _ => return Err(Error::UnsupportedAlgorithm(cksumtype)),
+    }
+}
+
+/// Returns the checksum type that must be used with the keys of an
+/// encryption type, as specified in RFC 3962 and RFC 4757.
+pub fn mandatory_checksum_type(etype: i32) -> Result<i32> {
+    match etype {
+        AES256_CTS_HMAC_SHA1_96 => return Ok(HMAC_SHA1_96_AES256),
+        AES128_CTS_HMAC_SHA1_96 => return Ok(HMAC_SHA1_96_AES128),
+        RC4_HMAC => return Ok(HMAC_MD5),
+        _ => return Err(Error::UnsupportedAlgorithm(etype)),
+    }
+}
 
 pub fn checksum_hmac_md5(
     key: &[u8],
@@ -101,4 +158,51 @@ mod test {
             checksum_sha_aes(key, 17, blob, &AesSizes::Aes128)
         );
     }
+
+    #[test]
+    fn checksum_by_type() {
+        let key = &[
+            0x25, 0xB2, 0x07, 0x6C, 0xDA, 0x3B, 0xFD, 0x62, 0x09, 0x16, 0x1A,
+            0x6C, 0x78, 0xA6, 0x9C, 0x1C,
+        ];
+        let blob = &[0x01, 0x02, 0x03];
+
+        assert_eq!(
+            checksum_sha_aes(key, 56, blob, &AesSizes::Aes128),
+            checksum(HMAC_SHA1_96_AES128, key, 56, blob).unwrap()
+        );
+        assert_eq!(
+            checksum_hmac_md5(key, 56, blob),
+            checksum(HMAC_MD5, key, 56, blob).unwrap()
+        );
+        assert_eq!(
+            Err(Error::UnsupportedAlgorithm(7)),
+            checksum(7, key, 56, blob)
+        );
+    }
+
+    #[test]
+    fn mandatory_checksum_types() {
+        assert_eq!(
+            Ok(HMAC_SHA1_96_AES256),
+            mandatory_checksum_type(AES256_CTS_HMAC_SHA1_96)
+        );
+        assert_eq!(
+            Ok(HMAC_SHA1_96_AES128),
+            mandatory_checksum_type(AES128_CTS_HMAC_SHA1_96)
+        );
+        assert_eq!(Ok(HMAC_MD5), mandatory_checksum_type(RC4_HMAC));
+        assert_eq!(
+            Err(Error::UnsupportedAlgorithm(1)),
+            mandatory_checksum_type(1)
+        );
+    }
+
+    #[test]
+    fn test_error_with_short_aes_key() {
+        assert_eq!(
+            Err(Error::InvalidKeyLength(32)),
+            checksum(HMAC_SHA1_96_AES256, &[1, 2, 3], 17, &[0; 8])
+        );
+    }
 }
@@ -86,7 +86,12 @@ impl KerberosCipher for AesCipher {
         );
     }
 
-    fn encrypt(&self, key: &[u8], key_usage: i32, plaintext: &[u8]) -> Vec<u8> {
+    fn encrypt(
+        &self,
+        key: &[u8],
+        key_usage: i32,
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>> {
         let preamble = self.preamble();
         return aes_hmac_sha1::encrypt(
             key,
@@ -101,6 +106,7 @@ impl KerberosCipher for AesCipher {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::Error;
 
     #[test]
     fn test_aes_256_hmac_sh1_encrypt() {
@@ -116,16 +122,18 @@ mod test {
                 0x1e, 0xa0, 0x16, 0x5e, 0xbb, 0x27, 0xc0, 0xd7, 0xce, 0x9b,
                 0x5a, 0xec, 0x7a
             ],
-            aes256_cipher.generate_key_from_string_and_encrypt(
-                "admin",
-                "admin1234".as_bytes(),
-                1,
-                &[
-                    0x33, 0x61, 0x68, 0x77, 0x7a, 0x74, 0x39, 0x4d, 0x47, 0x39,
-                    0x57, 0x56, 0x45, 0x75, 0x42, 0x56, 0x43, 0x35, 0x6a, 0x30,
-                    0x6f, 0x69, 0x36, 0x73, 0x49
-                ]
-            )
+            aes256_cipher
+                .generate_key_from_string_and_encrypt(
+                    "admin",
+                    "admin1234".as_bytes(),
+                    1,
+                    &[
+                        0x33, 0x61, 0x68, 0x77, 0x7a, 0x74, 0x39, 0x4d, 0x47,
+                        0x39, 0x57, 0x56, 0x45, 0x75, 0x42, 0x56, 0x43, 0x35,
+                        0x6a, 0x30, 0x6f, 0x69, 0x36, 0x73, 0x49
+                    ]
+                )
+                .unwrap()
         );
 
         assert_eq!(
@@ -136,15 +144,17 @@ mod test {
                 0x1f, 0x99, 0x0d, 0xe2, 0xc0, 0x27, 0x66, 0x1c, 0x98, 0x33,
                 0xbc, 0xce, 0xd3
             ],
-            aes256_cipher.generate_key_from_string_and_encrypt(
-                "test",
-                "test1234".as_bytes(),
-                2,
-                &[
-                    0x6c, 0x4a, 0x33, 0x66, 0x74, 0x66, 0x77, 0x78, 0x6a, 0x73,
-                    0x52, 0x35, 0x32, 0x32, 0x4f
-                ]
-            )
+            aes256_cipher
+                .generate_key_from_string_and_encrypt(
+                    "test",
+                    "test1234".as_bytes(),
+                    2,
+                    &[
+                        0x6c, 0x4a, 0x33, 0x66, 0x74, 0x66, 0x77, 0x78, 0x6a,
+                        0x73, 0x52, 0x35, 0x32, 0x32, 0x4f
+                    ]
+                )
+                .unwrap()
         );
 
         assert_eq!(
@@ -155,15 +165,17 @@ mod test {
                 0xb5, 0x59, 0xb3, 0xdf, 0x3f, 0xa1, 0xe4, 0x33, 0x5f, 0x82,
                 0xbd, 0xd3, 0x33, 0x1b, 0x60
             ],
-            aes256_cipher.generate_key_from_string_and_encrypt(
-                "1337",
-                "13371234".as_bytes(),
-                3,
-                &[
-                    0x51, 0x42, 0x64, 0x33, 0x69, 0x71, 0x6b, 0x4b, 0x79, 0x5a,
-                    0x72, 0x35, 0x59, 0x4a, 0x62, 0x6c, 0x4e
-                ]
-            )
+            aes256_cipher
+                .generate_key_from_string_and_encrypt(
+                    "1337",
+                    "13371234".as_bytes(),
+                    3,
+                    &[
+                        0x51, 0x42, 0x64, 0x33, 0x69, 0x71, 0x6b, 0x4b, 0x79,
+                        0x5a, 0x72, 0x35, 0x59, 0x4a, 0x62, 0x6c, 0x4e
+                    ]
+                )
+                .unwrap()
         );
 
         assert_eq!(
@@ -173,15 +185,17 @@ mod test {
                 0x05, 0x22, 0xf1, 0x83, 0x95, 0x18, 0xbf, 0x62, 0x46, 0xbb,
                 0xec, 0x0d, 0x4c, 0x89, 0xb0, 0xc5, 0xb5, 0x81, 0xae
             ],
-            aes256_cipher.generate_key_from_string_and_encrypt(
-                "",
-                "1234".as_bytes(),
-                4,
-                &[
-                    0x64, 0x4d, 0x61, 0x72, 0x7a, 0x4b, 0x43, 0x4f, 0x45, 0x54,
-                    0x37
-                ]
-            )
+            aes256_cipher
+                .generate_key_from_string_and_encrypt(
+                    "",
+                    "1234".as_bytes(),
+                    4,
+                    &[
+                        0x64, 0x4d, 0x61, 0x72, 0x7a, 0x4b, 0x43, 0x4f, 0x45,
+                        0x54, 0x37
+                    ]
+                )
+                .unwrap()
         );
 
         assert_eq!(
@@ -192,15 +206,17 @@ mod test {
                 0x3f, 0x3d, 0x93, 0x26, 0x7c, 0xbd, 0x69, 0xa6, 0x24, 0x48,
                 0x09, 0x3d
             ],
-            aes256_cipher.generate_key_from_string_and_encrypt(
-                "12345678",
-                "123456781234".as_bytes(),
-                5,
-                &[
-                    0x71, 0x75, 0x65, 0x4a, 0x6d, 0x72, 0x78, 0x76, 0x50, 0x47,
-                    0x5a, 0x68, 0x6d, 0x78
-                ]
-            )
+            aes256_cipher
+                .generate_key_from_string_and_encrypt(
+                    "12345678",
+                    "123456781234".as_bytes(),
+                    5,
+                    &[
+                        0x71, 0x75, 0x65, 0x4a, 0x6d, 0x72, 0x78, 0x76, 0x50,
+                        0x47, 0x5a, 0x68, 0x6d, 0x78
+                    ]
+                )
+                .unwrap()
         );
 
         assert_eq!(
@@ -212,16 +228,18 @@ mod test {
                 0x6e, 0xf8, 0x5d, 0x09, 0xc9, 0xb8, 0x34, 0x0b, 0x93, 0xa0,
                 0xd8
             ],
-            aes256_cipher.generate_key_from_string_and_encrypt(
-                "123456789",
-                "1234567891234".as_bytes(),
-                6,
-                &[
-                    0x6d, 0x4a, 0x79, 0x31, 0x42, 0x6d, 0x74, 0x54, 0x39, 0x33,
-                    0x31, 0x56, 0x72, 0x50, 0x63, 0x6b, 0x38, 0x6c, 0x61, 0x4e,
-                    0x77, 0x32, 0x56
-                ]
-            )
+            aes256_cipher
+                .generate_key_from_string_and_encrypt(
+                    "123456789",
+                    "1234567891234".as_bytes(),
+                    6,
+                    &[
+                        0x6d, 0x4a, 0x79, 0x31, 0x42, 0x6d, 0x74, 0x54, 0x39,
+                        0x33, 0x31, 0x56, 0x72, 0x50, 0x63, 0x6b, 0x38, 0x6c,
+                        0x61, 0x4e, 0x77, 0x32, 0x56
+                    ]
+                )
+                .unwrap()
         );
     }
 
@@ -373,15 +391,17 @@ mod test {
                 0x8c, 0xcd, 0x27, 0xf4, 0x27, 0x78, 0x19, 0xa2, 0x6b, 0x27,
                 0xd9
             ],
-            aes128_cipher.generate_key_from_string_and_encrypt(
-                "admin",
-                "admin1234".as_bytes(),
-                1,
-                &[
-                    0x6c, 0x38, 0x38, 0x70, 0x53, 0x78, 0x6b, 0x4d, 0x79, 0x78,
-                    0x77, 0x68, 0x67
-                ]
-            )
+            aes128_cipher
+                .generate_key_from_string_and_encrypt(
+                    "admin",
+                    "admin1234".as_bytes(),
+                    1,
+                    &[
+                        0x6c, 0x38, 0x38, 0x70, 0x53, 0x78, 0x6b, 0x4d, 0x79,
+                        0x78, 0x77, 0x68, 0x67
+                    ]
+                )
+                .unwrap()
         );
 
         assert_eq!(
@@ -392,15 +412,17 @@ mod test {
                 0x5e, 0x66, 0x24, 0xc5, 0xa5, 0x99, 0x84, 0x6a, 0x9f, 0xed,
                 0x46, 0xfe, 0xf5, 0xd5
             ],
-            aes128_cipher.generate_key_from_string_and_encrypt(
-                "test",
-                "test1234".as_bytes(),
-                2,
-                &[
-                    0x37, 0x78, 0x58, 0x72, 0x46, 0x36, 0x49, 0x4b, 0x6b, 0x63,
-                    0x54, 0x47, 0x75, 0x6f, 0x6f, 0x4e
-                ]
-            )
+            aes128_cipher
+                .generate_key_from_string_and_encrypt(
+                    "test",
+                    "test1234".as_bytes(),
+                    2,
+                    &[
+                        0x37, 0x78, 0x58, 0x72, 0x46, 0x36, 0x49, 0x4b, 0x6b,
+                        0x63, 0x54, 0x47, 0x75, 0x6f, 0x6f, 0x4e
+                    ]
+                )
+                .unwrap()
         );
 
         assert_eq!(
@@ -410,12 +432,17 @@ mod test {
                 0x9d, 0x5b, 0x03, 0xe1, 0x8a, 0x9f, 0x29, 0xd8, 0x64, 0xb6,
                 0x6c, 0xf9, 0x16, 0xc3, 0x62, 0x61, 0xd4, 0xa3
             ],
-            aes128_cipher.generate_key_from_string_and_encrypt(
-                "1337",
-                "13371234".as_bytes(),
-                3,
-                &[0x39, 0x4e, 0x72, 0x46, 0x64, 0x74, 0x74, 0x68, 0x4d, 0x38]
-            )
+            aes128_cipher
+                .generate_key_from_string_and_encrypt(
+                    "1337",
+                    "13371234".as_bytes(),
+                    3,
+                    &[
+                        0x39, 0x4e, 0x72, 0x46, 0x64, 0x74, 0x74, 0x68, 0x4d,
+                        0x38
+                    ]
+                )
+                .unwrap()
         );
 
         assert_eq!(
@@ -427,17 +454,19 @@ mod test {
                 0xfb, 0x57, 0xb1, 0x01, 0xa1, 0x2f, 0xde, 0xc9, 0x56, 0x76,
                 0x7a, 0xe2, 0x3c, 0x56, 0x71, 0xd7, 0xf0, 0x91, 0x80
             ],
-            aes128_cipher.generate_key_from_string_and_encrypt(
-                "",
-                "1234".as_bytes(),
-                4,
-                &[
-                    0x62, 0x4a, 0x71, 0x70, 0x5a, 0x49, 0x69, 0x43, 0x45, 0x44,
-                    0x68, 0x6f, 0x78, 0x51, 0x76, 0x47, 0x58, 0x74, 0x30, 0x43,
-                    0x6c, 0x62, 0x50, 0x30, 0x36, 0x66, 0x51, 0x4f, 0x56, 0x36,
-                    0x6b
-                ]
-            )
+            aes128_cipher
+                .generate_key_from_string_and_encrypt(
+                    "",
+                    "1234".as_bytes(),
+                    4,
+                    &[
+                        0x62, 0x4a, 0x71, 0x70, 0x5a, 0x49, 0x69, 0x43, 0x45,
+                        0x44, 0x68, 0x6f, 0x78, 0x51, 0x76, 0x47, 0x58, 0x74,
+                        0x30, 0x43, 0x6c, 0x62, 0x50, 0x30, 0x36, 0x66, 0x51,
+                        0x4f, 0x56, 0x36, 0x6b
+                    ]
+                )
+                .unwrap()
         );
 
         assert_eq!(
@@ -449,16 +478,18 @@ mod test {
                 0xca, 0x62, 0xef, 0x26, 0x24, 0x54, 0x95, 0xca, 0x0c, 0x01,
                 0xfd, 0x07, 0xf1
             ],
-            aes128_cipher.generate_key_from_string_and_encrypt(
-                "12345678",
-                "123456781234".as_bytes(),
-                5,
-                &[
-                    0x42, 0x39, 0x70, 0x37, 0x77, 0x6d, 0x6f, 0x59, 0x55, 0x57,
-                    0x5a, 0x76, 0x6a, 0x6e, 0x39, 0x44, 0x55, 0x61, 0x44, 0x4c,
-                    0x51, 0x4c, 0x70, 0x5a, 0x78
-                ]
-            )
+            aes128_cipher
+                .generate_key_from_string_and_encrypt(
+                    "12345678",
+                    "123456781234".as_bytes(),
+                    5,
+                    &[
+                        0x42, 0x39, 0x70, 0x37, 0x77, 0x6d, 0x6f, 0x59, 0x55,
+                        0x57, 0x5a, 0x76, 0x6a, 0x6e, 0x39, 0x44, 0x55, 0x61,
+                        0x44, 0x4c, 0x51, 0x4c, 0x70, 0x5a, 0x78
+                    ]
+                )
+                .unwrap()
         );
 
         assert_eq!(
@@ -470,16 +501,19 @@ mod test {
                 0xf2, 0x52, 0x34, 0x18, 0x58, 0x32, 0xff, 0x29, 0x2f, 0x0d,
                 0x26, 0x32, 0x6b, 0x2b, 0x01, 0xd2, 0xe3
             ],
-            aes128_cipher.generate_key_from_string_and_encrypt(
-                "123456789",
-                "1234567891234".as_bytes(),
-                6,
-                &[
-                    0x70, 0x4d, 0x46, 0x5a, 0x56, 0x6e, 0x79, 0x36, 0x47, 0x42,
-                    0x64, 0x35, 0x48, 0x35, 0x38, 0x73, 0x76, 0x37, 0x43, 0x37,
-                    0x77, 0x51, 0x37, 0x42, 0x69, 0x30, 0x6a, 0x48, 0x70
-                ]
-            )
+            aes128_cipher
+                .generate_key_from_string_and_encrypt(
+                    "123456789",
+                    "1234567891234".as_bytes(),
+                    6,
+                    &[
+                        0x70, 0x4d, 0x46, 0x5a, 0x56, 0x6e, 0x79, 0x36, 0x47,
+                        0x42, 0x64, 0x35, 0x48, 0x35, 0x38, 0x73, 0x76, 0x37,
+                        0x43, 0x37, 0x77, 0x51, 0x37, 0x42, 0x69, 0x30, 0x6a,
+                        0x48, 0x70
+                    ]
+                )
+                .unwrap()
         );
     }
 
@@ -619,4 +653,24 @@ mod test {
                 .unwrap()
         );
     }
+
+    #[test]
+    fn test_error_decrypting_with_short_key() {
+        let aes256_cipher = AesCipher::new(AesSizes::Aes256);
+
+        assert_eq!(
+            Err(Error::InvalidKeyLength(32)),
+            aes256_cipher.decrypt(&[1, 2, 3], 2, &[0; 32])
+        );
+    }
+
+    #[test]
+    fn test_error_encrypting_with_short_key() {
+        let aes128_cipher = AesCipher::new(AesSizes::Aes128);
+
+        assert_eq!(
+            Err(Error::InvalidKeyLength(16)),
+            aes128_cipher.encrypt(&[1, 2, 3], 2, &[0; 32])
+        );
+    }
 }
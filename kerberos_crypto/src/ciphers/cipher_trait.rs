@@ -35,7 +35,12 @@ pub trait KerberosCipher {
         return self.decrypt(&key, key_usage, ciphertext);
     }
 
-    fn encrypt(&self, key: &[u8], key_usage: i32, plaintext: &[u8]) -> Vec<u8>;
+    fn encrypt(
+        &self,
+        key: &[u8],
+        key_usage: i32,
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>>;
 
     fn generate_key_and_encrypt(
         &self,
@@ -43,7 +48,7 @@ pub trait KerberosCipher {
         salt: &[u8],
         key_usage: i32,
         ciphertext: &[u8],
-    ) -> Vec<u8> {
+    ) -> Result<Vec<u8>> {
         let key = self.generate_key(raw_key, salt);
         return self.encrypt(&key, key_usage, ciphertext);
     }
@@ -54,7 +59,7 @@ pub trait KerberosCipher {
         salt: &[u8],
         key_usage: i32,
         ciphertext: &[u8],
-    ) -> Vec<u8> {
+    ) -> Result<Vec<u8>> {
         let key = self.generate_key_from_string(password, salt);
         return self.encrypt(&key, key_usage, ciphertext);
     }
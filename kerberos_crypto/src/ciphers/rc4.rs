@@ -76,19 +76,24 @@ impl KerberosCipher for Rc4Cipher {
         return rc4_hmac_md5::decrypt(key, real_key_usage, ciphertext);
     }
 
-    fn encrypt(&self, key: &[u8], key_usage: i32, plaintext: &[u8]) -> Vec<u8> {
+    fn encrypt(
+        &self,
+        key: &[u8],
+        key_usage: i32,
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>> {
         let preamble = self.preamble();
         let real_key_usage = if key_usage == 3 {
             8 // RFC 4757 rules
         } else {
             key_usage
         };
-        return rc4_hmac_md5::encrypt(
+        return Ok(rc4_hmac_md5::encrypt(
             key,
             real_key_usage,
             plaintext,
             &preamble,
-        );
+        ));
     }
 }
 
@@ -110,16 +115,18 @@ mod test {
                 0xc2, 0xea, 0xa4, 0xf9, 0x0b, 0xc9, 0x14, 0x5a, 0x18, 0x8c,
                 0x85, 0xed, 0x0b, 0xfa, 0x0f, 0x00
             ],
-            rc4_cipher.generate_key_from_string_and_encrypt(
-                "admin",
-                &Vec::new(),
-                1,
-                &[
-                    0x5a, 0x67, 0x65, 0x59, 0x30, 0x5a, 0x49, 0x65, 0x41, 0x64,
-                    0x56, 0x75, 0x72, 0x54, 0x4b, 0x39, 0x62, 0x73, 0x35, 0x6b,
-                    0x62, 0x47
-                ]
-            )
+            rc4_cipher
+                .generate_key_from_string_and_encrypt(
+                    "admin",
+                    &Vec::new(),
+                    1,
+                    &[
+                        0x5a, 0x67, 0x65, 0x59, 0x30, 0x5a, 0x49, 0x65, 0x41,
+                        0x64, 0x56, 0x75, 0x72, 0x54, 0x4b, 0x39, 0x62, 0x73,
+                        0x35, 0x6b, 0x62, 0x47
+                    ]
+                )
+                .unwrap()
         );
 
         assert_eq!(
@@ -130,15 +137,17 @@ mod test {
                 0x12, 0x40, 0x5b, 0x87, 0x31, 0xaa, 0xf2, 0x51, 0x86, 0x9e,
                 0xdb, 0xbc
             ],
-            rc4_cipher.generate_key_from_string_and_encrypt(
-                "test",
-                &Vec::new(),
-                2,
-                &[
-                    0x44, 0x4c, 0x5a, 0x4c, 0x53, 0x30, 0x35, 0x47, 0x61, 0x63,
-                    0x4c, 0x4e, 0x39, 0x54, 0x6f, 0x7a, 0x42, 0x47
-                ]
-            )
+            rc4_cipher
+                .generate_key_from_string_and_encrypt(
+                    "test",
+                    &Vec::new(),
+                    2,
+                    &[
+                        0x44, 0x4c, 0x5a, 0x4c, 0x53, 0x30, 0x35, 0x47, 0x61,
+                        0x63, 0x4c, 0x4e, 0x39, 0x54, 0x6f, 0x7a, 0x42, 0x47
+                    ]
+                )
+                .unwrap()
         );
 
         assert_eq!(
@@ -149,16 +158,18 @@ mod test {
                 0x54, 0x61, 0x58, 0xfc, 0xee, 0xa8, 0x93, 0xd3, 0xb8, 0x6e,
                 0xc7, 0x5a, 0xf6, 0xf0, 0xdb
             ],
-            rc4_cipher.generate_key_from_string_and_encrypt(
-                "1337",
-                &Vec::new(),
-                3,
-                &[
-                    0x4c, 0x48, 0x59, 0x62, 0x31, 0x42, 0x77, 0x6a, 0x53, 0x50,
-                    0x79, 0x54, 0x59, 0x6e, 0x5a, 0x43, 0x78, 0x4f, 0x65, 0x6e,
-                    0x63
-                ]
-            )
+            rc4_cipher
+                .generate_key_from_string_and_encrypt(
+                    "1337",
+                    &Vec::new(),
+                    3,
+                    &[
+                        0x4c, 0x48, 0x59, 0x62, 0x31, 0x42, 0x77, 0x6a, 0x53,
+                        0x50, 0x79, 0x54, 0x59, 0x6e, 0x5a, 0x43, 0x78, 0x4f,
+                        0x65, 0x6e, 0x63
+                    ]
+                )
+                .unwrap()
         );
 
         assert_eq!(
@@ -168,15 +179,17 @@ mod test {
                 0x1c, 0x1f, 0x07, 0x6e, 0x98, 0x45, 0x65, 0x2c, 0xbf, 0xe7,
                 0x29, 0x13, 0x2f, 0xb6, 0x65, 0x9b, 0xf2, 0x89, 0x11
             ],
-            rc4_cipher.generate_key_from_string_and_encrypt(
-                "",
-                &Vec::new(),
-                4,
-                &[
-                    0x6d, 0x55, 0x77, 0x47, 0x4f, 0x49, 0x61, 0x59, 0x69, 0x79,
-                    0x31, 0x52, 0x44, 0x66, 0x75
-                ]
-            )
+            rc4_cipher
+                .generate_key_from_string_and_encrypt(
+                    "",
+                    &Vec::new(),
+                    4,
+                    &[
+                        0x6d, 0x55, 0x77, 0x47, 0x4f, 0x49, 0x61, 0x59, 0x69,
+                        0x79, 0x31, 0x52, 0x44, 0x66, 0x75
+                    ]
+                )
+                .unwrap()
         );
 
         assert_eq!(
@@ -186,15 +199,17 @@ mod test {
                 0x99, 0x82, 0x05, 0x6a, 0x58, 0x6b, 0x66, 0xe9, 0xde, 0xbe,
                 0xe9, 0xb5, 0xbc, 0x53, 0x72, 0xdf, 0xa3, 0x9d, 0xea, 0x8d
             ],
-            rc4_cipher.generate_key_from_string_and_encrypt(
-                "12345678",
-                &Vec::new(),
-                5,
-                &[
-                    0x74, 0x57, 0x34, 0x41, 0x68, 0x36, 0x73, 0x7a, 0x6f, 0x79,
-                    0x39, 0x32, 0x68, 0x68, 0x70, 0x59
-                ]
-            )
+            rc4_cipher
+                .generate_key_from_string_and_encrypt(
+                    "12345678",
+                    &Vec::new(),
+                    5,
+                    &[
+                        0x74, 0x57, 0x34, 0x41, 0x68, 0x36, 0x73, 0x7a, 0x6f,
+                        0x79, 0x39, 0x32, 0x68, 0x68, 0x70, 0x59
+                    ]
+                )
+                .unwrap()
         );
 
         assert_eq!(
@@ -206,16 +221,19 @@ mod test {
                 0xd3, 0xfe, 0x24, 0xa4, 0x04, 0x4b, 0xc2, 0x79, 0xb9, 0x33,
                 0x39, 0xe7, 0x9c, 0x14
             ],
-            rc4_cipher.generate_key_from_string_and_encrypt(
-                "123456789",
-                &Vec::new(),
-                6,
-                &[
-                    0x69, 0x5a, 0x7a, 0x79, 0x72, 0x76, 0x74, 0x44, 0x72, 0x36,
-                    0x77, 0x6f, 0x78, 0x49, 0x68, 0x41, 0x73, 0x48, 0x4b, 0x59,
-                    0x53, 0x42, 0x63, 0x46, 0x6b, 0x6e, 0x59, 0x78, 0x41, 0x53
-                ]
-            )
+            rc4_cipher
+                .generate_key_from_string_and_encrypt(
+                    "123456789",
+                    &Vec::new(),
+                    6,
+                    &[
+                        0x69, 0x5a, 0x7a, 0x79, 0x72, 0x76, 0x74, 0x44, 0x72,
+                        0x36, 0x77, 0x6f, 0x78, 0x49, 0x68, 0x41, 0x73, 0x48,
+                        0x4b, 0x59, 0x53, 0x42, 0x63, 0x46, 0x6b, 0x6e, 0x59,
+                        0x78, 0x41, 0x53
+                    ]
+                )
+                .unwrap()
         );
     }
 
@@ -1,5 +1,6 @@
 //! Useful public functions
 
+use crate::{AES128_KEY_SIZE, AES256_KEY_SIZE, RC4_KEY_SIZE};
 use himmelblau_kerberos_constants::etypes::{
     AES128_CTS_HMAC_SHA1_96, AES256_CTS_HMAC_SHA1_96, RC4_HMAC,
 };
@@ -15,6 +16,17 @@ pub fn supported_etypes() -> Vec<i32> {
     vec![AES256_CTS_HMAC_SHA1_96, AES128_CTS_HMAC_SHA1_96, RC4_HMAC]
 }
 
+/// Returns the size of the keys of an encryption type, or `None` if the
+/// encryption type is not supported by this library
+pub fn key_size(etype: i32) -> Option<usize> {
+    return match etype {
+        AES256_CTS_HMAC_SHA1_96 => Some(AES256_KEY_SIZE),
+        AES128_CTS_HMAC_SHA1_96 => Some(AES128_KEY_SIZE),
+        RC4_HMAC => Some(RC4_KEY_SIZE),
+        _ => None,
+    };
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -37,4 +49,12 @@ mod test {
             )
         );
     }
+
+    #[test]
+    fn key_sizes() {
+        assert_eq!(Some(32), key_size(AES256_CTS_HMAC_SHA1_96));
+        assert_eq!(Some(16), key_size(AES128_CTS_HMAC_SHA1_96));
+        assert_eq!(Some(16), key_size(RC4_HMAC));
+        assert_eq!(None, key_size(DES_CBC_MD5));
+    }
 }
@@ -24,13 +24,15 @@ mod error;
 pub use error::{Error, Result};
 
 mod checksum;
-pub use checksum::{checksum_hmac_md5, checksum_sha_aes};
+pub use checksum::{
+    checksum, checksum_hmac_md5, checksum_sha_aes, mandatory_checksum_type,
+};
 
 mod ciphers;
 pub use ciphers::{new_kerberos_cipher, AesCipher, KerberosCipher, Rc4Cipher};
 
 mod helpers;
-pub use helpers::{is_supported_etype, supported_etypes};
+pub use helpers::{is_supported_etype, key_size, supported_etypes};
 
 mod key;
 pub use key::Key;
@@ -60,7 +60,7 @@ impl KeytabEntry {
 
         // negative size means that the entry is deleted
         if size < 0 {
-            let (raw, _) = take(-size as usize)(raw)?;
+            let (raw, _) = take(size.unsigned_abs() as usize)(raw)?;
             return Ok((raw, Self::default()));
         }
 
@@ -249,4 +249,18 @@ mod tests {
 
         assert_eq!(RAW_ENTRY_NO_VNO.to_vec(), entry.build());
     }
+
+    #[test]
+    fn test_parse_deleted_keytab_entry() {
+        let (rest, entry) =
+            KeytabEntry::parse(&[0xff, 0xff, 0xff, 0xfe, 0x00, 0x00, 0x01])
+                .unwrap();
+        assert_eq!(KeytabEntry::default(), entry);
+        assert_eq!(&[0x01], rest);
+    }
+
+    #[test]
+    fn test_parse_keytab_entry_with_min_size() {
+        assert!(KeytabEntry::parse(&[0x80, 0x00, 0x00, 0x00]).is_err());
+    }
 }
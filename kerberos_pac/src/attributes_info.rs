@@ -0,0 +1,127 @@
+use crate::Sid;
+use nom::multi::count;
+use nom::number::complete::le_u32;
+use nom::IResult;
+
+/// The client requested the PAC explicitly.
+pub const PAC_WAS_REQUESTED: u32 = 0x00000001;
+/// The PAC was included without being requested.
+pub const PAC_WAS_GIVEN_IMPLICITLY: u32 = 0x00000002;
+
+/// Attributes of the PAC, included in the `PAC_ATTRIBUTES_INFO` buffer.
+///
+/// # Definition
+/// ```c
+/// typedef struct _PAC_ATTRIBUTES_INFO {
+///     ULONG FlagsLength;
+///     ULONG Flags[1];
+/// } PAC_ATTRIBUTES_INFO;
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct PacAttributesInfo {
+    /// Number of bits of the flags.
+    pub flags_length: u32,
+    pub flags: Vec<u32>,
+}
+
+impl PacAttributesInfo {
+    pub fn new(flags_length: u32, flags: Vec<u32>) -> Self {
+        return Self {
+            flags_length,
+            flags,
+        };
+    }
+
+    /// Whether the client requested the PAC explicitly.
+    pub fn was_requested(&self) -> bool {
+        return self.first_flags() & PAC_WAS_REQUESTED != 0;
+    }
+
+    /// Whether the PAC was included without being requested.
+    pub fn was_given_implicitly(&self) -> bool {
+        return self.first_flags() & PAC_WAS_GIVEN_IMPLICITLY != 0;
+    }
+
+    fn first_flags(&self) -> u32 {
+        return self.flags.first().cloned().unwrap_or(0);
+    }
+
+    /// Build the binary representation
+    pub fn build(&self) -> Vec<u8> {
+        let mut bytes = self.flags_length.to_le_bytes().to_vec();
+        for flags in self.flags.iter() {
+            bytes.extend_from_slice(&flags.to_le_bytes());
+        }
+        return bytes;
+    }
+
+    /// Creates a new instance from the binary representation
+    /// # Error
+    /// Returns error when the binary has not the expected format.
+    pub fn parse(raw: &[u8]) -> IResult<&[u8], Self> {
+        let (raw, flags_length) = le_u32(raw)?;
+        let words = (flags_length as usize).div_ceil(32);
+        let (raw, flags) = count(le_u32, words)(raw)?;
+        return Ok((raw, Self::new(flags_length, flags)));
+    }
+}
+
+/// SID of the client that requested the ticket, included by the KDC in
+/// the `PAC_REQUESTOR` buffer so the services can check that the PAC
+/// belongs to the client of the ticket.
+///
+/// # Definition
+/// ```c
+/// typedef struct _PAC_REQUESTOR {
+///     SID Sid;
+/// } PAC_REQUESTOR;
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct PacRequestor {
+    pub sid: Sid,
+}
+
+impl PacRequestor {
+    pub fn new(sid: Sid) -> Self {
+        return Self { sid };
+    }
+
+    /// Build the binary representation
+    pub fn build(&self) -> Vec<u8> {
+        return self.sid.build();
+    }
+
+    /// Creates a new instance from the binary representation
+    /// # Error
+    /// Returns error when the binary has not the expected format.
+    pub fn parse(raw: &[u8]) -> IResult<&[u8], Self> {
+        let (raw, sid) = Sid::parse(raw)?;
+        return Ok((raw, Self::new(sid)));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_and_build_attributes_info() {
+        let raw = vec![0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00];
+
+        let attributes_info = PacAttributesInfo::parse(&raw).unwrap().1;
+
+        assert_eq!(PacAttributesInfo::new(2, vec![1]), attributes_info);
+        assert!(attributes_info.was_requested());
+        assert!(!attributes_info.was_given_implicitly());
+        assert_eq!(raw, attributes_info.build());
+    }
+
+    #[test]
+    fn parse_and_build_requestor() {
+        let sid = Sid::new(1, [0, 0, 0, 0, 0, 5], vec![21, 1, 2, 3, 1105]);
+
+        let raw = PacRequestor::new(sid.clone()).build();
+
+        assert_eq!(sid, PacRequestor::parse(&raw).unwrap().1.sid);
+    }
+}
@@ -0,0 +1,516 @@
+use crate::ndr::{NdrReader, NdrWriter};
+use crate::{Error, Result};
+
+/// The claims set is not compressed.
+pub const COMPRESSION_FORMAT_NONE: u16 = 0;
+pub const COMPRESSION_FORMAT_LZNT1: u16 = 2;
+pub const COMPRESSION_FORMAT_XPRESS: u16 = 3;
+pub const COMPRESSION_FORMAT_XPRESS_HUFF: u16 = 4;
+
+pub const CLAIM_TYPE_INT64: u16 = 1;
+pub const CLAIM_TYPE_UINT64: u16 = 2;
+pub const CLAIM_TYPE_STRING: u16 = 3;
+pub const CLAIM_TYPE_BOOLEAN: u16 = 6;
+
+/// Claims issued from the attributes of the account in Active Directory.
+pub const CLAIMS_SOURCE_TYPE_AD: u16 = 1;
+/// Claims issued from the certificate of the account.
+pub const CLAIMS_SOURCE_TYPE_CERTIFICATE: u16 = 2;
+
+/// Claims of the client or the device, included in the
+/// `PAC_CLIENT_CLAIMS_INFO` and `PAC_DEVICE_CLAIMS_INFO` buffers. The
+/// claims set is a NDR encoded [`ClaimsSet`], that may be compressed.
+///
+/// # Definition
+/// ```c
+/// typedef struct _CLAIMS_SET_METADATA {
+///     ULONG ulClaimsSetSize;
+///     [size_is(ulClaimsSetSize)] BYTE* ClaimsSet;
+///     CLAIMS_COMPRESSION_FORMAT usCompressionFormat;
+///     ULONG ulUncompressedClaimsSetSize;
+///     USHORT usReservedType;
+///     ULONG ulReservedFieldSize;
+///     [size_is(ulReservedFieldSize)] BYTE* ReservedField;
+/// } CLAIMS_SET_METADATA;
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ClaimsSetMetadata {
+    pub claims_set: Vec<u8>,
+    pub compression_format: u16,
+    pub uncompressed_claims_set_size: u32,
+    pub reserved_type: u16,
+    pub reserved_field: Vec<u8>,
+}
+
+impl ClaimsSetMetadata {
+    /// Creates the metadata of an uncompressed claims set.
+    pub fn new(claims_set: &ClaimsSet) -> Self {
+        let claims_set = claims_set.build();
+        return Self {
+            uncompressed_claims_set_size: claims_set.len() as u32,
+            claims_set,
+            ..Default::default()
+        };
+    }
+
+    /// Decodes the claims set.
+    ///
+    /// # Error
+    /// Returns [`Error::UnsupportedCompression`] if the claims set is
+    /// compressed.
+    pub fn claims(&self) -> Result<ClaimsSet> {
+        if self.compression_format != COMPRESSION_FORMAT_NONE {
+            return Err(Error::UnsupportedCompression(self.compression_format));
+        }
+        return ClaimsSet::parse(&self.claims_set);
+    }
+
+    /// Creates a new instance from the NDR representation
+    /// # Error
+    /// Returns error when the binary has not the expected format.
+    pub fn parse(raw: &[u8]) -> Result<Self> {
+        let mut reader = NdrReader::new(raw)?;
+        let r = &mut reader;
+
+        let claims_set_size = r.u32()?;
+        let claims_set = r.pointer()?;
+        let compression_format = r.u16()?;
+        let uncompressed_claims_set_size = r.u32()?;
+        let reserved_type = r.u16()?;
+        let reserved_field_size = r.u32()?;
+        let reserved_field = r.pointer()?;
+
+        return Ok(Self {
+            claims_set: r.conformant_bytes(claims_set, claims_set_size)?,
+            compression_format,
+            uncompressed_claims_set_size,
+            reserved_type,
+            reserved_field: r
+                .conformant_bytes(reserved_field, reserved_field_size)?,
+        });
+    }
+
+    /// Build the NDR representation
+    pub fn build(&self) -> Vec<u8> {
+        let mut writer = NdrWriter::new();
+        let w = &mut writer;
+
+        w.u32(self.claims_set.len() as u32);
+        w.pointer(!self.claims_set.is_empty());
+        w.u16(self.compression_format);
+        w.u32(self.uncompressed_claims_set_size);
+        w.u16(self.reserved_type);
+        w.u32(self.reserved_field.len() as u32);
+        w.pointer(!self.reserved_field.is_empty());
+        w.conformant_bytes(&self.claims_set);
+        w.conformant_bytes(&self.reserved_field);
+
+        return writer.build();
+    }
+}
+
+/// Set of claims, grouped by their source.
+///
+/// # Definition
+/// ```c
+/// typedef struct _CLAIMS_SET {
+///     ULONG ulClaimsArrayCount;
+///     [size_is(ulClaimsArrayCount)] PCLAIMS_ARRAY ClaimsArrays;
+///     USHORT usReservedType;
+///     ULONG ulReservedFieldSize;
+///     [size_is(ulReservedFieldSize)] BYTE* ReservedField;
+/// } CLAIMS_SET;
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ClaimsSet {
+    pub claims_arrays: Vec<ClaimsArray>,
+    pub reserved_type: u16,
+    pub reserved_field: Vec<u8>,
+}
+
+/// Claims of the same source.
+///
+/// # Definition
+/// ```c
+/// typedef struct _CLAIMS_ARRAY {
+///     CLAIMS_SOURCE_TYPE usClaimsSourceType;
+///     ULONG ulClaimsCount;
+///     [size_is(ulClaimsCount)] PCLAIM_ENTRY ClaimEntries;
+/// } CLAIMS_ARRAY;
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ClaimsArray {
+    pub claims_source_type: u16,
+    pub claim_entries: Vec<ClaimEntry>,
+}
+
+/// Claim, identified by its name, with its values.
+///
+/// # Definition
+/// ```c
+/// typedef struct _CLAIM_ENTRY {
+///     CLAIM_ID Id;
+///     CLAIM_TYPE Type;
+///     [switch_is(Type)] union {
+///         [case(CLAIM_TYPE_INT64)] struct {
+///             ULONG ValueCount;
+///             [size_is(ValueCount)] LONG64* Int64Values;
+///         };
+///         [case(CLAIM_TYPE_UINT64)] struct {
+///             ULONG ValueCount;
+///             [size_is(ValueCount)] ULONG64* Uint64Values;
+///         };
+///         [case(CLAIM_TYPE_STRING)] struct {
+///             ULONG ValueCount;
+///             [size_is(ValueCount)] LPWSTR* StringValues;
+///         };
+///         [case(CLAIM_TYPE_BOOLEAN)] struct {
+///             ULONG ValueCount;
+///             [size_is(ValueCount)] ULONG64* BooleanValues;
+///         };
+///     } Values;
+/// } CLAIM_ENTRY;
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct ClaimEntry {
+    pub id: String,
+    pub values: ClaimValues,
+}
+
+impl ClaimEntry {
+    pub fn new(id: String, values: ClaimValues) -> Self {
+        return Self { id, values };
+    }
+}
+
+/// Values of a claim, by type.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ClaimValues {
+    Int64(Vec<i64>),
+    UInt64(Vec<u64>),
+    String(Vec<String>),
+    Boolean(Vec<bool>),
+}
+
+impl ClaimValues {
+    /// Value of the `CLAIM_TYPE` of the claim.
+    pub fn claim_type(&self) -> u16 {
+        return match self {
+            ClaimValues::Int64(_) => CLAIM_TYPE_INT64,
+            ClaimValues::UInt64(_) => CLAIM_TYPE_UINT64,
+            ClaimValues::String(_) => CLAIM_TYPE_STRING,
+            ClaimValues::Boolean(_) => CLAIM_TYPE_BOOLEAN,
+        };
+    }
+
+    pub fn len(&self) -> usize {
+        return match self {
+            ClaimValues::Int64(values) => values.len(),
+            ClaimValues::UInt64(values) => values.len(),
+            ClaimValues::String(values) => values.len(),
+            ClaimValues::Boolean(values) => values.len(),
+        };
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.len() == 0;
+    }
+}
+
+/// Scalar fields of a claim entry, whose referents are deferred.
+struct ClaimEntryHeader {
+    id: u32,
+    claim_type: u16,
+    value_count: u32,
+    values: u32,
+}
+
+impl ClaimsSet {
+    /// Claims of all the sources.
+    pub fn claims(&self) -> impl Iterator<Item = &ClaimEntry> {
+        return self
+            .claims_arrays
+            .iter()
+            .flat_map(|claims_array| claims_array.claim_entries.iter());
+    }
+
+    /// Creates a new instance from the NDR representation
+    /// # Error
+    /// Returns error when the binary has not the expected format.
+    pub fn parse(raw: &[u8]) -> Result<Self> {
+        let mut reader = NdrReader::new(raw)?;
+        let r = &mut reader;
+
+        let claims_array_count = r.u32()?;
+        let claims_arrays = r.pointer()?;
+        let reserved_type = r.u16()?;
+        let reserved_field_size = r.u32()?;
+        let reserved_field = r.pointer()?;
+
+        let mut claims_set = Self {
+            reserved_type,
+            ..Default::default()
+        };
+
+        if claims_arrays != 0 {
+            let count = r.conformant_count(claims_array_count)?;
+            let mut headers = Vec::with_capacity(r.capacity(count, 12));
+            for _ in 0..count {
+                headers.push((r.u16()?, r.u32()?, r.pointer()?));
+            }
+
+            for (claims_source_type, claims_count, claim_entries) in headers {
+                claims_set.claims_arrays.push(ClaimsArray {
+                    claims_source_type,
+                    claim_entries: read_claim_entries(
+                        r,
+                        claim_entries,
+                        claims_count,
+                    )?,
+                });
+            }
+        }
+
+        claims_set.reserved_field =
+            r.conformant_bytes(reserved_field, reserved_field_size)?;
+        return Ok(claims_set);
+    }
+
+    /// Build the NDR representation
+    pub fn build(&self) -> Vec<u8> {
+        let mut writer = NdrWriter::new();
+        let w = &mut writer;
+
+        w.u32(self.claims_arrays.len() as u32);
+        w.pointer(!self.claims_arrays.is_empty());
+        w.u16(self.reserved_type);
+        w.u32(self.reserved_field.len() as u32);
+        w.pointer(!self.reserved_field.is_empty());
+
+        if !self.claims_arrays.is_empty() {
+            w.u32(self.claims_arrays.len() as u32);
+            for claims_array in self.claims_arrays.iter() {
+                w.u16(claims_array.claims_source_type);
+                w.u32(claims_array.claim_entries.len() as u32);
+                w.pointer(!claims_array.claim_entries.is_empty());
+            }
+            for claims_array in self.claims_arrays.iter() {
+                write_claim_entries(w, &claims_array.claim_entries);
+            }
+        }
+
+        w.conformant_bytes(&self.reserved_field);
+        return writer.build();
+    }
+}
+
+fn read_claim_entries(
+    r: &mut NdrReader,
+    pointer: u32,
+    count: u32,
+) -> Result<Vec<ClaimEntry>> {
+    if pointer == 0 {
+        return Ok(Vec::new());
+    }
+
+    let count = r.conformant_count(count)?;
+    let mut headers = Vec::with_capacity(r.capacity(count, 16));
+    for _ in 0..count {
+        let id = r.pointer()?;
+        let claim_type = r.u16()?;
+        let switch = r.u16()?;
+        if switch != claim_type {
+            return Err(Error::InvalidNdr(format!(
+                "claim of type {} with values of type {}",
+                claim_type, switch
+            )));
+        }
+        headers.push(ClaimEntryHeader {
+            id,
+            claim_type,
+            value_count: r.u32()?,
+            values: r.pointer()?,
+        });
+    }
+
+    let mut entries = Vec::with_capacity(headers.len());
+    for header in headers {
+        let id = if header.id != 0 {
+            r.wide_string()?
+        } else {
+            String::new()
+        };
+        entries.push(ClaimEntry::new(id, read_claim_values(r, &header)?));
+    }
+    return Ok(entries);
+}
+
+fn read_claim_values(
+    r: &mut NdrReader,
+    header: &ClaimEntryHeader,
+) -> Result<ClaimValues> {
+    let count = if header.values != 0 {
+        r.conformant_count(header.value_count)?
+    } else {
+        0
+    };
+
+    let values = match header.claim_type {
+        CLAIM_TYPE_INT64 => ClaimValues::Int64(
+            (0..count)
+                .map(|_| r.u64().map(|value| value as i64))
+                .collect::<Result<_>>()?,
+        ),
+        CLAIM_TYPE_UINT64 => ClaimValues::UInt64(
+            (0..count).map(|_| r.u64()).collect::<Result<_>>()?,
+        ),
+        CLAIM_TYPE_BOOLEAN => ClaimValues::Boolean(
+            (0..count)
+                .map(|_| r.u64().map(|value| value != 0))
+                .collect::<Result<_>>()?,
+        ),
+        CLAIM_TYPE_STRING => {
+            let pointers = (0..count)
+                .map(|_| r.pointer())
+                .collect::<Result<Vec<u32>>>()?;
+            let mut strings = Vec::with_capacity(pointers.len());
+            for pointer in pointers {
+                strings.push(if pointer != 0 {
+                    r.wide_string()?
+                } else {
+                    String::new()
+                });
+            }
+            ClaimValues::String(strings)
+        }
+        claim_type => {
+            return Err(Error::InvalidNdr(format!(
+                "unknown claim type {}",
+                claim_type
+            )))
+        }
+    };
+    return Ok(values);
+}
+
+fn write_claim_entries(w: &mut NdrWriter, entries: &[ClaimEntry]) {
+    if entries.is_empty() {
+        return;
+    }
+
+    w.u32(entries.len() as u32);
+    for entry in entries.iter() {
+        w.pointer(true);
+        w.u16(entry.values.claim_type());
+        w.u16(entry.values.claim_type());
+        w.u32(entry.values.len() as u32);
+        w.pointer(!entry.values.is_empty());
+    }
+
+    for entry in entries.iter() {
+        w.wide_string(&entry.id);
+        if entry.values.is_empty() {
+            continue;
+        }
+
+        w.u32(entry.values.len() as u32);
+        match &entry.values {
+            ClaimValues::Int64(values) => {
+                values.iter().for_each(|value| w.u64(*value as u64))
+            }
+            ClaimValues::UInt64(values) => {
+                values.iter().for_each(|value| w.u64(*value))
+            }
+            ClaimValues::Boolean(values) => {
+                values.iter().for_each(|value| w.u64(*value as u64))
+            }
+            ClaimValues::String(values) => {
+                values.iter().for_each(|_| w.pointer(true));
+                values.iter().for_each(|value| w.wide_string(value));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn create_claims_set() -> ClaimsSet {
+        return ClaimsSet {
+            claims_arrays: vec![ClaimsArray {
+                claims_source_type: CLAIMS_SOURCE_TYPE_AD,
+                claim_entries: vec![
+                    ClaimEntry::new(
+                        "ad://ext/department".to_string(),
+                        ClaimValues::String(vec![
+                            "Magic".to_string(),
+                            "Castles".to_string(),
+                        ]),
+                    ),
+                    ClaimEntry::new(
+                        "ad://ext/level".to_string(),
+                        ClaimValues::Int64(vec![-1, 42]),
+                    ),
+                    ClaimEntry::new(
+                        "ad://ext/badges".to_string(),
+                        ClaimValues::UInt64(vec![7]),
+                    ),
+                    ClaimEntry::new(
+                        "ad://ext/king".to_string(),
+                        ClaimValues::Boolean(vec![true]),
+                    ),
+                ],
+            }],
+            ..Default::default()
+        };
+    }
+
+    #[test]
+    fn build_and_parse_claims() {
+        let claims_set = create_claims_set();
+        let metadata = ClaimsSetMetadata::new(&claims_set);
+
+        let metadata = ClaimsSetMetadata::parse(&metadata.build()).unwrap();
+
+        assert_eq!(claims_set, metadata.claims().unwrap());
+        assert_eq!(4, metadata.claims().unwrap().claims().count());
+    }
+
+    #[test]
+    fn error_with_compressed_claims() {
+        let mut metadata = ClaimsSetMetadata::new(&create_claims_set());
+        metadata.compression_format = COMPRESSION_FORMAT_XPRESS_HUFF;
+
+        assert_eq!(
+            Err(Error::UnsupportedCompression(
+                COMPRESSION_FORMAT_XPRESS_HUFF
+            )),
+            metadata.claims()
+        );
+    }
+
+    #[test]
+    fn error_parsing_claims_with_malformed_counts() {
+        let mut writer = NdrWriter::new();
+        writer.u32(u32::MAX);
+        writer.pointer(true);
+        writer.u16(0);
+        writer.u32(0);
+        writer.pointer(false);
+        writer.u32(u32::MAX);
+        writer.u16(CLAIMS_SOURCE_TYPE_AD);
+        let raw = writer.build();
+        assert!(matches!(ClaimsSet::parse(&raw), Err(Error::InvalidNdr(_))));
+
+        let mut writer = NdrWriter::new();
+        writer.u32(u32::MAX);
+        writer.pointer(true);
+        let raw = writer.build();
+        let mut reader = NdrReader::new(&raw).unwrap();
+        assert!(matches!(
+            read_claim_entries(&mut reader, 1, u32::MAX),
+            Err(Error::InvalidNdr(_))
+        ));
+    }
+}
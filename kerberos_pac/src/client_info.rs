@@ -0,0 +1,79 @@
+use nom::bytes::complete::take;
+use nom::number::complete::{le_u16, le_u64};
+use nom::IResult;
+
+/// Name of the client and time of the authentication, included in the
+/// `PAC_CLIENT_INFO` buffer, to check that the PAC belongs to the ticket.
+///
+/// # Definition
+/// ```c
+/// typedef struct _PAC_CLIENT_INFO {
+///     FILETIME ClientId;
+///     USHORT NameLength;
+///     WCHAR Name[1];
+/// } PAC_CLIENT_INFO;
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct PacClientInfo {
+    /// Authentication time of the ticket, as FILETIME.
+    pub client_id: u64,
+    pub name: String,
+}
+
+impl PacClientInfo {
+    pub fn new(client_id: u64, name: String) -> Self {
+        return Self { client_id, name };
+    }
+
+    /// Build the binary representation
+    pub fn build(&self) -> Vec<u8> {
+        let name: Vec<u8> = self
+            .name
+            .encode_utf16()
+            .flat_map(|character| character.to_le_bytes())
+            .collect();
+
+        let mut bytes = self.client_id.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(&name);
+        return bytes;
+    }
+
+    /// Creates a new instance from the binary representation
+    /// # Error
+    /// Returns error when the binary has not the expected format.
+    pub fn parse(raw: &[u8]) -> IResult<&[u8], Self> {
+        let (raw, client_id) = le_u64(raw)?;
+        let (raw, name_length) = le_u16(raw)?;
+        let (raw, name) = take(name_length as usize)(raw)?;
+
+        return Ok((raw, Self::new(client_id, utf16le_to_string(name))));
+    }
+}
+
+/// Decodes a string of UTF-16 little-endian characters.
+pub(crate) fn utf16le_to_string(raw: &[u8]) -> String {
+    let characters: Vec<u16> = raw
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    return String::from_utf16_lossy(&characters);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_and_build_client_info() {
+        let raw = vec![
+            0x00, 0x5f, 0x3e, 0x2b, 0x4c, 0x6a, 0xd7, 0x01, 0x0c, 0x00, 0x6d,
+            0x00, 0x69, 0x00, 0x63, 0x00, 0x6b, 0x00, 0x65, 0x00, 0x79, 0x00,
+        ];
+        let client_info =
+            PacClientInfo::new(0x01d76a4c2b3e5f00, "mickey".to_string());
+
+        assert_eq!(client_info, PacClientInfo::parse(&raw).unwrap().1);
+        assert_eq!(raw, client_info.build());
+    }
+}
@@ -0,0 +1,40 @@
+//! Errors raised by this library
+
+use std::result;
+use thiserror::Error;
+
+/// Result of the operations of this library.
+pub type Result<T> = result::Result<T, Error>;
+
+/// Type of error in this library.
+#[derive(Clone, PartialEq, Debug, Error)]
+pub enum Error {
+    /// The PACTYPE structure or its buffer descriptors are not valid.
+    #[error("Invalid PAC: {}", _0)]
+    InvalidPac(String),
+
+    /// A buffer of the PAC does not have the format of its type.
+    #[error("Invalid PAC buffer of type {}: {}", _0, _1)]
+    InvalidBuffer(u32, String),
+
+    /// Error decoding NDR data.
+    #[error("Invalid NDR data: {}", _0)]
+    InvalidNdr(String),
+
+    /// The PAC does not include a required buffer.
+    #[error("PAC buffer of type {} not found", _0)]
+    MissingBuffer(u32),
+
+    /// The signature of the PAC does not match its data.
+    #[error("Invalid PAC signature of type {}", _0)]
+    InvalidSignature(u32),
+
+    /// The signature type is unknown, or cannot be computed with the
+    /// given key.
+    #[error("Unsupported PAC signature type {} for a key of etype {}", _0, _1)]
+    UnsupportedSignature(i32, i32),
+
+    /// The claims are compressed with an unsupported format.
+    #[error("Unsupported claims compression format: {}", _0)]
+    UnsupportedCompression(u16),
+}
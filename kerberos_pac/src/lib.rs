@@ -0,0 +1,53 @@
+//! Types of the Privilege Attribute Certificate (PAC) included by Active
+//! Directory in the authorization data of the Kerberos tickets.
+//!
+//! The PAC contains the SIDs of the user and its groups, so the services
+//! can authorize the user without querying the domain controller.
+//!
+//! # Example
+//! Get the groups of a user from the data of an `AD-WIN2K-PAC` element:
+//! ```no_run
+//! use himmelblau_kerberos_pac::Pac;
+//!
+//! # let data = vec![];
+//! let pac = Pac::parse(&data).expect("Unable to parse the PAC");
+//!
+//! if let Some(logon_info) = pac.logon_info().expect("Invalid logon info") {
+//!     for sid in logon_info.group_sids() {
+//!         println!("{}", sid);
+//!     }
+//! }
+//! ```
+//! # References
+//! * [MS-PAC](https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-pac)
+//! * [MS-RPCE, type serialization](https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rpce)
+//!
+
+mod error;
+pub use error::{Error, Result};
+
+mod ndr;
+
+mod sid;
+pub use sid::Sid;
+
+mod pac_type;
+pub use pac_type::*;
+
+mod logon_info;
+pub use logon_info::*;
+
+mod client_info;
+pub use client_info::PacClientInfo;
+
+mod upn_dns_info;
+pub use upn_dns_info::*;
+
+mod attributes_info;
+pub use attributes_info::*;
+
+mod claims;
+pub use claims::*;
+
+mod signature;
+pub use signature::*;
@@ -0,0 +1,477 @@
+use crate::ndr::{NdrReader, NdrWriter};
+use crate::{Result, Sid};
+
+/// Attribute of a group which is enabled for the user.
+pub const SE_GROUP_ENABLED: u32 = 0x00000004;
+/// Attribute of a group which is enabled by default.
+pub const SE_GROUP_ENABLED_BY_DEFAULT: u32 = 0x00000002;
+/// Attribute of a group which cannot be disabled.
+pub const SE_GROUP_MANDATORY: u32 = 0x00000001;
+
+/// Flag of `user_flags` set when the extra SIDs are present.
+pub const LOGON_EXTRA_SIDS: u32 = 0x00000020;
+/// Flag of `user_flags` set when the resource groups are present.
+pub const LOGON_RESOURCE_GROUPS: u32 = 0x00000200;
+
+/// Group of the domain of the user, identified by its relative id.
+///
+/// # Definition
+/// ```c
+/// typedef struct _GROUP_MEMBERSHIP {
+///     ULONG RelativeId;
+///     ULONG Attributes;
+/// } GROUP_MEMBERSHIP;
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct GroupMembership {
+    pub relative_id: u32,
+    pub attributes: u32,
+}
+
+impl GroupMembership {
+    pub fn new(relative_id: u32, attributes: u32) -> Self {
+        return Self {
+            relative_id,
+            attributes,
+        };
+    }
+}
+
+/// SID of a group outside the domain of the user.
+///
+/// # Definition
+/// ```c
+/// typedef struct _KERB_SID_AND_ATTRIBUTES {
+///     PISID Sid;
+///     ULONG Attributes;
+/// } KERB_SID_AND_ATTRIBUTES;
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct SidAndAttributes {
+    pub sid: Sid,
+    pub attributes: u32,
+}
+
+impl SidAndAttributes {
+    pub fn new(sid: Sid, attributes: u32) -> Self {
+        return Self { sid, attributes };
+    }
+}
+
+/// Logon information of the user, included in the `PAC_LOGON_INFO`
+/// buffer. It contains the SIDs of the user and its groups. Times are
+/// expressed as FILETIME, in 100-nanosecond intervals since January 1,
+/// 1601.
+///
+/// # Definition
+/// ```c
+/// typedef struct _KERB_VALIDATION_INFO {
+///     FILETIME LogonTime;
+///     FILETIME LogoffTime;
+///     FILETIME KickOffTime;
+///     FILETIME PasswordLastSet;
+///     FILETIME PasswordCanChange;
+///     FILETIME PasswordMustChange;
+///     RPC_UNICODE_STRING EffectiveName;
+///     RPC_UNICODE_STRING FullName;
+///     RPC_UNICODE_STRING LogonScript;
+///     RPC_UNICODE_STRING ProfilePath;
+///     RPC_UNICODE_STRING HomeDirectory;
+///     RPC_UNICODE_STRING HomeDirectoryDrive;
+///     USHORT LogonCount;
+///     USHORT BadPasswordCount;
+///     ULONG UserId;
+///     ULONG PrimaryGroupId;
+///     ULONG GroupCount;
+///     [size_is(GroupCount)] PGROUP_MEMBERSHIP GroupIds;
+///     ULONG UserFlags;
+///     USER_SESSION_KEY UserSessionKey;
+///     RPC_UNICODE_STRING LogonServer;
+///     RPC_UNICODE_STRING LogonDomainName;
+///     PISID LogonDomainId;
+///     ULONG Reserved1[2];
+///     ULONG UserAccountControl;
+///     ULONG SubAuthStatus;
+///     FILETIME LastSuccessfulILogon;
+///     FILETIME LastFailedILogon;
+///     ULONG FailedILogonCount;
+///     ULONG Reserved3;
+///     ULONG SidCount;
+///     [size_is(SidCount)] PKERB_SID_AND_ATTRIBUTES ExtraSids;
+///     PISID ResourceGroupDomainSid;
+///     ULONG ResourceGroupCount;
+///     [size_is(ResourceGroupCount)] PGROUP_MEMBERSHIP ResourceGroupIds;
+/// } KERB_VALIDATION_INFO;
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct KerbValidationInfo {
+    pub logon_time: u64,
+    pub logoff_time: u64,
+    pub kick_off_time: u64,
+    pub password_last_set: u64,
+    pub password_can_change: u64,
+    pub password_must_change: u64,
+    pub effective_name: String,
+    pub full_name: String,
+    pub logon_script: String,
+    pub profile_path: String,
+    pub home_directory: String,
+    pub home_directory_drive: String,
+    pub logon_count: u16,
+    pub bad_password_count: u16,
+    pub user_id: u32,
+    pub primary_group_id: u32,
+    pub group_ids: Vec<GroupMembership>,
+    pub user_flags: u32,
+    pub user_session_key: [u8; 16],
+    pub logon_server: String,
+    pub logon_domain_name: String,
+    pub logon_domain_id: Option<Sid>,
+    pub user_account_control: u32,
+    pub sub_auth_status: u32,
+    pub last_successful_i_logon: u64,
+    pub last_failed_i_logon: u64,
+    pub failed_i_logon_count: u32,
+    pub extra_sids: Vec<SidAndAttributes>,
+    pub resource_group_domain_sid: Option<Sid>,
+    pub resource_group_ids: Vec<GroupMembership>,
+}
+
+impl KerbValidationInfo {
+    /// SID of the user, in the domain of the logon.
+    pub fn user_sid(&self) -> Option<Sid> {
+        return self
+            .logon_domain_id
+            .as_ref()
+            .map(|domain| domain.with_rid(self.user_id));
+    }
+
+    /// SIDs of all the groups of the user: the primary group and the
+    /// other groups of its domain, the extra SIDs and the resource groups.
+    pub fn group_sids(&self) -> Vec<Sid> {
+        let mut sids = Vec::new();
+
+        if let Some(domain) = &self.logon_domain_id {
+            sids.push(domain.with_rid(self.primary_group_id));
+            for group in self.group_ids.iter() {
+                if group.relative_id != self.primary_group_id {
+                    sids.push(domain.with_rid(group.relative_id));
+                }
+            }
+        }
+
+        for extra_sid in self.extra_sids.iter() {
+            sids.push(extra_sid.sid.clone());
+        }
+
+        if let Some(domain) = &self.resource_group_domain_sid {
+            for group in self.resource_group_ids.iter() {
+                sids.push(domain.with_rid(group.relative_id));
+            }
+        }
+
+        return sids;
+    }
+
+    /// Creates a new instance from the NDR representation
+    /// # Error
+    /// Returns error when the binary has not the expected format.
+    pub fn parse(raw: &[u8]) -> Result<Self> {
+        let mut reader = NdrReader::new(raw)?;
+        let r = &mut reader;
+
+        let mut info = Self {
+            logon_time: r.filetime()?,
+            logoff_time: r.filetime()?,
+            kick_off_time: r.filetime()?,
+            password_last_set: r.filetime()?,
+            password_can_change: r.filetime()?,
+            password_must_change: r.filetime()?,
+            ..Default::default()
+        };
+
+        let effective_name = r.unicode_string_header()?;
+        let full_name = r.unicode_string_header()?;
+        let logon_script = r.unicode_string_header()?;
+        let profile_path = r.unicode_string_header()?;
+        let home_directory = r.unicode_string_header()?;
+        let home_directory_drive = r.unicode_string_header()?;
+        info.logon_count = r.u16()?;
+        info.bad_password_count = r.u16()?;
+        info.user_id = r.u32()?;
+        info.primary_group_id = r.u32()?;
+        let group_count = r.u32()?;
+        let group_ids = r.pointer()?;
+        info.user_flags = r.u32()?;
+        info.user_session_key.copy_from_slice(&r.bytes(16)?);
+        let logon_server = r.unicode_string_header()?;
+        let logon_domain_name = r.unicode_string_header()?;
+        let logon_domain_id = r.pointer()?;
+        r.u32()?;
+        r.u32()?;
+        info.user_account_control = r.u32()?;
+        info.sub_auth_status = r.u32()?;
+        info.last_successful_i_logon = r.filetime()?;
+        info.last_failed_i_logon = r.filetime()?;
+        info.failed_i_logon_count = r.u32()?;
+        r.u32()?;
+        let sid_count = r.u32()?;
+        let extra_sids = r.pointer()?;
+        let resource_group_domain_sid = r.pointer()?;
+        let resource_group_count = r.u32()?;
+        let resource_group_ids = r.pointer()?;
+
+        info.effective_name = r.unicode_string(&effective_name)?;
+        info.full_name = r.unicode_string(&full_name)?;
+        info.logon_script = r.unicode_string(&logon_script)?;
+        info.profile_path = r.unicode_string(&profile_path)?;
+        info.home_directory = r.unicode_string(&home_directory)?;
+        info.home_directory_drive = r.unicode_string(&home_directory_drive)?;
+        info.group_ids = read_groups(r, group_ids, group_count)?;
+        info.logon_server = r.unicode_string(&logon_server)?;
+        info.logon_domain_name = r.unicode_string(&logon_domain_name)?;
+        info.logon_domain_id = r.sid(logon_domain_id)?;
+        info.extra_sids = read_sids(r, extra_sids, sid_count)?;
+        info.resource_group_domain_sid = r.sid(resource_group_domain_sid)?;
+        info.resource_group_ids =
+            read_groups(r, resource_group_ids, resource_group_count)?;
+
+        return Ok(info);
+    }
+
+    /// Build the NDR representation
+    pub fn build(&self) -> Vec<u8> {
+        let mut writer = NdrWriter::new();
+        let w = &mut writer;
+
+        w.filetime(self.logon_time);
+        w.filetime(self.logoff_time);
+        w.filetime(self.kick_off_time);
+        w.filetime(self.password_last_set);
+        w.filetime(self.password_can_change);
+        w.filetime(self.password_must_change);
+        w.unicode_string_header(&self.effective_name);
+        w.unicode_string_header(&self.full_name);
+        w.unicode_string_header(&self.logon_script);
+        w.unicode_string_header(&self.profile_path);
+        w.unicode_string_header(&self.home_directory);
+        w.unicode_string_header(&self.home_directory_drive);
+        w.u16(self.logon_count);
+        w.u16(self.bad_password_count);
+        w.u32(self.user_id);
+        w.u32(self.primary_group_id);
+        w.u32(self.group_ids.len() as u32);
+        w.pointer(!self.group_ids.is_empty());
+        w.u32(self.user_flags);
+        w.bytes(&self.user_session_key);
+        w.unicode_string_header(&self.logon_server);
+        w.unicode_string_header(&self.logon_domain_name);
+        w.pointer(self.logon_domain_id.is_some());
+        w.u32(0);
+        w.u32(0);
+        w.u32(self.user_account_control);
+        w.u32(self.sub_auth_status);
+        w.filetime(self.last_successful_i_logon);
+        w.filetime(self.last_failed_i_logon);
+        w.u32(self.failed_i_logon_count);
+        w.u32(0);
+        w.u32(self.extra_sids.len() as u32);
+        w.pointer(!self.extra_sids.is_empty());
+        w.pointer(self.resource_group_domain_sid.is_some());
+        w.u32(self.resource_group_ids.len() as u32);
+        w.pointer(!self.resource_group_ids.is_empty());
+
+        w.unicode_string(&self.effective_name);
+        w.unicode_string(&self.full_name);
+        w.unicode_string(&self.logon_script);
+        w.unicode_string(&self.profile_path);
+        w.unicode_string(&self.home_directory);
+        w.unicode_string(&self.home_directory_drive);
+        write_groups(w, &self.group_ids);
+        w.unicode_string(&self.logon_server);
+        w.unicode_string(&self.logon_domain_name);
+        w.sid(&self.logon_domain_id);
+        write_sids(w, &self.extra_sids);
+        w.sid(&self.resource_group_domain_sid);
+        write_groups(w, &self.resource_group_ids);
+
+        return writer.build();
+    }
+}
+
+fn read_groups(
+    r: &mut NdrReader,
+    pointer: u32,
+    count: u32,
+) -> Result<Vec<GroupMembership>> {
+    if pointer == 0 {
+        return Ok(Vec::new());
+    }
+
+    let count = r.conformant_count(count)?;
+    let mut groups = Vec::with_capacity(r.capacity(count, 8));
+    for _ in 0..count {
+        groups.push(GroupMembership::new(r.u32()?, r.u32()?));
+    }
+    return Ok(groups);
+}
+
+fn write_groups(w: &mut NdrWriter, groups: &[GroupMembership]) {
+    if groups.is_empty() {
+        return;
+    }
+
+    w.u32(groups.len() as u32);
+    for group in groups.iter() {
+        w.u32(group.relative_id);
+        w.u32(group.attributes);
+    }
+}
+
+fn read_sids(
+    r: &mut NdrReader,
+    pointer: u32,
+    count: u32,
+) -> Result<Vec<SidAndAttributes>> {
+    if pointer == 0 {
+        return Ok(Vec::new());
+    }
+
+    let count = r.conformant_count(count)?;
+    let mut pointers = Vec::with_capacity(r.capacity(count, 8));
+    for _ in 0..count {
+        pointers.push((r.pointer()?, r.u32()?));
+    }
+
+    let mut sids = Vec::with_capacity(pointers.len());
+    for (pointer, attributes) in pointers {
+        let sid = r.sid(pointer)?.unwrap_or_default();
+        sids.push(SidAndAttributes::new(sid, attributes));
+    }
+    return Ok(sids);
+}
+
+fn write_sids(w: &mut NdrWriter, sids: &[SidAndAttributes]) {
+    if sids.is_empty() {
+        return;
+    }
+
+    w.u32(sids.len() as u32);
+    for sid in sids.iter() {
+        w.pointer(true);
+        w.u32(sid.attributes);
+    }
+    for sid in sids.iter() {
+        w.sid(&Some(sid.sid.clone()));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Error;
+
+    fn domain_sid() -> Sid {
+        return Sid::new(
+            1,
+            [0, 0, 0, 0, 0, 5],
+            vec![21, 3623811015, 3361044348, 30300820],
+        );
+    }
+
+    fn create_logon_info() -> KerbValidationInfo {
+        let attributes =
+            SE_GROUP_MANDATORY | SE_GROUP_ENABLED_BY_DEFAULT | SE_GROUP_ENABLED;
+        return KerbValidationInfo {
+            logon_time: 0x01d7_6a4c_2b3e_5f00,
+            logoff_time: 0x7fff_ffff_ffff_ffff,
+            kick_off_time: 0x7fff_ffff_ffff_ffff,
+            effective_name: "mickey".to_string(),
+            full_name: "Mickey Mouse".to_string(),
+            logon_count: 12,
+            user_id: 1105,
+            primary_group_id: 513,
+            group_ids: vec![
+                GroupMembership::new(513, attributes),
+                GroupMembership::new(1106, attributes),
+            ],
+            user_flags: LOGON_EXTRA_SIDS | LOGON_RESOURCE_GROUPS,
+            logon_server: "DC01".to_string(),
+            logon_domain_name: "KINGDOM".to_string(),
+            logon_domain_id: Some(domain_sid()),
+            user_account_control: 0x10,
+            extra_sids: vec![SidAndAttributes::new(
+                Sid::new(1, [0, 0, 0, 0, 0, 18], vec![1]),
+                attributes,
+            )],
+            resource_group_domain_sid: Some(Sid::new(
+                1,
+                [0, 0, 0, 0, 0, 5],
+                vec![21, 1, 2, 3],
+            )),
+            resource_group_ids: vec![GroupMembership::new(2001, attributes)],
+            ..Default::default()
+        };
+    }
+
+    #[test]
+    fn build_and_parse_logon_info() {
+        let logon_info = create_logon_info();
+        let raw = logon_info.build();
+
+        assert_eq!(logon_info, KerbValidationInfo::parse(&raw).unwrap());
+    }
+
+    #[test]
+    fn get_sids_of_user_and_groups() {
+        let logon_info = create_logon_info();
+
+        assert_eq!(
+            "S-1-5-21-3623811015-3361044348-30300820-1105",
+            logon_info.user_sid().unwrap().to_string()
+        );
+        assert_eq!(
+            vec![
+                "S-1-5-21-3623811015-3361044348-30300820-513",
+                "S-1-5-21-3623811015-3361044348-30300820-1106",
+                "S-1-18-1",
+                "S-1-5-21-1-2-3-2001",
+            ],
+            logon_info
+                .group_sids()
+                .iter()
+                .map(|sid| sid.to_string())
+                .collect::<Vec<String>>()
+        );
+    }
+
+    #[test]
+    fn error_parsing_truncated_logon_info() {
+        let raw = create_logon_info().build();
+
+        assert!(matches!(
+            KerbValidationInfo::parse(&raw[..raw.len() - 40]),
+            Err(Error::InvalidNdr(_))
+        ));
+    }
+
+    #[test]
+    fn error_reading_groups_and_sids_with_malformed_count() {
+        let mut writer = NdrWriter::new();
+        writer.u32(u32::MAX);
+        writer.u32(513);
+        let raw = writer.build();
+
+        let mut reader = NdrReader::new(&raw).unwrap();
+        assert!(matches!(
+            read_groups(&mut reader, 1, u32::MAX),
+            Err(Error::InvalidNdr(_))
+        ));
+
+        let mut reader = NdrReader::new(&raw).unwrap();
+        assert!(matches!(
+            read_sids(&mut reader, 1, u32::MAX),
+            Err(Error::InvalidNdr(_))
+        ));
+    }
+}
@@ -0,0 +1,380 @@
+//! Minimal codec of the Network Data Representation (NDR) used by the
+//! buffers of the PAC, as described in [MS-RPCE] section 2.2.6 and in
+//! the chapter 14 of C706.
+//!
+//! Only the little-endian NDR20 transfer syntax, serialized with the
+//! type serialization version 1, is supported. The representation of the
+//! referents of the pointers is deferred after the structure that
+//! contains them, so the structures read and write their pointers first
+//! and then their referents, in the same order.
+
+use crate::error::{Error, Result};
+use crate::sid::Sid;
+use std::convert::TryInto;
+
+const COMMON_HEADER: [u8; 8] = [0x01, 0x10, 0x08, 0x00, 0xcc, 0xcc, 0xcc, 0xcc];
+const HEADERS_LEN: usize = 16;
+const FIRST_REFERENT_ID: u32 = 0x00020000;
+
+/// Header of a RPC_UNICODE_STRING, whose characters are deferred.
+pub(crate) struct UnicodeStringHeader {
+    length: u16,
+    pointer: u32,
+}
+
+pub(crate) struct NdrReader<'a> {
+    raw: &'a [u8],
+    position: usize,
+}
+
+impl<'a> NdrReader<'a> {
+    /// Creates a reader of data serialized with the type serialization
+    /// version 1, skipping its headers and its top-level pointer.
+    pub fn new(raw: &'a [u8]) -> Result<Self> {
+        if raw.len() < HEADERS_LEN || raw[..4] != COMMON_HEADER[..4] {
+            return Err(Error::InvalidNdr(
+                "invalid type serialization header".to_string(),
+            ));
+        }
+
+        let mut reader = Self {
+            raw,
+            position: HEADERS_LEN,
+        };
+        if reader.pointer()? == 0 {
+            return Err(Error::InvalidNdr(
+                "null top-level pointer".to_string(),
+            ));
+        }
+        return Ok(reader);
+    }
+
+    fn take(&mut self, size: usize) -> Result<&'a [u8]> {
+        let end = self.position + size;
+        if end > self.raw.len() {
+            return Err(Error::InvalidNdr(format!(
+                "unexpected end of data at {}",
+                self.position
+            )));
+        }
+        let data = &self.raw[self.position..end];
+        self.position = end;
+        return Ok(data);
+    }
+
+    /// Number of elements of `element_size` bytes to preallocate for an
+    /// array of `count` elements, bounded by the remaining data so a
+    /// malformed count cannot exhaust the memory.
+    pub fn capacity(&self, count: usize, element_size: usize) -> usize {
+        let remaining = self.raw.len() - self.position;
+        return count.min(remaining / element_size);
+    }
+
+    fn align(&mut self, alignment: usize) -> Result<()> {
+        let padding = (alignment - self.position % alignment) % alignment;
+        self.take(padding)?;
+        return Ok(());
+    }
+
+    pub fn u8(&mut self) -> Result<u8> {
+        return Ok(self.take(1)?[0]);
+    }
+
+    pub fn u16(&mut self) -> Result<u16> {
+        self.align(2)?;
+        return Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()));
+    }
+
+    pub fn u32(&mut self) -> Result<u32> {
+        self.align(4)?;
+        return Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()));
+    }
+
+    pub fn u64(&mut self) -> Result<u64> {
+        self.align(8)?;
+        return Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()));
+    }
+
+    /// Reads a FILETIME, which is a structure of two 32 bits integers.
+    pub fn filetime(&mut self) -> Result<u64> {
+        let low = self.u32()? as u64;
+        let high = self.u32()? as u64;
+        return Ok(high << 32 | low);
+    }
+
+    pub fn bytes(&mut self, size: usize) -> Result<Vec<u8>> {
+        return Ok(self.take(size)?.to_vec());
+    }
+
+    /// Reads the referent id of a pointer, which is 0 for null pointers.
+    pub fn pointer(&mut self) -> Result<u32> {
+        return self.u32();
+    }
+
+    /// Reads the maximum count of a conformant array, checking that it
+    /// matches the number of elements announced by the structure.
+    pub fn conformant_count(&mut self, expected: u32) -> Result<usize> {
+        let count = self.u32()?;
+        if count != expected {
+            return Err(Error::InvalidNdr(format!(
+                "array of {} elements, instead of {}",
+                count, expected
+            )));
+        }
+        return Ok(count as usize);
+    }
+
+    /// Reads a conformant array of bytes, if the pointer is not null.
+    pub fn conformant_bytes(
+        &mut self,
+        pointer: u32,
+        expected: u32,
+    ) -> Result<Vec<u8>> {
+        if pointer == 0 {
+            return Ok(Vec::new());
+        }
+        let count = self.conformant_count(expected)?;
+        return self.bytes(count);
+    }
+
+    pub fn unicode_string_header(&mut self) -> Result<UnicodeStringHeader> {
+        let length = self.u16()?;
+        let _maximum_length = self.u16()?;
+        let pointer = self.pointer()?;
+        return Ok(UnicodeStringHeader { length, pointer });
+    }
+
+    /// Reads the deferred characters of a RPC_UNICODE_STRING.
+    pub fn unicode_string(
+        &mut self,
+        header: &UnicodeStringHeader,
+    ) -> Result<String> {
+        if header.pointer == 0 {
+            return Ok(String::new());
+        }
+        let string = self.varying_string()?;
+        if string.len() * 2 < header.length as usize {
+            return Err(Error::InvalidNdr(
+                "string shorter than its length".to_string(),
+            ));
+        }
+        return Ok(String::from_utf16_lossy(
+            &string[..header.length as usize / 2],
+        ));
+    }
+
+    /// Reads a null-terminated string of wide characters, as `[string]
+    /// wchar_t*`.
+    pub fn wide_string(&mut self) -> Result<String> {
+        let mut string = self.varying_string()?;
+        if string.last() == Some(&0) {
+            string.pop();
+        }
+        return Ok(String::from_utf16_lossy(&string));
+    }
+
+    fn varying_string(&mut self) -> Result<Vec<u16>> {
+        let _max_count = self.u32()?;
+        let offset = self.u32()?;
+        let actual_count = self.u32()?;
+        if offset != 0 {
+            return Err(Error::InvalidNdr(format!(
+                "string with offset {}",
+                offset
+            )));
+        }
+
+        let mut string =
+            Vec::with_capacity(self.capacity(actual_count as usize, 2));
+        for _ in 0..actual_count {
+            string.push(self.u16()?);
+        }
+        return Ok(string);
+    }
+
+    /// Reads the deferred SID of a pointer, if it is not null.
+    pub fn sid(&mut self, pointer: u32) -> Result<Option<Sid>> {
+        if pointer == 0 {
+            return Ok(None);
+        }
+        let _max_count = self.u32()?;
+        let revision = self.u8()?;
+        let sub_authority_count = self.u8()?;
+        let identifier_authority = self.take(6)?.try_into().unwrap();
+        let mut sub_authorities = Vec::new();
+        for _ in 0..sub_authority_count {
+            sub_authorities.push(self.u32()?);
+        }
+        return Ok(Some(Sid::new(
+            revision,
+            identifier_authority,
+            sub_authorities,
+        )));
+    }
+}
+
+pub(crate) struct NdrWriter {
+    data: Vec<u8>,
+    referent_id: u32,
+}
+
+impl NdrWriter {
+    /// Creates a writer of data serialized with the type serialization
+    /// version 1, which starts with the top-level pointer.
+    pub fn new() -> Self {
+        let mut writer = Self {
+            data: vec![0; HEADERS_LEN],
+            referent_id: FIRST_REFERENT_ID,
+        };
+        writer.pointer(true);
+        return writer;
+    }
+
+    /// Returns the data with the headers of the type serialization.
+    pub fn build(mut self) -> Vec<u8> {
+        self.align(8);
+        let object_length = (self.data.len() - HEADERS_LEN) as u32;
+        self.data[..8].copy_from_slice(&COMMON_HEADER);
+        self.data[8..12].copy_from_slice(&object_length.to_le_bytes());
+        return self.data;
+    }
+
+    fn align(&mut self, alignment: usize) {
+        while !self.data.len().is_multiple_of(alignment) {
+            self.data.push(0);
+        }
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    pub fn u16(&mut self, value: u16) {
+        self.align(2);
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.align(4);
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.align(8);
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn filetime(&mut self, value: u64) {
+        self.u32(value as u32);
+        self.u32((value >> 32) as u32);
+    }
+
+    pub fn bytes(&mut self, value: &[u8]) {
+        self.data.extend_from_slice(value);
+    }
+
+    /// Writes a new referent id, or 0 if the pointer is null.
+    pub fn pointer(&mut self, present: bool) {
+        if present {
+            let referent_id = self.referent_id;
+            self.referent_id += 4;
+            self.u32(referent_id);
+        } else {
+            self.u32(0);
+        }
+    }
+
+    pub fn conformant_bytes(&mut self, value: &[u8]) {
+        if !value.is_empty() {
+            self.u32(value.len() as u32);
+            self.bytes(value);
+        }
+    }
+
+    pub fn unicode_string_header(&mut self, value: &str) {
+        let length = (value.encode_utf16().count() * 2) as u16;
+        self.u16(length);
+        self.u16(length);
+        self.pointer(true);
+    }
+
+    pub fn unicode_string(&mut self, value: &str) {
+        self.varying_string(&value.encode_utf16().collect::<Vec<u16>>());
+    }
+
+    pub fn wide_string(&mut self, value: &str) {
+        let mut string: Vec<u16> = value.encode_utf16().collect();
+        string.push(0);
+        self.varying_string(&string);
+    }
+
+    fn varying_string(&mut self, string: &[u16]) {
+        self.u32(string.len() as u32);
+        self.u32(0);
+        self.u32(string.len() as u32);
+        for character in string.iter() {
+            self.u16(*character);
+        }
+    }
+
+    pub fn sid(&mut self, sid: &Option<Sid>) {
+        if let Some(sid) = sid {
+            self.u32(sid.sub_authorities.len() as u32);
+            self.u8(sid.revision);
+            self.u8(sid.sub_authorities.len() as u8);
+            self.bytes(&sid.identifier_authority);
+            for sub_authority in sid.sub_authorities.iter() {
+                self.u32(*sub_authority);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn write_and_read_type_serialized_data() {
+        let mut writer = NdrWriter::new();
+        writer.u16(7);
+        writer.unicode_string_header("Mickey");
+        writer.u64(0x0102030405060708);
+        writer.unicode_string("Mickey");
+        let raw = writer.build();
+
+        assert_eq!(COMMON_HEADER, raw[..8]);
+        assert_eq!(0, raw.len() % 8);
+        assert_eq!([0x00, 0x00, 0x02, 0x00], raw[16..20]);
+
+        let mut reader = NdrReader::new(&raw).unwrap();
+        assert_eq!(7, reader.u16().unwrap());
+        let header = reader.unicode_string_header().unwrap();
+        assert_eq!(0x0102030405060708, reader.u64().unwrap());
+        assert_eq!("Mickey", reader.unicode_string(&header).unwrap());
+    }
+
+    #[test]
+    fn error_reading_beyond_data() {
+        let mut writer = NdrWriter::new();
+        writer.u32(1);
+        let raw = writer.build();
+
+        let mut reader = NdrReader::new(&raw).unwrap();
+        assert_eq!(1, reader.u32().unwrap());
+        assert!(matches!(reader.u32(), Err(Error::InvalidNdr(_))));
+    }
+
+    #[test]
+    fn error_reading_string_with_malformed_count() {
+        let mut writer = NdrWriter::new();
+        writer.u32(u32::MAX);
+        writer.u32(0);
+        writer.u32(u32::MAX);
+        writer.u16(0x4d);
+        let raw = writer.build();
+
+        let mut reader = NdrReader::new(&raw).unwrap();
+        assert!(matches!(reader.wide_string(), Err(Error::InvalidNdr(_))));
+    }
+}
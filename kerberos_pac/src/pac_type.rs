@@ -0,0 +1,353 @@
+use crate::{
+    ClaimsSetMetadata, Error, KerbValidationInfo, PacAttributesInfo,
+    PacClientInfo, PacRequestor, Result, UpnDnsInfo,
+};
+use nom::multi::count;
+use nom::number::complete::{le_u32, le_u64};
+use nom::IResult;
+use std::ops::Range;
+
+pub const PAC_LOGON_INFO: u32 = 1;
+pub const PAC_CREDENTIALS_INFO: u32 = 2;
+pub const PAC_SERVER_CHECKSUM: u32 = 6;
+pub const PAC_PRIVSVR_CHECKSUM: u32 = 7;
+pub const PAC_CLIENT_INFO: u32 = 10;
+pub const PAC_DELEGATION_INFO: u32 = 11;
+pub const PAC_UPN_DNS_INFO: u32 = 12;
+pub const PAC_CLIENT_CLAIMS_INFO: u32 = 13;
+pub const PAC_DEVICE_INFO: u32 = 14;
+pub const PAC_DEVICE_CLAIMS_INFO: u32 = 15;
+pub const PAC_TICKET_CHECKSUM: u32 = 16;
+pub const PAC_ATTRIBUTES_INFO: u32 = 17;
+pub const PAC_REQUESTOR: u32 = 18;
+pub const PAC_FULL_CHECKSUM: u32 = 19;
+
+const PACTYPE_HEADER_SIZE: usize = 8;
+const PAC_INFO_BUFFER_SIZE: usize = 16;
+
+/// Buffer of the PAC, with its type and its data.
+///
+/// # Definition
+/// ```c
+/// typedef struct _PAC_INFO_BUFFER {
+///     ULONG ulType;
+///     ULONG cbBufferSize;
+///     ULONG64 Offset;
+/// } PAC_INFO_BUFFER;
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct PacInfoBuffer {
+    pub ul_type: u32,
+    pub data: Vec<u8>,
+}
+
+impl PacInfoBuffer {
+    pub fn new(ul_type: u32, data: Vec<u8>) -> Self {
+        return Self { ul_type, data };
+    }
+}
+
+/// Privilege Attribute Certificate, included by Active Directory in the
+/// `AD-WIN2K-PAC` authorization data of the tickets, as described in
+/// [MS-PAC].
+///
+/// The bytes of a parsed PAC are kept, so its signatures are verified over
+/// the received data, as MIT and Samba do. The known buffers can be
+/// decoded with the methods of each type.
+///
+/// # Definition
+/// ```c
+/// typedef struct _PACTYPE {
+///     ULONG cBuffers;
+///     ULONG Version;
+///     PAC_INFO_BUFFER Buffers[1];
+/// } PACTYPE;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Pac {
+    pub version: u32,
+    pub buffers: Vec<PacInfoBuffer>,
+    pub(crate) raw: Option<RawPac>,
+}
+
+/// Bytes of a parsed PAC, with the location of its server, KDC and full
+/// signature buffers.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RawPac {
+    pub data: Vec<u8>,
+    pub signature_buffers: Vec<(u32, Range<usize>)>,
+}
+
+/// The PACs are compared by their buffers, whether they were parsed or not.
+impl PartialEq for Pac {
+    fn eq(&self, other: &Self) -> bool {
+        return self.version == other.version && self.buffers == other.buffers;
+    }
+}
+
+impl Pac {
+    pub fn new(buffers: Vec<PacInfoBuffer>) -> Self {
+        return Self {
+            version: 0,
+            buffers,
+            raw: None,
+        };
+    }
+
+    /// First buffer of the given type.
+    pub fn buffer(&self, ul_type: u32) -> Option<&PacInfoBuffer> {
+        return self.buffers.iter().find(|buffer| buffer.ul_type == ul_type);
+    }
+
+    /// Decodes the logon information, with the SIDs of the user and its
+    /// groups.
+    pub fn logon_info(&self) -> Result<Option<KerbValidationInfo>> {
+        return self.decode(PAC_LOGON_INFO, |data| {
+            return Ok(KerbValidationInfo::parse(data)?);
+        });
+    }
+
+    pub fn client_info(&self) -> Result<Option<PacClientInfo>> {
+        return self.decode(PAC_CLIENT_INFO, |data| {
+            return Ok(PacClientInfo::parse(data)?.1);
+        });
+    }
+
+    pub fn upn_dns_info(&self) -> Result<Option<UpnDnsInfo>> {
+        return self.decode(PAC_UPN_DNS_INFO, |data| {
+            return Ok(UpnDnsInfo::parse(data)?.1);
+        });
+    }
+
+    pub fn attributes_info(&self) -> Result<Option<PacAttributesInfo>> {
+        return self.decode(PAC_ATTRIBUTES_INFO, |data| {
+            return Ok(PacAttributesInfo::parse(data)?.1);
+        });
+    }
+
+    pub fn requestor(&self) -> Result<Option<PacRequestor>> {
+        return self.decode(PAC_REQUESTOR, |data| {
+            return Ok(PacRequestor::parse(data)?.1);
+        });
+    }
+
+    pub fn client_claims(&self) -> Result<Option<ClaimsSetMetadata>> {
+        return self.decode(PAC_CLIENT_CLAIMS_INFO, |data| {
+            return Ok(ClaimsSetMetadata::parse(data)?);
+        });
+    }
+
+    pub fn device_claims(&self) -> Result<Option<ClaimsSetMetadata>> {
+        return self.decode(PAC_DEVICE_CLAIMS_INFO, |data| {
+            return Ok(ClaimsSetMetadata::parse(data)?);
+        });
+    }
+
+    fn decode<'a, T, F>(&'a self, ul_type: u32, parse: F) -> Result<Option<T>>
+    where
+        F: Fn(&'a [u8]) -> std::result::Result<T, DecodeError<'a>>,
+    {
+        let buffer = match self.buffer(ul_type) {
+            Some(buffer) => buffer,
+            None => return Ok(None),
+        };
+        return parse(&buffer.data)
+            .map(Some)
+            .map_err(|error| error.into_error(ul_type));
+    }
+
+    /// Build the binary representation, placing the buffers after the
+    /// descriptors, aligned to 8 bytes.
+    pub fn build(&self) -> Vec<u8> {
+        let mut offset =
+            PACTYPE_HEADER_SIZE + PAC_INFO_BUFFER_SIZE * self.buffers.len();
+
+        let mut bytes = (self.buffers.len() as u32).to_le_bytes().to_vec();
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        for buffer in self.buffers.iter() {
+            bytes.extend_from_slice(&buffer.ul_type.to_le_bytes());
+            bytes.extend_from_slice(&(buffer.data.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(&(offset as u64).to_le_bytes());
+            offset += align8(buffer.data.len());
+        }
+
+        for buffer in self.buffers.iter() {
+            bytes.extend_from_slice(&buffer.data);
+            bytes.resize(align8(bytes.len()), 0);
+        }
+        return bytes;
+    }
+
+    /// Creates a new instance from the binary representation
+    /// # Error
+    /// Returns error when the binary has not the expected format.
+    pub fn parse(raw: &[u8]) -> Result<Self> {
+        let (_, (buffer_count, version)) = parse_header(raw)
+            .map_err(|_| Error::InvalidPac("invalid header".to_string()))?;
+        let (_, descriptors) = count(parse_descriptor, buffer_count as usize)(
+            &raw[PACTYPE_HEADER_SIZE..],
+        )
+        .map_err(|_| {
+            Error::InvalidPac(format!("less than {} buffers", buffer_count))
+        })?;
+
+        let mut buffers = Vec::with_capacity(descriptors.len());
+        let mut signature_buffers = Vec::new();
+        for (ul_type, size, offset) in descriptors {
+            let end = offset.saturating_add(size as u64);
+            if end > raw.len() as u64 {
+                return Err(Error::InvalidPac(format!(
+                    "buffer of type {} out of the PAC",
+                    ul_type
+                )));
+            }
+            let range = offset as usize..end as usize;
+            if is_zeroed_signature(ul_type) {
+                signature_buffers.push((ul_type, range.clone()));
+            }
+            buffers.push(PacInfoBuffer::new(ul_type, raw[range].to_vec()));
+        }
+
+        return Ok(Self {
+            version,
+            buffers,
+            raw: Some(RawPac {
+                data: raw.to_vec(),
+                signature_buffers,
+            }),
+        });
+    }
+}
+
+/// Error decoding a buffer, either from its NDR or its binary format.
+pub(crate) enum DecodeError<'a> {
+    Ndr(Error),
+    Binary(nom::Err<nom::error::Error<&'a [u8]>>),
+}
+
+impl DecodeError<'_> {
+    fn into_error(self, ul_type: u32) -> Error {
+        return match self {
+            DecodeError::Ndr(Error::InvalidNdr(description)) => {
+                Error::InvalidBuffer(ul_type, description)
+            }
+            DecodeError::Ndr(error) => error,
+            DecodeError::Binary(nom::Err::Incomplete(_)) => {
+                Error::InvalidBuffer(ul_type, "incomplete data".to_string())
+            }
+            DecodeError::Binary(
+                nom::Err::Error(error) | nom::Err::Failure(error),
+            ) => Error::InvalidBuffer(
+                ul_type,
+                error.code.description().to_string(),
+            ),
+        };
+    }
+}
+
+impl From<Error> for DecodeError<'_> {
+    fn from(error: Error) -> Self {
+        return DecodeError::Ndr(error);
+    }
+}
+
+impl<'a> From<nom::Err<nom::error::Error<&'a [u8]>>> for DecodeError<'a> {
+    fn from(error: nom::Err<nom::error::Error<&'a [u8]>>) -> Self {
+        return DecodeError::Binary(error);
+    }
+}
+
+fn parse_header(raw: &[u8]) -> IResult<&[u8], (u32, u32)> {
+    let (raw, buffer_count) = le_u32(raw)?;
+    let (raw, version) = le_u32(raw)?;
+    return Ok((raw, (buffer_count, version)));
+}
+
+fn parse_descriptor(raw: &[u8]) -> IResult<&[u8], (u32, u32, u64)> {
+    let (raw, ul_type) = le_u32(raw)?;
+    let (raw, size) = le_u32(raw)?;
+    let (raw, offset) = le_u64(raw)?;
+    return Ok((raw, (ul_type, size, offset)));
+}
+
+/// Whether the buffer is a signature which is zeroed to compute the
+/// server and full signatures.
+pub(crate) fn is_zeroed_signature(ul_type: u32) -> bool {
+    return ul_type == PAC_SERVER_CHECKSUM
+        || ul_type == PAC_PRIVSVR_CHECKSUM
+        || ul_type == PAC_FULL_CHECKSUM;
+}
+
+fn align8(length: usize) -> usize {
+    return length.div_ceil(8) * 8;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{GroupMembership, Sid};
+
+    fn create_pac() -> Pac {
+        let logon_info = KerbValidationInfo {
+            effective_name: "mickey".to_string(),
+            user_id: 1105,
+            primary_group_id: 513,
+            group_ids: vec![GroupMembership::new(513, 7)],
+            logon_domain_id: Some(Sid::new(
+                1,
+                [0, 0, 0, 0, 0, 5],
+                vec![21, 1, 2, 3],
+            )),
+            ..Default::default()
+        };
+        return Pac::new(vec![
+            PacInfoBuffer::new(PAC_LOGON_INFO, logon_info.build()),
+            PacInfoBuffer::new(
+                PAC_CLIENT_INFO,
+                PacClientInfo::new(1, "mickey".to_string()).build(),
+            ),
+            PacInfoBuffer::new(PAC_SERVER_CHECKSUM, vec![0x10, 0, 0, 0, 1]),
+        ]);
+    }
+
+    #[test]
+    fn build_and_parse_pac() {
+        let pac = create_pac();
+        let raw = pac.build();
+
+        assert_eq!([3, 0, 0, 0, 0, 0, 0, 0], raw[..8]);
+        assert_eq!([0x38, 0, 0, 0, 0, 0, 0, 0], raw[16..24]);
+        assert_eq!(pac, Pac::parse(&raw).unwrap());
+    }
+
+    #[test]
+    fn decode_buffers_of_pac() {
+        let pac = Pac::parse(&create_pac().build()).unwrap();
+
+        let logon_info = pac.logon_info().unwrap().unwrap();
+        assert_eq!(
+            "S-1-5-21-1-2-3-513",
+            logon_info.group_sids()[0].to_string()
+        );
+        assert_eq!("mickey", pac.client_info().unwrap().unwrap().name);
+        assert_eq!(None, pac.upn_dns_info().unwrap());
+    }
+
+    #[test]
+    fn error_decoding_invalid_buffer() {
+        let pac = Pac::new(vec![PacInfoBuffer::new(PAC_CLIENT_INFO, vec![1])]);
+
+        assert!(matches!(
+            pac.client_info(),
+            Err(Error::InvalidBuffer(PAC_CLIENT_INFO, _))
+        ));
+    }
+
+    #[test]
+    fn error_parsing_buffer_out_of_pac() {
+        let mut raw = create_pac().build();
+        raw.truncate(raw.len() - 8);
+
+        assert!(matches!(Pac::parse(&raw), Err(Error::InvalidPac(_))));
+    }
+}
@@ -0,0 +1,131 @@
+use nom::bytes::complete::take;
+use nom::multi::count;
+use nom::number::complete::{le_u32, u8 as parse_u8};
+use nom::IResult;
+use std::convert::TryInto;
+use std::fmt;
+
+/// Security identifier of Windows, such as the one of a user or a
+/// group.
+///
+/// # Definition
+/// ```c
+/// typedef struct _RPC_SID {
+///     unsigned char Revision;
+///     unsigned char SubAuthorityCount;
+///     RPC_SID_IDENTIFIER_AUTHORITY IdentifierAuthority;
+///     [size_is(SubAuthorityCount)] unsigned long SubAuthority[];
+/// } RPC_SID;
+/// ```
+///
+/// It is displayed in the `S-1-5-21-...` string format.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Default)]
+pub struct Sid {
+    pub revision: u8,
+    pub identifier_authority: [u8; 6],
+    pub sub_authorities: Vec<u32>,
+}
+
+impl Sid {
+    pub fn new(
+        revision: u8,
+        identifier_authority: [u8; 6],
+        sub_authorities: Vec<u32>,
+    ) -> Self {
+        return Self {
+            revision,
+            identifier_authority,
+            sub_authorities,
+        };
+    }
+
+    /// Creates the SID of an account of this domain, from its relative
+    /// identifier.
+    pub fn with_rid(&self, rid: u32) -> Self {
+        let mut sid = self.clone();
+        sid.sub_authorities.push(rid);
+        return sid;
+    }
+
+    /// Relative identifier of the account, which is the last
+    /// sub-authority.
+    pub fn rid(&self) -> Option<u32> {
+        return self.sub_authorities.last().cloned();
+    }
+
+    /// Build the binary representation
+    pub fn build(&self) -> Vec<u8> {
+        let mut bytes = vec![self.revision, self.sub_authorities.len() as u8];
+        bytes.extend_from_slice(&self.identifier_authority);
+        for sub_authority in self.sub_authorities.iter() {
+            bytes.extend_from_slice(&sub_authority.to_le_bytes());
+        }
+        return bytes;
+    }
+
+    /// Creates a new instance from the binary representation
+    /// # Error
+    /// Returns error when the binary has not the expected format.
+    pub fn parse(raw: &[u8]) -> IResult<&[u8], Self> {
+        let (raw, revision) = parse_u8(raw)?;
+        let (raw, sub_authority_count) = parse_u8(raw)?;
+        let (raw, identifier_authority) = take(6usize)(raw)?;
+        let (raw, sub_authorities) =
+            count(le_u32, sub_authority_count as usize)(raw)?;
+
+        return Ok((
+            raw,
+            Self::new(
+                revision,
+                identifier_authority.try_into().unwrap(),
+                sub_authorities,
+            ),
+        ));
+    }
+}
+
+impl fmt::Display for Sid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut authority = [0; 8];
+        authority[2..].copy_from_slice(&self.identifier_authority);
+        write!(f, "S-{}-{}", self.revision, u64::from_be_bytes(authority))?;
+        for sub_authority in self.sub_authorities.iter() {
+            write!(f, "-{}", sub_authority)?;
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn domain_sid() -> Sid {
+        return Sid::new(
+            1,
+            [0, 0, 0, 0, 0, 5],
+            vec![21, 3623811015, 3361044348, 30300820],
+        );
+    }
+
+    #[test]
+    fn display_sid() {
+        assert_eq!(
+            "S-1-5-21-3623811015-3361044348-30300820-1013",
+            domain_sid().with_rid(1013).to_string()
+        );
+    }
+
+    #[test]
+    fn parse_and_build_sid() {
+        let raw = vec![
+            0x01, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05, 0x20, 0x00, 0x00,
+            0x00, 0x20, 0x02, 0x00, 0x00,
+        ];
+        let sid = Sid::new(1, [0, 0, 0, 0, 0, 5], vec![32, 544]);
+
+        assert_eq!("S-1-5-32-544", sid.to_string());
+        assert_eq!(sid, Sid::parse(&raw).unwrap().1);
+        assert_eq!(raw, sid.build());
+    }
+}
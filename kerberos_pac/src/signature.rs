@@ -0,0 +1,468 @@
+use crate::pac_type::is_zeroed_signature;
+use crate::{
+    Error, Pac, PacInfoBuffer, Result, PAC_FULL_CHECKSUM, PAC_PRIVSVR_CHECKSUM,
+    PAC_SERVER_CHECKSUM, PAC_TICKET_CHECKSUM,
+};
+use himmelblau_kerberos_constants::checksum_types::{
+    HMAC_MD5, HMAC_SHA1_96_AES128, HMAC_SHA1_96_AES256,
+};
+use himmelblau_kerberos_constants::key_usages::KEY_USAGE_KERB_NON_KERB_CKSUM_SALT;
+use himmelblau_kerberos_crypto::{checksum, mandatory_checksum_type, Key};
+use nom::bytes::complete::take;
+use nom::combinator::opt;
+use nom::error::{Error as ParseError, ErrorKind};
+use nom::number::complete::{le_i32, le_u16};
+use nom::IResult;
+
+/// Signature of the PAC, included in the `PAC_SERVER_CHECKSUM`,
+/// `PAC_PRIVSVR_CHECKSUM`, `PAC_TICKET_CHECKSUM` and `PAC_FULL_CHECKSUM`
+/// buffers.
+///
+/// # Definition
+/// ```c
+/// typedef struct _PAC_SIGNATURE_DATA {
+///     ULONG SignatureType;
+///     UCHAR Signature[1];
+///     USHORT RODCIdentifier;
+/// } PAC_SIGNATURE_DATA;
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct PacSignatureData {
+    /// Checksum type of the signature, such as `HMAC_SHA1_96_AES256`.
+    pub signature_type: i32,
+    pub signature: Vec<u8>,
+    /// Present if the PAC was signed by a read-only domain controller.
+    pub rodc_identifier: Option<u16>,
+}
+
+impl PacSignatureData {
+    pub fn new(signature_type: i32, signature: Vec<u8>) -> Self {
+        return Self {
+            signature_type,
+            signature,
+            rodc_identifier: None,
+        };
+    }
+
+    /// Build the binary representation
+    pub fn build(&self) -> Vec<u8> {
+        let mut bytes = self.signature_type.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&self.signature);
+        if let Some(rodc_identifier) = self.rodc_identifier {
+            bytes.extend_from_slice(&rodc_identifier.to_le_bytes());
+        }
+        return bytes;
+    }
+
+    /// Creates a new instance from the binary representation
+    /// # Error
+    /// Returns error when the binary has not the expected format.
+    pub fn parse(raw: &[u8]) -> IResult<&[u8], Self> {
+        let (rest, signature_type) = le_i32(raw)?;
+        let size = signature_size(signature_type).ok_or_else(|| {
+            nom::Err::Error(ParseError::new(raw, ErrorKind::Switch))
+        })?;
+        let (rest, signature) = take(size)(rest)?;
+        let (rest, rodc_identifier) = opt(le_u16)(rest)?;
+
+        return Ok((
+            rest,
+            Self {
+                signature_type,
+                signature: signature.to_vec(),
+                rodc_identifier,
+            },
+        ));
+    }
+}
+
+fn parse_signature(ul_type: u32, data: &[u8]) -> Result<PacSignatureData> {
+    let (_, signature) = PacSignatureData::parse(data).map_err(|_| {
+        Error::InvalidBuffer(ul_type, "unknown signature type".to_string())
+    })?;
+    return Ok(signature);
+}
+
+/// Size of the signatures of each checksum type.
+fn signature_size(signature_type: i32) -> Option<usize> {
+    return match signature_type {
+        HMAC_MD5 => Some(16),
+        HMAC_SHA1_96_AES128 | HMAC_SHA1_96_AES256 => Some(12),
+        _ => None,
+    };
+}
+
+/// Checksum type of the signatures computed with a key.
+fn signature_type_of(key: &Key) -> Result<i32> {
+    let etype = key.etypes()[0];
+    if let Key::Secret(_) = key {
+        return Err(Error::UnsupportedSignature(0, etype));
+    }
+    return mandatory_checksum_type(etype)
+        .map_err(|_| Error::UnsupportedSignature(0, etype));
+}
+
+fn compute_signature(
+    signature_type: i32,
+    key: &Key,
+    data: &[u8],
+) -> Result<Vec<u8>> {
+    let etype = key.etypes()[0];
+    if signature_type_of(key)? != signature_type {
+        return Err(Error::UnsupportedSignature(signature_type, etype));
+    }
+    return checksum(
+        signature_type,
+        key.as_bytes(),
+        KEY_USAGE_KERB_NON_KERB_CKSUM_SALT,
+        data,
+    )
+    .map_err(|_| Error::UnsupportedSignature(signature_type, etype));
+}
+
+/// Signatures of the PAC, as described in [MS-PAC] section 2.8.
+///
+/// The server signature is computed with the key of the service over the
+/// whole PAC, with the server, KDC and full signatures zeroed. The
+/// signatures of a parsed PAC are verified over the received bytes. The KDC
+/// signature is computed with the key of the `krbtgt` account over the
+/// server signature. The ticket signature, computed with the `krbtgt`
+/// key over the encrypted part of the ticket, and the full signature,
+/// computed like the server signature but with the `krbtgt` key, were
+/// added by the KB5008380 and KB5020805 updates.
+impl Pac {
+    pub fn server_signature(&self) -> Result<Option<PacSignatureData>> {
+        return self.signature(PAC_SERVER_CHECKSUM);
+    }
+
+    pub fn kdc_signature(&self) -> Result<Option<PacSignatureData>> {
+        return self.signature(PAC_PRIVSVR_CHECKSUM);
+    }
+
+    pub fn ticket_signature(&self) -> Result<Option<PacSignatureData>> {
+        return self.signature(PAC_TICKET_CHECKSUM);
+    }
+
+    pub fn full_signature(&self) -> Result<Option<PacSignatureData>> {
+        return self.signature(PAC_FULL_CHECKSUM);
+    }
+
+    fn signature(&self, ul_type: u32) -> Result<Option<PacSignatureData>> {
+        let buffer = match self.buffer(ul_type) {
+            Some(buffer) => buffer,
+            None => return Ok(None),
+        };
+        return Ok(Some(parse_signature(ul_type, &buffer.data)?));
+    }
+
+    /// Verifies the server signature with the key of the service that
+    /// decrypted the ticket.
+    ///
+    /// # Error
+    /// Returns [`Error::InvalidSignature`] if the signature does not
+    /// match, and [`Error::MissingBuffer`] if it is not present.
+    pub fn verify_server_signature(&self, service_key: &Key) -> Result<()> {
+        return self.verify_signature(
+            PAC_SERVER_CHECKSUM,
+            service_key,
+            &self.data_with_zeroed_signatures()?,
+        );
+    }
+
+    /// Verifies the KDC signature with the key of the `krbtgt` account.
+    pub fn verify_kdc_signature(&self, krbtgt_key: &Key) -> Result<()> {
+        let server_signature = self
+            .server_signature()?
+            .ok_or(Error::MissingBuffer(PAC_SERVER_CHECKSUM))?;
+        return self.verify_signature(
+            PAC_PRIVSVR_CHECKSUM,
+            krbtgt_key,
+            &server_signature.signature,
+        );
+    }
+
+    /// Verifies the ticket signature with the key of the `krbtgt`
+    /// account. `enc_ticket_part` is the encoded EncTicketPart of the
+    /// ticket, with the data of the `AD-WIN2K-PAC` element replaced by a
+    /// single zero byte.
+    pub fn verify_ticket_signature(
+        &self,
+        krbtgt_key: &Key,
+        enc_ticket_part: &[u8],
+    ) -> Result<()> {
+        return self.verify_signature(
+            PAC_TICKET_CHECKSUM,
+            krbtgt_key,
+            enc_ticket_part,
+        );
+    }
+
+    /// Verifies the full signature with the key of the `krbtgt` account.
+    pub fn verify_full_signature(&self, krbtgt_key: &Key) -> Result<()> {
+        return self.verify_signature(
+            PAC_FULL_CHECKSUM,
+            krbtgt_key,
+            &self.data_with_zeroed_signatures()?,
+        );
+    }
+
+    fn verify_signature(
+        &self,
+        ul_type: u32,
+        key: &Key,
+        data: &[u8],
+    ) -> Result<()> {
+        let signature = self
+            .signature(ul_type)?
+            .ok_or(Error::MissingBuffer(ul_type))?;
+        let expected = compute_signature(signature.signature_type, key, data)?;
+        if expected != signature.signature {
+            return Err(Error::InvalidSignature(ul_type));
+        }
+        return Ok(());
+    }
+
+    /// Signs the PAC as the KDC does, adding the signature buffers that
+    /// are missing. The ticket and full signatures are only computed if
+    /// `enc_ticket_part` is given, as explained in
+    /// [`Pac::verify_ticket_signature`], since they are not included in
+    /// the tickets of the `krbtgt` service.
+    pub fn sign(
+        &mut self,
+        service_key: &Key,
+        krbtgt_key: &Key,
+        enc_ticket_part: Option<&[u8]>,
+    ) -> Result<()> {
+        let server_type = signature_type_of(service_key)?;
+        let kdc_type = signature_type_of(krbtgt_key)?;
+
+        self.set_signature(PAC_SERVER_CHECKSUM, server_type, None);
+        self.set_signature(PAC_PRIVSVR_CHECKSUM, kdc_type, None);
+
+        if let Some(enc_ticket_part) = enc_ticket_part {
+            let ticket_signature =
+                compute_signature(kdc_type, krbtgt_key, enc_ticket_part)?;
+            self.set_signature(
+                PAC_TICKET_CHECKSUM,
+                kdc_type,
+                Some(ticket_signature),
+            );
+
+            self.set_signature(PAC_FULL_CHECKSUM, kdc_type, None);
+            let full_signature = compute_signature(
+                kdc_type,
+                krbtgt_key,
+                &self.build_with_zeroed_signatures()?,
+            )?;
+            self.set_signature(
+                PAC_FULL_CHECKSUM,
+                kdc_type,
+                Some(full_signature),
+            );
+        }
+
+        let server_signature = compute_signature(
+            server_type,
+            service_key,
+            &self.build_with_zeroed_signatures()?,
+        )?;
+        let kdc_signature =
+            compute_signature(kdc_type, krbtgt_key, &server_signature)?;
+        self.set_signature(
+            PAC_SERVER_CHECKSUM,
+            server_type,
+            Some(server_signature),
+        );
+        self.set_signature(PAC_PRIVSVR_CHECKSUM, kdc_type, Some(kdc_signature));
+        return Ok(());
+    }
+
+    /// Replaces the signature of a buffer, or adds the buffer if missing.
+    /// The signature is zeroed if `None`.
+    fn set_signature(
+        &mut self,
+        ul_type: u32,
+        signature_type: i32,
+        signature: Option<Vec<u8>>,
+    ) {
+        // the bytes of the parsed PAC are no longer valid
+        self.raw = None;
+        let size = signature_size(signature_type).unwrap_or(0);
+        let data = PacSignatureData::new(
+            signature_type,
+            signature.unwrap_or_else(|| vec![0; size]),
+        )
+        .build();
+
+        match self
+            .buffers
+            .iter_mut()
+            .find(|buffer| buffer.ul_type == ul_type)
+        {
+            Some(buffer) => buffer.data = data,
+            None => self.buffers.push(PacInfoBuffer::new(ul_type, data)),
+        }
+    }
+
+    /// Bytes of the PAC with the server, KDC and full signatures zeroed.
+    /// The signatures of a parsed PAC are zeroed in place, while the other
+    /// PACs are built.
+    fn data_with_zeroed_signatures(&self) -> Result<Vec<u8>> {
+        let raw = match &self.raw {
+            Some(raw) => raw,
+            None => return self.build_with_zeroed_signatures(),
+        };
+
+        let mut data = raw.data.clone();
+        for (ul_type, range) in raw.signature_buffers.iter() {
+            let signature = parse_signature(*ul_type, &data[range.clone()])?;
+            // the signature follows the 4 bytes of its type
+            let start = range.start + 4;
+            let end = start + signature.signature.len();
+            data[start..end].iter_mut().for_each(|byte| *byte = 0);
+        }
+        return Ok(data);
+    }
+
+    /// Builds the PAC with the server, KDC and full signatures zeroed.
+    fn build_with_zeroed_signatures(&self) -> Result<Vec<u8>> {
+        let mut pac = self.clone();
+        for buffer in pac.buffers.iter_mut() {
+            if !is_zeroed_signature(buffer.ul_type) {
+                continue;
+            }
+
+            let mut signature = parse_signature(buffer.ul_type, &buffer.data)?;
+            signature.signature.iter_mut().for_each(|byte| *byte = 0);
+            buffer.data = signature.build();
+        }
+        return Ok(pac.build());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{PacClientInfo, PAC_CLIENT_INFO};
+
+    const SERVICE_KEY: Key = Key::AES256Key([4; 32]);
+    const KRBTGT_KEY: Key = Key::RC4Key([9; 16]);
+
+    fn create_signed_pac(enc_ticket_part: Option<&[u8]>) -> Pac {
+        let mut pac = Pac::new(vec![PacInfoBuffer::new(
+            PAC_CLIENT_INFO,
+            PacClientInfo::new(1, "mickey".to_string()).build(),
+        )]);
+        pac.sign(&SERVICE_KEY, &KRBTGT_KEY, enc_ticket_part)
+            .unwrap();
+        return Pac::parse(&pac.build()).unwrap();
+    }
+
+    #[test]
+    fn parse_and_build_signature_data() {
+        let raw = vec![
+            0x76, 0xff, 0xff, 0xff, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13,
+            14, 15, 16, 0x05, 0x00,
+        ];
+
+        let signature = PacSignatureData::parse(&raw).unwrap().1;
+
+        assert_eq!(HMAC_MD5, signature.signature_type);
+        assert_eq!((1..=16).collect::<Vec<u8>>(), signature.signature);
+        assert_eq!(Some(5), signature.rodc_identifier);
+        assert_eq!(raw, signature.build());
+    }
+
+    #[test]
+    fn sign_and_verify_pac() {
+        let pac = create_signed_pac(None);
+
+        assert_eq!(
+            HMAC_SHA1_96_AES256,
+            pac.server_signature().unwrap().unwrap().signature_type
+        );
+        assert_eq!(
+            HMAC_MD5,
+            pac.kdc_signature().unwrap().unwrap().signature_type
+        );
+        pac.verify_server_signature(&SERVICE_KEY).unwrap();
+        pac.verify_kdc_signature(&KRBTGT_KEY).unwrap();
+        assert_eq!(None, pac.ticket_signature().unwrap());
+    }
+
+    #[test]
+    fn sign_and_verify_ticket_and_full_signatures() {
+        let pac = create_signed_pac(Some(&[1, 2, 3]));
+
+        pac.verify_server_signature(&SERVICE_KEY).unwrap();
+        pac.verify_kdc_signature(&KRBTGT_KEY).unwrap();
+        pac.verify_ticket_signature(&KRBTGT_KEY, &[1, 2, 3])
+            .unwrap();
+        pac.verify_full_signature(&KRBTGT_KEY).unwrap();
+
+        assert_eq!(
+            Err(Error::InvalidSignature(PAC_TICKET_CHECKSUM)),
+            pac.verify_ticket_signature(&KRBTGT_KEY, &[1, 2, 4])
+        );
+    }
+
+    #[test]
+    fn verify_signatures_over_received_data() {
+        let mut pac = Pac::new(vec![PacInfoBuffer::new(
+            PAC_CLIENT_INFO,
+            PacClientInfo::new(1, "mickey".to_string()).build(),
+        )]);
+        pac.set_signature(PAC_SERVER_CHECKSUM, HMAC_SHA1_96_AES256, None);
+        pac.set_signature(PAC_PRIVSVR_CHECKSUM, HMAC_MD5, None);
+
+        // trailing data which is lost if the PAC is built again
+        let mut raw = pac.build();
+        raw.extend_from_slice(&[1; 8]);
+
+        let server_signature =
+            compute_signature(HMAC_SHA1_96_AES256, &SERVICE_KEY, &raw).unwrap();
+        let kdc_signature =
+            compute_signature(HMAC_MD5, &KRBTGT_KEY, &server_signature)
+                .unwrap();
+        let offsets = Pac::parse(&raw).unwrap().raw.unwrap().signature_buffers;
+        for ((_, range), signature) in
+            offsets.iter().zip(&[server_signature, kdc_signature])
+        {
+            let start = range.start + 4;
+            raw[start..start + signature.len()].copy_from_slice(signature);
+        }
+
+        let pac = Pac::parse(&raw).unwrap();
+        pac.verify_server_signature(&SERVICE_KEY).unwrap();
+        pac.verify_kdc_signature(&KRBTGT_KEY).unwrap();
+    }
+
+    #[test]
+    fn error_verifying_modified_pac() {
+        let mut raw = create_signed_pac(None).build();
+        // the first byte of the name of the client info
+        raw[66] ^= 1;
+        let pac = Pac::parse(&raw).unwrap();
+        assert_ne!("mickey", pac.client_info().unwrap().unwrap().name);
+
+        assert_eq!(
+            Err(Error::InvalidSignature(PAC_SERVER_CHECKSUM)),
+            pac.verify_server_signature(&SERVICE_KEY)
+        );
+        pac.verify_kdc_signature(&KRBTGT_KEY).unwrap();
+    }
+
+    #[test]
+    fn error_verifying_with_key_of_other_type() {
+        let pac = create_signed_pac(None);
+
+        assert_eq!(
+            Err(Error::UnsupportedSignature(HMAC_SHA1_96_AES256, 23)),
+            pac.verify_server_signature(&KRBTGT_KEY)
+        );
+        assert_eq!(
+            Err(Error::MissingBuffer(PAC_FULL_CHECKSUM)),
+            pac.verify_full_signature(&KRBTGT_KEY)
+        );
+    }
+}
@@ -0,0 +1,202 @@
+use crate::client_info::utf16le_to_string;
+use crate::Sid;
+use nom::error::{Error as ParseError, ErrorKind};
+use nom::number::complete::{le_u16, le_u32};
+use nom::{Err, IResult};
+
+/// Flag set when the user has no UPN, so it was built from the account
+/// name and the DNS domain.
+pub const UPN_DNS_CONSTRUCTED: u32 = 0x00000001;
+/// Flag set when the structure includes the SAM name and the SID of the
+/// user.
+pub const UPN_DNS_EXTENDED: u32 = 0x00000002;
+
+/// UPN and DNS domain of the user, included in the `UPN_DNS_INFO`
+/// buffer. The strings are stored after the structure, at the offsets
+/// indicated by it.
+///
+/// # Definition
+/// ```c
+/// typedef struct _UPN_DNS_INFO {
+///     USHORT UpnLength;
+///     USHORT UpnOffset;
+///     USHORT DnsDomainNameLength;
+///     USHORT DnsDomainNameOffset;
+///     ULONG Flags;
+///     // Only present if the UPN_DNS_EXTENDED flag is set
+///     USHORT SamNameLength;
+///     USHORT SamNameOffset;
+///     USHORT SidLength;
+///     USHORT SidOffset;
+/// } UPN_DNS_INFO;
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct UpnDnsInfo {
+    pub upn: String,
+    pub dns_domain_name: String,
+    pub flags: u32,
+    pub sam_name: Option<String>,
+    pub sid: Option<Sid>,
+}
+
+impl UpnDnsInfo {
+    pub fn new(upn: String, dns_domain_name: String, flags: u32) -> Self {
+        return Self {
+            upn,
+            dns_domain_name,
+            flags,
+            sam_name: None,
+            sid: None,
+        };
+    }
+
+    /// Build the binary representation. The extension with the SAM name
+    /// and the SID is included if both are present.
+    pub fn build(&self) -> Vec<u8> {
+        let mut flags = self.flags & !UPN_DNS_EXTENDED;
+        let mut fields = vec![encode_utf16le(&self.upn)];
+        fields.push(encode_utf16le(&self.dns_domain_name));
+        if let (Some(sam_name), Some(sid)) = (&self.sam_name, &self.sid) {
+            flags |= UPN_DNS_EXTENDED;
+            fields.push(encode_utf16le(sam_name));
+            fields.push(sid.build());
+        }
+
+        let header_length = if flags & UPN_DNS_EXTENDED != 0 {
+            20
+        } else {
+            12
+        };
+        let mut offset = align8(header_length);
+        let mut header = Vec::new();
+        let mut data = Vec::new();
+        for (i, field) in fields.iter().enumerate() {
+            header.extend_from_slice(&(field.len() as u16).to_le_bytes());
+            header.extend_from_slice(&(offset as u16).to_le_bytes());
+            if i == 1 {
+                header.extend_from_slice(&flags.to_le_bytes());
+            }
+            data.extend_from_slice(field);
+            data.resize(align8(data.len()), 0);
+            offset = align8(header_length) + data.len();
+        }
+
+        header.resize(align8(header_length), 0);
+        header.append(&mut data);
+        return header;
+    }
+
+    /// Creates a new instance from the binary representation
+    /// # Error
+    /// Returns error when the binary has not the expected format.
+    pub fn parse(raw: &[u8]) -> IResult<&[u8], Self> {
+        let (rest, upn_length) = le_u16(raw)?;
+        let (rest, upn_offset) = le_u16(rest)?;
+        let (rest, dns_domain_name_length) = le_u16(rest)?;
+        let (rest, dns_domain_name_offset) = le_u16(rest)?;
+        let (mut rest, flags) = le_u32(rest)?;
+
+        let mut upn_dns_info = Self::new(
+            utf16le_to_string(field(raw, upn_offset, upn_length)?),
+            utf16le_to_string(field(
+                raw,
+                dns_domain_name_offset,
+                dns_domain_name_length,
+            )?),
+            flags,
+        );
+
+        if flags & UPN_DNS_EXTENDED != 0 {
+            let (r, sam_name_length) = le_u16(rest)?;
+            let (r, sam_name_offset) = le_u16(r)?;
+            let (r, sid_length) = le_u16(r)?;
+            let (r, sid_offset) = le_u16(r)?;
+            rest = r;
+
+            upn_dns_info.sam_name = Some(utf16le_to_string(field(
+                raw,
+                sam_name_offset,
+                sam_name_length,
+            )?));
+            let (_, sid) = Sid::parse(field(raw, sid_offset, sid_length)?)?;
+            upn_dns_info.sid = Some(sid);
+        }
+
+        return Ok((rest, upn_dns_info));
+    }
+}
+
+/// Data of the buffer placed at `offset`.
+fn field(
+    raw: &[u8],
+    offset: u16,
+    length: u16,
+) -> Result<&[u8], Err<ParseError<&[u8]>>> {
+    let start = offset as usize;
+    let end = start + length as usize;
+    if end > raw.len() {
+        return Err(Err::Error(ParseError::new(raw, ErrorKind::Eof)));
+    }
+    return Ok(&raw[start..end]);
+}
+
+fn encode_utf16le(string: &str) -> Vec<u8> {
+    return string
+        .encode_utf16()
+        .flat_map(|character| character.to_le_bytes())
+        .collect();
+}
+
+fn align8(length: usize) -> usize {
+    return length.div_ceil(8) * 8;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_upn_dns_info() {
+        let raw = vec![
+            0x0c, 0x00, 0x10, 0x00, 0x08, 0x00, 0x20, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x6d, 0x00, 0x40, 0x00, 0x6b, 0x00,
+            0x2e, 0x00, 0x68, 0x00, 0x73, 0x00, 0x00, 0x00, 0x00, 0x00, 0x4b,
+            0x00, 0x2e, 0x00, 0x48, 0x00, 0x53, 0x00,
+        ];
+
+        let upn_dns_info = UpnDnsInfo::parse(&raw).unwrap().1;
+
+        assert_eq!(
+            UpnDnsInfo::new("m@k.hs".to_string(), "K.HS".to_string(), 0),
+            upn_dns_info
+        );
+        assert_eq!(raw, upn_dns_info.build());
+    }
+
+    #[test]
+    fn build_and_parse_upn_dns_info_with_sid() {
+        let mut upn_dns_info = UpnDnsInfo::new(
+            "mickey@kingdom.hearts".to_string(),
+            "KINGDOM.HEARTS".to_string(),
+            UPN_DNS_CONSTRUCTED,
+        );
+        upn_dns_info.sam_name = Some("mickey".to_string());
+        upn_dns_info.sid =
+            Some(Sid::new(1, [0, 0, 0, 0, 0, 5], vec![21, 1, 2, 3, 1105]));
+
+        let raw = upn_dns_info.build();
+        upn_dns_info.flags |= UPN_DNS_EXTENDED;
+
+        assert_eq!(upn_dns_info, UpnDnsInfo::parse(&raw).unwrap().1);
+    }
+
+    #[test]
+    fn error_parsing_upn_out_of_buffer() {
+        let raw = vec![
+            0x0c, 0x00, 0x10, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00,
+            0x00,
+        ];
+
+        assert!(UpnDnsInfo::parse(&raw).is_err());
+    }
+}